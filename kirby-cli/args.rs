@@ -0,0 +1,48 @@
+use clap::Args;
+use kirby_core::config::{CrawlConfig, DEFAULT_USER_AGENT};
+use kirby_core::frontier::Scope;
+use url::Url;
+
+/// The site a command crawls and how politely, for the commands that crawl a site without a
+/// config file.
+#[derive(Debug, Args)]
+pub struct SiteCrawlArgs {
+    /// The pages to start from, usually the home page.
+    #[arg(required = true, value_name = "URL")]
+    pub urls: Vec<Url>,
+    /// Which links are followed: "host", "domain", "prefix" or "any".
+    #[arg(long, default_value_t = Scope::SameDomain)]
+    pub scope: Scope,
+    /// How many links away from the start pages are crawled, every page in scope by default.
+    #[arg(long)]
+    pub max_depth: Option<u32>,
+    /// Stops after fetching this many pages.
+    #[arg(long)]
+    pub max_pages: Option<u64>,
+    /// How many requests are made at once. Each host gets one request at a time.
+    #[arg(short, long, default_value_t = 8)]
+    pub concurrency: usize,
+    /// Milliseconds to wait between requests to the same host.
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    pub delay: u64,
+    /// The User-Agent header sent with every request.
+    #[arg(long, default_value = DEFAULT_USER_AGENT)]
+    pub user_agent: String,
+}
+
+impl SiteCrawlArgs {
+    /// The crawl of the site, with the defaults for everything else.
+    pub fn config(&self) -> CrawlConfig {
+        let mut config = CrawlConfig {
+            seeds: self.urls.clone(),
+            scope: self.scope,
+            max_depth: self.max_depth.unwrap_or(u32::MAX),
+            ..CrawlConfig::default()
+        };
+        config.budget.max_pages = self.max_pages;
+        config.politeness.user_agent = self.user_agent.clone();
+        config.politeness.delay_ms = self.delay;
+        config.politeness.concurrency = self.concurrency;
+        config
+    }
+}
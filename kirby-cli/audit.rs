@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use clap::{Args, ValueEnum};
+use kirby_core::crawler::Crawler;
+use kirby_core::export::Sink;
+use kirby_core::record::PageRecord;
+use kirby_core::seo::{Finding, SeoAudit};
+use serde_json::json;
+
+use crate::args::SiteCrawlArgs;
+use crate::CommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The issues of each page followed by a count of each kind of issue.
+    Text,
+    /// One JSON document with the pages audited, the counts and every issue.
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct AuditArgs {
+    #[command(flatten)]
+    pub site: SiteCrawlArgs,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+}
+
+/// Adds the pages crawled to the audit.
+struct Audit(Arc<Mutex<SeoAudit>>);
+
+impl Audit {
+    fn lock(&self) -> MutexGuard<'_, SeoAudit> {
+        // Adding a page is a single step, a panic while holding the lock can't leave the audit
+        // half changed.
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Sink for Audit {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let body = body.map(String::from_utf8_lossy);
+        self.lock().add_page(record, body.as_deref());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn run(args: AuditArgs) -> Result<ExitCode, CommandError> {
+    audit(args, &mut io::stdout().lock())
+}
+
+fn audit(args: AuditArgs, out: &mut impl Write) -> Result<ExitCode, CommandError> {
+    let config = args.site.config();
+
+    let audit = Audit(Arc::default());
+    let summary = Crawler::with_sink(config, Audit(Arc::clone(&audit.0)))?.run()?;
+    let findings = audit.lock().findings();
+    let mut counts = BTreeMap::<_, usize>::new();
+    for finding in &findings {
+        *counts.entry(finding.issue.kind()).or_default() += 1;
+    }
+
+    match args.format {
+        Format::Json => {
+            let report = json!({
+                "pages": summary.pages,
+                "counts": counts,
+                "findings": findings,
+            });
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+        Format::Text => write_text(out, summary.pages, &findings, &counts)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn write_text(
+    out: &mut impl Write,
+    pages: u64,
+    findings: &[Finding],
+    counts: &BTreeMap<&str, usize>,
+) -> io::Result<()> {
+    let mut page = None;
+    for finding in findings {
+        if page != Some(&finding.url) {
+            page = Some(&finding.url);
+            writeln!(out, "{}", finding.url)?;
+        }
+        writeln!(out, "    {}", finding.issue)?;
+    }
+    if !findings.is_empty() {
+        writeln!(out)?;
+    }
+    writeln!(out, "{pages} pages audited, {} issues", findings.len())?;
+    for (kind, count) in counts {
+        writeln!(out, "    {count:>5} {}", kind.replace('_', " "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::{Cli, Command};
+
+    #[test]
+    fn reports_issues_per_page_and_in_total() {
+        let site = MockServer::start(
+            Site::new()
+                .page(
+                    "/",
+                    Page::html(
+                        r#"<title>Kirby</title> <meta name="description" content="A crawler">
+                           <a href="/a">A</a> <a href="/old">Old</a>"#,
+                    ),
+                )
+                .page(
+                    "/a",
+                    Page::html(r#"<title>Kirby</title> <img src="/x.png">"#),
+                )
+                .page("/old", Page::redirect("/older"))
+                .page("/older", Page::redirect("/new"))
+                .page(
+                    "/new",
+                    Page::html(r#"<title>New</title> <meta name="description" content="New">"#),
+                ),
+        );
+        let cli =
+            Cli::try_parse_from(["kirby", "audit", site.url().as_str(), "--delay", "0"]).unwrap();
+        let Command::Audit(args) = cli.command else {
+            panic!("not audit");
+        };
+        let mut out = Vec::new();
+        audit(args, &mut out).unwrap();
+
+        let base = site.url();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{base}\n    title \"Kirby\" also on {base}a\n\
+                 {base}a\n    title \"Kirby\" also on {base}\n    missing meta description\n    \
+                 images without alt text: /x.png\n\
+                 {base}new\n    redirect chain {base}old -> {base}older -> {base}new\n\
+                 \n3 pages audited, 5 issues\n\
+                 \x20       2 duplicate title\n\
+                 \x20       1 missing alt\n\
+                 \x20       1 missing description\n\
+                 \x20       1 redirect chain\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+use kirby_core::anchors::AnchorChecker;
+use kirby_core::crawler::{fetch_robots, Crawler};
+use kirby_core::export::Sink;
+use kirby_core::fetch::Fetcher;
+use kirby_core::frontier::Frontier;
+use kirby_core::linkgraph::page_links;
+use kirby_core::record::PageRecord;
+use kirby_core::robotstxt::RobotsTxt;
+use scraper::Html;
+use serde_json::json;
+use url::Url;
+
+use crate::args::SiteCrawlArgs;
+use crate::CommandError;
+
+#[derive(Debug, Args)]
+pub struct CheckLinksArgs {
+    #[command(flatten)]
+    pub site: SiteCrawlArgs,
+    /// Only checks links within the scope.
+    #[arg(long)]
+    pub internal_only: bool,
+    /// Also checks that links to a `#fragment` of a crawled page find an element with that id.
+    #[arg(long)]
+    pub anchors: bool,
+    /// Prints one JSON object per broken link target.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// A page linking to a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Referrer {
+    page: Url,
+    anchor_text: String,
+}
+
+/// What a crawl of the site found.
+#[derive(Debug, Default)]
+struct Site {
+    /// The final status of every page crawled.
+    statuses: HashMap<Url, u16>,
+    /// The pages linking to each target.
+    links: BTreeMap<Url, Vec<Referrer>>,
+    /// The anchors of crawled pages and the links to them, when they're checked.
+    anchors: Option<AnchorChecker>,
+}
+
+/// Collects the statuses and links of crawled pages.
+struct Collect(Arc<Mutex<Site>>);
+
+impl Collect {
+    fn lock(&self) -> MutexGuard<'_, Site> {
+        // The site is only extended, a panic while holding the lock can't leave it half changed
+        // in a way that matters.
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Sink for Collect {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let mut site = self.lock();
+        site.statuses.insert(record.url.clone(), record.status);
+        site.statuses
+            .insert(record.final_url().clone(), record.status);
+
+        let html = record
+            .header("content-type")
+            .is_some_and(|content_type| content_type.contains("html"));
+        if let Some(body) = body.filter(|_| html && (200..300).contains(&record.status)) {
+            let html = String::from_utf8_lossy(body);
+            let document = Html::parse_document(&html);
+            for edge in page_links(&document, record.final_url()) {
+                site.links.entry(edge.target).or_default().push(Referrer {
+                    page: record.url.clone(),
+                    anchor_text: edge.anchor_text,
+                });
+            }
+            if let Some(anchors) = &mut site.anchors {
+                anchors.add_page(record.final_url(), &html);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How a link target answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Checked {
+    Status(u16),
+    Failed(String),
+    /// robots.txt doesn't allow checking it.
+    Denied,
+}
+
+impl Checked {
+    fn is_broken(&self) -> bool {
+        match self {
+            Self::Status(status) => *status >= 400,
+            Self::Failed(_) => true,
+            Self::Denied => false,
+        }
+    }
+}
+
+pub fn run(args: CheckLinksArgs) -> Result<ExitCode, CommandError> {
+    check(args, &mut io::stdout().lock())
+}
+
+fn check(args: CheckLinksArgs, out: &mut impl Write) -> Result<ExitCode, CommandError> {
+    let config = args.site.config();
+    let robots_agent = config.politeness.robots_agent();
+
+    let site = Site {
+        anchors: args.anchors.then(AnchorChecker::default),
+        ..Site::default()
+    };
+    let collect = Collect(Arc::new(Mutex::new(site)));
+    let summary = Crawler::with_sink(config, Collect(Arc::clone(&collect.0)))?.run()?;
+    let site = std::mem::take(&mut *collect.lock());
+
+    // Targets the crawl didn't fetch, e.g. other sites or nofollow links, are checked with HEAD
+    // requests.
+    let mut scope = Frontier::new(args.site.scope);
+    for url in &args.site.urls {
+        let _ = scope.add_seed(url.clone());
+    }
+    let mut results = HashMap::new();
+    let mut unchecked = Vec::new();
+    for target in site.links.keys() {
+        match site.statuses.get(target) {
+            Some(status) => {
+                results.insert(target.clone(), Checked::Status(*status));
+            }
+            None if args.internal_only && scope.check_scope(target).is_err() => {}
+            None => unchecked.push(target.clone()),
+        }
+    }
+    let fetcher = Fetcher::new(args.site.user_agent.clone());
+    results.extend(check_targets(
+        &fetcher,
+        &robots_agent,
+        unchecked,
+        args.site.concurrency,
+        Duration::from_millis(args.site.delay),
+    ));
+
+    let mut broken = 0;
+    for (target, referrers) in &site.links {
+        let Some(checked) = results.get(target).filter(|checked| checked.is_broken()) else {
+            continue;
+        };
+        broken += 1;
+        if args.json {
+            let (status, error) = match checked {
+                Checked::Status(status) => (Some(*status), None),
+                Checked::Failed(error) => (None, Some(error)),
+                Checked::Denied => (None, None),
+            };
+            let referrers = referrers
+                .iter()
+                .map(|referrer| json!({"page": referrer.page, "anchor_text": referrer.anchor_text}))
+                .collect::<Vec<_>>();
+            let line = json!({
+                "url": target,
+                "status": status,
+                "error": error,
+                "referrers": referrers,
+            });
+            writeln!(out, "{line}")?;
+            continue;
+        }
+        match checked {
+            Checked::Status(status) => writeln!(out, "{status} {target}")?,
+            Checked::Failed(error) => writeln!(out, "error {target} ({error})")?,
+            Checked::Denied => {}
+        }
+        for referrer in referrers {
+            writeln!(out, "    on {} {:?}", referrer.page, referrer.anchor_text)?;
+        }
+    }
+
+    // Links to a fragment of a crawled page that has no element with that id.
+    let mut missing = BTreeMap::<Url, Vec<Referrer>>::new();
+    for link in site.anchors.iter().flat_map(AnchorChecker::broken) {
+        let mut target = link.target.clone();
+        target.set_fragment(Some(&link.fragment));
+        missing.entry(target).or_default().push(Referrer {
+            page: link.source.clone(),
+            anchor_text: link.anchor_text.clone(),
+        });
+    }
+    for (target, referrers) in &missing {
+        broken += 1;
+        if args.json {
+            let referrers = referrers
+                .iter()
+                .map(|referrer| json!({"page": referrer.page, "anchor_text": referrer.anchor_text}))
+                .collect::<Vec<_>>();
+            let line = json!({
+                "url": target,
+                "missing_anchor": target.fragment(),
+                "referrers": referrers,
+            });
+            writeln!(out, "{line}")?;
+            continue;
+        }
+        writeln!(out, "anchor {target}")?;
+        for referrer in referrers {
+            writeln!(out, "    on {} {:?}", referrer.page, referrer.anchor_text)?;
+        }
+    }
+    let denied = results.values().filter(|c| **c == Checked::Denied).count();
+    eprintln!(
+        "checked {} links on {} pages, {broken} broken, {denied} not checked because of robots.txt",
+        results.len() - denied,
+        summary.pages
+    );
+
+    Ok(if broken > 0 {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// HEAD-checks targets with `concurrency` threads, one host at a time per thread and waiting
+/// `delay` between requests to a host. Servers refusing HEAD requests are asked with GET.
+fn check_targets(
+    fetcher: &Fetcher,
+    robots_agent: &str,
+    targets: Vec<Url>,
+    concurrency: usize,
+    delay: Duration,
+) -> HashMap<Url, Checked> {
+    let mut by_origin = BTreeMap::<String, Vec<Url>>::new();
+    for target in targets {
+        by_origin
+            .entry(target.origin().ascii_serialization())
+            .or_default()
+            .push(target);
+    }
+    let queue = Mutex::new(by_origin.into_values().collect::<VecDeque<_>>());
+    let results = Mutex::new(HashMap::new());
+
+    let check_origin = |targets: Vec<Url>| {
+        let robots = fetch_robots(fetcher, &targets[0]);
+        let robots = robots.as_deref().map(RobotsTxt::parse);
+        let mut checked = Vec::new();
+        for (index, target) in targets.into_iter().enumerate() {
+            if index > 0 {
+                thread::sleep(delay);
+            }
+            let result = match &robots {
+                Err(error) => Checked::Failed(format!("robots.txt: {error}")),
+                Ok(robots) if !robots.check_url(robots_agent, &target).allowed => Checked::Denied,
+                Ok(_) => {
+                    let response = match fetcher.head(&target) {
+                        Ok(response) if matches!(response.status, 405 | 501) => {
+                            fetcher.fetch(&target)
+                        }
+                        response => response,
+                    };
+                    match response {
+                        Ok(response) => Checked::Status(response.status),
+                        Err(error) => Checked::Failed(error.to_string()),
+                    }
+                }
+            };
+            checked.push((target, result));
+        }
+        checked
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1) {
+            scope.spawn(|| loop {
+                // A thread panicking while holding these locks can't leave them half changed.
+                let targets = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+                let Some(targets) = targets else {
+                    break;
+                };
+                let checked = check_origin(targets);
+                results
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .extend(checked);
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::{Cli, Command};
+
+    #[test]
+    fn reports_broken_links_with_their_referrers() {
+        let site = MockServer::start(
+            Site::new()
+                .page(
+                    "/",
+                    Page::html(
+                        r#"<a href="/a">A</a> <a href="/a#top">Top of A</a>
+                           <a href="/missing">Gone</a> <a href="http://127.0.0.1:1/x">Dead</a>"#,
+                    ),
+                )
+                .page("/a", Page::html(r#"<a href="/missing">Also gone</a>"#)),
+        );
+        let cli =
+            Cli::try_parse_from(["kirby", "check-links", site.url().as_str(), "--delay", "0"])
+                .unwrap();
+        let Command::CheckLinks(args) = cli.command else {
+            panic!("not check-links");
+        };
+        let mut out = Vec::new();
+        let code = check(args, &mut out).unwrap();
+
+        assert_eq!(code, ExitCode::from(1));
+        let base = site.url();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "error http://127.0.0.1:1/x (robots.txt: {error})\n    on {base} \"Dead\"\n\
+                 404 {base}missing\n    on {base} \"Gone\"\n    on {base}a \"Also gone\"\n",
+                error = fetch_robots(
+                    &Fetcher::new("test".to_string()),
+                    &Url::parse("http://127.0.0.1:1/x").unwrap()
+                )
+                .unwrap_err()
+            )
+        );
+    }
+
+    #[test]
+    fn reports_links_to_missing_anchors() {
+        let site = MockServer::start(
+            Site::new()
+                .page(
+                    "/",
+                    Page::html(
+                        r##"<a href="/a#intro">Intro</a> <a href="/a#setup">Setup</a>
+                            <a href="#here">Here</a>"##,
+                    ),
+                )
+                .page("/a", Page::html(r#"<h2 id="intro">Intro</h2>"#)),
+        );
+        let check_links = |extra: &[&str]| {
+            let mut argv = vec!["kirby", "check-links", site.url().as_str(), "--delay", "0"];
+            argv.extend(extra);
+            let Command::CheckLinks(args) = Cli::try_parse_from(argv).unwrap().command else {
+                panic!("not check-links");
+            };
+            let mut out = Vec::new();
+            let code = check(args, &mut out).unwrap();
+            (code, String::from_utf8(out).unwrap())
+        };
+
+        assert_eq!(check_links(&[]), (ExitCode::SUCCESS, String::new()));
+        let base = site.url();
+        assert_eq!(
+            check_links(&["--anchors"]),
+            (
+                ExitCode::from(1),
+                format!(
+                    "anchor {base}#here\n    on {base} \"Here\"\n\
+                     anchor {base}a#setup\n    on {base} \"Setup\"\n"
+                )
+            )
+        );
+    }
+}
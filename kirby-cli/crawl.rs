@@ -0,0 +1,416 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, ValueEnum};
+use kirby_core::audit::Decision;
+use kirby_core::config::{CrawlConfig, CssSelector, SinkConfig};
+use kirby_core::crawler::dry_run::{DryRun, Plan};
+use kirby_core::crawler::Crawler;
+use kirby_core::export::csv::CsvSink;
+use kirby_core::export::Sink;
+use kirby_core::frontier::Scope;
+use kirby_core::record::{DiscoverySource, PageRecord};
+use kirby_core::store::fs::FsStore;
+use url::Url;
+
+use crate::CommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One JSON page record per line.
+    Jsonl,
+    /// The URL, status, fetch time, digest, content type and selected fields of each page.
+    Csv,
+}
+
+/// The options override the ones of the --config file, whose sinks are only used when neither
+/// --format nor --output is given.
+#[derive(Debug, Args)]
+pub struct CrawlArgs {
+    /// URLs to start from, added to the configured seeds.
+    #[arg(value_name = "SEED")]
+    pub seeds: Vec<Url>,
+    /// Reads the crawl definition from a TOML or YAML file.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Reads more seeds from a file, one URL per line. Empty lines and lines starting with `#`
+    /// are skipped.
+    #[arg(long, value_name = "FILE")]
+    pub seeds_file: Option<PathBuf>,
+    /// Which links are followed: "host", "domain", "prefix" or "any". Defaults to "domain".
+    #[arg(long)]
+    pub scope: Option<Scope>,
+    /// How many links away from a seed pages are crawled, 3 by default.
+    #[arg(long)]
+    pub max_depth: Option<u32>,
+    /// Stops after fetching this many pages.
+    #[arg(long)]
+    pub max_pages: Option<u64>,
+    /// How many pages are fetched at once, 8 by default. Each host gets one request at a time.
+    #[arg(short, long)]
+    pub concurrency: Option<usize>,
+    /// Milliseconds to wait between requests to the same host, 1000 by default.
+    #[arg(long, value_name = "MS")]
+    pub delay: Option<u64>,
+    /// JSONL by default.
+    #[arg(long, value_enum)]
+    pub format: Option<Format>,
+    /// A directory for JSONL files or the CSV file, stdout when it's not given.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Extracts the text of the first element matching a CSS selector into a field, e.g.
+    /// `title=h1`. Can be given more than once.
+    #[arg(long = "select", value_name = "NAME=SELECTOR", value_parser = parse_select)]
+    pub selects: Vec<(String, CssSelector)>,
+    /// The User-Agent header sent with every request.
+    #[arg(long)]
+    pub user_agent: Option<String>,
+    /// The name robots.txt rules are matched against, the user agent's product token (the
+    /// part before the `/`) by default.
+    #[arg(long)]
+    pub robots_agent: Option<String>,
+    /// Reports which URLs would be crawled or skipped and why, fetching only robots.txt files
+    /// and sitemaps. Writes JSONL with --format jsonl.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// A page store of an earlier crawl, whose pages the dry run follows the links of.
+    #[arg(long, value_name = "DIR", requires = "dry_run")]
+    pub cache: Option<PathBuf>,
+    /// Shows the crawl's throughput, hosts, queues and recent errors in the terminal, with
+    /// keys to pause and resume it. Needs --output or sinks in --config.
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with = "dry_run")]
+    pub tui: bool,
+}
+
+fn parse_select(value: &str) -> Result<(String, CssSelector), String> {
+    let (name, selector) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=SELECTOR, got {value:?}"))?;
+    Ok((name.trim().to_string(), CssSelector::parse(selector)?))
+}
+
+pub fn run(args: CrawlArgs) -> Result<ExitCode, CommandError> {
+    let config = config(&args)?;
+    if config.seeds.is_empty() {
+        return Err("no seeds were given".into());
+    }
+    if args.dry_run {
+        return dry_run(&args, &config);
+    }
+    #[cfg(feature = "tui")]
+    if args.tui && args.output.is_none() && (args.format.is_some() || config.sinks.is_empty()) {
+        return Err("--tui needs --output or sinks in --config, it takes over stdout".into());
+    }
+
+    let crawler = match (args.format, &args.output) {
+        (None, None) if !config.sinks.is_empty() => Crawler::from_config(config)?,
+        (format, Some(output)) => {
+            let sink = match format.unwrap_or(Format::Jsonl) {
+                Format::Jsonl => SinkConfig::Jsonl {
+                    directory: output.clone(),
+                },
+                Format::Csv => SinkConfig::Csv {
+                    path: output.clone(),
+                },
+            };
+            Crawler::from_config(CrawlConfig {
+                sinks: vec![sink],
+                ..config
+            })?
+        }
+        (Some(Format::Csv), None) => {
+            let columns = config.csv_columns();
+            Crawler::with_sink(config, CsvSink::new(io::stdout(), columns))?
+        }
+        (_, None) => Crawler::with_sink(config, JsonLines(io::stdout()))?,
+    };
+
+    #[cfg(feature = "tui")]
+    let summary = if args.tui {
+        crate::tui::run(crawler)?
+    } else {
+        crawler.run()?
+    };
+    #[cfg(not(feature = "tui"))]
+    let summary = crawler.run()?;
+    eprintln!(
+        "crawled {} pages, {} failed, {} denied by robots.txt",
+        summary.pages, summary.failed, summary.denied
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn dry_run(args: &CrawlArgs, config: &CrawlConfig) -> Result<ExitCode, CommandError> {
+    config.validate()?;
+    let cache = match &args.cache {
+        Some(path) if !path.join("pages").is_dir() => {
+            return Err(format!("{} isn't a page store", path.display()).into())
+        }
+        Some(path) => Some(FsStore::open(path)?),
+        None => None,
+    };
+    let mut dry_run = DryRun::new(config);
+    if let Some(cache) = &cache {
+        dry_run = dry_run.cache(cache);
+    }
+    let plan = dry_run.run()?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    write_plan(&plan, args.format == Some(Format::Jsonl), &mut out)?;
+    out.flush()?;
+    eprintln!(
+        "would crawl {} of {} URLs, {} links to URLs already reached",
+        plan.fetched().count(),
+        plan.urls.len(),
+        plan.duplicates
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn write_plan(plan: &Plan, jsonl: bool, out: &mut impl Write) -> io::Result<()> {
+    for planned in &plan.urls {
+        if jsonl {
+            writeln!(out, "{}", serde_json::to_string(planned)?)?;
+            continue;
+        }
+        let url = &planned.url;
+        let reason = match &planned.decision {
+            None => match &planned.discovery {
+                DiscoverySource::Seed => "seed".to_string(),
+                DiscoverySource::Link { from } => {
+                    format!("depth {}, linked from {from}", planned.depth)
+                }
+                DiscoverySource::Sitemap { sitemap } => format!("listed in {sitemap}"),
+                DiscoverySource::Feed { feed } => format!("listed in {feed}"),
+            },
+            Some(Decision::RobotsDenied { rule, .. }) => match rule {
+                Some(rule) => format!("robots.txt {rule}"),
+                None => "robots.txt".to_string(),
+            },
+            Some(Decision::OutOfScope { reason } | Decision::TrapDetected { reason }) => {
+                reason.clone()
+            }
+            Some(Decision::Duplicate { of }) => format!("duplicate of {of}"),
+            Some(Decision::BudgetExhausted { budget }) => format!("{budget} budget spent"),
+            Some(Decision::MimeRejected { mime }) => format!("{mime} isn't accepted"),
+        };
+        let verdict = if planned.decision.is_none() {
+            "crawl"
+        } else {
+            "skip "
+        };
+        writeln!(out, "{verdict} {url} ({reason})")?;
+    }
+    Ok(())
+}
+
+/// The configuration file, when there's one, with the options applied over it.
+fn config(args: &CrawlArgs) -> Result<CrawlConfig, CommandError> {
+    let mut config = match &args.config {
+        Some(path) => {
+            CrawlConfig::load(path).map_err(|error| format!("{}: {error}", path.display()))?
+        }
+        None => CrawlConfig::default(),
+    };
+    config.seeds.extend(args.seeds.iter().cloned());
+    if let Some(path) = &args.seeds_file {
+        config.seeds.extend(read_seeds(path)?);
+    }
+    if let Some(scope) = args.scope {
+        config.scope = scope;
+    }
+    if let Some(max_depth) = args.max_depth {
+        config.max_depth = max_depth;
+    }
+    if let Some(max_pages) = args.max_pages {
+        config.budget.max_pages = Some(max_pages);
+    }
+    let politeness = &mut config.politeness;
+    if let Some(concurrency) = args.concurrency {
+        politeness.concurrency = concurrency;
+    }
+    if let Some(delay) = args.delay {
+        politeness.delay_ms = delay;
+    }
+    if let Some(user_agent) = &args.user_agent {
+        politeness.user_agent = user_agent.clone();
+    }
+    if let Some(robots_agent) = &args.robots_agent {
+        politeness.robots_agent = Some(robots_agent.clone());
+    }
+    config.extract.extend(args.selects.iter().cloned());
+    Ok(config)
+}
+
+/// The seeds in a file, one per line.
+fn read_seeds(path: &Path) -> Result<Vec<Url>, CommandError> {
+    let mut seeds = Vec::new();
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let seed = Url::parse(line)
+            .map_err(|error| format!("{}:{}: {error}", path.display(), number + 1))?;
+        seeds.push(seed);
+    }
+    Ok(seeds)
+}
+
+/// Writes one JSON page record per line.
+pub(crate) struct JsonLines<W>(pub(crate) W);
+
+impl<W: Write> Sink for JsonLines<W> {
+    fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+        serde_json::to_writer(&mut self.0, record)?;
+        self.0.write_all(b"\n")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use clap::Parser;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::Cli;
+
+    #[test]
+    fn crawls_within_scope_and_robots() {
+        let site = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nDisallow: /private/")
+                .page(
+                    "/",
+                    Page::html(
+                        r#"<h1>Home</h1> <a href="/a">A</a> <a href="/private/x">Private</a>
+                           <a href="/ads" rel="nofollow">Ad</a> <a href="http://other.invalid/">Out</a>"#,
+                    ),
+                )
+                .page("/a", Page::redirect("/a/"))
+                .page("/a/", Page::html(r#"<h1>A</h1> <a href="/b">B</a>"#))
+                .page("/b", Page::html("<h1>B</h1>")),
+        );
+        let output = tempfile::tempdir().unwrap();
+        let cli = Cli::try_parse_from([
+            "kirby",
+            "crawl",
+            site.url().as_str(),
+            "--max-depth",
+            "1",
+            "--delay",
+            "0",
+            "--select",
+            "title=h1",
+            "--output",
+            output.path().to_str().unwrap(),
+        ])
+        .unwrap();
+        let crate::Command::Crawl(args) = cli.command else {
+            panic!("not parsed as a crawl");
+        };
+        assert_eq!(run(args).unwrap(), ExitCode::SUCCESS);
+
+        let pages = fs::read_to_string(output.path().join("pages-00000.jsonl")).unwrap();
+        let mut pages = pages
+            .lines()
+            .map(|line| PageRecord::from_json(line).unwrap())
+            .collect::<Vec<_>>();
+        pages.sort_by_key(|page| page.depth);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].fields["title"], "Home");
+        assert_eq!(pages[1].url.path(), "/a");
+        assert_eq!(pages[1].final_url().path(), "/a/");
+        assert_eq!(pages[1].fields["title"], "A");
+
+        site.assert_requested("/robots.txt", 1);
+        for path in ["/private/x", "/ads", "/b"] {
+            site.assert_not_requested(path);
+        }
+    }
+
+    #[test]
+    fn dry_runs_without_fetching_pages() {
+        let site = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nDisallow: /private/\nSitemap: {base}/sitemap.xml")
+                .sitemap("/sitemap.xml", ["/a", "/private/b"]),
+        );
+        let output = tempfile::tempdir().unwrap();
+        let report = output.path().join("plan.txt");
+        let cli = Cli::try_parse_from([
+            "kirby",
+            "crawl",
+            site.url().as_str(),
+            "--dry-run",
+            "--output",
+            report.to_str().unwrap(),
+        ])
+        .unwrap();
+        let crate::Command::Crawl(args) = cli.command else {
+            panic!("not parsed as a crawl");
+        };
+        assert_eq!(run(args).unwrap(), ExitCode::SUCCESS);
+
+        assert_eq!(
+            fs::read_to_string(&report).unwrap(),
+            format!(
+                "crawl {0} (seed)\n\
+                 crawl {0}a (listed in {0}sitemap.xml)\n\
+                 skip  {0}private/b (robots.txt Disallow: /private/)\n",
+                site.url()
+            )
+        );
+        assert_eq!(site.paths(), ["/robots.txt", "/sitemap.xml"]);
+    }
+
+    #[test]
+    fn applies_options_over_the_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crawl.yaml");
+        fs::write(
+            &path,
+            "seeds: [https://example.com/]\nscope: host\npoliteness: {delay_ms: 5000}\n\
+             extract: {title: h1}\n",
+        )
+        .unwrap();
+        let cli = Cli::try_parse_from([
+            "kirby",
+            "crawl",
+            "https://example.org/",
+            "--config",
+            path.to_str().unwrap(),
+            "--delay",
+            "100",
+            "--select",
+            "price=.price",
+        ])
+        .unwrap();
+        let crate::Command::Crawl(args) = cli.command else {
+            panic!("not parsed as a crawl");
+        };
+
+        let config = config(&args).unwrap();
+        assert_eq!(config.seeds.len(), 2);
+        assert_eq!(config.scope, Scope::SameHost);
+        assert_eq!(config.politeness.delay_ms, 100);
+        assert_eq!(
+            config.extract.keys().collect::<Vec<_>>(),
+            ["price", "title"]
+        );
+    }
+}
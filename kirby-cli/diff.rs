@@ -0,0 +1,155 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, ValueEnum};
+use kirby_core::crawldiff::CrawlDiff;
+use kirby_core::record::PageRecord;
+use serde_json::json;
+
+use crate::export::read_input;
+use crate::CommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One line per change followed by a count of each kind of change.
+    Text,
+    /// One JSON document with the counts and every change.
+    Json,
+    /// One JSON object per change.
+    Jsonl,
+}
+
+/// Exits with 1 when the runs differ, like `diff` does.
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// The earlier run: a page store, a directory of JSONL files or segments, or one such file.
+    pub old: PathBuf,
+    /// The later run, in any of the same forms.
+    pub new: PathBuf,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+}
+
+pub fn run(args: DiffArgs) -> Result<ExitCode, CommandError> {
+    diff(args, &mut io::stdout().lock())
+}
+
+fn diff(args: DiffArgs, out: &mut impl Write) -> Result<ExitCode, CommandError> {
+    let diff = CrawlDiff::from_records(records(&args.old)?, records(&args.new)?);
+    let summary = diff.summary();
+    match args.format {
+        Format::Text => {
+            for change in &diff.changes {
+                writeln!(out, "{change}")?;
+            }
+            if !diff.is_empty() {
+                writeln!(out)?;
+            }
+            writeln!(
+                out,
+                "{} added, {} removed, {} status changes, {} redirect changes, {} content \
+                 changes, {} unchanged",
+                summary.added,
+                summary.removed,
+                summary.status_changed,
+                summary.redirect_changed,
+                summary.content_changed,
+                summary.unchanged
+            )?;
+        }
+        Format::Json => {
+            let report = json!({"summary": summary, "changes": diff.changes});
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+        Format::Jsonl => diff.write_jsonl(&mut *out)?,
+    }
+    Ok(if diff.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn records(input: &Path) -> Result<Vec<PageRecord>, CommandError> {
+    let mut records = Vec::new();
+    read_input(input, &mut |record| {
+        records.push(record);
+        Ok(())
+    })?;
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::Utc;
+    use clap::Parser;
+    use kirby_core::record::Redirect;
+    use url::Url;
+
+    use super::*;
+    use crate::{Cli, Command};
+
+    #[test]
+    fn reports_changes_between_runs() {
+        let url = |path: &str| {
+            Url::parse("https://example.com")
+                .unwrap()
+                .join(path)
+                .unwrap()
+        };
+        let page = |path: &str, status| {
+            let mut record = PageRecord::new(url(path), status, Utc::now());
+            record.digest = Some("a".to_string());
+            serde_json::to_string(&record).unwrap()
+        };
+        let mut moved = PageRecord::new(url("/old"), 200, Utc::now());
+        moved.digest = Some("a".to_string());
+        moved.redirects = vec![Redirect {
+            from: url("/old"),
+            to: url("/new"),
+            status: 301,
+        }];
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.jsonl");
+        let new = dir.path().join("new.jsonl");
+        fs::write(
+            &old,
+            [page("/", 200), page("/sale", 200), page("/old", 200)].join("\n"),
+        )
+        .unwrap();
+        fs::write(
+            &new,
+            [
+                page("/", 200),
+                page("/sale", 404),
+                serde_json::to_string(&moved).unwrap(),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let cli = Cli::try_parse_from([
+            "kirby",
+            "diff",
+            old.to_str().unwrap(),
+            new.to_str().unwrap(),
+        ])
+        .unwrap();
+        let Command::Diff(args) = cli.command else {
+            panic!("not diff");
+        };
+        let mut out = Vec::new();
+        assert_eq!(diff(args, &mut out).unwrap(), ExitCode::from(1));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "~ https://example.com/old now redirects to https://example.com/new\n\
+             ~ https://example.com/sale status 200 -> 404\n\
+             \n0 added, 0 removed, 1 status changes, 1 redirect changes, 0 content changes, \
+             1 unchanged\n"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::{Args, ValueEnum};
+use flate2::read::MultiGzDecoder;
+use kirby_core::export::csv::CsvSink;
+use kirby_core::export::{Column, ColumnSource, Sink};
+use kirby_core::record::PageRecord;
+use kirby_core::store::fs::FsStore;
+use kirby_core::store::Store;
+use regex::Regex;
+use serde_json::Map;
+
+use crate::crawl::JsonLines;
+use crate::CommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Jsonl,
+    Csv,
+    /// Needs --output and a build with the `parquet` feature.
+    Parquet,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Page stores, SQLite stores, JSONL files (.jsonl, .jsonl.gz) or segments (.seg) to read.
+    /// A directory that isn't a page store is read file by file.
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+    pub format: Format,
+    /// Writes to a file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// A field to write: url, status, fetched_at, digest, header:NAME, or NAME=PATH for
+    /// extraction output, e.g. price=$.offers.price. Defaults to whole records in JSONL and to
+    /// url, status, fetched_at and digest otherwise.
+    #[arg(short, long = "field", value_name = "FIELD", value_parser = parse_field)]
+    pub fields: Vec<Column>,
+    /// Keeps pages whose URL starts with one of these.
+    #[arg(long, value_name = "PREFIX")]
+    pub url_prefix: Vec<String>,
+    /// Keeps pages whose URL matches a regular expression.
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub url_pattern: Option<Regex>,
+    /// Keeps pages with one of these statuses, e.g. 200 or 4xx.
+    #[arg(long, value_parser = parse_status)]
+    pub status: Vec<StatusFilter>,
+    /// Keeps pages fetched at or after a time, in RFC 3339 or as a date meaning midnight UTC.
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub since: Option<DateTime<Utc>>,
+    /// Keeps pages fetched before a time, in RFC 3339 or as a date meaning midnight UTC.
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// A status to keep, exactly or by its class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFilter {
+    Exact(u16),
+    /// The first digit, e.g. 4 for `4xx`.
+    Class(u16),
+}
+
+impl StatusFilter {
+    fn matches(self, status: u16) -> bool {
+        match self {
+            Self::Exact(expected) => status == expected,
+            Self::Class(class) => status / 100 == class,
+        }
+    }
+}
+
+impl ExportArgs {
+    /// Whether a record passes every filter.
+    fn keeps(&self, record: &PageRecord) -> bool {
+        let url = record.url.as_str();
+        (self.url_prefix.is_empty() || self.url_prefix.iter().any(|p| url.starts_with(p)))
+            && self.url_pattern.as_ref().is_none_or(|p| p.is_match(url))
+            && (self.status.is_empty() || self.status.iter().any(|s| s.matches(record.status)))
+            && self.since.is_none_or(|since| record.fetched_at >= since)
+            && self.until.is_none_or(|until| record.fetched_at < until)
+    }
+}
+
+pub fn run(args: ExportArgs) -> Result<ExitCode, CommandError> {
+    let mut sink = open_sink(&args)?;
+    let (mut read, mut written) = (0, 0);
+    for input in &args.inputs {
+        read_input(input, &mut |record| {
+            read += 1;
+            if args.keeps(&record) {
+                written += 1;
+                sink.write(&record, None)?;
+            }
+            Ok(())
+        })?;
+    }
+    sink.finish()?;
+    eprintln!("exported {written} of {read} pages");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn open_sink(args: &ExportArgs) -> Result<Box<dyn Sink>, CommandError> {
+    let writer = || -> io::Result<Box<dyn Write + Send>> {
+        Ok(match &args.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout()),
+        })
+    };
+    let columns = if args.fields.is_empty() {
+        Column::defaults()
+    } else {
+        args.fields.clone()
+    };
+    Ok(match args.format {
+        Format::Jsonl if args.fields.is_empty() => Box::new(JsonLines(writer()?)),
+        Format::Jsonl => Box::new(FieldLines {
+            writer: writer()?,
+            columns,
+        }),
+        Format::Csv => Box::new(CsvSink::new(writer()?, columns)),
+        #[cfg(feature = "parquet")]
+        Format::Parquet => {
+            use kirby_core::export::parquet::{ColumnType, ParquetSink};
+
+            let path = args
+                .output
+                .as_ref()
+                .ok_or("Parquet is only written to a file, pass --output")?;
+            let columns = columns
+                .into_iter()
+                .map(|column| {
+                    let column_type = match column.source {
+                        ColumnSource::Status => ColumnType::Int64,
+                        ColumnSource::FetchedAt => ColumnType::Timestamp,
+                        _ => ColumnType::Utf8,
+                    };
+                    (column, column_type)
+                })
+                .collect();
+            Box::new(ParquetSink::create(path, columns)?)
+        }
+        #[cfg(not(feature = "parquet"))]
+        Format::Parquet => return Err("kirby was built without the parquet feature".into()),
+    })
+}
+
+/// Writes the selected fields of each record as a JSON object per line.
+struct FieldLines<W> {
+    writer: W,
+    columns: Vec<Column>,
+}
+
+impl<W: Write> Sink for FieldLines<W> {
+    fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+        let line = self
+            .columns
+            .iter()
+            .map(|column| (column.name.clone(), column.value(record)))
+            .collect::<Map<_, _>>();
+        serde_json::to_writer(&mut self.writer, &line)?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+type Visit<'a> = dyn FnMut(PageRecord) -> Result<(), CommandError> + 'a;
+
+/// Calls `visit` with every record of an input, in the order the input keeps them.
+pub(crate) fn read_input(path: &Path, visit: &mut Visit) -> Result<(), CommandError> {
+    if !path.is_dir() {
+        return read_file(path, visit);
+    }
+    if path.join("pages").is_dir() {
+        return read_store(&FsStore::open(path)?, visit);
+    }
+    let mut files = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    files.sort();
+    for file in files.iter().filter(|file| file.is_file()) {
+        let name = file.to_string_lossy();
+        // Skips index sidecars, manifests and anything else that isn't pages.
+        if [".jsonl", ".jsonl.gz", ".seg"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+        {
+            read_file(file, visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path, visit: &mut Visit) -> Result<(), CommandError> {
+    let name = path.to_string_lossy();
+    if name.ends_with(".jsonl") {
+        read_jsonl(path, File::open(path)?, visit)
+    } else if name.ends_with(".jsonl.gz") {
+        read_jsonl(path, MultiGzDecoder::new(File::open(path)?), visit)
+    } else if name.ends_with(".seg") {
+        read_segment(path, visit)
+    } else if [".sqlite", ".sqlite3", ".db"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+    {
+        read_sqlite(path, visit)
+    } else {
+        Err(format!(
+            "{}: unknown input, expected a page store, .jsonl, .jsonl.gz or .seg",
+            path.display()
+        )
+        .into())
+    }
+}
+
+fn read_store(store: &impl Store, visit: &mut Visit) -> Result<(), CommandError> {
+    for url in store.list()? {
+        // A page deleted since listing is skipped.
+        if let Some(page) = store.get(&url)? {
+            visit(page.record)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_jsonl(path: &Path, reader: impl Read, visit: &mut Visit) -> Result<(), CommandError> {
+    for (number, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = PageRecord::from_json(&line)
+            .map_err(|error| format!("{}:{}: {error}", path.display(), number + 1))?;
+        visit(record)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+fn read_segment(path: &Path, visit: &mut Visit) -> Result<(), CommandError> {
+    use kirby_core::archive::segment::SegmentReader;
+
+    for record in SegmentReader::open(path)? {
+        let record = record.map_err(|error| format!("{}: {error}", path.display()))?;
+        visit(record.record)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn read_segment(path: &Path, _visit: &mut Visit) -> Result<(), CommandError> {
+    Err(format!(
+        "{}: kirby was built without the zstd feature",
+        path.display()
+    )
+    .into())
+}
+
+#[cfg(feature = "sqlite")]
+fn read_sqlite(path: &Path, visit: &mut Visit) -> Result<(), CommandError> {
+    if !path.is_file() {
+        return Err(format!("{}: no such store", path.display()).into());
+    }
+    read_store(&kirby_core::store::sqlite::SqliteStore::open(path)?, visit)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn read_sqlite(path: &Path, _visit: &mut Visit) -> Result<(), CommandError> {
+    Err(format!(
+        "{}: kirby was built without the sqlite feature",
+        path.display()
+    )
+    .into())
+}
+
+fn parse_field(spec: &str) -> Result<Column, String> {
+    let source = match spec {
+        "url" => ColumnSource::Url,
+        "status" => ColumnSource::Status,
+        "fetched_at" => ColumnSource::FetchedAt,
+        "digest" => ColumnSource::Digest,
+        _ => {
+            if let Some(name) = spec.strip_prefix("header:") {
+                ColumnSource::Header(name.to_string())
+            } else if let Some((name, path)) = spec.split_once('=') {
+                return Column::field(name, path).map_err(|error| error.to_string());
+            } else {
+                return Err(
+                    "expected url, status, fetched_at, digest, header:NAME or NAME=PATH"
+                        .to_string(),
+                );
+            }
+        }
+    };
+    Ok(Column::new(spec.trim_start_matches("header:"), source))
+}
+
+fn parse_status(value: &str) -> Result<StatusFilter, String> {
+    let class = value
+        .strip_suffix("xx")
+        .or_else(|| value.strip_suffix("XX"));
+    let filter = match class {
+        Some(class) => class.parse().ok().map(StatusFilter::Class),
+        None => value.parse().ok().map(StatusFilter::Exact),
+    };
+    filter
+        .filter(|filter| match *filter {
+            StatusFilter::Exact(status) => (100..600).contains(&status),
+            StatusFilter::Class(class) => (1..6).contains(&class),
+        })
+        .ok_or_else(|| "expected a status such as 200 or a class such as 4xx".to_string())
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.to_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| "expected an RFC 3339 time or a YYYY-MM-DD date".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use url::Url;
+
+    fn record(url: &str, status: u16, fetched_at: &str) -> PageRecord {
+        let mut record = PageRecord::new(
+            Url::parse(url).unwrap(),
+            status,
+            parse_time(fetched_at).unwrap(),
+        );
+        record.fields = json!({"title": format!("Page {status}")});
+        record
+    }
+
+    #[test]
+    fn exports_filtered_fields_from_stores_and_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = FsStore::open(dir.path().join("store")).unwrap();
+        for record in [
+            record("https://example.com/blog/a", 200, "2024-05-01T10:00:00Z"),
+            record("https://example.com/blog/b", 404, "2024-05-02T10:00:00Z"),
+            record("https://example.com/shop/c", 200, "2024-05-02T11:00:00Z"),
+        ] {
+            store.put(&record, Some(b"<p>Hi</p>")).unwrap();
+        }
+        let jsonl = dir.path().join("pages-00000.jsonl");
+        let late = record("https://example.com/blog/d", 200, "2024-05-03T09:00:00Z");
+        fs::write(
+            &jsonl,
+            format!("{}\n", serde_json::to_string(&late).unwrap()),
+        )
+        .unwrap();
+
+        let output = dir.path().join("pages.csv");
+        let args = ExportArgs {
+            inputs: vec![dir.path().join("store"), jsonl],
+            format: Format::Csv,
+            output: Some(output.clone()),
+            fields: vec![
+                parse_field("url").unwrap(),
+                parse_field("title=$.title").unwrap(),
+            ],
+            url_prefix: vec!["https://example.com/blog/".to_string()],
+            url_pattern: None,
+            status: vec![parse_status("2xx").unwrap()],
+            since: Some(parse_time("2024-05-01T12:00:00+02:00").unwrap()),
+            until: Some(parse_time("2024-05-04").unwrap()),
+        };
+        assert_eq!(run(args).unwrap(), ExitCode::SUCCESS);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "url,title\nhttps://example.com/blog/a,Page 200\nhttps://example.com/blog/d,Page 200\n"
+        );
+    }
+}
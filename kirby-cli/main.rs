@@ -0,0 +1,97 @@
+use std::error::Error;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use kirby_core::logging::{self, LogFormat};
+
+mod args;
+mod audit;
+mod check_links;
+mod crawl;
+mod diff;
+mod export;
+mod mirror;
+mod robots;
+mod serve;
+mod sitemap;
+#[cfg(feature = "tui")]
+mod tui;
+
+/// The error of a command, printed before exiting with a failure.
+pub type CommandError = Box<dyn Error + Send + Sync>;
+
+/// A polite web crawler.
+///
+/// Exits with 2 when a command fails.
+#[derive(Debug, Parser)]
+#[command(name = "kirby", version)]
+struct Cli {
+    /// How log lines on stderr are written: "human" or "json".
+    #[arg(long, global = true, default_value_t = LogFormat::Human)]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Crawls a site and reports SEO issues of its pages, like missing or duplicate titles,
+    /// broken canonicals, redirect chains and images without alt text.
+    Audit(audit::AuditArgs),
+    /// Crawls a site and checks every link on its pages, reporting broken ones with the pages
+    /// linking to them. Exits with 1 when a link is broken. --max-pages only stops the crawl,
+    /// the links of the pages crawled are all checked.
+    CheckLinks(check_links::CheckLinksArgs),
+    /// Crawls from seed URLs and writes out every page fetched.
+    Crawl(crawl::CrawlArgs),
+    /// Compares two crawl runs: the pages added and removed, and the status, redirect and
+    /// content changes. Exits with 1 when the runs differ.
+    Diff(diff::DiffArgs),
+    /// Writes selected fields of stored or archived pages to JSONL, CSV or Parquet.
+    Export(export::ExportArgs),
+    /// Downloads a site's pages and the assets they use into a directory, with links rewritten
+    /// to the local copies so it can be browsed offline. Assets don't count against
+    /// --max-pages.
+    Mirror(mirror::MirrorArgs),
+    /// Works with robots.txt files.
+    #[command(subcommand)]
+    Robots(robots::RobotsCommand),
+    /// Serves archived crawls over local HTTP, to browse them or run extraction against a
+    /// stable copy.
+    Serve(serve::ServeArgs),
+    /// Fetches sitemaps and the sitemaps their indexes list, validates them and dumps or diffs
+    /// the URLs they list.
+    Sitemap(sitemap::SitemapArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // The display takes over the terminal, log lines would be drawn over it.
+    #[cfg(feature = "tui")]
+    let logs = !matches!(&cli.command, Command::Crawl(args) if args.tui);
+    #[cfg(not(feature = "tui"))]
+    let logs = true;
+    if let Err(error) = logs.then(|| logging::init(cli.log_format)).transpose() {
+        eprintln!("kirby: {error}");
+        return ExitCode::from(2);
+    }
+
+    let result = match cli.command {
+        Command::Audit(args) => audit::run(args),
+        Command::CheckLinks(args) => check_links::run(args),
+        Command::Crawl(args) => crawl::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Export(args) => export::run(args),
+        Command::Mirror(args) => mirror::run(args),
+        Command::Robots(command) => robots::run(command),
+        Command::Serve(args) => serve::run(args),
+        Command::Sitemap(args) => sitemap::run(args),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("kirby: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
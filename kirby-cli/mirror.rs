@@ -0,0 +1,512 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use clap::Args;
+use kirby_core::config::CrawlConfig;
+use kirby_core::crawler::Crawler;
+use kirby_core::export::Sink;
+use kirby_core::frontier::Frontier;
+use kirby_core::record::PageRecord;
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::args::SiteCrawlArgs;
+use crate::CommandError;
+
+/// Elements whose attribute is an asset the page needs to display.
+const ASSETS: &[(&str, &str)] = &[
+    ("img[src]", "src"),
+    ("img[srcset]", "srcset"),
+    ("source[src]", "src"),
+    ("source[srcset]", "srcset"),
+    ("script[src]", "src"),
+    ("video[src]", "src"),
+    ("video[poster]", "poster"),
+    ("audio[src]", "src"),
+    ("embed[src]", "src"),
+    ("iframe[src]", "src"),
+    ("input[type=image][src]", "src"),
+    ("link[rel~=stylesheet][href]", "href"),
+    ("link[rel~=icon][href]", "href"),
+    ("link[rel~=apple-touch-icon][href]", "href"),
+    ("link[rel~=manifest][href]", "href"),
+];
+
+/// Pages are crawled like `kirby crawl` does, then the images, scripts, stylesheets and other
+/// assets they use are downloaded as well, as long as they're in scope and robots.txt allows
+/// it.
+#[derive(Debug, Args)]
+pub struct MirrorArgs {
+    #[command(flatten)]
+    pub site: SiteCrawlArgs,
+    /// The directory the copy is written to, with a directory for each host.
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
+/// A downloaded HTML page or stylesheet, whose links are rewritten once everything is
+/// downloaded.
+#[derive(Debug)]
+struct Document {
+    path: Vec<String>,
+    /// The URL its links are relative to.
+    base: Url,
+    html: bool,
+}
+
+/// What has been downloaded so far.
+#[derive(Debug, Default)]
+struct Mirror {
+    root: PathBuf,
+    /// The local path of every URL downloaded, as percent-encoded segments.
+    files: HashMap<Url, Vec<String>>,
+    documents: Vec<Document>,
+    /// The assets the documents use.
+    assets: BTreeSet<Url>,
+}
+
+/// Writes the pages and assets crawled into the mirror.
+struct Download(Arc<Mutex<Mirror>>);
+
+impl Download {
+    fn lock(&self) -> MutexGuard<'_, Mirror> {
+        // Every change to the mirror is a single step, a panic while holding the lock can't
+        // leave it inconsistent.
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Sink for Download {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        if !(200..300).contains(&record.status) {
+            return Ok(());
+        }
+        let body = body.unwrap_or_default();
+        let content_type = record
+            .header("content-type")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let html = content_type.contains("html");
+        let css = content_type.starts_with("text/css");
+
+        let mut mirror = self.lock();
+        let path = local_path(record.final_url(), html);
+        let file = disk_path(&mirror.root, &path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file, body)?;
+        mirror.files.insert(record.url.clone(), path.clone());
+        mirror
+            .files
+            .insert(record.final_url().clone(), path.clone());
+
+        if html || css {
+            let text = String::from_utf8_lossy(body);
+            let base = record.final_url();
+            let assets = if html {
+                html_assets(&text, base)
+            } else {
+                css_assets(&text, base)
+            };
+            mirror.assets.extend(assets);
+            mirror.documents.push(Document {
+                path,
+                base: base.clone(),
+                html,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn run(args: MirrorArgs) -> Result<ExitCode, CommandError> {
+    let config = args.site.config();
+
+    let download = Download(Arc::new(Mutex::new(Mirror {
+        root: args.output.clone(),
+        ..Mirror::default()
+    })));
+    let pages = Crawler::with_sink(config.clone(), Download(Arc::clone(&download.0)))?.run()?;
+
+    // Assets are fetched like seeds that are not followed, and stylesheets can use more assets,
+    // so this goes on until no new ones turn up.
+    let mut scope = Frontier::new(args.site.scope);
+    for url in &args.site.urls {
+        let _ = scope.add_seed(url.clone());
+    }
+    let mut requested = HashSet::new();
+    let mut assets = 0;
+    loop {
+        let seeds = {
+            let mirror = download.lock();
+            mirror
+                .assets
+                .iter()
+                .filter(|asset| !mirror.files.contains_key(*asset) && !requested.contains(*asset))
+                .filter(|asset| scope.check_scope(asset).is_ok())
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        if seeds.is_empty() {
+            break;
+        }
+        requested.extend(seeds.iter().cloned());
+        let mut config = CrawlConfig {
+            seeds,
+            max_depth: 0,
+            ..config.clone()
+        };
+        config.budget.max_pages = None;
+        assets += Crawler::with_sink(config, Download(Arc::clone(&download.0)))?
+            .run()?
+            .pages;
+    }
+
+    let mirror = std::mem::take(&mut *download.lock());
+    for document in &mirror.documents {
+        let file = disk_path(&mirror.root, &document.path);
+        let text = fs::read(&file)?;
+        let text = String::from_utf8_lossy(&text);
+        let rewritten = if document.html {
+            rewrite_html(&text, document, &mirror.files)
+        } else {
+            rewrite_css(&text, document, &mirror.files)
+        };
+        fs::write(&file, rewritten)?;
+    }
+    eprintln!(
+        "mirrored {} pages and {assets} assets into {}",
+        pages.pages,
+        args.output.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Where a URL is saved, as percent-encoded path segments starting with the host. HTML pages
+/// get an `.html` extension so that browsers open them from disk and a page like `/docs`
+/// doesn't clash with the directory of `/docs/intro`. A query is kept in the file name after
+/// an `@`.
+fn local_path(url: &Url, html: bool) -> Vec<String> {
+    let mut host = url.host_str().unwrap_or_default().to_string();
+    if let Some(port) = url.port() {
+        host = format!("{host}_{port}");
+    }
+    let mut path = vec![host];
+    path.extend(url.path_segments().into_iter().flatten().map(String::from));
+    if path.len() == 1 {
+        path.push(String::new());
+    }
+    // Unwrapping is safe here because the path has a file name after the host.
+    let file = path.last_mut().unwrap();
+    if file.is_empty() {
+        *file = "index.html".to_string();
+    }
+    if let Some(query) = url.query() {
+        file.push('@');
+        file.push_str(&query.replace('/', "%2F"));
+    }
+    let lowercase = file.to_ascii_lowercase();
+    if html && !lowercase.ends_with(".html") && !lowercase.ends_with(".htm") {
+        file.push_str(".html");
+    }
+    path
+}
+
+/// The file of a local path, with its segments decoded.
+fn disk_path(root: &Path, path: &[String]) -> PathBuf {
+    let mut file = root.to_path_buf();
+    for segment in path {
+        let segment = percent_decode_str(segment)
+            .decode_utf8_lossy()
+            .replace(['/', '\\'], "_");
+        file.push(match segment.as_str() {
+            "" | "." | ".." => "_",
+            segment => segment,
+        });
+    }
+    file
+}
+
+/// The link from one local file to another.
+fn relative(from: &[String], to: &[String]) -> String {
+    let from_dirs = &from[..from.len() - 1];
+    let to_dirs = &to[..to.len() - 1];
+    let common = from_dirs
+        .iter()
+        .zip(to_dirs)
+        .take_while(|(from, to)| from == to)
+        .count();
+    let mut link = "../".repeat(from_dirs.len() - common);
+    link.push_str(&to[common..].join("/"));
+    // A colon before the first slash would be read as a scheme.
+    if link
+        .split('/')
+        .next()
+        .is_some_and(|first| first.contains(':'))
+    {
+        link.insert_str(0, "./");
+    }
+    link
+}
+
+/// What a link in a document becomes: a relative link to the local copy when the target was
+/// downloaded, an absolute URL otherwise. `None` leaves links to fragments of the same
+/// document and to other schemes, like `mailto:`, as they are.
+fn local_link(
+    link: &str,
+    document: &Document,
+    files: &HashMap<Url, Vec<String>>,
+) -> Option<String> {
+    let link = link.trim();
+    if link.is_empty() || link.starts_with('#') {
+        return None;
+    }
+    let mut url = document.base.join(link).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let fragment = url.fragment().map(|fragment| format!("#{fragment}"));
+    url.set_fragment(None);
+    let mut local = match files.get(&url) {
+        Some(path) => relative(&document.path, path),
+        None => url.to_string(),
+    };
+    local.push_str(fragment.as_deref().unwrap_or_default());
+    Some(local)
+}
+
+fn html_assets(text: &str, base: &Url) -> Vec<Url> {
+    let document = Html::parse_document(text);
+    let mut links = Vec::new();
+    for (selector, attribute) in ASSETS {
+        // Unwrapping is safe here because the selectors are fixed and valid.
+        let selector = Selector::parse(selector).unwrap();
+        for element in document.select(&selector) {
+            let Some(value) = element.value().attr(attribute) else {
+                continue;
+            };
+            if *attribute == "srcset" {
+                links.extend(srcset_urls(value).map(str::to_string));
+            } else {
+                links.push(value.to_string());
+            }
+        }
+    }
+    // Unwrapping is safe here because the selector is fixed and valid.
+    let styles = Selector::parse("style, [style]").unwrap();
+    for element in document.select(&styles) {
+        let css = match element.value().attr("style") {
+            Some(style) => style.to_string(),
+            None => element.text().collect(),
+        };
+        links.extend(css_links(&css));
+    }
+    resolve(links, base)
+}
+
+fn css_assets(text: &str, base: &Url) -> Vec<Url> {
+    resolve(css_links(text), base)
+}
+
+/// The URLs of a `srcset`, without their width or density descriptors.
+fn srcset_urls(srcset: &str) -> impl Iterator<Item = &str> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+}
+
+fn css_links(css: &str) -> Vec<String> {
+    [css_url(), css_import()]
+        .iter()
+        .flat_map(|regex| regex.captures_iter(css))
+        .filter_map(|captures| quoted(&captures).map(str::to_string))
+        .collect()
+}
+
+/// Resolves links to assets, keeping HTTP(S) URLs without their fragments.
+fn resolve(links: Vec<String>, base: &Url) -> Vec<Url> {
+    links
+        .iter()
+        .filter_map(|link| base.join(link.trim()).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .collect()
+}
+
+fn rewrite_html(text: &str, document: &Document, files: &HashMap<Url, Vec<String>>) -> String {
+    // The attributes are rewritten in the text rather than through a parsed document, which
+    // keeps the rest of the page byte for byte.
+    let attributes =
+        Regex::new(r#"(?i)\b(href|src|poster|srcset)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>"']+))"#)
+            // Unwrapping is safe here because the pattern is fixed and valid.
+            .unwrap();
+    let text = attributes.replace_all(text, |captures: &Captures| {
+        let name = &captures[1];
+        // Unwrapping is safe here because one of the value groups always matches.
+        let value = quoted_from(captures, 2).unwrap().replace("&amp;", "&");
+        let rewritten = if name.eq_ignore_ascii_case("srcset") {
+            let candidates = value
+                .split(',')
+                .map(|candidate| {
+                    let candidate = candidate.trim();
+                    let (link, descriptor) = candidate
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((candidate, ""));
+                    let link = local_link(link, document, files).unwrap_or(link.to_string());
+                    format!("{link} {descriptor}").trim_end().to_string()
+                })
+                .collect::<Vec<_>>();
+            candidates.join(", ")
+        } else {
+            match local_link(&value, document, files) {
+                Some(link) => link,
+                None => return captures[0].to_string(),
+            }
+        };
+        format!("{name}=\"{}\"", rewritten.replace('&', "&amp;"))
+    });
+    rewrite_css(&text, document, files)
+}
+
+fn rewrite_css(text: &str, document: &Document, files: &HashMap<Url, Vec<String>>) -> String {
+    let text = css_url().replace_all(text, |captures: &Captures| {
+        // Unwrapping is safe here because one of the value groups always matches.
+        match local_link(quoted(captures).unwrap(), document, files) {
+            Some(link) => format!("url(\"{link}\")"),
+            None => captures[0].to_string(),
+        }
+    });
+    css_import()
+        .replace_all(&text, |captures: &Captures| {
+            // Unwrapping is safe here because one of the value groups always matches.
+            match local_link(quoted(captures).unwrap(), document, files) {
+                Some(link) => format!("@import \"{link}\""),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// `url(...)` in CSS, with the link in one of the groups 1 to 3.
+fn css_url() -> Regex {
+    // Unwrapping is safe here because the pattern is fixed and valid.
+    Regex::new(r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)"'\s]*))\s*\)"#).unwrap()
+}
+
+/// `@import "..."` in CSS, with the link in group 1 or 2.
+fn css_import() -> Regex {
+    // Unwrapping is safe here because the pattern is fixed and valid.
+    Regex::new(r#"@import\s+(?:"([^"]*)"|'([^']*)')"#).unwrap()
+}
+
+/// The value of the first group that matched.
+fn quoted<'t>(captures: &Captures<'t>) -> Option<&'t str> {
+    quoted_from(captures, 1)
+}
+
+fn quoted_from<'t>(captures: &Captures<'t>, first: usize) -> Option<&'t str> {
+    (first..captures.len()).find_map(|group| captures.get(group).map(|value| value.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::{Cli, Command};
+
+    #[test]
+    fn mirrors_pages_and_assets_with_local_links() {
+        let png = |body: &str| Page::new(200, body).header("Content-Type", "image/png");
+        let site = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nDisallow: /private/")
+                .page(
+                    "/",
+                    Page::html(
+                        r#"<link rel="stylesheet" href="/style.css"><img src="logo.png">
+                           <a href="/about">About</a> <a href="/about#team">Team</a>
+                           <a href="/private/x">Private</a> <a href="http://other.invalid/">Out</a>
+                           <a href="mailto:hi@example.com">Mail</a>"#,
+                    ),
+                )
+                .page("/about", Page::html(r#"<a href="/">Home</a>"#))
+                .page(
+                    "/style.css",
+                    Page::new(200, r#"body { background: url("img/bg.png") }"#)
+                        .header("Content-Type", "text/css"),
+                )
+                .page("/logo.png", png("logo"))
+                .page("/img/bg.png", png("background")),
+        );
+        let output = tempfile::tempdir().unwrap();
+        let cli = Cli::try_parse_from([
+            "kirby",
+            "mirror",
+            site.url().as_str(),
+            "-o",
+            output.path().to_str().unwrap(),
+            "--delay",
+            "0",
+        ])
+        .unwrap();
+        let Command::Mirror(args) = cli.command else {
+            panic!("not mirror");
+        };
+        assert_eq!(run(args).unwrap(), ExitCode::SUCCESS);
+
+        let host = output.path().join(&local_path(site.url(), true)[0]);
+        let home = fs::read_to_string(host.join("index.html")).unwrap();
+        for link in [
+            r#"href="style.css""#,
+            r#"src="logo.png""#,
+            r#"href="about.html""#,
+            r#"href="about.html#team""#,
+            r#"href="http://other.invalid/""#,
+            r#"href="mailto:hi@example.com""#,
+        ] {
+            assert!(home.contains(link), "{link} in {home}");
+        }
+        assert!(home.contains(&format!(r#"href="{}private/x""#, site.url())));
+        assert_eq!(
+            fs::read_to_string(host.join("about.html")).unwrap(),
+            r#"<a href="index.html">Home</a>"#
+        );
+        assert_eq!(
+            fs::read_to_string(host.join("style.css")).unwrap(),
+            r#"body { background: url("img/bg.png") }"#
+        );
+        assert_eq!(fs::read(host.join("img/bg.png")).unwrap(), b"background");
+        site.assert_not_requested("/private/x");
+    }
+
+    #[test]
+    fn links_between_local_files() {
+        let page = local_path(
+            &Url::parse("https://example.com/docs/intro?lang=en").unwrap(),
+            true,
+        );
+        assert_eq!(page, ["example.com", "docs", "intro@lang=en.html"]);
+        let asset = local_path(&Url::parse("https://example.com/logo.png").unwrap(), false);
+        assert_eq!(relative(&page, &asset), "../logo.png");
+        let home = local_path(&Url::parse("http://example.com:8080").unwrap(), true);
+        assert_eq!(home, ["example.com_8080", "index.html"]);
+        assert_eq!(relative(&page, &home), "../../example.com_8080/index.html");
+    }
+}
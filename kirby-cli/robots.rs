@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Subcommand};
+use kirby_core::config::DEFAULT_USER_AGENT;
+use kirby_core::crawler::fetch_robots;
+use kirby_core::fetch::Fetcher;
+use kirby_core::robotstxt::RobotsTxt;
+use serde_json::json;
+use url::Url;
+
+use crate::CommandError;
+
+#[derive(Debug, Subcommand)]
+pub enum RobotsCommand {
+    /// Checks whether URLs may be crawled and prints the rule that decided. Exits with 1 when
+    /// any URL is denied.
+    Check(CheckArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    #[arg(required = true, value_name = "URL")]
+    pub urls: Vec<Url>,
+    /// The user agent to check for, matched against the user-agent lines of the groups.
+    #[arg(short = 'a', long, default_value = "KirbyBot")]
+    pub user_agent: String,
+    /// Reads the robots.txt from a file instead of fetching the one of each URL's origin.
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<PathBuf>,
+    /// Prints one JSON object per URL.
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn run(command: RobotsCommand) -> Result<ExitCode, CommandError> {
+    match command {
+        RobotsCommand::Check(args) => check(args, &mut io::stdout().lock()),
+    }
+}
+
+fn check(args: CheckArgs, out: &mut impl Write) -> Result<ExitCode, CommandError> {
+    let file = args.file.as_ref().map(fs::read_to_string).transpose()?;
+    let fetcher = Fetcher::new(DEFAULT_USER_AGENT);
+    // Mapping of origin -> its robots.txt, or why it couldn't be fetched.
+    let mut fetched = HashMap::new();
+
+    let mut denied = false;
+    for url in &args.urls {
+        let robots = match &file {
+            Some(file) => Ok(file.as_str()),
+            None => fetched
+                .entry(url.origin().ascii_serialization())
+                .or_insert_with(|| fetch_robots(&fetcher, url))
+                .as_deref()
+                .map_err(ToString::to_string),
+        };
+
+        let robots = match robots {
+            Ok(robots) => robots,
+            Err(error) => {
+                // Nothing may be crawled while the robots.txt can't be fetched.
+                denied = true;
+                if args.json {
+                    let line = json!({"url": url, "allowed": false, "error": error});
+                    writeln!(out, "{line}")?;
+                } else {
+                    writeln!(out, "denied  {url} (robots.txt unavailable: {error})")?;
+                }
+                continue;
+            }
+        };
+        let robots = RobotsTxt::parse(robots);
+        let decision = robots.check_url(&args.user_agent, url);
+        denied |= !decision.allowed;
+
+        if args.json {
+            let line = json!({
+                "url": url,
+                "allowed": decision.allowed,
+                "agent": decision.agent,
+                "rule": decision.rule.map(|rule| rule.to_string()),
+                "line": decision.rule.and_then(|rule| rule.line),
+            });
+            writeln!(out, "{line}")?;
+            continue;
+        }
+        let verdict = if decision.allowed {
+            "allowed"
+        } else {
+            "denied "
+        };
+        let reason = match (decision.agent, decision.rule) {
+            (None, _) => "no group for the user agent".to_string(),
+            (Some(agent), None) => format!("User-agent: {agent}, no matching rule"),
+            (Some(agent), Some(rule)) => match rule.line {
+                Some(line) => format!("User-agent: {agent}, {rule} on line {line}"),
+                None => format!("User-agent: {agent}, {rule}"),
+            },
+        };
+        writeln!(out, "{verdict} {url} ({reason})")?;
+    }
+
+    Ok(if denied {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_test::server::MockServer;
+    use kirby_test::site::Site;
+
+    use super::*;
+
+    #[test]
+    fn explains_each_decision() {
+        let site = MockServer::start(
+            Site::new().robots("User-agent: *\nDisallow: /private/\nAllow: /private/press"),
+        );
+        let args = CheckArgs {
+            urls: ["/", "/private/report", "/private/press?page=2"]
+                .iter()
+                .map(|path| site.url().join(path).unwrap())
+                .collect(),
+            user_agent: "KirbyBot".to_string(),
+            file: None,
+            json: false,
+        };
+        let mut out = Vec::new();
+        assert_eq!(check(args, &mut out).unwrap(), ExitCode::from(1));
+
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert!(
+            lines[0].starts_with("allowed")
+                && lines[0].ends_with("(User-agent: *, no matching rule)")
+        );
+        assert!(
+            lines[1].starts_with("denied")
+                && lines[1].ends_with("(User-agent: *, Disallow: /private/ on line 2)")
+        );
+        assert!(lines[2].ends_with("(User-agent: *, Allow: /private/press on line 3)"));
+        assert_eq!(site.paths(), ["/robots.txt"]);
+    }
+}
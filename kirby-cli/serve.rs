@@ -0,0 +1,163 @@
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+
+use clap::Args;
+use kirby_core::archive::replay::Replay;
+use kirby_core::store::fs::FsStore;
+
+use crate::CommandError;
+
+/// Runs until interrupted.
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// WARC files, segments, page stores, or directories of WARC files and segments.
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub address: SocketAddr,
+}
+
+pub fn run(args: ServeArgs) -> Result<ExitCode, CommandError> {
+    let mut replay = Replay::default();
+    for input in &args.inputs {
+        add_input(&mut replay, input)?;
+    }
+    let urls = replay.urls().count();
+    let server = replay.serve(args.address)?;
+    eprintln!(
+        "serving {urls} archived URLs at http://{}/, listed at http://{0}/_replay",
+        server.address()
+    );
+    loop {
+        thread::park();
+    }
+}
+
+fn add_input(replay: &mut Replay, path: &Path) -> Result<(), CommandError> {
+    if !path.is_dir() {
+        return add_file(replay, path);
+    }
+    if path.join("pages").is_dir() {
+        replay.add_store(FsStore::open(path)?)?;
+        return Ok(());
+    }
+    let mut files = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    files.sort();
+    for file in files.iter().filter(|file| file.is_file()) {
+        let name = file.to_string_lossy();
+        // Skips index sidecars and anything else that isn't an archive.
+        if [".warc", ".warc.gz", ".seg"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+        {
+            add_file(replay, file)?;
+        }
+    }
+    Ok(())
+}
+
+fn add_file(replay: &mut Replay, path: &Path) -> Result<(), CommandError> {
+    let name = path.to_string_lossy();
+    let with_path = |error: io::Error| format!("{}: {error}", path.display());
+    if name.ends_with(".warc") || name.ends_with(".warc.gz") {
+        replay.add_warc(path).map_err(with_path)?;
+    } else if name.ends_with(".seg") {
+        add_segment(replay, path)?;
+    } else if [".sqlite", ".sqlite3", ".db"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+    {
+        add_sqlite(replay, path)?;
+    } else {
+        return Err(format!(
+            "{}: unknown input, expected a page store, .warc, .warc.gz or .seg",
+            path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+fn add_segment(replay: &mut Replay, path: &Path) -> Result<(), CommandError> {
+    replay
+        .add_segment(path)
+        .map_err(|error| format!("{}: {error}", path.display()).into())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn add_segment(_replay: &mut Replay, path: &Path) -> Result<(), CommandError> {
+    Err(format!(
+        "{}: kirby was built without the zstd feature",
+        path.display()
+    )
+    .into())
+}
+
+#[cfg(feature = "sqlite")]
+fn add_sqlite(replay: &mut Replay, path: &Path) -> Result<(), CommandError> {
+    if !path.is_file() {
+        return Err(format!("{}: no such store", path.display()).into());
+    }
+    replay.add_store(kirby_core::store::sqlite::SqliteStore::open(path)?)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn add_sqlite(_replay: &mut Replay, path: &Path) -> Result<(), CommandError> {
+    Err(format!(
+        "{}: kirby was built without the sqlite feature",
+        path.display()
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use chrono::Utc;
+    use kirby_core::archive::warc::{HttpResponse, WarcWriter};
+    use url::Url;
+
+    use super::*;
+
+    #[test]
+    fn serves_the_warc_files_of_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = WarcWriter::new(File::create(dir.path().join("a.warc")).unwrap());
+        let url = Url::parse("https://example.com/").unwrap();
+        let headers = vec![("Content-Type".to_string(), "text/plain".to_string())];
+        writer
+            .write_response(&HttpResponse {
+                url: &url,
+                date: Utc::now(),
+                status: 200,
+                headers: &headers,
+                body: b"Archived",
+            })
+            .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        fs::write(dir.path().join("notes.txt"), "not an archive").unwrap();
+
+        let mut replay = Replay::default();
+        add_input(&mut replay, dir.path()).unwrap();
+        let server = replay.serve("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with("\r\n\r\nArchived"), "{response}");
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, ValueEnum};
+use kirby_core::config::DEFAULT_USER_AGENT;
+use kirby_core::crawler::fetch_robots;
+use kirby_core::fetch::Fetcher;
+use kirby_core::robotstxt::RobotsTxt;
+use kirby_core::sitemap::{fetch_all, SitemapEntry, SitemapKind};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use url::Url;
+
+use crate::CommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One URL per line, ready for `kirby crawl --seeds-file`.
+    Urls,
+    /// One JSON object per URL, with its lastmod, changefreq, priority and sitemap.
+    Jsonl,
+}
+
+#[derive(Debug, Args)]
+pub struct SitemapArgs {
+    /// Sitemaps to read, or sites with --from-robots.
+    #[arg(required = true, value_name = "URL")]
+    pub urls: Vec<Url>,
+    /// Reads the sitemaps listed in the robots.txt of each URL's site instead.
+    #[arg(long)]
+    pub from_robots: bool,
+    #[arg(long, value_enum, default_value_t = Format::Urls)]
+    pub format: Format,
+    /// Writes to a file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Prints only what changed since a previous dump, in either format: `+` for added URLs,
+    /// `-` for removed ones and `~` for ones with another lastmod, changefreq or priority.
+    #[arg(long, value_name = "PREVIOUS")]
+    pub diff: Option<PathBuf>,
+    /// Stops after fetching this many sitemaps, counting the ones indexes list.
+    #[arg(long, default_value_t = 1000)]
+    pub max_sitemaps: usize,
+    /// Exits with 1 when a sitemap couldn't be read or breaks the sitemaps.org protocol.
+    #[arg(long)]
+    pub strict: bool,
+}
+
+/// A URL listed in a sitemap, as dumped in JSONL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Listed {
+    #[serde(flatten)]
+    entry: SitemapEntry,
+    /// The sitemap the URL was listed in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sitemap: Option<Url>,
+}
+
+pub fn run(args: SitemapArgs) -> Result<ExitCode, CommandError> {
+    let fetcher = Fetcher::new(DEFAULT_USER_AGENT);
+    let roots = if args.from_robots {
+        sitemaps_in_robots(&fetcher, &args.urls)?
+    } else {
+        args.urls.clone()
+    };
+
+    let mut invalid = false;
+    let mut listed = Vec::new();
+    for fetched in fetch_all(&fetcher, &roots, args.max_sitemaps) {
+        let sitemap = match fetched.result {
+            Ok(sitemap) => sitemap,
+            Err(error) => {
+                eprintln!("error: {}: {error}", fetched.url);
+                invalid = true;
+                continue;
+            }
+        };
+        for issue in &sitemap.issues {
+            eprintln!("warning: {}: {issue}", fetched.url);
+            invalid = true;
+        }
+        if sitemap.kind == SitemapKind::UrlSet {
+            listed.extend(sitemap.entries.into_iter().map(|entry| Listed {
+                entry,
+                sitemap: Some(fetched.url.clone()),
+            }));
+        }
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match &args.diff {
+        Some(previous) => write_diff(&read_dump(previous)?, &listed, args.format, &mut out)?,
+        None => write_dump(&listed, args.format, &mut out)?,
+    }
+    out.flush()?;
+
+    Ok(if args.strict && invalid {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// The sitemaps listed in the robots.txt files of the sites, in order.
+fn sitemaps_in_robots(fetcher: &Fetcher, sites: &[Url]) -> Result<Vec<Url>, CommandError> {
+    let mut sitemaps = Vec::new();
+    let mut origins = HashSet::new();
+    for site in sites {
+        if !origins.insert(site.origin().ascii_serialization()) {
+            continue;
+        }
+        let robots = fetch_robots(fetcher, site)?;
+        for sitemap in RobotsTxt::parse(&robots).sitemaps() {
+            match site.join(sitemap) {
+                Ok(sitemap) => sitemaps.push(sitemap),
+                Err(error) => eprintln!("warning: {site}: invalid sitemap {sitemap:?}: {error}"),
+            }
+        }
+    }
+    Ok(sitemaps)
+}
+
+fn write_dump(listed: &[Listed], format: Format, out: &mut impl Write) -> io::Result<()> {
+    for listed in listed {
+        match format {
+            Format::Urls => writeln!(out, "{}", listed.entry.loc)?,
+            Format::Jsonl => writeln!(out, "{}", serde_json::to_string(listed)?)?,
+        }
+    }
+    Ok(())
+}
+
+/// Reads a dump in either format, telling them apart line by line.
+fn read_dump(path: &Path) -> Result<Vec<Listed>, CommandError> {
+    let mut listed = Vec::new();
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        let entry = if line.is_empty() {
+            continue;
+        } else if line.starts_with('{') {
+            serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {e}", path.display(), number + 1))
+        } else {
+            Url::parse(line)
+                .map(|loc| Listed {
+                    entry: SitemapEntry {
+                        loc,
+                        lastmod: None,
+                        changefreq: None,
+                        priority: None,
+                    },
+                    sitemap: None,
+                })
+                .map_err(|e| format!("{}:{}: {e}", path.display(), number + 1))
+        };
+        listed.push(entry?);
+    }
+    Ok(listed)
+}
+
+fn write_diff(
+    previous: &[Listed],
+    current: &[Listed],
+    format: Format,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let before = previous
+        .iter()
+        .map(|listed| (&listed.entry.loc, listed))
+        .collect::<HashMap<_, _>>();
+    let after = current
+        .iter()
+        .map(|listed| &listed.entry.loc)
+        .collect::<HashSet<_>>();
+
+    let mut change = |kind: &str, listed: &Listed| match format {
+        Format::Urls => {
+            let sign = match kind {
+                "added" => '+',
+                "removed" => '-',
+                _ => '~',
+            };
+            writeln!(out, "{sign} {}", listed.entry.loc)
+        }
+        Format::Jsonl => {
+            let mut line = json!({ "change": kind });
+            if let (Some(line), Ok(serde_json::Value::Object(entry))) =
+                (line.as_object_mut(), serde_json::to_value(listed))
+            {
+                line.extend(entry);
+            }
+            writeln!(out, "{line}")
+        }
+    };
+
+    for listed in current {
+        match before.get(&listed.entry.loc) {
+            None => change("added", listed)?,
+            // A dump of bare URLs doesn't tell whether the details changed.
+            Some(old) if old.sitemap.is_some() && old.entry != listed.entry => {
+                change("changed", listed)?
+            }
+            Some(_) => {}
+        }
+    }
+    for listed in previous {
+        if !after.contains(&listed.entry.loc) {
+            change("removed", listed)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+
+    #[test]
+    fn resolves_indexes_from_robots_and_diffs() {
+        let xml = |body: &str| Page::new(200, body).header("Content-Type", "application/xml");
+        let site = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nSitemap: /index.xml")
+                .page(
+                    "/index.xml",
+                    xml("<sitemapindex><sitemap><loc>{base}/pages.xml</loc></sitemap></sitemapindex>"),
+                )
+                .page(
+                    "/pages.xml",
+                    xml("<urlset><url><loc>{base}/a</loc><lastmod>2024-05-02</lastmod></url>\
+                         <url><loc>{base}/c</loc><priority>2</priority></url></urlset>"),
+                ),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let previous = dir.path().join("previous.txt");
+        let listed = |path: &str, lastmod: Option<&str>| Listed {
+            entry: SitemapEntry {
+                loc: site.url().join(path).unwrap(),
+                lastmod: lastmod.map(str::to_string),
+                changefreq: None,
+                priority: None,
+            },
+            sitemap: Some(site.url().join("/pages.xml").unwrap()),
+        };
+        let mut dump = Vec::new();
+        write_dump(
+            &[listed("/a", Some("2024-05-01")), listed("/b", None)],
+            Format::Jsonl,
+            &mut dump,
+        )
+        .unwrap();
+        fs::write(&previous, dump).unwrap();
+
+        let output = dir.path().join("diff.txt");
+        let args = SitemapArgs {
+            urls: vec![site.url().clone()],
+            from_robots: true,
+            format: Format::Urls,
+            output: Some(output.clone()),
+            diff: Some(previous),
+            max_sitemaps: 10,
+            strict: true,
+        };
+        // The priority of /c is out of range.
+        assert_eq!(run(args).unwrap(), ExitCode::from(1));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            format!("~ {0}a\n+ {0}c\n- {0}b\n", site.url())
+        );
+        assert_eq!(site.paths(), ["/robots.txt", "/index.xml", "/pages.xml"]);
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use kirby_core::crawler::{Crawler, Summary};
+use kirby_core::dashboard::{Dashboard, DashboardStatus};
+use kirby_core::events::EventBus;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use url::Url;
+
+use crate::CommandError;
+
+/// How often the display is redrawn.
+const TICK: Duration = Duration::from_millis(250);
+
+/// Runs the crawl on a background thread while showing its progress in the terminal.
+///
+/// `p` pauses and resumes the crawl. `q` closes the display, resuming the crawl when it's
+/// paused, and the crawl carries on until it's over.
+pub fn run(crawler: Crawler) -> Result<Summary, CommandError> {
+    let bus = EventBus::new();
+    let dashboard = Dashboard::new();
+    dashboard.attach(&bus);
+    let paused = dashboard.clone();
+    let crawler = crawler.events(bus).pause_when(move || paused.is_paused());
+    let crawl = thread::spawn(move || crawler.run());
+
+    let mut terminal = ratatui::init();
+    let shown = show(&mut terminal, &dashboard, &crawl);
+    ratatui::restore();
+    dashboard.resume();
+    shown?;
+    match crawl.join() {
+        Ok(summary) => Ok(summary?),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// Redraws the display until the crawl is over or it's closed.
+fn show(
+    terminal: &mut DefaultTerminal,
+    dashboard: &Dashboard,
+    crawl: &JoinHandle<io::Result<Summary>>,
+) -> io::Result<()> {
+    while !crawl.is_finished() {
+        let status = dashboard.status();
+        terminal.draw(|frame| draw(frame, &status))?;
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('p' | ' ') if dashboard.is_paused() => dashboard.resume(),
+            KeyCode::Char('p' | ' ') => dashboard.pause(),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            // The terminal is in raw mode, so Ctrl-C arrives as a key instead of a signal.
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, status: &DashboardStatus) {
+    let [header, throughput, progress, hosts, errors, keys] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(2),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Length(8),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let (state, color) = if status.paused {
+        ("Paused", Color::Yellow)
+    } else {
+        ("Running", Color::Green)
+    };
+    frame.render_widget(
+        Line::styled(state, Style::new().fg(color).add_modifier(Modifier::BOLD)),
+        header,
+    );
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::raw(format!(
+                "{} responses, {}",
+                status.responses,
+                size(status.bytes)
+            )),
+            Line::raw(format!(
+                "Last minute: {} responses, {}",
+                status.responses_per_minute,
+                size(status.bytes_per_minute)
+            )),
+        ]),
+        throughput,
+    );
+
+    let done = &status.progress;
+    let eta = match done.eta_secs.or(done.eta_best_secs) {
+        Some(seconds) => format!(", ETA {}", duration(seconds)),
+        None => String::new(),
+    };
+    frame.render_widget(
+        Gauge::default()
+            .ratio(done.fraction.clamp(0.0, 1.0))
+            .label(format!(
+                "{} of {} URLs{eta}",
+                done.completed, done.discovered
+            ))
+            .gauge_style(Style::new().fg(Color::Blue)),
+        progress,
+    );
+
+    let names = status
+        .queues
+        .keys()
+        .chain(status.active.keys())
+        .collect::<BTreeSet<_>>();
+    let count =
+        |counts: &BTreeMap<String, u64>, host: &str| counts.get(host).copied().unwrap_or_default();
+    let rows = names.iter().map(|host| {
+        Row::new([
+            host.to_string(),
+            count(&status.queues, host).to_string(),
+            count(&status.active, host).to_string(),
+        ])
+    });
+    let queued = status.queues.values().sum::<u64>();
+    let fetching = status.active.values().sum::<u64>();
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new(["Host", "Queued", "Fetching"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(format!(
+            "Hosts: {} active, {queued} queued, {fetching} fetching",
+            status.active.len()
+        ))),
+        hosts,
+    );
+
+    let recent = status.recent_errors.iter().map(|error| {
+        let url = error.error.url().map_or("", Url::as_str);
+        format!("{} {url} {}", error.at.format("%H:%M:%S"), error.error)
+    });
+    frame.render_widget(
+        List::new(recent).block(Block::bordered().title("Recent errors")),
+        errors,
+    );
+
+    frame.render_widget(
+        Line::styled(
+            "p pause/resume  q close the display",
+            Style::new().fg(Color::DarkGray),
+        ),
+        keys,
+    );
+}
+
+/// E.g. `1.5 MB`.
+fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// E.g. `1h 5m`, or `40s` under a minute.
+fn duration(seconds: u64) -> String {
+    match (seconds / 3600, seconds / 60 % 60) {
+        (0, 0) => format!("{seconds}s"),
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_core::error::{ErrorKind, KirbyError};
+    use kirby_core::events::CrawlEvent;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::*;
+
+    #[test]
+    fn shows_throughput_hosts_and_errors() {
+        let dashboard = Dashboard::new();
+        let url = |s: &str| Url::parse(s).unwrap();
+        for page in [
+            "https://a.example/1",
+            "https://a.example/2",
+            "https://b.example/",
+        ] {
+            dashboard.observe(&CrawlEvent::UrlEnqueued {
+                url: url(page),
+                depth: 0,
+            });
+        }
+        dashboard.observe(&CrawlEvent::FetchStarted {
+            url: url("https://a.example/1"),
+            attempt: 1,
+        });
+        dashboard.observe(&CrawlEvent::FetchStarted {
+            url: url("https://b.example/"),
+            attempt: 1,
+        });
+        dashboard.observe(&CrawlEvent::FetchFinished {
+            url: url("https://b.example/"),
+            status: 200,
+            duration: Duration::from_millis(10),
+            bytes: 1500,
+        });
+        let error = KirbyError::new(ErrorKind::Parse, "<html> expected");
+        dashboard.observe(&CrawlEvent::Error { error });
+        dashboard.pause();
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        let status = dashboard.status();
+        terminal.draw(|frame| draw(frame, &status)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let lines = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "Paused");
+        assert_eq!(lines[1], "1 responses, 1.5 KB");
+        assert!(lines[3].contains("2 of 3 URLs"), "{}", lines[3]);
+        assert!(lines[4].contains("Hosts: 1 active, 1 queued, 1 fetching"));
+        assert!(lines[6].starts_with("│a.example"), "{}", lines[6]);
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("parsing failed: <html> expected")));
+        assert_eq!(lines[19], "p pause/resume  q close the display");
+    }
+}
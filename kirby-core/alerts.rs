@@ -0,0 +1,396 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::events::{CrawlEvent, EventBus, SubscriptionId};
+use crate::hoststats::HostStats;
+
+/// Something to alert on, for unattended crawls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertCondition {
+    /// More than this share of a host's latest fetches, between 0 and 1, failed or got a
+    /// status other than 2xx or 3xx.
+    ErrorRate(f64),
+    /// A host answered `429 Too Many Requests` this many times in a row.
+    RateLimited(u32),
+    /// The p95 latency of a host's latest responses went above this.
+    LatencySpike(Duration),
+    /// No fetch finished for this long.
+    NoProgress(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    ErrorRate,
+    RateLimited,
+    LatencySpike,
+    NoProgress,
+}
+
+/// A condition that was met, published as [`CrawlEvent::Alert`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub at: DateTime<Utc>,
+    pub kind: AlertKind,
+    /// The host the alert is about, `None` for the whole crawl.
+    pub host: Option<String>,
+    pub message: String,
+}
+
+/// Raises alerts when hosts fail, rate limit or slow down, or when the crawl stops making
+/// progress.
+///
+/// An alert fires once when its condition is met and again only after the condition cleared
+/// in between, so a struggling host doesn't flood the bus. The error rate and latency are
+/// those of [`HostStats`], and only judged once a host has at least
+/// [`min_fetches`](Self::min_fetches) fetches in its window.
+///
+/// [`attach`](Self::attach) follows the crawl through its events and publishes alerts on the
+/// same bus; since a stalled crawl has no events, [`watch`](Self::watch) checks for progress
+/// on a timer. An [`AlertWebhook`] can pass the alerts on.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_core::alerts::{AlertCondition, AlertKind, AlertMonitor};
+/// use kirby_core::events::{CrawlEvent, EventBus};
+/// use url::Url;
+///
+/// let bus = EventBus::new();
+/// let alerts = bus.subscribe();
+/// let monitor = AlertMonitor::new()
+///     .condition(AlertCondition::RateLimited(3))
+///     .condition(AlertCondition::NoProgress(Duration::from_secs(600)));
+/// monitor.attach(&bus);
+///
+/// let url = Url::parse("https://example.com/").unwrap();
+/// for _ in 0..3 {
+///     bus.publish(CrawlEvent::FetchFinished {
+///         url: url.clone(),
+///         status: 429,
+///         duration: Duration::from_millis(50),
+///         bytes: 0,
+///     });
+/// }
+///
+/// let alert = alerts.try_iter().find_map(|event| match event {
+///     CrawlEvent::Alert { alert } => Some(alert),
+///     _ => None,
+/// });
+/// assert_eq!(alert.unwrap().kind, AlertKind::RateLimited);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AlertMonitor {
+    conditions: Vec<AlertCondition>,
+    min_fetches: u64,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    stats: HostStats,
+    rate_limited: HashMap<String, u32>,
+    last_progress: Instant,
+    /// The alerts whose condition hasn't cleared yet.
+    active: HashSet<(AlertKind, Option<String>)>,
+}
+
+impl Default for AlertMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertMonitor {
+    /// A monitor without conditions, judging hosts after 20 fetches.
+    pub fn new() -> Self {
+        Self {
+            conditions: Vec::new(),
+            min_fetches: 20,
+            state: Arc::new(Mutex::new(State {
+                stats: HostStats::new(),
+                rate_limited: HashMap::new(),
+                last_progress: Instant::now(),
+                active: HashSet::new(),
+            })),
+        }
+    }
+
+    pub fn condition(mut self, condition: AlertCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Sets how many fetches a host needs before its error rate and latency are judged.
+    pub fn min_fetches(mut self, min_fetches: u64) -> Self {
+        self.min_fetches = min_fetches.max(1);
+        self
+    }
+
+    /// Follows the crawl through the events published on `bus`, publishing alerts on it.
+    pub fn attach(&self, bus: &EventBus) -> SubscriptionId {
+        let monitor = self.clone();
+        let publisher = bus.clone();
+        bus.on(move |event| {
+            for alert in monitor.observe(event) {
+                publisher.publish(CrawlEvent::Alert { alert });
+            }
+        })
+    }
+
+    /// Checks for progress every `interval` from a background thread, publishing alerts on
+    /// `bus`, until the returned watcher is dropped.
+    pub fn watch(&self, bus: &EventBus, interval: Duration) -> AlertWatcher {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let monitor = self.clone();
+        let bus = bus.clone();
+        let thread = thread::spawn(move || {
+            while stopped.recv_timeout(interval) == Err(mpsc::RecvTimeoutError::Timeout) {
+                for alert in monitor.check(Instant::now()) {
+                    bus.publish(CrawlEvent::Alert { alert });
+                }
+            }
+        });
+        AlertWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Takes an event into account, returning the alerts it raised.
+    pub fn observe(&self, event: &CrawlEvent) -> Vec<Alert> {
+        let mut state = self.state();
+        let host = match event {
+            CrawlEvent::FetchFinished { url, status, .. } => {
+                state.last_progress = Instant::now();
+                let Some(host) = url.host_str() else {
+                    return Vec::new();
+                };
+                let rate_limited = state.rate_limited.entry(host.to_string()).or_default();
+                if *status == 429 {
+                    *rate_limited += 1;
+                } else {
+                    *rate_limited = 0;
+                }
+                host.to_string()
+            }
+            CrawlEvent::Error { error } => match error.host() {
+                Some(host) => host.to_string(),
+                None => return Vec::new(),
+            },
+            _ => return Vec::new(),
+        };
+        state.stats.observe(event);
+
+        let mut alerts = Vec::new();
+        for condition in &self.conditions {
+            let (kind, message) = match *condition {
+                AlertCondition::ErrorRate(threshold) => {
+                    let health = state.stats.health(&host);
+                    let failing = health
+                        .filter(|health| health.fetches >= self.min_fetches)
+                        .map(|health| (1.0 - health.success_rate, health.fetches))
+                        .filter(|(rate, _)| *rate > threshold);
+                    let message = failing.map(|(rate, fetches)| {
+                        format!(
+                            "{:.1}% of the latest {fetches} fetches failed",
+                            rate * 100.0
+                        )
+                    });
+                    (AlertKind::ErrorRate, message)
+                }
+                AlertCondition::RateLimited(consecutive) => {
+                    let count = state.rate_limited.get(&host).copied().unwrap_or_default();
+                    let message = (count >= consecutive.max(1))
+                        .then(|| format!("{count} responses in a row were 429 Too Many Requests"));
+                    (AlertKind::RateLimited, message)
+                }
+                AlertCondition::LatencySpike(threshold) => {
+                    let message = state
+                        .stats
+                        .health(&host)
+                        .filter(|health| health.fetches >= self.min_fetches)
+                        .filter(|health| health.p95_ms > threshold.as_millis() as u64)
+                        .map(|health| format!("p95 latency is {} ms", health.p95_ms));
+                    (AlertKind::LatencySpike, message)
+                }
+                AlertCondition::NoProgress(_) => continue,
+            };
+            alerts.extend(state.update(kind, Some(&host), message));
+        }
+        alerts
+    }
+
+    /// Checks whether the crawl stalled as of `now`, returning the alerts raised.
+    pub fn check(&self, now: Instant) -> Vec<Alert> {
+        let mut state = self.state();
+        let stalled = now.saturating_duration_since(state.last_progress);
+        let mut alerts = Vec::new();
+        for condition in &self.conditions {
+            if let AlertCondition::NoProgress(after) = *condition {
+                let message = (stalled >= after)
+                    .then(|| format!("no fetch finished for {}s", stalled.as_secs()));
+                alerts.extend(state.update(AlertKind::NoProgress, None, message));
+            }
+        }
+        alerts
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Every update leaves the state consistent, so it's still usable after a panic.
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl State {
+    /// Raises an alert when its condition became met, `message` is `None` when it isn't.
+    fn update(
+        &mut self,
+        kind: AlertKind,
+        host: Option<&str>,
+        message: Option<String>,
+    ) -> Option<Alert> {
+        let key = (kind, host.map(str::to_string));
+        match message {
+            Some(message) if self.active.insert(key.clone()) => Some(Alert {
+                at: Utc::now(),
+                kind,
+                host: key.1,
+                message,
+            }),
+            Some(_) => None,
+            None => {
+                self.active.remove(&key);
+                None
+            }
+        }
+    }
+}
+
+/// The background thread started by [`AlertMonitor::watch`], stopped when dropped.
+pub struct AlertWatcher {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for AlertWatcher {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// POSTs alerts as JSON to an HTTP endpoint, e.g. a chat or paging integration.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct AlertWebhook {
+    agent: ureq::Agent,
+    endpoint: url::Url,
+    headers: Vec<(String, String)>,
+}
+
+#[cfg(feature = "http")]
+impl AlertWebhook {
+    pub fn new(endpoint: url::Url) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+            endpoint,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds a header to every request, e.g. `Authorization`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn send(&self, alert: &Alert) -> std::io::Result<()> {
+        let mut request = self
+            .agent
+            .post(self.endpoint.as_str())
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        match request.send_bytes(&serde_json::to_vec(alert)?) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, _)) => Err(std::io::Error::other(format!(
+                "POST {} failed with {status}",
+                self.endpoint
+            ))),
+            Err(error) => Err(std::io::Error::other(error)),
+        }
+    }
+
+    /// Sends every alert published on `bus`. Alerts that can't be delivered are dropped, so
+    /// an unreachable endpoint doesn't stop the crawl.
+    pub fn attach(self, bus: &EventBus) -> SubscriptionId {
+        bus.on(move |event| {
+            if let CrawlEvent::Alert { alert } = event {
+                let _ = self.send(alert);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+    use crate::error::{ErrorKind, KirbyError};
+
+    #[test]
+    fn fires_once_per_episode() {
+        let monitor = AlertMonitor::new()
+            .min_fetches(4)
+            .condition(AlertCondition::ErrorRate(0.5))
+            .condition(AlertCondition::LatencySpike(Duration::from_secs(1)))
+            .condition(AlertCondition::NoProgress(Duration::from_secs(60)));
+        let url = Url::parse("https://example.com/").unwrap();
+        let fetch = |status, ms| CrawlEvent::FetchFinished {
+            url: url.clone(),
+            status,
+            duration: Duration::from_millis(ms),
+            bytes: 0,
+        };
+        let error = || CrawlEvent::Error {
+            error: KirbyError::new(ErrorKind::Connect, "refused").with_url(url.clone()),
+        };
+
+        let mut kinds = Vec::new();
+        for event in [fetch(200, 10), error(), error(), fetch(500, 10), error()] {
+            kinds.extend(monitor.observe(&event).into_iter().map(|alert| alert.kind));
+        }
+        assert_eq!(kinds, [AlertKind::ErrorRate]);
+
+        // Clearing the condition lets it fire again later.
+        for _ in 0..4 {
+            assert!(monitor.observe(&fetch(200, 10)).is_empty());
+        }
+        let alerts = monitor.observe(&fetch(200, 5000));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, AlertKind::LatencySpike);
+        assert_eq!(alerts[0].host.as_deref(), Some("example.com"));
+        assert_eq!(alerts[0].message, "p95 latency is 5000 ms");
+        assert!(monitor.observe(&fetch(200, 5000)).is_empty());
+
+        let now = Instant::now();
+        assert!(monitor.check(now).is_empty());
+        let stalled = monitor.check(now + Duration::from_secs(90));
+        assert_eq!(stalled[0].kind, AlertKind::NoProgress);
+        assert!(monitor.check(now + Duration::from_secs(120)).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+use percent_encoding::percent_decode_str;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+use crate::extract::element_text;
+
+/// A link that points at a specific element of a document through its fragment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FragmentLink {
+    /// The page containing the link.
+    pub source: Url,
+    /// The linked document, without its fragment.
+    pub target: Url,
+    /// The percent-decoded fragment, without the `#`.
+    pub fragment: String,
+    pub anchor_text: String,
+}
+
+/// Validates that fragment links (`#section` and `page.html#section`) point at an element with
+/// a matching `id` (or an `<a name>`) in the target document.
+///
+/// Pages are added as they are crawled, links are only checked against documents that have
+/// been added so links to pages outside the crawl are never reported as broken.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::anchors::AnchorChecker;
+/// use url::Url;
+///
+/// let mut checker = AnchorChecker::default();
+/// checker.add_page(
+///     &Url::parse("https://example.com/").unwrap(),
+///     r##"<a href="#intro">Intro</a> <a href="/docs#install">Install</a> <h2 id="intro">Hi</h2>"##,
+/// );
+/// checker.add_page(
+///     &Url::parse("https://example.com/docs").unwrap(),
+///     r#"<h2 id="setup">Setup</h2>"#,
+/// );
+///
+/// let broken = checker.broken();
+/// assert_eq!(broken.len(), 1);
+/// assert_eq!(broken[0].target.as_str(), "https://example.com/docs");
+/// assert_eq!(broken[0].fragment, "install");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AnchorChecker {
+    /// Mapping of document URL (without fragment) -> ids and names in the document.
+    targets: HashMap<Url, HashSet<String>>,
+    links: Vec<FragmentLink>,
+}
+
+impl AnchorChecker {
+    /// Records the anchors a page provides and the fragment links it contains.
+    pub fn add_page(&mut self, url: &Url, html: &str) {
+        let document = Html::parse_document(html);
+        self.targets
+            .insert(without_fragment(url), anchor_targets(&document));
+        self.links.extend(fragment_links(&document, url));
+    }
+
+    /// Returns true if a fragment exists in a document that has been added, `None` when the
+    /// document hasn't been added.
+    pub fn has_anchor(&self, document: &Url, fragment: &str) -> Option<bool> {
+        let targets = self.targets.get(&without_fragment(document))?;
+        Some(is_implicit_target(fragment) || targets.contains(fragment))
+    }
+
+    /// All fragment links that point at a missing element in a known document, in the order
+    /// the pages were added.
+    pub fn broken(&self) -> Vec<&FragmentLink> {
+        self.links
+            .iter()
+            .filter(|link| self.has_anchor(&link.target, &link.fragment) == Some(false))
+            .collect()
+    }
+
+    /// Fragment links whose target document hasn't been added, so they couldn't be checked.
+    pub fn unchecked(&self) -> Vec<&FragmentLink> {
+        self.links
+            .iter()
+            .filter(|link| !self.targets.contains_key(&link.target))
+            .collect()
+    }
+}
+
+/// Collects the fragments a document can be linked to with: every `id` and every `<a name>`.
+pub fn anchor_targets(document: &Html) -> HashSet<String> {
+    // Unwrapping is safe here because the selector is a valid constant.
+    let selector = Selector::parse("[id], a[name]").unwrap();
+    let mut targets = HashSet::new();
+    for element in document.select(&selector) {
+        if let Some(id) = element.value().id() {
+            targets.insert(id.to_string());
+        }
+        if element.value().name() == "a" {
+            if let Some(name) = element.attr("name") {
+                targets.insert(name.to_string());
+            }
+        }
+    }
+    targets
+}
+
+/// Collects the links in a document that carry a fragment, resolved against the page URL or
+/// `<base href>`.
+///
+/// Client side routes (`#/path`, `#!path`) and text fragments (`#:~:text=`) aren't element
+/// references so they are skipped.
+pub fn fragment_links(document: &Html, page_url: &Url) -> Vec<FragmentLink> {
+    // Unwrapping is safe here because the selectors are valid constants.
+    let base_selector = Selector::parse("base[href]").unwrap();
+    let base = document
+        .select(&base_selector)
+        .next()
+        .and_then(|base| page_url.join(base.attr("href")?).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let selector = Selector::parse("a[href], area[href]").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|link| {
+            let href = link.attr("href")?.trim();
+            if !href.contains('#') {
+                return None;
+            }
+
+            let url = base.join(href).ok()?;
+            let fragment = url.fragment()?;
+            if fragment.starts_with('/') || fragment.starts_with('!') || fragment.starts_with(":~:")
+            {
+                return None;
+            }
+
+            Some(FragmentLink {
+                source: without_fragment(page_url),
+                target: without_fragment(&url),
+                fragment: percent_decode_str(fragment)
+                    .decode_utf8_lossy()
+                    .into_owned(),
+                anchor_text: element_text(link),
+            })
+        })
+        .collect()
+}
+
+/// Fragments that are valid without a matching element, per the HTML spec.
+fn is_implicit_target(fragment: &str) -> bool {
+    fragment.is_empty() || fragment.eq_ignore_ascii_case("top")
+}
+
+fn without_fragment(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn collects_targets_and_links() {
+        let html = r##"
+        <base href="https://example.com/docs/">
+        <h1 id="top-heading">Title</h1>
+        <a name="legacy"></a>
+        <a href="#caf%C3%A9">Café</a>
+        <a href="guide.html#step+1">Step</a>
+        <a href="#/route">Route</a>
+        <a href="#:~:text=hello">Text</a>
+        <a href="/plain">Plain</a>
+        "##;
+
+        let document = Html::parse_document(html);
+        let targets = anchor_targets(&document);
+        assert!(targets.contains("top-heading"));
+        assert!(targets.contains("legacy"));
+
+        let links = fragment_links(&document, &url("https://example.com/docs/index.html#x"));
+        let links = links
+            .iter()
+            .map(|l| {
+                (
+                    l.target.as_str(),
+                    l.fragment.as_str(),
+                    l.anchor_text.as_str(),
+                )
+            })
+            .collect::<Vec<(&str, &str, &str)>>();
+        assert_eq!(
+            links,
+            vec![
+                ("https://example.com/docs/", "café", "Café"),
+                ("https://example.com/docs/guide.html", "step+1", "Step"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_broken_and_unchecked_anchors() {
+        let mut checker = AnchorChecker::default();
+        checker.add_page(
+            &url("https://example.com/a"),
+            r##"
+            <section id="one"></section>
+            <a href="#one">ok</a>
+            <a href="#two">missing</a>
+            <a href="#">empty</a>
+            <a href="#top">top</a>
+            <a href="/b#anchor">other page</a>
+            <a href="https://elsewhere.com/#x">external</a>
+            "##,
+        );
+        checker.add_page(&url("https://example.com/b"), r#"<a name="anchor"></a>"#);
+
+        let broken = checker
+            .broken()
+            .iter()
+            .map(|l| l.fragment.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(broken, vec!["two"]);
+
+        let unchecked = checker.unchecked();
+        assert_eq!(unchecked.len(), 1);
+        assert_eq!(unchecked[0].target.as_str(), "https://elsewhere.com/");
+
+        assert_eq!(
+            checker.has_anchor(&url("https://example.com/b#x"), "anchor"),
+            Some(true)
+        );
+        assert_eq!(
+            checker.has_anchor(&url("https://example.com/c"), "anchor"),
+            None
+        );
+    }
+}
@@ -0,0 +1,9 @@
+pub mod cdxj;
+pub mod replay;
+#[cfg(feature = "http")]
+pub mod s3;
+#[cfg(feature = "zstd")]
+pub mod segment;
+#[cfg(feature = "wacz")]
+pub mod wacz;
+pub mod warc;
@@ -0,0 +1,354 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, Timelike, Utc};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::warc::{self, WarcRecordType, WrittenRecord};
+
+/// One line of a CDXJ index: where a capture of a URL is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdxjEntry {
+    /// The [`surt`] of the URL, which the index is sorted by.
+    pub key: String,
+    /// When the capture was made, to the second.
+    pub timestamp: DateTime<Utc>,
+    pub url: Url,
+    /// The response's media type, `warc/revisit` for revisit records.
+    pub mime: String,
+    pub status: Option<u16>,
+    pub digest: Option<String>,
+    /// The offset of the record in the WARC file, compressed when the file is.
+    pub offset: u64,
+    /// The length of the record in the WARC file, compressed when the file is.
+    pub length: u64,
+    /// The name of the WARC file.
+    pub filename: String,
+}
+
+impl CdxjEntry {
+    /// The entry of a response or revisit record, `None` for other records.
+    pub fn from_record(record: &WrittenRecord, filename: &str) -> Option<Self> {
+        let url = record.target_uri.clone()?;
+        let mime = match record.record_type {
+            WarcRecordType::Response => record.mime_type.as_deref().unwrap_or("unk"),
+            WarcRecordType::Revisit => "warc/revisit",
+            _ => return None,
+        };
+
+        Some(Self {
+            key: surt(&url),
+            // Unwrapping is safe here because dropping nanoseconds keeps the date in range.
+            timestamp: record.date.with_nanosecond(0).unwrap(),
+            url,
+            mime: mime.to_string(),
+            status: record.status,
+            digest: record.payload_digest.clone(),
+            offset: record.offset,
+            length: record.length,
+            filename: filename.to_string(),
+        })
+    }
+
+    /// Parses a line of a CDXJ index, e.g. written by pywb or this crate.
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let invalid =
+            || io::Error::new(ErrorKind::InvalidData, format!("invalid CDXJ line: {line}"));
+        let mut parts = line.splitn(3, ' ');
+        let (Some(key), Some(timestamp), Some(fields)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let timestamp = NaiveDateTime::parse_from_str(timestamp, "%Y%m%d%H%M%S")
+            .map_err(|_| invalid())?
+            .and_utc();
+        let fields: Value = serde_json::from_str(fields).map_err(|_| invalid())?;
+
+        // pywb writes numbers as strings, both are accepted.
+        let number = |name: &str| match &fields[name] {
+            Value::String(text) => text.parse::<u64>().ok(),
+            value => value.as_u64(),
+        };
+        let text = |name: &str| fields[name].as_str().map(str::to_string);
+
+        Ok(Self {
+            key: key.to_string(),
+            timestamp,
+            url: text("url")
+                .and_then(|url| Url::parse(&url).ok())
+                .ok_or_else(invalid)?,
+            mime: text("mime").unwrap_or_else(|| "unk".to_string()),
+            status: number("status").and_then(|status| u16::try_from(status).ok()),
+            digest: text("digest"),
+            offset: number("offset").ok_or_else(invalid)?,
+            length: number("length").ok_or_else(invalid)?,
+            filename: text("filename").ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl fmt::Display for CdxjEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fields = json!({
+            "url": self.url,
+            "mime": self.mime,
+            "offset": self.offset.to_string(),
+            "length": self.length.to_string(),
+            "filename": self.filename,
+        });
+        if let Some(status) = self.status {
+            fields["status"] = status.to_string().into();
+        }
+        if let Some(digest) = &self.digest {
+            fields["digest"] = digest.as_str().into();
+        }
+        write!(
+            f,
+            "{} {} {fields}",
+            self.key,
+            self.timestamp.format("%Y%m%d%H%M%S")
+        )
+    }
+}
+
+/// A CDXJ index, which makes the captures in WARC files seekable by URL and time.
+///
+/// Entries are added as records are written, see [`WarcWriter::set_index`], or read from
+/// existing WARC files with [`from_warc`](Self::from_warc). The index is written sorted by
+/// key and time, the order replay tools such as pywb expect.
+///
+/// [`WarcWriter::set_index`]: super::warc::WarcWriter::set_index
+///
+/// # Example
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use kirby_core::archive::cdxj::CdxjIndex;
+/// use kirby_core::archive::warc::{HttpResponse, WarcWriter};
+/// use url::Url;
+///
+/// let url = Url::parse("https://example.com/").unwrap();
+/// let mut writer = WarcWriter::gzip(Vec::new());
+/// writer.set_index("crawl.warc.gz");
+/// for day in [1, 2] {
+///     let date = Utc.with_ymd_and_hms(2026, 5, day, 8, 0, 0).unwrap();
+///     let body = format!("<p>Day {day}</p>");
+///     writer
+///         .write_response(&HttpResponse {
+///             url: &url,
+///             date,
+///             status: 200,
+///             headers: &[],
+///             body: body.as_bytes(),
+///         })
+///         .unwrap();
+/// }
+///
+/// let index = writer.index().unwrap();
+/// let noon = Utc.with_ymd_and_hms(2026, 5, 2, 12, 0, 0).unwrap();
+/// let capture = index.closest(&url, noon).unwrap();
+/// assert_eq!(capture.timestamp.date_naive().to_string(), "2026-05-02");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CdxjIndex {
+    entries: Vec<CdxjEntry>,
+}
+
+impl CdxjIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes an existing WARC file, uncompressed or with every record in its own gzip
+    /// member.
+    pub fn from_warc(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| io::Error::other(format!("not a WARC file name: {}", path.display())))?;
+
+        let mut index = Self::new();
+        for record in warc::scan_records(File::open(path)?)? {
+            index.add(&record, filename);
+        }
+        Ok(index)
+    }
+
+    /// Reads an index, skipping empty lines.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut index = Self::new();
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                index.entries.push(CdxjEntry::parse(&line)?);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Indexes a written record, returning whether it was a response or revisit record.
+    pub fn add(&mut self, record: &WrittenRecord, filename: &str) -> bool {
+        match CdxjEntry::from_record(record, filename) {
+            Some(entry) => {
+                self.entries.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds the entries of another index, e.g. of another WARC file of the same crawl.
+    pub fn merge(&mut self, other: CdxjIndex) {
+        self.entries.extend(other.entries);
+    }
+
+    /// The entries in the order they were added or read.
+    pub fn entries(&self) -> &[CdxjEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The captures of a URL, oldest first.
+    pub fn captures(&self, url: &Url) -> Vec<&CdxjEntry> {
+        let key = surt(url);
+        let mut captures = self
+            .entries
+            .iter()
+            .filter(|entry| entry.key == key)
+            .collect::<Vec<_>>();
+        captures.sort_by_key(|entry| entry.timestamp);
+        captures
+    }
+
+    /// The capture of a URL closest in time to `at`, the earlier one on ties.
+    pub fn closest(&self, url: &Url, at: DateTime<Utc>) -> Option<&CdxjEntry> {
+        self.captures(url)
+            .into_iter()
+            .min_by_key(|entry| (entry.timestamp - at).abs())
+    }
+
+    /// Writes the index, one line per entry sorted by key and time.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut lines = self
+            .entries
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        lines.sort();
+        for line in lines {
+            writeln!(writer, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The Sort-friendly URI Reordering Transform of a URL, e.g. `com,example)/a?b=1` for
+/// `https://www.example.com/a?b=1`.
+///
+/// The scheme and a leading `www.` are dropped, the host is reversed, and the query
+/// parameters are sorted, so variants of the same URL share a key.
+pub fn surt(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let mut key = host.split('.').rev().collect::<Vec<_>>().join(",");
+    if let Some(port) = url.port() {
+        key.push_str(&format!(":{port}"));
+    }
+    key.push(')');
+    key.push_str(&url.path().to_lowercase());
+
+    if let Some(query) = url.query().filter(|query| !query.is_empty()) {
+        let mut parameters = query.split('&').collect::<Vec<_>>();
+        parameters.sort_unstable();
+        key.push('?');
+        key.push_str(&parameters.join("&").to_lowercase());
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::archive::warc::{HttpResponse, WarcWriter};
+
+    fn write_warc(writer: &mut WarcWriter<impl Write>) -> Vec<WrittenRecord> {
+        let headers = vec![("Content-Type".to_string(), "text/html".to_string())];
+        let mut records = vec![writer.write_warcinfo("crawl.warc", &[]).unwrap()];
+        for (path, body) in [("/b", "<p>B</p>"), ("/a?z=1&y=2", "<p>B</p>"), ("/c", "")] {
+            let url = Url::parse("https://www.Example.com/")
+                .unwrap()
+                .join(path)
+                .unwrap();
+            let response = HttpResponse {
+                url: &url,
+                date: Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap(),
+                status: 404,
+                headers: &headers,
+                body: body.as_bytes(),
+            };
+            records.push(writer.write_response(&response).unwrap());
+        }
+        records
+    }
+
+    #[test]
+    fn writes_sorted_lines_and_reads_them_back() {
+        let mut writer = WarcWriter::new(Vec::new());
+        writer.set_index("crawl.warc");
+        let records = write_warc(&mut writer);
+        let index = writer.index().unwrap().clone();
+        assert_eq!(index.len(), 3);
+
+        let mut output = Vec::new();
+        index.write(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let first = output.lines().next().unwrap();
+        assert_eq!(
+            first,
+            format!(
+                r#"com,example)/a?y=2&z=1 20260501080000 {{"digest":"{}","filename":"crawl.warc","length":"{}","mime":"warc/revisit","offset":"{}","status":"404","url":"https://www.example.com/a?z=1&y=2"}}"#,
+                records[2].payload_digest.as_ref().unwrap(),
+                records[2].length,
+                records[2].offset
+            )
+        );
+
+        let read = CdxjIndex::read(output.as_bytes()).unwrap();
+        assert_eq!(read.entries()[0], index.entries()[1]);
+        let url = Url::parse("https://example.com/b").unwrap();
+        assert_eq!(read.captures(&url)[0].mime, "text/html");
+    }
+
+    #[test]
+    fn indexes_existing_warc_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, gzip) in [("crawl.warc", false), ("crawl.warc.gz", true)] {
+            let path = dir.path().join(name);
+            let file = File::create(&path).unwrap();
+            let mut writer = if gzip {
+                WarcWriter::gzip(file)
+            } else {
+                WarcWriter::new(file)
+            };
+            writer.set_index(name);
+            write_warc(&mut writer);
+            writer.flush().unwrap();
+
+            let index = CdxjIndex::from_warc(&path).unwrap();
+            assert_eq!(&index, writer.index().unwrap());
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{io, str};
+
+use regex::Regex;
+use url::Url;
+
+use super::warc::{read_response, scan_records, ArchivedResponse, WarcRecordType};
+use crate::http_server::{HttpServer, Request, Response};
+use crate::record::PageRecord;
+use crate::store::{Store, StoreError};
+
+/// Headers about the original transfer, which the replayed response doesn't share.
+const TRANSFER_HEADERS: &[&str] = &[
+    "connection",
+    "content-encoding",
+    "content-length",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+/// Where an archived response is kept.
+#[derive(Debug, Clone)]
+enum Location {
+    /// A `response` record, or a `revisit` with the offset of the record holding its payload.
+    Warc {
+        path: PathBuf,
+        offset: u64,
+        payload: Option<u64>,
+    },
+    #[cfg(feature = "zstd")]
+    Segment { path: PathBuf, offset: u64 },
+    /// A page of one of the stores, by the URL it's stored under.
+    Store { store: usize, url: Url },
+}
+
+/// The responses of completed crawls, by URL, read from WARC files, segments and page stores.
+///
+/// Only where each response is kept is read up front, the responses themselves are read when
+/// they're asked for. A URL archived more than once answers with what was added last.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::archive::replay::Replay;
+///
+/// let mut replay = Replay::default();
+/// replay.add_warc("crawl.warc.gz").unwrap();
+/// let server = replay.serve("127.0.0.1:8080").unwrap();
+/// println!("browse the archive at http://{}/", server.address());
+/// ```
+#[derive(Default)]
+pub struct Replay {
+    locations: BTreeMap<Url, Location>,
+    stores: Vec<Box<dyn Store + Send>>,
+    /// The origin of the first page added, which paths of other origins are resolved against.
+    primary: Option<Url>,
+}
+
+impl Replay {
+    /// Adds the `response` and `revisit` records of a WARC file, uncompressed or gzipped.
+    pub fn add_warc(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let records = scan_records(File::open(path)?)?;
+        let mut offsets = HashMap::new();
+        for record in records {
+            let Some(url) = record.target_uri else {
+                continue;
+            };
+            let payload = match record.record_type {
+                WarcRecordType::Response => {
+                    offsets.insert(record.record_id, record.offset);
+                    None
+                }
+                // Revisits refer to an earlier record, of this file when it was written with
+                // this one.
+                WarcRecordType::Revisit => record
+                    .refers_to
+                    .and_then(|target| offsets.get(&target.record_id).copied()),
+                _ => continue,
+            };
+            self.insert(
+                url,
+                Location::Warc {
+                    path: path.to_path_buf(),
+                    offset: record.offset,
+                    payload,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Adds the pages of a segment, through its index.
+    #[cfg(feature = "zstd")]
+    pub fn add_segment(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        use super::segment::SegmentReader;
+
+        let path = path.as_ref();
+        let mut reader = SegmentReader::open(path)?;
+        for entry in SegmentReader::index(path)? {
+            let record = reader.read_at(entry.offset)?.record;
+            let location = Location::Segment {
+                path: path.to_path_buf(),
+                offset: entry.offset,
+            };
+            self.insert_page(&record, location);
+        }
+        Ok(())
+    }
+
+    /// Adds the pages of a store, which need to have been stored with their bodies.
+    pub fn add_store(&mut self, store: impl Store + Send + 'static) -> Result<(), StoreError> {
+        let index = self.stores.len();
+        for url in store.list()? {
+            if let Some(page) = store.get(&url)? {
+                let location = Location::Store {
+                    store: index,
+                    url: page.record.url.clone(),
+                };
+                self.insert_page(&page.record, location);
+            }
+        }
+        self.stores.push(Box::new(store));
+        Ok(())
+    }
+
+    /// The URLs archived, sorted.
+    pub fn urls(&self) -> impl Iterator<Item = &Url> {
+        self.locations.keys()
+    }
+
+    /// The archived response for a URL. A page stored with the redirects it went through
+    /// answers the URL it was requested with by redirecting to where it ended up.
+    pub fn get(&self, url: &Url) -> Result<Option<ArchivedResponse>, StoreError> {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        let Some(location) = self.locations.get(&url) else {
+            return Ok(None);
+        };
+        let response = match location {
+            Location::Warc {
+                path,
+                offset,
+                payload,
+            } => {
+                let mut response = read_response(File::open(path)?, *offset)?;
+                if let Some(payload) = payload {
+                    response.body = read_response(File::open(path)?, *payload)?.body;
+                }
+                response
+            }
+            #[cfg(feature = "zstd")]
+            Location::Segment { path, offset } => {
+                let page = super::segment::SegmentReader::open(path)?.read_at(*offset)?;
+                page_response(&url, page.record, page.body)
+            }
+            Location::Store { store, url: key } => match self.stores[*store].get(key)? {
+                Some(page) => page_response(&url, page.record, page.body),
+                // Deleted from the store since it was added.
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(response))
+    }
+
+    /// Answers requests for archived URLs over HTTP on a background thread, until the returned
+    /// server is dropped.
+    ///
+    /// A URL is requested with it as the path, like `/https://example.com/about`. Other paths
+    /// are resolved against the page that linked to them, through the `Referer`, or else the
+    /// origin of the first page added, so a site's root-relative links keep working. Links to
+    /// archived origins in HTML and CSS are rewritten to point at the server, and
+    /// `/_replay` lists every URL archived.
+    pub fn serve(self, address: impl ToSocketAddrs) -> io::Result<ReplayServer> {
+        let mut origins = self
+            .locations
+            .keys()
+            .map(|url| url.origin().ascii_serialization())
+            .collect::<Vec<_>>();
+        origins.sort_by_key(|origin| std::cmp::Reverse(origin.len()));
+        origins.dedup();
+        let origins = origins.iter().map(|origin| regex::escape(origin));
+        // Longer origins come first so one that extends another, with a port, wins.
+        // Unwrapping is safe here because the pattern only has escaped literals.
+        let origins = Regex::new(&origins.collect::<Vec<_>>().join("|")).unwrap();
+
+        // Connections are answered on threads of their own and stores needn't be shared
+        // between threads, so requests take turns.
+        let archive = Mutex::new(self);
+        let server = HttpServer::start(address, move |request| {
+            match request.method.as_str() {
+                "GET" | "HEAD" => {}
+                _ => return Response::empty(405),
+            }
+            let archive = archive.lock().unwrap_or_else(|error| error.into_inner());
+            if request.path == "/_replay" {
+                return archive.listing();
+            }
+            let Some(url) = archive.target(request) else {
+                return Response::new(404, "text/plain", "not an archived URL");
+            };
+            let mut response = match archive.get(&url) {
+                Ok(Some(archived)) => replayed(archived, &origins),
+                Ok(None) => Response::new(404, "text/plain", format!("{url} is not archived")),
+                Err(error) => Response::new(500, "text/plain", error.to_string()),
+            };
+            if request.method == "HEAD" {
+                response.body.clear();
+            }
+            response
+        })?;
+        Ok(ReplayServer { server })
+    }
+
+    fn insert(&mut self, mut url: Url, location: Location) {
+        url.set_fragment(None);
+        self.primary
+            .get_or_insert_with(|| url.join("/").unwrap_or_else(|_| url.clone()));
+        self.locations.insert(url, location);
+    }
+
+    fn insert_page(&mut self, record: &PageRecord, location: Location) {
+        self.insert(record.url.clone(), location.clone());
+        if record.final_url() != &record.url {
+            self.insert(record.final_url().clone(), location);
+        }
+    }
+
+    /// The archived URL a request is for.
+    fn target(&self, request: &Request) -> Option<Url> {
+        let mut path = request.path.clone();
+        if let Some(query) = &request.query {
+            path.push('?');
+            path.push_str(query);
+        }
+        if let Some(url) = archived_url(&path) {
+            return Some(url);
+        }
+        let referrer = request
+            .header("referer")
+            .and_then(|referer| Url::parse(referer).ok())
+            .and_then(|referer| archived_url(referer.path()));
+        referrer
+            .as_ref()
+            .or(self.primary.as_ref())?
+            .join(&path)
+            .ok()
+    }
+
+    fn listing(&self) -> Response {
+        let mut html = String::from("<!DOCTYPE html>\n<title>Archived URLs</title>\n<ul>\n");
+        for url in self.urls() {
+            let url = url.as_str().replace('&', "&amp;");
+            html.push_str(&format!("<li><a href=\"/{url}\">{url}</a></li>\n"));
+        }
+        html.push_str("</ul>\n");
+        Response::new(200, "text/html; charset=utf-8", html)
+    }
+}
+
+/// The URL in a path like `/https://example.com/about`, `None` for other paths.
+fn archived_url(path: &str) -> Option<Url> {
+    let url = path.strip_prefix('/')?;
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+    Url::parse(url).ok()
+}
+
+/// The response for a page requested with `url`, a redirect to the final URL when the page
+/// was requested with that URL and redirected.
+fn page_response(url: &Url, record: PageRecord, body: Option<Vec<u8>>) -> ArchivedResponse {
+    match record.redirects.first() {
+        Some(redirect) if *url == record.url => ArchivedResponse {
+            status: redirect.status,
+            headers: vec![("Location".to_string(), record.final_url().to_string())],
+            body: Vec::new(),
+        },
+        _ => ArchivedResponse {
+            status: record.status,
+            headers: record.headers,
+            body: body.unwrap_or_default(),
+        },
+    }
+}
+
+/// An archived response as it's replayed, with the links to archived origins in its body and
+/// `Location` pointing at the server.
+fn replayed(archived: ArchivedResponse, origins: &Regex) -> Response {
+    let prefixed = |text: &str| origins.replace_all(text, "/$0").into_owned();
+    let mut headers = archived
+        .headers
+        .into_iter()
+        .filter(|(name, _)| !TRANSFER_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+        .collect::<Vec<_>>();
+    let mut rewrite = false;
+    for (name, value) in &mut headers {
+        if name.eq_ignore_ascii_case("location")
+            && origins.find(value).is_some_and(|m| m.start() == 0)
+        {
+            *value = prefixed(value);
+        }
+        if name.eq_ignore_ascii_case("content-type") {
+            let value = value.to_ascii_lowercase();
+            rewrite = value.contains("html") || value.starts_with("text/css");
+        }
+    }
+    let body = match str::from_utf8(&archived.body) {
+        Ok(text) if rewrite => prefixed(text).into_bytes(),
+        _ => archived.body,
+    };
+    Response {
+        status: archived.status,
+        headers,
+        body,
+    }
+}
+
+/// The HTTP server started by [`Replay::serve`], stopped when dropped.
+pub struct ReplayServer {
+    server: HttpServer,
+}
+
+impl ReplayServer {
+    pub fn address(&self) -> SocketAddr {
+        self.server.address()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::archive::warc::{HttpResponse, WarcWriter};
+    use crate::record::Redirect;
+    use crate::store::fs::FsStore;
+
+    fn request(server: &ReplayServer, request: &str) -> String {
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn replays_warc_responses_with_links_to_the_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crawl.warc.gz");
+        let mut writer = WarcWriter::gzip(File::create(&path).unwrap());
+        let html = vec![("Content-Type".to_string(), "text/html".to_string())];
+        let mut write = |url: &str, body: &str| {
+            let url = Url::parse(url).unwrap();
+            writer
+                .write_response(&HttpResponse {
+                    url: &url,
+                    date: Utc::now(),
+                    status: 200,
+                    headers: &html,
+                    body: body.as_bytes(),
+                })
+                .unwrap();
+        };
+        write(
+            "https://example.com/",
+            r#"<a href="https://example.com/about">About</a> <a href="/copy">Copy</a>"#,
+        );
+        write("https://example.com/about", "<p>About</p>");
+        write("https://example.com/copy", "<p>About</p>");
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut replay = Replay::default();
+        replay.add_warc(&path).unwrap();
+        let server = replay.serve("127.0.0.1:0").unwrap();
+
+        let home = request(&server, "GET / HTTP/1.1\r\n\r\n");
+        assert!(home.starts_with("HTTP/1.1 200 OK\r\n"), "{home}");
+        assert!(home.ends_with(
+            r#"<a href="/https://example.com/about">About</a> <a href="/copy">Copy</a>"#
+        ));
+        // A revisit record, whose payload is in the record of /about.
+        let copy = request(
+            &server,
+            "GET /copy HTTP/1.1\r\nReferer: http://localhost/https://example.com/\r\n\r\n",
+        );
+        assert!(copy.ends_with("\r\n\r\n<p>About</p>"), "{copy}");
+        let missing = request(&server, "GET /https://example.com/missing HTTP/1.1\r\n\r\n");
+        assert!(missing.starts_with("HTTP/1.1 404"), "{missing}");
+    }
+
+    #[test]
+    fn redirects_stored_pages_to_where_they_ended_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = FsStore::open(dir.path()).unwrap();
+        let url = |path: &str| {
+            Url::parse("https://example.com")
+                .unwrap()
+                .join(path)
+                .unwrap()
+        };
+        let mut page = PageRecord::new(url("/docs"), 200, Utc::now());
+        page.redirects = vec![Redirect {
+            from: url("/docs"),
+            to: url("/docs/"),
+            status: 301,
+        }];
+        store.put(&page, Some(b"Docs")).unwrap();
+
+        let mut replay = Replay::default();
+        replay.add_store(store).unwrap();
+        let redirect = replay.get(&url("/docs")).unwrap().unwrap();
+        assert_eq!(redirect.status, 301);
+        assert_eq!(
+            redirect.headers,
+            [("Location".to_string(), url("/docs/").to_string())]
+        );
+        assert_eq!(replay.get(&url("/docs/")).unwrap().unwrap().body, b"Docs");
+    }
+}
@@ -0,0 +1,365 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::export::Sink;
+use crate::record::PageRecord;
+
+/// The bytes every segment file starts with.
+pub const MAGIC: &[u8; 8] = b"KIRBYSG1";
+
+const HAS_BODY: u8 = 1;
+
+/// A page read back from a segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentRecord {
+    pub record: PageRecord,
+    pub body: Option<Vec<u8>>,
+}
+
+/// A line of a segment's index sidecar: where a page's record is in the segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentIndexEntry {
+    /// The offset of the record's length prefix.
+    pub offset: u64,
+    /// The length of the record including its length prefix.
+    pub length: u64,
+    pub url: Url,
+}
+
+impl SegmentIndexEntry {
+    /// Reads a segment's index sidecar.
+    pub fn read_all(reader: impl BufRead) -> io::Result<Vec<Self>> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid segment index line: {line}"),
+                )
+            };
+            let mut parts = line.splitn(3, ' ');
+            let (Some(offset), Some(length), Some(url)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid());
+            };
+            entries.push(Self {
+                offset: offset.parse().map_err(|_| invalid())?,
+                length: length.parse().map_err(|_| invalid())?,
+                url: Url::parse(url).map_err(|_| invalid())?,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Writes pages to append-only segment files, a compact alternative to WARC for crawls that
+/// don't need to be replayed by other tools.
+///
+/// A segment starts with [`MAGIC`], followed by one record per page: a little-endian `u32`
+/// length and a zstd frame holding a flags byte, the `u32` length of the JSON
+/// [`PageRecord`], the JSON and the body. Segments are named `<prefix>-00000.seg`,
+/// `<prefix>-00001.seg` and so on; a new one is started once the current one reaches
+/// `max_bytes` or is older than `max_age`. Each segment has an index sidecar, `.seg.idx`,
+/// with an `<offset> <length> <url>` line per record, for [`SegmentReader::read_at`].
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::archive::segment::{SegmentReader, SegmentWriter};
+/// use kirby_core::export::Sink;
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let mut writer = SegmentWriter::new(dir.path(), "crawl");
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// writer.write(&record, Some(b"<p>Hi</p>")).unwrap();
+/// writer.finish().unwrap();
+///
+/// let mut reader = SegmentReader::open(&writer.files()[0]).unwrap();
+/// let page = reader.next().unwrap().unwrap();
+/// assert_eq!(page.record, record);
+/// assert_eq!(page.body.as_deref(), Some(&b"<p>Hi</p>"[..]));
+/// ```
+pub struct SegmentWriter {
+    directory: PathBuf,
+    prefix: String,
+    level: i32,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    include_bodies: bool,
+    current: Option<OpenSegment>,
+    files: Vec<PathBuf>,
+}
+
+struct OpenSegment {
+    file: BufWriter<File>,
+    index: BufWriter<File>,
+    offset: u64,
+    opened: Instant,
+}
+
+impl OpenSegment {
+    fn finish(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.index.flush()
+    }
+}
+
+impl SegmentWriter {
+    /// Writes segments into `directory` with bodies, without rotating.
+    pub fn new(directory: impl AsRef<Path>, prefix: impl Into<String>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            prefix: prefix.into(),
+            level: 3,
+            max_bytes: None,
+            max_age: None,
+            include_bodies: true,
+            current: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// The zstd compression level, 3 by default.
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Starts a new segment once the current one holds this many compressed bytes.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Starts a new segment once the current one has been open this long.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn include_bodies(mut self, include_bodies: bool) -> Self {
+        self.include_bodies = include_bodies;
+        self
+    }
+
+    /// The segments written so far, in order.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The open segment, starting a new one when there is none or the current one is full
+    /// or too old.
+    fn segment(&mut self) -> io::Result<&mut OpenSegment> {
+        let rotate = self.current.as_ref().is_some_and(|segment| {
+            self.max_bytes
+                .is_some_and(|max_bytes| segment.offset >= max_bytes)
+                || self
+                    .max_age
+                    .is_some_and(|max_age| segment.opened.elapsed() >= max_age)
+        });
+        if rotate {
+            if let Some(segment) = self.current.take() {
+                segment.finish()?;
+            }
+        }
+
+        if self.current.is_none() {
+            let name = format!("{}-{:05}.seg", self.prefix, self.files.len());
+            let path = self.directory.join(name);
+            let mut file = BufWriter::new(File::create(&path)?);
+            file.write_all(MAGIC)?;
+            let index = BufWriter::new(File::create(index_path(&path))?);
+            self.current = Some(OpenSegment {
+                file,
+                index,
+                offset: MAGIC.len() as u64,
+                opened: Instant::now(),
+            });
+            self.files.push(path);
+        }
+
+        // Unwrapping is safe here because a segment was opened above.
+        Ok(self.current.as_mut().unwrap())
+    }
+}
+
+impl Sink for SegmentWriter {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let json = serde_json::to_vec(record)?;
+        let body = body.filter(|_| self.include_bodies);
+
+        let mut payload = Vec::with_capacity(5 + json.len() + body.map_or(0, <[u8]>::len));
+        payload.push(if body.is_some() { HAS_BODY } else { 0 });
+        payload.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&json);
+        payload.extend_from_slice(body.unwrap_or_default());
+        let compressed = zstd::encode_all(payload.as_slice(), self.level)?;
+        let compressed_len = u32::try_from(compressed.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "record too large"))?;
+
+        let segment = self.segment()?;
+        segment.file.write_all(&compressed_len.to_le_bytes())?;
+        segment.file.write_all(&compressed)?;
+        let length = 4 + compressed.len() as u64;
+        writeln!(segment.index, "{} {length} {}", segment.offset, record.url)?;
+        segment.offset += length;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(segment) => segment.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads the records of a segment in order, see [`SegmentWriter`].
+///
+/// A record cut short at the end of the segment, e.g. by a crash, ends the iteration with
+/// an [`ErrorKind::UnexpectedEof`] error.
+pub struct SegmentReader {
+    reader: BufReader<File>,
+}
+
+impl SegmentReader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a segment file"));
+        }
+        Ok(Self { reader })
+    }
+
+    /// Reads a segment's index sidecar.
+    pub fn index(path: impl AsRef<Path>) -> io::Result<Vec<SegmentIndexEntry>> {
+        SegmentIndexEntry::read_all(BufReader::new(File::open(index_path(path.as_ref()))?))
+    }
+
+    /// Reads the record at an offset from the index. Iteration continues after it.
+    pub fn read_at(&mut self, offset: u64) -> io::Result<SegmentRecord> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.read_record()?
+            .ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))
+    }
+
+    /// The next record, `None` at the end of the segment.
+    fn read_record(&mut self) -> io::Result<Option<SegmentRecord>> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut length = [0; 4];
+        self.reader.read_exact(&mut length)?;
+        let mut compressed = vec![0; u32::from_le_bytes(length) as usize];
+        self.reader.read_exact(&mut compressed)?;
+
+        let payload = zstd::decode_all(compressed.as_slice())?;
+        let invalid = || io::Error::new(ErrorKind::InvalidData, "invalid segment record");
+        let (&flags, rest) = payload.split_first().ok_or_else(invalid)?;
+        let (json_len, rest) = rest.split_first_chunk::<4>().ok_or_else(invalid)?;
+        let json_len = u32::from_le_bytes(*json_len) as usize;
+        if rest.len() < json_len {
+            return Err(invalid());
+        }
+        let (json, body) = rest.split_at(json_len);
+
+        Ok(Some(SegmentRecord {
+            record: serde_json::from_slice(json)?,
+            body: (flags & HAS_BODY != 0).then(|| body.to_vec()),
+        }))
+    }
+}
+
+impl Iterator for SegmentReader {
+    type Item = io::Result<SegmentRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+fn index_path(segment: &Path) -> PathBuf {
+    let mut path = segment.as_os_str().to_owned();
+    path.push(".idx");
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record(path: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        PageRecord::new(url, 200, date)
+    }
+
+    #[test]
+    fn rotates_and_seeks_through_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = SegmentWriter::new(dir.path(), "crawl").max_bytes(300);
+        for path in ["/a", "/b", "/c"] {
+            writer.write(&record(path), Some(path.as_bytes())).unwrap();
+        }
+        writer.write(&record("/d"), None).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(writer.files().len(), 2);
+        assert_eq!(writer.files()[1], dir.path().join("crawl-00001.seg"));
+
+        let index = SegmentReader::index(&writer.files()[0]).unwrap();
+        assert_eq!(index[0].offset, MAGIC.len() as u64);
+        assert_eq!(index[1].offset, index[0].offset + index[0].length);
+        let mut reader = SegmentReader::open(&writer.files()[0]).unwrap();
+        let b = reader.read_at(index[1].offset).unwrap();
+        assert_eq!(b.record.url, index[1].url);
+        assert_eq!(b.body.as_deref(), Some(&b"/b"[..]));
+
+        let last = SegmentReader::open(&writer.files()[1])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(last.last().unwrap().record, record("/d"));
+        assert_eq!(last.last().unwrap().body, None);
+    }
+
+    #[test]
+    fn reports_truncated_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = SegmentWriter::new(dir.path(), "crawl").max_age(Duration::ZERO);
+        writer.write(&record("/a"), None).unwrap();
+        writer.write(&record("/b"), None).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(writer.files().len(), 2);
+
+        let path = &writer.files()[0];
+        let bytes = fs::read(path).unwrap();
+        fs::write(path, &bytes[..bytes.len() - 1]).unwrap();
+        let error = SegmentReader::open(path)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
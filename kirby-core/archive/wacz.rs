@@ -0,0 +1,350 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use data_encoding::HEXLOWER;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::cdxj::CdxjIndex;
+use super::warc::WrittenRecord;
+
+/// The version of the WACZ specification the packages follow.
+pub const WACZ_VERSION: &str = "1.1.1";
+
+/// Signs the digest of a package's `datapackage.json`.
+///
+/// Signing is left to the caller, e.g. an authsign server or a key in an HSM, the returned
+/// value is stored as `signedData` in `datapackage-digest.json`.
+pub trait WaczSigner {
+    /// Signs `hash`, `sha256:` followed by hex, of a package created at `created`.
+    fn sign(&self, hash: &str, created: &str) -> io::Result<Value>;
+}
+
+/// A page listed in `pages/pages.jsonl`, which replay tools offer as entry points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaczPage {
+    pub url: Url,
+    pub timestamp: DateTime<Utc>,
+    pub title: Option<String>,
+}
+
+/// Packages WARC files as a WACZ file, which replayweb.page and similar viewers load directly.
+///
+/// The package holds the WARC files under `archive/`, a CDXJ index of their response and
+/// revisit records under `indexes/index.cdx`, the pages under `pages/pages.jsonl` and the
+/// `datapackage.json` manifest with the digest of every file. `datapackage-digest.json` holds
+/// the manifest's digest and, with a [`WaczSigner`], its signature.
+///
+/// The index is built from the records the [`WarcWriter`](super::warc::WarcWriter) returned,
+/// or by reading the WARC files, see [`add_warc_file`](Self::add_warc_file).
+///
+/// # Example
+///
+/// ```
+/// use std::fs::File;
+///
+/// use chrono::Utc;
+/// use kirby_core::archive::wacz::WaczWriter;
+/// use kirby_core::archive::warc::{HttpResponse, WarcWriter};
+/// use url::Url;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let warc = dir.path().join("crawl.warc.gz");
+/// let url = Url::parse("https://example.com/").unwrap();
+///
+/// let mut writer = WarcWriter::gzip(File::create(&warc).unwrap());
+/// let record = writer
+///     .write_response(&HttpResponse {
+///         url: &url,
+///         date: Utc::now(),
+///         status: 200,
+///         headers: &[],
+///         body: b"<title>Example</title>",
+///     })
+///     .unwrap();
+/// writer.flush().unwrap();
+///
+/// let mut wacz = WaczWriter::new("Example crawl");
+/// wacz.add_warc(&warc, &[record]).unwrap();
+/// wacz.add_page(url, Utc::now(), Some("Example".to_string()));
+/// wacz.write(dir.path().join("crawl.wacz")).unwrap();
+/// ```
+pub struct WaczWriter {
+    title: String,
+    description: Option<String>,
+    warcs: Vec<(String, PathBuf)>,
+    index: CdxjIndex,
+    pages: Vec<WaczPage>,
+    signer: Option<Box<dyn WaczSigner>>,
+}
+
+impl WaczWriter {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+            warcs: Vec::new(),
+            index: CdxjIndex::new(),
+            pages: Vec::new(),
+            signer: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn signer(mut self, signer: impl WaczSigner + 'static) -> Self {
+        self.signer = Some(Box::new(signer));
+        self
+    }
+
+    /// Adds a WARC file with the records written to it, which are indexed.
+    pub fn add_warc(
+        &mut self,
+        path: impl AsRef<Path>,
+        records: &[WrittenRecord],
+    ) -> io::Result<()> {
+        let name = self.warc_name(path.as_ref())?;
+        for record in records {
+            self.index.add(record, &name);
+        }
+        self.warcs.push((name, path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    /// Adds an existing WARC file, which is read to index it.
+    pub fn add_warc_file(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let name = self.warc_name(path.as_ref())?;
+        self.index.merge(CdxjIndex::from_warc(path.as_ref())?);
+        self.warcs.push((name, path.as_ref().to_path_buf()));
+        Ok(())
+    }
+
+    /// The name of a WARC file in the package, which must be unique.
+    fn warc_name(&self, path: &Path) -> io::Result<String> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| io::Error::other(format!("not a WARC file name: {}", path.display())))?;
+        if self.warcs.iter().any(|(existing, _)| existing == name) {
+            return Err(io::Error::other(format!(
+                "a WARC file named {name} was already added"
+            )));
+        }
+        Ok(name.to_string())
+    }
+
+    pub fn add_page(&mut self, url: Url, timestamp: DateTime<Utc>, title: Option<String>) {
+        self.pages.push(WaczPage {
+            url,
+            timestamp,
+            title,
+        });
+    }
+
+    /// Writes the package, replacing the file only once it's complete.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let temporary = path.with_extension("wacz.tmp");
+        let mut zip = ZipWriter::new(File::create(&temporary)?);
+        let mut resources = Vec::new();
+
+        // WARC files are stored uncompressed, so viewers can read records by range.
+        for (name, warc) in &self.warcs {
+            let entry = format!("archive/{name}");
+            let file = File::open(warc)?;
+            let large = file.metadata()?.len() >= u64::from(u32::MAX);
+            zip.start_file(entry.as_str(), options().large_file(large))?;
+            resources.push(resource(&entry, file, &mut zip)?);
+        }
+
+        let mut index = Vec::new();
+        self.index.write(&mut index)?;
+        resources.push(add_file(&mut zip, "indexes/index.cdx", &index)?);
+        resources.push(add_file(
+            &mut zip,
+            "pages/pages.jsonl",
+            &self.pages_jsonl()?,
+        )?);
+
+        let created = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut package = json!({
+            "profile": "data-package",
+            "wacz_version": WACZ_VERSION,
+            "title": self.title,
+            "created": created,
+            "software": concat!("kirby ", env!("CARGO_PKG_VERSION")),
+            "resources": resources,
+        });
+        if let Some(description) = &self.description {
+            package["description"] = description.as_str().into();
+        }
+        let package = serde_json::to_vec_pretty(&package)?;
+        add_file(&mut zip, "datapackage.json", &package)?;
+
+        let hash = format!("sha256:{}", HEXLOWER.encode(&Sha256::digest(&package)));
+        let mut digest = json!({"path": "datapackage.json", "hash": hash});
+        if let Some(signer) = &self.signer {
+            digest["signedData"] = signer.sign(&hash, &created)?;
+        }
+        let digest = serde_json::to_vec_pretty(&digest)?;
+        add_file(&mut zip, "datapackage-digest.json", &digest)?;
+
+        zip.finish()?.sync_all()?;
+        fs::rename(&temporary, path)
+    }
+
+    fn pages_jsonl(&self) -> io::Result<Vec<u8>> {
+        let mut jsonl = Vec::new();
+        let header = json!({"format": "json-pages-1.0", "id": "pages", "title": "All Pages"});
+        serde_json::to_writer(&mut jsonl, &header)?;
+        jsonl.push(b'\n');
+
+        for page in &self.pages {
+            let mut entry = json!({
+                "url": page.url,
+                "ts": page.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            });
+            if let Some(title) = &page.title {
+                entry["title"] = title.as_str().into();
+            }
+            serde_json::to_writer(&mut jsonl, &entry)?;
+            jsonl.push(b'\n');
+        }
+        Ok(jsonl)
+    }
+}
+
+fn add_file(zip: &mut ZipWriter<File>, name: &str, contents: &[u8]) -> io::Result<Value> {
+    zip.start_file(name, options())?;
+    resource(name, contents, zip)
+}
+
+fn options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Stored)
+}
+
+/// Copies a file into the package, returning its `datapackage.json` resource entry.
+fn resource(path: &str, mut reader: impl Read, writer: &mut impl Write) -> io::Result<Value> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut bytes = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+        bytes += read as u64;
+    }
+
+    let name = path.rsplit('/').next().unwrap_or(path);
+    Ok(json!({
+        "name": name,
+        "path": path,
+        "hash": format!("sha256:{}", HEXLOWER.encode(&hasher.finalize())),
+        "bytes": bytes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use chrono::TimeZone;
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::archive::warc::{HttpResponse, WarcWriter};
+
+    struct FixedSigner;
+
+    impl WaczSigner for FixedSigner {
+        fn sign(&self, hash: &str, created: &str) -> io::Result<Value> {
+            Ok(json!({"hash": hash, "created": created, "signature": "c2lnbmVk"}))
+        }
+    }
+
+    fn read(archive: &mut ZipArchive<File>, name: &str) -> String {
+        let mut contents = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn packages_warcs_with_index_pages_and_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let warc = dir.path().join("crawl.warc.gz");
+        let date = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let headers = vec![("Content-Type".to_string(), "text/html".to_string())];
+
+        let mut writer = WarcWriter::gzip(File::create(&warc).unwrap());
+        let mut records = vec![writer.write_warcinfo("crawl.warc.gz", &[]).unwrap()];
+        for path in ["/b", "/a?z=1&y=2"] {
+            let url = Url::parse("https://www.Example.com/")
+                .unwrap()
+                .join(path)
+                .unwrap();
+            let response = HttpResponse {
+                url: &url,
+                date,
+                status: 200,
+                headers: &headers,
+                body: b"<p>Same</p>",
+            };
+            records.push(writer.write_response(&response).unwrap());
+        }
+        writer.flush().unwrap();
+
+        let mut wacz = WaczWriter::new("Test crawl").signer(FixedSigner);
+        wacz.add_warc(&warc, &records).unwrap();
+        wacz.add_page(Url::parse("https://www.example.com/b").unwrap(), date, None);
+        assert!(wacz.add_warc(&warc, &[]).is_err());
+        let path = dir.path().join("crawl.wacz");
+        wacz.write(&path).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let stored = archive
+            .by_name("archive/crawl.warc.gz")
+            .unwrap()
+            .compression();
+        assert_eq!(stored, CompressionMethod::Stored);
+
+        let index = read(&mut archive, "indexes/index.cdx");
+        let lines = index.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("com,example)/a?y=2&z=1 20260501080000 {"));
+        let revisit: Value = serde_json::from_str(lines[0].splitn(3, ' ').nth(2).unwrap()).unwrap();
+        assert_eq!(revisit["mime"], "warc/revisit");
+        assert_eq!(revisit["offset"], records[2].offset.to_string());
+        assert_eq!(revisit["filename"], "crawl.warc.gz");
+
+        let pages = read(&mut archive, "pages/pages.jsonl");
+        assert_eq!(
+            pages.lines().nth(1).unwrap(),
+            r#"{"ts":"2026-05-01T08:00:00.000Z","url":"https://www.example.com/b"}"#
+        );
+
+        let package: Value = serde_json::from_str(&read(&mut archive, "datapackage.json")).unwrap();
+        assert_eq!(package["resources"][0]["path"], "archive/crawl.warc.gz");
+        assert_eq!(
+            package["resources"][0]["bytes"],
+            fs::metadata(&warc).unwrap().len()
+        );
+        let digest: Value =
+            serde_json::from_str(&read(&mut archive, "datapackage-digest.json")).unwrap();
+        assert_eq!(digest["signedData"]["hash"], digest["hash"]);
+    }
+}
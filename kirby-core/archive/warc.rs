@@ -0,0 +1,764 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::cdxj::CdxjIndex;
+
+/// The profile of revisit records whose payload is identical to an earlier record.
+pub const IDENTICAL_PAYLOAD_PROFILE: &str =
+    "http://netpreserve.org/warc/1.1/revisit/identical-payload-digest";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WarcRecordType {
+    Warcinfo,
+    Response,
+    Request,
+    Resource,
+    Metadata,
+    Revisit,
+}
+
+impl WarcRecordType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warcinfo => "warcinfo",
+            Self::Response => "response",
+            Self::Request => "request",
+            Self::Resource => "resource",
+            Self::Metadata => "metadata",
+            Self::Revisit => "revisit",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "warcinfo" => Self::Warcinfo,
+            "response" => Self::Response,
+            "request" => Self::Request,
+            "resource" => Self::Resource,
+            "metadata" => Self::Metadata,
+            "revisit" => Self::Revisit,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for WarcRecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A fetched HTTP response to archive.
+#[derive(Debug, Clone, Copy)]
+pub struct HttpResponse<'a> {
+    pub url: &'a Url,
+    pub date: DateTime<Utc>,
+    pub status: u16,
+    /// The response headers as received, in order.
+    pub headers: &'a [(String, String)],
+    /// The response body, after any transfer encoding was removed.
+    pub body: &'a [u8],
+}
+
+/// The record a revisit refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RevisitTarget {
+    pub record_id: String,
+    pub target_uri: Url,
+    pub date: DateTime<Utc>,
+}
+
+/// Where and what a record was written, e.g. for building an index of the archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WrittenRecord {
+    pub record_id: String,
+    pub record_type: WarcRecordType,
+    pub target_uri: Option<Url>,
+    pub date: DateTime<Utc>,
+    /// The offset of the record in the output, compressed when the writer compresses.
+    pub offset: u64,
+    /// The length of the record in the output, compressed when the writer compresses.
+    pub length: u64,
+    pub status: Option<u16>,
+    pub mime_type: Option<String>,
+    pub payload_digest: Option<String>,
+    /// The record an identical payload was already archived in, for revisit records.
+    pub refers_to: Option<RevisitTarget>,
+}
+
+/// Writes WARC 1.1 records, optionally with every record in its own gzip member (`.warc.gz`).
+///
+/// When a response's payload digest matches an earlier response the writer emits a `revisit`
+/// record referencing it instead of storing the body again. Digests from earlier crawls can be
+/// registered with [`WarcWriter::remember`] so recurring crawls only store what changed.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::archive::warc::{HttpResponse, WarcRecordType, WarcWriter};
+/// use url::Url;
+///
+/// let mut writer = WarcWriter::new(Vec::new());
+/// let headers = vec![("Content-Type".to_string(), "text/html".to_string())];
+/// let response = |url| HttpResponse {
+///     url,
+///     date: Utc::now(),
+///     status: 200,
+///     headers: &headers,
+///     body: b"<p>Hello</p>",
+/// };
+///
+/// let first = Url::parse("https://example.com/").unwrap();
+/// let second = Url::parse("https://example.com/index.html").unwrap();
+/// let original = writer.write_response(&response(&first)).unwrap();
+/// let revisit = writer.write_response(&response(&second)).unwrap();
+///
+/// assert_eq!(original.record_type, WarcRecordType::Response);
+/// assert_eq!(revisit.record_type, WarcRecordType::Revisit);
+/// assert_eq!(revisit.refers_to.unwrap().record_id, original.record_id);
+/// ```
+pub struct WarcWriter<W: Write> {
+    inner: W,
+    gzip: bool,
+    offset: u64,
+    revisits: bool,
+    /// Mapping of payload digest -> the response record holding that payload.
+    payloads: HashMap<String, RevisitTarget>,
+    /// The name of the output and the index of the records written to it.
+    index: Option<(String, CdxjIndex)>,
+}
+
+impl<W: Write> WarcWriter<W> {
+    /// Writes uncompressed records.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            gzip: false,
+            offset: 0,
+            revisits: true,
+            payloads: HashMap::new(),
+            index: None,
+        }
+    }
+
+    /// Writes every record as its own gzip member, the usual `.warc.gz` layout that lets readers
+    /// seek to any record.
+    pub fn gzip(inner: W) -> Self {
+        Self {
+            gzip: true,
+            ..Self::new(inner)
+        }
+    }
+
+    /// Whether responses with an already archived payload are written as revisit records,
+    /// enabled by default.
+    pub fn set_revisits(&mut self, enabled: bool) {
+        self.revisits = enabled;
+    }
+
+    /// Registers a payload archived elsewhere, e.g. in the WARC files of a previous crawl.
+    pub fn remember(&mut self, payload_digest: String, target: RevisitTarget) {
+        self.payloads.entry(payload_digest).or_insert(target);
+    }
+
+    /// Indexes the response and revisit records written from now on, as stored in a WARC file
+    /// named `filename`.
+    pub fn set_index(&mut self, filename: impl Into<String>) {
+        self.index = Some((filename.into(), CdxjIndex::new()));
+    }
+
+    /// The index of the records written since [`set_index`](Self::set_index).
+    pub fn index(&self) -> Option<&CdxjIndex> {
+        self.index.as_ref().map(|(_, index)| index)
+    }
+
+    /// Takes the index, leaving an empty one to index the records written from now on.
+    pub fn take_index(&mut self) -> Option<CdxjIndex> {
+        self.index.as_mut().map(|(_, index)| std::mem::take(index))
+    }
+
+    /// The number of bytes written so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes a `warcinfo` record describing the archive, fields are written as `name: value`
+    /// lines.
+    pub fn write_warcinfo(
+        &mut self,
+        filename: &str,
+        fields: &[(&str, &str)],
+    ) -> io::Result<WrittenRecord> {
+        let block = fields
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect::<String>();
+        self.write_record(
+            RecordHeader {
+                record_type: WarcRecordType::Warcinfo,
+                target_uri: None,
+                date: Utc::now(),
+                content_type: "application/warc-fields",
+                extra: vec![("WARC-Filename", filename.to_string())],
+            },
+            block.as_bytes(),
+            None,
+        )
+    }
+
+    /// Writes a `response` record, or a `revisit` record when the payload was archived before.
+    pub fn write_response(&mut self, response: &HttpResponse) -> io::Result<WrittenRecord> {
+        let payload_digest = payload_digest(response.body);
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\n",
+            response.status,
+            reason_phrase(response.status)
+        );
+        for (name, value) in response.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+
+        let mime_type = response
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| {
+                value
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            });
+
+        let previous = self
+            .payloads
+            .get(&payload_digest)
+            .filter(|_| self.revisits && !response.body.is_empty())
+            .cloned();
+
+        let mut written = if let Some(target) = &previous {
+            // Only the HTTP headers are kept, the payload lives in the referenced record.
+            self.write_record(
+                RecordHeader {
+                    record_type: WarcRecordType::Revisit,
+                    target_uri: Some(response.url),
+                    date: response.date,
+                    content_type: "application/http;msgtype=response",
+                    extra: vec![
+                        ("WARC-Profile", IDENTICAL_PAYLOAD_PROFILE.to_string()),
+                        ("WARC-Refers-To", target.record_id.clone()),
+                        ("WARC-Refers-To-Target-URI", target.target_uri.to_string()),
+                        ("WARC-Refers-To-Date", format_date(target.date)),
+                        ("WARC-Payload-Digest", payload_digest.clone()),
+                    ],
+                },
+                head.as_bytes(),
+                Some(target.clone()),
+            )?
+        } else {
+            let mut block = head.into_bytes();
+            block.extend_from_slice(response.body);
+            self.write_record(
+                RecordHeader {
+                    record_type: WarcRecordType::Response,
+                    target_uri: Some(response.url),
+                    date: response.date,
+                    content_type: "application/http;msgtype=response",
+                    extra: vec![("WARC-Payload-Digest", payload_digest.clone())],
+                },
+                &block,
+                None,
+            )?
+        };
+
+        if previous.is_none() && !response.body.is_empty() {
+            self.payloads.insert(
+                payload_digest.clone(),
+                RevisitTarget {
+                    record_id: written.record_id.clone(),
+                    target_uri: response.url.clone(),
+                    date: response.date,
+                },
+            );
+        }
+
+        written.status = Some(response.status);
+        written.mime_type = mime_type;
+        written.payload_digest = Some(payload_digest);
+        if let Some((filename, index)) = &mut self.index {
+            index.add(&written, filename);
+        }
+        Ok(written)
+    }
+
+    /// Writes a `metadata` record about an earlier record, e.g. extracted links or outcomes.
+    pub fn write_metadata(
+        &mut self,
+        target_uri: &Url,
+        concurrent_to: &str,
+        fields: &[(&str, &str)],
+    ) -> io::Result<WrittenRecord> {
+        let block = fields
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect::<String>();
+        self.write_record(
+            RecordHeader {
+                record_type: WarcRecordType::Metadata,
+                target_uri: Some(target_uri),
+                date: Utc::now(),
+                content_type: "application/warc-fields",
+                extra: vec![("WARC-Concurrent-To", concurrent_to.to_string())],
+            },
+            block.as_bytes(),
+            None,
+        )
+    }
+
+    fn write_record(
+        &mut self,
+        header: RecordHeader,
+        block: &[u8],
+        refers_to: Option<RevisitTarget>,
+    ) -> io::Result<WrittenRecord> {
+        let record_id = format!("<urn:uuid:{}>", uuid::Uuid::new_v4());
+
+        let mut record = format!(
+            "WARC/1.1\r\nWARC-Type: {}\r\nWARC-Record-ID: {record_id}\r\nWARC-Date: {}\r\n",
+            header.record_type,
+            format_date(header.date)
+        );
+        if let Some(uri) = header.target_uri {
+            record.push_str(&format!("WARC-Target-URI: {uri}\r\n"));
+        }
+        for (name, value) in &header.extra {
+            record.push_str(&format!("{name}: {value}\r\n"));
+        }
+        record.push_str(&format!(
+            "Content-Type: {}\r\nWARC-Block-Digest: {}\r\nContent-Length: {}\r\n\r\n",
+            header.content_type,
+            payload_digest(block),
+            block.len()
+        ));
+
+        let mut bytes = record.into_bytes();
+        bytes.extend_from_slice(block);
+        bytes.extend_from_slice(b"\r\n\r\n");
+
+        if self.gzip {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&bytes)?;
+            bytes = encoder.finish()?;
+        }
+        self.inner.write_all(&bytes)?;
+
+        let offset = self.offset;
+        self.offset += bytes.len() as u64;
+        Ok(WrittenRecord {
+            record_id,
+            record_type: header.record_type,
+            target_uri: header.target_uri.cloned(),
+            date: header.date,
+            offset,
+            length: bytes.len() as u64,
+            status: None,
+            mime_type: None,
+            payload_digest: None,
+            refers_to,
+        })
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct RecordHeader<'a> {
+    record_type: WarcRecordType,
+    target_uri: Option<&'a Url>,
+    date: DateTime<Utc>,
+    content_type: &'static str,
+    extra: Vec<(&'static str, String)>,
+}
+
+/// Reads the records of an existing WARC file, uncompressed or with every record in its own
+/// gzip member, as the writer would have returned them.
+pub fn scan_records(reader: impl Read) -> io::Result<Vec<WrittenRecord>> {
+    let mut reader = Counting {
+        inner: BufReader::new(reader),
+        position: 0,
+    };
+    let gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+
+    let mut records = Vec::new();
+    while !reader.fill_buf()?.is_empty() {
+        let offset = reader.position;
+        let record = if gzip {
+            let mut member = BufReader::new(flate2::bufread::GzDecoder::new(&mut reader));
+            let record = read_record(&mut member)?;
+            io::copy(&mut member, &mut io::sink())?;
+            record
+        } else {
+            read_record(&mut reader)?
+        };
+
+        let Some(mut record) = record else {
+            break;
+        };
+        record.offset = offset;
+        record.length = reader.position - offset;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// The HTTP response a `response` or `revisit` record holds. A revisit's body is empty, the
+/// payload lives in the record it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Reads the HTTP response of the record at an offset that [`scan_records`] or an index gave,
+/// uncompressed or in its own gzip member.
+pub fn read_response(mut reader: impl Read + Seek, offset: u64) -> io::Result<ArchivedResponse> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(reader);
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        read_http_block(&mut BufReader::new(flate2::bufread::GzDecoder::new(reader)))
+    } else {
+        read_http_block(&mut reader)
+    }
+}
+
+fn read_http_block(reader: &mut impl BufRead) -> io::Result<ArchivedResponse> {
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+    let mut version = String::new();
+    reader.read_line(&mut version)?;
+    if !version.starts_with("WARC/") {
+        return Err(invalid("not a WARC record"));
+    }
+    let headers = read_headers(reader)?;
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+    if !header("Content-Type").is_some_and(|t| t.starts_with("application/http")) {
+        return Err(invalid("not an HTTP response record"));
+    }
+    let length = header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok())
+        .ok_or_else(|| invalid("missing or invalid Content-Length"))?;
+
+    let mut block = reader.take(length);
+    let mut status_line = String::new();
+    block.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("invalid HTTP status line"))?;
+    let headers = read_headers(&mut block)?;
+    let mut body = Vec::new();
+    block.read_to_end(&mut body)?;
+    Ok(ArchivedResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Reads one record, `None` at the end of the input.
+fn read_record(reader: &mut impl BufRead) -> io::Result<Option<WrittenRecord>> {
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+    let mut version = String::new();
+    while version.trim().is_empty() {
+        version.clear();
+        if reader.read_line(&mut version)? == 0 {
+            return Ok(None);
+        }
+    }
+    if !version.starts_with("WARC/") {
+        return Err(invalid("not a WARC record"));
+    }
+
+    let headers = read_headers(reader)?;
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+    let record_type = header("WARC-Type")
+        .and_then(WarcRecordType::parse)
+        .ok_or_else(|| invalid("missing or unknown WARC-Type"))?;
+    let date = header("WARC-Date")
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .ok_or_else(|| invalid("missing or invalid WARC-Date"))?
+        .with_timezone(&Utc);
+    let length = header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok())
+        .ok_or_else(|| invalid("missing or invalid Content-Length"))?;
+
+    let mut block = reader.take(length);
+    let http = header("Content-Type").is_some_and(|t| t.starts_with("application/http"));
+    let (status, mime_type) = match record_type {
+        WarcRecordType::Response | WarcRecordType::Revisit if http => {
+            let mut status_line = String::new();
+            block.read_line(&mut status_line)?;
+            let status = status_line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|status| status.parse().ok());
+            let mime_type = read_headers(&mut block)?
+                .into_iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .map(|(_, value)| {
+                    value
+                        .split(';')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string()
+                });
+            (status, mime_type)
+        }
+        _ => (None, None),
+    };
+    io::copy(&mut block, &mut io::sink())?;
+    // The block is followed by two CRLFs.
+    reader.read_exact(&mut [0; 4])?;
+
+    let target_uri = header("WARC-Target-URI").and_then(|uri| Url::parse(uri).ok());
+    let refers_to = match (
+        header("WARC-Refers-To"),
+        header("WARC-Refers-To-Target-URI").and_then(|uri| Url::parse(uri).ok()),
+        header("WARC-Refers-To-Date").and_then(|date| DateTime::parse_from_rfc3339(date).ok()),
+    ) {
+        (Some(record_id), Some(target_uri), Some(date)) => Some(RevisitTarget {
+            record_id: record_id.to_string(),
+            target_uri,
+            date: date.with_timezone(&Utc),
+        }),
+        _ => None,
+    };
+
+    Ok(Some(WrittenRecord {
+        record_id: header("WARC-Record-ID").unwrap_or_default().to_string(),
+        record_type,
+        target_uri,
+        date,
+        offset: 0,
+        length: 0,
+        status,
+        mime_type,
+        payload_digest: header("WARC-Payload-Digest").map(str::to_string),
+        refers_to,
+    }))
+}
+
+/// Reads `name: value` lines up to an empty line.
+fn read_headers(reader: &mut impl BufRead) -> io::Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            return Ok(headers);
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+}
+
+/// Counts the bytes consumed from a reader, to find where gzip members start.
+struct Counting<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: BufRead> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Counting<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+/// The labelled SHA-256 digest of a payload as used in WARC headers, `sha256:` followed by the
+/// base32 encoded digest.
+pub fn payload_digest(payload: &[u8]) -> String {
+    format!(
+        "sha256:{}",
+        data_encoding::BASE32.encode(&Sha256::digest(payload))
+    )
+}
+
+/// Formats a date as the WARC-Date `YYYY-MM-DDThh:mm:ssZ`.
+pub fn format_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        410 => "Gone",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use chrono::TimeZone;
+    use flate2::read::MultiGzDecoder;
+
+    use super::*;
+
+    fn response<'a>(
+        url: &'a Url,
+        headers: &'a [(String, String)],
+        body: &'a [u8],
+    ) -> HttpResponse<'a> {
+        HttpResponse {
+            url,
+            date: Utc.with_ymd_and_hms(2026, 3, 1, 12, 30, 0).unwrap(),
+            status: 200,
+            headers,
+            body,
+        }
+    }
+
+    #[test]
+    fn writes_response_and_revisit_records() {
+        let url = Url::parse("https://example.com/page").unwrap();
+        let headers = vec![(
+            "Content-Type".to_string(),
+            "text/html; charset=utf-8".to_string(),
+        )];
+
+        let mut writer = WarcWriter::new(Vec::new());
+        let info = writer
+            .write_warcinfo("crawl.warc", &[("software", "kirby")])
+            .unwrap();
+        let original = writer
+            .write_response(&response(&url, &headers, b"body"))
+            .unwrap();
+        let revisit = writer
+            .write_response(&response(&url, &headers, b"body"))
+            .unwrap();
+        let empty = writer
+            .write_response(&response(&url, &headers, b""))
+            .unwrap();
+        let empty_again = writer
+            .write_response(&response(&url, &headers, b""))
+            .unwrap();
+
+        assert_eq!(info.offset, 0);
+        assert_eq!(original.offset, info.length);
+        assert_eq!(original.mime_type.as_deref(), Some("text/html"));
+        assert_eq!(revisit.record_type, WarcRecordType::Revisit);
+        assert_eq!(revisit.payload_digest, original.payload_digest);
+        assert_eq!(empty.record_type, WarcRecordType::Response);
+        assert_eq!(empty_again.record_type, WarcRecordType::Response);
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        let records = output.split("WARC/1.1\r\n").skip(1).collect::<Vec<&str>>();
+        assert_eq!(records.len(), 5);
+        assert!(records[1].contains("WARC-Date: 2026-03-01T12:30:00Z\r\n"));
+        assert!(records[1].ends_with(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\nbody\r\n\r\n"
+        ));
+        assert!(records[2].contains(&format!("WARC-Refers-To: {}\r\n", original.record_id)));
+        assert!(records[2].contains(IDENTICAL_PAYLOAD_PROFILE));
+        assert!(!records[2].contains("\r\n\r\nbody"));
+    }
+
+    #[test]
+    fn compresses_records_and_remembers_earlier_payloads() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut writer = WarcWriter::gzip(Vec::new());
+        writer.remember(
+            payload_digest(b"old"),
+            RevisitTarget {
+                record_id: "<urn:uuid:previous>".into(),
+                target_uri: url.clone(),
+                date: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            },
+        );
+
+        let revisit = writer.write_response(&response(&url, &[], b"old")).unwrap();
+        assert_eq!(
+            revisit.refers_to.map(|t| t.record_id).as_deref(),
+            Some("<urn:uuid:previous>")
+        );
+
+        writer.set_revisits(false);
+        let full = writer.write_response(&response(&url, &[], b"old")).unwrap();
+        assert_eq!(full.record_type, WarcRecordType::Response);
+        assert_eq!(full.offset, revisit.length);
+
+        let mut output = String::new();
+        MultiGzDecoder::new(writer.into_inner().as_slice())
+            .read_to_string(&mut output)
+            .unwrap();
+        assert!(output.contains("WARC-Refers-To-Date: 2025-01-01T00:00:00Z\r\n"));
+        assert!(output.ends_with("\r\n\r\nold\r\n\r\n"));
+    }
+}
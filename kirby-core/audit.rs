@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Why a URL wasn't crawled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum Decision {
+    /// robots.txt doesn't allow the user agent to fetch the URL.
+    RobotsDenied {
+        user_agent: String,
+        /// The rule that denied it, e.g. `Disallow: /private/`, when known.
+        rule: Option<String>,
+    },
+    /// The URL is outside of the crawl's scope, e.g. on another domain or too deep.
+    OutOfScope { reason: String },
+    /// The URL or its content was already crawled.
+    Duplicate {
+        /// The URL or digest it duplicates.
+        of: String,
+    },
+    /// A crawl budget ran out, e.g. `pages` or `bytes`.
+    BudgetExhausted { budget: String },
+    /// The URL looks like part of a crawler trap, e.g. an endless calendar.
+    TrapDetected { reason: String },
+    /// The response's content type isn't one the crawl accepts.
+    MimeRejected { mime: String },
+}
+
+/// A decision about a URL and when it was made.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub url: Url,
+    #[serde(flatten)]
+    pub decision: Decision,
+}
+
+/// An append-only log of why URLs weren't crawled, to answer "why is this page missing?".
+///
+/// Entries are written as JSON lines as soon as they're recorded, so the log survives a crash
+/// and can be read with other tools as well. Opening a log indexes it by URL, so
+/// [`query`](Self::query) only reads the entries of the URL asked about.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::audit::{AuditLog, Decision};
+/// use url::Url;
+///
+/// # let dir = tempfile::tempdir().unwrap();
+/// # let path = dir.path().join("audit.jsonl");
+/// let mut log = AuditLog::open(&path).unwrap();
+/// let url = Url::parse("https://example.com/private/report").unwrap();
+/// log.record(
+///     &url,
+///     Decision::RobotsDenied {
+///         user_agent: "KirbyBot".to_string(),
+///         rule: Some("Disallow: /private/".to_string()),
+///     },
+/// )
+/// .unwrap();
+///
+/// let entries = log.query(&url).unwrap();
+/// assert_eq!(entries.len(), 1);
+/// assert!(matches!(entries[0].decision, Decision::RobotsDenied { .. }));
+/// ```
+#[derive(Debug)]
+pub struct AuditLog {
+    file: File,
+    /// The offset of every entry, by URL.
+    offsets: HashMap<String, Vec<u64>>,
+    len: u64,
+}
+
+impl AuditLog {
+    /// Opens the log at `path`, creating it when it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut offsets = HashMap::<String, Vec<u64>>::new();
+        let mut reader = BufReader::new(&file);
+        let mut line = String::new();
+        let mut len = 0;
+        let mut complete = true;
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)? as u64;
+            if read == 0 {
+                break;
+            }
+            complete = line.ends_with('\n');
+            // A line cut short by a crash is skipped rather than failing the whole log.
+            if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+                offsets.entry(entry.url.into()).or_default().push(len);
+            }
+            len += read;
+        }
+        // Entries appended after a torn line mustn't end up on that line.
+        if !complete {
+            file.write_all(b"\n")?;
+            len += 1;
+        }
+
+        Ok(Self { file, offsets, len })
+    }
+
+    /// Records a decision made now.
+    pub fn record(&mut self, url: &Url, decision: Decision) -> io::Result<()> {
+        self.append(&AuditEntry {
+            at: Utc::now(),
+            url: url.clone(),
+            decision,
+        })
+    }
+
+    pub fn append(&mut self, entry: &AuditEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.offsets
+            .entry(entry.url.to_string())
+            .or_default()
+            .push(self.len);
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    /// Every decision made about `url`, oldest first.
+    pub fn query(&self, url: &Url) -> io::Result<Vec<AuditEntry>> {
+        let Some(offsets) = self.offsets.get(url.as_str()) else {
+            return Ok(Vec::new());
+        };
+        let mut file = &self.file;
+        let mut entries = Vec::with_capacity(offsets.len());
+        for &offset in offsets {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut line = String::new();
+            BufReader::new(file.take(self.len - offset)).read_line(&mut line)?;
+            entries.push(serde_json::from_str(&line)?);
+        }
+        Ok(entries)
+    }
+
+    /// The URLs with at least one decision.
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.offsets.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn queries_by_url_after_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let at = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let a = Url::parse("https://example.com/a").unwrap();
+        let b = Url::parse("https://example.com/b").unwrap();
+
+        let mut log = AuditLog::open(&path).unwrap();
+        let budget = AuditEntry {
+            at,
+            url: a.clone(),
+            decision: Decision::BudgetExhausted {
+                budget: "pages".to_string(),
+            },
+        };
+        log.append(&budget).unwrap();
+        log.record(
+            &b,
+            Decision::MimeRejected {
+                mime: "application/pdf".to_string(),
+            },
+        )
+        .unwrap();
+        drop(log);
+
+        // A torn last line, as left by a crash.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"at\":\"2026-05").unwrap();
+
+        let mut log = AuditLog::open(&path).unwrap();
+        let duplicate = Decision::Duplicate {
+            of: "https://example.com/".to_string(),
+        };
+        log.record(&a, duplicate.clone()).unwrap();
+
+        let entries = log.query(&a).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], budget);
+        assert_eq!(entries[1].decision, duplicate);
+        assert_eq!(log.query(&b).unwrap().len(), 1);
+        assert!(log
+            .query(&Url::parse("https://example.com/c").unwrap())
+            .unwrap()
+            .is_empty());
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text
+            .lines()
+            .next()
+            .unwrap()
+            .contains("\"decision\":\"budget_exhausted\",\"budget\":\"pages\""));
+        drop(log);
+        assert_eq!(AuditLog::open(&path).unwrap().query(&a).unwrap().len(), 2);
+    }
+}
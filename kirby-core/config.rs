@@ -0,0 +1,721 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use regex::Regex;
+use scraper::Selector;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::export::csv::CsvSink;
+use crate::export::jsonl::JsonlSink;
+use crate::export::{Column, ColumnSource, Sink};
+use crate::frontier::{on_domain, Scope};
+use crate::render::RenderOptions;
+
+/// The User-Agent sent when none is configured.
+pub const DEFAULT_USER_AGENT: &str = concat!("KirbyBot/", env!("CARGO_PKG_VERSION"));
+
+/// A crawl definition, read from a TOML or YAML file.
+///
+/// Only `seeds` is required. Unknown keys are rejected, so a misspelt option fails with the
+/// line and column it's on instead of being ignored.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_core::config::CrawlConfig;
+/// use kirby_core::frontier::Scope;
+///
+/// let config = CrawlConfig::from_toml(
+///     r#"
+///     seeds = ["https://example.com/"]
+///     scope = "host"
+///
+///     [budget]
+///     max_pages = 1000
+///
+///     [[domains]]
+///     domain = "shop.example.com"
+///     delay_ms = 5000
+///
+///     [extract]
+///     title = "h1"
+///
+///     [[sinks]]
+///     type = "jsonl"
+///     directory = "pages"
+///     "#,
+/// )
+/// .unwrap();
+/// assert_eq!(config.scope, Scope::SameHost);
+/// assert_eq!(config.politeness.delay(), Duration::from_secs(1));
+/// assert!(config.domain("shop.example.com").is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrawlConfig {
+    pub seeds: Vec<Url>,
+    /// Metadata attached to seeds, added to the records of the seed and of every URL found
+    /// from it.
+    #[serde(default)]
+    pub seed_metadata: BTreeMap<Url, Value>,
+    #[serde(default)]
+    pub scope: Scope,
+    /// How many links away from a seed pages are crawled.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+    #[serde(default)]
+    pub budget: Budget,
+    #[serde(default)]
+    pub politeness: Politeness,
+    /// Overrides for some domains, the first matching one applies.
+    #[serde(default)]
+    pub domains: Vec<DomainConfig>,
+    /// Fields extracted from HTML pages by name, each the text of the first element matching
+    /// a CSS selector.
+    #[serde(default)]
+    pub extract: BTreeMap<String, CssSelector>,
+    /// Named sets of fields, extracted instead of `extract` from the pages a rule names them
+    /// for.
+    #[serde(default)]
+    pub selector_sets: BTreeMap<String, BTreeMap<String, CssSelector>>,
+    /// How URLs matching a pattern are treated, the first matching rule applies.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// A registered handler called with every page, redirect and failed fetch.
+    #[serde(default)]
+    pub handler: Option<PluginConfig>,
+    /// Registered stages every page is run through before it's written out, in order.
+    #[serde(default)]
+    pub stages: Vec<PluginConfig>,
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    /// Renders HTML pages in a headless browser before they're captured.
+    #[serde(default)]
+    pub render: Option<RenderConfig>,
+    /// Logs in before crawling, and again whenever the session expires.
+    #[serde(default)]
+    pub login: Option<LoginConfig>,
+    /// Writes one body per document, duplicates are written without one and with the reason
+    /// they're duplicates.
+    #[serde(default)]
+    pub dedup: Option<DedupConfig>,
+}
+
+/// When a crawl stops early.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    /// Stops after fetching this many pages.
+    pub max_pages: Option<u64>,
+}
+
+/// How a crawl identifies itself and how hard it hits servers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Politeness {
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// The name robots.txt rules are matched against, the user agent's product token (the
+    /// part before the `/`) by default.
+    #[serde(default)]
+    pub robots_agent: Option<String>,
+    /// Milliseconds to wait between requests to the same host.
+    #[serde(default = "default_delay_ms")]
+    pub delay_ms: u64,
+    /// How many pages are fetched at once. Each host gets one request at a time.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+}
+
+impl Default for Politeness {
+    fn default() -> Self {
+        Self {
+            user_agent: default_user_agent(),
+            robots_agent: None,
+            delay_ms: default_delay_ms(),
+            concurrency: default_concurrency(),
+        }
+    }
+}
+
+impl Politeness {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    /// The configured robots agent or the user agent's product token.
+    pub fn robots_agent(&self) -> String {
+        self.robots_agent.clone().unwrap_or_else(|| {
+            let token = self.user_agent.split('/').next().unwrap_or_default();
+            token.trim().to_string()
+        })
+    }
+}
+
+/// Settings for a domain and its subdomains that differ from the rest of the crawl.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainConfig {
+    pub domain: String,
+    /// Milliseconds between requests to each of the domain's hosts.
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    /// Stops fetching from the domain after this many pages.
+    #[serde(default)]
+    pub max_pages: Option<u64>,
+}
+
+/// How the URLs matching a pattern are crawled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub pattern: UrlPattern,
+    /// Matching URLs aren't crawled when they're discovered.
+    #[serde(default)]
+    pub skip: bool,
+    /// Whether the links of matching pages are followed.
+    #[serde(default = "default_follow")]
+    pub follow: bool,
+    /// The selector set extracted from matching pages instead of `extract`.
+    #[serde(default)]
+    pub extract: Option<String>,
+}
+
+/// How pages are rendered in a browser, see [`Renderer`](crate::render::Renderer).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderConfig {
+    /// The WebDriver server running the browser, e.g. chromedriver's `http://localhost:9515/`.
+    pub webdriver: Url,
+    /// What's done to the pages no profile matches.
+    #[serde(default)]
+    pub options: RenderOptions,
+    /// Options for the URLs matching a pattern, the first matching profile is used.
+    #[serde(default)]
+    pub profiles: Vec<RenderProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderProfile {
+    pub pattern: UrlPattern,
+    #[serde(default)]
+    pub options: RenderOptions,
+}
+
+/// A login form the crawl signs in with, see [`Session`](crate::login::Session).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoginConfig {
+    /// The page with the login form.
+    pub url: Url,
+    /// The form to submit, the first one on the page when not set.
+    #[serde(default)]
+    pub form: Option<CssSelector>,
+    /// The values filled in, by input name. The form's other inputs, e.g. a CSRF token, are
+    /// sent as they are.
+    pub fields: BTreeMap<String, String>,
+    /// An element that's only on the page after logging in, e.g. a logout link.
+    #[serde(default)]
+    pub success_selector: Option<CssSelector>,
+    /// A cookie that's only set by logging in, e.g. `session`.
+    #[serde(default)]
+    pub success_cookie: Option<String>,
+}
+
+/// How duplicate pages are found, see [`DedupPolicy`](crate::dedup::policy::DedupPolicy).
+/// Redirects, canonical links and identical content are always used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DedupConfig {
+    /// HTML pages whose text SimHashes are at most this many bits apart are duplicates.
+    #[serde(default)]
+    pub similar_text_distance: Option<u32>,
+    /// Images whose perceptual hashes are at most this many bits apart are duplicates, this
+    /// needs the `images` feature.
+    #[serde(default)]
+    pub similar_image_distance: Option<u32>,
+}
+
+/// A regular expression matched against whole URLs, checked when the configuration is read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct UrlPattern(String);
+
+impl UrlPattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        Regex::new(pattern).map_err(|error| format!("invalid pattern {pattern:?}: {error}"))?;
+        Ok(Self(pattern.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn regex(&self) -> Regex {
+        // Unwrapping is safe here because the pattern was checked when it was created.
+        Regex::new(&self.0).unwrap()
+    }
+}
+
+impl<'de> Deserialize<'de> for UrlPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::parse(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// Where crawled pages are written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// JSONL files in a directory, see [`JsonlSink`].
+    Jsonl { directory: PathBuf },
+    /// A CSV file of the [`csv_columns`](CrawlConfig::csv_columns).
+    Csv { path: PathBuf },
+    /// A Parquet file of the [`csv_columns`](CrawlConfig::csv_columns), which needs the
+    /// `parquet` feature.
+    Parquet { path: PathBuf },
+    /// A sink registered under `name` by the application running the crawl.
+    Plugin {
+        name: String,
+        #[serde(default)]
+        options: Value,
+    },
+}
+
+impl SinkConfig {
+    /// Creates the sink's files.
+    pub fn open(&self, config: &CrawlConfig) -> io::Result<Box<dyn Sink + Send>> {
+        match self {
+            Self::Jsonl { directory } => {
+                fs::create_dir_all(directory)?;
+                Ok(Box::new(JsonlSink::new(directory, "pages")))
+            }
+            Self::Csv { path } => Ok(Box::new(CsvSink::new(
+                BufWriter::new(File::create(path)?),
+                config.csv_columns(),
+            ))),
+            #[cfg(feature = "parquet")]
+            Self::Parquet { path } => {
+                use crate::export::parquet::{ColumnType, ParquetSink};
+
+                let columns = config
+                    .csv_columns()
+                    .into_iter()
+                    .map(|column| {
+                        let column_type = match column.source {
+                            ColumnSource::Status => ColumnType::Int64,
+                            ColumnSource::FetchedAt => ColumnType::Timestamp,
+                            _ => ColumnType::Utf8,
+                        };
+                        (column, column_type)
+                    })
+                    .collect();
+                Ok(Box::new(ParquetSink::create(path, columns)?))
+            }
+            #[cfg(not(feature = "parquet"))]
+            Self::Parquet { .. } => {
+                Err(io::Error::other("Parquet output needs the parquet feature"))
+            }
+            Self::Plugin { name, .. } => Err(io::Error::other(format!(
+                "no sink is registered as {name:?}"
+            ))),
+        }
+    }
+}
+
+/// A component an application registered by name, with the options to create it with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub name: String,
+    /// Handed to the component's constructor as they are, `null` when there are none.
+    #[serde(default)]
+    pub options: Value,
+}
+
+/// A CSS selector, checked when the configuration is read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct CssSelector(String);
+
+impl CssSelector {
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        Selector::parse(selector)
+            .map_err(|error| format!("invalid selector {selector:?}: {error}"))?;
+        Ok(Self(selector.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn selector(&self) -> Selector {
+        // Unwrapping is safe here because the selector was checked when it was created.
+        Selector::parse(&self.0).unwrap()
+    }
+}
+
+impl<'de> Deserialize<'de> for CssSelector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::parse(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// The languages a configuration is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// The format a file's extension stands for.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    /// The file isn't valid TOML or YAML or doesn't follow the schema, the message says where.
+    Parse(String),
+    /// A value is well-formed but can't be used, `key` is its dotted path, e.g. `seeds[1]`.
+    Invalid {
+        key: String,
+        message: String,
+    },
+    /// The file's extension is neither `.toml`, `.yaml` nor `.yml`.
+    UnknownFormat(PathBuf),
+}
+
+impl ConfigError {
+    fn invalid(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "couldn't read the configuration: {error}"),
+            Self::Parse(message) => f.write_str(message.trim_end()),
+            Self::Invalid { key, message } => write!(f, "invalid `{key}`: {message}"),
+            Self::UnknownFormat(path) => {
+                write!(f, "{} isn't a .toml, .yaml or .yml file", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Default for CrawlConfig {
+    /// A configuration without seeds or sinks, with every other setting at its default.
+    fn default() -> Self {
+        Self {
+            seeds: Vec::new(),
+            seed_metadata: BTreeMap::new(),
+            scope: Scope::default(),
+            max_depth: default_max_depth(),
+            budget: Budget::default(),
+            politeness: Politeness::default(),
+            domains: Vec::new(),
+            extract: BTreeMap::new(),
+            selector_sets: BTreeMap::new(),
+            rules: Vec::new(),
+            handler: None,
+            stages: Vec::new(),
+            sinks: Vec::new(),
+            render: None,
+            login: None,
+            dedup: None,
+        }
+    }
+}
+
+impl CrawlConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|error| ConfigError::Parse(error.to_string()))
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, ConfigError> {
+        serde_yaml::from_str(text).map_err(|error| ConfigError::Parse(error.to_string()))
+    }
+
+    /// Reads a configuration file, in the format its extension names.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)
+            .ok_or_else(|| ConfigError::UnknownFormat(path.to_path_buf()))?;
+        let text = fs::read_to_string(path)?;
+        match format {
+            ConfigFormat::Toml => Self::from_toml(&text),
+            ConfigFormat::Yaml => Self::from_yaml(&text),
+        }
+    }
+
+    /// Checks what the schema can't, e.g. that there are seeds to start from.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.seeds.is_empty() {
+            return Err(ConfigError::invalid("seeds", "at least one seed is needed"));
+        }
+        for (index, seed) in self.seeds.iter().enumerate() {
+            if !matches!(seed.scheme(), "http" | "https") {
+                return Err(ConfigError::invalid(
+                    format!("seeds[{index}]"),
+                    format!("{seed} isn't an http or https URL"),
+                ));
+            }
+        }
+        if self.politeness.user_agent.trim().is_empty() {
+            return Err(ConfigError::invalid("politeness.user_agent", "it's empty"));
+        }
+        if self.politeness.concurrency == 0 {
+            return Err(ConfigError::invalid(
+                "politeness.concurrency",
+                "at least one page must be fetched at a time",
+            ));
+        }
+        if let Some(url) = self
+            .seed_metadata
+            .keys()
+            .find(|url| !self.seeds.contains(url))
+        {
+            return Err(ConfigError::invalid(
+                format!("seed_metadata.\"{url}\""),
+                "there's no such seed",
+            ));
+        }
+        for (index, domain) in self.domains.iter().enumerate() {
+            if domain.domain.is_empty() || domain.domain.contains(['/', ':']) {
+                return Err(ConfigError::invalid(
+                    format!("domains[{index}].domain"),
+                    format!("{:?} isn't a domain name", domain.domain),
+                ));
+            }
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            if let Some(set) = &rule.extract {
+                if !self.selector_sets.contains_key(set) {
+                    return Err(ConfigError::invalid(
+                        format!("rules[{index}].extract"),
+                        format!("there's no selector set {set:?}"),
+                    ));
+                }
+            }
+        }
+        if let Some(login) = &self.login {
+            if !matches!(login.url.scheme(), "http" | "https") {
+                return Err(ConfigError::invalid(
+                    "login.url",
+                    format!("{} isn't an http or https URL", login.url),
+                ));
+            }
+            if login.success_selector.is_none() && login.success_cookie.is_none() {
+                return Err(ConfigError::invalid(
+                    "login",
+                    "a success_selector or success_cookie is needed to tell whether it worked",
+                ));
+            }
+        }
+        if let Some(render) = &self.render {
+            if !matches!(render.webdriver.scheme(), "http" | "https") {
+                return Err(ConfigError::invalid(
+                    "render.webdriver",
+                    format!("{} isn't an http or https URL", render.webdriver),
+                ));
+            }
+        }
+        if let Some(dedup) = &self.dedup {
+            if dedup
+                .similar_text_distance
+                .is_some_and(|distance| distance > 63)
+            {
+                return Err(ConfigError::invalid(
+                    "dedup.similar_text_distance",
+                    "SimHashes are 64 bits, at most 63 can differ",
+                ));
+            }
+            if dedup
+                .similar_image_distance
+                .is_some_and(|distance| distance > 63)
+            {
+                return Err(ConfigError::invalid(
+                    "dedup.similar_image_distance",
+                    "image hashes are 64 bits, at most 63 can differ",
+                ));
+            }
+            #[cfg(not(feature = "images"))]
+            if dedup.similar_image_distance.is_some() {
+                return Err(ConfigError::invalid(
+                    "dedup.similar_image_distance",
+                    "comparing images needs the images feature",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The overrides for a host, from the first domain entry covering it.
+    pub fn domain(&self, host: &str) -> Option<&DomainConfig> {
+        self.domains
+            .iter()
+            .find(|domain| on_domain(host, &domain.domain))
+    }
+
+    /// The columns of tabular output: the record's own, the content type and the extracted
+    /// fields, of `extract` and the selector sets.
+    pub fn csv_columns(&self) -> Vec<Column> {
+        let mut columns = Column::defaults();
+        columns.push(Column::new(
+            "content_type",
+            ColumnSource::Header("content-type".to_string()),
+        ));
+        let names = self
+            .extract
+            .keys()
+            .chain(self.selector_sets.values().flat_map(BTreeMap::keys))
+            .collect::<BTreeSet<_>>();
+        for name in names {
+            // Unwrapping is safe here because the pointer escapes the name.
+            let pointer = format!("/{}", name.replace('~', "~0").replace('/', "~1"));
+            columns.push(Column::field(name.clone(), &pointer).unwrap());
+        }
+        columns
+    }
+}
+
+fn default_max_depth() -> u32 {
+    3
+}
+
+fn default_follow() -> bool {
+    true
+}
+
+fn default_user_agent() -> String {
+    DEFAULT_USER_AGENT.to_string()
+}
+
+fn default_delay_ms() -> u64 {
+    1000
+}
+
+fn default_concurrency() -> usize {
+    8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_toml_and_yaml_alike() {
+        let toml = CrawlConfig::from_toml(
+            r#"
+            seeds = ["https://example.com/"]
+            max_depth = 1
+
+            [politeness]
+            delay_ms = 250
+
+            [[domains]]
+            domain = "example.com"
+            max_pages = 10
+
+            [extract]
+            title = "h1"
+
+            [[sinks]]
+            type = "csv"
+            path = "pages.csv"
+            "#,
+        )
+        .unwrap();
+        let yaml = CrawlConfig::from_yaml(
+            "seeds: [https://example.com/]\n\
+             max_depth: 1\n\
+             politeness: {delay_ms: 250}\n\
+             domains:\n  - domain: example.com\n    max_pages: 10\n\
+             extract: {title: h1}\n\
+             sinks:\n  - type: csv\n    path: pages.csv\n",
+        )
+        .unwrap();
+        assert_eq!(toml, yaml);
+        assert!(toml.validate().is_ok());
+        assert_eq!(toml.domain("shop.example.com").unwrap().max_pages, Some(10));
+        assert_eq!(toml.domain("example.org"), None);
+        assert_eq!(toml.csv_columns().last().unwrap().name, "title");
+    }
+
+    #[test]
+    fn points_at_what_is_wrong() {
+        let error = CrawlConfig::from_toml("seeds = []\nscope = \"planet\"\n").unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("line 2"), "{message}");
+        assert!(message.contains("unknown scope \"planet\""), "{message}");
+
+        let error = CrawlConfig::from_yaml("seeds: []\nextract:\n  title: \"h1[\"\n").unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("invalid selector"), "{message}");
+        assert!(message.contains("line 3"), "{message}");
+
+        let error = CrawlConfig::from_toml("seeds = []\n[politeness]\ndelay = 5\n").unwrap_err();
+        assert!(error.to_string().contains("unknown field `delay`"));
+
+        let config = CrawlConfig::from_toml(r#"seeds = ["ftp://example.com/"]"#).unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "invalid `seeds[0]`: ftp://example.com/ isn't an http or https URL"
+        );
+
+        let config = CrawlConfig::from_toml(
+            "seeds = [\"https://example.com/\"]\n[[rules]]\npattern = \"/a\"\nextract = \"product\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "invalid `rules[0].extract`: there's no selector set \"product\""
+        );
+        let config = CrawlConfig::from_toml(
+            "seeds = [\"https://example.com/\"]\n[seed_metadata.\"https://example.org/\"]\nid = 7\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "invalid `seed_metadata.\"https://example.org/\"`: there's no such seed"
+        );
+        let error = CrawlConfig::from_toml("seeds = []\n[[rules]]\npattern = \"(\"\n").unwrap_err();
+        assert!(error.to_string().contains("invalid pattern"), "{error}");
+    }
+}
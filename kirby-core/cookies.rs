@@ -0,0 +1,231 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+use url::Url;
+
+use crate::frontier::on_domain;
+
+/// A cookie and where it's sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The host that set the cookie, or the domain it's shared with.
+    pub domain: String,
+    /// Whether the cookie is only sent to `domain` itself, not its subdomains.
+    pub host_only: bool,
+    pub path: String,
+    /// Whether the cookie is only sent over HTTPS.
+    pub secure: bool,
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` header of a response from `url`, with whether it deletes the
+    /// cookie. `None` when it's malformed or sets the cookie for another domain.
+    fn parse(url: &Url, set_cookie: &str) -> Option<(Self, bool)> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut parts = set_cookie.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let mut cookie = Self {
+            name: name.trim().to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            secure: false,
+        };
+        if cookie.name.is_empty() {
+            return None;
+        }
+
+        let mut expired = false;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    if !on_domain(&host, &domain) {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "max-age" => expired |= value.parse::<i64>().is_ok_and(|age| age <= 0),
+                "expires" => {
+                    expired |= DateTime::parse_from_rfc2822(&value.replace("GMT", "+0000"))
+                        .is_ok_and(|expires| expires < Utc::now())
+                }
+                _ => {}
+            }
+        }
+        Some((cookie, expired))
+    }
+
+    /// Whether the cookie is sent with a request to `url`.
+    pub fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            on_domain(&host, &self.domain)
+        };
+        let path = url.path();
+        let path_matches = path == self.path
+            || path
+                .strip_prefix(&self.path)
+                .is_some_and(|rest| self.path.ends_with('/') || rest.starts_with('/'));
+        domain_matches && path_matches && (!self.secure || url.scheme() == "https")
+    }
+}
+
+/// The directory of a URL's path, where its cookies apply when they don't set `Path`.
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(end) => url.path()[..end].to_string(),
+    }
+}
+
+/// Cookies kept between requests, e.g. the session a login established. Clones share their
+/// cookies.
+///
+/// Only what sessions need is supported: cookies are sent back to the host that set them,
+/// or to the subdomains of the `Domain` they set, under their `Path`. They last until the
+/// server deletes them, there's no expiry otherwise.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::cookies::CookieJar;
+/// use url::Url;
+///
+/// let jar = CookieJar::new();
+/// let login = Url::parse("https://example.com/login").unwrap();
+/// jar.store(&login, "session=k1rby; Path=/; HttpOnly");
+///
+/// let page = Url::parse("https://example.com/account").unwrap();
+/// assert_eq!(jar.header(&page).as_deref(), Some("session=k1rby"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar(Arc<Mutex<Vec<Cookie>>>);
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn cookies(&self) -> MutexGuard<'_, Vec<Cookie>> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Stores the cookie a `Set-Cookie` header of a response from `url` sets, or deletes it.
+    pub fn store(&self, url: &Url, set_cookie: &str) {
+        if let Some((cookie, expired)) = Cookie::parse(url, set_cookie) {
+            if expired {
+                self.cookies().retain(|kept| !same_cookie(kept, &cookie));
+            } else {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    /// Adds a cookie, replacing the one with the same name, domain and path.
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies();
+        match cookies.iter_mut().find(|kept| same_cookie(kept, &cookie)) {
+            Some(kept) => *kept = cookie,
+            None => cookies.push(cookie),
+        }
+    }
+
+    /// The `Cookie` header for a request to `url`, `None` when no cookie is sent.
+    pub fn header(&self, url: &Url) -> Option<String> {
+        let cookies = self.cookies();
+        let mut sent = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(url))
+            .collect::<Vec<_>>();
+        // Cookies with longer paths are sent first, as browsers do.
+        sent.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let header = sent
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        (!header.is_empty()).then_some(header)
+    }
+
+    /// Whether there's a cookie with this name, for any domain.
+    pub fn contains(&self, name: &str) -> bool {
+        self.cookies().iter().any(|cookie| cookie.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies().is_empty()
+    }
+}
+
+fn same_cookie(a: &Cookie, b: &Cookie) -> bool {
+    a.name == b.name && a.domain == b.domain && a.path == b.path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn sends_cookies_where_they_apply() {
+        let jar = CookieJar::new();
+        jar.store(&url("https://www.example.com/account/login"), "a=1");
+        jar.store(
+            &url("https://www.example.com/"),
+            "b=2; Domain=.example.com; Secure",
+        );
+        jar.store(&url("https://www.example.com/"), "c=3; Domain=other.com");
+        jar.store(&url("https://www.example.com/"), "d=4; Path=/shop");
+
+        assert_eq!(
+            jar.header(&url("https://www.example.com/account/orders"))
+                .as_deref(),
+            Some("a=1; b=2")
+        );
+        assert_eq!(
+            jar.header(&url("https://shop.example.com/shop/cart"))
+                .as_deref(),
+            Some("b=2")
+        );
+        assert_eq!(jar.header(&url("http://shop.example.com/")), None);
+        assert_eq!(jar.header(&url("http://www.example.com/shopping")), None);
+        assert_eq!(jar.len(), 3);
+    }
+
+    #[test]
+    fn replaces_and_deletes_cookies() {
+        let jar = CookieJar::new();
+        let page = url("https://example.com/");
+        jar.store(&page, "session=old; Path=/");
+        jar.store(&page, "session=\"new\"; Path=/");
+        assert_eq!(jar.header(&page).as_deref(), Some("session=new"));
+        assert!(jar.contains("session"));
+
+        jar.store(&page, "session=; Path=/; Max-Age=0");
+        assert!(jar.is_empty());
+        jar.store(&page, "session=new; Path=/");
+        jar.store(
+            &page,
+            "session=; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        );
+        assert!(jar.is_empty());
+    }
+}
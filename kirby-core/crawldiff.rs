@@ -0,0 +1,350 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::Serialize;
+use url::Url;
+
+use crate::record::PageRecord;
+use crate::store::{Store, StoreError};
+
+/// A difference between two crawls for one URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    /// The URL was only crawled in the new run.
+    Added {
+        url: Url,
+        status: u16,
+    },
+    /// The URL was only crawled in the old run.
+    Removed {
+        url: Url,
+        status: u16,
+    },
+    StatusChanged {
+        url: Url,
+        from: u16,
+        to: u16,
+    },
+    /// The URL started or stopped redirecting, or redirects somewhere else. `None` is not
+    /// redirecting.
+    RedirectChanged {
+        url: Url,
+        from: Option<Url>,
+        to: Option<Url>,
+    },
+    /// The content digest changed, or the body digest when either run lacks content digests.
+    ContentChanged {
+        url: Url,
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+impl Change {
+    pub fn url(&self) -> &Url {
+        match self {
+            Self::Added { url, .. }
+            | Self::Removed { url, .. }
+            | Self::StatusChanged { url, .. }
+            | Self::RedirectChanged { url, .. }
+            | Self::ContentChanged { url, .. } => url,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { url, status } => write!(f, "+ {url} ({status})"),
+            Self::Removed { url, status } => write!(f, "- {url} ({status})"),
+            Self::StatusChanged { url, from, to } => write!(f, "~ {url} status {from} -> {to}"),
+            Self::RedirectChanged { url, from, to } => match (from, to) {
+                (None, Some(to)) => write!(f, "~ {url} now redirects to {to}"),
+                (Some(from), None) => write!(f, "~ {url} no longer redirects to {from}"),
+                (Some(from), Some(to)) => {
+                    write!(f, "~ {url} redirects to {to} instead of {from}")
+                }
+                (None, None) => write!(f, "~ {url} redirect unchanged"),
+            },
+            Self::ContentChanged { url, .. } => write!(f, "~ {url} content changed"),
+        }
+    }
+}
+
+/// How many URLs changed in each way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub status_changed: usize,
+    pub redirect_changed: usize,
+    pub content_changed: usize,
+    /// URLs crawled in both runs without a status or content change.
+    pub unchanged: usize,
+}
+
+/// The changes between two crawls of a site, sorted by URL.
+///
+/// A URL whose status and content both changed has a change for each.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::crawldiff::{Change, CrawlDiff};
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let page = |path: &str, status| {
+///     let url = Url::parse("https://example.com/").unwrap().join(path).unwrap();
+///     PageRecord::new(url, status, Utc::now())
+/// };
+///
+/// let old = vec![page("/", 200), page("/sale", 200)];
+/// let new = vec![page("/", 200), page("/sale", 404), page("/new", 200)];
+///
+/// let diff = CrawlDiff::from_records(old, new);
+/// assert_eq!(diff.summary().added, 1);
+/// assert_eq!(diff.summary().status_changed, 1);
+/// assert!(matches!(diff.changes[1], Change::StatusChanged { from: 200, to: 404, .. }));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CrawlDiff {
+    pub changes: Vec<Change>,
+    #[serde(skip)]
+    unchanged: usize,
+}
+
+impl CrawlDiff {
+    /// Compares the records of two runs, e.g. two stores or two runs kept in one store.
+    ///
+    /// When a run has several records for a URL the last one counts.
+    pub fn from_records(
+        old: impl IntoIterator<Item = PageRecord>,
+        new: impl IntoIterator<Item = PageRecord>,
+    ) -> Self {
+        let by_url = |records: Vec<PageRecord>| {
+            records
+                .into_iter()
+                .map(|record| (record.url.to_string(), record))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let mut old = by_url(old.into_iter().collect());
+        let new = by_url(new.into_iter().collect());
+
+        let mut diff = Self::default();
+        for (key, new) in new {
+            let Some(old) = old.remove(&key) else {
+                diff.changes.push(Change::Added {
+                    url: new.url,
+                    status: new.status,
+                });
+                continue;
+            };
+
+            let mut changed = false;
+            if old.status != new.status {
+                diff.changes.push(Change::StatusChanged {
+                    url: new.url.clone(),
+                    from: old.status,
+                    to: new.status,
+                });
+                changed = true;
+            }
+            let (from, to) = (redirect_target(&old), redirect_target(&new));
+            if from != to {
+                diff.changes.push(Change::RedirectChanged {
+                    url: new.url.clone(),
+                    from,
+                    to,
+                });
+                changed = true;
+            }
+            let (from, to) = match (&old.content_digest, &new.content_digest) {
+                (Some(from), Some(to)) => (Some(from), Some(to)),
+                _ => (old.digest.as_ref(), new.digest.as_ref()),
+            };
+            if from != to {
+                diff.changes.push(Change::ContentChanged {
+                    url: new.url,
+                    from: from.cloned(),
+                    to: to.cloned(),
+                });
+                changed = true;
+            }
+            if !changed {
+                diff.unchanged += 1;
+            }
+        }
+        diff.changes
+            .extend(old.into_values().map(|record| Change::Removed {
+                url: record.url,
+                status: record.status,
+            }));
+
+        diff.changes
+            .sort_by(|a, b| a.url().as_str().cmp(b.url().as_str()));
+        diff
+    }
+
+    /// Compares every page of two stores.
+    pub fn between(old: &dyn Store, new: &dyn Store) -> Result<Self, StoreError> {
+        Ok(Self::from_records(records(old)?, records(new)?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn summary(&self) -> DiffSummary {
+        let mut summary = DiffSummary {
+            unchanged: self.unchanged,
+            ..DiffSummary::default()
+        };
+        for change in &self.changes {
+            match change {
+                Change::Added { .. } => summary.added += 1,
+                Change::Removed { .. } => summary.removed += 1,
+                Change::StatusChanged { .. } => summary.status_changed += 1,
+                Change::RedirectChanged { .. } => summary.redirect_changed += 1,
+                Change::ContentChanged { .. } => summary.content_changed += 1,
+            }
+        }
+        summary
+    }
+
+    /// Writes one JSON object per change, for change-monitoring pipelines.
+    pub fn write_jsonl(&self, mut writer: impl Write) -> io::Result<()> {
+        for change in &self.changes {
+            serde_json::to_writer(&mut writer, change)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a URL ended up after its redirects, `None` when it didn't redirect.
+fn redirect_target(record: &PageRecord) -> Option<Url> {
+    (!record.redirects.is_empty()).then(|| record.final_url().clone())
+}
+
+fn records(store: &dyn Store) -> Result<Vec<PageRecord>, StoreError> {
+    let mut records = Vec::new();
+    for url in store.list()? {
+        if let Some(page) = store.get(&url)? {
+            records.push(page.record);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::record::Redirect;
+    use crate::store::fs::FsStore;
+
+    fn page(path: &str, status: u16, digest: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        let mut record = PageRecord::new(url, status, date);
+        record.digest = Some(digest.to_string());
+        record
+    }
+
+    #[test]
+    fn finds_every_kind_of_change() {
+        let old = vec![
+            page("/", 200, "a"),
+            page("/gone", 200, "b"),
+            page("/moved", 200, "c"),
+            page("/edited", 200, "d"),
+        ];
+        let new = vec![
+            page("/", 200, "a"),
+            page("/moved", 301, "x"),
+            page("/edited", 200, "e"),
+            page("/added", 200, "f"),
+        ];
+
+        let diff = CrawlDiff::from_records(old, new);
+        assert_eq!(
+            diff.summary(),
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                status_changed: 1,
+                redirect_changed: 0,
+                content_changed: 2,
+                unchanged: 1,
+            }
+        );
+
+        let mut jsonl = Vec::new();
+        diff.write_jsonl(&mut jsonl).unwrap();
+        let lines = String::from_utf8(jsonl).unwrap();
+        assert_eq!(
+            lines.lines().next().unwrap(),
+            r#"{"change":"added","url":"https://example.com/added","status":200}"#
+        );
+        assert_eq!(lines.lines().count(), 5);
+    }
+
+    #[test]
+    fn prefers_content_digests_over_body_digests() {
+        let mut old = page("/", 200, "body-a");
+        let mut new = page("/", 200, "body-b");
+        old.content_digest = Some("same".to_string());
+        new.content_digest = Some("same".to_string());
+
+        assert!(CrawlDiff::from_records([old], [new]).is_empty());
+    }
+
+    #[test]
+    fn finds_changed_redirects() {
+        let redirect = |path: &str, to: &str| {
+            let mut record = page(path, 200, "a");
+            record.redirects = vec![Redirect {
+                from: record.url.clone(),
+                to: record.url.join(to).unwrap(),
+                status: 301,
+            }];
+            record
+        };
+        let old = vec![page("/a", 200, "a"), redirect("/b", "/c")];
+        let new = vec![redirect("/a", "/new-a"), redirect("/b", "/d")];
+
+        let changes = CrawlDiff::from_records(old, new)
+            .changes
+            .iter()
+            .map(Change::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                "~ https://example.com/a now redirects to https://example.com/new-a",
+                "~ https://example.com/b redirects to https://example.com/d instead of \
+                 https://example.com/c",
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_two_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut old = FsStore::open(dir.path().join("old")).unwrap();
+        let mut new = FsStore::open(dir.path().join("new")).unwrap();
+        old.put(&page("/", 200, "a"), None).unwrap();
+        new.put(&page("/", 500, "a"), None).unwrap();
+
+        let diff = CrawlDiff::between(&old, &new).unwrap();
+        assert_eq!(diff.summary().status_changed, 1);
+    }
+}
@@ -0,0 +1,1105 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use scraper::{Html, Selector};
+use serde_json::{Map, Value};
+use url::Url;
+
+use self::builder::CrawlerBuilder;
+use self::handler::{CrawlHandler, Page};
+use self::pipeline::{Fetched, Pipeline, Stage};
+use self::registry::Registry;
+use self::rules::Rules;
+use self::stream::Pages;
+use crate::config::{ConfigError, CrawlConfig};
+use crate::cookies::CookieJar;
+use crate::dedup::digest::{ContentDigest, DigestAlgorithm};
+use crate::dedup::policy::{DedupPolicy, DedupSink};
+use crate::error::{ErrorKind, KirbyError};
+use crate::events::{CrawlEvent, EventBus};
+use crate::export::Sink;
+use crate::fetch::{Fetcher, Response};
+use crate::frontier::{host_key, Frontier, Next, QueuedUrl};
+use crate::linkgraph::page_links;
+use crate::login::Session;
+use crate::metrics::Metrics;
+use crate::record::PageRecord;
+use crate::render::Renderer;
+use crate::robotsmeta::RobotsMeta;
+use crate::robotstxt::RobotsTxt;
+use crate::trace::{self, log_event, url_span};
+
+pub mod builder;
+pub mod dry_run;
+pub mod handler;
+pub mod pipeline;
+pub mod registry;
+pub mod rules;
+pub mod simulation;
+pub mod stream;
+
+/// What robots.txt is assumed to say when it can't be fetched, as RFC 9309 asks for server
+/// errors: nothing may be crawled.
+const UNREACHABLE_ROBOTS: &str = "User-agent: *\nDisallow: /";
+
+/// How often a paused crawl checks whether it was resumed.
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+type PauseCheck = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// Crawls from seed URLs, following links within the scope and writing every page to a sink.
+///
+/// Hosts are crawled politely: one request at a time with a delay in between, and only where
+/// their robots.txt allows it. Links are not followed from pages that ask for it with
+/// `nofollow`.
+///
+/// A crawler is assembled in code with [`Crawler::builder`], or from a configuration file.
+///
+/// Crawling doesn't run on an async runtime: it runs on threads of its own and blocks until
+/// it's over. Async applications call [`run`](Self::run) from a blocking task, e.g. tokio's
+/// `spawn_blocking` or smol's `unblock`, or read [`pages`](Self::pages) as a stream with the
+/// `stream` feature, which any executor can poll.
+///
+/// The PostgreSQL stores and the gRPC sink do run async code, on a
+/// [`Runtime`](crate::runtime::Runtime): tokio by default, or async-std and smol with the
+/// `async-std` and `smol` features.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::config::CrawlConfig;
+/// use kirby_core::crawler::Crawler;
+///
+/// let config = CrawlConfig::load("crawl.toml").unwrap();
+/// let summary = Crawler::from_config(config).unwrap().run().unwrap();
+/// println!("crawled {} pages", summary.pages);
+/// ```
+pub struct Crawler {
+    config: CrawlConfig,
+    sink: Box<dyn Sink + Send>,
+    events: Option<EventBus>,
+    paused: Option<PauseCheck>,
+    handler: Option<Box<dyn CrawlHandler>>,
+    pipeline: Pipeline,
+    renderer: Option<Renderer>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+/// What a crawl did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub pages: u64,
+    pub failed: u64,
+    pub denied: u64,
+}
+
+impl Crawler {
+    /// Assembles a crawler from code instead of a configuration file.
+    pub fn builder() -> CrawlerBuilder {
+        CrawlerBuilder::default()
+    }
+
+    /// A crawler writing to the sinks the configuration lists.
+    pub fn from_config(config: CrawlConfig) -> Result<Self, ConfigError> {
+        Self::from_config_with(config, &Registry::new())
+    }
+
+    /// A crawler for a configuration that may use the components in `registry`.
+    pub fn from_config_with(config: CrawlConfig, registry: &Registry) -> Result<Self, ConfigError> {
+        config.validate()?;
+        if config.sinks.is_empty() {
+            return Err(ConfigError::Invalid {
+                key: "sinks".to_string(),
+                message: "at least one sink is needed".to_string(),
+            });
+        }
+        Ok(Self {
+            sink: Box::new(Sinks(registry.open_sinks(&config)?)),
+            events: None,
+            paused: None,
+            handler: registry.create_handler(&config)?,
+            pipeline: registry.create_pipeline(&config)?,
+            renderer: None,
+            metrics: None,
+            config,
+        })
+    }
+
+    /// A crawler writing to `sink` instead of the sinks the configuration lists.
+    pub fn with_sink(
+        config: CrawlConfig,
+        sink: impl Sink + Send + 'static,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        // There are no components registered, so this fails when the configuration names any.
+        let registry = Registry::new();
+        Ok(Self {
+            sink: Box::new(sink),
+            events: None,
+            paused: None,
+            handler: registry.create_handler(&config)?,
+            pipeline: registry.create_pipeline(&config)?,
+            renderer: None,
+            metrics: None,
+            config,
+        })
+    }
+
+    /// Publishes the crawl's events on `bus` as well as logging them, e.g. for a dashboard to
+    /// follow.
+    pub fn events(mut self, bus: EventBus) -> Self {
+        self.events = Some(bus);
+        self
+    }
+
+    /// Calls `handler` with every page, redirect and failed fetch, and crawls the URLs it
+    /// asks for.
+    pub fn handler(mut self, handler: impl CrawlHandler + 'static) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Runs every page through `stage` before it's written out, after the stages added before.
+    pub fn stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.pipeline.push(Box::new(stage));
+        self
+    }
+
+    /// Renders HTML pages in `renderer`'s browser, instead of one started for the configured
+    /// `render` options.
+    pub fn renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    /// Records the crawl's fetches, retries, robots.txt denials and queue depths in `metrics`.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Starts no fetches while `paused` returns true, the ones already started finish.
+    pub fn pause_when(mut self, paused: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.paused = Some(Arc::new(paused));
+        self
+    }
+
+    pub fn config(&self) -> &CrawlConfig {
+        &self.config
+    }
+
+    /// Starts crawling in the background, handing over each page as it's written out to the
+    /// sinks, see [`Pages`].
+    pub fn pages(mut self) -> Pages {
+        let feed = Pages::feed();
+        let sinks = vec![self.sink, Box::new(feed.clone()) as Box<dyn Sink + Send>];
+        self.sink = Box::new(Sinks(sinks));
+        Pages::start(self, &feed)
+    }
+
+    /// Crawls until there's nothing left in scope or the budget is spent, fetching with
+    /// `politeness.concurrency` threads. Fails when a sink does, the browser for the `render`
+    /// options can't be started or logging in fails.
+    pub fn run(self) -> io::Result<Summary> {
+        let config = &self.config;
+        let renderer = match (self.renderer, &config.render) {
+            (Some(renderer), _) => Some(renderer),
+            (None, Some(render)) => Some(Renderer::from_config(render).map_err(io::Error::other)?),
+            (None, None) => None,
+        };
+        let sink = match &config.dedup {
+            Some(dedup) => Box::new(DedupSink::new(self.sink, DedupPolicy::from_config(dedup))),
+            None => self.sink,
+        };
+        let mut fetcher = Fetcher::new(config.politeness.user_agent.clone());
+        let session = config.login.clone().map(Session::new);
+        if let Some(session) = &session {
+            fetcher = fetcher.cookies(CookieJar::new());
+            session
+                .login(0, &fetcher, renderer.as_ref())
+                .map_err(io::Error::other)?;
+        }
+        let mut frontier = Frontier::new(config.scope)
+            .max_depth(config.max_depth)
+            .delay(config.politeness.delay());
+        for domain in &config.domains {
+            if let Some(delay_ms) = domain.delay_ms {
+                frontier = frontier.domain_delay(&domain.domain, Duration::from_millis(delay_ms));
+            }
+        }
+        // Seeds given twice are crawled once.
+        let seeds = config
+            .seeds
+            .iter()
+            .filter(|seed| {
+                let metadata = config.seed_metadata.get(seed).cloned();
+                url_span(trace::Stage::Enqueue, seed, 0, None).in_scope(|| {
+                    frontier
+                        .add_seed_with((*seed).clone(), metadata.unwrap_or_default())
+                        .is_ok()
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let crawl = Crawl {
+            state: Mutex::new(State {
+                frontier,
+                robots: HashMap::new(),
+                sink,
+                in_flight: 0,
+                started: 0,
+                domain_started: HashMap::new(),
+                summary: Summary::default(),
+                error: None,
+            }),
+            changed: Condvar::new(),
+            fetcher,
+            session,
+            events: self.events,
+            paused: self.paused,
+            handler: self.handler,
+            pipeline: self.pipeline,
+            renderer,
+            metrics: self.metrics,
+            robots_agent: config.politeness.robots_agent(),
+            selects: config
+                .extract
+                .iter()
+                .map(|(name, selector)| (name.clone(), selector.selector()))
+                .collect(),
+            rules: Rules::new(config),
+            config,
+        };
+        for url in seeds {
+            crawl.record_queue_depth(&crawl.lock().frontier, &url);
+            crawl.emit(CrawlEvent::UrlEnqueued { url, depth: 0 });
+        }
+        // The workers trace to the subscriber of the thread running the crawl.
+        let dispatch = tracing::dispatcher::get_default(Clone::clone);
+        thread::scope(|scope| {
+            for _ in 0..config.politeness.concurrency.max(1) {
+                scope.spawn(|| tracing::dispatcher::with_default(&dispatch, || crawl.work()));
+            }
+        });
+
+        let mut state = crawl.lock();
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+        state.sink.finish()?;
+        Ok(state.summary)
+    }
+}
+
+/// The robots.txt of a URL's origin, empty when there's none. Fails when it couldn't be
+/// fetched or the server failed, with [`ErrorKind::Http`] for a server error, which RFC 9309
+/// treats as disallowing everything.
+pub fn fetch_robots(fetcher: &Fetcher, url: &Url) -> Result<String, KirbyError> {
+    let robots_url = url.join("/robots.txt").map_err(|error| {
+        KirbyError::new(ErrorKind::Parse, format!("no robots.txt: {error}"))
+            .with_url(url.clone())
+            .with_source(error)
+    })?;
+    let response = fetcher.fetch(&robots_url)?;
+    match response.status {
+        200..=299 => Ok(response.text()),
+        status if status < 500 => Ok(String::new()),
+        status => Err(KirbyError::new(ErrorKind::Http { status }, "").with_url(robots_url)),
+    }
+}
+
+/// Counts a fetch against the budget of the URL's domain, giving the domain when that was the
+/// last fetch it allowed. Fails with the domain when its budget is already spent.
+fn take_domain_budget<'c>(
+    config: &'c CrawlConfig,
+    started: &mut HashMap<String, u64>,
+    url: &Url,
+) -> Result<Option<&'c str>, &'c str> {
+    let Some(domain) = config.domain(url.host_str().unwrap_or_default()) else {
+        return Ok(None);
+    };
+    let Some(max_pages) = domain.max_pages else {
+        return Ok(None);
+    };
+    let started = started.entry(domain.domain.clone()).or_default();
+    if *started >= max_pages {
+        return Err(&domain.domain);
+    }
+    *started += 1;
+    Ok((*started == max_pages).then_some(domain.domain.as_str()))
+}
+
+/// The links of a page to follow: none when the page or its `X-Robots-Tag` header says
+/// `nofollow` for the agent, and otherwise the ones without `rel="nofollow"`.
+fn follow_links(
+    document: &Html,
+    headers: &[(String, String)],
+    base: &Url,
+    robots_agent: &str,
+) -> Vec<Url> {
+    let mut meta = RobotsMeta::from_element(document.root_element());
+    for (_, value) in headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("x-robots-tag"))
+    {
+        meta.add_header(value);
+    }
+    if meta.directives_for(robots_agent).nofollow {
+        return Vec::new();
+    }
+    page_links(document, base)
+        .into_iter()
+        .filter(|edge| {
+            !edge
+                .rel
+                .as_deref()
+                .is_some_and(|rel| rel.contains("nofollow"))
+        })
+        .map(|edge| edge.target)
+        .collect()
+}
+
+/// Writes every page to each of several sinks.
+struct Sinks(Vec<Box<dyn Sink + Send>>);
+
+impl Sink for Sinks {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        self.0
+            .iter_mut()
+            .try_for_each(|sink| sink.write(record, body))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.0.iter_mut().try_for_each(|sink| sink.finish())
+    }
+}
+
+struct State {
+    frontier: Frontier,
+    /// Parsed robots.txt files by origin.
+    robots: HashMap<String, Arc<RobotsTxt<'static>>>,
+    sink: Box<dyn Sink + Send>,
+    in_flight: usize,
+    /// Fetches handed out, counted against the page budget.
+    started: u64,
+    /// Fetches handed out for each domain with a budget of its own.
+    domain_started: HashMap<String, u64>,
+    summary: Summary,
+    error: Option<io::Error>,
+}
+
+struct Crawl<'a> {
+    state: Mutex<State>,
+    changed: Condvar,
+    fetcher: Fetcher,
+    session: Option<Session>,
+    events: Option<EventBus>,
+    paused: Option<PauseCheck>,
+    handler: Option<Box<dyn CrawlHandler>>,
+    pipeline: Pipeline,
+    renderer: Option<Renderer>,
+    metrics: Option<Arc<Metrics>>,
+    robots_agent: String,
+    selects: Vec<(String, Selector)>,
+    rules: Rules,
+    config: &'a CrawlConfig,
+}
+
+/// What happened to one URL.
+enum Outcome {
+    Denied,
+    Failed {
+        error: KirbyError,
+        /// The URLs the handler asked for.
+        links: Vec<Url>,
+    },
+    Fetched {
+        /// What the pipeline made of the page.
+        pages: Vec<Fetched>,
+        links: Vec<Url>,
+    },
+}
+
+impl Crawl<'_> {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A worker panicking while holding the lock doesn't leave the state inconsistent, each
+        // change to it is a single step.
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn emit(&self, event: CrawlEvent) {
+        log_event(&event);
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+
+    /// Sets the depth of the queue of a URL's host in the metrics.
+    fn record_queue_depth(&self, frontier: &Frontier, url: &Url) {
+        if let Some(metrics) = &self.metrics {
+            metrics.set_queue_depth(&host_key(url), frontier.host_len(url) as u64);
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.as_ref().is_some_and(|paused| paused())
+    }
+
+    fn work(&self) {
+        while let Some((queued, robots)) = self.next() {
+            let origin = queued.url.origin().ascii_serialization();
+            let robots = robots.unwrap_or_else(|| self.fetch_robots(&queued.url));
+            let outcome = self.visit(&queued, &robots);
+
+            let mut state = self.lock();
+            state.robots.insert(origin, robots);
+            state.frontier.done(&queued.url, Instant::now());
+            state.in_flight -= 1;
+            let links = match outcome {
+                Outcome::Denied => {
+                    state.summary.denied += 1;
+                    Vec::new()
+                }
+                Outcome::Failed { error, links } => {
+                    self.emit(CrawlEvent::Error { error });
+                    state.summary.failed += 1;
+                    links
+                }
+                Outcome::Fetched { pages, links } => {
+                    state.summary.pages += 1;
+                    for page in pages {
+                        if let Err(error) = state.sink.write(&page.record, Some(&page.body)) {
+                            state.error.get_or_insert(error);
+                        }
+                    }
+                    links
+                }
+            };
+            for link in links {
+                if self.rules.check(&link).is_err() {
+                    continue;
+                }
+                let depth = queued.depth + 1;
+                let _span = url_span(trace::Stage::Enqueue, &link, depth, None).entered();
+                if state.frontier.add(link.clone(), &queued).is_ok() {
+                    self.record_queue_depth(&state.frontier, &link);
+                    self.emit(CrawlEvent::UrlEnqueued { url: link, depth });
+                }
+            }
+            self.changed.notify_all();
+        }
+    }
+
+    /// The next URL to crawl with its origin's robots.txt when it's known, `None` once the
+    /// crawl is over.
+    fn next(&self) -> Option<(QueuedUrl, Option<Arc<RobotsTxt<'static>>>)> {
+        let max_pages = self.config.budget.max_pages;
+        let mut state = self.lock();
+        loop {
+            let over_budget = max_pages.is_some_and(|max| state.started >= max);
+            if state.error.is_some() || over_budget {
+                return None;
+            }
+            if self.is_paused() {
+                let (paused, _) = self
+                    .changed
+                    .wait_timeout(state, PAUSE_POLL)
+                    .unwrap_or_else(|error| error.into_inner());
+                state = paused;
+                continue;
+            }
+            state = match state.frontier.next(Instant::now()) {
+                Next::Ready(queued) => {
+                    self.record_queue_depth(&state.frontier, &queued.url);
+                    match take_domain_budget(self.config, &mut state.domain_started, &queued.url) {
+                        Ok(Some(domain)) => self.emit(CrawlEvent::BudgetExhausted {
+                            budget: format!("pages on {domain}"),
+                        }),
+                        Ok(None) => {}
+                        Err(_) => {
+                            state.frontier.done(&queued.url, Instant::now());
+                            continue;
+                        }
+                    }
+                    state.in_flight += 1;
+                    state.started += 1;
+                    if max_pages == Some(state.started) {
+                        self.emit(CrawlEvent::BudgetExhausted {
+                            budget: "pages".to_string(),
+                        });
+                    }
+                    let origin = queued.url.origin().ascii_serialization();
+                    let robots = state.robots.get(&origin).cloned();
+                    return Some((queued, robots));
+                }
+                Next::Empty if state.in_flight == 0 => return None,
+                Next::Wait(wait) => {
+                    let (state, _) = self
+                        .changed
+                        .wait_timeout(state, wait)
+                        .unwrap_or_else(|error| error.into_inner());
+                    state
+                }
+                Next::Busy | Next::Empty => self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|error| error.into_inner()),
+            };
+        }
+    }
+
+    /// The robots.txt of a URL's origin, denying everything when it couldn't be fetched.
+    fn fetch_robots(&self, url: &Url) -> Arc<RobotsTxt<'static>> {
+        let robots = fetch_robots(&self.fetcher, url);
+        let robots = robots.as_deref().unwrap_or(UNREACHABLE_ROBOTS);
+        Arc::new(RobotsTxt::parse(robots).into_owned())
+    }
+
+    fn visit(&self, queued: &QueuedUrl, robots: &RobotsTxt) -> Outcome {
+        let url = &queued.url;
+        if !robots.is_allowed_url(&self.robots_agent, url) {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_robots_denial();
+            }
+            self.emit(CrawlEvent::RobotsDenied { url: url.clone() });
+            return Outcome::Denied;
+        }
+
+        let fetch_span = url_span(trace::Stage::Fetch, url, queued.depth, Some(1)).entered();
+        self.emit(CrawlEvent::FetchStarted {
+            url: url.clone(),
+            attempt: 1,
+        });
+        let started = Instant::now();
+        let mut response = match self.fetch(url) {
+            Ok(response) => response,
+            Err(error) => return self.failed(url, error),
+        };
+        let (duration, bytes) = (started.elapsed(), response.body.len() as u64);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_fetch(response.status, duration, bytes);
+        }
+        self.emit(CrawlEvent::FetchFinished {
+            url: url.clone(),
+            status: response.status,
+            duration,
+            bytes,
+        });
+        drop(fetch_span);
+
+        let mut record = response.record(Utc::now());
+        record.depth = queued.depth;
+        record.discovery = Some(queued.discovery.clone());
+        record.metadata = Value::clone(&queued.metadata);
+        let (mut script, mut screenshot) = (None, None);
+        // Error pages aren't worth the browser's time.
+        if let Some(renderer) = self
+            .renderer
+            .as_ref()
+            .filter(|_| response.is_html() && (200..300).contains(&response.status))
+        {
+            match renderer.render(response.final_url()) {
+                Ok(rendered) => {
+                    response.body = rendered.html.into_bytes();
+                    record.digest = Some(
+                        ContentDigest::compute(DigestAlgorithm::Sha256, &response.body).to_string(),
+                    );
+                    script = rendered.script;
+                    screenshot = rendered.screenshot;
+                }
+                Err(error) => return self.failed(url, error.with_url(url.clone())),
+            }
+        }
+        let document = response.is_html().then(|| {
+            url_span(trace::Stage::Parse, url, queued.depth, None)
+                .in_scope(|| Html::parse_document(&response.text()))
+        });
+        let mut links = match &document {
+            Some(document) => url_span(trace::Stage::Extract, url, queued.depth, None)
+                .in_scope(|| self.extract(document, &response, &mut record)),
+            None => Vec::new(),
+        };
+        if let Some(script) = script {
+            record.add_field("script", script);
+        }
+        if let Some(handler) = &self.handler {
+            for redirect in &response.redirects {
+                links.extend(handler.on_redirect(redirect));
+            }
+            let handled = handler.on_page(&Page {
+                record: &record,
+                body: &response.body,
+                document: document.as_ref(),
+            });
+            links.extend(handled.follow);
+            if !handled.items.is_empty() {
+                record.add_field("items", Value::Array(handled.items));
+            }
+        }
+        // The screenshot is written after the page, but skips the stages meant for pages.
+        let screenshot = screenshot.map(|capture| Fetched {
+            record: capture.record(&record),
+            body: capture.image,
+        });
+        let page = Fetched {
+            record,
+            body: response.body,
+        };
+        match self.pipeline.run(page) {
+            Ok(mut pages) => {
+                pages.extend(screenshot);
+                Outcome::Fetched { pages, links }
+            }
+            // The page was fetched, so its links are still followed.
+            Err(error) => Outcome::Failed { error, links },
+        }
+    }
+
+    /// Fetches a URL, logging in again and retrying once when the session expired.
+    fn fetch(&self, url: &Url) -> Result<Response, KirbyError> {
+        let Some(session) = &self.session else {
+            return self.fetcher.fetch(url);
+        };
+        let logins = session.logins();
+        let response = self.fetcher.fetch(url)?;
+        if !session.is_logged_out(&response) {
+            return Ok(response);
+        }
+        session.login(logins, &self.fetcher, self.renderer.as_ref())?;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_retry();
+        }
+        self.fetcher.fetch(url)
+    }
+
+    /// A failed fetch, with the URLs the handler asks for instead.
+    fn failed(&self, url: &Url, error: KirbyError) -> Outcome {
+        let links = match &self.handler {
+            Some(handler) => handler.on_error(url, &error),
+            None => Vec::new(),
+        };
+        Outcome::Failed { error, links }
+    }
+
+    /// Fills in the selected fields and returns the links to follow.
+    fn extract(&self, document: &Html, response: &Response, record: &mut PageRecord) -> Vec<Url> {
+        // A rule's selector set replaces the fields extracted from every page.
+        let selects = self.rules.selects(&record.url).unwrap_or(&self.selects);
+        if !selects.is_empty() {
+            let fields = selects
+                .iter()
+                .map(|(name, selector)| {
+                    let text = document.select(selector).next().map(|element| {
+                        let text = element.text().collect::<String>();
+                        text.split_whitespace().collect::<Vec<_>>().join(" ")
+                    });
+                    (name.clone(), text.map_or(Value::Null, Value::String))
+                })
+                .collect::<Map<_, _>>();
+            record.fields = Value::Object(fields);
+        }
+
+        if !self.rules.follows(&record.url) {
+            return Vec::new();
+        }
+        follow_links(
+            document,
+            &response.headers,
+            response.final_url(),
+            &self.robots_agent,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::config::DomainConfig;
+    use crate::trace::test_subscriber::Spans;
+
+    /// Collects the pages written to it.
+    struct Pages(std::sync::Arc<Mutex<Vec<PageRecord>>>);
+
+    impl Sink for Pages {
+        fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stops_at_the_budget_of_a_domain() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::links(["/a", "/b"]))
+                .page("/a", Page::html("<p>A</p>")),
+        );
+        let mut config = CrawlConfig {
+            seeds: vec![server.url().clone()],
+            ..CrawlConfig::default()
+        };
+        config.politeness.delay_ms = 0;
+        config.politeness.concurrency = 1;
+        config.domains.push(DomainConfig {
+            domain: server.url().host_str().unwrap().to_string(),
+            delay_ms: None,
+            max_pages: Some(2),
+        });
+        assert!(matches!(
+            Crawler::from_config(config.clone()),
+            Err(ConfigError::Invalid { key, .. }) if key == "sinks"
+        ));
+
+        let pages = std::sync::Arc::default();
+        let crawler = Crawler::with_sink(config, Pages(std::sync::Arc::clone(&pages))).unwrap();
+        let summary = crawler.run().unwrap();
+        assert_eq!(summary.pages, 2);
+        let pages = pages.lock().unwrap();
+        assert_eq!(pages[1].url.path(), "/a");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn passes_seed_metadata_on_to_the_pages_found_from_it() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::links(["/product/7"]))
+                .page("/product/7", Page::html("<h1>Kirby</h1>")),
+        );
+        let pages = Arc::default();
+        Crawler::builder()
+            .seed_with(server.url().clone(), serde_json::json!({"campaign": "q3"}))
+            .delay(Duration::ZERO)
+            .sink(Pages(Arc::clone(&pages)))
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        let pages = pages.lock().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert!(pages
+            .iter()
+            .all(|page| page.metadata == serde_json::json!({"campaign": "q3"})));
+    }
+
+    #[test]
+    fn traces_each_url_through_its_stages() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::links(["/a"]))
+                .page("/a", Page::html("<p>A</p>")),
+        );
+        let spans = Spans::default();
+        tracing::subscriber::with_default(spans.clone(), || {
+            Crawler::builder()
+                .seed(server.url().clone())
+                .delay(Duration::ZERO)
+                .sink(Pages(Arc::default()))
+                .build()
+                .unwrap()
+                .run()
+                .unwrap();
+        });
+
+        let a = server.url().join("/a").unwrap();
+        let stages = spans
+            .spans()
+            .into_iter()
+            .filter(|(_, fields)| fields["url"] == a.as_str())
+            .map(|(name, fields)| (name, fields["depth"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stages,
+            ["enqueue", "fetch", "parse", "extract"].map(|name| (name, "1".to_string()))
+        );
+    }
+
+    #[test]
+    fn fails_to_fetch_robots_on_server_errors() {
+        let server = MockServer::start(Site::new().pages(
+            "/robots.txt",
+            [Page::new(404, "Not found"), Page::new(503, "Unavailable")],
+        ));
+        let fetcher = Fetcher::new("test".to_string());
+        assert_eq!(fetch_robots(&fetcher, server.url()).unwrap(), "");
+        let error = fetch_robots(&fetcher, server.url()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Http { status: 503 });
+        assert_eq!(
+            error.url(),
+            Some(&server.url().join("/robots.txt").unwrap())
+        );
+    }
+
+    #[test]
+    fn records_metrics_while_crawling() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nDisallow: /private")
+                .page("/", Page::links(["/a", "/private"]))
+                .page("/a", Page::html("<p>A</p>")),
+        );
+        let metrics = Arc::new(Metrics::new());
+        Crawler::builder()
+            .seed(server.url().clone())
+            .delay(Duration::ZERO)
+            .concurrency(1)
+            .metrics(Arc::clone(&metrics))
+            .sink(Pages(Arc::default()))
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        let text = metrics.gather();
+        assert!(text.contains("kirby_requests_total{status=\"200\"} 2\n"));
+        assert!(text.contains("kirby_fetch_duration_seconds_count 2\n"));
+        assert!(text.contains("kirby_robots_denials_total 1\n"));
+        assert!(text.contains("kirby_retries_total 0\n"));
+        // Every queue was drained.
+        assert!(!text.contains("kirby_queue_depth{"));
+    }
+
+    #[test]
+    fn writes_one_body_per_document() {
+        /// Collects the pages written to it and whether they came with a body.
+        struct Bodies(Arc<Mutex<Vec<(PageRecord, bool)>>>);
+
+        impl Sink for Bodies {
+            fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((record.clone(), body.is_some()));
+                Ok(())
+            }
+
+            fn finish(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::links(["/a", "/b", "/c"]))
+                .page("/a", Page::html("<h1>Kirby</h1>"))
+                .page(
+                    "/b",
+                    Page::html(r#"<link rel="canonical" href="/a"><h1>Kirby, printable</h1>"#),
+                )
+                .page("/c", Page::html("<div><h1>Kirby</h1></div>")),
+        );
+        let mut config = CrawlConfig {
+            seeds: vec![server.url().clone()],
+            dedup: Some(Default::default()),
+            ..CrawlConfig::default()
+        };
+        config.politeness.delay_ms = 0;
+        config.politeness.concurrency = 1;
+        let pages = Arc::default();
+        Crawler::with_sink(config, Bodies(Arc::clone(&pages)))
+            .unwrap()
+            .run()
+            .unwrap();
+
+        let a = server.url().join("/a").unwrap();
+        let pages = pages.lock().unwrap();
+        let written = pages
+            .iter()
+            .map(|(record, body)| (record.url.path(), *body, &record.fields["duplicate"]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            written,
+            [
+                ("/", true, &Value::Null),
+                ("/a", true, &Value::Null),
+                (
+                    "/b",
+                    false,
+                    &serde_json::json!({
+                        "status": "duplicate",
+                        "of": a,
+                        "reason": "canonical",
+                        "canonical": a,
+                    })
+                ),
+                (
+                    "/c",
+                    false,
+                    &serde_json::json!({
+                        "status": "duplicate",
+                        "of": a,
+                        "reason": "content",
+                        "digest": ContentDigest::of_text(DigestAlgorithm::Sha256, "Kirby"),
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_rendered_pages_and_their_screenshots() {
+        use crate::render::test_browser::TestBrowser;
+        use crate::render::{RenderOptions, Screenshot};
+
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::html("<p>Loading</p>"))
+                .page("/feed", Page::html("<p>Loading</p>")),
+        );
+        let rendered = r#"<a href="/feed">Feed</a>"#;
+        let browser = TestBrowser::new(rendered).answer(serde_json::json!("Kirby"));
+        let calls = browser.calls();
+        let renderer = Renderer::new(browser).options(RenderOptions {
+            script: Some("document.title".to_string()),
+            screenshot: Some(Screenshot::default()),
+            ..RenderOptions::default()
+        });
+        let seed = server.url().clone();
+        let pages = Arc::default();
+        Crawler::builder()
+            .seed(seed.clone())
+            .delay(Duration::ZERO)
+            .renderer(renderer)
+            .sink(Pages(Arc::clone(&pages)))
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        let pages = pages.lock().unwrap();
+        let paths = pages
+            .iter()
+            .map(|page| (page.url.path(), page.header("content-type").unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                ("/", "text/html; charset=utf-8"),
+                ("/", "image/png"),
+                ("/feed", "text/html; charset=utf-8"),
+                ("/feed", "image/png")
+            ]
+        );
+        let digest = ContentDigest::compute(DigestAlgorithm::Sha256, rendered.as_bytes());
+        assert_eq!(pages[0].digest, Some(digest.to_string()));
+        assert_eq!(pages[0].fields, serde_json::json!({"script": "Kirby"}));
+        assert_eq!(calls.lock().unwrap()[0], format!("navigate {seed}"));
+    }
+
+    #[test]
+    fn logs_in_again_when_the_session_expires() {
+        use std::collections::BTreeMap;
+
+        use crate::config::{CssSelector, LoginConfig};
+
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/login",
+                    Page::html(
+                        r#"<form method="post" action="/session"><input name="user"></form>"#,
+                    ),
+                )
+                .page(
+                    "/session",
+                    Page::new(303, "")
+                        .header("Location", "/account")
+                        .header("Set-Cookie", "session=s1"),
+                )
+                .page("/account", Page::html(r#"<a class="logout">Log out</a>"#))
+                .robots("User-agent: *\nAllow: /")
+                // The session expired by the time the crawl starts.
+                .pages(
+                    "/",
+                    [
+                        Page::new(302, "").header("Location", "/login?next=/"),
+                        Page::html("<p>Welcome back</p>"),
+                    ],
+                ),
+        );
+        let login = LoginConfig {
+            url: server.url().join("/login").unwrap(),
+            form: None,
+            fields: BTreeMap::from([("user".to_string(), "kirby".to_string())]),
+            success_selector: Some(CssSelector::parse(".logout").unwrap()),
+            success_cookie: None,
+        };
+        let mut config = CrawlConfig {
+            seeds: vec![server.url().clone()],
+            login: Some(login),
+            ..CrawlConfig::default()
+        };
+        config.politeness.delay_ms = 0;
+        let pages = Arc::default();
+        let summary = Crawler::with_sink(config, Pages(Arc::clone(&pages)))
+            .unwrap()
+            .run()
+            .unwrap();
+
+        assert_eq!(summary.pages, 1);
+        assert_eq!(pages.lock().unwrap()[0].url.path(), "/");
+        let requests = server.requests();
+        assert_eq!(requests[7].body, b"user=kirby");
+        assert_eq!(requests[9].path, "/");
+        assert_eq!(requests[9].header("cookie"), Some("session=s1"));
+    }
+
+    #[test]
+    fn publishes_events_and_waits_while_paused() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::html("<p>Home</p>")),
+        );
+        let mut config = CrawlConfig {
+            seeds: vec![server.url().clone()],
+            ..CrawlConfig::default()
+        };
+        config.politeness.delay_ms = 0;
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        let paused = Arc::new(AtomicBool::new(true));
+        let flag = Arc::clone(&paused);
+        let crawler = Crawler::with_sink(config, Pages(Arc::default()))
+            .unwrap()
+            .events(bus)
+            .pause_when(move || flag.load(Ordering::SeqCst));
+
+        let crawl = thread::spawn(move || crawler.run().unwrap());
+        thread::sleep(Duration::from_millis(300));
+        assert!(!crawl.is_finished());
+        paused.store(false, Ordering::SeqCst);
+        assert_eq!(crawl.join().unwrap().pages, 1);
+        assert_eq!(server.requests().len(), 2);
+
+        let events = events.try_iter().collect::<Vec<_>>();
+        assert!(matches!(
+            events[0],
+            CrawlEvent::UrlEnqueued { depth: 0, .. }
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(CrawlEvent::FetchFinished { status: 200, .. })
+        ));
+    }
+}
@@ -0,0 +1,342 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use url::Url;
+
+use super::handler::CrawlHandler;
+use super::pipeline::Stage;
+use super::registry::Registry;
+use super::stream::Pages;
+use super::{Crawler, Sinks};
+use crate::config::{
+    ConfigError, CrawlConfig, CssSelector, DomainConfig, LoginConfig, DEFAULT_USER_AGENT,
+};
+use crate::export::Sink;
+use crate::frontier::Scope;
+use crate::metrics::Metrics;
+use crate::render::Renderer;
+
+/// Who a crawler says it is: the `User-Agent` it sends and the name robots.txt rules are
+/// matched against.
+///
+/// Site owners look for a way to reach whoever runs a crawler, so the user agent should name
+/// the bot and link to a page about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    user_agent: String,
+    robots_agent: Option<String>,
+}
+
+impl Identity {
+    /// Matches robots.txt rules against the user agent's product token, the part before the
+    /// `/`.
+    pub fn new(user_agent: impl Into<String>) -> Self {
+        Self {
+            user_agent: user_agent.into(),
+            robots_agent: None,
+        }
+    }
+
+    pub fn robots_agent(mut self, robots_agent: impl Into<String>) -> Self {
+        self.robots_agent = Some(robots_agent.into());
+        self
+    }
+}
+
+impl Default for Identity {
+    fn default() -> Self {
+        Self::new(DEFAULT_USER_AGENT)
+    }
+}
+
+/// Assembles a [`Crawler`] from code, see [`Crawler::builder`].
+///
+/// Everything but the seeds and the sink has a default: the seeds' domains are crawled up to
+/// 3 links deep as `KirbyBot`, one request per host at a time with a second in between, 8 hosts
+/// at once and only where robots.txt allows it.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::crawler::builder::Identity;
+/// use kirby_core::crawler::Crawler;
+/// use kirby_core::export::jsonl::JsonlSink;
+/// use kirby_core::frontier::Scope;
+/// use url::Url;
+///
+/// let bot = Identity::new("examplebot/1.0 (+https://example.com/bot)");
+/// let crawler = Crawler::builder()
+///     .seed(Url::parse("https://example.com/").unwrap())
+///     .scope(Scope::SameDomain)
+///     .max_depth(3)
+///     .identity(bot)
+///     .sink(JsonlSink::new("pages", "example"))
+///     .build()
+///     .unwrap();
+/// let summary = crawler.run().unwrap();
+/// println!("crawled {} pages", summary.pages);
+/// ```
+#[derive(Default)]
+pub struct CrawlerBuilder {
+    config: CrawlConfig,
+    sinks: Vec<Box<dyn Sink + Send>>,
+    handler: Option<Box<dyn CrawlHandler>>,
+    stages: Vec<Box<dyn Stage>>,
+    renderer: Option<Renderer>,
+    metrics: Option<Arc<Metrics>>,
+    registry: Registry,
+}
+
+impl CrawlerBuilder {
+    /// Starts from a configuration, e.g. one loaded from a file. Its sinks are opened by
+    /// [`build`](Self::build) along with the ones added.
+    pub fn config(mut self, config: CrawlConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn seed(mut self, url: Url) -> Self {
+        self.config.seeds.push(url);
+        self
+    }
+
+    /// Adds a seed with metadata, e.g. `json!({"campaign": "q3"})`, that's added to the records
+    /// of the seed and of every URL found from it.
+    pub fn seed_with(mut self, url: Url, metadata: Value) -> Self {
+        self.config.seed_metadata.insert(url.clone(), metadata);
+        self.seed(url)
+    }
+
+    pub fn seeds(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        self.config.seeds.extend(urls);
+        self
+    }
+
+    /// Which links are followed, [`Scope::SameDomain`] by default.
+    pub fn scope(mut self, scope: Scope) -> Self {
+        self.config.scope = scope;
+        self
+    }
+
+    /// How many links away from a seed pages are crawled, 3 by default.
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.config.max_depth = max_depth;
+        self
+    }
+
+    /// Stops after fetching this many pages.
+    pub fn max_pages(mut self, max_pages: u64) -> Self {
+        self.config.budget.max_pages = Some(max_pages);
+        self
+    }
+
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.config.politeness.user_agent = identity.user_agent;
+        self.config.politeness.robots_agent = identity.robots_agent;
+        self
+    }
+
+    /// How long to wait between requests to the same host, a second by default.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.config.politeness.delay_ms = delay.as_millis() as u64;
+        self
+    }
+
+    /// How many pages are fetched at once, 8 by default. Each host gets one request at a time.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config.politeness.concurrency = concurrency;
+        self
+    }
+
+    /// Overrides the delay or page budget for a domain and its subdomains.
+    pub fn domain(mut self, domain: DomainConfig) -> Self {
+        self.config.domains.push(domain);
+        self
+    }
+
+    /// Extracts the text of the first element matching `selector` into a field of every
+    /// HTML page's record.
+    pub fn extract(mut self, name: impl Into<String>, selector: CssSelector) -> Self {
+        self.config.extract.insert(name.into(), selector);
+        self
+    }
+
+    /// Logs in with a form before crawling, and again whenever the session expires, see
+    /// [`Session`](crate::login::Session).
+    pub fn login(mut self, login: LoginConfig) -> Self {
+        self.config.login = Some(login);
+        self
+    }
+
+    /// Adds a sink every page is written to.
+    pub fn sink(mut self, sink: impl Sink + Send + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Calls `handler` with every page, redirect and failed fetch, see [`CrawlHandler`].
+    pub fn handler(mut self, handler: impl CrawlHandler + 'static) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Runs every page through `stage` before it's written out, after the configured stages,
+    /// see [`Stage`].
+    pub fn stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Renders HTML pages in `renderer`'s browser, instead of one started for the configured
+    /// `render` options.
+    pub fn renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    /// Records the crawl's fetches, retries, robots.txt denials and queue depths in `metrics`,
+    /// e.g. for [`Metrics::serve`] to expose.
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Creates the sinks, handler and stages the configuration names from `registry`. The
+    /// handler added with [`handler`](Self::handler) replaces the configured one.
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Builds the crawler and starts crawling in the background, handing over each page as
+    /// it's written out, see [`Pages`]. No sink is needed.
+    pub fn pages(mut self) -> Result<Pages, ConfigError> {
+        let feed = Pages::feed();
+        self.sinks.push(Box::new(feed.clone()));
+        Ok(Pages::start(self.build()?, &feed))
+    }
+
+    /// Fails when the configuration is invalid, no sink was added or a configured sink can't
+    /// be opened.
+    pub fn build(self) -> Result<Crawler, ConfigError> {
+        let Self {
+            config,
+            sinks: added,
+            handler,
+            stages,
+            renderer,
+            metrics,
+            registry,
+        } = self;
+        config.validate()?;
+        if config.sinks.is_empty() && added.is_empty() {
+            return Err(ConfigError::Invalid {
+                key: "sinks".to_string(),
+                message: "at least one sink is needed".to_string(),
+            });
+        }
+        let mut sinks = registry.open_sinks(&config)?;
+        sinks.extend(added);
+        let handler = match handler {
+            Some(handler) => Some(handler),
+            None => registry.create_handler(&config)?,
+        };
+        let mut pipeline = registry.create_pipeline(&config)?;
+        for stage in stages {
+            pipeline.push(stage);
+        }
+        let sink = match sinks.len() {
+            // Unwrapping is safe here because there's exactly one sink.
+            1 => sinks.pop().unwrap(),
+            _ => Box::new(Sinks(sinks)),
+        };
+        Ok(Crawler {
+            config,
+            sink,
+            events: None,
+            paused: None,
+            handler,
+            pipeline,
+            renderer,
+            metrics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Mutex;
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::record::PageRecord;
+
+    struct Pages(Arc<Mutex<Vec<PageRecord>>>);
+
+    impl Sink for Pages {
+        fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn builds_a_crawler_with_defaults() {
+        assert!(matches!(
+            Crawler::builder().sink(Pages(Arc::default())).build(),
+            Err(ConfigError::Invalid { key, .. }) if key == "seeds"
+        ));
+        let seed = Url::parse("https://example.com/").unwrap();
+        assert!(matches!(
+            Crawler::builder().seed(seed.clone()).build(),
+            Err(ConfigError::Invalid { key, .. }) if key == "sinks"
+        ));
+
+        let crawler = Crawler::builder()
+            .seed(seed)
+            .identity(Identity::new("examplebot/1.0").robots_agent("example"))
+            .sink(Pages(Arc::default()))
+            .build()
+            .unwrap();
+        let config = crawler.config();
+        assert_eq!(config.max_depth, 3);
+        assert_eq!(config.scope, Scope::SameDomain);
+        assert_eq!(config.politeness.user_agent, "examplebot/1.0");
+        assert_eq!(config.politeness.robots_agent(), "example");
+        assert_eq!(config.politeness.delay_ms, 1000);
+    }
+
+    #[test]
+    fn crawls_into_every_sink() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::html("<h1>Home</h1>")),
+        );
+        let (first, second) = (Arc::default(), Arc::default());
+        let summary = Crawler::builder()
+            .seed(server.url().clone())
+            .delay(Duration::ZERO)
+            .extract("heading", CssSelector::parse("h1").unwrap())
+            .sink(Pages(Arc::clone(&first)))
+            .sink(Pages(Arc::clone(&second)))
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        assert_eq!(summary.pages, 1);
+        assert_eq!(first.lock().unwrap()[0].fields["heading"], "Home");
+        assert_eq!(second.lock().unwrap().len(), 1);
+        let requests = server.requests();
+        assert_eq!(requests[0].header("user-agent"), Some(DEFAULT_USER_AGENT));
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use scraper::Html;
+use serde::Serialize;
+use url::Url;
+
+use super::rules::Rules;
+use super::{fetch_robots, follow_links, take_domain_budget, UNREACHABLE_ROBOTS};
+use crate::audit::Decision;
+use crate::config::CrawlConfig;
+use crate::fetch::Fetcher;
+use crate::frontier::{Frontier, Next};
+use crate::record::{DiscoverySource, PageRecord};
+use crate::robotstxt::RobotsTxt;
+use crate::sitemap::{fetch_all, SitemapKind};
+use crate::store::{Store, StoreError};
+
+/// The most sitemaps read for each site, counting the ones indexes list.
+const MAX_SITEMAPS: usize = 100;
+
+/// A URL a crawl would reach and whether it would be fetched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedUrl {
+    pub url: Url,
+    pub depth: u32,
+    pub discovery: DiscoverySource,
+    /// Why the URL wouldn't be fetched, `None` when it would.
+    #[serde(flatten)]
+    pub decision: Option<Decision>,
+}
+
+/// What a [`DryRun`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    /// Every URL reached, in the order the crawl would reach them.
+    pub urls: Vec<PlannedUrl>,
+    /// How many links pointed at URLs reached before.
+    pub duplicates: u64,
+}
+
+impl Plan {
+    /// The URLs that would be fetched.
+    pub fn fetched(&self) -> impl Iterator<Item = &PlannedUrl> {
+        self.urls
+            .iter()
+            .filter(|planned| planned.decision.is_none())
+    }
+}
+
+/// Goes through the discovery and decisions of a crawl without fetching any page, to check what
+/// a configuration would crawl before running it.
+///
+/// Only robots.txt files and sitemaps are fetched. There are no pages to find links on, so the
+/// URLs the sites' sitemaps list stand in for them. With a [`cache`](Self::cache) of an earlier
+/// crawl, the links of the cached pages are followed as well.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::config::CrawlConfig;
+/// use kirby_core::crawler::dry_run::DryRun;
+///
+/// let config = CrawlConfig::load("crawl.toml").unwrap();
+/// let plan = DryRun::new(&config).run().unwrap();
+/// for planned in plan.fetched() {
+///     println!("{}", planned.url);
+/// }
+/// ```
+pub struct DryRun<'a> {
+    config: &'a CrawlConfig,
+    cache: Option<&'a dyn Store>,
+    sitemaps: bool,
+    rules: Rules,
+}
+
+impl<'a> DryRun<'a> {
+    pub fn new(config: &'a CrawlConfig) -> Self {
+        Self {
+            config,
+            cache: None,
+            sitemaps: true,
+            rules: Rules::new(config),
+        }
+    }
+
+    /// Follows the links of the pages an earlier crawl stored with their bodies.
+    pub fn cache(mut self, cache: &'a dyn Store) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Whether the URLs in the sites' sitemaps are planned, true by default.
+    pub fn sitemaps(mut self, sitemaps: bool) -> Self {
+        self.sitemaps = sitemaps;
+        self
+    }
+
+    /// Plans the crawl. Fails when the cache can't be read.
+    pub fn run(&self) -> Result<Plan, StoreError> {
+        let config = self.config;
+        let fetcher = Fetcher::new(config.politeness.user_agent.clone());
+        let robots_agent = config.politeness.robots_agent();
+        let mut frontier = Frontier::new(config.scope).max_depth(config.max_depth);
+        let mut plan = Plan::default();
+        for seed in &config.seeds {
+            if let Err(decision) = frontier.add_seed(seed.clone()) {
+                plan.reject(seed.clone(), 0, DiscoverySource::Seed, decision);
+            }
+        }
+
+        // Parsed robots.txt files by origin.
+        let mut robots = HashMap::<String, RobotsTxt<'static>>::new();
+        let mut started = 0;
+        let mut domain_started = HashMap::new();
+        // There's no delay, so a host is ready again as soon as it's done and the frontier hands
+        // out URLs until it's empty.
+        while let Next::Ready(queued) = frontier.next(Instant::now()) {
+            frontier.done(&queued.url, Instant::now());
+            let origin = queued.url.origin().ascii_serialization();
+            if !robots.contains_key(&origin) {
+                let text = fetch_robots(&fetcher, &queued.url);
+                let parsed = RobotsTxt::parse(text.as_deref().unwrap_or(UNREACHABLE_ROBOTS));
+                if self.sitemaps {
+                    self.plan_sitemaps(&fetcher, &queued.url, &parsed, &mut frontier, &mut plan);
+                }
+                robots.insert(origin.clone(), parsed.into_owned());
+            }
+
+            let decision = if config.budget.max_pages.is_some_and(|max| started >= max) {
+                Some(Decision::BudgetExhausted {
+                    budget: "pages".to_string(),
+                })
+            } else if let Err(domain) = take_domain_budget(config, &mut domain_started, &queued.url)
+            {
+                Some(Decision::BudgetExhausted {
+                    budget: format!("pages on {domain}"),
+                })
+            } else {
+                started += 1;
+                let check = robots[&origin].check_url(&robots_agent, &queued.url);
+                (!check.allowed).then(|| Decision::RobotsDenied {
+                    user_agent: robots_agent.clone(),
+                    rule: check.rule.map(|rule| rule.to_string()),
+                })
+            };
+            let fetched = decision.is_none();
+            plan.urls.push(PlannedUrl {
+                url: queued.url.clone(),
+                depth: queued.depth,
+                discovery: queued.discovery.clone(),
+                decision,
+            });
+
+            let follows = fetched && self.rules.follows(&queued.url);
+            if let Some(cache) = self.cache.filter(|_| follows) {
+                if let Some(page) = cache.get(&queued.url)? {
+                    let links = cached_links(&page.record, page.body.as_deref(), &robots_agent);
+                    for link in links {
+                        let added = self
+                            .rules
+                            .check(&link)
+                            .and_then(|()| frontier.add(link.clone(), &queued));
+                        match added {
+                            Ok(()) => {}
+                            Err(Decision::Duplicate { .. }) => plan.duplicates += 1,
+                            Err(decision) => plan.reject(
+                                link,
+                                queued.depth + 1,
+                                DiscoverySource::Link {
+                                    from: queued.url.clone(),
+                                },
+                                decision,
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Queues the URLs of the sitemaps a robots.txt lists.
+    fn plan_sitemaps(
+        &self,
+        fetcher: &Fetcher,
+        site: &Url,
+        robots: &RobotsTxt,
+        frontier: &mut Frontier,
+        plan: &mut Plan,
+    ) {
+        let roots = robots
+            .sitemaps()
+            .iter()
+            .filter_map(|sitemap| site.join(sitemap).ok())
+            .collect::<Vec<_>>();
+        for fetched in fetch_all(fetcher, &roots, MAX_SITEMAPS) {
+            let Ok(sitemap) = fetched.result else {
+                continue;
+            };
+            if sitemap.kind != SitemapKind::UrlSet {
+                continue;
+            }
+            for entry in sitemap.entries {
+                let discovery = DiscoverySource::Sitemap {
+                    sitemap: fetched.url.clone(),
+                };
+                let added = self.rules.check(&entry.loc).and_then(|()| {
+                    frontier.add_discovered(entry.loc.clone(), 0, discovery.clone())
+                });
+                match added {
+                    Ok(()) => {}
+                    Err(Decision::Duplicate { .. }) => plan.duplicates += 1,
+                    Err(decision) => plan.reject(entry.loc, 0, discovery, decision),
+                }
+            }
+        }
+    }
+}
+
+impl Plan {
+    fn reject(&mut self, url: Url, depth: u32, discovery: DiscoverySource, decision: Decision) {
+        self.urls.push(PlannedUrl {
+            url,
+            depth,
+            discovery,
+            decision: Some(decision),
+        });
+    }
+}
+
+/// The links to follow on a cached HTML page.
+fn cached_links(record: &PageRecord, body: Option<&[u8]>, robots_agent: &str) -> Vec<Url> {
+    let mime = record
+        .header("content-type")
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+    let (Some("text/html" | "application/xhtml+xml"), Some(body)) = (mime.as_deref(), body) else {
+        return Vec::new();
+    };
+    let document = Html::parse_document(&String::from_utf8_lossy(body));
+    follow_links(&document, &record.headers, record.final_url(), robots_agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::Site;
+
+    use super::*;
+    use crate::store::fs::FsStore;
+
+    #[test]
+    fn plans_from_sitemaps_and_cached_links() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nDisallow: /private/\nSitemap: /sitemap.xml")
+                .sitemap("/sitemap.xml", ["/a", "/private/b"]),
+        );
+        let site = server.url().clone();
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FsStore::open(dir.path()).unwrap();
+        let mut page = PageRecord::new(site.join("/a").unwrap(), 200, Utc::now());
+        page.headers = vec![("Content-Type".to_string(), "text/html".to_string())];
+        let body =
+            br#"<a href="/">Home</a> <a href="/c">C</a> <a href="https://example.net/">Out</a>"#;
+        cache.put(&page, Some(body)).unwrap();
+
+        let mut config = CrawlConfig {
+            seeds: vec![site.clone()],
+            max_depth: 1,
+            ..CrawlConfig::default()
+        };
+        config.budget.max_pages = Some(3);
+        let plan = DryRun::new(&config).cache(&cache).run().unwrap();
+
+        let urls = plan
+            .urls
+            .iter()
+            .map(|planned| (planned.url.path(), planned.decision.as_ref()))
+            .collect::<Vec<_>>();
+        assert!(
+            matches!(
+                urls[..],
+                [
+                    ("/", None),
+                    ("/a", None),
+                    // https://example.net/ linked from /a.
+                    ("/", Some(Decision::OutOfScope { .. })),
+                    ("/private/b", Some(Decision::RobotsDenied { .. })),
+                    ("/c", Some(Decision::BudgetExhausted { .. })),
+                ]
+            ),
+            "{urls:?}"
+        );
+        assert_eq!(plan.duplicates, 1);
+        assert_eq!(server.requests().len(), 2);
+    }
+}
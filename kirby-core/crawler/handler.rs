@@ -0,0 +1,177 @@
+use scraper::Html;
+use serde_json::Value;
+use url::Url;
+
+use crate::error::KirbyError;
+use crate::record::{PageRecord, Redirect};
+
+/// A page fetched by the crawler, as a [`CrawlHandler`] sees it.
+pub struct Page<'a> {
+    pub record: &'a PageRecord,
+    pub body: &'a [u8],
+    /// The parsed document of HTML pages.
+    pub document: Option<&'a Html>,
+}
+
+/// What a [`CrawlHandler`] made of a page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Handled {
+    /// URLs to crawl next, on top of the page's links. They're subject to the scope and depth
+    /// like links are.
+    pub follow: Vec<Url>,
+    /// Structured items extracted from the page, added to its record as `fields.items` so they
+    /// reach the sinks with it.
+    pub items: Vec<Value>,
+}
+
+/// Reacts to what a crawl fetches, spider-style: each page, each redirect followed to reach
+/// it and each URL that failed, and asks for more URLs to crawl.
+///
+/// The methods are called on the crawler's worker threads, several at once.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::crawler::handler::{CrawlHandler, Handled, Page};
+/// use kirby_core::crawler::Crawler;
+/// use kirby_core::export::jsonl::JsonlSink;
+/// use scraper::Selector;
+/// use serde_json::json;
+/// use url::Url;
+///
+/// struct Products;
+///
+/// impl CrawlHandler for Products {
+///     fn on_page(&self, page: &Page<'_>) -> Handled {
+///         let Some(document) = page.document else {
+///             return Handled::default();
+///         };
+///         let name = Selector::parse("h1.product").unwrap();
+///         let items = document
+///             .select(&name)
+///             .map(|element| json!({ "name": element.text().collect::<String>() }))
+///             .collect();
+///         Handled { follow: Vec::new(), items }
+///     }
+/// }
+///
+/// let crawler = Crawler::builder()
+///     .seed(Url::parse("https://shop.example/").unwrap())
+///     .handler(Products)
+///     .sink(JsonlSink::new("pages", "shop"))
+///     .build()
+///     .unwrap();
+/// crawler.run().unwrap();
+/// ```
+pub trait CrawlHandler: Send + Sync {
+    /// Called with every page fetched, after the redirects it went through.
+    fn on_page(&self, page: &Page<'_>) -> Handled {
+        let _ = page;
+        Handled::default()
+    }
+
+    /// Called with each redirect followed to reach a page, returning URLs to crawl next.
+    fn on_redirect(&self, redirect: &Redirect) -> Vec<Url> {
+        let _ = redirect;
+        Vec::new()
+    }
+
+    /// Called when a URL couldn't be fetched, returning URLs to crawl next.
+    fn on_error(&self, url: &Url, error: &KirbyError) -> Vec<Url> {
+        let _ = (url, error);
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{self, Failure, Site};
+    use scraper::Selector;
+    use serde_json::json;
+
+    use super::*;
+    use crate::crawler::Crawler;
+    use crate::export::Sink;
+
+    struct Pages(Arc<Mutex<Vec<PageRecord>>>);
+
+    impl Sink for Pages {
+        fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Follows `/extra` and `/gone` from the home page and extracts the heading of each page.
+    struct Spider {
+        errors: Arc<Mutex<Vec<Url>>>,
+    }
+
+    impl CrawlHandler for Spider {
+        fn on_page(&self, page: &Page<'_>) -> Handled {
+            let h1 = Selector::parse("h1").unwrap();
+            let heading = page
+                .document
+                .and_then(|document| document.select(&h1).next())
+                .map(|heading| heading.text().collect::<String>());
+            let follow = match page.record.url.path() {
+                "/" => vec![
+                    page.record.url.join("/extra").unwrap(),
+                    page.record.url.join("/gone").unwrap(),
+                ],
+                _ => Vec::new(),
+            };
+            Handled {
+                follow,
+                items: vec![json!({ "heading": heading })],
+            }
+        }
+
+        fn on_error(&self, url: &Url, _error: &KirbyError) -> Vec<Url> {
+            self.errors.lock().unwrap().push(url.clone());
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn follows_what_the_handler_asks_for_and_keeps_its_items() {
+        // The server hangs up on /gone, so fetching it fails.
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", site::Page::html("<h1>Home</h1>"))
+                .page("/extra", site::Page::html("<h1>Extra</h1>"))
+                .page("/gone", site::Page::html("").fail(Failure::Disconnect)),
+        );
+        let pages = Arc::default();
+        let errors = Arc::default();
+        let summary = Crawler::builder()
+            .seed(server.url().clone())
+            .delay(Duration::ZERO)
+            .concurrency(1)
+            .handler(Spider {
+                errors: Arc::clone(&errors),
+            })
+            .sink(Pages(Arc::clone(&pages)))
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        assert_eq!((summary.pages, summary.failed), (2, 1));
+        let pages = pages.lock().unwrap();
+        assert_eq!(pages[0].fields, json!({"items": [{"heading": "Home"}]}));
+        assert_eq!(pages[1].url.path(), "/extra");
+        assert_eq!(pages[1].fields["items"][0]["heading"], "Extra");
+        assert_eq!(errors.lock().unwrap()[0].path(), "/gone");
+    }
+}
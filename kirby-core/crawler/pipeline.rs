@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::dedup::digest::{ContentDigest, DigestAlgorithm};
+use crate::error::KirbyError;
+use crate::record::PageRecord;
+
+/// A fetched page on its way from the fetcher to the sinks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fetched {
+    pub record: PageRecord,
+    pub body: Vec<u8>,
+}
+
+/// A step between fetching a page and writing it out, e.g. scrubbing personal data,
+/// classifying pages or dropping ones that aren't wanted.
+///
+/// Each page a stage returns goes on to the next stage, so a stage can drop a page by
+/// returning none and split it by returning several. Stages are called on the crawler's
+/// worker threads, several at once.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::crawler::pipeline::{Fetched, Pipeline, Stage};
+/// use kirby_core::error::KirbyError;
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// /// Masks e-mail addresses in page bodies.
+/// struct ScrubEmails;
+///
+/// impl Stage for ScrubEmails {
+///     fn name(&self) -> &str {
+///         "scrub-emails"
+///     }
+///
+///     fn process(&self, mut page: Fetched) -> Result<Vec<Fetched>, KirbyError> {
+///         let text = String::from_utf8_lossy(&page.body);
+///         let words = text.split(' ').map(|word| if word.contains('@') { "[email]" } else { word });
+///         page.body = words.collect::<Vec<_>>().join(" ").into_bytes();
+///         Ok(vec![page])
+///     }
+/// }
+///
+/// let pipeline = Pipeline::new().stage(ScrubEmails);
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// let page = Fetched { record, body: b"Write to kirby@example.com".to_vec() };
+/// assert_eq!(pipeline.run(page).unwrap()[0].body, b"Write to [email]");
+/// ```
+pub trait Stage: Send + Sync {
+    /// Identifies the stage in errors.
+    fn name(&self) -> &str;
+
+    /// The pages to pass on for `page`. Failing fails the page, it isn't written out.
+    fn process(&self, page: Fetched) -> Result<Vec<Fetched>, KirbyError>;
+}
+
+/// Stages run one after the other on every page, see [`Stage`].
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a stage after the ones added before.
+    pub fn stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.push(Box::new(stage));
+        self
+    }
+
+    pub fn push(&mut self, stage: Box<dyn Stage>) {
+        self.stages.push(stage);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Runs a page through every stage. Errors name the stage and carry the page's URL.
+    pub fn run(&self, page: Fetched) -> Result<Vec<Fetched>, KirbyError> {
+        let mut pages = vec![page];
+        for stage in &self.stages {
+            let mut next = Vec::new();
+            for page in pages {
+                let url = page.record.url.clone();
+                match stage.process(page) {
+                    Ok(processed) => next.extend(processed),
+                    Err(error) => {
+                        let message = format!("{} stage: {}", stage.name(), error.message());
+                        return Err(KirbyError::new(error.kind(), message)
+                            .with_url(url)
+                            .with_source(error));
+                    }
+                }
+            }
+            pages = next;
+        }
+        Ok(pages)
+    }
+}
+
+/// Drops pages whose body is the same as an earlier page's.
+#[derive(Debug, Default)]
+pub struct DedupBodies {
+    algorithm: DigestAlgorithm,
+    seen: Mutex<HashSet<ContentDigest>>,
+}
+
+impl DedupBodies {
+    pub fn new(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            algorithm,
+            seen: Mutex::default(),
+        }
+    }
+
+    fn seen(&self) -> MutexGuard<'_, HashSet<ContentDigest>> {
+        // Inserting a digest is a single step, the set is whole after a panic.
+        self.seen.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Stage for DedupBodies {
+    fn name(&self) -> &str {
+        "dedup-bodies"
+    }
+
+    fn process(&self, page: Fetched) -> Result<Vec<Fetched>, KirbyError> {
+        let digest = ContentDigest::compute(self.algorithm, &page.body);
+        Ok(if self.seen().insert(digest) {
+            vec![page]
+        } else {
+            Vec::new()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use url::Url;
+
+    use super::*;
+    use crate::error::ErrorKind;
+
+    /// Splits a page into one per line of its body, failing on empty lines.
+    struct Lines;
+
+    impl Stage for Lines {
+        fn name(&self) -> &str {
+            "lines"
+        }
+
+        fn process(&self, page: Fetched) -> Result<Vec<Fetched>, KirbyError> {
+            let mut pages = Vec::new();
+            for line in String::from_utf8_lossy(&page.body).lines() {
+                if line.is_empty() {
+                    return Err(KirbyError::new(ErrorKind::Parse, "empty line"));
+                }
+                pages.push(Fetched {
+                    record: page.record.clone(),
+                    body: line.as_bytes().to_vec(),
+                });
+            }
+            Ok(pages)
+        }
+    }
+
+    #[test]
+    fn passes_every_output_of_a_stage_to_the_next() {
+        let page = |body: &str| Fetched {
+            record: PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now()),
+            body: body.as_bytes().to_vec(),
+        };
+        let pipeline = Pipeline::new().stage(Lines).stage(DedupBodies::default());
+
+        let pages = pipeline.run(page("a\nb\na")).unwrap();
+        let bodies = pages.iter().map(|page| &page.body[..]).collect::<Vec<_>>();
+        assert_eq!(bodies, [b"a", b"b"]);
+        // Bodies seen on earlier pages are dropped too.
+        assert!(pipeline.run(page("b")).unwrap().is_empty());
+
+        let error = pipeline.run(page("c\n\nd")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "parsing failed: lines stage: empty line (https://example.com/)"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use serde_json::Value;
+
+use super::handler::CrawlHandler;
+use super::pipeline::{Pipeline, Stage};
+use super::Crawler;
+use crate::config::{ConfigError, CrawlConfig, PluginConfig, SinkConfig};
+use crate::export::Sink;
+
+/// Why a registered component couldn't be created from its options.
+pub type PluginError = Box<dyn Error + Send + Sync>;
+
+type Constructor<T> = Box<dyn Fn(&Value, &CrawlConfig) -> Result<T, PluginError> + Send + Sync>;
+
+/// Components an application registers by name, so configuration files can use them: sinks
+/// as `[[sinks]]` of type `plugin`, a `[handler]` and `[[stages]]`.
+///
+/// Each is created from the `options` it's configured with, and the whole configuration for
+/// the ones that depend on other settings.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::config::CrawlConfig;
+/// use kirby_core::crawler::pipeline::DedupBodies;
+/// use kirby_core::crawler::registry::Registry;
+/// use kirby_core::export::jsonl::JsonlSink;
+///
+/// let registry = Registry::new()
+///     .sink("archive", |options, _config| {
+///         let directory = options["directory"].as_str().ok_or("`directory` is missing")?;
+///         Ok(JsonlSink::new(directory, "archive"))
+///     })
+///     .stage("dedup", |_options, _config| Ok(DedupBodies::default()));
+///
+/// let config = CrawlConfig::from_toml(
+///     r#"
+///     seeds = ["https://example.com/"]
+///
+///     [[stages]]
+///     name = "dedup"
+///
+///     [[sinks]]
+///     type = "plugin"
+///     name = "archive"
+///     options = { directory = "pages" }
+///     "#,
+/// )
+/// .unwrap();
+/// let summary = registry.crawler(config).unwrap().run().unwrap();
+/// println!("crawled {} pages", summary.pages);
+/// ```
+#[derive(Default)]
+pub struct Registry {
+    sinks: HashMap<String, Constructor<Box<dyn Sink + Send>>>,
+    handlers: HashMap<String, Constructor<Box<dyn CrawlHandler>>>,
+    stages: HashMap<String, Constructor<Box<dyn Stage>>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a sink, replacing any registered as `name` before.
+    pub fn sink<S: Sink + Send + 'static>(
+        mut self,
+        name: impl Into<String>,
+        create: impl Fn(&Value, &CrawlConfig) -> Result<S, PluginError> + Send + Sync + 'static,
+    ) -> Self {
+        let create: Constructor<Box<dyn Sink + Send>> =
+            Box::new(move |options, config| Ok(Box::new(create(options, config)?)));
+        self.sinks.insert(name.into(), create);
+        self
+    }
+
+    /// Registers a handler, replacing any registered as `name` before.
+    pub fn handler<H: CrawlHandler + 'static>(
+        mut self,
+        name: impl Into<String>,
+        create: impl Fn(&Value, &CrawlConfig) -> Result<H, PluginError> + Send + Sync + 'static,
+    ) -> Self {
+        let create: Constructor<Box<dyn CrawlHandler>> =
+            Box::new(move |options, config| Ok(Box::new(create(options, config)?)));
+        self.handlers.insert(name.into(), create);
+        self
+    }
+
+    /// Registers a pipeline stage, replacing any registered as `name` before.
+    pub fn stage<S: Stage + 'static>(
+        mut self,
+        name: impl Into<String>,
+        create: impl Fn(&Value, &CrawlConfig) -> Result<S, PluginError> + Send + Sync + 'static,
+    ) -> Self {
+        let create: Constructor<Box<dyn Stage>> =
+            Box::new(move |options, config| Ok(Box::new(create(options, config)?)));
+        self.stages.insert(name.into(), create);
+        self
+    }
+
+    /// A crawler for a configuration that may use the registered components, like
+    /// [`Crawler::from_config`].
+    pub fn crawler(&self, config: CrawlConfig) -> Result<Crawler, ConfigError> {
+        Crawler::from_config_with(config, self)
+    }
+
+    /// Opens the sinks the configuration lists.
+    pub(super) fn open_sinks(
+        &self,
+        config: &CrawlConfig,
+    ) -> Result<Vec<Box<dyn Sink + Send>>, ConfigError> {
+        let mut sinks = Vec::new();
+        for (index, sink) in config.sinks.iter().enumerate() {
+            let key = format!("sinks[{index}]");
+            let opened = match sink {
+                SinkConfig::Plugin { name, options } => {
+                    let create = find(&self.sinks, name, &key, "sink")?;
+                    create(options, config).map_err(|error| invalid(&key, error))?
+                }
+                _ => sink.open(config).map_err(|error| invalid(&key, error))?,
+            };
+            sinks.push(opened);
+        }
+        Ok(sinks)
+    }
+
+    /// Creates the handler the configuration names, if any.
+    pub(super) fn create_handler(
+        &self,
+        config: &CrawlConfig,
+    ) -> Result<Option<Box<dyn CrawlHandler>>, ConfigError> {
+        match &config.handler {
+            Some(plugin) => create(&self.handlers, plugin, "handler", "handler", config).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates the stages the configuration lists.
+    pub(super) fn create_pipeline(&self, config: &CrawlConfig) -> Result<Pipeline, ConfigError> {
+        let mut pipeline = Pipeline::new();
+        for (index, plugin) in config.stages.iter().enumerate() {
+            let key = format!("stages[{index}]");
+            pipeline.push(create(&self.stages, plugin, &key, "stage", config)?);
+        }
+        Ok(pipeline)
+    }
+}
+
+fn create<T>(
+    registered: &HashMap<String, Constructor<T>>,
+    plugin: &PluginConfig,
+    key: &str,
+    kind: &str,
+    config: &CrawlConfig,
+) -> Result<T, ConfigError> {
+    let create = find(registered, &plugin.name, key, kind)?;
+    create(&plugin.options, config).map_err(|error| invalid(key, error))
+}
+
+fn find<'a, T>(
+    registered: &'a HashMap<String, Constructor<T>>,
+    name: &str,
+    key: &str,
+    kind: &str,
+) -> Result<&'a Constructor<T>, ConfigError> {
+    registered.get(name).ok_or_else(|| ConfigError::Invalid {
+        key: format!("{key}.name"),
+        message: format!("no {kind} is registered as {name:?}"),
+    })
+}
+
+fn invalid(key: &str, error: impl ToString) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_string(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{self, Site};
+
+    use super::*;
+    use crate::crawler::handler::{Handled, Page};
+    use crate::record::PageRecord;
+
+    struct Pages(Arc<Mutex<Vec<PageRecord>>>);
+
+    impl Sink for Pages {
+        fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Adds its `label` option to every page as an item.
+    struct Label(Value);
+
+    impl CrawlHandler for Label {
+        fn on_page(&self, _page: &Page<'_>) -> Handled {
+            Handled {
+                follow: Vec::new(),
+                items: vec![self.0.clone()],
+            }
+        }
+    }
+
+    #[test]
+    fn creates_the_components_a_configuration_names() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", site::Page::html("<h1>Home</h1>")),
+        );
+        let pages = Arc::new(Mutex::new(Vec::new()));
+        let collected = Arc::clone(&pages);
+        let registry = Registry::new()
+            .sink("collect", move |_options, _config| {
+                Ok(Pages(Arc::clone(&collected)))
+            })
+            .handler("label", |options, _config| {
+                Ok(Label(options["label"].clone()))
+            });
+        let toml = |handler: &str| {
+            format!(
+                "seeds = [\"{}\"]\n\
+                 [politeness]\ndelay_ms = 0\n\
+                 [handler]\nname = \"{handler}\"\noptions = {{ label = \"kirby\" }}\n\
+                 [[sinks]]\ntype = \"plugin\"\nname = \"collect\"\n",
+                server.url()
+            )
+        };
+
+        let config = CrawlConfig::from_toml(&toml("labels")).unwrap();
+        assert_eq!(
+            registry.crawler(config).err().unwrap().to_string(),
+            "invalid `handler.name`: no handler is registered as \"labels\""
+        );
+
+        let config = CrawlConfig::from_toml(&toml("label")).unwrap();
+        assert!(Crawler::from_config(config.clone()).is_err());
+        let summary = registry.crawler(config).unwrap().run().unwrap();
+        assert_eq!(summary.pages, 1);
+        assert_eq!(pages.lock().unwrap()[0].fields["items"][0], "kirby");
+    }
+}
@@ -0,0 +1,203 @@
+use regex::Regex;
+use scraper::Selector;
+use url::Url;
+
+use crate::audit::Decision;
+use crate::config::{CrawlConfig, Rule};
+
+/// The rules of a configuration, compiled to be evaluated for every URL discovered.
+///
+/// The first rule whose pattern matches a URL decides how it's crawled: whether it's skipped,
+/// whether the links of its page are followed and which fields are extracted from it. URLs
+/// no rule matches are crawled as usual.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::config::CrawlConfig;
+/// use kirby_core::crawler::rules::Rules;
+/// use url::Url;
+///
+/// let config = CrawlConfig::from_toml(
+///     r#"
+///     seeds = ["https://shop.example/"]
+///
+///     [selector_sets.product]
+///     name = "h1.product-name"
+///     price = ".price"
+///
+///     [[rules]]
+///     pattern = "/cart|/login"
+///     skip = true
+///
+///     [[rules]]
+///     pattern = "^https://shop\\.example/products/"
+///     follow = false
+///     extract = "product"
+///     "#,
+/// )
+/// .unwrap();
+/// let rules = Rules::new(&config);
+///
+/// let url = |s: &str| Url::parse(s).unwrap();
+/// assert!(rules.check(&url("https://shop.example/cart")).is_err());
+/// assert!(!rules.follows(&url("https://shop.example/products/42")));
+/// assert!(rules.follows(&url("https://shop.example/about")));
+/// ```
+#[derive(Debug, Default)]
+pub struct Rules {
+    rules: Vec<Compiled>,
+}
+
+#[derive(Debug)]
+struct Compiled {
+    pattern: Regex,
+    rule: Rule,
+    /// The fields of the rule's selector set.
+    selects: Option<Vec<(String, Selector)>>,
+}
+
+impl Rules {
+    /// Compiles the configuration's rules, which is expected to be
+    /// [valid](CrawlConfig::validate).
+    pub fn new(config: &CrawlConfig) -> Self {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| Compiled {
+                pattern: rule.pattern.regex(),
+                rule: rule.clone(),
+                selects: rule
+                    .extract
+                    .as_ref()
+                    .and_then(|set| config.selector_sets.get(set))
+                    .map(|set| {
+                        set.iter()
+                            .map(|(name, selector)| (name.clone(), selector.selector()))
+                            .collect()
+                    }),
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// The first rule matching a URL, with its index.
+    pub fn find(&self, url: &Url) -> Option<(usize, &Rule)> {
+        self.compiled(url)
+            .map(|(index, compiled)| (index, &compiled.rule))
+    }
+
+    /// Fails for URLs a rule skips.
+    pub fn check(&self, url: &Url) -> Result<(), Decision> {
+        match self.find(url) {
+            Some((index, rule)) if rule.skip => Err(Decision::OutOfScope {
+                reason: format!("rules[{index}] skips {}", rule.pattern.as_str()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the links on a URL's page are followed.
+    pub fn follows(&self, url: &Url) -> bool {
+        self.find(url).is_none_or(|(_, rule)| rule.follow)
+    }
+
+    /// The fields to extract from a URL's page when a rule names a selector set for it.
+    pub(crate) fn selects(&self, url: &Url) -> Option<&[(String, Selector)]> {
+        self.compiled(url)?.1.selects.as_deref()
+    }
+
+    fn compiled(&self, url: &Url) -> Option<(usize, &Compiled)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, compiled)| compiled.pattern.is_match(url.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::crawler::Crawler;
+    use crate::export::Sink;
+    use crate::record::PageRecord;
+
+    struct Pages(Arc<Mutex<Vec<PageRecord>>>);
+
+    impl Sink for Pages {
+        fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn skips_stops_following_and_extracts_by_rule() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page(
+                    "/",
+                    Page::html(
+                        r#"<h1>Shop</h1><a href="/cart">Cart</a><a href="/products/1">Kirby</a>"#,
+                    ),
+                )
+                .page(
+                    "/products/1",
+                    Page::html(
+                        r#"<h1>Kirby</h1><p class="price">5</p><a href="/products/2">More</a>"#,
+                    ),
+                ),
+        );
+        let config = CrawlConfig::from_toml(&format!(
+            r#"
+            seeds = ["{}"]
+            extract = {{ heading = "h1" }}
+
+            [politeness]
+            delay_ms = 0
+            concurrency = 1
+
+            [selector_sets.product]
+            price = ".price"
+
+            [[rules]]
+            pattern = "/cart$"
+            skip = true
+
+            [[rules]]
+            pattern = "/products/"
+            follow = false
+            extract = "product"
+            "#,
+            server.url()
+        ))
+        .unwrap();
+        let pages = Arc::default();
+        let summary = Crawler::builder()
+            .config(config)
+            .sink(Pages(Arc::clone(&pages)))
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        assert_eq!((summary.pages, summary.failed), (2, 0));
+        let pages = pages.lock().unwrap();
+        assert_eq!(pages[0].fields["heading"], "Shop");
+        assert_eq!(pages[1].url.path(), "/products/1");
+        assert_eq!(pages[1].fields, serde_json::json!({"price": "5"}));
+        server.assert_not_requested("/cart");
+        server.assert_not_requested("/products/2");
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use scraper::Html;
+use url::Url;
+
+use super::rules::Rules;
+use super::{follow_links, take_domain_budget, Summary, UNREACHABLE_ROBOTS};
+use crate::config::CrawlConfig;
+use crate::error::{ErrorKind, KirbyError};
+use crate::events::CrawlEvent;
+use crate::fetch::{Response, DEFAULT_MAX_REDIRECTS};
+use crate::frontier::{Frontier, Next, QueuedUrl};
+use crate::record::{Redirect, Timings};
+use crate::robotstxt::RobotsTxt;
+
+/// A scripted set of websites for a [`Simulation`] to crawl, the responses it gets by URL.
+///
+/// URLs without a page are `404 Not Found`, robots.txt included, which allows everything.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_core::crawler::simulation::{Failure, SimulatedPage, SimulatedSite};
+///
+/// let robots = SimulatedPage::text("User-agent: *\nDisallow: /private/");
+/// let slow = SimulatedPage::html("Finally").latency(Duration::from_secs(5));
+/// let flaky = SimulatedPage::html("Back up").fail(Failure::Status(503));
+/// let site = SimulatedSite::new()
+///     .page("https://example.com/robots.txt", robots)
+///     .page("https://example.com/", SimulatedPage::links(["/slow", "/flaky", "/private/a"]))
+///     .page("https://example.com/slow", slow)
+///     .page("https://example.com/flaky", flaky);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedSite {
+    pages: HashMap<Url, SimulatedPage>,
+}
+
+impl SimulatedSite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `page` at `url`. A URL with a query only matches requests with that query, one
+    /// without matches any query.
+    ///
+    /// # Panics
+    ///
+    /// When `url` isn't an absolute URL.
+    pub fn page(mut self, url: &str, page: SimulatedPage) -> Self {
+        let url = Url::parse(url).unwrap_or_else(|error| panic!("invalid URL {url:?}: {error}"));
+        self.pages.insert(url, page);
+        self
+    }
+
+    fn get(&self, url: &Url) -> Option<&SimulatedPage> {
+        self.pages.get(url).or_else(|| {
+            let mut without_query = url.clone();
+            without_query.set_query(None);
+            self.pages.get(&without_query)
+        })
+    }
+}
+
+/// A scripted response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedPage {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+    latency: Duration,
+    /// How the first requests for the page fail, in order.
+    failures: Vec<Failure>,
+}
+
+/// A failure injected before a page is served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Responds with this status and an empty body.
+    Status(u16),
+    /// Fails to respond at all, e.g. with [`ErrorKind::Connect`] or a timeout.
+    Error(ErrorKind),
+}
+
+impl SimulatedPage {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            latency: Duration::ZERO,
+            failures: Vec::new(),
+        }
+    }
+
+    pub fn html(body: impl Into<String>) -> Self {
+        Self::new(200, body).header("Content-Type", "text/html; charset=utf-8")
+    }
+
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::new(200, body).header("Content-Type", "text/plain; charset=utf-8")
+    }
+
+    /// An HTML page linking to `urls`, relative to the page or absolute.
+    pub fn links<'a>(urls: impl IntoIterator<Item = &'a str>) -> Self {
+        let links = urls
+            .into_iter()
+            .map(|url| format!("<a href=\"{url}\">{url}</a>\n"))
+            .collect::<String>();
+        Self::html(format!("<html><body>\n{links}</body></html>"))
+    }
+
+    /// A `301 Moved Permanently` to `location`.
+    pub fn redirect(location: &str) -> Self {
+        Self::new(301, "").header("Location", location)
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Takes this long of virtual time to respond, failures included.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Fails the next request for the page that isn't failed yet, e.g. `.fail(a).fail(b)` fails
+    /// the first request with `a`, the second with `b` and serves the page from the third.
+    pub fn fail(mut self, failure: Failure) -> Self {
+        self.failures.push(failure);
+        self
+    }
+}
+
+/// An event of a simulated crawl, with the virtual time since the crawl started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedEvent {
+    pub at: Duration,
+    pub event: CrawlEvent,
+}
+
+/// What a [`Simulation`] did and when.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    /// Every event of the crawl, in the order of their virtual time.
+    pub events: Vec<TimedEvent>,
+    pub summary: Summary,
+    /// The virtual time the crawl took.
+    pub elapsed: Duration,
+}
+
+impl Timeline {
+    /// When each attempt to fetch a URL started, in order.
+    pub fn fetches(&self) -> impl Iterator<Item = (Duration, &Url)> {
+        self.events.iter().filter_map(|timed| match &timed.event {
+            CrawlEvent::FetchStarted { url, .. } => Some((timed.at, url)),
+            _ => None,
+        })
+    }
+}
+
+/// Crawls a [`SimulatedSite`] instead of the web, with a virtual clock instead of the real one.
+///
+/// The crawl is scheduled as [`Crawler::run`](super::Crawler::run) schedules it, with the same
+/// frontier, politeness delays, budgets, robots.txt and rules, but every request takes the
+/// virtual time its page is scripted to take and nothing is ever waited for. A crawl that
+/// would take hours runs in milliseconds, and it runs the same way every time, so a scheduler
+/// change can be checked against its [`Timeline`].
+///
+/// Failed fetches can be retried with [`retries`](Self::retries), to check a retry policy
+/// against the delays and budgets. Pages aren't written to sinks and there's no handler or
+/// pipeline.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_core::config::CrawlConfig;
+/// use kirby_core::crawler::simulation::{Failure, SimulatedPage, SimulatedSite, Simulation};
+/// use url::Url;
+///
+/// let site = SimulatedSite::new()
+///     .page("https://example.com/", SimulatedPage::links(["/a"]))
+///     .page("https://example.com/a", SimulatedPage::html("A").fail(Failure::Status(503)));
+/// let mut config = CrawlConfig {
+///     seeds: vec![Url::parse("https://example.com/").unwrap()],
+///     ..CrawlConfig::default()
+/// };
+/// config.politeness.delay_ms = 10_000;
+///
+/// let timeline = Simulation::new(&config, &site)
+///     .retries(1, Duration::from_secs(60))
+///     .run();
+/// assert_eq!(timeline.summary.pages, 2);
+/// // The politeness delay before /a, then the delay before its retry.
+/// assert_eq!(timeline.elapsed, Duration::from_secs(70));
+/// ```
+pub struct Simulation<'a> {
+    config: &'a CrawlConfig,
+    site: &'a SimulatedSite,
+    max_retries: u32,
+    retry_delay: Duration,
+    rules: Rules,
+}
+
+impl<'a> Simulation<'a> {
+    /// Crawls `site` as `config` says, without retries.
+    pub fn new(config: &'a CrawlConfig, site: &'a SimulatedSite) -> Self {
+        Self {
+            config,
+            site,
+            max_retries: 0,
+            retry_delay: Duration::ZERO,
+            rules: Rules::new(config),
+        }
+    }
+
+    /// Sets how often a fetch that failed in a retryable way is retried, and the delay before
+    /// the first retry, which doubles with every attempt.
+    pub fn retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Runs the crawl to its end.
+    pub fn run(&self) -> Timeline {
+        let config = self.config;
+        let mut frontier = Frontier::new(config.scope)
+            .max_depth(config.max_depth)
+            .delay(config.politeness.delay());
+        for domain in &config.domains {
+            if let Some(delay_ms) = domain.delay_ms {
+                frontier = frontier.domain_delay(&domain.domain, Duration::from_millis(delay_ms));
+            }
+        }
+        let mut run = Run {
+            simulation: self,
+            // The frontier takes instants, virtual time is counted from this one.
+            start: Instant::now(),
+            now: Duration::ZERO,
+            frontier,
+            robots: HashMap::new(),
+            requests: HashMap::new(),
+            in_flight: Vec::new(),
+            visits: 0,
+            started: 0,
+            domain_started: HashMap::new(),
+            robots_agent: config.politeness.robots_agent(),
+            timeline: Timeline::default(),
+        };
+        for seed in &config.seeds {
+            let metadata = config.seed_metadata.get(seed).cloned();
+            if run
+                .frontier
+                .add_seed_with(seed.clone(), metadata.unwrap_or_default())
+                .is_ok()
+            {
+                run.emit(
+                    Duration::ZERO,
+                    CrawlEvent::UrlEnqueued {
+                        url: seed.clone(),
+                        depth: 0,
+                    },
+                );
+            }
+        }
+        run.run();
+
+        let mut timeline = run.timeline;
+        timeline.elapsed = run.now;
+        // Visits emit their events when they start, this puts them in order with the rest. The
+        // sort is stable, so events at the same time stay in the order they happened.
+        timeline.events.sort_by_key(|timed| timed.at);
+        timeline
+    }
+}
+
+struct Run<'s, 'a> {
+    simulation: &'s Simulation<'a>,
+    start: Instant,
+    now: Duration,
+    frontier: Frontier,
+    /// Parsed robots.txt files by origin.
+    robots: HashMap<String, RobotsTxt<'static>>,
+    /// How often each URL was requested, to fail the first requests of a page.
+    requests: HashMap<Url, usize>,
+    in_flight: Vec<Visit>,
+    /// Visits started, to finish the ones ending at the same time in the order they started.
+    visits: u64,
+    /// Fetches handed out, counted against the page budget.
+    started: u64,
+    /// Fetches handed out for each domain with a budget of its own.
+    domain_started: HashMap<String, u64>,
+    robots_agent: String,
+    timeline: Timeline,
+}
+
+/// A URL being crawled, whose outcome is already known.
+struct Visit {
+    queued: QueuedUrl,
+    done_at: Duration,
+    order: u64,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Denied,
+    Failed,
+    Fetched { links: Vec<Url> },
+}
+
+impl Run<'_, '_> {
+    fn emit(&mut self, at: Duration, event: CrawlEvent) {
+        self.timeline.events.push(TimedEvent { at, event });
+    }
+
+    fn run(&mut self) {
+        let config = self.simulation.config;
+        let concurrency = config.politeness.concurrency.max(1);
+        loop {
+            let mut wake = None;
+            while self.in_flight.len() < concurrency {
+                if config
+                    .budget
+                    .max_pages
+                    .is_some_and(|max| self.started >= max)
+                {
+                    break;
+                }
+                match self.frontier.next(self.start + self.now) {
+                    Next::Ready(queued) => self.start_visit(queued),
+                    Next::Wait(wait) => {
+                        wake = Some(self.now + wait);
+                        break;
+                    }
+                    Next::Busy | Next::Empty => break,
+                }
+            }
+
+            let next_done = self
+                .in_flight
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, visit)| (visit.done_at, visit.order))
+                .map(|(index, visit)| (index, visit.done_at));
+            match (next_done, wake) {
+                (Some((index, done_at)), wake) if wake.is_none_or(|wake| done_at <= wake) => {
+                    self.now = done_at;
+                    let visit = self.in_flight.swap_remove(index);
+                    self.finish_visit(visit);
+                }
+                (_, Some(wake)) => self.now = wake,
+                (None, None) => return,
+                (Some(_), None) => unreachable!("covered by the first arm"),
+            }
+        }
+    }
+
+    fn start_visit(&mut self, queued: QueuedUrl) {
+        let config = self.simulation.config;
+        match take_domain_budget(config, &mut self.domain_started, &queued.url) {
+            Ok(Some(domain)) => self.emit(
+                self.now,
+                CrawlEvent::BudgetExhausted {
+                    budget: format!("pages on {domain}"),
+                },
+            ),
+            Ok(None) => {}
+            Err(_) => {
+                self.frontier.done(&queued.url, self.start + self.now);
+                return;
+            }
+        }
+        self.started += 1;
+        if config.budget.max_pages == Some(self.started) {
+            self.emit(
+                self.now,
+                CrawlEvent::BudgetExhausted {
+                    budget: "pages".to_string(),
+                },
+            );
+        }
+
+        let mut at = self.now;
+        let outcome = self.visit(&queued, &mut at);
+        self.in_flight.push(Visit {
+            queued,
+            done_at: at,
+            order: self.visits,
+            outcome,
+        });
+        self.visits += 1;
+    }
+
+    fn finish_visit(&mut self, visit: Visit) {
+        self.frontier.done(&visit.queued.url, self.start + self.now);
+        let links = match visit.outcome {
+            Outcome::Denied => {
+                self.timeline.summary.denied += 1;
+                return;
+            }
+            Outcome::Failed => {
+                self.timeline.summary.failed += 1;
+                return;
+            }
+            Outcome::Fetched { links } => {
+                self.timeline.summary.pages += 1;
+                links
+            }
+        };
+        for link in links {
+            if self.simulation.rules.check(&link).is_err() {
+                continue;
+            }
+            if self.frontier.add(link.clone(), &visit.queued).is_ok() {
+                self.emit(
+                    self.now,
+                    CrawlEvent::UrlEnqueued {
+                        url: link,
+                        depth: visit.queued.depth + 1,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Crawls a URL from `at`, moving `at` on to when the visit is over.
+    fn visit(&mut self, queued: &QueuedUrl, at: &mut Duration) -> Outcome {
+        let url = &queued.url;
+        let origin = url.origin().ascii_serialization();
+        if !self.robots.contains_key(&origin) {
+            let robots = self.fetch_robots(url, at);
+            let robots = RobotsTxt::parse(&robots).into_owned();
+            self.robots.insert(origin.clone(), robots);
+        }
+        if !self.robots[&origin].is_allowed_url(&self.robots_agent, url) {
+            self.emit(*at, CrawlEvent::RobotsDenied { url: url.clone() });
+            return Outcome::Denied;
+        }
+
+        let simulation = self.simulation;
+        let mut attempt = 1;
+        let response = loop {
+            self.emit(
+                *at,
+                CrawlEvent::FetchStarted {
+                    url: url.clone(),
+                    attempt,
+                },
+            );
+            let started = *at;
+            let result = self.fetch(url, at);
+            let retryable = match &result {
+                Ok(response) => {
+                    self.emit(
+                        *at,
+                        CrawlEvent::FetchFinished {
+                            url: url.clone(),
+                            status: response.status,
+                            duration: *at - started,
+                            bytes: response.body.len() as u64,
+                        },
+                    );
+                    ErrorKind::Http {
+                        status: response.status,
+                    }
+                    .is_retryable()
+                }
+                Err(error) => error.kind().is_retryable(),
+            };
+            if !retryable || attempt > simulation.max_retries {
+                break result;
+            }
+            if let Err(error) = result {
+                self.emit(*at, CrawlEvent::Error { error });
+            }
+            *at += simulation.retry_delay * 2u32.pow(attempt - 1);
+            attempt += 1;
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(error) => {
+                self.emit(*at, CrawlEvent::Error { error });
+                return Outcome::Failed;
+            }
+        };
+
+        let links = if response.is_html() && simulation.rules.follows(url) {
+            let document = Html::parse_document(&response.text());
+            follow_links(
+                &document,
+                &response.headers,
+                response.final_url(),
+                &self.robots_agent,
+            )
+        } else {
+            Vec::new()
+        };
+        Outcome::Fetched { links }
+    }
+
+    /// The robots.txt of a URL's origin, as [`Crawler`](super::Crawler) reads it.
+    fn fetch_robots(&mut self, url: &Url, at: &mut Duration) -> String {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return UNREACHABLE_ROBOTS.to_string();
+        };
+        match self.fetch(&robots_url, at) {
+            Ok(response) if (200..300).contains(&response.status) => response.text(),
+            Ok(response) if response.status < 500 => String::new(),
+            Ok(_) | Err(_) => UNREACHABLE_ROBOTS.to_string(),
+        }
+    }
+
+    /// Requests a URL from the site, following its redirects, moving `at` on by the time the
+    /// responses took.
+    fn fetch(&mut self, url: &Url, at: &mut Duration) -> Result<Response, KirbyError> {
+        let started = *at;
+        let mut redirects = Vec::new();
+        let mut current = url.clone();
+        loop {
+            let requests = self.requests.entry(current.clone()).or_default();
+            *requests += 1;
+            let nth = *requests;
+            let not_found = SimulatedPage::new(404, "");
+            let page = self.simulation.site.get(&current).unwrap_or(&not_found);
+            *at += page.latency;
+            let (status, headers, body) = match page.failures.get(nth - 1) {
+                Some(Failure::Error(kind)) => {
+                    let error = KirbyError::new(*kind, "injected by the simulation")
+                        .with_url(current)
+                        .with_attempt(nth as u32);
+                    return Err(error);
+                }
+                Some(Failure::Status(status)) => (*status, Vec::new(), Vec::new()),
+                None => (
+                    page.status,
+                    page.headers.clone(),
+                    page.body.clone().into_bytes(),
+                ),
+            };
+
+            let location = headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+                .filter(|_| (300..400).contains(&status))
+                .and_then(|(_, location)| current.join(location).ok());
+            if let Some(location) = location {
+                if redirects.len() < DEFAULT_MAX_REDIRECTS as usize {
+                    redirects.push(Redirect {
+                        from: current,
+                        to: location.clone(),
+                        status,
+                    });
+                    current = location;
+                    continue;
+                }
+            }
+            return Ok(Response {
+                url: url.clone(),
+                redirects,
+                status,
+                headers,
+                body,
+                timings: Timings {
+                    total_ms: Some((*at - started).as_millis() as u64),
+                    ..Timings::default()
+                },
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DomainConfig;
+
+    fn config(seeds: &[&str]) -> CrawlConfig {
+        CrawlConfig {
+            seeds: seeds.iter().map(|seed| Url::parse(seed).unwrap()).collect(),
+            ..CrawlConfig::default()
+        }
+    }
+
+    #[test]
+    fn keeps_to_the_delays_of_each_host() {
+        let site = SimulatedSite::new()
+            .page(
+                "https://a.example/",
+                SimulatedPage::links(["/1", "/2"]).latency(Duration::from_millis(100)),
+            )
+            .page("https://a.example/1", SimulatedPage::html("1"))
+            .page("https://a.example/2", SimulatedPage::html("2"))
+            .page(
+                "https://b.example/",
+                SimulatedPage::html("B").latency(Duration::from_secs(3)),
+            );
+        let mut config = config(&["https://a.example/", "https://b.example/"]);
+        config.scope = crate::frontier::Scope::SameHost;
+        config.politeness.delay_ms = 1_000;
+        config.politeness.concurrency = 2;
+        config.domains.push(DomainConfig {
+            domain: "a.example".to_string(),
+            delay_ms: Some(2_000),
+            max_pages: None,
+        });
+
+        let timeline = Simulation::new(&config, &site).run();
+        let fetches = timeline
+            .fetches()
+            .map(|(at, url)| (at.as_millis(), url.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fetches,
+            [
+                (0, "https://a.example/"),
+                (0, "https://b.example/"),
+                (2_100, "https://a.example/1"),
+                (4_100, "https://a.example/2"),
+            ]
+        );
+        assert_eq!(timeline.summary.pages, 4);
+        assert_eq!(timeline.elapsed, Duration::from_millis(4_100));
+        assert_eq!(Simulation::new(&config, &site).run(), timeline);
+    }
+
+    #[test]
+    fn retries_with_backoff_within_the_budget() {
+        let site = SimulatedSite::new()
+            .page(
+                "https://example.com/robots.txt",
+                SimulatedPage::text("User-agent: *\nDisallow: /private/"),
+            )
+            .page(
+                "https://example.com/",
+                SimulatedPage::links(["/flaky", "/private/a", "/down", "/c"]),
+            )
+            .page(
+                "https://example.com/flaky",
+                SimulatedPage::html("Back up")
+                    .fail(Failure::Status(503))
+                    .fail(Failure::Error(ErrorKind::Connect)),
+            )
+            .page(
+                "https://example.com/down",
+                SimulatedPage::html("Down")
+                    .fail(Failure::Status(500))
+                    .fail(Failure::Status(500))
+                    .fail(Failure::Status(500)),
+            );
+        let mut config = config(&["https://example.com/"]);
+        config.politeness.delay_ms = 0;
+        config.budget.max_pages = Some(4);
+
+        let timeline = Simulation::new(&config, &site)
+            .retries(2, Duration::from_secs(1))
+            .run();
+        let fetches = timeline
+            .fetches()
+            .map(|(at, url)| (at.as_secs(), url.path()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fetches,
+            [
+                (0, "/"),
+                (0, "/flaky"),
+                (1, "/flaky"),
+                (3, "/flaky"),
+                (3, "/down"),
+                (4, "/down"),
+                (6, "/down"),
+            ]
+        );
+        // The denied URL counts against the budget as well, so /c isn't fetched.
+        assert_eq!(
+            timeline.summary,
+            Summary {
+                pages: 3,
+                failed: 0,
+                denied: 1,
+            }
+        );
+        assert!(timeline.events.iter().any(|timed| matches!(
+            &timed.event,
+            CrawlEvent::BudgetExhausted { budget } if budget == "pages"
+        )));
+        assert_eq!(timeline.elapsed, Duration::from_secs(6));
+    }
+}
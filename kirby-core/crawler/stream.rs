@@ -0,0 +1,291 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::Waker;
+use std::thread::{self, JoinHandle};
+
+use super::{Crawler, Summary};
+use crate::export::Sink;
+use crate::record::PageRecord;
+
+/// How many fetched pages wait to be read before the crawl stops fetching more.
+const BUFFERED: usize = 16;
+
+/// The pages of a crawl running in the background, in the order they're written out, see
+/// [`Crawler::pages`].
+///
+/// Pages are read by iterating, which blocks until the next page is fetched, or as a
+/// `futures_core::Stream` with the `stream` feature. The crawl waits for pages to be read
+/// when a few are waiting, so a slow reader slows it down rather than piling pages up in
+/// memory. Dropping the pages stops the crawl.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::crawler::Crawler;
+/// use url::Url;
+///
+/// let mut pages = Crawler::builder()
+///     .seed(Url::parse("https://example.com/").unwrap())
+///     .pages()
+///     .unwrap();
+/// for page in &mut pages {
+///     println!("{} {}", page.status, page.url);
+/// }
+/// let summary = pages.finish().unwrap();
+/// println!("{} failed", summary.failed);
+/// ```
+pub struct Pages {
+    shared: Arc<Shared>,
+    crawl: Option<JoinHandle<io::Result<Summary>>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Queue {
+    pages: VecDeque<PageRecord>,
+    /// Set once the crawl is over, no more pages are coming.
+    over: bool,
+    /// Set once the pages are dropped, no more pages are read.
+    dropped: bool,
+    /// Set once the pages are finished, the rest of the crawl's pages are thrown away.
+    discarded: bool,
+    /// Wakes the task waiting on the stream for the next page.
+    waker: Option<Waker>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        // Each change to the queue is a single step, it's whole after a panic.
+        self.queue.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Wakes whoever waits on the queue, reader or crawl.
+    fn notify(&self, queue: &mut Queue) {
+        self.changed.notify_all();
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The sink handing pages over to [`Pages`].
+#[derive(Clone)]
+pub(super) struct Feed(Arc<Shared>);
+
+impl Sink for Feed {
+    fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+        let mut queue = self.0.lock();
+        while queue.pages.len() >= BUFFERED && !queue.dropped && !queue.discarded {
+            queue = self
+                .0
+                .changed
+                .wait(queue)
+                .unwrap_or_else(|error| error.into_inner());
+        }
+        if queue.discarded {
+            return Ok(());
+        }
+        if queue.dropped {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the crawl's pages are no longer read",
+            ));
+        }
+        queue.pages.push_back(record.clone());
+        self.0.notify(&mut queue);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Ends the pages when the crawl is over, however it ended.
+struct Over(Arc<Shared>);
+
+impl Drop for Over {
+    fn drop(&mut self) {
+        let mut queue = self.0.lock();
+        queue.over = true;
+        self.0.notify(&mut queue);
+    }
+}
+
+impl Pages {
+    /// The sink to give the crawler that [`start`](Self::start) reads the pages of.
+    pub(super) fn feed() -> Feed {
+        Feed(Arc::default())
+    }
+
+    /// Runs `crawler`, which writes to `feed`, on a background thread.
+    pub(super) fn start(crawler: Crawler, feed: &Feed) -> Self {
+        let shared = Arc::clone(&feed.0);
+        let over = Over(Arc::clone(&shared));
+        let crawl = thread::spawn(move || {
+            let _over = over;
+            crawler.run()
+        });
+        Self {
+            shared,
+            crawl: Some(crawl),
+        }
+    }
+
+    /// Waits for the crawl to be over and returns what it did. The pages that weren't read
+    /// are thrown away.
+    pub fn finish(mut self) -> io::Result<Summary> {
+        {
+            let mut queue = self.shared.lock();
+            queue.discarded = true;
+            queue.pages.clear();
+            self.shared.notify(&mut queue);
+        }
+        // Unwrapping is safe here because the crawl is only taken here, which consumes the pages.
+        match self.crawl.take().unwrap().join() {
+            Ok(summary) => summary,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl Iterator for Pages {
+    type Item = PageRecord;
+
+    fn next(&mut self) -> Option<PageRecord> {
+        let mut queue = self.shared.lock();
+        loop {
+            if let Some(page) = queue.pages.pop_front() {
+                self.shared.notify(&mut queue);
+                return Some(page);
+            }
+            if queue.over {
+                return None;
+            }
+            queue = self
+                .shared
+                .changed
+                .wait(queue)
+                .unwrap_or_else(|error| error.into_inner());
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Pages {
+    type Item = PageRecord;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        context: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<PageRecord>> {
+        use std::task::Poll;
+
+        let mut queue = self.shared.lock();
+        if let Some(page) = queue.pages.pop_front() {
+            self.shared.notify(&mut queue);
+            return Poll::Ready(Some(page));
+        }
+        if queue.over {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(context.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        let mut queue = self.shared.lock();
+        queue.dropped = true;
+        self.shared.notify(&mut queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+
+    /// A crawl of a home page linking to `/a` and `/b`.
+    fn site() -> (MockServer, Pages) {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nAllow: /")
+                .page("/", Page::links(["/a", "/b"]))
+                .page("/a", Page::html("A"))
+                .page(
+                    "/b",
+                    Page::new(404, "B").header("Content-Type", "text/html"),
+                ),
+        );
+        let pages = Crawler::builder()
+            .seed(server.url().clone())
+            .delay(Duration::ZERO)
+            .concurrency(1)
+            .pages()
+            .unwrap();
+        (server, pages)
+    }
+
+    #[test]
+    fn iterates_over_the_pages_as_they_are_crawled() {
+        let (_server, mut pages) = site();
+        let read = (&mut pages)
+            .map(|page| (page.url.path().to_string(), page.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            read,
+            [
+                ("/".to_string(), 200),
+                ("/a".to_string(), 200),
+                ("/b".to_string(), 404)
+            ]
+        );
+        assert_eq!(pages.finish().unwrap().pages, 3);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn streams_the_pages() {
+        use std::pin::Pin;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+
+        use futures_core::Stream;
+        use url::Url;
+
+        /// Unparks the thread polling the stream.
+        struct Unpark(thread::Thread);
+
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let (_server, mut pages) = site();
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut context = Context::from_waker(&waker);
+        let mut urls = Vec::<Url>::new();
+        loop {
+            match Pin::new(&mut pages).poll_next(&mut context) {
+                Poll::Ready(Some(page)) => urls.push(page.url),
+                Poll::Ready(None) => break,
+                Poll::Pending => thread::park(),
+            }
+        }
+        assert_eq!(urls.len(), 3);
+        pages.finish().unwrap();
+    }
+}
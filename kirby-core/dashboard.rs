@@ -0,0 +1,459 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+use serde::Serialize;
+use url::Url;
+
+use crate::error::KirbyError;
+use crate::events::{CrawlEvent, EventBus, SubscriptionId};
+use crate::http_server::{HttpServer, Request, Response};
+use crate::progress::{Progress, ProgressTracker};
+
+/// How many of the latest errors the dashboard shows.
+pub const RECENT_ERRORS: usize = 20;
+
+/// The window throughput is measured over.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// An error shown on the dashboard.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecentError {
+    pub at: DateTime<Utc>,
+    pub error: KirbyError,
+}
+
+/// What the dashboard shows, also served as JSON from `/status.json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DashboardStatus {
+    pub paused: bool,
+    pub responses: u64,
+    pub bytes: u64,
+    /// Responses in the last minute.
+    pub responses_per_minute: u64,
+    /// Bytes downloaded in the last minute.
+    pub bytes_per_minute: u64,
+    pub progress: Progress,
+    /// URLs enqueued but not fetched yet, per host.
+    pub queues: BTreeMap<String, u64>,
+    /// Fetches underway, per host.
+    pub active: BTreeMap<String, u64>,
+    /// The latest errors, newest first.
+    pub recent_errors: Vec<RecentError>,
+}
+
+/// A live status page for long-running crawls, showing throughput, per-host queues and
+/// recent errors, with buttons to pause and resume the crawl.
+///
+/// The dashboard follows the crawl through its events, see [`attach`](Self::attach) and
+/// [`Crawler::events`](crate::crawler::Crawler::events). Pausing only sets a flag, which a
+/// crawler given [`is_paused`](Self::is_paused) through
+/// [`Crawler::pause_when`](crate::crawler::Crawler::pause_when) checks before starting
+/// fetches. [`serve`](Self::serve) only listens on localhost, since the page has no
+/// authentication.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::dashboard::Dashboard;
+/// use kirby_core::events::{CrawlEvent, EventBus};
+/// use url::Url;
+///
+/// let bus = EventBus::new();
+/// let dashboard = Dashboard::new();
+/// dashboard.attach(&bus);
+/// let server = dashboard.serve(0).unwrap();
+/// println!("Dashboard at http://{}/", server.address());
+///
+/// let url = Url::parse("https://example.com/").unwrap();
+/// bus.publish(CrawlEvent::UrlEnqueued { url, depth: 0 });
+/// assert_eq!(dashboard.status().queues["example.com"], 1);
+///
+/// dashboard.pause();
+/// assert!(dashboard.is_paused());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    state: Arc<Mutex<State>>,
+    paused: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    responses: u64,
+    bytes: u64,
+    /// When each response of the throughput window was received, with its size.
+    window: VecDeque<(Instant, u64)>,
+    progress: ProgressTracker,
+    queues: BTreeMap<String, u64>,
+    active: BTreeMap<String, u64>,
+    /// Newest first.
+    errors: VecDeque<RecentError>,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows the crawl through the events published on `bus`.
+    pub fn attach(&self, bus: &EventBus) -> SubscriptionId {
+        let dashboard = self.clone();
+        bus.on(move |event| dashboard.observe(event))
+    }
+
+    pub fn observe(&self, event: &CrawlEvent) {
+        let mut state = self.state();
+        state.progress.observe(event);
+        let host = event
+            .url()
+            .and_then(|url| url.host_str())
+            .map(str::to_string);
+        match event {
+            CrawlEvent::UrlEnqueued { .. } => {
+                if let Some(host) = host {
+                    *state.queues.entry(host).or_default() += 1;
+                }
+            }
+            // Retries were already taken off the queue and counted by their first attempt.
+            CrawlEvent::FetchStarted { attempt: 1, .. } => {
+                if let Some(host) = host {
+                    decrement(&mut state.queues, &host);
+                    *state.active.entry(host).or_default() += 1;
+                }
+            }
+            CrawlEvent::FetchFinished { bytes, .. } => {
+                state.responses += 1;
+                state.bytes += bytes;
+                state.window.push_back((Instant::now(), *bytes));
+                if let Some(host) = host {
+                    decrement(&mut state.active, &host);
+                }
+            }
+            CrawlEvent::Error { error } => {
+                if let Some(host) = host {
+                    decrement(&mut state.active, &host);
+                }
+                state.errors.push_front(RecentError {
+                    at: Utc::now(),
+                    error: error.clone(),
+                });
+                state.errors.truncate(RECENT_ERRORS);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> DashboardStatus {
+        let mut state = self.state();
+        while let Some((at, _)) = state.window.front() {
+            if at.elapsed() <= THROUGHPUT_WINDOW {
+                break;
+            }
+            state.window.pop_front();
+        }
+
+        DashboardStatus {
+            paused: self.is_paused(),
+            responses: state.responses,
+            bytes: state.bytes,
+            responses_per_minute: state.window.len() as u64,
+            bytes_per_minute: state.window.iter().map(|(_, bytes)| bytes).sum(),
+            progress: state.progress.progress(),
+            queues: state.queues.clone(),
+            active: state.active.clone(),
+            recent_errors: state.errors.iter().cloned().collect(),
+        }
+    }
+
+    /// Serves the dashboard on `127.0.0.1:<port>` until the returned server is dropped, port
+    /// 0 picks a free one.
+    ///
+    /// Requests must name the dashboard in their `Host`, as `127.0.0.1:<port>` or
+    /// `localhost:<port>`, so other sites can't reach it through DNS rebinding. Pausing and
+    /// resuming are refused when the `Origin` or `Referer` is another site, so pages elsewhere
+    /// can't post to it.
+    pub fn serve(&self, port: u16) -> io::Result<DashboardServer> {
+        let dashboard = self.clone();
+        let bound = Arc::new(OnceLock::new());
+        let own_port = Arc::clone(&bound);
+        let server = HttpServer::start((Ipv4Addr::LOCALHOST, port), move |request| {
+            if !own_port.get().is_some_and(|port| is_own(request, *port)) {
+                return Response::new(403, "text/plain", "not a request from the dashboard");
+            }
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/") => Response::new(200, "text/html; charset=utf-8", dashboard.html()),
+                ("GET", "/status.json") => {
+                    // Unwrapping is safe here because the status only has string keys.
+                    let json = serde_json::to_vec(&dashboard.status()).unwrap();
+                    Response::new(200, "application/json", json)
+                }
+                ("POST", "/pause" | "/resume") => {
+                    if request.path == "/pause" {
+                        dashboard.pause();
+                    } else {
+                        dashboard.resume();
+                    }
+                    let mut response = Response::empty(303);
+                    response
+                        .headers
+                        .push(("Location".to_string(), "/".to_string()));
+                    response
+                }
+                (_, "/" | "/status.json" | "/pause" | "/resume") => Response::empty(405),
+                _ => Response::empty(404),
+            }
+        })?;
+        // Requests are refused until the port is known, which is right away.
+        let _ = bound.set(server.address().port());
+        Ok(DashboardServer { server })
+    }
+
+    fn html(&self) -> String {
+        let status = self.status();
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+             <meta http-equiv=\"refresh\" content=\"5\"><title>Kirby crawl</title></head><body>\n",
+        );
+        let (state, action, label) = if status.paused {
+            ("Paused", "/resume", "Resume")
+        } else {
+            ("Running", "/pause", "Pause")
+        };
+        let _ = writeln!(
+            html,
+            "<h1>{state}</h1>\n<form method=\"post\" action=\"{action}\"><button>{label}</button></form>"
+        );
+        let _ = writeln!(
+            html,
+            "<p>{} responses, {} bytes. Last minute: {} responses, {} bytes.</p>",
+            status.responses, status.bytes, status.responses_per_minute, status.bytes_per_minute
+        );
+        let progress = &status.progress;
+        let eta = match (progress.eta_best_secs, progress.eta_secs) {
+            (Some(best), Some(expected)) if best == expected => format!("ETA {}", duration(best)),
+            (Some(best), Some(expected)) => {
+                format!("ETA {} to {}", duration(best), duration(expected))
+            }
+            (Some(best), None) => format!(
+                "ETA at least {}, the frontier grows faster than it's crawled",
+                duration(best)
+            ),
+            _ => "no ETA yet".to_string(),
+        };
+        let _ = writeln!(
+            html,
+            "<p>{} of {} URLs done ({:.1}%), {eta}.</p>",
+            progress.completed,
+            progress.discovered,
+            progress.fraction * 100.0
+        );
+
+        html.push_str("<h2>Queues</h2>\n<table><tr><th>Host</th><th>Queued</th></tr>\n");
+        for (host, depth) in &status.queues {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{depth}</td></tr>",
+                escape(host.as_str())
+            );
+        }
+        html.push_str("</table>\n<h2>Recent errors</h2>\n<table><tr><th>Time</th><th>URL</th><th>Error</th></tr>\n");
+        for error in &status.recent_errors {
+            let url = error.error.url().map_or("", Url::as_str);
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                error.at.format("%Y-%m-%d %H:%M:%S"),
+                escape(url),
+                escape(error.error.to_string().as_str())
+            );
+        }
+        html.push_str("</table>\n</body></html>\n");
+        html
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Every update leaves the state consistent, so it's still usable after a panic.
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+/// Whether a request was sent to the dashboard on `port` from the dashboard's own page, see
+/// [`Dashboard::serve`]. POSTs from clients that send neither `Origin` nor `Referer`, which
+/// browsers do, are allowed.
+fn is_own(request: &Request, port: u16) -> bool {
+    let own = |url: &Url| {
+        url.scheme() == "http"
+            && matches!(url.host_str(), Some("127.0.0.1" | "localhost"))
+            && url.port_or_known_default() == Some(port)
+    };
+    let host = request
+        .header("host")
+        .and_then(|host| Url::parse(&format!("http://{host}/")).ok());
+    if !host.is_some_and(|host| own(&host)) {
+        return false;
+    }
+    if request.method != "POST" {
+        return true;
+    }
+    match request
+        .header("origin")
+        .or_else(|| request.header("referer"))
+    {
+        Some(source) => Url::parse(source).is_ok_and(|source| own(&source)),
+        None => true,
+    }
+}
+
+/// Takes one off a host's count, removing the host at 0.
+fn decrement(counts: &mut BTreeMap<String, u64>, host: &str) {
+    if let Some(count) = counts.get_mut(host) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(host);
+        }
+    }
+}
+
+/// E.g. `1h 5m`, or `40s` under a minute.
+fn duration(seconds: u64) -> String {
+    match (seconds / 3600, seconds / 60 % 60) {
+        (0, 0) => format!("{seconds}s"),
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+/// The HTTP server started by [`Dashboard::serve`], stopped when dropped.
+pub struct DashboardServer {
+    server: HttpServer,
+}
+
+impl DashboardServer {
+    pub fn address(&self) -> SocketAddr {
+        self.server.address()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::error::ErrorKind;
+
+    /// Sends a request with the given request line and headers, and the Host of the server.
+    fn request(server: &DashboardServer, request: &str) -> String {
+        let host = format!("Host: localhost:{}\r\n", server.address().port());
+        send(server, &request.replacen("\r\n", &format!("\r\n{host}"), 1))
+    }
+
+    fn send(server: &DashboardServer, request: &str) -> String {
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn shows_queues_and_errors_and_pauses() {
+        let dashboard = Dashboard::new();
+        let url = |s: &str| Url::parse(s).unwrap();
+        for page in [
+            "https://a.example/1",
+            "https://a.example/2",
+            "https://b.example/",
+        ] {
+            dashboard.observe(&CrawlEvent::UrlEnqueued {
+                url: url(page),
+                depth: 1,
+            });
+        }
+        for attempt in [1, 2] {
+            dashboard.observe(&CrawlEvent::FetchStarted {
+                url: url("https://b.example/"),
+                attempt,
+            });
+        }
+        dashboard.observe(&CrawlEvent::FetchFinished {
+            url: url("https://b.example/"),
+            status: 200,
+            duration: Duration::from_millis(10),
+            bytes: 512,
+        });
+        let error = KirbyError::new(ErrorKind::Parse, "<html> expected");
+        dashboard.observe(&CrawlEvent::Error { error });
+
+        let server = dashboard.serve(0).unwrap();
+        assert!(server.address().ip().is_loopback());
+        let page = request(&server, "GET / HTTP/1.1\r\n\r\n");
+        assert!(page.contains("<h1>Running</h1>"));
+        assert!(page.contains("<tr><td>a.example</td><td>2</td></tr>"));
+        assert!(!page.contains("b.example"));
+        assert!(page.contains("<td>parsing failed: &lt;html&gt; expected</td>"));
+        assert!(page.contains("<p>2 of 3 URLs done (66.7%), no ETA yet.</p>"));
+
+        let pause = request(&server, "POST /pause HTTP/1.1\r\nContent-Length: 0\r\n\r\n");
+        assert!(pause.starts_with("HTTP/1.1 303 See Other\r\n"));
+        assert!(pause.contains("\r\nLocation: /\r\n"));
+        assert!(dashboard.is_paused());
+
+        let json = request(&server, "GET /status.json HTTP/1.1\r\n\r\n");
+        let body = json.split("\r\n\r\n").nth(1).unwrap();
+        let status: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(status["paused"], true);
+        assert_eq!(status["bytes_per_minute"], 512);
+    }
+
+    #[test]
+    fn refuses_requests_from_other_sites() {
+        let dashboard = Dashboard::new();
+        let server = dashboard.serve(0).unwrap();
+        let port = server.address().port();
+
+        let rebound = send(&server, "GET / HTTP/1.1\r\nHost: evil.example\r\n\r\n");
+        assert!(rebound.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        let other_port = send(&server, "GET / HTTP/1.1\r\nHost: localhost:1\r\n\r\n");
+        assert!(other_port.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        let no_host = send(&server, "GET / HTTP/1.1\r\n\r\n");
+        assert!(no_host.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+        for source in [
+            "Origin: https://evil.example",
+            "Origin: null",
+            "Referer: http://evil.example/dashboard",
+        ] {
+            let pause = request(
+                &server,
+                &format!("POST /pause HTTP/1.1\r\n{source}\r\n\r\n"),
+            );
+            assert!(pause.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{source}");
+        }
+        assert!(!dashboard.is_paused());
+
+        let own = format!("Origin: http://127.0.0.1:{port}");
+        let pause = request(&server, &format!("POST /pause HTTP/1.1\r\n{own}\r\n\r\n"));
+        assert!(pause.starts_with("HTTP/1.1 303 See Other\r\n"));
+        assert!(dashboard.is_paused());
+    }
+}
@@ -0,0 +1,27 @@
+pub mod digest;
+pub mod imagehash;
+pub mod minhash;
+pub mod policy;
+pub mod simhash;
+
+/// Splits text into lowercase words, punctuation is dropped.
+pub(crate) fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// A 64-bit hash that is stable across runs and platforms, so fingerprints can be stored and
+/// compared later. FNV-1a followed by the SplitMix64 finalizer to spread the bits.
+pub(crate) fn stable_hash(bytes: &[u8], seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use scraper::Html;
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::extract::{collapse_whitespace, visible_text};
+
+/// The hash function used for content digests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DigestAlgorithm {
+    #[default]
+    Sha256,
+    /// 64-bit XXH3, much faster than SHA-256 but not collision resistant against crafted input.
+    #[cfg(feature = "xxhash")]
+    Xxh3,
+}
+
+impl DigestAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            #[cfg(feature = "xxhash")]
+            Self::Xxh3 => "xxh3",
+        }
+    }
+}
+
+/// A digest of canonicalized page content, displayed and serialized as `algorithm:hex`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentDigest {
+    algorithm: DigestAlgorithm,
+    bytes: Vec<u8>,
+}
+
+impl ContentDigest {
+    /// Hashes content as-is.
+    pub fn compute(algorithm: DigestAlgorithm, content: &[u8]) -> Self {
+        let bytes = match algorithm {
+            DigestAlgorithm::Sha256 => Sha256::digest(content).to_vec(),
+            #[cfg(feature = "xxhash")]
+            DigestAlgorithm::Xxh3 => xxhash_rust::xxh3::xxh3_64(content).to_be_bytes().to_vec(),
+        };
+        Self { algorithm, bytes }
+    }
+
+    /// Hashes text after canonicalizing it with [`canonicalize_text`].
+    pub fn of_text(algorithm: DigestAlgorithm, text: &str) -> Self {
+        Self::compute(algorithm, canonicalize_text(text).as_bytes())
+    }
+
+    /// Hashes the visible text of an HTML document, see [`canonicalize_html`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kirby_core::dedup::digest::{ContentDigest, DigestAlgorithm};
+    ///
+    /// let a = ContentDigest::of_html(DigestAlgorithm::Sha256, "<p>Hello   <b>world</b></p>");
+    /// let b = ContentDigest::of_html(
+    ///     DigestAlgorithm::Sha256,
+    ///     "<html><head><script>track()</script></head><body><p>Hello world</p></body></html>",
+    /// );
+    /// assert_eq!(a, b);
+    /// assert!(a.to_string().starts_with("sha256:"));
+    /// ```
+    pub fn of_html(algorithm: DigestAlgorithm, html: &str) -> Self {
+        Self::compute(algorithm, canonicalize_html(html).as_bytes())
+    }
+
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The digest as lowercase hexadecimal.
+    pub fn hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+impl fmt::Display for ContentDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm.name(), self.hex())
+    }
+}
+
+impl Serialize for ContentDigest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Canonicalizes text so insignificant differences don't change its digest: whitespace runs are
+/// collapsed to a single space and leading or trailing whitespace is removed.
+pub fn canonicalize_text(text: &str) -> String {
+    collapse_whitespace(text)
+}
+
+/// Canonicalizes an HTML document into its visible text, ignoring markup, scripts, styles and
+/// whitespace so pages that only differ in those are considered identical.
+pub fn canonicalize_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+    canonicalize_text(&visible_text(document.root_element()))
+}
+
+/// Whether a page's content was seen before.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DigestMatch {
+    /// The first page with this content.
+    Unique,
+    /// Another URL already had the same content.
+    Duplicate { original: Url },
+}
+
+impl DigestMatch {
+    pub fn is_duplicate(&self) -> bool {
+        matches!(self, Self::Duplicate { .. })
+    }
+}
+
+/// The URLs sharing one digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateCluster {
+    pub digest: ContentDigest,
+    /// The first URL seen with this content.
+    pub original: Url,
+    /// The other URLs, in the order they were added.
+    pub duplicates: Vec<Url>,
+}
+
+/// Duplicate statistics for a crawl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct DuplicateStats {
+    /// Distinct URLs added to the index.
+    pub pages: usize,
+    /// Distinct digests.
+    pub unique: usize,
+    /// Pages whose content matched an earlier page.
+    pub duplicates: usize,
+    /// Digests shared by more than one URL.
+    pub clusters: usize,
+    /// The number of URLs in the largest cluster.
+    pub largest_cluster: usize,
+}
+
+/// An index of content digest -> URLs used to detect exact duplicate pages during a crawl.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::dedup::digest::{DigestIndex, DigestMatch};
+/// use url::Url;
+///
+/// let mut index = DigestIndex::default();
+/// let page = Url::parse("https://example.com/post").unwrap();
+/// let print = Url::parse("https://example.com/post?print=1").unwrap();
+///
+/// assert_eq!(index.add_html(&page, "<p>Hello</p>"), DigestMatch::Unique);
+/// assert_eq!(
+///     index.add_html(&print, "<body><p>Hello</p></body>"),
+///     DigestMatch::Duplicate { original: page.clone() }
+/// );
+/// assert_eq!(index.stats().duplicates, 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DigestIndex {
+    algorithm: DigestAlgorithm,
+    /// Mapping of digest -> position in `clusters`.
+    by_digest: HashMap<ContentDigest, usize>,
+    /// Mapping of URL -> the digest of its latest content.
+    by_url: HashMap<Url, ContentDigest>,
+    clusters: Vec<DuplicateCluster>,
+}
+
+impl DigestIndex {
+    pub fn new(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            algorithm,
+            ..Default::default()
+        }
+    }
+
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    /// Digests an HTML page and adds it, see [`DigestIndex::add`].
+    pub fn add_html(&mut self, url: &Url, html: &str) -> DigestMatch {
+        let digest = ContentDigest::of_html(self.algorithm, html);
+        self.add(url, digest)
+    }
+
+    /// Records the digest of a page's content, returning whether another URL already had it.
+    ///
+    /// Adding a URL again with unchanged content gives the same answer as the first time, when
+    /// its content changed it is moved to the cluster of its new digest.
+    pub fn add(&mut self, url: &Url, digest: ContentDigest) -> DigestMatch {
+        if let Some(previous) = self.by_url.get(url) {
+            if *previous == digest {
+                return match self.original(&digest) {
+                    Some(original) if original != url => DigestMatch::Duplicate {
+                        original: original.clone(),
+                    },
+                    _ => DigestMatch::Unique,
+                };
+            }
+            let previous = previous.clone();
+            self.remove_from_cluster(url, &previous);
+        }
+        self.by_url.insert(url.clone(), digest.clone());
+
+        if let Some(&position) = self.by_digest.get(&digest) {
+            let cluster = &mut self.clusters[position];
+            cluster.duplicates.push(url.clone());
+            return DigestMatch::Duplicate {
+                original: cluster.original.clone(),
+            };
+        }
+
+        self.by_digest.insert(digest.clone(), self.clusters.len());
+        self.clusters.push(DuplicateCluster {
+            digest,
+            original: url.clone(),
+            duplicates: Vec::new(),
+        });
+        DigestMatch::Unique
+    }
+
+    fn remove_from_cluster(&mut self, url: &Url, digest: &ContentDigest) {
+        let Some(&position) = self.by_digest.get(digest) else {
+            return;
+        };
+        let cluster = &mut self.clusters[position];
+        if cluster.original == *url {
+            // The earliest duplicate becomes the representative. A cluster left without URLs
+            // stays in place so positions remain stable, it is skipped when reporting.
+            if cluster.duplicates.is_empty() {
+                self.by_digest.remove(digest);
+                return;
+            }
+            cluster.original = cluster.duplicates.remove(0);
+        } else {
+            cluster.duplicates.retain(|u| u != url);
+        }
+    }
+
+    /// The first URL seen with the given content.
+    pub fn original(&self, digest: &ContentDigest) -> Option<&Url> {
+        let position = self.by_digest.get(digest)?;
+        Some(&self.clusters[*position].original)
+    }
+
+    /// The digest of the latest content added for a URL.
+    pub fn digest(&self, url: &Url) -> Option<&ContentDigest> {
+        self.by_url.get(url)
+    }
+
+    /// Groups of URLs sharing the same content, in the order their content was first seen.
+    pub fn clusters(&self) -> impl Iterator<Item = &DuplicateCluster> {
+        self.clusters
+            .iter()
+            .filter(|cluster| self.by_digest.contains_key(&cluster.digest))
+            .filter(|cluster| !cluster.duplicates.is_empty())
+    }
+
+    pub fn stats(&self) -> DuplicateStats {
+        let duplicates = self
+            .clusters
+            .iter()
+            .map(|c| c.duplicates.len())
+            .sum::<usize>();
+        let sizes = self
+            .clusters()
+            .map(|c| c.duplicates.len() + 1)
+            .collect::<Vec<usize>>();
+
+        DuplicateStats {
+            pages: self.by_url.len(),
+            unique: self.by_digest.len(),
+            duplicates,
+            clusters: sizes.len(),
+            largest_cluster: sizes.into_iter().max().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn canonicalizes_content() {
+        assert_eq!(canonicalize_text("  a \n\t b  "), "a b");
+        assert_eq!(
+            canonicalize_html("<style>p{}</style><div>Hi</div><p>there</p>"),
+            "Hi there"
+        );
+
+        let digest = ContentDigest::of_text(DigestAlgorithm::Sha256, "hello");
+        assert_eq!(
+            digest.to_string(),
+            "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(
+            serde_json::to_value(&digest).unwrap(),
+            serde_json::json!(digest.to_string())
+        );
+    }
+
+    #[test]
+    fn tracks_duplicates_and_stats() {
+        let mut index = DigestIndex::default();
+        let (a, b, c, d) = (
+            url("https://example.com/a"),
+            url("https://example.com/b"),
+            url("https://example.com/c"),
+            url("https://example.com/d"),
+        );
+
+        assert_eq!(index.add_html(&a, "one"), DigestMatch::Unique);
+        assert!(index.add_html(&b, "one").is_duplicate());
+        assert!(index.add_html(&c, "  one ").is_duplicate());
+        assert_eq!(index.add_html(&d, "two"), DigestMatch::Unique);
+        assert_eq!(index.add_html(&a, "one"), DigestMatch::Unique);
+        assert!(index.add_html(&b, "one").is_duplicate());
+
+        assert_eq!(
+            index.stats(),
+            DuplicateStats {
+                pages: 4,
+                unique: 2,
+                duplicates: 2,
+                clusters: 1,
+                largest_cluster: 3,
+            }
+        );
+
+        // `a` changed, so `b` becomes the representative of its old content.
+        assert_eq!(
+            index.add_html(&a, "two"),
+            DigestMatch::Duplicate {
+                original: d.clone()
+            }
+        );
+        let clusters = index.clusters().collect::<Vec<&DuplicateCluster>>();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].original, b);
+        assert_eq!(clusters[0].duplicates, vec![c.clone()]);
+        assert_eq!(clusters[1].original, d);
+        assert_eq!(clusters[1].duplicates, vec![a.clone()]);
+        assert_eq!(index.original(index.digest(&c).unwrap()), Some(&b));
+    }
+
+    #[cfg(feature = "xxhash")]
+    #[test]
+    fn supports_xxh3() {
+        let digest = ContentDigest::of_text(DigestAlgorithm::Xxh3, "hello");
+        assert_eq!(digest.as_bytes().len(), 8);
+        assert!(digest.to_string().starts_with("xxh3:"));
+    }
+}
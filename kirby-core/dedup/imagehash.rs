@@ -0,0 +1,327 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::simhash::{NearDuplicate, SimHash, SimHashIndex};
+
+/// A grayscale image, the input of the perceptual hashes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LumaImage {
+    width: u32,
+    height: u32,
+    /// Row-major luma values, `width * height` of them.
+    pixels: Vec<u8>,
+}
+
+impl LumaImage {
+    /// Wraps row-major luma values, `None` when the buffer doesn't match the dimensions or the
+    /// image is empty.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Converts row-major RGB values to luma using the ITU-R BT.601 weights.
+    pub fn from_rgb(width: u32, height: u32, rgb: &[u8]) -> Option<Self> {
+        if rgb.len() != width as usize * height as usize * 3 {
+            return None;
+        }
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|p| {
+                let luma =
+                    0.299 * f64::from(p[0]) + 0.587 * f64::from(p[1]) + 0.114 * f64::from(p[2]);
+                luma.round() as u8
+            })
+            .collect();
+        Self::new(width, height, pixels)
+    }
+
+    /// Decodes a PNG, JPEG, GIF or WebP image.
+    #[cfg(feature = "images")]
+    pub fn decode(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        let image = image::load_from_memory(bytes)?.into_luma8();
+        let (width, height) = image.dimensions();
+        Self::new(width, height, image.into_raw()).ok_or_else(|| {
+            image::ImageError::Limits(image::error::LimitError::from_kind(
+                image::error::LimitErrorKind::DimensionError,
+            ))
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Shrinks the image by averaging the source pixels covered by each target pixel.
+    fn resize(&self, width: usize, height: usize) -> Vec<f64> {
+        let (source_width, source_height) = (self.width as usize, self.height as usize);
+        let mut resized = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let y0 = y * source_height / height;
+            let y1 = ((y + 1) * source_height / height).max(y0 + 1);
+            for x in 0..width {
+                let x0 = x * source_width / width;
+                let x1 = ((x + 1) * source_width / width).max(x0 + 1);
+
+                let mut sum = 0u64;
+                for row in y0..y1 {
+                    let start = row * source_width;
+                    sum += self.pixels[start + x0..start + x1]
+                        .iter()
+                        .map(|p| u64::from(*p))
+                        .sum::<u64>();
+                }
+                resized.push(sum as f64 / ((x1 - x0) * (y1 - y0)) as f64);
+            }
+        }
+        resized
+    }
+}
+
+/// A 64-bit perceptual image hash, resized or re-encoded copies of an image have hashes that
+/// differ in few bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ImageHash(pub u64);
+
+impl ImageHash {
+    /// The difference hash: each bit records whether a pixel is brighter than its right
+    /// neighbour in a 9x8 thumbnail. Fast and robust against scaling and compression.
+    pub fn dhash(image: &LumaImage) -> Self {
+        let pixels = image.resize(9, 8);
+        let mut hash = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                let bit = pixels[y * 9 + x] > pixels[y * 9 + x + 1];
+                hash = hash << 1 | u64::from(bit);
+            }
+        }
+        Self(hash)
+    }
+
+    /// The DCT based perceptual hash: each bit records whether one of the 8x8 lowest frequencies
+    /// of a 32x32 thumbnail is above their median. Slower than [`ImageHash::dhash`] but also
+    /// robust against brightness and contrast changes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kirby_core::dedup::imagehash::{ImageHash, LumaImage};
+    ///
+    /// let gradient = |size: u32| {
+    ///     let pixels = (0..size * size)
+    ///         .map(|i| ((i % size) * 255 / size) as u8 ^ ((i / size) * 64 / size) as u8)
+    ///         .collect();
+    ///     LumaImage::new(size, size, pixels).unwrap()
+    /// };
+    ///
+    /// let original = ImageHash::phash(&gradient(256));
+    /// let thumbnail = ImageHash::phash(&gradient(64));
+    /// assert!(original.distance(thumbnail) <= 6);
+    /// ```
+    pub fn phash(image: &LumaImage) -> Self {
+        const SIZE: usize = 32;
+        const LOW: usize = 8;
+
+        let pixels = image.resize(SIZE, SIZE);
+        let coefficients = dct_low_frequencies(&pixels, SIZE, LOW);
+
+        // The DC term is the average brightness, it's left out of the median.
+        let mut sorted = coefficients[1..].to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let median = (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0;
+
+        let hash = coefficients
+            .iter()
+            .fold(0u64, |hash, c| hash << 1 | u64::from(*c > median));
+        Self(hash)
+    }
+
+    /// The number of differing bits.
+    pub fn distance(self, other: ImageHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl fmt::Display for ImageHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The `low x low` lowest frequency coefficients of a 2D DCT-II, row-major.
+fn dct_low_frequencies(pixels: &[f64], size: usize, low: usize) -> Vec<f64> {
+    let cosines = (0..low)
+        .map(|u| {
+            (0..size)
+                .map(|x| ((2 * x + 1) as f64 * u as f64 * PI / (2 * size) as f64).cos())
+                .collect::<Vec<f64>>()
+        })
+        .collect::<Vec<Vec<f64>>>();
+
+    // Transform the rows first, then the columns of the result.
+    let rows = (0..size)
+        .map(|y| {
+            (0..low)
+                .map(|u| {
+                    (0..size)
+                        .map(|x| pixels[y * size + x] * cosines[u][x])
+                        .sum()
+                })
+                .collect::<Vec<f64>>()
+        })
+        .collect::<Vec<Vec<f64>>>();
+
+    cosines
+        .iter()
+        .flat_map(|column_cosines| {
+            let rows = &rows;
+            (0..low).map(move |u| {
+                rows.iter()
+                    .zip(column_cosines)
+                    .map(|(row, cosine)| row[u] * cosine)
+                    .sum()
+            })
+        })
+        .collect()
+}
+
+/// Finds near-duplicate images, e.g. resized or re-encoded copies, by the Hamming distance of
+/// their hashes. Uses the same block tables as [`SimHashIndex`].
+#[derive(Debug, Clone)]
+pub struct ImageHashIndex {
+    index: SimHashIndex,
+}
+
+impl Default for ImageHashIndex {
+    /// An index matching hashes up to 6 bits apart.
+    fn default() -> Self {
+        Self::new(6)
+    }
+}
+
+impl ImageHashIndex {
+    pub fn new(max_distance: u32) -> Self {
+        Self {
+            index: SimHashIndex::new(max_distance),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Earlier images within the maximum distance of a hash, closest first.
+    pub fn find(&self, hash: ImageHash) -> Vec<NearDuplicate> {
+        self.index.find(SimHash(hash.0))
+    }
+
+    /// Adds an image, returning the closest earlier image within the maximum distance so the
+    /// copy doesn't need to be stored again.
+    pub fn insert(&mut self, url: &Url, hash: ImageHash) -> Option<NearDuplicate> {
+        self.index.insert(url, SimHash(hash.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A synthetic picture: a bright disc on a darker, shaded background.
+    fn picture(width: u32, height: u32) -> LumaImage {
+        let pixels = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| {
+                    let (fx, fy) = (x as f64 / width as f64, y as f64 / height as f64);
+                    let in_disc = (fx - 0.35).powi(2) + (fy - 0.4).powi(2) < 0.06;
+                    if in_disc {
+                        230
+                    } else {
+                        (40.0 + 120.0 * fx * fy) as u8
+                    }
+                })
+            })
+            .collect();
+        LumaImage::new(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn hashes_survive_resizing_and_brightness_changes() {
+        let original = picture(400, 300);
+        let resized = picture(120, 90);
+        let brighter = LumaImage::new(
+            400,
+            300,
+            original
+                .pixels
+                .iter()
+                .map(|p| p.saturating_add(20))
+                .collect(),
+        )
+        .unwrap();
+        let different =
+            LumaImage::new(400, 300, original.pixels.iter().rev().copied().collect()).unwrap();
+
+        for hash in [ImageHash::dhash, ImageHash::phash] {
+            assert!(hash(&original).distance(hash(&resized)) <= 6);
+            assert!(hash(&original).distance(hash(&brighter)) <= 6);
+            assert!(hash(&original).distance(hash(&different)) > 16);
+        }
+
+        assert_eq!(LumaImage::new(2, 2, vec![0; 3]), None);
+        assert_eq!(
+            LumaImage::from_rgb(1, 1, &[255, 255, 255]).unwrap().pixels,
+            vec![255]
+        );
+    }
+
+    #[test]
+    fn indexes_near_duplicate_images() {
+        let mut index = ImageHashIndex::default();
+        let original = Url::parse("https://example.com/a.jpg").unwrap();
+        assert_eq!(
+            index.insert(&original, ImageHash::phash(&picture(400, 300))),
+            None
+        );
+
+        let copy = index
+            .insert(
+                &Url::parse("https://cdn.example.com/a-small.jpg").unwrap(),
+                ImageHash::phash(&picture(200, 150)),
+            )
+            .unwrap();
+        assert_eq!(copy.url, original);
+        assert_eq!(index.len(), 2);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn decodes_images() {
+        let mut png = Vec::new();
+        image::GrayImage::from_raw(2, 1, vec![10, 200])
+            .unwrap()
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let decoded = LumaImage::decode(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 1));
+        assert_eq!(decoded.pixels, vec![10, 200]);
+        assert!(LumaImage::decode(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{stable_hash, words};
+
+/// Builds MinHash signatures from word shingles, estimating the Jaccard similarity of documents
+/// without keeping their text around.
+///
+/// Hash functions are derived from fixed seeds so signatures made with the same `num_hashes` and
+/// `shingle_size` can be compared across separate crawl runs.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::dedup::minhash::MinHasher;
+///
+/// let hasher = MinHasher::new(128, 3);
+/// let a = hasher.signature("the quick brown fox jumps over the lazy dog near the river bank");
+/// let b = hasher.signature("the quick brown fox jumps over the lazy dog near the river shore");
+///
+/// let similarity = a.similarity(&b).unwrap();
+/// assert!(similarity > 0.6 && similarity < 1.0);
+///
+/// // Signatures can be stored and compared later.
+/// let stored = serde_json::to_string(&a).unwrap();
+/// let restored = serde_json::from_str(&stored).unwrap();
+/// assert_eq!(a, restored);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinHasher {
+    shingle_size: usize,
+    seeds: Vec<u64>,
+}
+
+impl Default for MinHasher {
+    /// 128 hash functions over 5-word shingles.
+    fn default() -> Self {
+        Self::new(128, 5)
+    }
+}
+
+impl MinHasher {
+    /// Creates a hasher with `num_hashes` hash functions over `shingle_size`-word shingles, more
+    /// hashes give a more accurate similarity estimate.
+    pub fn new(num_hashes: usize, shingle_size: usize) -> Self {
+        let seeds = (0..num_hashes as u64)
+            .map(|i| stable_hash(&i.to_le_bytes(), 0x6d69_6e68_6173_6800))
+            .collect();
+        Self {
+            shingle_size: shingle_size.max(1),
+            seeds,
+        }
+    }
+
+    pub fn num_hashes(&self) -> usize {
+        self.seeds.len()
+    }
+
+    pub fn shingle_size(&self) -> usize {
+        self.shingle_size
+    }
+
+    /// The hashed w-shingles of a text: every run of `shingle_size` consecutive lowercase words,
+    /// or the whole text when it has fewer words.
+    pub fn shingles(&self, text: &str) -> HashSet<u64> {
+        let words = words(text);
+        if words.is_empty() {
+            return HashSet::new();
+        }
+
+        words
+            .windows(self.shingle_size.min(words.len()))
+            .map(|shingle| stable_hash(shingle.join(" ").as_bytes(), 0))
+            .collect()
+    }
+
+    pub fn signature(&self, text: &str) -> MinHashSignature {
+        self.signature_of_shingles(&self.shingles(text))
+    }
+
+    /// The signature of already hashed shingles, for callers with their own features.
+    pub fn signature_of_shingles(&self, shingles: &HashSet<u64>) -> MinHashSignature {
+        let values = self
+            .seeds
+            .iter()
+            .map(|seed| {
+                shingles
+                    .iter()
+                    .map(|shingle| stable_hash(&shingle.to_le_bytes(), *seed))
+                    .min()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+
+        MinHashSignature {
+            shingle_size: self.shingle_size,
+            values,
+        }
+    }
+}
+
+/// A MinHash signature, serializable so it can be stored with a crawl and compared later.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MinHashSignature {
+    pub shingle_size: usize,
+    pub values: Vec<u64>,
+}
+
+impl MinHashSignature {
+    /// The estimated Jaccard similarity of the two documents, `None` when the signatures were
+    /// made with different parameters and can't be compared.
+    pub fn similarity(&self, other: &MinHashSignature) -> Option<f64> {
+        if self.shingle_size != other.shingle_size
+            || self.values.len() != other.values.len()
+            || self.values.is_empty()
+        {
+            return None;
+        }
+
+        let matching = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .filter(|(a, b)| a == b)
+            .count();
+        Some(matching as f64 / self.values.len() as f64)
+    }
+}
+
+/// The exact Jaccard similarity of two shingle sets.
+pub fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / a.union(b).count() as f64
+}
+
+/// Locality sensitive hashing over MinHash signatures: signatures are split into bands of rows
+/// and documents sharing any band become candidates, so similar documents can be found in a large
+/// corpus without comparing every pair.
+///
+/// Documents with a similarity above roughly [`LshIndex::threshold`] are likely to become
+/// candidates, more bands lower the threshold and more rows raise it.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::dedup::minhash::{LshIndex, MinHasher};
+///
+/// let hasher = MinHasher::new(64, 2);
+/// let mut index = LshIndex::new(16, 4);
+/// index.insert("a", hasher.signature("kirby floats over dream land on a warp star"));
+/// index.insert("b", hasher.signature("bake the bread until the crust is golden"));
+///
+/// let query = hasher.signature("kirby floats over dream land on a warp star today");
+/// let similar = index.similar(&query, 0.5);
+/// assert_eq!(similar.len(), 1);
+/// assert_eq!(*similar[0].0, "a");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LshIndex<K> {
+    bands: usize,
+    rows: usize,
+    /// Mapping of band hash -> positions in `entries`, one table per band.
+    buckets: Vec<HashMap<u64, Vec<usize>>>,
+    entries: Vec<(K, MinHashSignature)>,
+}
+
+impl<K> LshIndex<K> {
+    /// Creates an index for signatures of `bands * rows` values.
+    pub fn new(bands: usize, rows: usize) -> Self {
+        let bands = bands.max(1);
+        Self {
+            bands,
+            rows: rows.max(1),
+            buckets: vec![HashMap::new(); bands],
+            entries: Vec::new(),
+        }
+    }
+
+    /// The similarity at which documents have a 50% chance of becoming candidates.
+    pub fn threshold(&self) -> f64 {
+        (1.0 / self.bands as f64).powf(1.0 / self.rows as f64)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn band_hashes<'a>(
+        &'a self,
+        signature: &'a MinHashSignature,
+    ) -> impl Iterator<Item = u64> + 'a {
+        signature
+            .values
+            .chunks(self.rows)
+            .take(self.bands)
+            .map(|band| {
+                let bytes = band
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect::<Vec<u8>>();
+                stable_hash(&bytes, 0)
+            })
+    }
+
+    /// Adds a document's signature to the index.
+    pub fn insert(&mut self, key: K, signature: MinHashSignature) {
+        let position = self.entries.len();
+        let hashes = self.band_hashes(&signature).collect::<Vec<u64>>();
+        for (bucket, hash) in self.buckets.iter_mut().zip(hashes) {
+            bucket.entry(hash).or_default().push(position);
+        }
+        self.entries.push((key, signature));
+    }
+
+    /// Documents sharing at least one band with the signature, in insertion order.
+    pub fn candidates(&self, signature: &MinHashSignature) -> Vec<&K> {
+        self.candidate_positions(signature)
+            .into_iter()
+            .map(|position| &self.entries[position].0)
+            .collect()
+    }
+
+    fn candidate_positions(&self, signature: &MinHashSignature) -> Vec<usize> {
+        let mut positions = self
+            .band_hashes(signature)
+            .zip(&self.buckets)
+            .filter_map(|(hash, bucket)| bucket.get(&hash))
+            .flatten()
+            .copied()
+            .collect::<Vec<usize>>();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    /// Candidates whose estimated similarity is at least `min_similarity`, most similar first.
+    pub fn similar(&self, signature: &MinHashSignature, min_similarity: f64) -> Vec<(&K, f64)> {
+        let mut similar = self
+            .candidate_positions(signature)
+            .into_iter()
+            .filter_map(|position| {
+                let (key, other) = &self.entries[position];
+                let similarity = signature.similarity(other)?;
+                (similarity >= min_similarity).then_some((key, similarity))
+            })
+            .collect::<Vec<(&K, f64)>>();
+        similar.sort_by(|a, b| b.1.total_cmp(&a.1));
+        similar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_jaccard_similarity() {
+        let hasher = MinHasher::new(256, 2);
+        let a = "one two three four five six seven eight nine ten";
+        let b = "one two three four five six seven eight eleven twelve";
+
+        let exact = jaccard(&hasher.shingles(a), &hasher.shingles(b));
+        let estimate = hasher
+            .signature(a)
+            .similarity(&hasher.signature(b))
+            .unwrap();
+        assert!((exact - estimate).abs() < 0.15, "{exact} vs {estimate}");
+
+        assert_eq!(hasher.shingles("Hi, there!"), hasher.shingles("hi there"));
+        assert_eq!(hasher.shingles("single").len(), 1);
+        assert_eq!(
+            hasher.signature(a).similarity(&hasher.signature(a)),
+            Some(1.0)
+        );
+
+        // Signatures are stable across hasher instances, but not across parameters.
+        assert_eq!(MinHasher::new(256, 2).signature(a), hasher.signature(a));
+        assert_eq!(
+            MinHasher::new(256, 3)
+                .signature(a)
+                .similarity(&hasher.signature(a)),
+            None
+        );
+    }
+
+    #[test]
+    fn buckets_similar_documents() {
+        let hasher = MinHasher::new(100, 3);
+        let mut index = LshIndex::new(20, 5);
+        assert!((index.threshold() - 0.549).abs() < 0.01);
+
+        let base = "the crawler fetches pages from every host in the seed list and stores the \
+                    responses in rotating archive files for later processing";
+        index.insert(1, hasher.signature(base));
+        index.insert(2, hasher.signature(&format!("{base} by the indexer")));
+        index.insert(
+            3,
+            hasher.signature("completely different words about cooking pasta"),
+        );
+
+        let similar = index.similar(&hasher.signature(base), 0.5);
+        let keys = similar.iter().map(|(k, _)| **k).collect::<Vec<i32>>();
+        assert_eq!(keys, vec![1, 2]);
+        assert_eq!(similar[0].1, 1.0);
+        assert!(!index.candidates(&hasher.signature(base)).contains(&&3));
+    }
+}
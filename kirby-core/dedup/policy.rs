@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+use super::digest::{ContentDigest, DigestAlgorithm, DigestIndex, DigestMatch};
+#[cfg(feature = "images")]
+use super::imagehash::LumaImage;
+use super::imagehash::{ImageHash, ImageHashIndex};
+use super::simhash::{SimHash, SimHashIndex};
+use crate::config::DedupConfig;
+use crate::export::Sink;
+use crate::record::PageRecord;
+
+/// Why a URL was folded into another one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum DuplicateReason {
+    /// The URL redirected to another URL.
+    Redirect { target: Url },
+    /// The page declared another URL as canonical.
+    Canonical { canonical: Url },
+    /// The page's content matched an earlier page.
+    Content { digest: ContentDigest },
+    /// The page's text is close to an earlier page's, their SimHashes are `distance` bits
+    /// apart.
+    SimilarText { distance: u32 },
+    /// The image looks like an earlier one, their perceptual hashes are `distance` bits apart.
+    SimilarImage { distance: u32 },
+}
+
+impl fmt::Display for DuplicateReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Redirect { target } => write!(f, "it redirects to {target}"),
+            Self::Canonical { canonical } => write!(f, "its canonical link is {canonical}"),
+            Self::Content { digest } => write!(f, "its content has the same digest ({digest})"),
+            Self::SimilarText { distance } => {
+                write!(f, "its text is {distance} SimHash bits away")
+            }
+            Self::SimilarImage { distance } => {
+                write!(f, "it looks the same, {distance} image hash bits away")
+            }
+        }
+    }
+}
+
+/// The outcome of running a URL through the [`DedupPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DedupDecision {
+    /// The URL represents its document and should be stored.
+    Representative,
+    /// The URL is a duplicate of another one and can be skipped.
+    Duplicate {
+        of: Url,
+        #[serde(flatten)]
+        reason: DuplicateReason,
+    },
+}
+
+impl DedupDecision {
+    pub fn is_duplicate(&self) -> bool {
+        matches!(self, Self::Duplicate { .. })
+    }
+
+    /// Explains the decision for a URL, e.g. "https://a.com/?ref=x is a duplicate of
+    /// https://a.com/ because its canonical link is https://a.com/".
+    pub fn explain(&self, url: &Url) -> String {
+        match self {
+            Self::Representative => format!("{url} is the representative of its document"),
+            Self::Duplicate { of, reason } => {
+                format!("{url} is a duplicate of {of} because {reason}")
+            }
+        }
+    }
+}
+
+/// Folds redirect aliases, canonical links and identical content into a single decision per URL,
+/// so exports contain one representative of every document.
+///
+/// Signals are applied in order of how explicit they are: a redirect beats a canonical link,
+/// which beats matching content, which beats similar text or images when those are compared,
+/// see [`similar_text`](Self::similar_text). Decisions chain, a page whose canonical URL redirects elsewhere
+/// is a duplicate of the redirect's final target. Canonical links are trusted as declared, so
+/// their targets should be crawled for the document to be stored.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::dedup::policy::{DedupDecision, DedupPolicy};
+/// use url::Url;
+///
+/// let url = |s: &str| Url::parse(s).unwrap();
+/// let mut policy = DedupPolicy::default();
+///
+/// policy.add_redirect(&url("http://example.com/"), &url("https://example.com/"));
+/// assert_eq!(
+///     policy.add_page(&url("https://example.com/"), "<p>Home</p>"),
+///     DedupDecision::Representative
+/// );
+///
+/// let decision = policy.add_page(
+///     &url("https://example.com/?utm_source=feed"),
+///     r#"<link rel="canonical" href="/"><p>Home</p>"#,
+/// );
+/// assert_eq!(
+///     decision.explain(&url("https://example.com/?utm_source=feed")),
+///     "https://example.com/?utm_source=feed is a duplicate of https://example.com/ \
+///      because its canonical link is https://example.com/"
+/// );
+///
+/// let representatives = policy.representatives().collect::<Vec<&Url>>();
+/// assert_eq!(representatives, vec![&url("https://example.com/")]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DedupPolicy {
+    /// Mapping of redirecting URL -> the URL it redirects to.
+    redirects: HashMap<Url, Url>,
+    digests: DigestIndex,
+    similar_text: Option<SimHashIndex>,
+    similar_images: Option<ImageHashIndex>,
+    /// Mapping of page URL -> decision, and the order pages were added in.
+    decisions: HashMap<Url, DedupDecision>,
+    order: Vec<Url>,
+}
+
+impl DedupPolicy {
+    pub fn new(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            digests: DigestIndex::new(algorithm),
+            ..Default::default()
+        }
+    }
+
+    /// A policy with the comparisons the configuration turns on.
+    pub fn from_config(config: &DedupConfig) -> Self {
+        let mut policy = Self::default();
+        if let Some(max_distance) = config.similar_text_distance {
+            policy = policy.similar_text(max_distance);
+        }
+        #[cfg(feature = "images")]
+        if let Some(max_distance) = config.similar_image_distance {
+            policy = policy.similar_images(max_distance);
+        }
+        policy
+    }
+
+    /// Also folds HTML pages whose text SimHashes are at most `max_distance` bits apart, e.g.
+    /// mirrors with different boilerplate.
+    pub fn similar_text(mut self, max_distance: u32) -> Self {
+        self.similar_text = Some(SimHashIndex::new(max_distance));
+        self
+    }
+
+    /// Also folds images whose perceptual hashes are at most `max_distance` bits apart, e.g.
+    /// resized or re-encoded copies. Only images added with [`add_record`](Self::add_record)
+    /// are compared.
+    #[cfg(feature = "images")]
+    pub fn similar_images(mut self, max_distance: u32) -> Self {
+        self.similar_images = Some(ImageHashIndex::new(max_distance));
+        self
+    }
+
+    /// Records that `from` redirected to `to`, the redirecting URL becomes a duplicate of the
+    /// redirect's final target.
+    pub fn add_redirect(&mut self, from: &Url, to: &Url) {
+        if from == to {
+            return;
+        }
+        self.redirects.insert(from.clone(), to.clone());
+        self.record(
+            from,
+            DedupDecision::Duplicate {
+                of: self.resolve(to),
+                reason: DuplicateReason::Redirect { target: to.clone() },
+            },
+        );
+    }
+
+    /// Decides whether a fetched HTML page is a duplicate, using its canonical link and content.
+    pub fn add_page(&mut self, url: &Url, html: &str) -> DedupDecision {
+        let canonical = canonical_url(html, url);
+        let digest = ContentDigest::of_html(self.digests.algorithm(), html);
+        let fingerprint = self
+            .similar_text
+            .is_some()
+            .then(|| Fingerprint::Text(SimHash::from_html(html)));
+        self.decide(url, canonical.as_ref(), digest, fingerprint)
+    }
+
+    /// Decides whether a crawled page is a duplicate from its record and body. The redirects
+    /// it followed are added first, then its final URL is decided: HTML like
+    /// [`add_page`](Self::add_page), using the record's canonical URL when it has one, images
+    /// by their pixels when [`similar_images`](Self::similar_images) is on and everything else
+    /// by its bytes.
+    pub fn add_record(&mut self, record: &PageRecord, body: &[u8]) -> DedupDecision {
+        for redirect in &record.redirects {
+            self.add_redirect(&redirect.from, &redirect.to);
+        }
+        let url = record.final_url();
+        let content_type = record
+            .header("content-type")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if content_type.starts_with("text/html") || content_type.contains("xhtml") {
+            let html = String::from_utf8_lossy(body);
+            let canonical = record
+                .canonical_url
+                .clone()
+                .or_else(|| canonical_url(&html, url));
+            let digest = ContentDigest::of_html(self.digests.algorithm(), &html);
+            let fingerprint = self
+                .similar_text
+                .is_some()
+                .then(|| Fingerprint::Text(SimHash::from_html(&html)));
+            return self.decide(url, canonical.as_ref(), digest, fingerprint);
+        }
+
+        let digest = ContentDigest::compute(self.digests.algorithm(), body);
+        #[cfg(feature = "images")]
+        let fingerprint = match &self.similar_images {
+            Some(_) if content_type.starts_with("image/") => LumaImage::decode(body)
+                .ok()
+                .map(|image| Fingerprint::Image(ImageHash::phash(&image))),
+            _ => None,
+        };
+        #[cfg(not(feature = "images"))]
+        let fingerprint = None;
+        self.decide(url, record.canonical_url.as_ref(), digest, fingerprint)
+    }
+
+    /// Decides whether a page is a duplicate from its already extracted canonical URL and content
+    /// digest.
+    pub fn add(
+        &mut self,
+        url: &Url,
+        canonical: Option<&Url>,
+        digest: ContentDigest,
+    ) -> DedupDecision {
+        self.decide(url, canonical, digest, None)
+    }
+
+    fn decide(
+        &mut self,
+        url: &Url,
+        canonical: Option<&Url>,
+        digest: ContentDigest,
+        fingerprint: Option<Fingerprint>,
+    ) -> DedupDecision {
+        let content = self.digests.add(url, digest.clone());
+        // Fingerprints are indexed whatever the decision, so later copies find them.
+        let similar = match fingerprint {
+            Some(Fingerprint::Text(hash)) => self
+                .similar_text
+                .as_mut()
+                .and_then(|index| index.insert(url, hash))
+                .map(|near| {
+                    (
+                        near.url,
+                        DuplicateReason::SimilarText {
+                            distance: near.distance,
+                        },
+                    )
+                }),
+            Some(Fingerprint::Image(hash)) => self
+                .similar_images
+                .as_mut()
+                .and_then(|index| index.insert(url, hash))
+                .map(|near| {
+                    (
+                        near.url,
+                        DuplicateReason::SimilarImage {
+                            distance: near.distance,
+                        },
+                    )
+                }),
+            None => None,
+        };
+
+        let decision = if let Some(target) = self.redirects.get(url) {
+            DedupDecision::Duplicate {
+                of: self.resolve(target),
+                reason: DuplicateReason::Redirect {
+                    target: target.clone(),
+                },
+            }
+        } else if let Some(canonical) = canonical.filter(|c| *c != url) {
+            DedupDecision::Duplicate {
+                of: self.resolve(canonical),
+                reason: DuplicateReason::Canonical {
+                    canonical: canonical.clone(),
+                },
+            }
+        } else if let DigestMatch::Duplicate { original } = content {
+            DedupDecision::Duplicate {
+                of: self.resolve(&original),
+                reason: DuplicateReason::Content { digest },
+            }
+        } else if let Some((similar, reason)) = similar {
+            DedupDecision::Duplicate {
+                of: self.resolve(&similar),
+                reason,
+            }
+        } else {
+            DedupDecision::Representative
+        };
+
+        // A chain leading back to the URL itself (two pages declaring each other canonical)
+        // leaves the URL as the representative.
+        let decision = match decision {
+            DedupDecision::Duplicate { of, .. } if of == *url => DedupDecision::Representative,
+            decision => decision,
+        };
+        self.record(url, decision.clone());
+        decision
+    }
+
+    fn record(&mut self, url: &Url, decision: DedupDecision) {
+        if self.decisions.insert(url.clone(), decision).is_none() {
+            self.order.push(url.clone());
+        }
+    }
+
+    /// Follows duplicate decisions and redirects to the URL representing a document.
+    pub fn resolve(&self, url: &Url) -> Url {
+        let mut current = url;
+        // Bounded so cycles between decisions can't loop forever.
+        for _ in 0..=self.decisions.len() + self.redirects.len() {
+            let next = match self.decisions.get(current) {
+                Some(DedupDecision::Duplicate { of, .. }) => of,
+                Some(DedupDecision::Representative) => break,
+                None => match self.redirects.get(current) {
+                    Some(target) => target,
+                    None => break,
+                },
+            };
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        current.clone()
+    }
+
+    /// The decision made for a URL.
+    pub fn decision(&self, url: &Url) -> Option<&DedupDecision> {
+        self.decisions.get(url)
+    }
+
+    /// The pages that represent their document, in the order they were added.
+    pub fn representatives(&self) -> impl Iterator<Item = &Url> {
+        self.order
+            .iter()
+            .filter(|url| self.decisions.get(*url) == Some(&DedupDecision::Representative))
+    }
+
+    /// Every URL with its decision, in the order they were added.
+    pub fn decisions(&self) -> impl Iterator<Item = (&Url, &DedupDecision)> {
+        self.order.iter().map(|url| (url, &self.decisions[url]))
+    }
+}
+
+/// A near-duplicate fingerprint of a page's content.
+enum Fingerprint {
+    Text(SimHash),
+    #[cfg_attr(not(feature = "images"), allow(dead_code))]
+    Image(ImageHash),
+}
+
+/// Writes what a [`DedupPolicy`] makes of every page to another sink, so the output holds one
+/// body per document.
+///
+/// Representatives are written as they are. Duplicates are written without their body, with
+/// the decision in the record's `duplicate` field, e.g. `{"status": "duplicate", "of":
+/// "https://example.com/", "reason": "canonical", "canonical": "https://example.com/"}`.
+/// Failed fetches and pages without a body are passed on untouched, and a record for the same
+/// URL as the one written just before it, such as a page's screenshot, shares its decision.
+pub struct DedupSink<S> {
+    inner: S,
+    policy: DedupPolicy,
+    /// The URL of the last page and what was decided for it.
+    last: Option<(Url, DedupDecision)>,
+}
+
+impl<S: Sink> DedupSink<S> {
+    pub fn new(inner: S, policy: DedupPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            last: None,
+        }
+    }
+
+    pub fn policy(&self) -> &DedupPolicy {
+        &self.policy
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for DedupSink<S> {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let Some(body) = body.filter(|_| (200..300).contains(&record.status)) else {
+            return self.inner.write(record, body);
+        };
+        let decision = match &self.last {
+            Some((url, decision)) if *url == record.url => decision.clone(),
+            _ => self.policy.add_record(record, body),
+        };
+        self.last = Some((record.url.clone(), decision.clone()));
+
+        if !decision.is_duplicate() {
+            return self.inner.write(record, Some(body));
+        }
+        let mut record = record.clone();
+        // Serializing the decision can't fail, it's made of strings.
+        let decision = serde_json::to_value(decision).unwrap_or_default();
+        record.add_field("duplicate", decision);
+        self.inner.write(&record, None)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// The absolute URL of a page's `<link rel="canonical">`, resolved against its `<base href>` or
+/// the page URL.
+pub fn canonical_url(html: &str, page_url: &Url) -> Option<Url> {
+    let document = Html::parse_document(html);
+
+    // Unwrapping is safe here because the selectors are valid constants.
+    let base_selector = Selector::parse("base[href]").unwrap();
+    let base = document
+        .select(&base_selector)
+        .next()
+        .and_then(|base| page_url.join(base.attr("href")?).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let selector = Selector::parse("link[rel][href]").unwrap();
+    document
+        .select(&selector)
+        .find(|link| {
+            link.attr("rel")
+                .unwrap_or_default()
+                .split_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case("canonical"))
+        })
+        .and_then(|link| base.join(link.attr("href")?.trim()).ok())
+        .map(|mut canonical| {
+            canonical.set_fragment(None);
+            canonical
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn finds_canonical_links() {
+        let html =
+            r#"<base href="https://example.com/blog/"><link rel="Canonical" href="post#top">"#;
+        assert_eq!(
+            canonical_url(html, &url("https://example.com/blog/post?ref=x")),
+            Some(url("https://example.com/blog/post"))
+        );
+        assert_eq!(
+            canonical_url("<p>none</p>", &url("https://example.com/")),
+            None
+        );
+    }
+
+    #[test]
+    fn chains_signals_into_one_decision() {
+        let mut policy = DedupPolicy::default();
+        let (a, b, c, d, e) = (
+            url("https://example.com/a"),
+            url("https://example.com/b"),
+            url("https://example.com/c"),
+            url("https://example.com/d"),
+            url("https://example.com/e"),
+        );
+
+        // `b` redirects to `a`, `c` declares `b` canonical so it resolves to `a` as well.
+        assert_eq!(
+            policy.add_page(&a, "<p>A</p>"),
+            DedupDecision::Representative
+        );
+        policy.add_redirect(&b, &a);
+        let decision = policy.add_page(&c, r#"<link rel="canonical" href="/b"><p>C</p>"#);
+        assert_eq!(
+            decision,
+            DedupDecision::Duplicate {
+                of: a.clone(),
+                reason: DuplicateReason::Canonical {
+                    canonical: b.clone()
+                },
+            }
+        );
+
+        // `d` has the same content as `c`, which resolves to `a`.
+        let decision = policy.add_page(&d, "<div>C</div>");
+        assert!(matches!(
+            decision,
+            DedupDecision::Duplicate { ref of, reason: DuplicateReason::Content { .. } } if *of == a
+        ));
+
+        // Pages declaring each other canonical don't both disappear.
+        policy.add_page(&e, r#"<link rel="canonical" href="/f"><p>E</p>"#);
+        let decision = policy.add_page(
+            &url("https://example.com/f"),
+            r#"<link rel="canonical" href="/e"><p>F</p>"#,
+        );
+        assert_eq!(decision, DedupDecision::Representative);
+
+        let representatives = policy.representatives().collect::<Vec<&Url>>();
+        assert_eq!(representatives, vec![&a, &url("https://example.com/f")]);
+        assert_eq!(policy.decisions().count(), 6);
+
+        let json = serde_json::to_value(policy.decision(&b).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "status": "duplicate",
+                "of": "https://example.com/a",
+                "reason": "redirect",
+                "target": "https://example.com/a",
+            })
+        );
+    }
+
+    #[test]
+    fn writes_similar_pages_without_their_body() {
+        #[derive(Default)]
+        struct Written(Vec<(String, Option<usize>, serde_json::Value)>);
+
+        impl Sink for Written {
+            fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+                let duplicate = record.fields["duplicate"]["reason"].clone();
+                self.0
+                    .push((record.url.to_string(), body.map(<[u8]>::len), duplicate));
+                Ok(())
+            }
+
+            fn finish(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let page = |path: &str, content_type: &str| {
+            let mut record = PageRecord::new(url(path), 200, chrono::Utc::now());
+            record.headers = vec![("Content-Type".to_string(), content_type.to_string())];
+            record
+        };
+        let article = "<p>Kirby inhales enemies to copy their abilities and then floats away \
+                       over the hills of Dream Land while the king watches from his castle</p>";
+        let mirror = format!("{article}<footer>Share on social media</footer>");
+
+        let mut sink = DedupSink::new(Written::default(), DedupPolicy::default().similar_text(8));
+        let a = page("https://example.com/a", "text/html");
+        sink.write(&a, Some(article.as_bytes())).unwrap();
+        let b = page("https://mirror.example.com/a", "text/html");
+        sink.write(&b, Some(mirror.as_bytes())).unwrap();
+        // The mirror's screenshot goes where the mirror went.
+        sink.write(
+            &page("https://mirror.example.com/a", "image/png"),
+            Some(b"png"),
+        )
+        .unwrap();
+        let missing = PageRecord::new(url("https://example.com/gone"), 404, chrono::Utc::now());
+        sink.write(&missing, Some(b"gone")).unwrap();
+
+        let similar = serde_json::json!("similar_text");
+        assert_eq!(
+            sink.into_inner().0,
+            [
+                (
+                    "https://example.com/a".to_string(),
+                    Some(article.len()),
+                    Value::Null
+                ),
+                (
+                    "https://mirror.example.com/a".to_string(),
+                    None,
+                    similar.clone()
+                ),
+                ("https://mirror.example.com/a".to_string(), None, similar),
+                ("https://example.com/gone".to_string(), Some(4), Value::Null),
+            ]
+        );
+    }
+}
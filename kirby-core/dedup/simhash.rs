@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{stable_hash, words};
+use crate::extract::visible_text;
+
+/// How many consecutive words make up a feature, short texts use single words instead.
+const SHINGLE_SIZE: usize = 3;
+
+/// A 64-bit SimHash fingerprint, similar texts have fingerprints that differ in few bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SimHash(pub u64);
+
+impl SimHash {
+    /// Fingerprints text from its lowercase word shingles, weighted by how often they occur.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kirby_core::dedup::simhash::SimHash;
+    ///
+    /// let article = "Kirby inhales enemies to copy their abilities and then floats away over \
+    ///                the hills of Dream Land while the king watches from his castle";
+    /// let mirror = format!("{article} | Share on social media");
+    /// let other = "Regular expressions are compiled once and cached in a static for reuse";
+    ///
+    /// let a = SimHash::from_text(article);
+    /// assert!(a.distance(SimHash::from_text(&mirror)) <= 8);
+    /// assert!(a.distance(SimHash::from_text(other)) > 8);
+    /// ```
+    pub fn from_text(text: &str) -> Self {
+        let words = words(text);
+        let mut features: HashMap<String, u32> = HashMap::new();
+        if words.len() < SHINGLE_SIZE {
+            for word in words {
+                *features.entry(word).or_default() += 1;
+            }
+        } else {
+            for shingle in words.windows(SHINGLE_SIZE) {
+                *features.entry(shingle.join(" ")).or_default() += 1;
+            }
+        }
+
+        Self::from_features(features.iter().map(|(f, w)| (f.as_str(), *w)))
+    }
+
+    /// Fingerprints the visible text of an HTML document.
+    pub fn from_html(html: &str) -> Self {
+        let document = Html::parse_document(html);
+        Self::from_text(&visible_text(document.root_element()))
+    }
+
+    /// Fingerprints weighted features: each bit is set when the features whose hash has that bit
+    /// set outweigh those that don't.
+    pub fn from_features<'a>(features: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        let mut totals = [0i64; 64];
+        for (feature, weight) in features {
+            let hash = stable_hash(feature.as_bytes(), 0);
+            for (bit, total) in totals.iter_mut().enumerate() {
+                if hash & (1 << bit) != 0 {
+                    *total += i64::from(weight);
+                } else {
+                    *total -= i64::from(weight);
+                }
+            }
+        }
+
+        let fingerprint = totals
+            .iter()
+            .enumerate()
+            .filter(|(_, total)| **total > 0)
+            .fold(0u64, |fingerprint, (bit, _)| fingerprint | (1 << bit));
+        Self(fingerprint)
+    }
+
+    /// The number of differing bits.
+    pub fn distance(self, other: SimHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+
+    /// The share of matching bits, from `0.0` to `1.0`.
+    pub fn similarity(self, other: SimHash) -> f64 {
+        1.0 - f64::from(self.distance(other)) / 64.0
+    }
+}
+
+impl fmt::Display for SimHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// An earlier page within the index's distance of a new one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NearDuplicate {
+    pub url: Url,
+    pub distance: u32,
+}
+
+/// Finds fingerprints within a Hamming distance of each other without comparing every pair.
+///
+/// The 64 bits are split into `max_distance + 1` blocks, two fingerprints within the distance
+/// must have at least one block in common. Every block gets a table keyed by its bits so a
+/// lookup only compares the fingerprints sharing a block.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::dedup::simhash::{SimHash, SimHashIndex};
+/// use url::Url;
+///
+/// let mut index = SimHashIndex::new(3);
+/// let original = Url::parse("https://example.com/post").unwrap();
+/// index.insert(&original, SimHash(0b1010_1100));
+///
+/// let matches = index.find(SimHash(0b1010_0101));
+/// assert_eq!(matches[0].url, original);
+/// assert_eq!(matches[0].distance, 2);
+///
+/// assert!(index.find(SimHash(!0b1010_1100)).is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimHashIndex {
+    max_distance: u32,
+    /// The `(shift, mask)` of every block.
+    blocks: Vec<(u32, u64)>,
+    /// Mapping of block bits -> positions in `entries`, one table per block.
+    tables: Vec<HashMap<u64, Vec<usize>>>,
+    entries: Vec<(Url, SimHash)>,
+    /// Union-find parents linking near-duplicate entries into clusters.
+    parents: Vec<usize>,
+}
+
+impl Default for SimHashIndex {
+    /// An index matching fingerprints up to 3 bits apart, which works well for web pages.
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl SimHashIndex {
+    /// Creates an index matching fingerprints at most `max_distance` bits apart, larger distances
+    /// make smaller blocks and so slower lookups.
+    pub fn new(max_distance: u32) -> Self {
+        let max_distance = max_distance.min(63);
+        let count = max_distance + 1;
+        let mut blocks = Vec::with_capacity(count as usize);
+        let mut shift = 0;
+        for block in 0..count {
+            let width = 64 / count + u32::from(block < 64 % count);
+            let mask = if width == 64 {
+                u64::MAX
+            } else {
+                (1 << width) - 1
+            };
+            blocks.push((shift, mask));
+            shift += width;
+        }
+
+        Self {
+            max_distance,
+            tables: vec![HashMap::new(); blocks.len()],
+            blocks,
+            entries: Vec::new(),
+            parents: Vec::new(),
+        }
+    }
+
+    pub fn max_distance(&self) -> u32 {
+        self.max_distance
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Earlier pages within the maximum distance of a fingerprint, closest first.
+    pub fn find(&self, hash: SimHash) -> Vec<NearDuplicate> {
+        self.near_duplicates(hash, &self.candidates(hash))
+    }
+
+    fn near_duplicates(&self, hash: SimHash, positions: &[usize]) -> Vec<NearDuplicate> {
+        let mut matches = positions
+            .iter()
+            .map(|position| {
+                let (url, other) = &self.entries[*position];
+                NearDuplicate {
+                    url: url.clone(),
+                    distance: hash.distance(*other),
+                }
+            })
+            .collect::<Vec<NearDuplicate>>();
+        matches.sort_by_key(|m| m.distance);
+        matches
+    }
+
+    fn candidates(&self, hash: SimHash) -> Vec<usize> {
+        let mut positions = self
+            .blocks
+            .iter()
+            .zip(&self.tables)
+            .filter_map(|((shift, mask), table)| table.get(&((hash.0 >> shift) & mask)))
+            .flatten()
+            .copied()
+            .filter(|position| hash.distance(self.entries[*position].1) <= self.max_distance)
+            .collect::<Vec<usize>>();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    /// Adds a page, returning the closest earlier page within the maximum distance.
+    pub fn insert(&mut self, url: &Url, hash: SimHash) -> Option<NearDuplicate> {
+        let candidates = self.candidates(hash);
+        let position = self.entries.len();
+        self.entries.push((url.clone(), hash));
+        self.parents.push(position);
+        for ((shift, mask), table) in self.blocks.iter().zip(&mut self.tables) {
+            table
+                .entry((hash.0 >> shift) & mask)
+                .or_default()
+                .push(position);
+        }
+
+        for candidate in &candidates {
+            let (a, b) = (self.root(*candidate), self.root(position));
+            // The earliest page stays the root so it represents the cluster.
+            self.parents[a.max(b)] = a.min(b);
+        }
+
+        self.near_duplicates(hash, &candidates).into_iter().next()
+    }
+
+    fn root(&mut self, mut position: usize) -> usize {
+        while self.parents[position] != position {
+            self.parents[position] = self.parents[self.parents[position]];
+            position = self.parents[position];
+        }
+        position
+    }
+
+    /// Groups of pages linked by near-duplicate matches, the first page of each group was added
+    /// first. Pages without near duplicates aren't included.
+    pub fn clusters(&mut self) -> Vec<Vec<&Url>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group_of_root: HashMap<usize, usize> = HashMap::new();
+        for position in 0..self.entries.len() {
+            let root = self.root(position);
+            let group = *group_of_root.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(position);
+        }
+
+        groups
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.into_iter().map(|p| &self.entries[p].0).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn fingerprints_similar_text_closely() {
+        let base = "The crawler follows links across the site, respects robots rules, \
+                    stores every page it fetches and reports which pages changed since the \
+                    previous run so operators can review the differences quickly. Each host \
+                    gets its own politeness delay and a budget of requests per minute, slow \
+                    hosts are backed off automatically. Extracted records are written to \
+                    rotating files together with the response headers, the fetch time and a \
+                    digest of the content, which makes it easy to resume an interrupted crawl \
+                    or to compare two crawls of the same site made weeks apart.";
+        let print_view = format!("Print this page. {base}. Copyright 2026 Example Inc.");
+        let unrelated = "Bake the bread at two hundred degrees for forty minutes until the \
+                         crust is golden and sounds hollow when tapped underneath";
+
+        let a = SimHash::from_text(base);
+        assert_eq!(a, SimHash::from_text(&base.to_uppercase()));
+        assert!(a.distance(SimHash::from_text(&print_view)) <= 10);
+        assert!(a.distance(SimHash::from_text(unrelated)) > 16);
+        assert_eq!(
+            SimHash::from_html(&format!("<p>{base}</p><script>x()</script>")),
+            a
+        );
+        assert_eq!(SimHash(0xff).to_string(), "00000000000000ff");
+    }
+
+    #[test]
+    fn indexes_and_clusters_fingerprints() {
+        let mut index = SimHashIndex::new(2);
+        assert_eq!(index.blocks.len(), 3);
+
+        assert_eq!(index.insert(&url("https://a.com/1"), SimHash(0)), None);
+        assert_eq!(
+            index.insert(&url("https://a.com/2"), SimHash(0b11 << 60)),
+            Some(NearDuplicate {
+                url: url("https://a.com/1"),
+                distance: 2
+            })
+        );
+        assert_eq!(
+            index.insert(&url("https://b.com/1"), SimHash(u64::MAX)),
+            None
+        );
+        // Four bits from the first page but within two of the second.
+        let third = SimHash(0b11 << 60 | 0b11 << 20);
+        assert_eq!(
+            index
+                .insert(&url("https://a.com/3"), third)
+                .unwrap()
+                .distance,
+            2
+        );
+        // Itself and the second page.
+        assert_eq!(index.find(third).len(), 2);
+
+        let clusters = index.clusters();
+        assert_eq!(
+            clusters,
+            vec![vec![
+                &url("https://a.com/1"),
+                &url("https://a.com/2"),
+                &url("https://a.com/3")
+            ]]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+
+use crate::extract::collapse_whitespace;
+
+/// Elements that start a new block of text, similar to `extract::visible_text`.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+const HIDDEN_ELEMENTS: &[&str] = &["head", "noscript", "script", "style", "template"];
+
+/// Common advertising containers that change between every fetch.
+const DEFAULT_IGNORE: &str = r#"ins.adsbygoogle, iframe, .ad, .ads, .advert, .advertisement, [id^="ad-"], [class*="ad-slot"], [class*="sponsored"]"#;
+
+/// Timestamps and relative times, which are masked by default so they don't count as changes.
+static DEFAULT_MASKS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r"\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?",
+        r"\b\d{1,2}:\d{2}(?::\d{2})?(?:\s?[AaPp][Mm])?\b",
+        r"(?i)\b\d+\s+(?:second|minute|hour|day|week|month|year)s?\s+ago\b",
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+});
+
+/// Options controlling which parts of a page are compared.
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Elements (and their descendants) excluded from the comparison.
+    pub ignore: Vec<Selector>,
+    /// Text matching any of these patterns is replaced with a placeholder before comparing.
+    pub masks: Vec<Regex>,
+}
+
+impl Default for DiffOptions {
+    /// Ignores common ad containers and masks dates, times and relative times ("5 minutes ago").
+    fn default() -> Self {
+        Self {
+            // Unwrapping is safe here because the selector is a valid constant.
+            ignore: vec![Selector::parse(DEFAULT_IGNORE).unwrap()],
+            masks: DEFAULT_MASKS.clone(),
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Compares everything, without ignoring elements or masking text.
+    pub fn strict() -> Self {
+        Self {
+            ignore: Vec::new(),
+            masks: Vec::new(),
+        }
+    }
+}
+
+/// A run of text within a single block level element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Block {
+    /// A readable path to the block's element, e.g. `body>main#content>p.intro`.
+    pub path: String,
+    pub text: String,
+}
+
+impl Block {
+    /// Splits a document into its blocks of visible text in document order.
+    pub fn extract(html: &str, options: &DiffOptions) -> Vec<Self> {
+        let document = Html::parse_document(html);
+        Self::from_element(document.root_element(), options)
+    }
+
+    /// Splits the contents of an element into its blocks of visible text.
+    pub fn from_element(element: ElementRef, options: &DiffOptions) -> Vec<Self> {
+        let mut blocks = Vec::new();
+        let mut text = String::new();
+        walk(
+            element,
+            &element_label(element),
+            options,
+            &mut text,
+            &mut blocks,
+        );
+        push_block(&element_label(element), &mut text, options, &mut blocks);
+        blocks
+    }
+}
+
+fn walk(
+    element: ElementRef,
+    path: &str,
+    options: &DiffOptions,
+    text: &mut String,
+    blocks: &mut Vec<Block>,
+) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(e) if HIDDEN_ELEMENTS.contains(&e.name()) => {}
+            Node::Element(e) => {
+                // Unwrapping is safe here because the node is an element.
+                let child = ElementRef::wrap(child).unwrap();
+                if options.ignore.iter().any(|s| s.matches(&child)) {
+                    continue;
+                }
+
+                if BLOCK_ELEMENTS.contains(&e.name()) {
+                    push_block(path, text, options, blocks);
+                    let child_path = format!("{path}>{}", element_label(child));
+                    walk(child, &child_path, options, text, blocks);
+                    push_block(&child_path, text, options, blocks);
+                } else {
+                    walk(child, path, options, text, blocks);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_block(path: &str, text: &mut String, options: &DiffOptions, blocks: &mut Vec<Block>) {
+    let mut collapsed = collapse_whitespace(text);
+    text.clear();
+    for mask in &options.masks {
+        collapsed = mask.replace_all(&collapsed, "…").into_owned();
+    }
+
+    if !collapsed.is_empty() {
+        blocks.push(Block {
+            path: path.trim_start_matches("html>").to_string(),
+            text: collapsed,
+        });
+    }
+}
+
+/// The tag name with the id or first class, e.g. `div#main` or `p.intro`.
+fn element_label(element: ElementRef) -> String {
+    let value = element.value();
+    if let Some(id) = value.id() {
+        format!("{}#{id}", value.name())
+    } else if let Some(class) = value
+        .attr("class")
+        .and_then(|c| c.split_whitespace().next())
+    {
+        format!("{}.{class}", value.name())
+    } else {
+        value.name().to_string()
+    }
+}
+
+/// A difference between two versions of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Added {
+        path: String,
+        text: String,
+    },
+    Removed {
+        path: String,
+        text: String,
+    },
+    /// A block at the same position and path whose text changed.
+    Modified {
+        path: String,
+        old: String,
+        new: String,
+    },
+}
+
+/// The structural differences between two versions of a page.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct DomDiff {
+    pub changes: Vec<Change>,
+    /// The share of blocks that are unchanged, from 0.0 (completely different) to 1.0.
+    pub similarity: f64,
+}
+
+impl DomDiff {
+    /// Compares two HTML documents block by block.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kirby_core::domdiff::{Change, DiffOptions, DomDiff};
+    ///
+    /// let old = "<h1>Menu</h1><p>Cake</p><p>Updated 10:30</p>";
+    /// let new = "<h1>Menu</h1><p>Pie</p><p>Updated 11:45</p>";
+    ///
+    /// let diff = DomDiff::compare(old, new, &DiffOptions::default());
+    /// assert_eq!(
+    ///     diff.changes,
+    ///     vec![Change::Modified { path: "body>p".into(), old: "Cake".into(), new: "Pie".into() }]
+    /// );
+    /// ```
+    pub fn compare(old_html: &str, new_html: &str, options: &DiffOptions) -> Self {
+        Self::compare_blocks(
+            &Block::extract(old_html, options),
+            &Block::extract(new_html, options),
+        )
+    }
+
+    /// Compares two lists of blocks, which may come from [`Block::extract`] or any other
+    /// extraction producing comparable regions.
+    pub fn compare_blocks(old: &[Block], new: &[Block]) -> Self {
+        // Trim the common prefix and suffix since most of a page is usually unchanged.
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_middle = &old[prefix..old.len() - suffix];
+        let new_middle = &new[prefix..new.len() - suffix];
+
+        let (unchanged, ops) = lcs_ops(old_middle, new_middle);
+        let unchanged = prefix + suffix + unchanged;
+
+        let mut changes = Vec::new();
+        let mut removed: Vec<&Block> = Vec::new();
+        let mut added: Vec<&Block> = Vec::new();
+        for op in ops.into_iter().chain(std::iter::once(Op::Keep)) {
+            match op {
+                Op::Remove(i) => removed.push(&old_middle[i]),
+                Op::Add(j) => added.push(&new_middle[j]),
+                Op::Keep => {
+                    flush_hunk(&mut removed, &mut added, &mut changes);
+                }
+            }
+        }
+
+        let total = old.len().max(new.len());
+        let similarity = if total == 0 {
+            1.0
+        } else {
+            unchanged as f64 / total as f64
+        };
+
+        Self {
+            changes,
+            similarity,
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+enum Op {
+    Keep,
+    Remove(usize),
+    Add(usize),
+}
+
+/// Computes the longest common subsequence of two block lists, returning its length and the
+/// edit operations in order.
+fn lcs_ops(old: &[Block], new: &[Block]) -> (usize, Vec<Op>) {
+    let (n, m) = (old.len(), new.len());
+    let mut table = vec![0usize; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[at(i, j)] = if old[i] == new[j] {
+                table[at(i + 1, j + 1)] + 1
+            } else {
+                table[at(i + 1, j)].max(table[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            ops.push(Op::Keep);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || table[at(i, j + 1)] >= table[at(i + 1, j)]) {
+            ops.push(Op::Add(j));
+            j += 1;
+        } else {
+            ops.push(Op::Remove(i));
+            i += 1;
+        }
+    }
+
+    (table[at(0, 0)], ops)
+}
+
+/// Turns a run of removed and added blocks into changes, pairing blocks with the same path as
+/// modifications.
+fn flush_hunk(removed: &mut Vec<&Block>, added: &mut Vec<&Block>, changes: &mut Vec<Change>) {
+    let mut added_iter = added.drain(..).peekable();
+    for old in removed.drain(..) {
+        match added_iter.peek() {
+            Some(new) if new.path == old.path => {
+                changes.push(Change::Modified {
+                    path: old.path.clone(),
+                    old: old.text.clone(),
+                    new: new.text.clone(),
+                });
+                added_iter.next();
+            }
+            _ => changes.push(Change::Removed {
+                path: old.path.clone(),
+                text: old.text.clone(),
+            }),
+        }
+    }
+    for new in added_iter {
+        changes.push(Change::Added {
+            path: new.path.clone(),
+            text: new.text.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_documents_into_blocks() {
+        let html = r#"
+        <main id="content">
+            Intro <b>bold</b> text
+            <p class="lead first">Lead</p>
+            Trailing
+            <script>ignored()</script>
+        </main>
+        "#;
+
+        let blocks = Block::extract(html, &DiffOptions::strict());
+        let blocks = blocks
+            .iter()
+            .map(|b| (b.path.as_str(), b.text.as_str()))
+            .collect::<Vec<(&str, &str)>>();
+        assert_eq!(
+            blocks,
+            vec![
+                ("body>main#content", "Intro bold text"),
+                ("body>main#content>p.lead", "Lead"),
+                ("body>main#content", "Trailing"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_added_removed_and_modified_blocks() {
+        let old = r#"
+        <h1>News</h1>
+        <ul><li>First</li><li>Second</li><li>Third</li></ul>
+        <p>Footer</p>
+        "#;
+        let new = r#"
+        <h1>News</h1>
+        <ul><li>Zeroth</li><li>First</li><li>Third (edited)</li></ul>
+        <div>New section</div>
+        <p>Footer</p>
+        "#;
+
+        let diff = DomDiff::compare(old, new, &DiffOptions::default());
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change::Added {
+                    path: "body>ul>li".into(),
+                    text: "Zeroth".into()
+                },
+                Change::Modified {
+                    path: "body>ul>li".into(),
+                    old: "Second".into(),
+                    new: "Third (edited)".into()
+                },
+                Change::Removed {
+                    path: "body>ul>li".into(),
+                    text: "Third".into()
+                },
+                Change::Added {
+                    path: "body>div".into(),
+                    text: "New section".into()
+                },
+            ]
+        );
+        assert!((diff.similarity - 3.0 / 6.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn ignores_ads_and_timestamps_by_default() {
+        let old = r#"
+        <p>Posted 2024-01-01T10:00:00Z, 5 minutes ago</p>
+        <div class="ad">Buy now!</div>
+        <p>Body</p>
+        "#;
+        let new = r#"
+        <p>Posted 2024-01-02T11:30:00Z, 2 hours ago</p>
+        <div class="ad">Something else entirely</div>
+        <p>Body</p>
+        "#;
+
+        let diff = DomDiff::compare(old, new, &DiffOptions::default());
+        assert!(diff.is_unchanged());
+        assert_eq!(diff.similarity, 1.0);
+
+        let diff = DomDiff::compare(old, new, &DiffOptions::strict());
+        assert_eq!(diff.changes.len(), 2);
+    }
+}
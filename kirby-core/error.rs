@@ -0,0 +1,319 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::extract::from_html::FromHtmlError;
+use crate::extract::from_json::FromJsonError;
+use crate::record::RecordError;
+use crate::store::StoreError;
+
+/// What went wrong, so callers can branch on it, e.g. to decide whether to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The host name couldn't be resolved.
+    Dns,
+    /// No connection could be made, e.g. it was refused or reset.
+    Connect,
+    Tls,
+    Timeout {
+        phase: Phase,
+    },
+    /// The response had a 4xx or 5xx status.
+    Http {
+        status: u16,
+    },
+    /// robots.txt doesn't allow the URL to be fetched.
+    RobotsDenied,
+    /// A response, record or extraction output couldn't be parsed.
+    Parse,
+    /// Output couldn't be stored.
+    Storage,
+    /// The browser rendering the page failed, e.g. a script threw or a selector never
+    /// appeared.
+    Render,
+    /// Logging in to the site failed, e.g. the credentials were rejected.
+    Login,
+}
+
+/// The part of a fetch that took too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Dns,
+    Connect,
+    Tls,
+    /// Waiting for the response to start.
+    FirstByte,
+    /// Reading the response body.
+    Body,
+}
+
+impl ErrorKind {
+    /// The kind's name without its details, e.g. `timeout`, as it's serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Dns => "dns",
+            Self::Connect => "connect",
+            Self::Tls => "tls",
+            Self::Timeout { .. } => "timeout",
+            Self::Http { .. } => "http",
+            Self::RobotsDenied => "robots_denied",
+            Self::Parse => "parse",
+            Self::Storage => "storage",
+            Self::Render => "render",
+            Self::Login => "login",
+        }
+    }
+
+    /// The status class of an HTTP error, e.g. 4 for 404.
+    pub fn status_class(self) -> Option<u16> {
+        match self {
+            Self::Http { status } => Some(status / 100),
+            _ => None,
+        }
+    }
+
+    /// Whether trying again later may succeed: network failures, timeouts, `429 Too Many
+    /// Requests` and server errors.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            Self::Dns | Self::Connect | Self::Timeout { .. } => true,
+            Self::Http { status } => status == 429 || status >= 500,
+            Self::Tls
+            | Self::RobotsDenied
+            | Self::Parse
+            | Self::Storage
+            | Self::Render
+            | Self::Login => false,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns => write!(f, "DNS lookup failed"),
+            Self::Connect => write!(f, "connection failed"),
+            Self::Tls => write!(f, "TLS handshake failed"),
+            Self::Timeout { phase } => write!(f, "timed out {phase}"),
+            Self::Http { status } => write!(f, "HTTP {status}"),
+            Self::RobotsDenied => write!(f, "denied by robots.txt"),
+            Self::Parse => write!(f, "parsing failed"),
+            Self::Storage => write!(f, "storing failed"),
+            Self::Render => write!(f, "rendering failed"),
+            Self::Login => write!(f, "logging in failed"),
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dns => "resolving the host",
+            Self::Connect => "connecting",
+            Self::Tls => "in the TLS handshake",
+            Self::FirstByte => "waiting for the first byte",
+            Self::Body => "reading the body",
+        })
+    }
+}
+
+/// An error of any part of a crawl, with the URL, host and attempt it happened on.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::error::{ErrorKind, KirbyError, Phase};
+/// use url::Url;
+///
+/// let error = KirbyError::new(ErrorKind::Timeout { phase: Phase::FirstByte }, "after 30s")
+///     .with_url(Url::parse("https://example.com/slow").unwrap())
+///     .with_attempt(2);
+///
+/// assert!(error.kind().is_retryable());
+/// assert_eq!(error.host(), Some("example.com"));
+/// assert_eq!(
+///     error.to_string(),
+///     "timed out waiting for the first byte: after 30s (https://example.com/slow, attempt 2)"
+/// );
+/// ```
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct KirbyError(Box<Inner>);
+
+/// Boxed, so results failing with a [`KirbyError`] stay small.
+#[derive(Debug, Clone, Serialize)]
+struct Inner {
+    #[serde(flatten)]
+    kind: ErrorKind,
+    url: Option<Url>,
+    host: Option<String>,
+    attempt: Option<u32>,
+    message: String,
+    #[serde(skip)]
+    source: Option<Arc<dyn Error + Send + Sync>>,
+}
+
+impl KirbyError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self(Box::new(Inner {
+            kind,
+            url: None,
+            host: None,
+            attempt: None,
+            message: message.into(),
+            source: None,
+        }))
+    }
+
+    /// Sets the URL, and the host when the URL has one.
+    pub fn with_url(mut self, url: Url) -> Self {
+        if let Some(host) = url.host_str() {
+            self.0.host = Some(host.to_string());
+        }
+        self.0.url = Some(url);
+        self
+    }
+
+    /// Sets the host, for errors about a host rather than a URL.
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.0.host = Some(host.into());
+        self
+    }
+
+    /// Sets the attempt, 1 for the first.
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.0.attempt = Some(attempt);
+        self
+    }
+
+    pub fn with_source(mut self, source: impl Error + Send + Sync + 'static) -> Self {
+        self.0.source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind
+    }
+
+    pub fn url(&self) -> Option<&Url> {
+        self.0.url.as_ref()
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.0.host.as_deref()
+    }
+
+    pub fn attempt(&self) -> Option<u32> {
+        self.0.attempt
+    }
+
+    pub fn message(&self) -> &str {
+        &self.0.message
+    }
+}
+
+impl fmt::Display for KirbyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let error = &self.0;
+        write!(f, "{}", error.kind)?;
+        if !error.message.is_empty() {
+            write!(f, ": {}", error.message)?;
+        }
+        let target = error
+            .url
+            .as_ref()
+            .map(Url::as_str)
+            .or(error.host.as_deref());
+        match (target, error.attempt) {
+            (Some(target), Some(attempt)) => write!(f, " ({target}, attempt {attempt})"),
+            (Some(target), None) => write!(f, " ({target})"),
+            (None, Some(attempt)) => write!(f, " (attempt {attempt})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Error for KirbyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0
+            .source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+/// Errors are equal when everything but their source is.
+impl PartialEq for KirbyError {
+    fn eq(&self, other: &Self) -> bool {
+        let (error, other) = (&self.0, &other.0);
+        error.kind == other.kind
+            && error.url == other.url
+            && error.host == other.host
+            && error.attempt == other.attempt
+            && error.message == other.message
+    }
+}
+
+impl From<StoreError> for KirbyError {
+    fn from(error: StoreError) -> Self {
+        Self::new(ErrorKind::Storage, error.to_string()).with_source(error)
+    }
+}
+
+impl From<RecordError> for KirbyError {
+    fn from(error: RecordError) -> Self {
+        Self::new(ErrorKind::Parse, error.to_string()).with_source(error)
+    }
+}
+
+impl From<FromHtmlError> for KirbyError {
+    fn from(error: FromHtmlError) -> Self {
+        Self::new(ErrorKind::Parse, error.to_string()).with_source(error)
+    }
+}
+
+impl From<FromJsonError> for KirbyError {
+    fn from(error: FromJsonError) -> Self {
+        Self::new(ErrorKind::Parse, error.to_string()).with_source(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn keeps_context_and_source() {
+        let error = KirbyError::from(StoreError::Io(io::Error::other("disk full")))
+            .with_url(Url::parse("https://example.com/a").unwrap());
+        assert_eq!(error.kind(), ErrorKind::Storage);
+        assert!(!error.kind().is_retryable());
+        assert!(error.source().unwrap().to_string().contains("disk full"));
+
+        let denied = KirbyError::new(ErrorKind::Http { status: 503 }, "")
+            .with_host("example.com")
+            .with_attempt(1);
+        assert_eq!(denied.kind().status_class(), Some(5));
+        assert_eq!(denied.to_string(), "HTTP 503 (example.com, attempt 1)");
+        assert_eq!(
+            serde_json::to_value(&denied).unwrap(),
+            json!({
+                "kind": "http",
+                "status": 503,
+                "url": null,
+                "host": "example.com",
+                "attempt": 1,
+                "message": "",
+            })
+        );
+    }
+}
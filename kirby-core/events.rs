@@ -0,0 +1,243 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use url::Url;
+
+use crate::alerts::Alert;
+use crate::error::KirbyError;
+
+/// Something that happened during a crawl.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CrawlEvent {
+    UrlEnqueued {
+        url: Url,
+        depth: u32,
+    },
+    FetchStarted {
+        url: Url,
+        /// 1 for the first attempt.
+        attempt: u32,
+    },
+    FetchFinished {
+        url: Url,
+        status: u16,
+        #[serde(with = "millis")]
+        duration: Duration,
+        bytes: u64,
+    },
+    RobotsDenied {
+        url: Url,
+    },
+    /// No more requests are sent to a host until `until`, e.g. after it asked to back off.
+    HostPaused {
+        host: String,
+        until: DateTime<Utc>,
+        reason: String,
+    },
+    /// A crawl budget ran out, e.g. `pages` or `bytes`, so no more URLs are fetched.
+    BudgetExhausted {
+        budget: String,
+    },
+    Error {
+        error: KirbyError,
+    },
+    /// An [`AlertMonitor`](crate::alerts::AlertMonitor) condition was met.
+    Alert {
+        alert: Alert,
+    },
+}
+
+impl CrawlEvent {
+    /// The URL the event is about, if it's about one.
+    pub fn url(&self) -> Option<&Url> {
+        match self {
+            Self::UrlEnqueued { url, .. }
+            | Self::FetchStarted { url, .. }
+            | Self::FetchFinished { url, .. }
+            | Self::RobotsDenied { url } => Some(url),
+            Self::Error { error } => error.url(),
+            Self::HostPaused { .. } | Self::BudgetExhausted { .. } | Self::Alert { .. } => None,
+        }
+    }
+}
+
+mod millis {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+}
+
+type Callback = Box<dyn Fn(&CrawlEvent) + Send + Sync>;
+
+/// Identifies a callback registered with [`EventBus::on`], to remove it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Delivers crawl events to callbacks and channels, so user code can react to the crawl's
+/// progress without polling.
+///
+/// Callbacks run on the thread that publishes the event, in the order they were registered,
+/// so they should be quick. Each channel from [`subscribe`](Self::subscribe) gets every
+/// event; a channel is dropped from the bus once its receiver is.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// use kirby_core::events::{CrawlEvent, EventBus};
+/// use url::Url;
+///
+/// let bus = EventBus::new();
+/// let denied = Arc::new(AtomicUsize::new(0));
+/// let counter = Arc::clone(&denied);
+/// bus.on(move |event| {
+///     if let CrawlEvent::RobotsDenied { .. } = event {
+///         counter.fetch_add(1, Ordering::SeqCst);
+///     }
+/// });
+/// let events = bus.subscribe();
+///
+/// let url = Url::parse("https://example.com/private").unwrap();
+/// bus.publish(CrawlEvent::RobotsDenied { url: url.clone() });
+///
+/// assert_eq!(denied.load(Ordering::SeqCst), 1);
+/// assert_eq!(events.try_recv().unwrap().url(), Some(&url));
+/// ```
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    next_id: u64,
+    callbacks: Vec<(SubscriptionId, Arc<Callback>)>,
+    channels: Vec<Sender<CrawlEvent>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` with every event published from now on.
+    pub fn on(&self, callback: impl Fn(&CrawlEvent) + Send + Sync + 'static) -> SubscriptionId {
+        let mut subscribers = self.subscribers();
+        let id = SubscriptionId(subscribers.next_id);
+        subscribers.next_id += 1;
+        subscribers
+            .callbacks
+            .push((id, Arc::new(Box::new(callback))));
+        id
+    }
+
+    /// Removes a callback, returning false when it was already removed.
+    pub fn off(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.subscribers();
+        let before = subscribers.callbacks.len();
+        subscribers
+            .callbacks
+            .retain(|(callback_id, _)| *callback_id != id);
+        subscribers.callbacks.len() < before
+    }
+
+    /// A channel receiving every event published from now on.
+    pub fn subscribe(&self) -> Receiver<CrawlEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers().channels.push(sender);
+        receiver
+    }
+
+    /// Delivers an event to every callback and channel.
+    pub fn publish(&self, event: CrawlEvent) {
+        // The callbacks run without holding the lock, so they can register or remove others.
+        let callbacks = {
+            let mut subscribers = self.subscribers();
+            subscribers
+                .channels
+                .retain(|channel| channel.send(event.clone()).is_ok());
+            subscribers
+                .callbacks
+                .iter()
+                .map(|(_, callback)| Arc::clone(callback))
+                .collect::<Vec<_>>()
+        };
+        for callback in callbacks {
+            callback(&event);
+        }
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, Subscribers> {
+        // Publishing only sends and clones while holding the lock, so the subscribers are
+        // consistent even after a panic.
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn delivers_to_channels_until_they_are_dropped() {
+        let bus = EventBus::new();
+        let kept = bus.subscribe();
+        let dropped = bus.subscribe();
+        drop(dropped);
+
+        let publisher = bus.clone();
+        thread::spawn(move || {
+            publisher.publish(CrawlEvent::BudgetExhausted {
+                budget: "pages".to_string(),
+            });
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(bus.subscribers().channels.len(), 1);
+        let event = kept.recv().unwrap();
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({"type": "budget_exhausted", "budget": "pages"})
+        );
+    }
+
+    #[test]
+    fn removes_callbacks() {
+        let bus = EventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        let id = bus.on(move |event| log.lock().unwrap().push(event.clone()));
+
+        let url = Url::parse("https://example.com/").unwrap();
+        let finished = CrawlEvent::FetchFinished {
+            url: url.clone(),
+            status: 200,
+            duration: Duration::from_millis(1500),
+            bytes: 10,
+        };
+        bus.publish(finished.clone());
+        assert!(bus.off(id));
+        assert!(!bus.off(id));
+        bus.publish(CrawlEvent::UrlEnqueued { url, depth: 1 });
+
+        assert_eq!(serde_json::to_value(&finished).unwrap()["duration"], 1500);
+        assert_eq!(*seen.lock().unwrap(), [finished]);
+    }
+}
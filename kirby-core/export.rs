@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use flate2::write::GzEncoder;
+use serde_json::Value;
+
+use crate::extract::json_path::{JsonPath, JsonPathError};
+use crate::record::PageRecord;
+
+pub mod csv;
+#[cfg(feature = "http")]
+pub mod elasticsearch;
+#[cfg(feature = "sqlite")]
+pub mod fts;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod jsonl;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "http")]
+pub mod webhook;
+
+/// Receives crawled pages and writes them out in some format.
+pub trait Sink {
+    /// Writes one page, with its body when the sink keeps bodies.
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()>;
+
+    /// Flushes and closes the output, it must be called for the output to be complete.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        (**self).write(record, body)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+/// A column of tabular output and where its values come from.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::export::Column;
+/// use kirby_core::record::PageRecord;
+/// use serde_json::json;
+/// use url::Url;
+///
+/// let mut record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// record.fields = json!({"product": {"name": "Warp Star"}});
+///
+/// let column = Column::field("name", "$.product.name").unwrap();
+/// assert_eq!(column.value(&record), "Warp Star");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub source: ColumnSource,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSource {
+    Url,
+    Status,
+    FetchedAt,
+    Digest,
+    /// A response header, compared case-insensitively.
+    Header(String),
+    /// Extraction output selected from the record's fields.
+    Field(JsonPath),
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, source: ColumnSource) -> Self {
+        Self {
+            name: name.into(),
+            source,
+        }
+    }
+
+    /// A column of extraction output, e.g. `$.product.price`.
+    pub fn field(name: impl Into<String>, path: &str) -> Result<Self, JsonPathError> {
+        Ok(Self::new(name, ColumnSource::Field(JsonPath::parse(path)?)))
+    }
+
+    /// The `url`, `status`, `fetched_at` and `digest` columns.
+    pub fn defaults() -> Vec<Column> {
+        vec![
+            Self::new("url", ColumnSource::Url),
+            Self::new("status", ColumnSource::Status),
+            Self::new("fetched_at", ColumnSource::FetchedAt),
+            Self::new("digest", ColumnSource::Digest),
+        ]
+    }
+
+    /// The column's value for a record, `null` when it's missing.
+    ///
+    /// A field path matching several values gives them as an array.
+    pub fn value(&self, record: &PageRecord) -> Value {
+        match &self.source {
+            ColumnSource::Url => record.url.as_str().into(),
+            ColumnSource::Status => record.status.into(),
+            ColumnSource::FetchedAt => record.fetched_at.to_rfc3339().into(),
+            ColumnSource::Digest => record.digest.clone().into(),
+            ColumnSource::Header(name) => record.header(name).into(),
+            ColumnSource::Field(path) => {
+                let mut values = path.select(&record.fields);
+                match values.len() {
+                    0 => Value::Null,
+                    1 => values.remove(0).clone(),
+                    _ => values.into_iter().cloned().collect(),
+                }
+            }
+        }
+    }
+}
+
+/// How an output file is compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// The extension added to the file name, e.g. `.gz`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Gzip => ".gz",
+            #[cfg(feature = "zstd")]
+            Self::Zstd => ".zst",
+        }
+    }
+}
+
+/// A file being written through its compressor.
+pub(crate) enum CompressedFile {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl CompressedFile {
+    pub(crate) fn create(
+        path: &std::path::Path,
+        compression: Compression,
+    ) -> io::Result<CompressedFile> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match compression {
+            Compression::None => Self::Plain(file),
+            Compression::Gzip => Self::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Self::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Writes the compressor's trailer and flushes the file.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            Self::Plain(file) => file,
+            Self::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        file.flush()
+    }
+}
+
+impl Write for CompressedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use serde_json::{Map, Value};
+
+use super::{Column, ColumnSource, Sink};
+use crate::extract::json_path::JsonPath;
+use crate::record::PageRecord;
+
+/// How a nested value is written to a single cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flatten {
+    /// Arrays are joined with the separator, objects are written as JSON.
+    Join(String),
+    /// Only the first element of an array is written.
+    First,
+    /// Arrays and objects are written as JSON.
+    Json,
+}
+
+impl Default for Flatten {
+    fn default() -> Self {
+        Self::Join("; ".to_string())
+    }
+}
+
+impl Flatten {
+    /// The text of a cell, empty for `null`.
+    pub fn cell(&self, value: &Value) -> String {
+        match (self, value) {
+            (_, Value::Null) => String::new(),
+            (_, Value::String(text)) => text.clone(),
+            (_, Value::Bool(_) | Value::Number(_)) => value.to_string(),
+            (Self::Join(separator), Value::Array(items)) => items
+                .iter()
+                .filter(|item| !item.is_null())
+                .map(|item| self.cell(item))
+                .collect::<Vec<_>>()
+                .join(separator),
+            (Self::First, Value::Array(items)) => items
+                .first()
+                .map(|item| self.cell(item))
+                .unwrap_or_default(),
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// Writes the chosen columns of every page as CSV, starting with a header row.
+///
+/// Nested values are flattened into a cell by a [`Flatten`] rule, which can be set for all
+/// columns or per column. Use [`columns_from_sample`] to get one column per leaf of an
+/// extraction output instead.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::export::csv::CsvSink;
+/// use kirby_core::export::{Column, ColumnSource, Sink};
+/// use kirby_core::record::PageRecord;
+/// use serde_json::json;
+/// use url::Url;
+///
+/// let columns = vec![
+///     Column::new("url", ColumnSource::Url),
+///     Column::field("tags", "$.tags").unwrap(),
+/// ];
+/// let mut sink = CsvSink::new(Vec::new(), columns);
+///
+/// let mut record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// record.fields = json!({"tags": ["pink", "round"]});
+/// sink.write(&record, None).unwrap();
+///
+/// let csv = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+/// assert_eq!(csv, "url,tags\nhttps://example.com/,pink; round\n");
+/// ```
+pub struct CsvSink<W: Write> {
+    writer: csv::Writer<W>,
+    columns: Vec<Column>,
+    flatten: Flatten,
+    column_flatten: HashMap<String, Flatten>,
+    wrote_header: bool,
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(writer: W, columns: Vec<Column>) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(writer),
+            columns,
+            flatten: Flatten::default(),
+            column_flatten: HashMap::new(),
+            wrote_header: false,
+        }
+    }
+
+    /// Sets how nested values are flattened, `Join("; ")` by default.
+    pub fn flatten(mut self, flatten: Flatten) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// Sets how nested values are flattened in one column.
+    pub fn flatten_column(mut self, name: impl Into<String>, flatten: Flatten) -> Self {
+        self.column_flatten.insert(name.into(), flatten);
+        self
+    }
+
+    /// Flushes the output and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.write_header()?;
+        self.writer.into_inner().map_err(|error| error.into_error())
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.wrote_header {
+            self.writer
+                .write_record(self.columns.iter().map(|column| &column.name))?;
+            self.wrote_header = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Sink for CsvSink<W> {
+    fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+        self.write_header()?;
+
+        let cells = self.columns.iter().map(|column| {
+            let flatten = self
+                .column_flatten
+                .get(&column.name)
+                .unwrap_or(&self.flatten);
+            flatten.cell(&column.value(record))
+        });
+        let cells = cells.collect::<Vec<_>>();
+        Ok(self.writer.write_record(&cells)?)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        self.writer.flush()
+    }
+}
+
+/// One column per leaf of a sample extraction output, named by its path with dots, e.g.
+/// `product.offers.price`.
+///
+/// Arrays are treated as leaves and left to the sink's [`Flatten`] rule.
+pub fn columns_from_sample(fields: &Value) -> Vec<Column> {
+    let mut columns = Vec::new();
+    if let Value::Object(object) = fields {
+        add_leaves(object, &mut Vec::new(), &mut columns);
+    }
+    columns
+}
+
+fn add_leaves(object: &Map<String, Value>, path: &mut Vec<String>, columns: &mut Vec<Column>) {
+    for (key, value) in object {
+        path.push(key.clone());
+        match value {
+            Value::Object(inner) if !inner.is_empty() => add_leaves(inner, path, columns),
+            _ => {
+                // A JSON pointer, so keys containing dots or quotes are addressed as they are.
+                let pointer = path
+                    .iter()
+                    .map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+                    .collect::<String>();
+                // Unwrapping is safe here because every pointer is a valid path.
+                let source = ColumnSource::Field(JsonPath::parse(&pointer).unwrap());
+                columns.push(Column::new(path.join("."), source));
+            }
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+
+    fn record(fields: Value) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let mut record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, date);
+        record.fields = fields;
+        record
+    }
+
+    #[test]
+    fn flattens_nested_values() {
+        let value = json!([{"a": 1}, "b", null]);
+        assert_eq!(Flatten::Join("|".to_string()).cell(&value), r#"{"a":1}|b"#);
+        assert_eq!(Flatten::First.cell(&value), r#"{"a":1}"#);
+        assert_eq!(Flatten::Json.cell(&value), r#"[{"a":1},"b",null]"#);
+        assert_eq!(Flatten::Json.cell(&json!("plain")), "plain");
+    }
+
+    #[test]
+    fn writes_selected_columns_with_overrides() {
+        let columns = vec![
+            Column::new("status", ColumnSource::Status),
+            Column::field("names", "$.items[*].name").unwrap(),
+            Column::field("prices", "$.items[*].price").unwrap(),
+        ];
+        let mut sink = CsvSink::new(Vec::new(), columns).flatten_column("prices", Flatten::First);
+        sink.write(
+            &record(json!({"items": [{"name": "Star, Warp", "price": 5}, {"name": "Tomato", "price": 2}]})),
+            None,
+        )
+        .unwrap();
+        sink.write(&record(Value::Null), None).unwrap();
+
+        let csv = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "status,names,prices\n200,\"Star, Warp; Tomato\",5\n200,,\n"
+        );
+    }
+
+    #[test]
+    fn derives_columns_from_a_sample() {
+        let fields = json!({"title": "Kirby", "product": {"price": 5, "a/b": true}, "tags": []});
+        let columns = columns_from_sample(&fields);
+
+        let names = columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["product.a/b", "product.price", "tags", "title"]);
+        assert_eq!(columns[0].value(&record(fields)), true);
+    }
+}
@@ -0,0 +1,338 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use scraper::Html;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::Sink;
+use crate::extract::visible_text;
+use crate::record::PageRecord;
+
+/// Bulk-indexes pages into Elasticsearch or OpenSearch.
+///
+/// Each page becomes a document holding the serialized [`PageRecord`], its host and, when the
+/// body is given, its visible text under `text`. Documents are keyed by a hash of the URL, so
+/// recrawling a page replaces its document.
+///
+/// The index name may contain `{host}` and `{date}` (the fetch date as `2026.05.01`), e.g.
+/// `kirby-{host}-{date}`. Documents are sent through the `_bulk` API every `batch_size` pages.
+/// When the cluster answers `429 Too Many Requests`, for the whole request or single
+/// documents, those are retried with exponential backoff. [`write`](Sink::write) blocks while
+/// that happens, so a crawl feeding the sink slows down to the pace the cluster accepts.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::export::elasticsearch::ElasticsearchSink;
+/// use serde_json::json;
+/// use url::Url;
+///
+/// let endpoint = Url::parse("http://localhost:9200/").unwrap();
+/// let sink = ElasticsearchSink::new(endpoint)
+///     .index("kirby-{date}")
+///     .template(
+///         "kirby",
+///         json!({
+///             "index_patterns": ["kirby-*"],
+///             "template": {"mappings": {"properties": {"text": {"type": "text"}}}}
+///         }),
+///     )
+///     .header("Authorization", "ApiKey c2VjcmV0");
+/// ```
+pub struct ElasticsearchSink {
+    agent: ureq::Agent,
+    endpoint: Url,
+    index: String,
+    template: Option<(String, Value)>,
+    template_installed: bool,
+    headers: Vec<(String, String)>,
+    batch_size: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    pending: Vec<String>,
+}
+
+impl ElasticsearchSink {
+    /// Indexes into `kirby` on the cluster at `endpoint`, 500 documents at a time.
+    pub fn new(mut endpoint: Url) -> Self {
+        if !endpoint.path().ends_with('/') {
+            endpoint.set_path(&format!("{}/", endpoint.path()));
+        }
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(60))
+                .build(),
+            endpoint,
+            index: "kirby".to_string(),
+            template: None,
+            template_installed: false,
+            headers: Vec::new(),
+            batch_size: 500,
+            max_retries: 5,
+            retry_delay: Duration::from_millis(500),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Sets the index name, which may contain `{host}` and `{date}`.
+    pub fn index(mut self, index: impl Into<String>) -> Self {
+        self.index = index.into();
+        self
+    }
+
+    /// Installs an index template before the first documents are sent.
+    ///
+    /// The body is sent as is to `_index_template/<name>`, so it holds the index patterns
+    /// and the mappings.
+    pub fn template(mut self, name: impl Into<String>, body: Value) -> Self {
+        self.template = Some((name.into(), body));
+        self
+    }
+
+    /// Adds a header to every request, e.g. `Authorization`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how often a rate-limited request is retried, and the delay before the first
+    /// retry, which doubles with every attempt.
+    pub fn retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    fn index_name(&self, record: &PageRecord) -> String {
+        self.index
+            .replace("{host}", record.url.host_str().unwrap_or_default())
+            .replace("{date}", &record.fetched_at.format("%Y.%m.%d").to_string())
+            .to_lowercase()
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> io::Result<SendResult> {
+        let url = self.endpoint.join(path).map_err(io::Error::other)?;
+        let mut request = self
+            .agent
+            .request(method, url.as_str())
+            .set("Content-Type", content_type);
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+
+        match request.send_bytes(body) {
+            Ok(response) => Ok(SendResult::Ok(response.into_string()?)),
+            Err(ureq::Error::Status(429, _)) => Ok(SendResult::RateLimited),
+            Err(ureq::Error::Status(status, response)) => Err(io::Error::other(format!(
+                "{method} {url} failed with {status}: {}",
+                response.into_string().unwrap_or_default()
+            ))),
+            Err(error) => Err(io::Error::other(error)),
+        }
+    }
+
+    fn install_template(&mut self) -> io::Result<()> {
+        if let (Some((name, body)), false) = (&self.template, self.template_installed) {
+            let path = format!("_index_template/{name}");
+            if let SendResult::RateLimited =
+                self.send("PUT", &path, "application/json", &serde_json::to_vec(body)?)?
+            {
+                return Err(io::Error::other(
+                    "rate limited while installing the index template",
+                ));
+            }
+            self.template_installed = true;
+        }
+        Ok(())
+    }
+
+    /// Sends the buffered documents, retrying the ones that were rate limited.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.install_template()?;
+
+        let mut items = std::mem::take(&mut self.pending);
+        let mut attempt = 0;
+        while !items.is_empty() {
+            let body = items.concat();
+            match self.send("POST", "_bulk", "application/x-ndjson", body.as_bytes())? {
+                SendResult::RateLimited => {}
+                SendResult::Ok(response) => {
+                    let response: Value = serde_json::from_str(&response)?;
+                    let results = response["items"].as_array().cloned().unwrap_or_default();
+
+                    let mut retry = Vec::new();
+                    let mut rejected = Vec::new();
+                    for (item, result) in items.into_iter().zip(results) {
+                        // Each result is keyed by the action, e.g. `{"index": {"status": 201}}`.
+                        let result = result.as_object().and_then(|o| o.values().next().cloned());
+                        let result = result.unwrap_or_default();
+                        match result["status"].as_u64() {
+                            Some(429) => retry.push(item),
+                            Some(status) if status >= 300 => rejected.push(result["error"].clone()),
+                            _ => {}
+                        }
+                    }
+                    if let Some(error) = rejected.first() {
+                        self.pending = retry;
+                        return Err(io::Error::other(format!(
+                            "Elasticsearch rejected {} documents, the first because of {error}",
+                            rejected.len()
+                        )));
+                    }
+                    items = retry;
+                }
+            }
+
+            if !items.is_empty() {
+                attempt += 1;
+                if attempt > self.max_retries {
+                    let count = items.len();
+                    self.pending = items;
+                    return Err(io::Error::other(format!(
+                        "{count} documents were still rate limited after {} retries",
+                        self.max_retries
+                    )));
+                }
+                thread::sleep(self.retry_delay * 2u32.pow(attempt - 1));
+            }
+        }
+        Ok(())
+    }
+}
+
+enum SendResult {
+    Ok(String),
+    RateLimited,
+}
+
+impl Sink for ElasticsearchSink {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let mut document = serde_json::to_value(record)?;
+        if let Value::Object(object) = &mut document {
+            object.insert("host".to_string(), record.url.host_str().into());
+            if let Some(html) = body.and_then(|body| std::str::from_utf8(body).ok()) {
+                let text = visible_text(Html::parse_document(html).root_element());
+                object.insert("text".to_string(), text.into());
+            }
+        }
+
+        let id = Sha256::digest(record.url.as_str().as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        let action = json!({"index": {"_index": self.index_name(record), "_id": id}});
+        self.pending.push(format!("{action}\n{document}\n"));
+
+        if self.pending.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+
+    fn record(path: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = Url::parse("https://Example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        PageRecord::new(url, 200, date)
+    }
+
+    #[test]
+    fn installs_the_template_and_retries_rate_limited_documents() {
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/_index_template/kirby",
+                    Page::json(r#"{"acknowledged": true}"#),
+                )
+                .pages(
+                    "/_bulk",
+                    [
+                        Page::new(429, r#"{"error": "busy"}"#),
+                        Page::json(
+                            r#"{"items": [{"index": {"status": 201}}, {"index": {"status": 429}}]}"#,
+                        ),
+                        Page::json(r#"{"items": [{"index": {"status": 201}}]}"#),
+                    ],
+                ),
+        );
+        let mut sink = ElasticsearchSink::new(server.url().clone())
+            .index("kirby-{host}-{date}")
+            .template("kirby", json!({"index_patterns": ["kirby-*"]}))
+            .batch_size(2)
+            .retries(3, Duration::from_millis(1));
+
+        sink.write(&record("/a"), Some(b"<h1>Hello</h1><script>x</script>"))
+            .unwrap();
+        sink.write(&record("/b"), None).unwrap();
+        sink.finish().unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            (requests[0].method.as_str(), requests[0].path.as_str()),
+            ("PUT", "/_index_template/kirby")
+        );
+        assert_eq!(requests[1].path, "/_bulk");
+        assert_eq!(
+            requests[1].header("content-type"),
+            Some("application/x-ndjson")
+        );
+
+        let lines = String::from_utf8(requests[2].body.clone()).unwrap();
+        let lines = lines
+            .lines()
+            .map(|l| serde_json::from_str::<Value>(l).unwrap());
+        let lines = lines.collect::<Vec<_>>();
+        assert_eq!(lines[0]["index"]["_index"], "kirby-example.com-2026.05.01");
+        assert_eq!(lines[1]["text"], "Hello");
+        assert_eq!(lines[1]["host"], "example.com");
+
+        // Only the rate-limited document is sent again.
+        let retried = String::from_utf8(requests[3].body.clone()).unwrap();
+        assert_eq!(retried.lines().count(), 2);
+        assert!(retried.contains("https://example.com/b"));
+    }
+
+    #[test]
+    fn reports_rejected_documents() {
+        let server = MockServer::start(Site::new().page(
+            "/_bulk",
+            Page::json(
+                r#"{"items": [{"index": {"status": 400, "error": {"type": "mapper_parsing_exception"}}}]}"#,
+            ),
+        ));
+        let mut sink = ElasticsearchSink::new(server.url().clone());
+
+        sink.write(&record("/a"), None).unwrap();
+        let error = sink.finish().unwrap_err();
+        assert!(error.to_string().contains("mapper_parsing_exception"));
+    }
+}
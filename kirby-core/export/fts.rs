@@ -0,0 +1,222 @@
+use std::io;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use scraper::{Html, Selector};
+
+use super::Sink;
+use crate::extract::{element_text, visible_text};
+use crate::record::PageRecord;
+use crate::store::StoreError;
+
+/// A page matching a full-text search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub url: String,
+    pub title: String,
+    /// The matching part of the text, with matches between `[` and `]`.
+    pub snippet: String,
+    /// Lower is better, see SQLite's `bm25`.
+    pub rank: f64,
+}
+
+/// Keeps an SQLite FTS5 index of the pages' title and visible text, for full-text search
+/// over a crawl without running a search engine.
+///
+/// Pages are keyed by their canonical URL, or the URL the final response came from, so a
+/// page recrawled or reached through several URLs is indexed once. Only successful HTML
+/// pages with a body are indexed.
+///
+/// ```sql
+/// CREATE TABLE fts_urls (id INTEGER PRIMARY KEY, url TEXT NOT NULL UNIQUE);
+/// CREATE VIRTUAL TABLE fts_pages USING fts5(title, text);  -- rowid = fts_urls.id
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::export::fts::FtsSink;
+/// use kirby_core::export::Sink;
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let mut sink = FtsSink::in_memory().unwrap();
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// let html = "<title>Kirby</title><p>A pink puffball who inhales his enemies.</p>";
+/// sink.write(&record, Some(html.as_bytes())).unwrap();
+///
+/// let hits = sink.search("puffball", 10).unwrap();
+/// assert_eq!(hits[0].title, "Kirby");
+/// assert_eq!(hits[0].snippet, "A pink [puffball] who inhales his enemies.");
+/// ```
+pub struct FtsSink {
+    connection: Connection,
+}
+
+impl FtsSink {
+    /// Opens or creates a database file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Creates an index that only lives in memory.
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Uses an existing connection, creating the tables when needed.
+    pub fn from_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS fts_urls (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL UNIQUE
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS fts_pages USING fts5(
+                title,
+                text,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );",
+        )?;
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Finds the pages matching an FTS5 query, e.g. `kirby AND "star rod"`, best first.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, StoreError> {
+        let mut statement = self.connection.prepare(
+            "SELECT u.url, f.title, snippet(fts_pages, 1, '[', ']', '…', 16), f.rank
+             FROM fts_pages f JOIN fts_urls u ON u.id = f.rowid
+             WHERE fts_pages MATCH ?1
+             ORDER BY f.rank
+             LIMIT ?2",
+        )?;
+        let hits = statement
+            .query_map(params![query, limit as i64], |row| {
+                Ok(SearchHit {
+                    url: row.get(0)?,
+                    title: row.get(1)?,
+                    snippet: row.get(2)?,
+                    rank: row.get(3)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(hits)
+    }
+
+    /// Removes a page from the index, returning whether it was indexed.
+    pub fn remove(&mut self, url: &str) -> Result<bool, StoreError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM fts_pages WHERE rowid = (SELECT id FROM fts_urls WHERE url = ?1)",
+            params![url],
+        )?;
+        let removed = transaction.execute("DELETE FROM fts_urls WHERE url = ?1", params![url])?;
+        transaction.commit()?;
+        Ok(removed > 0)
+    }
+
+    fn index(&mut self, url: &str, title: &str, text: &str) -> Result<(), StoreError> {
+        let transaction = self.connection.transaction()?;
+        let id: i64 = transaction.query_row(
+            "INSERT INTO fts_urls (url) VALUES (?1)
+             ON CONFLICT (url) DO UPDATE SET url = excluded.url
+             RETURNING id",
+            params![url],
+            |row| row.get(0),
+        )?;
+        transaction.execute("DELETE FROM fts_pages WHERE rowid = ?1", params![id])?;
+        transaction.execute(
+            "INSERT INTO fts_pages (rowid, title, text) VALUES (?1, ?2, ?3)",
+            params![id, title, text],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl Sink for FtsSink {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let html = match body.map(std::str::from_utf8) {
+            Some(Ok(html)) if (200..300).contains(&record.status) => html,
+            _ => return Ok(()),
+        };
+        let is_html = record
+            .header("content-type")
+            .is_none_or(|content_type| content_type.contains("html"));
+        if !is_html {
+            return Ok(());
+        }
+
+        let document = Html::parse_document(html);
+        // Unwrapping is safe here because the selector is valid.
+        let selector = Selector::parse("title").unwrap();
+        let title = document
+            .select(&selector)
+            .next()
+            .map(element_text)
+            .unwrap_or_default();
+        let text = visible_text(document.root_element());
+
+        let url = record.canonical_url.as_ref().unwrap_or(record.final_url());
+        self.index(url.as_str(), &title, &text)
+            .map_err(io::Error::other)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use url::Url;
+
+    use super::*;
+
+    fn record(path: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        PageRecord::new(url, 200, date)
+    }
+
+    #[test]
+    fn indexes_each_canonical_url_once() {
+        let mut sink = FtsSink::in_memory().unwrap();
+        let mut duplicate = record("/kirby?ref=nav");
+        duplicate.canonical_url = Some(Url::parse("https://example.com/kirby").unwrap());
+
+        sink.write(
+            &record("/kirby"),
+            Some(b"<title>Kirby</title><p>Old text</p>"),
+        )
+        .unwrap();
+        sink.write(&duplicate, Some(b"<title>Kirby</title><p>Star warrior</p>"))
+            .unwrap();
+        sink.write(
+            &record("/meta"),
+            Some(b"<p>Meta Knight, a star warrior</p>"),
+        )
+        .unwrap();
+        let mut missing = record("/missing");
+        missing.status = 404;
+        sink.write(&missing, Some(b"<p>Star not found</p>"))
+            .unwrap();
+
+        assert!(sink.search("old", 10).unwrap().is_empty());
+        let hits = sink.search("star", 10).unwrap();
+        let urls = hits.iter().map(|hit| hit.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls.len(), 2);
+        assert!(urls.contains(&"https://example.com/kirby"));
+
+        assert!(sink.remove("https://example.com/meta").unwrap());
+        assert_eq!(sink.search("warrior", 10).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,374 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Weak};
+use std::task::{Context, Poll};
+
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, oneshot};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, BoxFuture, BoxStream, Service};
+use tonic::server::{Grpc, NamedService, ServerStreamingService};
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use super::Sink;
+use crate::record::PageRecord;
+use crate::runtime::{BoxFuture as RuntimeFuture, Runtime, TokioRuntime};
+
+/// The messages of `proto/kirby.proto`, written out so building needs no `protoc`.
+pub mod proto {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SubscribeRequest {
+        /// Only pages whose URL starts with this prefix, every page when empty.
+        #[prost(string, tag = "1")]
+        pub url_prefix: String,
+        #[prost(bool, tag = "2")]
+        pub include_bodies: bool,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Page {
+        #[prost(string, tag = "1")]
+        pub url: String,
+        #[prost(uint32, tag = "2")]
+        pub status: u32,
+        /// RFC 3339, e.g. `2026-05-01T08:00:00Z`.
+        #[prost(string, tag = "3")]
+        pub fetched_at: String,
+        /// The [`PageRecord`](crate::record::PageRecord) as JSON.
+        #[prost(string, tag = "4")]
+        pub record: String,
+        #[prost(bytes = "vec", optional, tag = "5")]
+        pub body: Option<Vec<u8>>,
+    }
+}
+
+/// The path of the `Subscribe` method.
+pub const SUBSCRIBE_PATH: &str = "/kirby.v1.Crawl/Subscribe";
+
+/// How many pages a subscriber may fall behind by default.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+type Pages = broadcast::Sender<Arc<proto::Page>>;
+
+/// Streams pages over gRPC to every subscriber, as they are written.
+///
+/// The service is `kirby.v1.Crawl` of `proto/kirby.proto`, from which clients in other
+/// languages can be generated. A subscriber receives the pages written after it subscribed,
+/// optionally only those under a URL prefix, and its stream ends when the sink is finished.
+/// Writes never wait for subscribers: one that falls more than the capacity behind has its
+/// stream ended with `DATA_LOSS`.
+///
+/// The server runs in the background on a [`Runtime`], tokio's by default, so the sink can
+/// be used from synchronous code.
+///
+/// # Example
+///
+/// ```no_run
+/// use chrono::Utc;
+/// use kirby_core::export::grpc::GrpcSink;
+/// use kirby_core::export::Sink;
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let mut sink = GrpcSink::bind("127.0.0.1:50051").unwrap();
+///
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// sink.write(&record, Some(b"<p>Hi</p>")).unwrap();
+/// sink.finish().unwrap();
+/// ```
+pub struct GrpcSink {
+    pages: Option<Arc<Pages>>,
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<mpsc::Receiver<Result<(), tonic::transport::Error>>>,
+}
+
+impl GrpcSink {
+    /// Starts serving on an address, letting subscribers fall [`DEFAULT_CAPACITY`] pages
+    /// behind.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::bind_with_capacity(address, DEFAULT_CAPACITY)
+    }
+
+    pub fn bind_with_capacity(address: impl ToSocketAddrs, capacity: usize) -> io::Result<Self> {
+        Self::bind_with_runtime(address, capacity, Arc::new(TokioRuntime))
+    }
+
+    /// Starts serving with the server spawned on `runtime`. tonic still does its I/O on
+    /// tokio's reactor and serves each connection on a tokio task.
+    pub fn bind_with_runtime(
+        address: impl ToSocketAddrs,
+        capacity: usize,
+        runtime: Arc<dyn Runtime>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let reactor = TokioRuntime::handle();
+
+        let pages = Arc::new(broadcast::channel(capacity.max(1)).0);
+        let service = CrawlService {
+            pages: Arc::downgrade(&pages),
+        };
+        let (shutdown, signal) = oneshot::channel::<()>();
+
+        let incoming = {
+            let _guard = reactor.enter();
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            TcpIncoming::from_listener(listener, true, None).map_err(io::Error::other)?
+        };
+        let (finished, server) = mpsc::channel();
+        let serve = async move {
+            let result = Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async {
+                    signal.await.ok();
+                })
+                .await;
+            finished.send(result).ok();
+        };
+        runtime.spawn(Box::pin(InReactor {
+            reactor,
+            future: Box::pin(serve),
+        }));
+
+        Ok(Self {
+            pages: Some(pages),
+            address,
+            shutdown: Some(shutdown),
+            server: Some(server),
+        })
+    }
+
+    /// The address the server listens on, useful when binding to port 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Ends the subscribers' streams and stops the server once they are sent.
+    fn stop(&mut self) -> io::Result<()> {
+        self.pages = None;
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+        match self.server.take().map(|server| server.recv()) {
+            Some(Ok(result)) => result.map_err(io::Error::other),
+            Some(Err(_)) => Err(io::Error::other("the gRPC server panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Sink for GrpcSink {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let Some(pages) = self
+            .pages
+            .as_ref()
+            .filter(|pages| pages.receiver_count() > 0)
+        else {
+            return Ok(());
+        };
+
+        let page = proto::Page {
+            url: record.url.to_string(),
+            status: record.status.into(),
+            fetched_at: record.fetched_at.to_rfc3339(),
+            record: serde_json::to_string(record)?,
+            body: body.map(<[u8]>::to_vec),
+        };
+        // Sending only fails when every subscriber left in the meantime.
+        pages.send(Arc::new(page)).ok();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.stop()
+    }
+}
+
+impl Drop for GrpcSink {
+    fn drop(&mut self) {
+        self.stop().ok();
+    }
+}
+
+/// Polls a future inside tokio's reactor, whichever runtime it was spawned on.
+struct InReactor {
+    reactor: &'static Handle,
+    future: RuntimeFuture<'static, ()>,
+}
+
+impl Future for InReactor {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let _guard = self.reactor.enter();
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Routes requests to `Subscribe`, the service's only method.
+#[derive(Clone)]
+struct CrawlService {
+    /// Weak, so the streams end when the sink drops the sender.
+    pages: Weak<Pages>,
+}
+
+impl NamedService for CrawlService {
+    const NAME: &'static str = "kirby.v1.Crawl";
+}
+
+impl Service<http::Request<BoxBody>> for CrawlService {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Infallible>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+        if request.uri().path() != SUBSCRIBE_PATH {
+            return Box::pin(async { Ok(Status::unimplemented("unknown method").into_http()) });
+        }
+
+        let subscribe = Subscribe {
+            pages: self.pages.clone(),
+        };
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::default());
+            Ok(grpc.server_streaming(subscribe, request).await)
+        })
+    }
+}
+
+struct Subscribe {
+    pages: Weak<Pages>,
+}
+
+impl ServerStreamingService<proto::SubscribeRequest> for Subscribe {
+    type Response = proto::Page;
+    type ResponseStream = BoxStream<proto::Page>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<proto::SubscribeRequest>) -> Self::Future {
+        let pages = self.pages.upgrade().map(|pages| pages.subscribe());
+        let request = request.into_inner();
+
+        Box::pin(async move {
+            let pages = pages.ok_or_else(|| Status::unavailable("the crawl has finished"))?;
+            let stream = BroadcastStream::new(pages).filter_map(move |page| match page {
+                Ok(page) if !page.url.starts_with(&request.url_prefix) => None,
+                Ok(page) if request.include_bodies => Some(Ok(page.as_ref().clone())),
+                Ok(page) => Some(Ok(proto::Page {
+                    body: None,
+                    ..page.as_ref().clone()
+                })),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => Some(Err(Status::data_loss(
+                    format!("the subscriber fell behind and missed {missed} pages"),
+                ))),
+            });
+            Ok(Response::new(Box::pin(stream) as Self::ResponseStream))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use chrono::TimeZone;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::Channel;
+    use url::Url;
+
+    use super::*;
+
+    fn record(path: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        PageRecord::new(url, 200, date)
+    }
+
+    fn check_streams_pages(mut sink: GrpcSink) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let mut stream = runtime.block_on(async {
+            let channel = Channel::from_shared(format!("http://{}", sink.address()))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut client = tonic::client::Grpc::new(channel);
+            client.ready().await.unwrap();
+            let request = proto::SubscribeRequest {
+                url_prefix: "https://example.com/blog/".to_string(),
+                include_bodies: true,
+            };
+            client
+                .server_streaming(
+                    Request::new(request),
+                    PathAndQuery::from_static(SUBSCRIBE_PATH),
+                    ProstCodec::<proto::SubscribeRequest, proto::Page>::default(),
+                )
+                .await
+                .unwrap()
+                .into_inner()
+        });
+
+        sink.write(&record("/about"), None).unwrap();
+        sink.write(&record("/blog/first"), Some(b"<p>First</p>"))
+            .unwrap();
+        let finished = thread::spawn(move || sink.finish());
+
+        let pages = runtime.block_on(async {
+            let mut pages = Vec::new();
+            while let Some(page) = stream.message().await.unwrap() {
+                pages.push(page);
+            }
+            pages
+        });
+        finished.join().unwrap().unwrap();
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].url, "https://example.com/blog/first");
+        assert_eq!(pages[0].fetched_at, "2026-05-01T08:00:00+00:00");
+        assert_eq!(pages[0].body.as_deref(), Some(&b"<p>First</p>"[..]));
+        let record = PageRecord::from_json(&pages[0].record).unwrap();
+        assert_eq!(record.status, 200);
+    }
+
+    #[test]
+    fn streams_pages_to_subscribers() {
+        check_streams_pages(GrpcSink::bind("127.0.0.1:0").unwrap());
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn serves_on_async_std() {
+        let runtime = Arc::new(crate::runtime::AsyncStdRuntime);
+        check_streams_pages(GrpcSink::bind_with_runtime("127.0.0.1:0", 16, runtime).unwrap());
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn serves_on_smol() {
+        let runtime = Arc::new(crate::runtime::SmolRuntime);
+        check_streams_pages(GrpcSink::bind_with_runtime("127.0.0.1:0", 16, runtime).unwrap());
+    }
+}
@@ -0,0 +1,216 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use data_encoding::BASE64;
+use serde_json::Value;
+
+use super::{CompressedFile, Compression, Sink};
+use crate::record::PageRecord;
+
+/// Writes one JSON object per page to newline-delimited files.
+///
+/// Each line is the serialized [`PageRecord`], with the body added as base64 under `body` when
+/// bodies are included. Files are named `<prefix>-00000.jsonl`, `<prefix>-00001.jsonl` and so on,
+/// a new file is started once the current one holds `max_bytes` of uncompressed JSON.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::export::jsonl::JsonlSink;
+/// use kirby_core::export::{Compression, Sink};
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let mut sink = JsonlSink::new(dir.path(), "pages").compression(Compression::Gzip);
+///
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// sink.write(&record, None).unwrap();
+/// sink.finish().unwrap();
+///
+/// assert_eq!(sink.files(), [dir.path().join("pages-00000.jsonl.gz")]);
+/// ```
+pub struct JsonlSink {
+    directory: PathBuf,
+    prefix: String,
+    compression: Compression,
+    max_bytes: Option<u64>,
+    include_bodies: bool,
+    current: Option<CompressedFile>,
+    current_bytes: u64,
+    files: Vec<PathBuf>,
+}
+
+impl JsonlSink {
+    /// Writes uncompressed files into `directory` without rotating or including bodies.
+    pub fn new(directory: impl AsRef<Path>, prefix: impl Into<String>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            prefix: prefix.into(),
+            compression: Compression::None,
+            max_bytes: None,
+            include_bodies: false,
+            current: None,
+            current_bytes: 0,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Starts a new file once the current one holds this many bytes of uncompressed JSON.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn include_bodies(mut self, include_bodies: bool) -> Self {
+        self.include_bodies = include_bodies;
+        self
+    }
+
+    /// The files written so far, in order.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The open file, starting a new one when there is none or the current one is full.
+    fn file(&mut self) -> io::Result<&mut CompressedFile> {
+        let full = self
+            .max_bytes
+            .is_some_and(|max_bytes| self.current_bytes >= max_bytes);
+        if full {
+            if let Some(file) = self.current.take() {
+                file.finish()?;
+            }
+        }
+
+        if self.current.is_none() {
+            let name = format!(
+                "{}-{:05}.jsonl{}",
+                self.prefix,
+                self.files.len(),
+                self.compression.extension()
+            );
+            let path = self.directory.join(name);
+            self.current = Some(CompressedFile::create(&path, self.compression)?);
+            self.current_bytes = 0;
+            self.files.push(path);
+        }
+
+        // Unwrapping is safe here because a file was opened above.
+        Ok(self.current.as_mut().unwrap())
+    }
+}
+
+impl Sink for JsonlSink {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let mut line = match (self.include_bodies, body) {
+            (true, Some(body)) => {
+                let mut value = serde_json::to_value(record)?;
+                if let Value::Object(object) = &mut value {
+                    object.insert("body".to_string(), BASE64.encode(body).into());
+                }
+                serde_json::to_vec(&value)?
+            }
+            _ => serde_json::to_vec(record)?,
+        };
+        line.push(b'\n');
+
+        self.file()?.write_all(&line)?;
+        self.current_bytes += line.len() as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(file) => file.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+
+    use chrono::TimeZone;
+    use flate2::read::GzDecoder;
+    use url::Url;
+
+    use super::*;
+
+    fn record(path: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        PageRecord::new(
+            Url::parse("https://example.com/")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+            200,
+            date,
+        )
+    }
+
+    fn lines(path: &Path) -> Vec<Value> {
+        let mut text = String::new();
+        let file = fs::File::open(path).unwrap();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => GzDecoder::new(file).read_to_string(&mut text),
+            #[cfg(feature = "zstd")]
+            Some("zst") => zstd::Decoder::new(file).unwrap().read_to_string(&mut text),
+            _ => io::BufReader::new(file).read_to_string(&mut text),
+        }
+        .unwrap();
+        text.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_one_record_per_line_with_bodies() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = JsonlSink::new(dir.path(), "crawl").include_bodies(true);
+        sink.write(&record("/a"), Some(b"<p>a</p>")).unwrap();
+        sink.write(&record("/b"), None).unwrap();
+        sink.finish().unwrap();
+
+        let lines = lines(&sink.files()[0]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["url"], "https://example.com/a");
+        assert_eq!(lines[0]["body"], "PHA+YTwvcD4=");
+        assert_eq!(lines[1].get("body"), None);
+    }
+
+    #[test]
+    fn rotates_compressed_files_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = JsonlSink::new(dir.path(), "crawl")
+            .compression(Compression::Gzip)
+            .max_bytes(1);
+        for path in ["/a", "/b", "/c"] {
+            sink.write(&record(path), None).unwrap();
+        }
+        sink.finish().unwrap();
+
+        assert_eq!(sink.files().len(), 3);
+        assert_eq!(sink.files()[2], dir.path().join("crawl-00002.jsonl.gz"));
+        assert_eq!(lines(&sink.files()[1])[0]["url"], "https://example.com/b");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compresses_with_zstd() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = JsonlSink::new(dir.path(), "crawl").compression(Compression::Zstd);
+        sink.write(&record("/a"), None).unwrap();
+        sink.finish().unwrap();
+
+        assert_eq!(lines(&sink.files()[0])[0]["status"], 200);
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use ::parquet::arrow::ArrowWriter;
+use arrow_array::builder::{
+    BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::DateTime;
+use serde_json::Value;
+
+use super::{Column, ColumnSource, Sink};
+use crate::record::PageRecord;
+
+/// The type a column is stored as.
+///
+/// Values that don't convert to the type are stored as nulls, except for [`Utf8`](Self::Utf8)
+/// which stores anything that isn't a string as JSON text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Utf8,
+    Int64,
+    Float64,
+    Boolean,
+    /// Microseconds in UTC, parsed from RFC 3339 text.
+    Timestamp,
+}
+
+impl ColumnType {
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Utf8 => DataType::Utf8,
+            Self::Int64 => DataType::Int64,
+            Self::Float64 => DataType::Float64,
+            Self::Boolean => DataType::Boolean,
+            Self::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        }
+    }
+}
+
+/// Writes page records to a Parquet file, one row per page, so crawl output loads straight
+/// into DuckDB or Spark.
+///
+/// The schema is given as columns with their types, bodies aren't written. Rows are buffered
+/// and converted to Arrow every `batch_size` pages.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::export::parquet::{default_columns, ColumnType, ParquetSink};
+/// use kirby_core::export::{Column, Sink};
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let mut columns = default_columns();
+/// columns.push((Column::field("price", "$.offers.price").unwrap(), ColumnType::Float64));
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let mut sink = ParquetSink::create(dir.path().join("pages.parquet"), columns).unwrap();
+///
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// sink.write(&record, None).unwrap();
+/// sink.finish().unwrap();
+/// ```
+pub struct ParquetSink<W: Write + Send> {
+    columns: Vec<(Column, ColumnType)>,
+    schema: SchemaRef,
+    writer: Option<ArrowWriter<W>>,
+    rows: Vec<Vec<Value>>,
+    batch_size: usize,
+}
+
+impl ParquetSink<File> {
+    /// Creates a Parquet file.
+    pub fn create(path: impl AsRef<Path>, columns: Vec<(Column, ColumnType)>) -> io::Result<Self> {
+        Self::new(File::create(path)?, columns)
+    }
+}
+
+impl<W: Write + Send> ParquetSink<W> {
+    pub fn new(writer: W, columns: Vec<(Column, ColumnType)>) -> io::Result<Self> {
+        let fields = columns
+            .iter()
+            .map(|(column, column_type)| {
+                let nullable = !matches!(
+                    column.source,
+                    ColumnSource::Url | ColumnSource::Status | ColumnSource::FetchedAt
+                );
+                Field::new(&column.name, column_type.data_type(), nullable)
+            })
+            .collect::<Vec<_>>();
+        let schema = Arc::new(Schema::new(fields));
+        let writer =
+            ArrowWriter::try_new(writer, schema.clone(), None).map_err(io::Error::other)?;
+
+        Ok(Self {
+            columns,
+            schema,
+            writer: Some(writer),
+            rows: Vec::new(),
+            batch_size: 1024,
+        })
+    }
+
+    /// Sets how many pages are buffered before they're converted to Arrow and handed to the
+    /// Parquet writer.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Returns the underlying writer, once the sink is finished.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush_rows()?;
+        match self.writer.take() {
+            Some(writer) => writer.into_inner().map_err(io::Error::other),
+            None => Err(io::Error::other("the Parquet sink is already finished")),
+        }
+    }
+
+    fn flush_rows(&mut self) -> io::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return Err(io::Error::other("the Parquet sink is already finished"));
+        };
+
+        let arrays = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, (_, column_type))| {
+                build_array(*column_type, self.rows.iter().map(|row| &row[index]))
+            })
+            .collect::<Vec<_>>();
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays).map_err(io::Error::other)?;
+        writer.write(&batch).map_err(io::Error::other)?;
+
+        self.rows.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Sink for ParquetSink<W> {
+    fn write(&mut self, record: &PageRecord, _body: Option<&[u8]>) -> io::Result<()> {
+        let row = self
+            .columns
+            .iter()
+            .map(|(column, _)| column.value(record))
+            .collect();
+        self.rows.push(row);
+
+        if self.rows.len() >= self.batch_size {
+            self.flush_rows()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush_rows()?;
+        match self.writer.take() {
+            Some(writer) => writer.close().map(|_| ()).map_err(io::Error::other),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The record's own columns, `url`, `status`, `fetched_at` and `digest`, with their types.
+pub fn default_columns() -> Vec<(Column, ColumnType)> {
+    Column::defaults()
+        .into_iter()
+        .map(|column| {
+            let column_type = match column.source {
+                ColumnSource::Status => ColumnType::Int64,
+                ColumnSource::FetchedAt => ColumnType::Timestamp,
+                _ => ColumnType::Utf8,
+            };
+            (column, column_type)
+        })
+        .collect()
+}
+
+fn build_array<'a>(column_type: ColumnType, values: impl Iterator<Item = &'a Value>) -> ArrayRef {
+    match column_type {
+        ColumnType::Utf8 => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    Value::String(text) => builder.append_value(text),
+                    other => builder.append_value(other.to_string()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Int64 => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                builder.append_option(match value {
+                    Value::String(text) => text.trim().parse().ok(),
+                    value => value.as_i64(),
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Float64 => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                builder.append_option(match value {
+                    Value::String(text) => text.trim().parse().ok(),
+                    value => value.as_f64(),
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            for value in values {
+                builder.append_option(value.as_bool());
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Timestamp => {
+            let mut builder = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+            for value in values {
+                let timestamp = value
+                    .as_str()
+                    .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+                    .map(|time| time.timestamp_micros());
+                builder.append_option(timestamp);
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, Int64Type, TimestampMicrosecondType};
+    use chrono::TimeZone;
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+
+    /// Reads a small file back as one batch.
+    fn read(path: &Path) -> RecordBatch {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        builder.build().unwrap().next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn writes_records_with_mapped_fields() {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let mut columns = default_columns();
+        columns.push((
+            Column::field("price", "$.price").unwrap(),
+            ColumnType::Float64,
+        ));
+        columns.push((Column::field("tags", "$.tags").unwrap(), ColumnType::Utf8));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pages.parquet");
+        let mut sink = ParquetSink::create(&path, columns).unwrap().batch_size(2);
+        for (index, fields) in [
+            json!({"price": "9.50", "tags": ["a", "b"]}),
+            json!({"price": 12}),
+            json!(null),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let url = Url::parse(&format!("https://example.com/{index}")).unwrap();
+            let mut record = PageRecord::new(url, 200, date);
+            record.fields = fields;
+            sink.write(&record, None).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let first = read(&path);
+        assert_eq!(first.num_rows(), 3);
+
+        assert_eq!(
+            first.column(0).as_string::<i32>().value(1),
+            "https://example.com/1"
+        );
+        assert_eq!(first.column(1).as_primitive::<Int64Type>().value(0), 200);
+        assert_eq!(
+            first
+                .column(2)
+                .as_primitive::<TimestampMicrosecondType>()
+                .value(0),
+            date.timestamp_micros()
+        );
+        assert!(first.column(3).is_null(0));
+        let prices = first.column(4).as_primitive::<Float64Type>();
+        assert_eq!((prices.value(0), prices.value(1)), (9.5, 12.0));
+        assert_eq!(first.column(5).as_string::<i32>().value(0), r#"["a","b"]"#);
+        assert!(first.column(4).is_null(2));
+    }
+}
@@ -0,0 +1,358 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use data_encoding::{BASE64, HEXLOWER};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use url::Url;
+
+use super::Sink;
+use crate::record::PageRecord;
+
+/// The header holding the HMAC-SHA256 of the payload, as `sha256=<hex>`.
+pub const SIGNATURE_HEADER: &str = "X-Kirby-Signature";
+
+/// POSTs pages as JSON to an HTTP endpoint.
+///
+/// With a batch size of 1, the default, each payload is a serialized [`PageRecord`], otherwise
+/// it's an array of them. Bodies are added as base64 under `body` when included. With a secret
+/// every payload is signed, see [`SIGNATURE_HEADER`], so the receiver can check where it came
+/// from.
+///
+/// Failed deliveries are retried with exponential backoff on connection errors, `429` and
+/// `5xx` responses. When the retries run out, the payload is written to the spool directory if
+/// there is one, instead of failing the crawl. Spooled payloads are delivered, oldest first,
+/// before the next payload, and new payloads are spooled too while that fails, so an outage
+/// only costs one series of retries. They survive restarts: a sink using the same spool
+/// delivers them once the endpoint is back.
+///
+/// # Example
+///
+/// ```no_run
+/// use chrono::Utc;
+/// use kirby_core::export::webhook::WebhookSink;
+/// use kirby_core::export::Sink;
+/// use kirby_core::record::PageRecord;
+/// use url::Url;
+///
+/// let endpoint = Url::parse("https://hooks.example.com/kirby").unwrap();
+/// let mut sink = WebhookSink::new(endpoint)
+///     .secret("s3cret")
+///     .batch_size(50)
+///     .spool("/var/spool/kirby");
+///
+/// let record = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// sink.write(&record, None).unwrap();
+/// sink.finish().unwrap();
+/// ```
+pub struct WebhookSink {
+    agent: ureq::Agent,
+    endpoint: Url,
+    secret: Option<Vec<u8>>,
+    headers: Vec<(String, String)>,
+    batch_size: usize,
+    include_bodies: bool,
+    max_retries: u32,
+    retry_delay: Duration,
+    spool: Option<PathBuf>,
+    spooled: u64,
+    pending: Vec<Value>,
+}
+
+impl WebhookSink {
+    /// Delivers each page on its own, unsigned and without a spool.
+    pub fn new(endpoint: Url) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            endpoint,
+            secret: None,
+            headers: Vec::new(),
+            batch_size: 1,
+            include_bodies: false,
+            max_retries: 5,
+            retry_delay: Duration::from_millis(500),
+            spool: None,
+            spooled: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Signs every payload with this secret.
+    pub fn secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.secret = Some(secret.as_ref().to_vec());
+        self
+    }
+
+    /// Adds a header to every request, e.g. `Authorization`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends this many pages per request, as an array, when above 1.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn include_bodies(mut self, include_bodies: bool) -> Self {
+        self.include_bodies = include_bodies;
+        self
+    }
+
+    /// Sets how often a failed delivery is retried, and the delay before the first retry,
+    /// which doubles with every attempt.
+    pub fn retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Keeps payloads that couldn't be delivered in this directory.
+    pub fn spool(mut self, directory: impl AsRef<Path>) -> Self {
+        self.spool = Some(directory.as_ref().to_path_buf());
+        self
+    }
+
+    /// Delivers the spooled payloads, oldest first, stopping at the first failure.
+    ///
+    /// Each is tried once. Returns how many are still spooled.
+    pub fn deliver_spooled(&mut self) -> io::Result<usize> {
+        let files = self.spooled_files()?;
+        for (i, path) in files.iter().enumerate() {
+            let payload = fs::read(path)?;
+            match self.send(&payload) {
+                Ok(()) => fs::remove_file(path)?,
+                Err(Failure::Retryable(_)) => return Ok(files.len() - i),
+                Err(Failure::Permanent(error)) => return Err(error),
+            }
+        }
+        Ok(0)
+    }
+
+    /// The spooled payloads, oldest first.
+    fn spooled_files(&self) -> io::Result<Vec<PathBuf>> {
+        let Some(spool) = &self.spool else {
+            return Ok(Vec::new());
+        };
+        let entries = match fs::read_dir(spool) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn write_spool(&mut self, payload: &[u8]) -> io::Result<()> {
+        // Unwrapping is safe here because payloads are only spooled when there is a spool.
+        let spool = self.spool.as_ref().unwrap();
+        fs::create_dir_all(spool)?;
+
+        // Names sort in the order payloads were spooled, also across sinks.
+        let name = format!(
+            "{}-{:06}",
+            Utc::now().format("%Y%m%dT%H%M%S%.6fZ"),
+            self.spooled
+        );
+        self.spooled += 1;
+        let temporary = spool.join(format!("{name}.tmp"));
+        fs::write(&temporary, payload)?;
+        fs::rename(&temporary, spool.join(format!("{name}.json")))
+    }
+
+    fn send(&self, payload: &[u8]) -> Result<(), Failure> {
+        let mut request = self
+            .agent
+            .post(self.endpoint.as_str())
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        if let Some(secret) = &self.secret {
+            request = request.set(SIGNATURE_HEADER, &sign(secret, payload));
+        }
+
+        match request.send_bytes(payload) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, _)) if status == 429 || status >= 500 => Err(
+                Failure::Retryable(io::Error::other(format!("webhook answered {status}"))),
+            ),
+            Err(ureq::Error::Status(status, response)) => {
+                Err(Failure::Permanent(io::Error::other(format!(
+                    "webhook rejected the payload with {status}: {}",
+                    response.into_string().unwrap_or_default()
+                ))))
+            }
+            Err(error) => Err(Failure::Retryable(io::Error::other(error))),
+        }
+    }
+
+    fn deliver(&mut self, payload: &[u8]) -> io::Result<()> {
+        if self.deliver_spooled()? > 0 {
+            return self.write_spool(payload);
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.send(payload) {
+                Ok(()) => return Ok(()),
+                Err(Failure::Permanent(error)) => return Err(error),
+                Err(Failure::Retryable(error)) => {
+                    if attempt == self.max_retries {
+                        return match self.spool {
+                            Some(_) => self.write_spool(payload),
+                            None => Err(error),
+                        };
+                    }
+                    thread::sleep(self.retry_delay * 2u32.pow(attempt));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let payload = match self.pending.len() {
+            0 => return Ok(()),
+            // Unwrapping is safe here because there is exactly one pending page.
+            1 if self.batch_size == 1 => serde_json::to_vec(&self.pending.pop().unwrap())?,
+            _ => serde_json::to_vec(&std::mem::take(&mut self.pending))?,
+        };
+        self.deliver(&payload)
+    }
+}
+
+enum Failure {
+    /// The endpoint may accept the payload later.
+    Retryable(io::Error),
+    Permanent(io::Error),
+}
+
+/// The signature header value for a payload.
+fn sign(secret: &[u8], payload: &[u8]) -> String {
+    // Unwrapping is safe here because HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(payload);
+    format!("sha256={}", HEXLOWER.encode(&mac.finalize().into_bytes()))
+}
+
+impl Sink for WebhookSink {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        let mut value = serde_json::to_value(record)?;
+        if let (true, Some(body), Value::Object(object)) = (self.include_bodies, body, &mut value) {
+            object.insert("body".to_string(), BASE64.encode(body).into());
+        }
+        self.pending.push(value);
+
+        if self.pending.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Sends the last batch. Payloads that still can't be delivered stay in the spool.
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Failure, Page, Site};
+
+    use super::*;
+
+    fn record(path: &str) -> PageRecord {
+        let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        PageRecord::new(url, 200, date)
+    }
+
+    #[test]
+    fn signs_batches_and_retries_server_errors() {
+        let server =
+            MockServer::start(Site::new().page("/", Page::new(200, "").fail(Failure::Status(503))));
+        let mut sink = WebhookSink::new(server.url().clone())
+            .secret("key")
+            .batch_size(2)
+            .include_bodies(true)
+            .retries(2, Duration::from_millis(1));
+
+        sink.write(&record("/a"), Some(b"<p>A</p>")).unwrap();
+        sink.write(&record("/b"), None).unwrap();
+        sink.finish().unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let payload: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(payload[0]["url"], "https://example.com/a");
+        assert_eq!(payload[0]["body"], BASE64.encode(b"<p>A</p>"));
+        assert_eq!(payload[1]["url"], "https://example.com/b");
+        assert_eq!(
+            requests[1].header(SIGNATURE_HEADER),
+            Some(sign(b"key", &requests[1].body).as_str())
+        );
+    }
+
+    #[test]
+    fn spools_payloads_during_outages() {
+        let dir = tempfile::tempdir().unwrap();
+        let flaky = Page::new(200, "")
+            .fail(Failure::Status(500))
+            .fail(Failure::Status(500));
+        let server = MockServer::start(Site::new().page("/", flaky));
+        let mut sink = WebhookSink::new(server.url().clone())
+            .spool(dir.path())
+            .retries(0, Duration::from_millis(1));
+
+        // The first page is spooled after failing, the second because the spool can't be
+        // delivered yet.
+        sink.write(&record("/a"), None).unwrap();
+        sink.write(&record("/b"), None).unwrap();
+        assert_eq!(sink.spooled_files().unwrap().len(), 2);
+
+        assert_eq!(sink.deliver_spooled().unwrap(), 0);
+        let requests = server.requests();
+        let delivered = requests[2..]
+            .iter()
+            .map(|request| serde_json::from_slice::<Value>(&request.body).unwrap()["url"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delivered,
+            ["https://example.com/a", "https://example.com/b"]
+        );
+    }
+
+    #[test]
+    fn fails_on_rejected_payloads() {
+        let server = MockServer::start(Site::new().page("/", Page::new(400, "bad payload")));
+        let mut sink = WebhookSink::new(server.url().clone());
+
+        let error = sink.write(&record("/a"), None).unwrap_err();
+        assert!(error.to_string().contains("bad payload"));
+    }
+}
@@ -0,0 +1,96 @@
+use scraper::{ElementRef, Node};
+
+pub mod contact;
+pub mod from_html;
+pub mod from_json;
+pub mod json_path;
+pub mod opensearch;
+pub mod schema_org;
+pub mod social;
+pub mod structured_data;
+pub mod table;
+
+/// Elements that are rendered on their own line, used to keep line structure in `visible_text`.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Elements whose contents are never shown to a reader.
+const HIDDEN_ELEMENTS: &[&str] = &["head", "noscript", "script", "style", "template"];
+
+/// Collects all text within an element, collapsing runs of whitespace into a single space and
+/// trimming the ends, which is what a reader would see rendered in the browser.
+pub(crate) fn element_text(element: ElementRef) -> String {
+    collapse_whitespace(&element.text().collect::<String>())
+}
+
+/// Collapses all runs of whitespace into a single space and trims the ends.
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Extracts the text a reader would see, skipping scripts, styles and other hidden elements.
+///
+/// Block level elements (paragraphs, list items, table cells, ..) are placed on their own lines
+/// and whitespace within each line is collapsed, blank lines are removed.
+pub fn visible_text(element: ElementRef) -> String {
+    fn walk(element: ElementRef, out: &mut String) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => out.push_str(text),
+                Node::Element(e) if HIDDEN_ELEMENTS.contains(&e.name()) => {}
+                Node::Element(e) => {
+                    let block = BLOCK_ELEMENTS.contains(&e.name());
+                    if block {
+                        out.push('\n');
+                    }
+                    // Unwrapping is safe here because the node is an element.
+                    walk(ElementRef::wrap(child).unwrap(), out);
+                    if block {
+                        out.push('\n');
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut raw = String::new();
+    walk(element, &mut raw);
+
+    raw.lines()
+        .map(collapse_whitespace)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
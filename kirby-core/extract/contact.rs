@@ -0,0 +1,359 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+
+use super::{element_text, visible_text};
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b").unwrap()
+});
+static OBFUSCATED_AT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s*[\[({]\s*(?:at|@)\s*[\])}]\s*").unwrap());
+static OBFUSCATED_DOT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s*[\[({]\s*(?:dot|\.)\s*[\])}]\s*").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\+\d{7,15}|(?:\+\d{1,3}[\s.-]?)?(?:\(\d{1,4}\)[\s.-]?)?\d{2,5}(?:[\s.-]\d{2,5}){1,4}",
+    )
+    .unwrap()
+});
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\d{4}[-./]\d{1,2}[-./]\d{1,2}|\d{1,2}[-./]\d{1,2}[-./]\d{2,4})$").unwrap()
+});
+static PHONE_HINT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(?:phone|tel|telephone|call|fax|mobile|cell)\b").unwrap());
+static STREET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b\d{1,5}\s+(?:[a-z0-9.'-]+\s+){0,4}(?:street|st|avenue|ave|road|rd|boulevard|blvd|lane|ln|drive|dr|court|ct|way|place|pl|terrace|highway|hwy)\b",
+    )
+    .unwrap()
+});
+static POSTCODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b\d{5}(?:-\d{4})?\b|\b[A-Z]{1,2}\d[A-Z\d]?\s*\d[A-Z]{2}\b").unwrap()
+});
+
+/// File extensions that look like a TLD in `name@2x.png` style asset names.
+const ASSET_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "avif"];
+
+/// Contact details found on a page, each list is ordered by descending confidence.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct ContactInfo {
+    pub emails: Vec<Contact>,
+    pub phones: Vec<Contact>,
+    /// Lines that look like postal addresses, these are hints rather than parsed addresses.
+    pub addresses: Vec<Contact>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contact {
+    /// The normalized value, emails are lowercased and phone numbers are reduced to digits
+    /// (keeping a leading `+`).
+    pub value: String,
+    pub source: ContactSource,
+    /// How likely the value is to be real contact information, from 0.0 to 1.0.
+    pub confidence: f32,
+}
+
+/// Where a contact detail was found, which is the main input to its confidence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContactSource {
+    /// A `mailto:` or `tel:` link.
+    Link,
+    /// Semantic markup such as `<address>` or schema.org `PostalAddress`.
+    Markup,
+    /// Plain page text.
+    Text,
+    /// Page text with an obfuscated email like `name [at] example [dot] com`.
+    Obfuscated,
+}
+
+impl ContactInfo {
+    /// Extracts contact details from an HTML document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let html = r#"
+    /// <footer>
+    ///   <a href="mailto:hello@example.com">Email us</a>
+    ///   <p>Support: support [at] example [dot] com, call +1 555 010 9999</p>
+    /// </footer>
+    /// "#;
+    ///
+    /// let contact = kirby_core::extract::contact::ContactInfo::extract(html);
+    /// assert_eq!(contact.emails[0].value, "hello@example.com");
+    /// assert_eq!(contact.emails[1].value, "support@example.com");
+    /// assert_eq!(contact.phones[0].value, "+15550109999");
+    /// ```
+    pub fn extract(html: &str) -> Self {
+        let document = Html::parse_document(html);
+        Self::from_element(document.root_element())
+    }
+
+    /// Extracts contact details from within an element.
+    pub fn from_element(element: ElementRef) -> Self {
+        let mut info = Self::default();
+
+        // Unwrapping is safe here because the selectors are valid constants.
+        let links = Selector::parse(r#"a[href]"#).unwrap();
+        for link in element.select(&links) {
+            let href = link.attr("href").unwrap_or_default().trim();
+            if let Some(mailto) = strip_scheme(href, "mailto:") {
+                let addresses = mailto.split('?').next().unwrap_or_default();
+                for address in addresses.split(',') {
+                    if EMAIL.is_match(address) {
+                        add(
+                            &mut info.emails,
+                            address.trim().to_lowercase(),
+                            ContactSource::Link,
+                            1.0,
+                        );
+                    }
+                }
+            } else if let Some(tel) = strip_scheme(href, "tel:") {
+                let phone = normalize_phone(tel);
+                if digit_count(&phone) >= 3 {
+                    add(&mut info.phones, phone, ContactSource::Link, 1.0);
+                }
+            }
+        }
+
+        let markup = Selector::parse(
+            r#"address, [itemprop="address"], [itemtype$="schema.org/PostalAddress"]"#,
+        )
+        .unwrap();
+        let properties = Selector::parse("[itemprop]").unwrap();
+        let mut markup_lines: Vec<String> = Vec::new();
+        for address in element.select(&markup) {
+            let text = visible_text(address);
+            let parts = address
+                .select(&properties)
+                .map(element_text)
+                .filter(|p| !p.is_empty())
+                .collect::<Vec<String>>();
+            let value = if parts.is_empty() {
+                text.lines().collect::<Vec<&str>>().join(", ")
+            } else {
+                parts.join(", ")
+            };
+            markup_lines.extend(text.lines().map(str::to_string));
+
+            if !value.is_empty() {
+                let confidence = if address.value().name() == "address" {
+                    0.8
+                } else {
+                    0.9
+                };
+                add(
+                    &mut info.addresses,
+                    value,
+                    ContactSource::Markup,
+                    confidence,
+                );
+            }
+        }
+
+        let text = visible_text(element);
+        for line in text.lines() {
+            extract_emails(line, &mut info.emails);
+            extract_phones(line, &mut info.phones);
+
+            if STREET.is_match(line) && !markup_lines.iter().any(|l| l == line) {
+                let confidence = if POSTCODE.is_match(line) { 0.6 } else { 0.4 };
+                add(
+                    &mut info.addresses,
+                    line.to_string(),
+                    ContactSource::Text,
+                    confidence,
+                );
+            }
+        }
+
+        for list in [&mut info.emails, &mut info.phones, &mut info.addresses] {
+            list.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        }
+
+        info
+    }
+}
+
+fn extract_emails(line: &str, emails: &mut Vec<Contact>) {
+    let plain = EMAIL
+        .find_iter(line)
+        .map(|m| m.as_str().to_lowercase())
+        .collect::<Vec<String>>();
+    for email in &plain {
+        if !is_asset_name(email) {
+            add(emails, email.clone(), ContactSource::Text, 0.9);
+        }
+    }
+
+    let deobfuscated = OBFUSCATED_AT.replace_all(line, "@");
+    let deobfuscated = OBFUSCATED_DOT.replace_all(&deobfuscated, ".");
+    for email in EMAIL.find_iter(&deobfuscated) {
+        let email = email.as_str().to_lowercase();
+        if !plain.contains(&email) && !is_asset_name(&email) {
+            add(emails, email, ContactSource::Obfuscated, 0.7);
+        }
+    }
+}
+
+fn extract_phones(line: &str, phones: &mut Vec<Contact>) {
+    let hinted = PHONE_HINT.is_match(line);
+    for m in PHONE.find_iter(line) {
+        let candidate = m.as_str();
+
+        // Skip numbers embedded in larger tokens, such as ids or parts of URLs.
+        let before = line[..m.start()].chars().next_back();
+        let after = line[m.end()..].chars().next();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '/' || c == '-')
+            || after.is_some_and(|c| c.is_alphanumeric() || c == '/')
+        {
+            continue;
+        }
+
+        let digits = digit_count(candidate);
+        if !(7..=15).contains(&digits) || DATE.is_match(candidate) {
+            continue;
+        }
+
+        let formatted = candidate.starts_with('+') || candidate.contains('(');
+        let mut confidence: f32 = if formatted { 0.7 } else { 0.5 };
+        if hinted {
+            confidence += 0.2;
+        }
+        add(
+            phones,
+            normalize_phone(candidate),
+            ContactSource::Text,
+            confidence,
+        );
+    }
+}
+
+/// Adds a contact, when the value was already found only the highest confidence is kept.
+fn add(list: &mut Vec<Contact>, value: String, source: ContactSource, confidence: f32) {
+    if let Some(existing) = list.iter_mut().find(|c| c.value == value) {
+        if confidence > existing.confidence {
+            existing.confidence = confidence;
+            existing.source = source;
+        }
+        return;
+    }
+
+    list.push(Contact {
+        value,
+        source,
+        confidence,
+    });
+}
+
+fn strip_scheme<'a>(href: &'a str, scheme: &str) -> Option<&'a str> {
+    if href.len() >= scheme.len() && href[..scheme.len()].eq_ignore_ascii_case(scheme) {
+        Some(&href[scheme.len()..])
+    } else {
+        None
+    }
+}
+
+fn is_asset_name(email: &str) -> bool {
+    email
+        .rsplit('.')
+        .next()
+        .is_some_and(|tld| ASSET_EXTENSIONS.contains(&tld))
+}
+
+/// Reduces a phone number to its digits, keeping a leading `+` for international numbers.
+fn normalize_phone(phone: &str) -> String {
+    let phone = phone.trim();
+    let digits = phone.chars().filter(char::is_ascii_digit);
+    if phone.starts_with('+') {
+        std::iter::once('+').chain(digits).collect()
+    } else {
+        digits.collect()
+    }
+}
+
+fn digit_count(s: &str) -> usize {
+    s.chars().filter(char::is_ascii_digit).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(contacts: &[Contact]) -> Vec<&str> {
+        contacts.iter().map(|c| c.value.as_str()).collect()
+    }
+
+    #[test]
+    fn extracts_emails_from_links_text_and_obfuscation() {
+        let html = r#"
+        <a href="MAILTO:Sales@Example.com?subject=Hi">Sales</a>
+        <p>Write to info@example.org or sales@example.com.</p>
+        <p>Press: press (at) example (dot) co [dot] uk</p>
+        <img src="logo@2x.png" alt="logo@2x.png">
+        <p>logo@2x.png</p>
+        <script>var x = "hidden@example.com";</script>
+        "#;
+
+        let info = ContactInfo::extract(html);
+        assert_eq!(
+            values(&info.emails),
+            vec![
+                "sales@example.com",
+                "info@example.org",
+                "press@example.co.uk"
+            ]
+        );
+        assert_eq!(info.emails[0].source, ContactSource::Link);
+        assert_eq!(info.emails[0].confidence, 1.0);
+        assert_eq!(info.emails[2].source, ContactSource::Obfuscated);
+    }
+
+    #[test]
+    fn extracts_phone_numbers() {
+        let html = r#"
+        <a href="tel:+44-20-7946-0000">Call</a>
+        <p>Phone: (02) 9876 5432</p>
+        <p>Shipped on 2024-01-15, questions? 555 0199</p>
+        <p>Product id ABC1234567 or see /items/555-123-4567</p>
+        <p>+44 20 7946 0000</p>
+        "#;
+
+        let info = ContactInfo::extract(html);
+        assert_eq!(
+            values(&info.phones),
+            vec!["+442079460000", "0298765432", "5550199"]
+        );
+        assert!((info.phones[1].confidence - 0.9).abs() < f32::EPSILON);
+        assert_eq!(info.phones[2].confidence, 0.5);
+    }
+
+    #[test]
+    fn extracts_address_hints() {
+        let html = r#"
+        <div itemscope itemtype="https://schema.org/PostalAddress">
+            <span itemprop="streetAddress">1 Dream Lane</span>
+            <span itemprop="addressLocality">Dream Land</span>
+        </div>
+        <address>Popstar HQ<br>12 Warp Star Road</address>
+        <p>Visit us at 742 Evergreen Terrace, Springfield 49007</p>
+        <p>We have 3 offices and 2 stores.</p>
+        "#;
+
+        let info = ContactInfo::extract(html);
+        assert_eq!(
+            values(&info.addresses),
+            vec![
+                "1 Dream Lane, Dream Land",
+                "Popstar HQ, 12 Warp Star Road",
+                "Visit us at 742 Evergreen Terrace, Springfield 49007",
+            ]
+        );
+        assert_eq!(info.addresses[2].source, ContactSource::Text);
+    }
+}
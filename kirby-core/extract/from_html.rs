@@ -0,0 +1,309 @@
+use std::fmt;
+use std::str::FromStr;
+
+use scraper::{ElementRef, Html};
+
+/// Maps an HTML document onto a struct, usually implemented with `#[derive(FromHtml)]`.
+///
+/// Each field is annotated with a CSS selector and what to take from the matched element, the
+/// selectors are validated when the derive is expanded so typos are compile errors.
+///
+/// | Attribute                                | Extracts                                  |
+/// |------------------------------------------|-------------------------------------------|
+/// | `#[selector("h1")]` or `#[selector("h1", text)]` | the whitespace-collapsed text      |
+/// | `#[selector("a", attr = "href")]`        | the value of an attribute                 |
+/// | `#[selector("main", html)]`              | the outer HTML                            |
+/// | `#[selector("main", inner_html)]`        | the inner HTML                            |
+/// | `#[selector("div.author", nested)]`      | another `FromHtml` type scoped to the match |
+///
+/// Field types decide how many matches are used: `T` requires the first match, `Option<T>` is
+/// `None` when nothing matches and `Vec<T>` collects every match. Values are converted with
+/// `FromStr` so any parsable type (numbers, URLs, ..) can be used. Fields without a selector are
+/// filled with `Default::default()`.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::extract::from_html::FromHtml;
+///
+/// #[derive(FromHtml)]
+/// struct Post {
+///     #[selector("h1.title", text)]
+///     title: String,
+///     #[selector("span.likes")]
+///     likes: u32,
+///     #[selector("a.tag", attr = "href")]
+///     tags: Vec<String>,
+///     #[selector("p.subtitle")]
+///     subtitle: Option<String>,
+/// }
+///
+/// let html = r#"
+/// <h1 class="title">Hello</h1>
+/// <span class="likes">42</span>
+/// <a class="tag" href="/tags/rust">rust</a>
+/// "#;
+///
+/// let post = Post::from_html(html).unwrap();
+/// assert_eq!(post.title, "Hello");
+/// assert_eq!(post.likes, 42);
+/// assert_eq!(post.tags, vec!["/tags/rust"]);
+/// assert_eq!(post.subtitle, None);
+/// ```
+pub trait FromHtml: Sized {
+    /// Extracts the value from within an element, selectors are matched against its descendants.
+    fn from_element(element: ElementRef) -> Result<Self, FromHtmlError>;
+
+    /// Parses a full HTML document and extracts the value from it.
+    fn from_html(html: &str) -> Result<Self, FromHtmlError> {
+        let document = Html::parse_document(html);
+        Self::from_element(document.root_element())
+    }
+}
+
+pub use kirby_derive::FromHtml;
+
+/// What to take from an element matched by a field selector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extract {
+    Text,
+    Html,
+    InnerHtml,
+    Attr(&'static str),
+}
+
+/// A value that can be extracted from a single piece of text, implemented for every `FromStr`
+/// type with a displayable error.
+pub trait FromHtmlValue: Sized {
+    fn from_html_value(value: &str) -> Result<Self, String>;
+}
+
+impl<T> FromHtmlValue for T
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn from_html_value(value: &str) -> Result<Self, String> {
+        value.parse().map_err(|e: T::Err| e.to_string())
+    }
+}
+
+/// Returned when a field of a `FromHtml` type could not be extracted, it records the type, field
+/// and selector so the failing part of the mapping is obvious.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromHtmlError {
+    pub type_name: &'static str,
+    pub field: &'static str,
+    pub selector: &'static str,
+    pub kind: FromHtmlErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromHtmlErrorKind {
+    /// A required field's selector matched nothing.
+    NoMatch,
+    /// The selector matched but the element doesn't have the requested attribute.
+    MissingAttribute(&'static str),
+    /// The extracted text couldn't be parsed into the field's type.
+    Parse { value: String, message: String },
+    /// A nested `FromHtml` type failed.
+    Nested(Box<FromHtmlError>),
+}
+
+impl fmt::Display for FromHtmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to extract `{}.{}` using selector `{}`: ",
+            self.type_name, self.field, self.selector
+        )?;
+        match &self.kind {
+            FromHtmlErrorKind::NoMatch => write!(f, "no element matched"),
+            FromHtmlErrorKind::MissingAttribute(attr) => {
+                write!(f, "matched element has no `{attr}` attribute")
+            }
+            FromHtmlErrorKind::Parse { value, message } => {
+                write!(f, "could not parse {value:?}: {message}")
+            }
+            FromHtmlErrorKind::Nested(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for FromHtmlError {}
+
+/// Support code for `#[derive(FromHtml)]`, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use scraper::ElementRef;
+    pub use scraper::Selector;
+    pub use std::sync::LazyLock;
+
+    use super::{Extract, FromHtml, FromHtmlErrorKind, FromHtmlValue};
+    use crate::extract::element_text;
+
+    fn extract(element: ElementRef, extract: Extract) -> Result<String, FromHtmlErrorKind> {
+        match extract {
+            Extract::Text => Ok(element_text(element)),
+            Extract::Html => Ok(element.html()),
+            Extract::InnerHtml => Ok(element.inner_html()),
+            Extract::Attr(name) => element
+                .attr(name)
+                .map(str::to_string)
+                .ok_or(FromHtmlErrorKind::MissingAttribute(name)),
+        }
+    }
+
+    fn value<T: FromHtmlValue>(element: ElementRef, how: Extract) -> Result<T, FromHtmlErrorKind> {
+        let value = extract(element, how)?;
+        T::from_html_value(&value).map_err(|message| FromHtmlErrorKind::Parse { value, message })
+    }
+
+    fn nested<T: FromHtml>(element: ElementRef) -> Result<T, FromHtmlErrorKind> {
+        T::from_element(element).map_err(|e| FromHtmlErrorKind::Nested(Box::new(e)))
+    }
+
+    pub fn one<T: FromHtmlValue>(
+        element: ElementRef,
+        selector: &Selector,
+        how: Extract,
+    ) -> Result<T, FromHtmlErrorKind> {
+        let matched = element
+            .select(selector)
+            .next()
+            .ok_or(FromHtmlErrorKind::NoMatch)?;
+        value(matched, how)
+    }
+
+    pub fn optional<T: FromHtmlValue>(
+        element: ElementRef,
+        selector: &Selector,
+        how: Extract,
+    ) -> Result<Option<T>, FromHtmlErrorKind> {
+        element
+            .select(selector)
+            .next()
+            .map(|matched| value(matched, how))
+            .transpose()
+    }
+
+    pub fn all<T: FromHtmlValue>(
+        element: ElementRef,
+        selector: &Selector,
+        how: Extract,
+    ) -> Result<Vec<T>, FromHtmlErrorKind> {
+        element
+            .select(selector)
+            .map(|matched| value(matched, how))
+            .collect()
+    }
+
+    pub fn one_nested<T: FromHtml>(
+        element: ElementRef,
+        selector: &Selector,
+    ) -> Result<T, FromHtmlErrorKind> {
+        let matched = element
+            .select(selector)
+            .next()
+            .ok_or(FromHtmlErrorKind::NoMatch)?;
+        nested(matched)
+    }
+
+    pub fn optional_nested<T: FromHtml>(
+        element: ElementRef,
+        selector: &Selector,
+    ) -> Result<Option<T>, FromHtmlErrorKind> {
+        element.select(selector).next().map(nested).transpose()
+    }
+
+    pub fn all_nested<T: FromHtml>(
+        element: ElementRef,
+        selector: &Selector,
+    ) -> Result<Vec<T>, FromHtmlErrorKind> {
+        element.select(selector).map(nested).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, FromHtml)]
+    struct Author {
+        #[selector(".name")]
+        name: String,
+        #[selector("a", attr = "href")]
+        profile: Option<String>,
+    }
+
+    #[derive(Debug, FromHtml)]
+    struct Article {
+        #[selector("h1")]
+        title: String,
+        #[selector(".author", nested)]
+        author: Author,
+        #[selector(".comment", nested)]
+        comments: Vec<Author>,
+        #[selector("article", inner_html)]
+        body: String,
+        word_count: usize,
+    }
+
+    #[test]
+    fn derives_nested_structs() {
+        let html = r#"
+        <h1> A   title </h1>
+        <div class="author"><span class="name">Kirby</span><a href="/kirby">profile</a></div>
+        <article><p>Hi</p></article>
+        <div class="comment"><span class="name">Dedede</span></div>
+        <div class="comment"><span class="name">Waddle Dee</span></div>
+        "#;
+
+        let article = Article::from_html(html).unwrap();
+        assert_eq!(article.title, "A title");
+        assert_eq!(article.author.name, "Kirby");
+        assert_eq!(article.author.profile.as_deref(), Some("/kirby"));
+        assert_eq!(article.body, "<p>Hi</p>");
+        assert_eq!(article.word_count, 0);
+
+        let names = article
+            .comments
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["Dedede", "Waddle Dee"]);
+        assert_eq!(article.comments[1].profile, None);
+    }
+
+    #[test]
+    fn reports_which_field_failed() {
+        #[derive(Debug, FromHtml)]
+        struct Price {
+            #[selector(".price")]
+            amount: f64,
+        }
+
+        let price = Price::from_html(r#"<p class="price"> 2.5 </p>"#).unwrap();
+        assert_eq!(price.amount, 2.5);
+
+        let error = Price::from_html("<p>nothing</p>").unwrap_err();
+        assert_eq!(error.field, "amount");
+        assert_eq!(error.kind, FromHtmlErrorKind::NoMatch);
+        assert_eq!(
+            error.to_string(),
+            "failed to extract `Price.amount` using selector `.price`: no element matched"
+        );
+
+        let error = Price::from_html(r#"<p class="price">free</p>"#).unwrap_err();
+        assert!(
+            matches!(error.kind, FromHtmlErrorKind::Parse { ref value, .. } if value == "free")
+        );
+
+        let error = Article::from_html("<h1>t</h1><div class=\"author\"></div>").unwrap_err();
+        assert_eq!(error.field, "author");
+        let FromHtmlErrorKind::Nested(inner) = error.kind else {
+            panic!("expected nested error");
+        };
+        assert_eq!((inner.type_name, inner.field), ("Author", "name"));
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// Maps a JSON document onto a struct, usually implemented with `#[derive(FromJson)]`.
+///
+/// The JSON counterpart of [`FromHtml`](super::from_html::FromHtml): each field is annotated
+/// with a [`JsonPath`](super::json_path::JsonPath) expression or JSON pointer instead of a CSS
+/// selector, so API responses can be mapped onto the same output types as HTML pages. A type can
+/// derive both and be filled from either.
+///
+/// | Attribute                                | Extracts                                  |
+/// |------------------------------------------|-------------------------------------------|
+/// | `#[json_path("$.name")]`                 | a scalar converted with `FromStr`         |
+/// | `#[json_path("$.meta", json)]`           | any value, deserialized with serde        |
+/// | `#[json_path("$.author", nested)]`       | another `FromJson` type scoped to the match |
+///
+/// Scalars are converted through their text: strings as-is, numbers and booleans as written, so
+/// a field can be any parsable type. Field types decide how many matches are used just like
+/// `FromHtml`: `T` requires the first match, `Option<T>` is `None` when nothing (or only `null`)
+/// matches and `Vec<T>` collects every match, e.g. of `$.tags[*]`. Fields without a path are
+/// filled with `Default::default()`.
+///
+/// Paths are parsed the first time the type is extracted, an invalid path is reported as a
+/// [`FromJsonErrorKind::InvalidPath`] error for that field.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::extract::from_json::FromJson;
+///
+/// #[derive(FromJson)]
+/// struct Post {
+///     #[json_path("$.data.title")]
+///     title: String,
+///     #[json_path("/data/stats/likes")]
+///     likes: u32,
+///     #[json_path("$.data.tags[*].slug")]
+///     tags: Vec<String>,
+///     #[json_path("$.data.subtitle")]
+///     subtitle: Option<String>,
+/// }
+///
+/// let json = r#"{"data": {
+///     "title": "Hello",
+///     "stats": {"likes": 42},
+///     "tags": [{"slug": "rust"}],
+///     "subtitle": null
+/// }}"#;
+///
+/// let post = Post::from_json(json).unwrap();
+/// assert_eq!(post.title, "Hello");
+/// assert_eq!(post.likes, 42);
+/// assert_eq!(post.tags, vec!["rust"]);
+/// assert_eq!(post.subtitle, None);
+/// ```
+pub trait FromJson: Sized {
+    /// Extracts the value from a parsed JSON value, paths are evaluated relative to it.
+    fn from_value(value: &Value) -> Result<Self, FromJsonError>;
+
+    /// Parses a JSON document and extracts the value from it.
+    fn from_json(json: &str) -> Result<Self, FromJsonError> {
+        let value = serde_json::from_str::<Value>(json).map_err(|e| FromJsonError {
+            type_name: short_type_name::<Self>(),
+            field: "",
+            path: "",
+            kind: FromJsonErrorKind::Json(e.to_string()),
+        })?;
+        Self::from_value(&value)
+    }
+}
+
+pub use kirby_derive::FromJson;
+
+/// The type name without its module path, as the derive records it.
+fn short_type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Returned when a field of a `FromJson` type could not be extracted, it records the type, field
+/// and path so the failing part of the mapping is obvious.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromJsonError {
+    pub type_name: &'static str,
+    pub field: &'static str,
+    pub path: &'static str,
+    pub kind: FromJsonErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromJsonErrorKind {
+    /// The document isn't valid JSON.
+    Json(String),
+    /// The field's path couldn't be parsed.
+    InvalidPath(String),
+    /// A required field's path matched nothing.
+    NoMatch,
+    /// The matched value couldn't be converted into the field's type.
+    Parse { value: String, message: String },
+    /// A nested `FromJson` type failed.
+    Nested(Box<FromJsonError>),
+}
+
+impl fmt::Display for FromJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let FromJsonErrorKind::Json(error) = &self.kind {
+            return write!(f, "failed to extract `{}`: {error}", self.type_name);
+        }
+
+        write!(
+            f,
+            "failed to extract `{}.{}` using path `{}`: ",
+            self.type_name, self.field, self.path
+        )?;
+        match &self.kind {
+            FromJsonErrorKind::Json(_) => unreachable!(),
+            FromJsonErrorKind::InvalidPath(message) => write!(f, "{message}"),
+            FromJsonErrorKind::NoMatch => write!(f, "no value matched"),
+            FromJsonErrorKind::Parse { value, message } => {
+                write!(f, "could not parse {value}: {message}")
+            }
+            FromJsonErrorKind::Nested(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for FromJsonError {}
+
+/// Support code for `#[derive(FromJson)]`, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json::Value;
+    pub use std::sync::LazyLock;
+
+    pub use crate::extract::json_path::{JsonPath, JsonPathError};
+
+    use super::{FromJson, FromJsonErrorKind};
+    use crate::extract::from_html::FromHtmlValue;
+
+    type Convert<T> = fn(&Value) -> Result<T, FromJsonErrorKind>;
+
+    pub fn scalar<T: FromHtmlValue>(value: &Value) -> Result<T, FromJsonErrorKind> {
+        let text = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        T::from_html_value(&text).map_err(|message| FromJsonErrorKind::Parse {
+            value: value.to_string(),
+            message,
+        })
+    }
+
+    pub fn json<T: serde::de::DeserializeOwned>(value: &Value) -> Result<T, FromJsonErrorKind> {
+        T::deserialize(value).map_err(|e| FromJsonErrorKind::Parse {
+            value: value.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    pub fn nested<T: FromJson>(value: &Value) -> Result<T, FromJsonErrorKind> {
+        T::from_value(value).map_err(|e| FromJsonErrorKind::Nested(Box::new(e)))
+    }
+
+    fn compiled(path: &Result<JsonPath, JsonPathError>) -> Result<&JsonPath, FromJsonErrorKind> {
+        path.as_ref()
+            .map_err(|e| FromJsonErrorKind::InvalidPath(e.to_string()))
+    }
+
+    pub fn one<T>(
+        value: &Value,
+        path: &Result<JsonPath, JsonPathError>,
+        convert: Convert<T>,
+    ) -> Result<T, FromJsonErrorKind> {
+        let matched = compiled(path)?
+            .select(value)
+            .into_iter()
+            .find(|v| !v.is_null())
+            .ok_or(FromJsonErrorKind::NoMatch)?;
+        convert(matched)
+    }
+
+    pub fn optional<T>(
+        value: &Value,
+        path: &Result<JsonPath, JsonPathError>,
+        convert: Convert<T>,
+    ) -> Result<Option<T>, FromJsonErrorKind> {
+        compiled(path)?
+            .select(value)
+            .into_iter()
+            .find(|v| !v.is_null())
+            .map(convert)
+            .transpose()
+    }
+
+    pub fn all<T>(
+        value: &Value,
+        path: &Result<JsonPath, JsonPathError>,
+        convert: Convert<T>,
+    ) -> Result<Vec<T>, FromJsonErrorKind> {
+        compiled(path)?
+            .select(value)
+            .into_iter()
+            .filter(|v| !v.is_null())
+            .map(convert)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::from_html::FromHtml;
+
+    #[derive(Debug, FromJson, FromHtml)]
+    struct Product {
+        #[selector("h1")]
+        #[json_path("$.name")]
+        name: String,
+        #[selector(".price")]
+        #[json_path("$.offer.price")]
+        price: f64,
+        #[json_path("$.offer", json)]
+        raw_offer: Option<Value>,
+        #[json_path("$.reviews[*]", nested)]
+        reviews: Vec<Review>,
+    }
+
+    #[derive(Debug, FromJson)]
+    struct Review {
+        #[json_path("$.author")]
+        author: String,
+        #[json_path("$.rating")]
+        rating: Option<u8>,
+    }
+
+    #[test]
+    fn shares_types_with_html_extraction() {
+        let json = r#"{
+            "name": "Warp Star",
+            "offer": {"price": "9.99", "currency": "USD"},
+            "reviews": [{"author": "Kirby", "rating": 5}, {"author": "Dedede"}]
+        }"#;
+        let from_json = Product::from_json(json).unwrap();
+        let from_html =
+            Product::from_html(r#"<h1>Warp Star</h1><p class="price">9.99</p>"#).unwrap();
+
+        assert_eq!(from_json.name, from_html.name);
+        assert_eq!(from_json.price, from_html.price);
+        assert_eq!(from_json.raw_offer.unwrap()["currency"], "USD");
+        assert_eq!(from_html.raw_offer, None);
+
+        let reviews = from_json
+            .reviews
+            .iter()
+            .map(|r| (r.author.as_str(), r.rating))
+            .collect::<Vec<(&str, Option<u8>)>>();
+        assert_eq!(reviews, vec![("Kirby", Some(5)), ("Dedede", None)]);
+    }
+
+    #[test]
+    fn reports_which_field_failed() {
+        let error = Review::from_json(r#"{"rating": 3}"#).unwrap_err();
+        assert_eq!(
+            (error.field, error.kind.clone()),
+            ("author", FromJsonErrorKind::NoMatch)
+        );
+        assert_eq!(
+            error.to_string(),
+            "failed to extract `Review.author` using path `$.author`: no value matched"
+        );
+
+        let error = Review::from_json(r#"{"author": "Kirby", "rating": 500}"#).unwrap_err();
+        assert!(matches!(error.kind, FromJsonErrorKind::Parse { ref value, .. } if value == "500"));
+
+        let error = Product::from_json(r#"{"name": "x", "offer": {"price": 1}, "reviews": [{}]}"#)
+            .unwrap_err();
+        let FromJsonErrorKind::Nested(inner) = error.kind else {
+            panic!("expected nested error");
+        };
+        assert_eq!((inner.type_name, inner.field), ("Review", "author"));
+
+        #[derive(Debug, FromJson)]
+        struct Broken {
+            #[json_path("items[0]")]
+            _item: String,
+        }
+        let error = Broken::from_json("{}").unwrap_err();
+        assert!(matches!(error.kind, FromJsonErrorKind::InvalidPath(_)));
+
+        assert!(matches!(
+            Review::from_json("{ nope").unwrap_err().kind,
+            FromJsonErrorKind::Json(_)
+        ));
+    }
+}
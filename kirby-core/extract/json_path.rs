@@ -0,0 +1,347 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+/// A compiled JSONPath expression (`$.items[*].name`) or JSON pointer (`/items/0/name`).
+///
+/// The supported JSONPath subset covers what's needed to address fields in API responses:
+///
+/// | Syntax                  | Selects                                          |
+/// |-------------------------|--------------------------------------------------|
+/// | `$`                     | the root value                                   |
+/// | `.name` or `['name']`   | a member of an object                            |
+/// | `[0]`, `[-1]`           | an array element, negative indices count from the end |
+/// | `[1:3]`, `[:2]`         | a slice of an array                              |
+/// | `.*` or `[*]`           | every member or element                          |
+/// | `..name`, `..*`         | the same, searched at any depth                  |
+///
+/// Filter expressions (`[?(..)]`) and unions aren't supported.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::extract::json_path::JsonPath;
+/// use serde_json::json;
+///
+/// let value = json!({"items": [{"name": "Kirby"}, {"name": "Meta Knight"}]});
+///
+/// let path = JsonPath::parse("$.items[*].name").unwrap();
+/// assert_eq!(path.select(&value), vec!["Kirby", "Meta Knight"]);
+///
+/// let pointer = JsonPath::parse("/items/1/name").unwrap();
+/// assert_eq!(pointer.first(&value).unwrap(), "Meta Knight");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    /// Whether the segment applies at any depth (`..`) rather than to direct children only.
+    recursive: bool,
+    segment: Segment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// An object member, or an array element when the name is an index (JSON pointer tokens).
+    Name(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPathError {
+    pub path: String,
+    /// Byte offset of the invalid part of the expression.
+    pub position: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid JSON path `{}` at position {}: {}",
+            self.path, self.position, self.message
+        )
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+impl JsonPath {
+    /// Parses a JSONPath expression starting with `$`, or a JSON pointer when it starts with `/`
+    /// (or is empty, selecting the root).
+    pub fn parse(path: &str) -> Result<Self, JsonPathError> {
+        let trimmed = path.trim();
+        if trimmed.is_empty() || trimmed.starts_with('/') {
+            return Ok(Self::from_pointer(trimmed));
+        }
+
+        let error = |position: usize, message: &'static str| JsonPathError {
+            path: path.to_string(),
+            position,
+            message,
+        };
+
+        let bytes = trimmed.as_bytes();
+        if bytes[0] != b'$' {
+            return Err(error(0, "expected the expression to start with `$` or `/`"));
+        }
+
+        let mut steps = Vec::new();
+        let mut i = 1;
+        while i < bytes.len() {
+            let recursive = trimmed[i..].starts_with("..");
+            match bytes[i] {
+                b'.' => {
+                    i += if recursive { 2 } else { 1 };
+                    if trimmed[i..].starts_with('[') {
+                        if !recursive {
+                            return Err(error(i, "unexpected `[` after `.`"));
+                        }
+                        parse_bracket(trimmed, &mut i, true, &mut steps)
+                            .map_err(|(position, message)| error(position, message))?;
+                        continue;
+                    }
+
+                    let end = trimmed[i..]
+                        .find(['.', '['])
+                        .map_or(trimmed.len(), |end| i + end);
+                    let name = &trimmed[i..end];
+                    if name.is_empty() {
+                        return Err(error(i, "expected a member name"));
+                    }
+                    let segment = if name == "*" {
+                        Segment::Wildcard
+                    } else {
+                        Segment::Name(name.to_string())
+                    };
+                    steps.push(Step { recursive, segment });
+                    i = end;
+                }
+                b'[' => parse_bracket(trimmed, &mut i, false, &mut steps)
+                    .map_err(|(position, message)| error(position, message))?,
+                _ => return Err(error(i, "expected `.` or `[`")),
+            }
+        }
+
+        Ok(Self { steps })
+    }
+
+    fn from_pointer(pointer: &str) -> Self {
+        let steps = pointer
+            .split('/')
+            .skip(1)
+            .map(|token| Step {
+                recursive: false,
+                segment: Segment::Name(token.replace("~1", "/").replace("~0", "~")),
+            })
+            .collect();
+        Self { steps }
+    }
+
+    /// Every value matched by the path, in document order.
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![value];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for value in current {
+                if step.recursive {
+                    descend(value, &step.segment, &mut next);
+                } else {
+                    apply(value, &step.segment, &mut next);
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// The first value matched by the path.
+    pub fn first<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.select(value).into_iter().next()
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Parses a bracketed segment (`[0]`, `['name']`, `[1:2]`, `[*]`) starting at `*i`.
+fn parse_bracket(
+    path: &str,
+    i: &mut usize,
+    recursive: bool,
+    steps: &mut Vec<Step>,
+) -> Result<(), (usize, &'static str)> {
+    let start = *i + 1;
+    let rest = &path[start..];
+
+    let (segment, len) =
+        if let Some(quote) = rest.chars().next().filter(|c| *c == '\'' || *c == '"') {
+            let close = rest[1..]
+                .find(quote)
+                .ok_or((start, "unterminated quoted name"))?;
+            let name = &rest[1..close + 1];
+            if !rest[close + 2..].starts_with(']') {
+                return Err((start + close + 2, "expected `]`"));
+            }
+            (Segment::Name(name.to_string()), close + 3)
+        } else {
+            let close = rest.find(']').ok_or((start, "expected `]`"))?;
+            let inner = rest[..close].trim();
+            let segment = if inner == "*" {
+                Segment::Wildcard
+            } else if let Some((from, to)) = inner.split_once(':') {
+                let bound = |b: &str| -> Result<Option<i64>, (usize, &'static str)> {
+                    let b = b.trim();
+                    if b.is_empty() {
+                        return Ok(None);
+                    }
+                    b.parse()
+                        .map(Some)
+                        .map_err(|_| (start, "invalid slice bound"))
+                };
+                Segment::Slice(bound(from)?, bound(to)?)
+            } else {
+                Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| (start, "expected an index, slice, `*` or quoted name"))?,
+                )
+            };
+            (segment, close + 1)
+        };
+
+    steps.push(Step { recursive, segment });
+    *i = start + len;
+    Ok(())
+}
+
+fn apply<'a>(value: &'a Value, segment: &Segment, out: &mut Vec<&'a Value>) {
+    match (segment, value) {
+        (Segment::Name(name), Value::Object(object)) => out.extend(object.get(name)),
+        (Segment::Name(name), Value::Array(array)) => {
+            out.extend(name.parse::<usize>().ok().and_then(|i| array.get(i)))
+        }
+        (Segment::Index(index), Value::Array(array)) => {
+            let index = if *index < 0 {
+                array.len() as i64 + index
+            } else {
+                *index
+            };
+            out.extend(usize::try_from(index).ok().and_then(|i| array.get(i)));
+        }
+        (Segment::Slice(from, to), Value::Array(array)) => {
+            let len = array.len() as i64;
+            let clamp = |bound: i64| {
+                let bound = if bound < 0 { len + bound } else { bound };
+                bound.clamp(0, len) as usize
+            };
+            let from = clamp(from.unwrap_or(0));
+            let to = clamp(to.unwrap_or(len));
+            if from < to {
+                out.extend(&array[from..to]);
+            }
+        }
+        (Segment::Wildcard, Value::Object(object)) => out.extend(object.values()),
+        (Segment::Wildcard, Value::Array(array)) => out.extend(array),
+        _ => {}
+    }
+}
+
+/// Applies a segment to a value and all of its descendants.
+fn descend<'a>(value: &'a Value, segment: &Segment, out: &mut Vec<&'a Value>) {
+    apply(value, segment, out);
+    match value {
+        Value::Object(object) => object.values().for_each(|v| descend(v, segment, out)),
+        Value::Array(array) => array.iter().for_each(|v| descend(v, segment, out)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(path: &str, value: &Value) -> Vec<Value> {
+        JsonPath::parse(path)
+            .unwrap()
+            .select(value)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn selects_values() {
+        let value = json!({
+            "store": {
+                "books": [
+                    {"title": "A", "price": 5, "tags": ["x"]},
+                    {"title": "B", "price": 8},
+                    {"title": "C", "price": 13, "meta": {"title": "nested"}}
+                ],
+                "owner.name": "Dedede"
+            }
+        });
+
+        assert_eq!(select("$", &value), vec![value.clone()]);
+        assert_eq!(select("$.store.books[0].title", &value), vec![json!("A")]);
+        assert_eq!(select("$.store.books[-1].price", &value), vec![json!(13)]);
+        assert_eq!(
+            select("$.store.books[:2].title", &value),
+            vec![json!("A"), json!("B")]
+        );
+        assert_eq!(
+            select("$['store'][\"owner.name\"]", &value),
+            vec![json!("Dedede")]
+        );
+        assert_eq!(
+            select("$..title", &value),
+            vec![json!("A"), json!("B"), json!("C"), json!("nested")]
+        );
+        assert_eq!(select("$.store.books[*].tags[*]", &value), vec![json!("x")]);
+        assert_eq!(
+            select("$..books..[0]", &value),
+            vec![json!({"title": "A", "price": 5, "tags": ["x"]}), json!("x")]
+        );
+        assert!(select("$.store.missing[0]", &value).is_empty());
+
+        assert_eq!(select("/store/books/1/title", &value), vec![json!("B")]);
+        assert_eq!(select("/store/owner.name", &value), vec![json!("Dedede")]);
+        assert_eq!(select("", &value), vec![value.clone()]);
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for path in [
+            "store.books",
+            "$.",
+            "$[0",
+            "$['name]",
+            "$[x]",
+            "$.a[1:y]",
+            "$.[0]",
+        ] {
+            assert!(JsonPath::parse(path).is_err(), "{path} should be invalid");
+        }
+
+        let error = JsonPath::parse("$.items[oops]").unwrap_err();
+        assert_eq!(error.position, 8);
+        assert_eq!(
+            error.to_string(),
+            "invalid JSON path `$.items[oops]` at position 8: expected an index, slice, `*` or quoted name"
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use std::fmt;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::{form_urlencoded, Url};
+
+const OPENSEARCH_TYPE: &str = "application/opensearchdescription+xml";
+
+/// A `<link rel="search">` pointing at an OpenSearch description document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpenSearchLink {
+    /// The absolute URL of the description document.
+    pub href: Url,
+    pub title: Option<String>,
+}
+
+/// Finds the OpenSearch description documents advertised by a page, relative links are resolved
+/// against the page's `<base href>` or `page_url`.
+///
+/// Fetching the documents is left to the caller, their bodies can then be parsed with
+/// [`OpenSearchDescription::parse`].
+///
+/// # Example
+///
+/// ```
+/// use url::Url;
+///
+/// let html = r#"
+/// <link rel="search" type="application/opensearchdescription+xml"
+///       title="Example" href="/opensearch.xml">
+/// "#;
+///
+/// let page = Url::parse("https://example.com/docs/").unwrap();
+/// let links = kirby_core::extract::opensearch::discover(html, &page);
+/// assert_eq!(links[0].href.as_str(), "https://example.com/opensearch.xml");
+/// ```
+pub fn discover(html: &str, page_url: &Url) -> Vec<OpenSearchLink> {
+    let document = Html::parse_document(html);
+
+    // Unwrapping is safe here because the selectors are valid constants.
+    let base_selector = Selector::parse("base[href]").unwrap();
+    let base = document
+        .select(&base_selector)
+        .next()
+        .and_then(|base| page_url.join(base.attr("href")?).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let link_selector = Selector::parse("link[rel][href]").unwrap();
+    document
+        .select(&link_selector)
+        .filter(|link| {
+            let rel = link.attr("rel").unwrap_or_default();
+            let link_type = link.attr("type").unwrap_or_default().trim();
+            rel.split_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("search"))
+                && link_type.eq_ignore_ascii_case(OPENSEARCH_TYPE)
+        })
+        .filter_map(|link| {
+            Some(OpenSearchLink {
+                href: base.join(link.attr("href")?.trim()).ok()?,
+                title: link
+                    .attr("title")
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// A parsed OpenSearch 1.1 description document.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct OpenSearchDescription {
+    pub short_name: Option<String>,
+    pub description: Option<String>,
+    pub input_encoding: Option<String>,
+    pub urls: Vec<UrlTemplate>,
+}
+
+/// A `<Url>` element describing how to build a search request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UrlTemplate {
+    pub template: String,
+    /// The MIME type of the search results, e.g. `text/html` or `application/rss+xml`.
+    pub mime_type: String,
+    pub method: String,
+    /// The role of the URL, `results` unless stated otherwise.
+    pub rel: String,
+    pub index_offset: u64,
+    pub page_offset: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenSearchError {
+    /// The document is not well formed XML.
+    Xml(String),
+    /// The root element is not `<OpenSearchDescription>`.
+    NotOpenSearch,
+}
+
+impl fmt::Display for OpenSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(error) => write!(f, "invalid OpenSearch description XML: {error}"),
+            Self::NotOpenSearch => write!(f, "document is not an OpenSearch description"),
+        }
+    }
+}
+
+impl std::error::Error for OpenSearchError {}
+
+impl OpenSearchDescription {
+    /// Parses an OpenSearch description document, unknown elements are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kirby_core::extract::opensearch::OpenSearchDescription;
+    ///
+    /// let xml = r#"
+    /// <OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
+    ///   <ShortName>Example</ShortName>
+    ///   <Url type="text/html" template="https://example.com/search?q={searchTerms}&amp;p={startPage?}"/>
+    /// </OpenSearchDescription>
+    /// "#;
+    ///
+    /// let description = OpenSearchDescription::parse(xml).unwrap();
+    /// let search = description.search_url().unwrap();
+    /// assert_eq!(search.expand("kirby star"), "https://example.com/search?q=kirby+star&p=1");
+    /// ```
+    pub fn parse(xml: &str) -> Result<Self, OpenSearchError> {
+        let mut reader = Reader::from_str(xml);
+        let mut description = Self::default();
+        let mut path: Vec<String> = Vec::new();
+        let mut text = String::new();
+        let mut has_root = false;
+        let xml_error = |e: quick_xml::Error| OpenSearchError::Xml(e.to_string());
+
+        loop {
+            match reader.read_event().map_err(xml_error)? {
+                Event::Start(start) => {
+                    let name = String::from_utf8_lossy(start.local_name().as_ref()).to_string();
+                    if path.is_empty() {
+                        if name != "OpenSearchDescription" {
+                            return Err(OpenSearchError::NotOpenSearch);
+                        }
+                        has_root = true;
+                    }
+                    if path.len() == 1 && name == "Url" {
+                        description.urls.extend(url_template(&start));
+                    }
+                    path.push(name);
+                    text.clear();
+                }
+                Event::Empty(start) => {
+                    let name = start.local_name();
+                    if path.is_empty() {
+                        if name.as_ref() != b"OpenSearchDescription" {
+                            return Err(OpenSearchError::NotOpenSearch);
+                        }
+                        has_root = true;
+                    }
+                    if path.len() == 1 && name.as_ref() == b"Url" {
+                        description.urls.extend(url_template(&start));
+                    }
+                }
+                Event::Text(t) => text.push_str(&t.decode().map_err(|e| xml_error(e.into()))?),
+                Event::CData(t) => text.push_str(&t.decode().map_err(|e| xml_error(e.into()))?),
+                Event::GeneralRef(r) => {
+                    let raw = format!("&{};", r.decode().map_err(|e| xml_error(e.into()))?);
+                    match quick_xml::escape::unescape(&raw) {
+                        Ok(resolved) => text.push_str(&resolved),
+                        Err(_) => text.push_str(&raw),
+                    }
+                }
+                Event::End(_) => {
+                    if path.len() == 2 {
+                        let value = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+                        match path[1].as_str() {
+                            "ShortName" => description.short_name = value,
+                            "Description" => description.description = value,
+                            "InputEncoding" => description.input_encoding = value,
+                            _ => {}
+                        }
+                    }
+                    path.pop();
+                    text.clear();
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        if !has_root {
+            return Err(OpenSearchError::NotOpenSearch);
+        }
+
+        Ok(description)
+    }
+
+    /// The template for searching in a browser: a `text/html` results URL, falling back to the
+    /// first results URL of any type.
+    pub fn search_url(&self) -> Option<&UrlTemplate> {
+        let results = || self.urls.iter().filter(|u| u.rel == "results");
+        results()
+            .find(|u| u.mime_type.eq_ignore_ascii_case("text/html"))
+            .or_else(|| results().next())
+    }
+}
+
+fn url_template(element: &quick_xml::events::BytesStart) -> Option<UrlTemplate> {
+    let attr = |name: &str| -> Option<String> {
+        let attribute = element.try_get_attribute(name).ok()??;
+        Some(attribute.unescape_value().ok()?.trim().to_string())
+    };
+
+    Some(UrlTemplate {
+        template: attr("template")?,
+        mime_type: attr("type").unwrap_or_default(),
+        method: attr("method")
+            .map(|m| m.to_ascii_uppercase())
+            .unwrap_or_else(|| "GET".to_string()),
+        rel: attr("rel").unwrap_or_else(|| "results".to_string()),
+        index_offset: attr("indexOffset")
+            .and_then(|o| o.parse().ok())
+            .unwrap_or(1),
+        page_offset: attr("pageOffset").and_then(|o| o.parse().ok()).unwrap_or(1),
+    })
+}
+
+impl UrlTemplate {
+    /// Builds the URL for the first page of results for a query.
+    ///
+    /// `{searchTerms}` is replaced with the URL encoded terms, `{startIndex}` and `{startPage}`
+    /// with their offsets, encodings with `UTF-8` and `{language}` with `*`. Any other optional
+    /// (`{name?}`) or unknown parameters are left empty.
+    pub fn expand(&self, terms: &str) -> String {
+        let mut expanded = String::with_capacity(self.template.len() + terms.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            expanded.push_str(&rest[..start]);
+
+            let parameter = rest[start + 1..start + end].trim_end_matches('?');
+            match parameter {
+                "searchTerms" => expanded.extend(form_urlencoded::byte_serialize(terms.as_bytes())),
+                "startIndex" => expanded.push_str(&self.index_offset.to_string()),
+                "startPage" => expanded.push_str(&self.page_offset.to_string()),
+                "inputEncoding" | "outputEncoding" => expanded.push_str("UTF-8"),
+                "language" => expanded.push('*'),
+                _ => {}
+            }
+
+            rest = &rest[start + end + 1..];
+        }
+
+        expanded.push_str(rest);
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovers_search_links() {
+        let html = r#"
+        <head>
+            <base href="https://cdn.example.com/assets/">
+            <link rel="search" type="application/opensearchdescription+xml" title=" Docs " href="docs.xml">
+            <link rel="Search alternate" type="Application/OpenSearchDescription+XML" href="https://example.com/all.xml">
+            <link rel="search" type="text/html" href="/search">
+            <link rel="stylesheet" type="application/opensearchdescription+xml" href="/nope.xml">
+        </head>
+        "#;
+
+        let page = Url::parse("https://example.com/page").unwrap();
+        let links = discover(html, &page);
+        assert_eq!(
+            links,
+            vec![
+                OpenSearchLink {
+                    href: Url::parse("https://cdn.example.com/assets/docs.xml").unwrap(),
+                    title: Some("Docs".into()),
+                },
+                OpenSearchLink {
+                    href: Url::parse("https://example.com/all.xml").unwrap(),
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_description_documents() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <os:OpenSearchDescription xmlns:os="http://a9.com/-/spec/opensearch/1.1/">
+            <os:ShortName>Kirby &amp; Friends</os:ShortName>
+            <os:Description><![CDATA[Search <everything>]]></os:Description>
+            <os:InputEncoding>UTF-8</os:InputEncoding>
+            <os:Url type="application/rss+xml" template="https://example.com/rss?q={searchTerms}"/>
+            <os:Url type="text/html" method="post" indexOffset="0"
+                    template="https://example.com/s?q={searchTerms}&amp;i={startIndex}&amp;l={language}&amp;x={custom:param?}"/>
+            <os:Url type="application/x-suggestions+json" rel="suggestions"
+                    template="https://example.com/suggest?q={searchTerms}"/>
+        </os:OpenSearchDescription>
+        "#;
+
+        let description = OpenSearchDescription::parse(xml).unwrap();
+        assert_eq!(description.short_name.as_deref(), Some("Kirby & Friends"));
+        assert_eq!(
+            description.description.as_deref(),
+            Some("Search <everything>")
+        );
+        assert_eq!(description.input_encoding.as_deref(), Some("UTF-8"));
+        assert_eq!(description.urls.len(), 3);
+
+        let search = description.search_url().unwrap();
+        assert_eq!(search.method, "POST");
+        assert_eq!(
+            search.expand("poyo & co"),
+            "https://example.com/s?q=poyo+%26+co&i=0&l=*&x="
+        );
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert_eq!(
+            OpenSearchDescription::parse("<rss><channel/></rss>"),
+            Err(OpenSearchError::NotOpenSearch)
+        );
+        assert!(matches!(
+            OpenSearchDescription::parse("<OpenSearchDescription><ShortName></Oops>"),
+            Err(OpenSearchError::Xml(_))
+        ));
+    }
+}
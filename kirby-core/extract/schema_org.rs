@@ -0,0 +1,460 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::structured_data;
+
+/// A strongly typed schema.org entity recognized from structured data.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entity {
+    Article(Article),
+    Product(Product),
+    Event(Event),
+    Organization(Organization),
+}
+
+impl Entity {
+    /// Extracts the recognized entities from the JSON-LD in an HTML document, items of other
+    /// types are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kirby_core::extract::schema_org::{Availability, Entity};
+    ///
+    /// let html = r#"
+    /// <script type="application/ld+json">
+    /// {
+    ///   "@context": "https://schema.org",
+    ///   "@type": "Product",
+    ///   "name": "Warp Star",
+    ///   "offers": {"price": "19.99", "priceCurrency": "USD",
+    ///              "availability": "https://schema.org/InStock"}
+    /// }
+    /// </script>
+    /// "#;
+    ///
+    /// let Entity::Product(product) = &Entity::extract_all(html)[0] else { panic!() };
+    /// assert_eq!(product.name.as_deref(), Some("Warp Star"));
+    /// assert_eq!(product.price, Some(19.99));
+    /// assert_eq!(product.currency.as_deref(), Some("USD"));
+    /// assert_eq!(product.availability, Some(Availability::InStock));
+    /// ```
+    pub fn extract_all(html: &str) -> Vec<Self> {
+        structured_data::json_ld(html)
+            .iter()
+            .filter_map(Self::from_value)
+            .collect()
+    }
+
+    /// Converts a JSON-LD item based on its `@type`, returning `None` for unsupported types.
+    ///
+    /// Subtypes are recognized as their parent, e.g. `NewsArticle` is an [`Article`] and
+    /// `LocalBusiness` is an [`Organization`].
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let types = many(&value["@type"])
+            .into_iter()
+            .filter_map(Value::as_str)
+            .map(|t| t.rsplit('/').next().unwrap_or(t))
+            .collect::<Vec<&str>>();
+
+        for t in types {
+            if ARTICLE_TYPES.contains(&t) {
+                return Some(Self::Article(Article::from_value(value)));
+            } else if PRODUCT_TYPES.contains(&t) {
+                return Some(Self::Product(Product::from_value(value)));
+            } else if ORGANIZATION_TYPES.contains(&t) {
+                return Some(Self::Organization(Organization::from_value(value)));
+            } else if t.ends_with("Event") {
+                return Some(Self::Event(Event::from_value(value)));
+            }
+        }
+
+        None
+    }
+}
+
+const ARTICLE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "BlogPosting",
+    "TechArticle",
+    "ScholarlyArticle",
+    "Report",
+    "SocialMediaPosting",
+];
+const PRODUCT_TYPES: &[&str] = &[
+    "Product",
+    "ProductGroup",
+    "IndividualProduct",
+    "ProductModel",
+];
+const ORGANIZATION_TYPES: &[&str] = &[
+    "Organization",
+    "Corporation",
+    "LocalBusiness",
+    "OnlineStore",
+    "OnlineBusiness",
+    "NGO",
+    "EducationalOrganization",
+    "NewsMediaOrganization",
+    "GovernmentOrganization",
+    "Store",
+    "Restaurant",
+];
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Article {
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    /// Author names, whether given as strings or `Person`/`Organization` objects.
+    pub authors: Vec<String>,
+    pub publisher: Option<String>,
+    pub date_published: Option<String>,
+    pub date_modified: Option<String>,
+    pub images: Vec<String>,
+    pub url: Option<String>,
+}
+
+impl Article {
+    pub fn from_value(value: &Value) -> Self {
+        Self {
+            headline: text(&value["headline"]).or_else(|| text(&value["name"])),
+            description: text(&value["description"]),
+            authors: many(&value["author"])
+                .into_iter()
+                .filter_map(text)
+                .collect(),
+            publisher: text(&value["publisher"]),
+            date_published: text(&value["datePublished"]),
+            date_modified: text(&value["dateModified"]),
+            images: many(&value["image"]).into_iter().filter_map(url).collect(),
+            url: url(&value["url"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Product {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub sku: Option<String>,
+    pub brand: Option<String>,
+    pub images: Vec<String>,
+    /// The price of the first offer, or the lowest price of an `AggregateOffer`.
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub availability: Option<Availability>,
+    pub rating: Option<f64>,
+    pub review_count: Option<u64>,
+    pub url: Option<String>,
+}
+
+impl Product {
+    pub fn from_value(value: &Value) -> Self {
+        let offer = many(&value["offers"]).into_iter().next();
+        let offer_field = |key: &str| offer.map(|o| &o[key]).unwrap_or(&Value::Null);
+        let rating = &value["aggregateRating"];
+
+        Self {
+            name: text(&value["name"]),
+            description: text(&value["description"]),
+            sku: text(&value["sku"]),
+            brand: text(&value["brand"]),
+            images: many(&value["image"]).into_iter().filter_map(url).collect(),
+            price: number(offer_field("price")).or_else(|| number(offer_field("lowPrice"))),
+            currency: text(offer_field("priceCurrency")),
+            availability: text(offer_field("availability")).map(|a| Availability::parse(&a)),
+            rating: number(&rating["ratingValue"]),
+            review_count: number(&rating["reviewCount"])
+                .or_else(|| number(&rating["ratingCount"]))
+                .map(|n| n as u64),
+            url: url(&value["url"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Event {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// The name of the venue, or the URL for virtual locations.
+    pub location: Option<String>,
+    pub address: Option<String>,
+    pub organizer: Option<String>,
+    /// The status without the schema.org prefix, e.g. `EventScheduled` or `EventCancelled`.
+    pub status: Option<String>,
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub images: Vec<String>,
+    pub url: Option<String>,
+}
+
+impl Event {
+    pub fn from_value(value: &Value) -> Self {
+        let location = many(&value["location"]).into_iter().next();
+        let offer = many(&value["offers"]).into_iter().next();
+        let offer_field = |key: &str| offer.map(|o| &o[key]).unwrap_or(&Value::Null);
+
+        Self {
+            name: text(&value["name"]),
+            description: text(&value["description"]),
+            start_date: text(&value["startDate"]),
+            end_date: text(&value["endDate"]),
+            location: location.and_then(|l| text(l).or_else(|| url(&l["url"]))),
+            address: location.and_then(|l| address(&l["address"])),
+            organizer: text(&value["organizer"]),
+            status: text(&value["eventStatus"]).map(|s| strip_schema_prefix(&s).to_string()),
+            price: number(offer_field("price")).or_else(|| number(offer_field("lowPrice"))),
+            currency: text(offer_field("priceCurrency")),
+            images: many(&value["image"]).into_iter().filter_map(url).collect(),
+            url: url(&value["url"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Organization {
+    pub name: Option<String>,
+    pub legal_name: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub logo: Option<String>,
+    pub email: Option<String>,
+    pub telephone: Option<String>,
+    pub address: Option<String>,
+    /// Links to the organization's other profiles (social networks, Wikipedia, ..).
+    pub same_as: Vec<String>,
+}
+
+impl Organization {
+    pub fn from_value(value: &Value) -> Self {
+        Self {
+            name: text(&value["name"]),
+            legal_name: text(&value["legalName"]),
+            description: text(&value["description"]),
+            url: url(&value["url"]),
+            logo: many(&value["logo"]).into_iter().find_map(url),
+            email: text(&value["email"]).map(|e| e.trim_start_matches("mailto:").to_string()),
+            telephone: text(&value["telephone"]),
+            address: many(&value["address"]).into_iter().find_map(address),
+            same_as: many(&value["sameAs"]).into_iter().filter_map(url).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    InStock,
+    OutOfStock,
+    SoldOut,
+    PreOrder,
+    PreSale,
+    BackOrder,
+    LimitedAvailability,
+    OnlineOnly,
+    InStoreOnly,
+    Discontinued,
+    /// Any value that isn't a known schema.org `ItemAvailability`.
+    Other(String),
+}
+
+impl Availability {
+    /// Parses an availability given as a full schema.org URL or just the name, ignoring case.
+    pub fn parse(availability: &str) -> Self {
+        match strip_schema_prefix(availability)
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "instock" => Self::InStock,
+            "outofstock" => Self::OutOfStock,
+            "soldout" => Self::SoldOut,
+            "preorder" => Self::PreOrder,
+            "presale" => Self::PreSale,
+            "backorder" => Self::BackOrder,
+            "limitedavailability" => Self::LimitedAvailability,
+            "onlineonly" => Self::OnlineOnly,
+            "instoreonly" => Self::InStoreOnly,
+            "discontinued" => Self::Discontinued,
+            _ => Self::Other(availability.to_string()),
+        }
+    }
+}
+
+// The entities deserialize leniently from any JSON-LD shaped value, fields with unexpected
+// types are left empty rather than failing the whole entity.
+macro_rules! lenient_deserialize {
+    ($($ty:ty),*) => {
+        $(
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    Ok(Self::from_value(&Value::deserialize(deserializer)?))
+                }
+            }
+        )*
+    };
+}
+
+lenient_deserialize!(Article, Product, Event, Organization);
+
+/// Treats a single value as a list of one, and null as an empty list.
+fn many(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Null => Vec::new(),
+        Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    }
+}
+
+/// Reads text from a string, number or the `name` of a nested object, using the first item
+/// of arrays.
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(values) => values.iter().find_map(text),
+        Value::Object(object) => object.get("name").and_then(text),
+        _ => None,
+    }
+}
+
+/// Reads a number from a number or numeric string (allowing thousands separators).
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().replace(',', "").parse().ok(),
+        Value::Array(values) => values.iter().find_map(number),
+        _ => None,
+    }
+}
+
+/// Reads a URL from a string or the `url`/`@id` of a nested object (e.g. `ImageObject`).
+fn url(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Object(object) => object
+            .get("url")
+            .or_else(|| object.get("contentUrl"))
+            .or_else(|| object.get("@id"))
+            .and_then(url),
+        _ => None,
+    }
+}
+
+/// Reads an address from a string or a `PostalAddress` object, joining its parts with ", ".
+fn address(value: &Value) -> Option<String> {
+    if let Value::Object(object) = value {
+        let parts = [
+            "streetAddress",
+            "addressLocality",
+            "addressRegion",
+            "postalCode",
+            "addressCountry",
+        ]
+        .iter()
+        .filter_map(|key| object.get(*key).and_then(text))
+        .collect::<Vec<String>>();
+        return (!parts.is_empty()).then(|| parts.join(", "));
+    }
+
+    text(value)
+}
+
+fn strip_schema_prefix(value: &str) -> &str {
+    let value = value.trim();
+    value.rsplit('/').next().unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_articles_with_mixed_shapes() {
+        let value = json!({
+            "@type": ["NewsArticle"],
+            "headline": "Kirby saves the day",
+            "author": [{"@type": "Person", "name": "Waddle Dee"}, "Meta Knight"],
+            "publisher": {"@type": "Organization", "name": "Dream Land Times"},
+            "image": {"@type": "ImageObject", "url": "https://example.com/a.png"},
+            "datePublished": "2024-01-01"
+        });
+
+        let Some(Entity::Article(article)) = Entity::from_value(&value) else {
+            panic!("expected an article");
+        };
+        assert_eq!(article.headline.as_deref(), Some("Kirby saves the day"));
+        assert_eq!(article.authors, vec!["Waddle Dee", "Meta Knight"]);
+        assert_eq!(article.publisher.as_deref(), Some("Dream Land Times"));
+        assert_eq!(article.images, vec!["https://example.com/a.png"]);
+        assert_eq!(article.date_published.as_deref(), Some("2024-01-01"));
+    }
+
+    #[test]
+    fn reads_products_leniently() {
+        let product: Product = serde_json::from_value(json!({
+            "@type": "Product",
+            "name": "Warp Star",
+            "sku": 1234,
+            "brand": {"@type": "Brand", "name": "HAL"},
+            "offers": [{"@type": "AggregateOffer", "lowPrice": "1,299.50", "priceCurrency": "JPY",
+                        "availability": "http://schema.org/PreOrder"}],
+            "aggregateRating": {"ratingValue": 4.5, "reviewCount": "12"},
+            "image": ["https://example.com/1.png", {"url": "https://example.com/2.png"}],
+            "description": ["first", "second"],
+            "url": 7
+        }))
+        .unwrap();
+
+        assert_eq!(product.sku.as_deref(), Some("1234"));
+        assert_eq!(product.brand.as_deref(), Some("HAL"));
+        assert_eq!(product.price, Some(1299.5));
+        assert_eq!(product.currency.as_deref(), Some("JPY"));
+        assert_eq!(product.availability, Some(Availability::PreOrder));
+        assert_eq!(product.rating, Some(4.5));
+        assert_eq!(product.review_count, Some(12));
+        assert_eq!(product.images.len(), 2);
+        assert_eq!(product.description.as_deref(), Some("first"));
+        assert_eq!(product.url, None);
+    }
+
+    #[test]
+    fn reads_events_and_organizations() {
+        let html = r#"
+        <script type="application/ld+json">
+        {"@graph": [
+            {"@type": "MusicEvent", "name": "Gourmet Race", "startDate": "2024-05-01T20:00",
+             "eventStatus": "https://schema.org/EventScheduled",
+             "location": {"@type": "Place", "name": "Dream Stadium",
+                          "address": {"streetAddress": "1 Star Rd", "addressCountry": {"name": "JP"}}},
+             "offers": {"price": 10, "priceCurrency": "USD"}},
+            {"@type": "LocalBusiness", "name": "Kirby's Cafe", "email": "mailto:cafe@example.com",
+             "sameAs": "https://x.com/kirbycafe", "address": "2 Star Rd"},
+            {"@type": "WebPage", "name": "ignored"}
+        ]}
+        </script>
+        "#;
+
+        let entities = Entity::extract_all(html);
+        assert_eq!(entities.len(), 2);
+
+        let Entity::Event(event) = &entities[0] else {
+            panic!("expected an event");
+        };
+        assert_eq!(event.location.as_deref(), Some("Dream Stadium"));
+        assert_eq!(event.address.as_deref(), Some("1 Star Rd, JP"));
+        assert_eq!(event.status.as_deref(), Some("EventScheduled"));
+        assert_eq!(event.price, Some(10.0));
+
+        let Entity::Organization(organization) = &entities[1] else {
+            panic!("expected an organization");
+        };
+        assert_eq!(organization.email.as_deref(), Some("cafe@example.com"));
+        assert_eq!(organization.same_as, vec!["https://x.com/kirbycafe"]);
+        assert_eq!(organization.address.as_deref(), Some("2 Star Rd"));
+    }
+}
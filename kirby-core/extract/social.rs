@@ -0,0 +1,380 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+/// Elements that usually hold a site's own social links, as opposed to links in the content.
+const CHROME_ELEMENTS: &[&str] = &["header", "footer", "nav", "aside"];
+
+/// Links to a site's profiles on well known social platforms, normalized to a canonical URL
+/// (`https`, no `www.`/`m.`, no query, fragment or trailing slash).
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct SocialProfiles {
+    pub twitter: Option<String>,
+    pub linkedin: Option<String>,
+    pub github: Option<String>,
+    pub facebook: Option<String>,
+    pub instagram: Option<String>,
+    pub youtube: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocialPlatform {
+    Twitter,
+    LinkedIn,
+    GitHub,
+    Facebook,
+    Instagram,
+    YouTube,
+}
+
+impl SocialProfiles {
+    /// Extracts social profile links from an HTML document.
+    ///
+    /// Links inside `<header>`, `<footer>`, `<nav>` and `<aside>` elements, `rel="me"` links and
+    /// the `twitter:site` meta tag are preferred over links in the page content, since content
+    /// often links to other people's profiles. Share buttons and links to individual posts are
+    /// ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let html = r#"
+    /// <footer>
+    ///   <a href="https://twitter.com/KirbyBot?ref=footer">Twitter</a>
+    ///   <a href="https://www.github.com/insprac/kirby">GitHub</a>
+    /// </footer>
+    /// "#;
+    ///
+    /// let profiles = kirby_core::extract::social::SocialProfiles::extract(html);
+    /// assert_eq!(profiles.twitter.as_deref(), Some("https://x.com/KirbyBot"));
+    /// assert_eq!(profiles.github.as_deref(), Some("https://github.com/insprac"));
+    /// ```
+    pub fn extract(html: &str) -> Self {
+        let document = Html::parse_document(html);
+        Self::from_element(document.root_element())
+    }
+
+    /// Extracts social profile links from within an element.
+    pub fn from_element(element: ElementRef) -> Self {
+        // (platform, url, preferred) in document order.
+        let mut candidates: Vec<(SocialPlatform, String, bool)> = Vec::new();
+
+        // Unwrapping is safe here because the selectors are valid constants.
+        let twitter_site = Selector::parse(r#"meta[name="twitter:site"]"#).unwrap();
+        for meta in element.select(&twitter_site) {
+            let handle = meta.attr("content").unwrap_or_default().trim();
+            let handle = handle.strip_prefix('@').unwrap_or(handle);
+            if let Some(profile) = parse_profile_url(&format!("https://x.com/{handle}")) {
+                candidates.push((profile.0, profile.1, true));
+            }
+        }
+
+        let links = Selector::parse("a[href], link[href]").unwrap();
+        for link in element.select(&links) {
+            let Some(profile) = link.attr("href").and_then(parse_profile_url) else {
+                continue;
+            };
+            let rel_me = link
+                .attr("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("me")));
+            let in_chrome = link.ancestors().any(|node| {
+                node.value()
+                    .as_element()
+                    .is_some_and(|e| CHROME_ELEMENTS.contains(&e.name()))
+            });
+            candidates.push((profile.0, profile.1, rel_me || in_chrome));
+        }
+
+        let mut profiles = Self::default();
+        for preferred in [true, false] {
+            for (platform, url, _) in candidates.iter().filter(|c| c.2 == preferred) {
+                profiles
+                    .get_mut(*platform)
+                    .get_or_insert_with(|| url.clone());
+            }
+        }
+
+        profiles
+    }
+
+    /// Returns the profile URL for a platform.
+    pub fn get(&self, platform: SocialPlatform) -> Option<&str> {
+        match platform {
+            SocialPlatform::Twitter => self.twitter.as_deref(),
+            SocialPlatform::LinkedIn => self.linkedin.as_deref(),
+            SocialPlatform::GitHub => self.github.as_deref(),
+            SocialPlatform::Facebook => self.facebook.as_deref(),
+            SocialPlatform::Instagram => self.instagram.as_deref(),
+            SocialPlatform::YouTube => self.youtube.as_deref(),
+        }
+    }
+
+    fn get_mut(&mut self, platform: SocialPlatform) -> &mut Option<String> {
+        match platform {
+            SocialPlatform::Twitter => &mut self.twitter,
+            SocialPlatform::LinkedIn => &mut self.linkedin,
+            SocialPlatform::GitHub => &mut self.github,
+            SocialPlatform::Facebook => &mut self.facebook,
+            SocialPlatform::Instagram => &mut self.instagram,
+            SocialPlatform::YouTube => &mut self.youtube,
+        }
+    }
+
+    /// Fills in any missing profiles from another page of the same site, profiles that are
+    /// already known are kept.
+    pub fn merge(&mut self, other: &SocialProfiles) {
+        for platform in [
+            SocialPlatform::Twitter,
+            SocialPlatform::LinkedIn,
+            SocialPlatform::GitHub,
+            SocialPlatform::Facebook,
+            SocialPlatform::Instagram,
+            SocialPlatform::YouTube,
+        ] {
+            if let Some(url) = other.get(platform) {
+                self.get_mut(platform)
+                    .get_or_insert_with(|| url.to_string());
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Recognizes a link to a profile on a known social platform and returns its normalized URL.
+///
+/// Links that are not profiles (posts, videos, share dialogs, ..) return `None`, except for
+/// GitHub repository links which resolve to the owning user or organization.
+pub fn parse_profile_url(href: &str) -> Option<(SocialPlatform, String)> {
+    let url = Url::parse(href.trim()).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    let host = url.host_str()?.to_ascii_lowercase();
+    let host = host
+        .strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .or_else(|| host.strip_prefix("mobile."))
+        .unwrap_or(&host);
+    let segments = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<&str>>())
+        .unwrap_or_default();
+    let first = segments.first().copied().unwrap_or("");
+    let is_handle = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+    };
+
+    match host {
+        "twitter.com" | "x.com" => {
+            const RESERVED: &[&str] = &[
+                "home", "i", "intent", "search", "share", "hashtag", "explore", "settings",
+            ];
+            let valid = segments.len() == 1
+                && first.len() <= 15
+                && first.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !RESERVED.contains(&first.to_ascii_lowercase().as_str());
+            (valid && !first.is_empty())
+                .then(|| (SocialPlatform::Twitter, format!("https://x.com/{first}")))
+        }
+        "linkedin.com" => {
+            let kind = first.to_ascii_lowercase();
+            let slug = segments.get(1).copied().unwrap_or("");
+            (["in", "company", "school"].contains(&kind.as_str()) && is_handle(slug)).then(|| {
+                (
+                    SocialPlatform::LinkedIn,
+                    format!("https://linkedin.com/{kind}/{slug}"),
+                )
+            })
+        }
+        "github.com" => {
+            const RESERVED: &[&str] = &[
+                "about",
+                "features",
+                "login",
+                "pricing",
+                "search",
+                "sponsors",
+                "topics",
+                "marketplace",
+                "settings",
+            ];
+            let user = match first {
+                "orgs" => segments.get(1).copied().unwrap_or(""),
+                user => user,
+            };
+            (is_handle(user) && !RESERVED.contains(&user.to_ascii_lowercase().as_str()))
+                .then(|| (SocialPlatform::GitHub, format!("https://github.com/{user}")))
+        }
+        "facebook.com" | "fb.com" => {
+            if first == "profile.php" {
+                let id = url.query_pairs().find(|(k, _)| k == "id")?.1;
+                return Some((
+                    SocialPlatform::Facebook,
+                    format!("https://facebook.com/profile.php?id={id}"),
+                ));
+            }
+
+            const RESERVED: &[&str] = &["sharer", "sharer.php", "share.php", "dialog", "plugins"];
+            let page = match first {
+                "pg" | "people" => segments.get(1).copied().unwrap_or(""),
+                page => page,
+            };
+            (segments.len() <= 2 && is_handle(page) && !RESERVED.contains(&page)).then(|| {
+                (
+                    SocialPlatform::Facebook,
+                    format!("https://facebook.com/{page}"),
+                )
+            })
+        }
+        "instagram.com" => {
+            const RESERVED: &[&str] = &["p", "reel", "reels", "explore", "stories", "accounts"];
+            (segments.len() == 1 && is_handle(first) && !RESERVED.contains(&first)).then(|| {
+                (
+                    SocialPlatform::Instagram,
+                    format!("https://instagram.com/{first}"),
+                )
+            })
+        }
+        "youtube.com" => {
+            let path = if first.starts_with('@') && is_handle(&first[1..]) {
+                first.to_string()
+            } else if ["channel", "c", "user"].contains(&first)
+                && segments.get(1).is_some_and(|s| is_handle(s))
+            {
+                format!("{first}/{}", segments[1])
+            } else {
+                return None;
+            };
+            Some((
+                SocialPlatform::YouTube,
+                format!("https://youtube.com/{path}"),
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_profile_urls() {
+        let cases = [
+            (
+                "https://twitter.com/KirbyBot/",
+                Some("https://x.com/KirbyBot"),
+            ),
+            (
+                "http://mobile.twitter.com/kirby?lang=en",
+                Some("https://x.com/kirby"),
+            ),
+            ("https://x.com/intent/tweet?text=hi", None),
+            ("https://x.com/kirby/status/1", None),
+            (
+                "https://www.linkedin.com/company/hal-labs/about/",
+                Some("https://linkedin.com/company/hal-labs"),
+            ),
+            ("https://linkedin.com/feed", None),
+            (
+                "https://github.com/insprac/kirby",
+                Some("https://github.com/insprac"),
+            ),
+            (
+                "https://github.com/orgs/rust-lang/people",
+                Some("https://github.com/rust-lang"),
+            ),
+            ("https://github.com/features", None),
+            (
+                "https://m.facebook.com/kirby#top",
+                Some("https://facebook.com/kirby"),
+            ),
+            (
+                "https://facebook.com/profile.php?id=42&ref=x",
+                Some("https://facebook.com/profile.php?id=42"),
+            ),
+            ("https://www.facebook.com/sharer/sharer.php?u=x", None),
+            (
+                "https://instagram.com/kirby",
+                Some("https://instagram.com/kirby"),
+            ),
+            ("https://instagram.com/p/abc123", None),
+            (
+                "https://www.youtube.com/@Kirby",
+                Some("https://youtube.com/@Kirby"),
+            ),
+            (
+                "https://youtube.com/channel/UC123",
+                Some("https://youtube.com/channel/UC123"),
+            ),
+            ("https://youtube.com/watch?v=abc", None),
+            ("mailto:kirby@example.com", None),
+            ("/relative", None),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(
+                parse_profile_url(url).map(|p| p.1),
+                expected.map(str::to_string),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn prefers_links_in_page_chrome() {
+        let html = r#"
+        <head><meta name="twitter:site" content="@KirbyOfficial"></head>
+        <body>
+            <article>
+                <p>Thanks to <a href="https://github.com/someone">someone</a> and
+                <a href="https://instagram.com/friend">a friend</a>.</p>
+                <a href="https://twitter.com/someone">their twitter</a>
+            </article>
+            <footer>
+                <a href="https://github.com/kirby">GitHub</a>
+                <a href="https://www.youtube.com/@kirby/videos">YouTube</a>
+            </footer>
+        </body>
+        "#;
+
+        let profiles = SocialProfiles::extract(html);
+        assert_eq!(
+            profiles,
+            SocialProfiles {
+                twitter: Some("https://x.com/KirbyOfficial".into()),
+                github: Some("https://github.com/kirby".into()),
+                instagram: Some("https://instagram.com/friend".into()),
+                youtube: Some("https://youtube.com/@kirby".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn merges_profiles_across_pages() {
+        let mut site = SocialProfiles::extract(
+            r#"<footer><a href="https://github.com/kirby">GitHub</a></footer>"#,
+        );
+        let about = SocialProfiles::extract(
+            r#"<a rel="me" href="https://linkedin.com/in/kirby">LinkedIn</a>
+               <footer><a href="https://github.com/other">GitHub</a></footer>"#,
+        );
+
+        assert!(!site.is_empty());
+        site.merge(&about);
+        assert_eq!(site.github.as_deref(), Some("https://github.com/kirby"));
+        assert_eq!(
+            site.linkedin.as_deref(),
+            Some("https://linkedin.com/in/kirby")
+        );
+        assert_eq!(site.get(SocialPlatform::Twitter), None);
+    }
+}
@@ -0,0 +1,97 @@
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+/// Extracts the JSON-LD items embedded in an HTML document through
+/// `<script type="application/ld+json">` blocks.
+///
+/// Blocks containing an array or an `@graph` are flattened so every item is returned on its own,
+/// blocks that aren't valid JSON are skipped.
+///
+/// # Example
+///
+/// ```
+/// let html = r#"
+/// <script type="application/ld+json">
+///   {"@context": "https://schema.org", "@graph": [{"@type": "WebSite"}, {"@type": "Person"}]}
+/// </script>
+/// "#;
+///
+/// let items = kirby_core::extract::structured_data::json_ld(html);
+/// assert_eq!(items.len(), 2);
+/// assert_eq!(items[1]["@type"], "Person");
+/// ```
+pub fn json_ld(html: &str) -> Vec<Value> {
+    let document = Html::parse_document(html);
+    json_ld_from_element(document.root_element())
+}
+
+/// Extracts the JSON-LD items within an element, see [`json_ld`].
+pub fn json_ld_from_element(element: ElementRef) -> Vec<Value> {
+    // Unwrapping is safe here because the selector is a valid constant.
+    let selector = Selector::parse("script[type]").unwrap();
+    let mut items = Vec::new();
+
+    for script in element.select(&selector) {
+        let script_type = script.attr("type").unwrap_or_default();
+        if !script_type
+            .trim()
+            .eq_ignore_ascii_case("application/ld+json")
+        {
+            continue;
+        }
+
+        let source = script.text().collect::<String>();
+        if let Ok(value) = serde_json::from_str::<Value>(strip_wrappers(&source)) {
+            flatten(value, &mut items);
+        }
+    }
+
+    items
+}
+
+/// Removes comment and CDATA wrappers that some sites put around their JSON-LD.
+fn strip_wrappers(source: &str) -> &str {
+    let mut source = source.trim();
+    for (start, end) in [
+        ("<!--", "-->"),
+        ("//<![CDATA[", "//]]>"),
+        ("<![CDATA[", "]]>"),
+    ] {
+        if let Some(inner) = source.strip_prefix(start).and_then(|s| s.strip_suffix(end)) {
+            source = inner.trim();
+        }
+    }
+    source
+}
+
+fn flatten(value: Value, items: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => values.into_iter().for_each(|v| flatten(v, items)),
+        Value::Object(mut object) => match object.remove("@graph") {
+            Some(graph) => flatten(graph, items),
+            None => items.push(Value::Object(object)),
+        },
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattens_arrays_and_graphs() {
+        let html = r#"
+        <script type="application/ld+json">[{"@type": "A"}, {"@graph": [{"@type": "B"}]}]</script>
+        <script type="Application/LD+JSON"><!-- {"@type": "C"} --></script>
+        <script type="application/ld+json">{ not json </script>
+        <script type="application/json">{"@type": "D"}</script>
+        "#;
+
+        let types = json_ld(html)
+            .iter()
+            .map(|item| item["@type"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(types, vec!["A", "B", "C"]);
+    }
+}
@@ -0,0 +1,395 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::element_text;
+
+/// Upper bounds for spans as defined by the HTML spec, anything larger is clamped.
+const MAX_COLSPAN: usize = 1000;
+const MAX_ROWSPAN: usize = 65534;
+
+/// A table extracted from an HTML `<table>` element.
+///
+/// Cells spanning multiple columns or rows (`colspan`/`rowspan`) are expanded so their text is
+/// repeated in every slot they cover, meaning every row has exactly one cell per column.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Table {
+    /// The text of the `<caption>` element if there is one.
+    pub caption: Option<String>,
+    /// Column names, empty when no header row could be detected.
+    pub headers: Vec<String>,
+    /// The body rows (excluding header rows), each with one cell per column.
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Extracts every table in an HTML document, including nested tables (which are extracted
+    /// separately and not merged into the cells of their parent).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let html = r#"
+    /// <table>
+    ///   <tr><th>Name</th><th>Score</th></tr>
+    ///   <tr><td>Kirby</td><td>10</td></tr>
+    /// </table>
+    /// "#;
+    ///
+    /// let tables = kirby_core::extract::table::Table::extract_all(html);
+    /// assert_eq!(tables[0].headers, vec!["Name", "Score"]);
+    /// assert_eq!(tables[0].rows, vec![vec!["Kirby", "10"]]);
+    /// ```
+    pub fn extract_all(html: &str) -> Vec<Self> {
+        let document = Html::parse_document(html);
+        // Unwrapping is safe here because the selector is a valid constant.
+        let selector = Selector::parse("table").unwrap();
+        document.select(&selector).map(Self::from_element).collect()
+    }
+
+    /// Builds a table from a `<table>` element.
+    ///
+    /// Rows inside `<thead>` are treated as headers, otherwise any leading rows made up of only
+    /// `<th>` cells are. When there are multiple header rows the labels of each column are
+    /// joined with `" / "`.
+    pub fn from_element(table: ElementRef) -> Self {
+        let mut caption = None;
+        let mut header_rows: Vec<Vec<String>> = Vec::new();
+        let mut body_rows: Vec<GridRow> = Vec::new();
+        let mut loose_rows: Vec<ElementRef> = Vec::new();
+
+        for child in table.child_elements() {
+            match child.value().name() {
+                "caption" if caption.is_none() => caption = Some(element_text(child)),
+                "thead" => header_rows.extend(
+                    expand_rows(row_elements(child))
+                        .into_iter()
+                        .map(|r| r.cells),
+                ),
+                "tbody" | "tfoot" => body_rows.extend(expand_rows(row_elements(child))),
+                "tr" => loose_rows.push(child),
+                _ => {}
+            }
+        }
+        body_rows.extend(expand_rows(loose_rows));
+
+        // Without a <thead> fall back to leading rows made up entirely of <th> cells.
+        if header_rows.is_empty() {
+            let count = body_rows.iter().take_while(|row| row.is_header).count();
+            header_rows = body_rows.drain(..count).map(|r| r.cells).collect();
+        }
+
+        let width = header_rows
+            .iter()
+            .chain(body_rows.iter().map(|r| &r.cells))
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+
+        let headers = if header_rows.is_empty() {
+            Vec::new()
+        } else {
+            (0..width)
+                .map(|column| {
+                    let mut labels: Vec<&str> = Vec::new();
+                    for row in &header_rows {
+                        let label = row.get(column).map(String::as_str).unwrap_or("");
+                        if !label.is_empty() && labels.last() != Some(&label) {
+                            labels.push(label);
+                        }
+                    }
+                    labels.join(" / ")
+                })
+                .collect()
+        };
+
+        let rows = body_rows
+            .into_iter()
+            .map(|mut row| {
+                row.cells.resize(width, String::new());
+                row.cells
+            })
+            .collect();
+
+        Self {
+            caption,
+            headers,
+            rows,
+        }
+    }
+
+    /// The number of columns in the table.
+    pub fn width(&self) -> usize {
+        self.rows
+            .first()
+            .map(Vec::len)
+            .unwrap_or(self.headers.len())
+    }
+
+    /// Renders the table as CSV (RFC 4180), the header row is included if there is one.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let lines = (!self.headers.is_empty())
+            .then_some(&self.headers)
+            .into_iter()
+            .chain(&self.rows);
+
+        for line in lines {
+            let fields = line.iter().map(|f| csv_field(f)).collect::<Vec<String>>();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+
+        csv
+    }
+
+    /// Converts the rows to JSON, each row is an object keyed by header when the table has
+    /// headers, otherwise each row is an array of cells.
+    ///
+    /// Empty or repeated header names are suffixed with their column index so no cell is lost.
+    pub fn to_json(&self) -> Value {
+        if self.headers.is_empty() {
+            return Value::from(self.rows.clone());
+        }
+
+        let mut keys: Vec<String> = Vec::with_capacity(self.headers.len());
+        for (i, header) in self.headers.iter().enumerate() {
+            if header.is_empty() || keys.contains(header) {
+                keys.push(format!("{header}_{i}"));
+            } else {
+                keys.push(header.clone());
+            }
+        }
+
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let object = keys
+                    .iter()
+                    .cloned()
+                    .zip(row.iter().cloned().map(Value::String))
+                    .collect::<Map<String, Value>>();
+                Value::Object(object)
+            })
+            .collect();
+
+        Value::Array(rows)
+    }
+}
+
+/// A row after spans have been expanded.
+struct GridRow {
+    cells: Vec<String>,
+    /// True when every cell originating in this row is a <th>.
+    is_header: bool,
+}
+
+/// A cell continuing down from a row above via `rowspan`.
+struct PendingSpan {
+    text: String,
+    remaining: usize,
+}
+
+/// Fills the next column of a row from a span above it, returns false if there is none.
+fn take_pending(pending: &mut [Option<PendingSpan>], cells: &mut Vec<String>) -> bool {
+    let column = cells.len();
+    let Some(Some(span)) = pending.get_mut(column) else {
+        return false;
+    };
+    cells.push(span.text.clone());
+    span.remaining -= 1;
+    if span.remaining == 0 {
+        pending[column] = None;
+    }
+    true
+}
+
+fn row_elements(section: ElementRef) -> Vec<ElementRef> {
+    section
+        .child_elements()
+        .filter(|e| e.value().name() == "tr")
+        .collect()
+}
+
+/// Expands the rows of a single table section into a grid, spans do not cross sections.
+fn expand_rows(rows: Vec<ElementRef>) -> Vec<GridRow> {
+    let mut pending: Vec<Option<PendingSpan>> = Vec::new();
+    let mut grid = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let mut cells: Vec<String> = Vec::new();
+        let mut has_cells = false;
+        let mut is_header = true;
+
+        for cell in row.child_elements() {
+            let name = cell.value().name();
+            if name != "td" && name != "th" {
+                continue;
+            }
+            has_cells = true;
+            is_header &= name == "th";
+
+            while take_pending(&mut pending, &mut cells) {}
+
+            let text = element_text(cell);
+            let colspan = span_attr(cell, "colspan", MAX_COLSPAN);
+            let rowspan = span_attr(cell, "rowspan", MAX_ROWSPAN);
+            for _ in 0..colspan {
+                if rowspan > 1 {
+                    let column = cells.len();
+                    if pending.len() <= column {
+                        pending.resize_with(column + 1, || None);
+                    }
+                    pending[column] = Some(PendingSpan {
+                        text: text.clone(),
+                        remaining: rowspan - 1,
+                    });
+                }
+                cells.push(text.clone());
+            }
+        }
+
+        // Fill in any spans continuing past the last cell of this row.
+        if let Some(last) = pending.iter().rposition(Option::is_some) {
+            while cells.len() <= last {
+                if !take_pending(&mut pending, &mut cells) {
+                    cells.push(String::new());
+                }
+            }
+        }
+
+        grid.push(GridRow {
+            cells,
+            is_header: is_header && has_cells,
+        });
+    }
+
+    grid
+}
+
+/// Parses a span attribute, where missing or invalid values default to 1. A rowspan of 0 means
+/// the cell spans every remaining row in the section.
+fn span_attr(cell: ElementRef, name: &str, max: usize) -> usize {
+    match cell.attr(name).map(|v| v.trim().parse::<usize>()) {
+        Some(Ok(0)) if name == "rowspan" => max,
+        Some(Ok(0)) | Some(Err(_)) | None => 1,
+        Some(Ok(n)) => n.min(max),
+    }
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or newline.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_headers_from_thead_and_th_rows() {
+        let html = r#"
+        <table>
+            <caption> Scores </caption>
+            <thead><tr><td>Name</td><td>Score</td></tr></thead>
+            <tbody>
+                <tr><td>Kirby</td><td>10</td></tr>
+                <tr><td>Meta   Knight</td><td>8</td></tr>
+            </tbody>
+        </table>
+        <table>
+            <tr><th>A</th><th>B</th></tr>
+            <tr><th>1</th><td>2</td></tr>
+        </table>
+        <table>
+            <tr><td>no</td><td>headers</td></tr>
+        </table>
+        "#;
+
+        let tables = Table::extract_all(html);
+        assert_eq!(tables.len(), 3);
+
+        assert_eq!(tables[0].caption.as_deref(), Some("Scores"));
+        assert_eq!(tables[0].headers, vec!["Name", "Score"]);
+        assert_eq!(
+            tables[0].rows,
+            vec![vec!["Kirby", "10"], vec!["Meta Knight", "8"]]
+        );
+
+        assert_eq!(tables[1].headers, vec!["A", "B"]);
+        assert_eq!(tables[1].rows, vec![vec!["1", "2"]]);
+
+        assert!(tables[2].headers.is_empty());
+        assert_eq!(tables[2].rows, vec![vec!["no", "headers"]]);
+    }
+
+    #[test]
+    fn expands_colspan_and_rowspan() {
+        let html = r#"
+        <table>
+            <thead>
+                <tr><th rowspan="2">Name</th><th colspan="2">Scores</th></tr>
+                <tr><th>Math</th><th>Art</th></tr>
+            </thead>
+            <tr><td rowspan="2">Kirby</td><td>1</td><td>2</td></tr>
+            <tr><td colspan="2">absent</td></tr>
+            <tr><td>Dedede</td><td>3</td></tr>
+        </table>
+        "#;
+
+        let table = &Table::extract_all(html)[0];
+        assert_eq!(table.headers, vec!["Name", "Scores / Math", "Scores / Art"]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["Kirby", "1", "2"],
+                vec!["Kirby", "absent", "absent"],
+                vec!["Dedede", "3", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_tables_are_extracted_separately() {
+        let html = r#"
+        <table>
+            <tr><td>outer</td><td><table><tr><td>inner</td></tr></table></td></tr>
+        </table>
+        "#;
+
+        let tables = Table::extract_all(html);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].rows.len(), 1);
+        assert_eq!(tables[1].rows, vec![vec!["inner"]]);
+    }
+
+    #[test]
+    fn exports_csv_and_json() {
+        let table = Table {
+            caption: None,
+            headers: vec!["Name".into(), "Quote".into(), "".into()],
+            rows: vec![vec!["Kirby".into(), "Poyo, \"poyo\"".into(), "x".into()]],
+        };
+
+        assert_eq!(
+            table.to_csv(),
+            "Name,Quote,\r\nKirby,\"Poyo, \"\"poyo\"\"\",x\r\n"
+        );
+        assert_eq!(
+            table.to_json(),
+            serde_json::json!([{ "Name": "Kirby", "Quote": "Poyo, \"poyo\"", "_2": "x" }])
+        );
+
+        let table = Table {
+            rows: vec![vec!["a".into(), "b".into()]],
+            ..Default::default()
+        };
+        assert_eq!(table.to_csv(), "a,b\r\n");
+        assert_eq!(table.to_json(), serde_json::json!([["a", "b"]]));
+    }
+}
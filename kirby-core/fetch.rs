@@ -0,0 +1,371 @@
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use url::Url;
+
+use crate::cookies::CookieJar;
+use crate::dedup::digest::{ContentDigest, DigestAlgorithm};
+use crate::error::{ErrorKind, KirbyError, Phase};
+use crate::record::{PageRecord, Redirect, Timings};
+
+/// How long a fetch may take by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many redirects are followed by default.
+pub const DEFAULT_MAX_REDIRECTS: u32 = 10;
+
+/// How much of a body is read by default, 10 MiB.
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// The response to a fetch, after following its redirects.
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// The URL that was requested.
+    pub url: Url,
+    /// The redirects followed before the final response, in order.
+    pub redirects: Vec<Redirect>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The body, cut off after the fetcher's maximum size.
+    pub body: Vec<u8>,
+    pub timings: Timings,
+}
+
+impl Response {
+    /// The URL the final response came from.
+    pub fn final_url(&self) -> &Url {
+        self.redirects
+            .last()
+            .map(|redirect| &redirect.to)
+            .unwrap_or(&self.url)
+    }
+
+    /// The first header with the given name, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The media type without its parameters, lowercased, e.g. `text/html`.
+    pub fn mime(&self) -> Option<String> {
+        let content_type = self.header("content-type")?;
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        Some(mime.to_ascii_lowercase())
+    }
+
+    pub fn is_html(&self) -> bool {
+        matches!(
+            self.mime().as_deref(),
+            Some("text/html" | "application/xhtml+xml")
+        )
+    }
+
+    /// The body as text, replacing invalid UTF-8.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// A record of the fetch with the body's SHA-256 digest, its depth and discovery are left
+    /// for the caller to fill in.
+    pub fn record(&self, fetched_at: DateTime<Utc>) -> PageRecord {
+        let mut record = PageRecord::new(self.url.clone(), self.status, fetched_at);
+        record.redirects = self.redirects.clone();
+        record.headers = self.headers.clone();
+        record.timings = self.timings;
+        record.digest =
+            Some(ContentDigest::compute(DigestAlgorithm::Sha256, &self.body).to_string());
+        record
+    }
+}
+
+/// Fetches URLs over HTTP(S), following redirects itself so each one is recorded.
+///
+/// Error statuses aren't errors here: a `404` is a [`Response`] like any other, only failures to
+/// get a response at all are [`KirbyError`]s.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::fetch::Fetcher;
+/// use url::Url;
+///
+/// let fetcher = Fetcher::new("KirbyBot/1.0 (+https://example.com/bot)");
+/// let response = fetcher
+///     .fetch(&Url::parse("https://example.com/").unwrap())
+///     .unwrap();
+/// println!("{} {} bytes", response.status, response.body.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    agent: ureq::Agent,
+    user_agent: String,
+    max_redirects: u32,
+    max_body_bytes: u64,
+    cookies: Option<CookieJar>,
+}
+
+impl Fetcher {
+    /// Sends `user_agent` with every request, with the default limits.
+    pub fn new(user_agent: impl Into<String>) -> Self {
+        Self {
+            agent: agent(DEFAULT_TIMEOUT),
+            user_agent: user_agent.into(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            cookies: None,
+        }
+    }
+
+    /// How long each request may take, instead of [`DEFAULT_TIMEOUT`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.agent = agent(timeout);
+        self
+    }
+
+    /// Follows up to this many redirects instead of [`DEFAULT_MAX_REDIRECTS`], the redirect
+    /// after the last one is returned as the response.
+    pub fn max_redirects(mut self, max_redirects: u32) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Reads up to this much of each body instead of [`DEFAULT_MAX_BODY_BYTES`].
+    pub fn max_body_bytes(mut self, max_body_bytes: u64) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Keeps the cookies responses set in `jar` and sends them back, which no cookies are
+    /// otherwise.
+    pub fn cookies(mut self, jar: CookieJar) -> Self {
+        self.cookies = Some(jar);
+        self
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    pub fn cookie_jar(&self) -> Option<&CookieJar> {
+        self.cookies.as_ref()
+    }
+
+    /// GETs a URL, following its redirects.
+    // A large error costs little next to the request it failed.
+    pub fn fetch(&self, url: &Url) -> Result<Response, KirbyError> {
+        self.request("GET", url, None)
+    }
+
+    /// Sends a HEAD request for a URL, following its redirects, to check a URL without
+    /// downloading it. The response has no body.
+    pub fn head(&self, url: &Url) -> Result<Response, KirbyError> {
+        self.request("HEAD", url, None)
+    }
+
+    /// POSTs form fields to a URL, as a browser submits a form, following the redirects with
+    /// GETs.
+    pub fn post_form(
+        &self,
+        url: &Url,
+        fields: &[(String, String)],
+    ) -> Result<Response, KirbyError> {
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields)
+            .finish();
+        self.request("POST", url, Some(form.as_bytes()))
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        url: &Url,
+        form: Option<&[u8]>,
+    ) -> Result<Response, KirbyError> {
+        let started = Instant::now();
+        let mut redirects = Vec::new();
+        let (mut method, mut form) = (method, form);
+        let mut current = url.clone();
+        loop {
+            let mut request = self
+                .agent
+                .request(method, current.as_str())
+                .set("User-Agent", &self.user_agent);
+            if let Some(cookie) = self.cookies.as_ref().and_then(|jar| jar.header(&current)) {
+                request = request.set("Cookie", &cookie);
+            }
+            let sent = match form {
+                Some(form) => request
+                    .set("Content-Type", "application/x-www-form-urlencoded")
+                    .send_bytes(form),
+                None => request.call(),
+            };
+            let response = match sent {
+                Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+                Err(ureq::Error::Transport(transport)) => {
+                    return Err(transport_error(transport, Phase::FirstByte).with_url(current));
+                }
+            };
+            let status = response.status();
+            let first_byte = started.elapsed();
+            if let Some(jar) = &self.cookies {
+                for set_cookie in response.all("set-cookie") {
+                    jar.store(&current, set_cookie);
+                }
+            }
+
+            let location = response
+                .header("location")
+                .filter(|_| (300..400).contains(&status))
+                .and_then(|location| current.join(location).ok());
+            if let Some(location) = location {
+                if redirects.len() < self.max_redirects as usize {
+                    redirects.push(Redirect {
+                        from: current,
+                        to: location.clone(),
+                        status,
+                    });
+                    current = location;
+                    // Only these keep the method and body, the rest are followed with a GET.
+                    if !matches!(status, 307 | 308) {
+                        (method, form) = ("GET", None);
+                    }
+                    continue;
+                }
+            }
+
+            let headers = response
+                .headers_names()
+                .into_iter()
+                .flat_map(|name| {
+                    response
+                        .all(&name)
+                        .into_iter()
+                        .map(|value| (name.clone(), value.to_string()))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            let mut body = Vec::new();
+            response
+                .into_reader()
+                .take(self.max_body_bytes)
+                .read_to_end(&mut body)
+                .map_err(|error| io_error(error, Phase::Body).with_url(current))?;
+
+            return Ok(Response {
+                url: url.clone(),
+                redirects,
+                status,
+                headers,
+                body,
+                timings: Timings {
+                    first_byte_ms: Some(first_byte.as_millis() as u64),
+                    total_ms: Some(started.elapsed().as_millis() as u64),
+                    ..Timings::default()
+                },
+            });
+        }
+    }
+}
+
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .redirects(0)
+        .build()
+}
+
+fn transport_error(transport: ureq::Transport, phase: Phase) -> KirbyError {
+    let kind = match transport.kind() {
+        ureq::ErrorKind::Dns => ErrorKind::Dns,
+        ureq::ErrorKind::InvalidUrl | ureq::ErrorKind::UnknownScheme => ErrorKind::Parse,
+        ureq::ErrorKind::Io => {
+            let timed_out = std::error::Error::source(&transport)
+                .and_then(|source| source.downcast_ref::<io::Error>())
+                .is_some_and(|error| is_timeout(error.kind()));
+            if timed_out {
+                ErrorKind::Timeout { phase }
+            } else {
+                ErrorKind::Connect
+            }
+        }
+        _ => ErrorKind::Connect,
+    };
+    KirbyError::new(kind, transport.to_string()).with_source(transport)
+}
+
+fn io_error(error: io::Error, phase: Phase) -> KirbyError {
+    let kind = if is_timeout(error.kind()) {
+        ErrorKind::Timeout { phase }
+    } else {
+        ErrorKind::Connect
+    };
+    KirbyError::new(kind, error.to_string()).with_source(error)
+}
+
+fn is_timeout(kind: io::ErrorKind) -> bool {
+    matches!(kind, io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+
+    #[test]
+    fn follows_and_records_redirects() {
+        let server = MockServer::start(Site::new().page("/old", Page::redirect("/new")).page(
+            "/new",
+            Page::new(404, r#"{"error": "gone"}"#).header("Content-Type", "application/json"),
+        ));
+        let url = server.url().join("/old").unwrap();
+        let response = Fetcher::new("KirbyBot/1.0").fetch(&url).unwrap();
+
+        assert_eq!(response.status, 404);
+        assert_eq!(response.final_url().path(), "/new");
+        assert_eq!(response.redirects[0].status, 301);
+        assert_eq!(response.mime().as_deref(), Some("application/json"));
+        assert!(!response.is_html());
+        assert_eq!(response.text(), r#"{"error": "gone"}"#);
+
+        let record = response.record(Utc::now());
+        assert_eq!(record.url, url);
+        assert!(record.digest.unwrap().starts_with("sha256:"));
+
+        let requests = server.requests();
+        assert_eq!(requests[1].path, "/new");
+        assert_eq!(requests[1].header("user-agent"), Some("KirbyBot/1.0"));
+    }
+
+    #[test]
+    fn posts_forms_and_keeps_cookies() {
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/login",
+                    Page::new(303, "")
+                        .header("Location", "/account")
+                        .header("Set-Cookie", "session=k1rby"),
+                )
+                .page("/account", Page::html("<p>Hi</p>")),
+        );
+        let jar = CookieJar::new();
+        let fetcher = Fetcher::new("KirbyBot/1.0").cookies(jar.clone());
+        let fields = [("user".to_string(), "kirby & co".to_string())];
+        let response = fetcher
+            .post_form(&server.url().join("/login").unwrap(), &fields)
+            .unwrap();
+
+        assert_eq!(response.final_url().path(), "/account");
+        assert!(jar.contains("session"));
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].body, b"user=kirby+%26+co");
+        assert_eq!(requests[1].method, "GET");
+        assert_eq!(requests[1].header("cookie"), Some("session=k1rby"));
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use url::Url;
+
+use crate::audit::Decision;
+use crate::record::DiscoverySource;
+
+/// Which URLs a crawl follows, relative to its seeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scope {
+    /// URLs on the host of a seed.
+    SameHost,
+    /// URLs on the host of a seed or its subdomains, with a leading `www.` of the seed ignored,
+    /// so `www.example.com` also covers `blog.example.com`.
+    #[default]
+    SameDomain,
+    /// URLs under the directory of a seed, e.g. `https://example.com/docs/` for
+    /// `https://example.com/docs/index.html`.
+    Prefix,
+    /// Any URL.
+    Any,
+}
+
+impl FromStr for Scope {
+    type Err = ParseScopeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "host" | "same-host" => Ok(Self::SameHost),
+            "domain" | "same-domain" => Ok(Self::SameDomain),
+            "prefix" => Ok(Self::Prefix),
+            "any" => Ok(Self::Any),
+            _ => Err(ParseScopeError(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::SameHost => "host",
+            Self::SameDomain => "domain",
+            Self::Prefix => "prefix",
+            Self::Any => "any",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScopeError(String);
+
+impl fmt::Display for ParseScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown scope {:?}, expected \"host\", \"domain\", \"prefix\" or \"any\"",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseScopeError {}
+
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Scope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A URL waiting to be crawled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedUrl {
+    pub url: Url,
+    pub depth: u32,
+    pub discovery: DiscoverySource,
+    /// What the user attached to the URL's seed, passed on to the links found from it. `null`
+    /// when there's nothing.
+    pub metadata: Arc<Value>,
+}
+
+/// What [`Frontier::next`] has to crawl.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Next {
+    Ready(QueuedUrl),
+    /// Every host with queued URLs is waiting out its delay, the first is ready after this long.
+    Wait(Duration),
+    /// Every host with queued URLs is being fetched from, ask again after one is
+    /// [`done`](Frontier::done).
+    Busy,
+    /// Nothing is queued.
+    Empty,
+}
+
+/// The URLs a crawl still has to fetch, one queue per host.
+///
+/// URLs are only queued once and only when they're within the scope and depth of the crawl,
+/// rejected ones come back as the audit [`Decision`] explaining why. Hosts take turns, and a
+/// host is handed out again only once its previous URL is [`done`](Self::done) and the delay
+/// since has passed, so no host gets more than one request at a time.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Instant;
+///
+/// use kirby_core::frontier::{Frontier, Next, Scope};
+/// use url::Url;
+///
+/// let mut frontier = Frontier::new(Scope::SameDomain).max_depth(2);
+/// frontier.add_seed(Url::parse("https://www.example.com/").unwrap()).unwrap();
+///
+/// let Next::Ready(page) = frontier.next(Instant::now()) else { panic!() };
+/// frontier.add(Url::parse("https://blog.example.com/").unwrap(), &page).unwrap();
+/// assert!(frontier.add(Url::parse("https://example.net/").unwrap(), &page).is_err());
+/// frontier.done(&page.url, Instant::now());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Frontier {
+    scope: Scope,
+    max_depth: Option<u32>,
+    delay: Duration,
+    /// Delays for some domains instead of `delay`, the first matching one applies.
+    domain_delays: Vec<(String, Duration)>,
+    seeds: Vec<Url>,
+    seen: HashSet<String>,
+    queues: HashMap<String, VecDeque<QueuedUrl>>,
+    /// Hosts with queued URLs, in the order they take turns.
+    hosts: VecDeque<String>,
+    busy: HashSet<String>,
+    ready_at: HashMap<String, Instant>,
+    len: usize,
+}
+
+impl Frontier {
+    /// An empty frontier without a depth limit or delay.
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            max_depth: None,
+            delay: Duration::ZERO,
+            domain_delays: Vec::new(),
+            seeds: Vec::new(),
+            seen: HashSet::new(),
+            queues: HashMap::new(),
+            hosts: VecDeque::new(),
+            busy: HashSet::new(),
+            ready_at: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Rejects URLs more than this many links away from a seed.
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Waits this long between requests to the same host.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Waits this long between requests to the hosts of a domain instead, e.g. `example.com`
+    /// for `shop.example.com`.
+    pub fn domain_delay(mut self, domain: impl Into<String>, delay: Duration) -> Self {
+        self.domain_delays.push((domain.into(), delay));
+        self
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+
+    pub fn seeds(&self) -> &[Url] {
+        &self.seeds
+    }
+
+    /// Queues a seed, which widens the scope to cover it.
+    pub fn add_seed(&mut self, url: Url) -> Result<(), Decision> {
+        self.add_seed_with(url, Value::Null)
+    }
+
+    /// Queues a seed with metadata that's passed on to the links found from it.
+    pub fn add_seed_with(&mut self, url: Url, metadata: Value) -> Result<(), Decision> {
+        let mut seed = url.clone();
+        seed.set_fragment(None);
+        self.seeds.push(seed);
+        self.push(QueuedUrl {
+            url,
+            depth: 0,
+            discovery: DiscoverySource::Seed,
+            metadata: Arc::new(metadata),
+        })
+    }
+
+    /// Queues a link found on `from`, one level deeper, with the metadata of `from`.
+    pub fn add(&mut self, url: Url, from: &QueuedUrl) -> Result<(), Decision> {
+        let discovery = DiscoverySource::Link {
+            from: from.url.clone(),
+        };
+        self.queue(url, from.depth + 1, discovery, Arc::clone(&from.metadata))
+    }
+
+    /// Queues a URL found some other way, e.g. in a sitemap.
+    pub fn add_discovered(
+        &mut self,
+        url: Url,
+        depth: u32,
+        discovery: DiscoverySource,
+    ) -> Result<(), Decision> {
+        self.queue(url, depth, discovery, Arc::default())
+    }
+
+    fn queue(
+        &mut self,
+        url: Url,
+        depth: u32,
+        discovery: DiscoverySource,
+        metadata: Arc<Value>,
+    ) -> Result<(), Decision> {
+        if let Some(max_depth) = self.max_depth.filter(|max_depth| depth > *max_depth) {
+            return Err(Decision::OutOfScope {
+                reason: format!("depth {depth} is over the maximum of {max_depth}"),
+            });
+        }
+        self.check_scope(&url)?;
+        self.push(QueuedUrl {
+            url,
+            depth,
+            discovery,
+            metadata,
+        })
+    }
+
+    /// Whether a URL is within the scope of the seeds added so far.
+    pub fn check_scope(&self, url: &Url) -> Result<(), Decision> {
+        let host = url.host_str().unwrap_or_default();
+        let in_scope = match self.scope {
+            Scope::Any => true,
+            Scope::SameHost => self.seeds.iter().any(|seed| seed.host_str() == Some(host)),
+            Scope::SameDomain => self.seeds.iter().any(|seed| {
+                let domain = seed.host_str().unwrap_or_default();
+                on_domain(host, domain.strip_prefix("www.").unwrap_or(domain))
+            }),
+            Scope::Prefix => self.seeds.iter().any(|seed| {
+                let path = seed.path();
+                let directory = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+                seed.origin() == url.origin() && url.path().starts_with(directory)
+            }),
+        };
+        if in_scope {
+            Ok(())
+        } else {
+            Err(Decision::OutOfScope {
+                reason: format!("outside of the {} scope of the seeds", self.scope),
+            })
+        }
+    }
+
+    /// The next URL to crawl at `now`, which keeps its host busy until it's
+    /// [`done`](Self::done).
+    pub fn next(&mut self, now: Instant) -> Next {
+        if self.len == 0 {
+            return Next::Empty;
+        }
+        let mut wait = None::<Duration>;
+        for _ in 0..self.hosts.len() {
+            // Unwrapping is safe here because the loop runs once per queued host.
+            let host = self.hosts.pop_front().unwrap();
+            let ready_at = self.ready_at.get(&host).copied();
+            if self.busy.contains(&host) || ready_at.is_some_and(|at| at > now) {
+                if let Some(at) = ready_at.filter(|at| *at > now) {
+                    let until = at - now;
+                    wait = Some(wait.map_or(until, |wait| wait.min(until)));
+                }
+                self.hosts.push_back(host);
+                continue;
+            }
+
+            // Unwrapping is safe here because only hosts with queued URLs take turns.
+            let queue = self.queues.get_mut(&host).unwrap();
+            let url = queue.pop_front().unwrap();
+            if queue.is_empty() {
+                self.queues.remove(&host);
+            } else {
+                self.hosts.push_back(host.clone());
+            }
+            self.busy.insert(host);
+            self.len -= 1;
+            return Next::Ready(url);
+        }
+        wait.map_or(Next::Busy, Next::Wait)
+    }
+
+    /// Marks the fetch of a URL handed out by [`next`](Self::next) as done at `now`, so its
+    /// host is ready again after the delay.
+    pub fn done(&mut self, url: &Url, now: Instant) {
+        let delay = self
+            .domain_delays
+            .iter()
+            .find(|(domain, _)| on_domain(url.host_str().unwrap_or_default(), domain))
+            .map_or(self.delay, |(_, delay)| *delay);
+        let host = host_key(url);
+        self.busy.remove(&host);
+        self.ready_at.insert(host, now + delay);
+    }
+
+    /// How many URLs are queued.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many hosts have queued URLs.
+    pub fn hosts(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// How many URLs are queued for the host of `url`.
+    pub fn host_len(&self, url: &Url) -> usize {
+        self.queues.get(&host_key(url)).map_or(0, VecDeque::len)
+    }
+
+    fn push(&mut self, mut queued: QueuedUrl) -> Result<(), Decision> {
+        queued.url.set_fragment(None);
+        if !self.seen.insert(queued.url.to_string()) {
+            return Err(Decision::Duplicate {
+                of: queued.url.to_string(),
+            });
+        }
+        let host = host_key(&queued.url);
+        let queue = self.queues.entry(host.clone()).or_default();
+        if queue.is_empty() {
+            self.hosts.push_back(host);
+        }
+        queue.push_back(queued);
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Whether a host is a domain or one of its subdomains.
+pub(crate) fn on_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+/// The host of a URL with its port, since different ports are often different servers.
+pub(crate) fn host_key(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (host, None) => host.unwrap_or_default().to_string(),
+        (None, Some(_)) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn keeps_urls_within_scope_and_depth() {
+        let mut frontier = Frontier::new(Scope::Prefix).max_depth(1);
+        frontier
+            .add_seed(url("https://example.com/docs/index.html"))
+            .unwrap();
+        let Next::Ready(seed) = frontier.next(Instant::now()) else {
+            panic!("the seed isn't ready");
+        };
+
+        frontier
+            .add(url("https://example.com/docs/guide#intro"), &seed)
+            .unwrap();
+        assert_eq!(
+            frontier.add(url("https://example.com/docs/guide"), &seed),
+            Err(Decision::Duplicate {
+                of: "https://example.com/docs/guide".to_string()
+            })
+        );
+        assert!(frontier
+            .add(url("https://example.com/blog/"), &seed)
+            .is_err());
+        assert!(frontier
+            .add(url("http://example.com/docs/"), &seed)
+            .is_err());
+        assert_eq!(frontier.len(), 1);
+
+        let deeper = QueuedUrl {
+            depth: 1,
+            ..seed.clone()
+        };
+        assert!(matches!(
+            frontier.add(url("https://example.com/docs/api"), &deeper),
+            Err(Decision::OutOfScope { .. })
+        ));
+
+        let mut domain = Frontier::new(Scope::SameDomain);
+        domain.add_seed(url("https://www.example.com/")).unwrap();
+        assert!(domain
+            .check_scope(&url("https://shop.example.com/"))
+            .is_ok());
+        assert!(domain.check_scope(&url("https://example.com/")).is_ok());
+        assert!(domain.check_scope(&url("https://badexample.com/")).is_err());
+        assert_eq!("same-host".parse(), Ok(Scope::SameHost));
+    }
+
+    #[test]
+    fn takes_turns_between_hosts_and_waits_out_the_delay() {
+        let start = Instant::now();
+        let mut frontier = Frontier::new(Scope::Any)
+            .delay(Duration::from_secs(1))
+            .domain_delay("b.example", Duration::from_secs(5));
+        for seed in [
+            "https://a.example/1",
+            "https://a.example/2",
+            "https://b.example/",
+        ] {
+            frontier.add_seed(url(seed)).unwrap();
+        }
+
+        let Next::Ready(first) = frontier.next(start) else {
+            panic!("nothing is ready");
+        };
+        assert_eq!(first.url.as_str(), "https://a.example/1");
+        let Next::Ready(second) = frontier.next(start) else {
+            panic!("nothing is ready");
+        };
+        assert_eq!(second.url.host_str(), Some("b.example"));
+        assert_eq!(frontier.next(start), Next::Busy);
+
+        frontier.done(&first.url, start);
+        assert_eq!(frontier.next(start), Next::Wait(Duration::from_secs(1)));
+        let later = start + Duration::from_secs(1);
+        assert!(matches!(frontier.next(later), Next::Ready(url) if url.url.path() == "/2"));
+        assert_eq!(frontier.next(later), Next::Empty);
+
+        frontier.done(&second.url, start);
+        frontier.add_seed(url("https://b.example/more")).unwrap();
+        assert_eq!(frontier.next(later), Next::Wait(Duration::from_secs(4)));
+    }
+}
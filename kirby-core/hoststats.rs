@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::CrawlEvent;
+
+/// How many of a host's latest fetches the statistics cover by default.
+pub const DEFAULT_WINDOW: usize = 200;
+
+/// Statuses that mean a host is refusing or rate limiting the crawler.
+const BLOCKING_STATUSES: [u16; 3] = [403, 429, 503];
+
+/// How a host has been doing over its latest fetches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostHealth {
+    /// The fetches in the window, failed ones included.
+    pub fetches: u64,
+    /// The share of fetches with a 2xx or 3xx response, between 0 and 1.
+    pub success_rate: f64,
+    /// The share of fetches answered with 403, 429 or 503, between 0 and 1.
+    pub block_rate: f64,
+    /// The share of fetches that failed without a response, between 0 and 1.
+    pub error_rate: f64,
+    /// The median latency of the responses in the window, in milliseconds.
+    pub p50_ms: u64,
+    /// The latency 95% of the responses in the window were faster than or as fast as.
+    pub p95_ms: u64,
+    /// Bytes downloaded from the host over the whole crawl.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Outcome {
+    Response { status: u16, latency_ms: u64 },
+    Failure,
+}
+
+#[derive(Debug, Clone, Default)]
+struct HostWindow {
+    outcomes: VecDeque<Outcome>,
+    bytes: u64,
+}
+
+/// Rolling per-host statistics, for throttling or pausing hosts that are struggling and for
+/// the end-of-crawl report.
+///
+/// Only a host's latest fetches count towards its rates and latencies, so they follow the
+/// host's current state; bytes are counted over the whole crawl.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_core::hoststats::HostStats;
+///
+/// let mut stats = HostStats::new();
+/// for (status, ms) in [(200, 80), (200, 120), (429, 15), (200, 100)] {
+///     stats.record_response("example.com", status, Duration::from_millis(ms), 1024);
+/// }
+///
+/// let health = stats.health("example.com").unwrap();
+/// assert_eq!(health.success_rate, 0.75);
+/// assert_eq!(health.block_rate, 0.25);
+/// assert_eq!(health.p50_ms, 80);
+/// assert_eq!(health.p95_ms, 120);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HostStats {
+    window: usize,
+    hosts: BTreeMap<String, HostWindow>,
+}
+
+impl Default for HostStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostStats {
+    /// Keeps the latest [`DEFAULT_WINDOW`] fetches of each host.
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Keeps the latest `window` fetches of each host, at least one.
+    pub fn with_window(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            hosts: BTreeMap::new(),
+        }
+    }
+
+    pub fn record_response(&mut self, host: &str, status: u16, latency: Duration, bytes: u64) {
+        let latency_ms = latency.as_millis() as u64;
+        let window = self.push(host, Outcome::Response { status, latency_ms });
+        window.bytes += bytes;
+    }
+
+    /// Records a fetch that failed without a response.
+    pub fn record_failure(&mut self, host: &str) {
+        self.push(host, Outcome::Failure);
+    }
+
+    /// Records finished fetches and fetch errors.
+    pub fn observe(&mut self, event: &CrawlEvent) {
+        match event {
+            CrawlEvent::FetchFinished {
+                url,
+                status,
+                duration,
+                bytes,
+            } => {
+                if let Some(host) = url.host_str() {
+                    self.record_response(host, *status, *duration, *bytes);
+                }
+            }
+            // Robots.txt denials and storage or parse failures aren't the host's doing.
+            CrawlEvent::Error { error } if error.kind().is_retryable() => {
+                if let Some(host) = error.host() {
+                    self.record_failure(host);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The statistics of a host, `None` before its first fetch.
+    pub fn health(&self, host: &str) -> Option<HostHealth> {
+        self.hosts.get(host).map(health)
+    }
+
+    /// The statistics of every host, sorted by host.
+    pub fn hosts(&self) -> impl Iterator<Item = (&str, HostHealth)> {
+        self.hosts
+            .iter()
+            .map(|(host, window)| (host.as_str(), health(window)))
+    }
+
+    fn push(&mut self, host: &str, outcome: Outcome) -> &mut HostWindow {
+        let window = self.hosts.entry(host.to_string()).or_default();
+        if window.outcomes.len() == self.window {
+            window.outcomes.pop_front();
+        }
+        window.outcomes.push_back(outcome);
+        window
+    }
+}
+
+fn health(window: &HostWindow) -> HostHealth {
+    let fetches = window.outcomes.len() as u64;
+    let mut successes = 0;
+    let mut blocks = 0;
+    let mut failures = 0;
+    let mut latencies = Vec::with_capacity(window.outcomes.len());
+    for outcome in &window.outcomes {
+        match *outcome {
+            Outcome::Response { status, latency_ms } => {
+                latencies.push(latency_ms);
+                if (200..400).contains(&status) {
+                    successes += 1;
+                } else if BLOCKING_STATUSES.contains(&status) {
+                    blocks += 1;
+                }
+            }
+            Outcome::Failure => failures += 1,
+        }
+    }
+    latencies.sort_unstable();
+
+    let rate = |count: u64| count as f64 / fetches.max(1) as f64;
+    HostHealth {
+        fetches,
+        success_rate: rate(successes),
+        block_rate: rate(blocks),
+        error_rate: rate(failures),
+        p50_ms: percentile(&latencies, 50),
+        p95_ms: percentile(&latencies, 95),
+        bytes: window.bytes,
+    }
+}
+
+/// The nearest-rank percentile of sorted values, 0 without values.
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+    use crate::error::{ErrorKind, KirbyError};
+
+    #[test]
+    fn only_counts_the_latest_fetches() {
+        let mut stats = HostStats::with_window(4);
+        let url = Url::parse("https://example.com/").unwrap();
+        for ms in [1000, 1000, 10, 20] {
+            stats.observe(&CrawlEvent::FetchFinished {
+                url: url.clone(),
+                status: 200,
+                duration: Duration::from_millis(ms),
+                bytes: 100,
+            });
+        }
+        for kind in [ErrorKind::Connect, ErrorKind::Storage, ErrorKind::Connect] {
+            let error = KirbyError::new(kind, "").with_url(url.clone());
+            stats.observe(&CrawlEvent::Error { error });
+        }
+
+        let health = stats.health("example.com").unwrap();
+        assert_eq!(health.fetches, 4);
+        assert_eq!(health.error_rate, 0.5);
+        assert_eq!(health.success_rate, 0.5);
+        assert_eq!((health.p50_ms, health.p95_ms), (10, 20));
+        assert_eq!(health.bytes, 400);
+        assert_eq!(stats.health("other.example"), None);
+    }
+}
@@ -0,0 +1,351 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A request to an [`HttpServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Request {
+    pub method: String,
+    /// The path, without the query.
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The first header with the given name, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.into(),
+        }
+    }
+
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+/// The longest request line or header line accepted.
+const MAX_LINE: usize = 8 * 1024;
+
+/// The most headers a request may have.
+const MAX_HEADERS: usize = 100;
+
+/// The largest request body accepted.
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+/// How long a client has to send its whole request.
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+
+/// How many connections are handled at once, more are turned away with a `503`.
+const MAX_CONNECTIONS: usize = 64;
+
+/// A small HTTP/1.1 server answering one request per connection, for the metrics endpoint and
+/// the dashboard. It stops when dropped.
+///
+/// Every connection is handled on a thread of its own and has [`REQUEST_DEADLINE`] to send its
+/// request, so a slow client can't hold up the others. Oversized request lines, headers and
+/// bodies are rejected.
+pub(crate) struct HttpServer {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    pub fn start(
+        address: impl ToSocketAddrs,
+        handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let stop = Arc::clone(&stopped);
+        let handler = Arc::new(handler);
+        let connections = Arc::new(AtomicUsize::new(0));
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let connection = Connection::open(&connections);
+                if connections.load(Ordering::SeqCst) > MAX_CONNECTIONS {
+                    let _ = write_response(&stream, &Response::empty(503));
+                    continue;
+                }
+                let handler = Arc::clone(&handler);
+                thread::spawn(move || {
+                    // A failed request only affects that request.
+                    let _ = respond(stream, &*handler);
+                    drop(connection);
+                });
+            }
+        });
+
+        Ok(Self {
+            address,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wakes the thread up from accepting so it sees it was stopped.
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Counts a connection as open until it's dropped, even when its handler panics.
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    fn open(connections: &Arc<AtomicUsize>) -> Self {
+        connections.fetch_add(1, Ordering::SeqCst);
+        Self(Arc::clone(connections))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn respond(stream: TcpStream, handler: &dyn Fn(&Request) -> Response) -> io::Result<()> {
+    stream.set_write_timeout(Some(REQUEST_DEADLINE))?;
+    let deadline = Instant::now() + REQUEST_DEADLINE;
+    let response = match read_request(&stream, deadline)? {
+        Ok(request) => handler(&request),
+        Err(status) => Response::empty(status),
+    };
+    write_response(&stream, &response)
+}
+
+/// Reads a request, or the status it's rejected with when it's too large.
+fn read_request(stream: &TcpStream, deadline: Instant) -> io::Result<Result<Request, u16>> {
+    let mut reader = BufReader::new(stream);
+    let Some(request_line) = read_line(&mut reader, deadline)? else {
+        return Ok(Err(414));
+    };
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target.to_string(), None),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let Some(line) = read_line(&mut reader, deadline)? else {
+            return Ok(Err(431));
+        };
+        if line.trim_end().is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Ok(Err(431));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<u64>().ok())
+        .unwrap_or_default();
+    if content_length > MAX_BODY {
+        return Ok(Err(413));
+    }
+
+    let mut body = Vec::new();
+    let mut chunk = [0; 8 * 1024];
+    while (body.len() as u64) < content_length {
+        set_deadline(&reader, deadline)?;
+        let wanted = chunk
+            .len()
+            .min((content_length - body.len() as u64) as usize);
+        match reader.read(&mut chunk[..wanted])? {
+            0 => break,
+            read => body.extend_from_slice(&chunk[..read]),
+        }
+    }
+
+    Ok(Ok(Request {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// Reads a line before the deadline, `None` when it's longer than [`MAX_LINE`].
+fn read_line(reader: &mut BufReader<&TcpStream>, deadline: Instant) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    loop {
+        set_deadline(reader, deadline)?;
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+        let (length, complete) = match available.iter().position(|byte| *byte == b'\n') {
+            Some(end) => (end + 1, true),
+            None => (available.len(), false),
+        };
+        line.extend_from_slice(&available[..length]);
+        reader.consume(length);
+        if line.len() > MAX_LINE {
+            return Ok(None);
+        }
+        if complete {
+            break;
+        }
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// Makes the next read fail once the deadline has passed.
+fn set_deadline(reader: &BufReader<&TcpStream>, deadline: Instant) -> io::Result<()> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "the request wasn't sent in time",
+        ));
+    }
+    reader.get_ref().set_read_timeout(Some(remaining))
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len()
+    )?;
+    for (name, value) in &response.headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    stream.write_all(b"\r\n")?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        204 => "No Content",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(server: &HttpServer, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn echo() -> HttpServer {
+        HttpServer::start("127.0.0.1:0", |request| {
+            Response::new(200, "text/plain", request.body.clone())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_oversized_requests() {
+        let server = echo();
+        let response = send(&server, b"PUT / HTTP/1.1\r\nContent-Length: 4\r\n\r\nkirb");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nkirb"));
+
+        let mut long_line = b"GET /".to_vec();
+        long_line.resize(2 * MAX_LINE, b'a');
+        let response = send(&server, &long_line);
+        assert!(response.starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+
+        let headers = "X-Kirby: 1\r\n".repeat(MAX_HEADERS + 1);
+        let response = send(
+            &server,
+            format!("GET / HTTP/1.1\r\n{headers}\r\n").as_bytes(),
+        );
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+        let response = send(
+            &server,
+            b"PUT / HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+    }
+
+    #[test]
+    fn answers_while_a_client_is_slow() {
+        let server = echo();
+        let mut slow = TcpStream::connect(server.address()).unwrap();
+        slow.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+
+        let started = Instant::now();
+        let response = send(&server, b"GET / HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(started.elapsed() < REQUEST_DEADLINE / 2);
+    }
+}
@@ -1 +1,46 @@
+// Lets the derive macros refer to `::kirby_core` from within this crate as well.
+extern crate self as kirby_core;
+
+pub mod alerts;
+pub mod anchors;
+pub mod archive;
+pub mod audit;
+pub mod config;
+pub mod cookies;
+pub mod crawldiff;
+#[cfg(feature = "http")]
+pub mod crawler;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod dedup;
+pub mod domdiff;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod extract;
+#[cfg(feature = "http")]
+pub mod fetch;
+pub mod frontier;
+pub mod hoststats;
+mod http_server;
+pub mod linkgraph;
+#[cfg(feature = "logging")]
+pub mod logging;
+#[cfg(feature = "http")]
+pub mod login;
+pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod progress;
+pub mod record;
+pub mod render;
+pub mod report;
+pub mod robotsmeta;
 pub mod robotstxt;
+#[cfg(any(feature = "grpc", feature = "postgres"))]
+pub mod runtime;
+pub mod seo;
+pub mod sitemap;
+pub mod state;
+pub mod store;
+pub mod trace;
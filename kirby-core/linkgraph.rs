@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use quick_xml::escape::escape;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+use crate::export::{CompressedFile, Compression};
+use crate::extract::element_text;
+
+/// A link from one page to another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub source: Url,
+    /// The linked URL, without its fragment.
+    pub target: Url,
+    pub anchor_text: String,
+    /// The `rel` keywords, lowercased and separated by single spaces, e.g. `nofollow ugc`.
+    pub rel: Option<String>,
+}
+
+/// The graph of links between crawled pages, for PageRank, site structure visualization or
+/// finding orphan pages.
+///
+/// Pages are added as they are crawled; every HTTP(S) link of `<a href>` and `<area href>` is
+/// an edge, resolved against the page URL or `<base href>`. The graph can be written as
+/// GraphML, Graphviz DOT, or a tab-separated edge list.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::linkgraph::LinkGraph;
+/// use url::Url;
+///
+/// let mut graph = LinkGraph::new();
+/// graph.add_page(
+///     &Url::parse("https://example.com/").unwrap(),
+///     r#"<a href="/about">About us</a> <a href="https://ads.example.net/" rel="Sponsored">Ad</a>"#,
+/// );
+///
+/// let mut dot = Vec::new();
+/// graph.write_dot(&mut dot).unwrap();
+/// assert!(String::from_utf8(dot).unwrap().contains(
+///     r#""https://example.com/" -> "https://example.com/about" [label="About us"];"#
+/// ));
+/// assert_eq!(graph.edges()[1].rel.as_deref(), Some("sponsored"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LinkGraph {
+    edges: Vec<Edge>,
+    /// Mapping of URL -> position in `nodes`.
+    ids: HashMap<Url, usize>,
+    nodes: Vec<Url>,
+}
+
+impl LinkGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the links of a crawled page.
+    pub fn add_page(&mut self, url: &Url, html: &str) {
+        let document = Html::parse_document(html);
+        let mut page = url.clone();
+        page.set_fragment(None);
+        self.node(&page);
+        for edge in page_links(&document, url) {
+            self.add_edge(edge);
+        }
+    }
+
+    pub fn add_edge(&mut self, edge: Edge) {
+        self.node(&edge.source);
+        self.node(&edge.target);
+        self.edges.push(edge);
+    }
+
+    /// The edges in the order they were added.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// The pages and link targets, in the order they were first seen.
+    pub fn nodes(&self) -> &[Url] {
+        &self.nodes
+    }
+
+    /// Writes the graph as GraphML, with the anchor text and `rel` as edge attributes.
+    pub fn write_graphml(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="url" for="node" attr.name="url" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="anchor" for="edge" attr.name="anchor_text" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="rel" for="edge" attr.name="rel" attr.type="string"/>"#
+        )?;
+        writeln!(writer, r#"  <graph id="links" edgedefault="directed">"#)?;
+        for (id, url) in self.nodes.iter().enumerate() {
+            writeln!(
+                writer,
+                r#"    <node id="n{id}"><data key="url">{}</data></node>"#,
+                escape(url.as_str())
+            )?;
+        }
+        for edge in &self.edges {
+            write!(
+                writer,
+                r#"    <edge source="n{}" target="n{}"><data key="anchor">{}</data>"#,
+                self.ids[&edge.source],
+                self.ids[&edge.target],
+                escape(edge.anchor_text.as_str())
+            )?;
+            if let Some(rel) = &edge.rel {
+                write!(writer, r#"<data key="rel">{}</data>"#, escape(rel.as_str()))?;
+            }
+            writeln!(writer, "</edge>")?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+
+    /// Writes the graph in Graphviz's DOT language, labelling edges with their anchor text.
+    pub fn write_dot(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "digraph links {{")?;
+        for url in &self.nodes {
+            writeln!(writer, "  {};", dot_string(url.as_str()))?;
+        }
+        for edge in &self.edges {
+            write!(
+                writer,
+                "  {} -> {} [label={}",
+                dot_string(edge.source.as_str()),
+                dot_string(edge.target.as_str()),
+                dot_string(&edge.anchor_text)
+            )?;
+            if let Some(rel) = &edge.rel {
+                write!(writer, ", rel={}", dot_string(rel))?;
+            }
+            writeln!(writer, "];")?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Writes one `source<TAB>target<TAB>rel<TAB>anchor text` line per edge, with tabs and
+    /// line breaks in the anchor text replaced by spaces.
+    pub fn write_edge_list(&self, mut writer: impl Write) -> io::Result<()> {
+        for edge in &self.edges {
+            let anchor_text = edge.anchor_text.replace(['\t', '\r', '\n'], " ");
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{anchor_text}",
+                edge.source,
+                edge.target,
+                edge.rel.as_deref().unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the edge list to a file, compressed with `compression`, whose extension is
+    /// added to the path.
+    pub fn write_edge_list_file(
+        &self,
+        path: impl AsRef<Path>,
+        compression: Compression,
+    ) -> io::Result<()> {
+        let mut path = path.as_ref().as_os_str().to_owned();
+        path.push(compression.extension());
+        let mut file = CompressedFile::create(Path::new(&path), compression)?;
+        self.write_edge_list(&mut file)?;
+        file.finish()
+    }
+
+    fn node(&mut self, url: &Url) {
+        if !self.ids.contains_key(url) {
+            self.ids.insert(url.clone(), self.nodes.len());
+            self.nodes.push(url.clone());
+        }
+    }
+}
+
+/// Collects the HTTP(S) links of a document, resolved against the page URL or `<base href>`.
+pub fn page_links(document: &Html, page_url: &Url) -> Vec<Edge> {
+    // Unwrapping is safe here because the selectors are valid constants.
+    let base_selector = Selector::parse("base[href]").unwrap();
+    let base = document
+        .select(&base_selector)
+        .next()
+        .and_then(|base| page_url.join(base.attr("href")?).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let mut source = page_url.clone();
+    source.set_fragment(None);
+    let selector = Selector::parse("a[href], area[href]").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|link| {
+            let mut target = base.join(link.attr("href")?.trim()).ok()?;
+            if !matches!(target.scheme(), "http" | "https") {
+                return None;
+            }
+            target.set_fragment(None);
+            let rel = link
+                .attr("rel")
+                .map(|rel| rel.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|rel| !rel.is_empty())
+                .map(|rel| rel.to_lowercase());
+
+            Some(Edge {
+                source: source.clone(),
+                target,
+                anchor_text: element_text(link),
+                rel,
+            })
+        })
+        .collect()
+}
+
+/// A DOT double-quoted string.
+fn dot_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    fn graph() -> LinkGraph {
+        let mut graph = LinkGraph::new();
+        graph.add_page(
+            &Url::parse("https://example.com/docs/#top").unwrap(),
+            r#"
+            <base href="https://example.com/guide/">
+            <a href="intro#setup" rel=" NoFollow  ugc ">Intro &amp; "setup"</a>
+            <a href="mailto:kirby@example.com">Mail</a>
+            <area href="/">
+            "#,
+        );
+        graph
+    }
+
+    #[test]
+    fn writes_graphml_and_dot() {
+        let graph = graph();
+        assert_eq!(graph.nodes().len(), 3);
+
+        let mut graphml = Vec::new();
+        graph.write_graphml(&mut graphml).unwrap();
+        let graphml = String::from_utf8(graphml).unwrap();
+        assert!(graphml.contains(
+            r#"<node id="n1"><data key="url">https://example.com/guide/intro</data></node>"#
+        ));
+        assert!(graphml.contains(
+            r#"<edge source="n0" target="n1"><data key="anchor">Intro &amp; &quot;setup&quot;</data><data key="rel">nofollow ugc</data></edge>"#
+        ));
+
+        let mut dot = Vec::new();
+        graph.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(
+            dot.contains(r#"  "https://example.com/docs/" -> "https://example.com/" [label=""];"#)
+        );
+        assert!(dot.contains(r#"[label="Intro & \"setup\"", rel="nofollow ugc"];"#));
+    }
+
+    #[test]
+    fn writes_compressed_edge_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        graph()
+            .write_edge_list_file(dir.path().join("edges.tsv"), Compression::Gzip)
+            .unwrap();
+
+        let mut text = String::new();
+        let file = fs::File::open(dir.path().join("edges.tsv.gz")).unwrap();
+        GzDecoder::new(file).read_to_string(&mut text).unwrap();
+        assert_eq!(
+            text,
+            "https://example.com/docs/\thttps://example.com/guide/intro\tnofollow ugc\tIntro & \"setup\"\n\
+             https://example.com/docs/\thttps://example.com/\t\t\n"
+        );
+    }
+}
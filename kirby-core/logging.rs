@@ -0,0 +1,182 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// The filter used when `RUST_LOG` isn't set.
+pub const DEFAULT_FILTER: &str = "info";
+
+/// How log lines are written.
+///
+/// Both formats carry the same [standard fields](crate::trace::fields), as logged by kirby's
+/// spans and [`log_event`](crate::trace::log_event).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One readable line per event, for terminals.
+    #[default]
+    Human,
+    /// One JSON object per line, for ELK, Loki and other log pipelines. The event's fields are
+    /// top-level keys next to `timestamp`, `level`, `target` and `message`, and the fields of
+    /// the span it happened in are under `span`.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ParseLogFormatError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "human" | "text" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(ParseLogFormatError(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Human => "human",
+            Self::Json => "json",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLogFormatError(String);
+
+impl fmt::Display for ParseLogFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown log format {:?}, expected \"human\" or \"json\"",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseLogFormatError {}
+
+/// A subscriber writing log lines in `format` to `writer`, for events enabled by `filter`
+/// (in `RUST_LOG` syntax, e.g. `info,kirby_core=debug`).
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::events::CrawlEvent;
+/// use kirby_core::logging::{subscriber, LogFormat};
+/// use kirby_core::trace::log_event;
+///
+/// let format = "json".parse::<LogFormat>().unwrap();
+/// let subscriber = subscriber(format, "info", std::io::stdout);
+/// tracing::subscriber::with_default(subscriber, || {
+///     log_event(&CrawlEvent::BudgetExhausted { budget: "pages".to_string() });
+/// });
+/// ```
+pub fn subscriber<W>(
+    format: LogFormat,
+    filter: &str,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(filter))
+        .with_writer(writer);
+    match format {
+        LogFormat::Human => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(false)
+                .finish(),
+        ),
+    }
+}
+
+/// Logs to stderr in `format` for the rest of the process, filtered by `RUST_LOG` or else
+/// [`DEFAULT_FILTER`]. Fails when a global subscriber was already set.
+pub fn init(format: LogFormat) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+    tracing::subscriber::set_global_default(subscriber(format, &filter, io::stderr))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde_json::Value;
+    use url::Url;
+
+    use super::*;
+    use crate::events::CrawlEvent;
+    use crate::trace::{log_event, url_span, Stage};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn writes_flat_json_lines_with_the_span() {
+        let buffer = Buffer::default();
+        let subscriber = subscriber(LogFormat::Json, "info", buffer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let url = Url::parse("https://example.com/a").unwrap();
+            let _span = url_span(Stage::Fetch, &url, 1, Some(2)).entered();
+            log_event(&CrawlEvent::FetchStarted {
+                url: url.clone(),
+                attempt: 2,
+            });
+            log_event(&CrawlEvent::FetchFinished {
+                url,
+                status: 200,
+                duration: Duration::from_millis(120),
+                bytes: 512,
+            });
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        // The debug event is filtered out.
+        assert_eq!(lines.len(), 1);
+        let line: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "fetched");
+        assert_eq!(line["event"], "fetch_finished");
+        assert_eq!(line["host"], "example.com");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["duration_ms"], 120);
+        assert_eq!(line["span"]["name"], "fetch");
+        assert_eq!(line["span"]["attempt"], 2);
+
+        assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
@@ -0,0 +1,421 @@
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use crate::config::LoginConfig;
+use crate::cookies::CookieJar;
+use crate::error::{ErrorKind, KirbyError};
+use crate::fetch::{Fetcher, Response};
+use crate::render::{Browser, Renderer};
+
+/// How long a login in the browser may take to show that it worked.
+pub const BROWSER_LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the browser is checked while logging in.
+const LOGIN_POLL: Duration = Duration::from_millis(100);
+
+/// Logs a crawl in, and back in when its session expires.
+///
+/// The login form is read and submitted with the crawl's fetcher, keeping the session in the
+/// fetcher's cookie jar. When the crawl renders pages, the form is filled in and submitted in
+/// the browser instead, and the browser's cookies are copied to the jar, so both share the
+/// session.
+///
+/// A page counts as logged out when it answers `401 Unauthorized` or redirects to the login
+/// page.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::collections::BTreeMap;
+///
+/// use kirby_core::config::{CssSelector, LoginConfig};
+/// use kirby_core::cookies::CookieJar;
+/// use kirby_core::fetch::Fetcher;
+/// use kirby_core::login::Session;
+/// use url::Url;
+///
+/// let session = Session::new(LoginConfig {
+///     url: Url::parse("https://example.com/login").unwrap(),
+///     form: None,
+///     fields: BTreeMap::from([
+///         ("user".to_string(), "kirby".to_string()),
+///         ("password".to_string(), "warp-star".to_string()),
+///     ]),
+///     success_selector: Some(CssSelector::parse("a.logout").unwrap()),
+///     success_cookie: None,
+/// });
+/// let fetcher = Fetcher::new("KirbyBot/1.0").cookies(CookieJar::new());
+/// session.login(session.logins(), &fetcher, None).unwrap();
+///
+/// let account = fetcher.fetch(&Url::parse("https://example.com/account").unwrap()).unwrap();
+/// assert!(!session.is_logged_out(&account));
+/// ```
+pub struct Session {
+    config: LoginConfig,
+    /// How many times the crawl logged in.
+    logins: Mutex<u64>,
+}
+
+impl Session {
+    pub fn new(config: LoginConfig) -> Self {
+        Self {
+            config,
+            logins: Mutex::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &LoginConfig {
+        &self.config
+    }
+
+    /// How many times the crawl logged in, to pass to [`login`](Self::login).
+    pub fn logins(&self) -> u64 {
+        *self.lock()
+    }
+
+    /// Logs in with `fetcher`, or in the browser of `renderer`, unless that already happened
+    /// since the number of [`logins`](Self::logins) was `seen`. Threads finding the session
+    /// expired at the same time so log in once.
+    ///
+    /// The fetcher must keep cookies, see [`Fetcher::cookies`].
+    pub fn login(
+        &self,
+        seen: u64,
+        fetcher: &Fetcher,
+        renderer: Option<&Renderer>,
+    ) -> Result<(), KirbyError> {
+        let mut logins = self.lock();
+        if *logins != seen {
+            return Ok(());
+        }
+        let jar = fetcher
+            .cookie_jar()
+            .ok_or_else(|| login_error("the fetcher keeps no cookies"))?;
+        match renderer {
+            Some(renderer) => login_in_browser(&self.config, renderer.browser().as_mut(), jar),
+            None => submit_form(&self.config, fetcher, jar),
+        }
+        .map_err(|error| error.with_url(self.config.url.clone()))?;
+        *logins += 1;
+        Ok(())
+    }
+
+    /// Whether a response shows that the session expired.
+    pub fn is_logged_out(&self, response: &Response) -> bool {
+        let login = &self.config.url;
+        let at_login = |url: &Url| url.origin() == login.origin() && url.path() == login.path();
+        response.status == 401 || (!at_login(&response.url) && at_login(response.final_url()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, u64> {
+        self.logins
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+fn login_error(message: impl Into<String>) -> KirbyError {
+    KirbyError::new(ErrorKind::Login, message)
+}
+
+/// Reads the login form, fills it in and submits it as a browser would.
+fn submit_form(config: &LoginConfig, fetcher: &Fetcher, jar: &CookieJar) -> Result<(), KirbyError> {
+    let page = fetcher.fetch(&config.url)?;
+    if !(200..300).contains(&page.status) {
+        return Err(login_error(format!(
+            "the login page answered {}",
+            page.status
+        )));
+    }
+    let document = Html::parse_document(&page.text());
+    let selector = config
+        .form
+        .as_ref()
+        .map_or_else(|| Selector::parse("form").unwrap(), |form| form.selector());
+    let form = document
+        .select(&selector)
+        .next()
+        .ok_or_else(|| login_error("there's no login form on the page"))?;
+
+    let mut fields = form_fields(form);
+    for (name, value) in &config.fields {
+        match fields.iter_mut().find(|(field, _)| field == name) {
+            Some(field) => field.1 = value.clone(),
+            None => fields.push((name.clone(), value.clone())),
+        }
+    }
+    let action = match form
+        .value()
+        .attr("action")
+        .filter(|action| !action.is_empty())
+    {
+        Some(action) => page
+            .final_url()
+            .join(action)
+            .map_err(|error| login_error(format!("the form's action {action:?}: {error}")))?,
+        None => page.final_url().clone(),
+    };
+    let response = match form.value().attr("method") {
+        Some(method) if method.eq_ignore_ascii_case("get") => {
+            let mut action = action;
+            action.query_pairs_mut().clear().extend_pairs(&fields);
+            fetcher.fetch(&action)?
+        }
+        _ => fetcher.post_form(&action, &fields)?,
+    };
+
+    if response.status >= 400 {
+        return Err(login_error(format!(
+            "the form was answered with {}",
+            response.status
+        )));
+    }
+    if let Some(selector) = &config.success_selector {
+        let page = Html::parse_document(&response.text());
+        if page.select(&selector.selector()).next().is_none() {
+            return Err(login_error(format!(
+                "`{}` isn't on the page after logging in",
+                selector.as_str()
+            )));
+        }
+    }
+    match &config.success_cookie {
+        Some(cookie) if !jar.contains(cookie) => Err(login_error(format!(
+            "logging in didn't set the cookie `{cookie}`"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The names and values a form submits as it is: checked checkboxes and radio buttons,
+/// selected options and the other inputs but buttons and files.
+fn form_fields(form: ElementRef<'_>) -> Vec<(String, String)> {
+    let inputs = Selector::parse("input[name], select[name], textarea[name]").unwrap();
+    let selected = Selector::parse("option[selected]").unwrap();
+    let mut fields = Vec::new();
+    for input in form.select(&inputs) {
+        let element = input.value();
+        let name = element.attr("name").unwrap_or_default().to_string();
+        let value = match element.name() {
+            "textarea" => input.text().collect(),
+            "select" => match input.select(&selected).next() {
+                Some(option) => option
+                    .value()
+                    .attr("value")
+                    .map_or_else(|| option.text().collect(), str::to_string),
+                None => continue,
+            },
+            _ => {
+                let kind = element.attr("type").unwrap_or("text").to_ascii_lowercase();
+                let unchecked = matches!(kind.as_str(), "checkbox" | "radio")
+                    && element.attr("checked").is_none();
+                if unchecked || matches!(kind.as_str(), "submit" | "button" | "image" | "file") {
+                    continue;
+                }
+                let default = if kind == "checkbox" { "on" } else { "" };
+                element.attr("value").unwrap_or(default).to_string()
+            }
+        };
+        fields.push((name, value));
+    }
+    fields
+}
+
+/// Fills in and submits the login form in the browser, then waits for the page to show that
+/// it worked and copies the browser's cookies to `jar`.
+fn login_in_browser(
+    config: &LoginConfig,
+    browser: &mut dyn Browser,
+    jar: &CookieJar,
+) -> Result<(), KirbyError> {
+    browser.navigate(&config.url)?;
+    let form = config.form.as_ref().map_or("form", |form| form.as_str());
+    for (name, value) in &config.fields {
+        let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+        browser.fill(&format!("{form} [name=\"{name}\"]"), value)?;
+    }
+    browser.execute(&format!(
+        "const form = document.querySelector({});\n\
+         form.requestSubmit ? form.requestSubmit() : form.submit();",
+        serde_json::Value::from(form)
+    ))?;
+
+    let started = Instant::now();
+    loop {
+        let cookies = browser.cookies()?;
+        let has_cookie = config
+            .success_cookie
+            .as_ref()
+            .is_none_or(|name| cookies.iter().any(|cookie| &cookie.name == name));
+        let has_element = match &config.success_selector {
+            Some(selector) => browser.count(selector.as_str())? > 0,
+            None => true,
+        };
+        if has_cookie && has_element {
+            for cookie in cookies {
+                jar.insert(cookie);
+            }
+            return Ok(());
+        }
+        if started.elapsed() >= BROWSER_LOGIN_TIMEOUT {
+            return Err(login_error(format!(
+                "the browser didn't show it was logged in after {BROWSER_LOGIN_TIMEOUT:?}"
+            )));
+        }
+        thread::sleep(LOGIN_POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::config::CssSelector;
+    use crate::cookies::Cookie;
+    use crate::render::test_browser::TestBrowser;
+
+    fn config(url: Url) -> LoginConfig {
+        LoginConfig {
+            url,
+            form: Some(CssSelector::parse("form#login").unwrap()),
+            fields: BTreeMap::from([
+                ("password".to_string(), "warp-star".to_string()),
+                ("user".to_string(), "kirby".to_string()),
+            ]),
+            success_selector: Some(CssSelector::parse(".logout").unwrap()),
+            success_cookie: Some("session".to_string()),
+        }
+    }
+
+    #[test]
+    fn submits_the_login_form() {
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/login",
+                    Page::html(
+                        r#"<form id="search"><input name="q"></form>
+                        <form id="login" action="/session" method="post">
+                          <input type="hidden" name="csrf" value="t0k3n">
+                          <input name="user"> <input type="password" name="password">
+                          <input type="checkbox" name="remember" value="yes">
+                          <select name="lang"><option>de</option><option selected>en</option></select>
+                          <button type="submit">Log in</button>
+                        </form>"#,
+                    ),
+                )
+                .page(
+                    "/session",
+                    Page::new(303, "")
+                        .header("Location", "/account")
+                        .header("Set-Cookie", "session=s1; Path=/"),
+                )
+                .page(
+                    "/account",
+                    Page::html(r#"<a class="logout" href="/logout">Log out</a>"#),
+                ),
+        );
+        let login = server.url().join("/login").unwrap();
+        let session = Session::new(config(login.clone()));
+        let jar = CookieJar::new();
+        let fetcher = Fetcher::new("KirbyBot/1.0").cookies(jar.clone());
+
+        session.login(0, &fetcher, None).unwrap();
+        assert_eq!(session.logins(), 1);
+        // Someone else logged in since, so nothing's done.
+        session.login(0, &fetcher, None).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[1].path, "/session");
+        assert_eq!(
+            requests[1].body,
+            b"csrf=t0k3n&user=kirby&password=warp-star&lang=en"
+        );
+        assert_eq!(requests[2].header("cookie"), Some("session=s1"));
+        assert_eq!(
+            jar.header(&login.join("/account").unwrap()).as_deref(),
+            Some("session=s1")
+        );
+    }
+
+    #[test]
+    fn fails_when_the_login_is_rejected() {
+        let server = MockServer::start(Site::new().pages(
+            "/login",
+            [
+                Page::html(r#"<form id="login" method="post"><input name="user"></form>"#),
+                Page::html("<p>Wrong password</p>"),
+            ],
+        ));
+        let session = Session::new(config(server.url().join("/login").unwrap()));
+        let fetcher = Fetcher::new("KirbyBot/1.0").cookies(CookieJar::new());
+
+        let error = session.login(0, &fetcher, None).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Login);
+        assert_eq!(
+            error.message(),
+            "`.logout` isn't on the page after logging in"
+        );
+        assert_eq!(session.logins(), 0);
+        assert_eq!(server.requests()[1].path, "/login");
+    }
+
+    #[test]
+    fn logs_in_in_the_browser() {
+        let login = Url::parse("https://example.com/login").unwrap();
+        let browser = TestBrowser::new(r#"<a class="logout">Log out</a>"#).cookie(Cookie {
+            name: "session".to_string(),
+            value: "s1".to_string(),
+            domain: "example.com".to_string(),
+            host_only: true,
+            path: "/".to_string(),
+            secure: true,
+        });
+        let calls = browser.calls();
+        let renderer = Renderer::new(browser);
+        let jar = CookieJar::new();
+        let fetcher = Fetcher::new("KirbyBot/1.0").cookies(jar.clone());
+
+        Session::new(config(login.clone()))
+            .login(0, &fetcher, Some(&renderer))
+            .unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "navigate https://example.com/login",
+                "fill form#login [name=\"password\"] warp-star",
+                "fill form#login [name=\"user\"] kirby",
+                "execute",
+            ]
+        );
+        assert_eq!(jar.header(&login).as_deref(), Some("session=s1"));
+    }
+
+    #[test]
+    fn tells_logged_out_pages_apart() {
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/account",
+                    Page::new(302, "").header("Location", "/login?next=/account"),
+                )
+                .page("/login", Page::html("<form></form>"))
+                .page("/api", Page::new(401, "")),
+        );
+        let session = Session::new(config(server.url().join("/login").unwrap()));
+        let fetcher = Fetcher::new("KirbyBot/1.0");
+
+        let fetch = |path: &str| fetcher.fetch(&server.url().join(path).unwrap()).unwrap();
+        assert!(session.is_logged_out(&fetch("/account")));
+        assert!(!session.is_logged_out(&fetch("/login")));
+        assert!(session.is_logged_out(&fetch("/api")));
+    }
+}
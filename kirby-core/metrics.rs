@@ -0,0 +1,347 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::export::Sink;
+use crate::http_server::{HttpServer, Response};
+use crate::record::PageRecord;
+
+/// The upper bounds of the fetch latency histogram, in seconds.
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Crawl metrics in the Prometheus text format.
+///
+/// A crawler given metrics with [`Crawler::metrics`](crate::crawler::Crawler::metrics) records
+/// its fetches, retries, robots.txt denials and queue depths as they happen;
+/// [`gather`](Self::gather) renders the current values for a scrape, and
+/// [`serve`](Self::serve) answers scrapes over HTTP. Metrics are shared between threads
+/// through an `Arc`.
+///
+/// | Metric                           | Type      | Labels   |
+/// |----------------------------------|-----------|----------|
+/// | `kirby_requests_total`           | counter   | `status` |
+/// | `kirby_fetch_duration_seconds`   | histogram |          |
+/// | `kirby_downloaded_bytes_total`   | counter   |          |
+/// | `kirby_retries_total`            | counter   |          |
+/// | `kirby_robots_denials_total`     | counter   |          |
+/// | `kirby_queue_depth`              | gauge     | `host`   |
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_core::metrics::Metrics;
+///
+/// let metrics = Metrics::new();
+/// metrics.record_fetch(200, Duration::from_millis(120), 5120);
+/// metrics.set_queue_depth("example.com", 42);
+///
+/// let text = metrics.gather();
+/// assert!(text.contains("kirby_requests_total{status=\"200\"} 1\n"));
+/// assert!(text.contains("kirby_fetch_duration_seconds_bucket{le=\"0.25\"} 1\n"));
+/// assert!(text.contains("kirby_queue_depth{host=\"example.com\"} 42\n"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Metrics {
+    values: Mutex<Values>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Values {
+    pub requests: BTreeMap<u16, u64>,
+    /// Count per bucket of `LATENCY_BUCKETS`, not cumulative.
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    pub latency_count: u64,
+    pub latency_sum: f64,
+    pub bytes: u64,
+    pub retries: u64,
+    pub robots_denials: u64,
+    pub queue_depths: BTreeMap<String, u64>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a fetch that got a response.
+    pub fn record_fetch(&self, status: u16, latency: Duration, bytes: u64) {
+        let mut values = self.values();
+        *values.requests.entry(status).or_default() += 1;
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            values.latency_buckets[bucket] += 1;
+        }
+        values.latency_count += 1;
+        values.latency_sum += seconds;
+        values.bytes += bytes;
+    }
+
+    /// Records a fetch from its page record, using the total time of its timings.
+    pub fn record_page(&self, record: &PageRecord, body: Option<&[u8]>) {
+        let latency = Duration::from_millis(record.timings.total_ms.unwrap_or_default());
+        let bytes = body.map_or(0, |body| body.len() as u64);
+        self.record_fetch(record.status, latency, bytes);
+    }
+
+    pub fn record_retry(&self) {
+        self.values().retries += 1;
+    }
+
+    /// Records a URL that robots.txt didn't allow to be fetched.
+    pub fn record_robots_denial(&self) {
+        self.values().robots_denials += 1;
+    }
+
+    /// Sets the number of URLs queued for a host, removing the host at zero.
+    pub fn set_queue_depth(&self, host: &str, depth: u64) {
+        let mut values = self.values();
+        if depth == 0 {
+            values.queue_depths.remove(host);
+        } else {
+            values.queue_depths.insert(host.to_string(), depth);
+        }
+    }
+
+    /// The current values in the Prometheus text exposition format.
+    pub fn gather(&self) -> String {
+        let values = self.values();
+        let mut text = String::new();
+
+        header(
+            &mut text,
+            "kirby_requests_total",
+            "counter",
+            "Fetches by response status.",
+        );
+        for (status, count) in &values.requests {
+            let _ = writeln!(text, "kirby_requests_total{{status=\"{status}\"}} {count}");
+        }
+
+        header(
+            &mut text,
+            "kirby_fetch_duration_seconds",
+            "histogram",
+            "How long fetches took.",
+        );
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(values.latency_buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                text,
+                "kirby_fetch_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            text,
+            "kirby_fetch_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            values.latency_count
+        );
+        let _ = writeln!(
+            text,
+            "kirby_fetch_duration_seconds_sum {}",
+            values.latency_sum
+        );
+        let _ = writeln!(
+            text,
+            "kirby_fetch_duration_seconds_count {}",
+            values.latency_count
+        );
+
+        for (name, help, value) in [
+            (
+                "kirby_downloaded_bytes_total",
+                "Bytes of response bodies downloaded.",
+                values.bytes,
+            ),
+            (
+                "kirby_retries_total",
+                "Fetches that were retried.",
+                values.retries,
+            ),
+            (
+                "kirby_robots_denials_total",
+                "URLs robots.txt didn't allow to be fetched.",
+                values.robots_denials,
+            ),
+        ] {
+            header(&mut text, name, "counter", help);
+            let _ = writeln!(text, "{name} {value}");
+        }
+
+        header(
+            &mut text,
+            "kirby_queue_depth",
+            "gauge",
+            "URLs queued per host.",
+        );
+        for (host, depth) in &values.queue_depths {
+            let _ = writeln!(
+                text,
+                "kirby_queue_depth{{host=\"{}\"}} {depth}",
+                label(host)
+            );
+        }
+        text
+    }
+
+    /// A copy of the current values, for exporters other than Prometheus.
+    #[cfg(feature = "otel")]
+    pub(crate) fn snapshot(&self) -> Values {
+        self.values().clone()
+    }
+
+    /// Answers requests for `/metrics` with the gathered metrics on a background thread,
+    /// until the returned server is dropped.
+    pub fn serve(self: &Arc<Self>, address: impl ToSocketAddrs) -> io::Result<MetricsServer> {
+        let metrics = Arc::clone(self);
+        let server = HttpServer::start(address, move |request| {
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/" | "/metrics") => {
+                    Response::new(200, "text/plain; version=0.0.4", metrics.gather())
+                }
+                ("GET", _) => Response::empty(404),
+                _ => Response::empty(405),
+            }
+        })?;
+        Ok(MetricsServer { server })
+    }
+
+    fn values(&self) -> MutexGuard<'_, Values> {
+        // Every update leaves the values consistent, so they are still usable after a panic.
+        self.values
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+/// The HTTP endpoint started by [`Metrics::serve`], stopped when dropped.
+pub struct MetricsServer {
+    server: HttpServer,
+}
+
+impl MetricsServer {
+    pub fn address(&self) -> SocketAddr {
+        self.server.address()
+    }
+}
+
+/// Records the pages written to a sink before passing them on.
+pub struct MetricsSink<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+}
+
+impl<S: Sink> MetricsSink<S> {
+    pub fn new(inner: S, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink> Sink for MetricsSink<S> {
+    fn write(&mut self, record: &PageRecord, body: Option<&[u8]>) -> io::Result<()> {
+        self.metrics.record_page(record, body);
+        self.inner.write(record, body)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {name} {help}");
+    let _ = writeln!(text, "# TYPE {name} {kind}");
+}
+
+/// Escapes a label value.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use chrono::Utc;
+    use url::Url;
+
+    use super::*;
+    use crate::export::csv::CsvSink;
+    use crate::export::Column;
+
+    #[test]
+    fn gathers_histograms_and_counters() {
+        let metrics = Metrics::new();
+        metrics.record_fetch(200, Duration::from_millis(20), 100);
+        metrics.record_fetch(404, Duration::from_secs(30), 10);
+        metrics.record_retry();
+        metrics.record_robots_denial();
+        metrics.set_queue_depth("a\"b", 3);
+        metrics.set_queue_depth("gone.example", 1);
+        metrics.set_queue_depth("gone.example", 0);
+
+        let text = metrics.gather();
+        for line in [
+            "kirby_requests_total{status=\"404\"} 1",
+            "kirby_fetch_duration_seconds_bucket{le=\"0.01\"} 0",
+            "kirby_fetch_duration_seconds_bucket{le=\"0.025\"} 1",
+            "kirby_fetch_duration_seconds_bucket{le=\"10\"} 1",
+            "kirby_fetch_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "kirby_fetch_duration_seconds_sum 30.02",
+            "kirby_downloaded_bytes_total 110",
+            "kirby_retries_total 1",
+            "kirby_robots_denials_total 1",
+            "kirby_queue_depth{host=\"a\\\"b\"} 3",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{line} missing from:\n{text}"
+            );
+        }
+        assert!(!text.contains("gone.example"));
+    }
+
+    #[test]
+    fn serves_metrics_recorded_by_the_sink() {
+        let metrics = Arc::new(Metrics::new());
+        let mut sink = MetricsSink::new(
+            CsvSink::new(Vec::new(), Column::defaults()),
+            Arc::clone(&metrics),
+        );
+        let mut record =
+            PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+        record.timings.total_ms = Some(300);
+        sink.write(&record, Some(b"<p>Hi</p>")).unwrap();
+
+        let server = metrics.serve("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("kirby_fetch_duration_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(response.contains("kirby_downloaded_bytes_total 9\n"));
+
+        let csv = sink.into_inner().into_inner().unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .contains("https://example.com/"));
+    }
+}
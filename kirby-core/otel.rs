@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use data_encoding::HEXLOWER;
+use serde_json::{json, Value};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Dispatch, Event, Level, Metadata, Subscriber};
+use url::Url;
+use uuid::Uuid;
+
+use crate::metrics::{Metrics, LATENCY_BUCKETS};
+
+/// OTLP's `SPAN_KIND_INTERNAL`.
+const SPAN_KIND_INTERNAL: u8 = 1;
+
+/// OTLP's `AGGREGATION_TEMPORALITY_CUMULATIVE`.
+const CUMULATIVE: u8 = 2;
+
+/// Sends traces and metrics to an OpenTelemetry collector over OTLP/HTTP, with JSON bodies.
+///
+/// Everything sent carries the same resource attributes: `service.name` (`kirby` unless set),
+/// `service.version`, and the bot and crawl id when set as `kirby.bot` and `kirby.crawl.id`,
+/// so a crawl can be told apart from others in Jaeger, Tempo or Grafana.
+///
+/// [`tracer`](Self::tracer) turns the crawl's spans, see [`trace`](crate::trace), into OTLP
+/// spans. [`export_metrics`](Self::export_metrics) sends the values of a [`Metrics`], and
+/// [`push_metrics`](Self::push_metrics) does so periodically:
+///
+/// | Metric                 | Type      | Attributes                  |
+/// |------------------------|-----------|-----------------------------|
+/// | `kirby.requests`       | sum       | `http.response.status_code` |
+/// | `kirby.fetch.duration` | histogram |                             |
+/// | `kirby.downloaded`     | sum       |                             |
+/// | `kirby.retries`        | sum       |                             |
+/// | `kirby.robots_denials` | sum       |                             |
+/// | `kirby.queue.depth`    | gauge     | `server.address`            |
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use kirby_core::metrics::Metrics;
+/// use kirby_core::otel::OtlpExporter;
+/// use url::Url;
+///
+/// let exporter = OtlpExporter::new(Url::parse("http://localhost:4318/").unwrap())
+///     .bot("kirby/0.1 (+https://example.com/bot)")
+///     .crawl_id("2026-05-01-news");
+///
+/// let tracer = exporter.tracer();
+/// tracing::subscriber::set_global_default(tracer.clone()).unwrap();
+/// let metrics = Arc::new(Metrics::new());
+/// let pusher = exporter.push_metrics(Arc::clone(&metrics), Duration::from_secs(30));
+///
+/// // Crawl...
+///
+/// tracer.flush().unwrap();
+/// drop(pusher);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    agent: ureq::Agent,
+    endpoint: Url,
+    headers: Vec<(String, String)>,
+    resource: Vec<(String, String)>,
+    level: Level,
+    batch_size: usize,
+    started: SystemTime,
+}
+
+impl OtlpExporter {
+    /// Sends to the collector at `endpoint`, e.g. `http://localhost:4318/`, under `v1/traces`
+    /// and `v1/metrics`.
+    ///
+    /// Metrics are cumulative from the moment the exporter is created.
+    pub fn new(mut endpoint: Url) -> Self {
+        if !endpoint.path().ends_with('/') {
+            endpoint.set_path(&format!("{}/", endpoint.path()));
+        }
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+            endpoint,
+            headers: Vec::new(),
+            resource: vec![
+                ("service.name".to_string(), "kirby".to_string()),
+                (
+                    "service.version".to_string(),
+                    env!("CARGO_PKG_VERSION").to_string(),
+                ),
+            ],
+            level: Level::INFO,
+            batch_size: 512,
+            started: SystemTime::now(),
+        }
+    }
+
+    pub fn service_name(self, name: impl Into<String>) -> Self {
+        self.attribute("service.name", name)
+    }
+
+    /// Sets the bot the crawl runs as, e.g. its user agent.
+    pub fn bot(self, bot: impl Into<String>) -> Self {
+        self.attribute("kirby.bot", bot)
+    }
+
+    pub fn crawl_id(self, crawl_id: impl Into<String>) -> Self {
+        self.attribute("kirby.crawl.id", crawl_id)
+    }
+
+    /// Sets a resource attribute, replacing an earlier value.
+    pub fn attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        let value = value.into();
+        match self.resource.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.resource.push((key, value)),
+        }
+        self
+    }
+
+    /// Adds a header to every request, e.g. `Authorization`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the most verbose level of the spans and events traced, `INFO` by default.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets how many spans are sent at a time, 512 by default.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// A tracing subscriber sending finished spans in batches from a background thread.
+    ///
+    /// Spans are sent when a batch is full and every five seconds; call
+    /// [`OtlpTracer::flush`] at the end of the crawl to send the rest.
+    pub fn tracer(&self) -> OtlpTracer {
+        let (sender, receiver) = mpsc::channel();
+        let exporter = self.clone();
+        thread::spawn(move || exporter.send_spans(receiver));
+        OtlpTracer {
+            inner: Arc::new(TracerInner {
+                level: self.level,
+                next_id: AtomicU64::new(1),
+                spans: Mutex::new(HashMap::new()),
+                stacks: Mutex::new(HashMap::new()),
+                sender: Mutex::new(sender),
+            }),
+        }
+    }
+
+    /// Sends the current values of `metrics`.
+    pub fn export_metrics(&self, metrics: &Metrics) -> io::Result<()> {
+        let values = metrics.snapshot();
+        let start = unix_nanos(self.started);
+        let now = unix_nanos(SystemTime::now());
+        let point = |attributes: Value, value: u64| {
+            json!({
+                "attributes": attributes,
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "asInt": value.to_string(),
+            })
+        };
+        let sum = |name: &str, unit: &str, description: &str, points: Vec<Value>| {
+            json!({
+                "name": name,
+                "unit": unit,
+                "description": description,
+                "sum": {
+                    "dataPoints": points,
+                    "aggregationTemporality": CUMULATIVE,
+                    "isMonotonic": true,
+                },
+            })
+        };
+
+        let requests = values
+            .requests
+            .iter()
+            .map(|(status, count)| {
+                let attributes = json!([int_attribute("http.response.status_code", *status)]);
+                point(attributes, *count)
+            })
+            .collect();
+        let overflow = values.latency_count - values.latency_buckets.iter().sum::<u64>();
+        let bucket_counts = values
+            .latency_buckets
+            .iter()
+            .chain([&overflow])
+            .map(u64::to_string)
+            .collect::<Vec<_>>();
+        let queue_depths = values
+            .queue_depths
+            .iter()
+            .map(|(host, depth)| point(json!([attribute("server.address", host)]), *depth))
+            .collect::<Vec<_>>();
+
+        let metrics = json!([
+            sum("kirby.requests", "{request}", "Fetches by response status.", requests),
+            {
+                "name": "kirby.fetch.duration",
+                "unit": "s",
+                "description": "How long fetches took.",
+                "histogram": {
+                    "dataPoints": [{
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": now,
+                        "count": values.latency_count.to_string(),
+                        "sum": values.latency_sum,
+                        "bucketCounts": bucket_counts,
+                        "explicitBounds": LATENCY_BUCKETS,
+                    }],
+                    "aggregationTemporality": CUMULATIVE,
+                },
+            },
+            sum(
+                "kirby.downloaded",
+                "By",
+                "Bytes of response bodies downloaded.",
+                vec![point(json!([]), values.bytes)],
+            ),
+            sum(
+                "kirby.retries",
+                "{retry}",
+                "Fetches that were retried.",
+                vec![point(json!([]), values.retries)],
+            ),
+            sum(
+                "kirby.robots_denials",
+                "{url}",
+                "URLs robots.txt didn't allow to be fetched.",
+                vec![point(json!([]), values.robots_denials)],
+            ),
+            {
+                "name": "kirby.queue.depth",
+                "unit": "{url}",
+                "description": "URLs queued per host.",
+                "gauge": {"dataPoints": queue_depths},
+            },
+        ]);
+        self.send(
+            "v1/metrics",
+            json!({
+                "resourceMetrics": [{
+                    "resource": self.resource(),
+                    "scopeMetrics": [{"scope": scope(), "metrics": metrics}],
+                }]
+            }),
+        )
+    }
+
+    /// Sends the values of `metrics` every `interval` from a background thread, and once
+    /// more when the returned pusher is dropped.
+    ///
+    /// Failed exports are skipped; the next one sends the values again.
+    pub fn push_metrics(&self, metrics: Arc<Metrics>, interval: Duration) -> MetricsPusher {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let exporter = self.clone();
+        let thread = thread::spawn(move || loop {
+            let result = stopped.recv_timeout(interval);
+            let _ = exporter.export_metrics(&metrics);
+            if result != Err(mpsc::RecvTimeoutError::Timeout) {
+                break;
+            }
+        });
+        MetricsPusher {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    fn send_spans(self, receiver: mpsc::Receiver<Message>) {
+        // Spans of the export itself would otherwise come back to the tracer.
+        let _guard = tracing::dispatcher::set_default(&Dispatch::none());
+        let mut batch = Vec::new();
+        loop {
+            match receiver.recv_timeout(Duration::from_secs(5)) {
+                Ok(Message::Span(span)) => {
+                    batch.push(span);
+                    if batch.len() >= self.batch_size {
+                        // Failed batches are dropped, so a missing collector can't fill the
+                        // memory up. Only flushing reports failures.
+                        let _ = self.export_spans(&std::mem::take(&mut batch));
+                    }
+                }
+                Ok(Message::Flush(done)) => {
+                    let _ = done.send(self.export_spans(&std::mem::take(&mut batch)));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let _ = self.export_spans(&std::mem::take(&mut batch));
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    let _ = self.export_spans(&batch);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn export_spans(&self, spans: &[FinishedSpan]) -> io::Result<()> {
+        if spans.is_empty() {
+            return Ok(());
+        }
+        let spans = spans.iter().map(FinishedSpan::to_json).collect::<Vec<_>>();
+        self.send(
+            "v1/traces",
+            json!({
+                "resourceSpans": [{
+                    "resource": self.resource(),
+                    "scopeSpans": [{"scope": scope(), "spans": spans}],
+                }]
+            }),
+        )
+    }
+
+    fn resource(&self) -> Value {
+        let attributes = self
+            .resource
+            .iter()
+            .map(|(key, value)| attribute(key, value))
+            .collect::<Vec<_>>();
+        json!({ "attributes": attributes })
+    }
+
+    fn send(&self, path: &str, body: Value) -> io::Result<()> {
+        let url = self.endpoint.join(path).map_err(io::Error::other)?;
+        let mut request = self
+            .agent
+            .post(url.as_str())
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        match request.send_bytes(&serde_json::to_vec(&body)?) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, response)) => Err(io::Error::other(format!(
+                "POST {url} failed with {status}: {}",
+                response.into_string().unwrap_or_default()
+            ))),
+            Err(error) => Err(io::Error::other(error)),
+        }
+    }
+}
+
+/// The background thread started by [`OtlpExporter::push_metrics`], stopped when dropped.
+pub struct MetricsPusher {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for MetricsPusher {
+    fn drop(&mut self) {
+        // Disconnecting wakes the thread up for its last export.
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A tracing [`Subscriber`] sending spans to an OpenTelemetry collector, created by
+/// [`OtlpExporter::tracer`].
+///
+/// Span fields become span attributes, and events logged within a span become span events
+/// named by their message. Events outside of spans aren't sent. Clones share their spans, so
+/// a clone kept after installing the tracer can [`flush`](Self::flush) it.
+#[derive(Clone)]
+pub struct OtlpTracer {
+    inner: Arc<TracerInner>,
+}
+
+struct TracerInner {
+    level: Level,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, OpenSpan>>,
+    /// The spans each thread is in, innermost last.
+    stacks: Mutex<HashMap<ThreadId, Vec<Id>>>,
+    sender: Mutex<mpsc::Sender<Message>>,
+}
+
+enum Message {
+    Span(FinishedSpan),
+    Flush(mpsc::Sender<io::Result<()>>),
+}
+
+struct OpenSpan {
+    /// Handles to the span still alive.
+    references: usize,
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_id: Option<[u8; 8]>,
+    name: &'static str,
+    start: SystemTime,
+    attributes: Vec<Value>,
+    events: Vec<Value>,
+}
+
+struct FinishedSpan {
+    span: OpenSpan,
+    end: SystemTime,
+}
+
+impl OtlpTracer {
+    /// Sends the finished spans not sent yet, waiting for the collector to accept them.
+    pub fn flush(&self) -> io::Result<()> {
+        let (done, result) = mpsc::channel();
+        let disconnected = || io::Error::other("the export thread stopped");
+        self.lock(&self.inner.sender)
+            .send(Message::Flush(done))
+            .map_err(|_| disconnected())?;
+        result.recv().map_err(|_| disconnected())?
+    }
+
+    fn current(&self) -> Option<Id> {
+        self.lock(&self.inner.stacks)
+            .get(&thread::current().id())
+            .and_then(|stack| stack.last().cloned())
+    }
+
+    fn lock<'a, T>(&self, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        // Every update leaves the spans consistent, so they're still usable after a panic.
+        mutex.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Subscriber for OtlpTracer {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.inner.level
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.inner.level))
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let parent = if attributes.is_contextual() {
+            self.current()
+        } else {
+            attributes.parent().cloned()
+        };
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut spans = self.lock(&self.inner.spans);
+        let parent = parent.and_then(|parent| spans.get(&parent.into_u64()));
+        let (trace_id, parent_id) = match parent {
+            Some(parent) => (parent.trace_id, Some(parent.span_id)),
+            None => (*Uuid::new_v4().as_bytes(), None),
+        };
+        let mut span_id = [0; 8];
+        span_id.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+        spans.insert(
+            id,
+            OpenSpan {
+                references: 1,
+                trace_id,
+                span_id,
+                parent_id,
+                name: attributes.metadata().name(),
+                start: SystemTime::now(),
+                attributes: fields.attributes,
+                events: Vec::new(),
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        if let Some(span) = self.lock(&self.inner.spans).get_mut(&span.into_u64()) {
+            span.attributes.extend(fields.attributes);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let span = if event.is_contextual() {
+            self.current()
+        } else {
+            event.parent().cloned()
+        };
+        let Some(span) = span else {
+            return;
+        };
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let name = fields
+            .message
+            .unwrap_or_else(|| event.metadata().name().to_string());
+        if let Some(span) = self.lock(&self.inner.spans).get_mut(&span.into_u64()) {
+            span.events.push(json!({
+                "timeUnixNano": unix_nanos(SystemTime::now()),
+                "name": name,
+                "attributes": fields.attributes,
+            }));
+        }
+    }
+
+    fn enter(&self, span: &Id) {
+        self.lock(&self.inner.stacks)
+            .entry(thread::current().id())
+            .or_default()
+            .push(span.clone());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut stacks = self.lock(&self.inner.stacks);
+        let thread = thread::current().id();
+        if let Some(stack) = stacks.get_mut(&thread) {
+            if let Some(index) = stack.iter().rposition(|entered| entered == span) {
+                stack.remove(index);
+            }
+            if stack.is_empty() {
+                stacks.remove(&thread);
+            }
+        }
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(open) = self.lock(&self.inner.spans).get_mut(&span.into_u64()) {
+            open.references += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.lock(&self.inner.spans);
+        let Some(open) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        open.references -= 1;
+        if open.references > 0 {
+            return false;
+        }
+        // Unwrapping is safe here because the span was just found.
+        let span = spans.remove(&span.into_u64()).unwrap();
+        drop(spans);
+        let finished = FinishedSpan {
+            span,
+            end: SystemTime::now(),
+        };
+        // Without the export thread there's nowhere left to send the span.
+        let _ = self.lock(&self.inner.sender).send(Message::Span(finished));
+        true
+    }
+}
+
+impl FinishedSpan {
+    fn to_json(&self) -> Value {
+        let span = &self.span;
+        let mut json = json!({
+            "traceId": HEXLOWER.encode(&span.trace_id),
+            "spanId": HEXLOWER.encode(&span.span_id),
+            "name": span.name,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": unix_nanos(span.start),
+            "endTimeUnixNano": unix_nanos(self.end),
+            "attributes": span.attributes,
+            "events": span.events,
+        });
+        if let Some(parent_id) = span.parent_id {
+            json["parentSpanId"] = HEXLOWER.encode(&parent_id).into();
+        }
+        json
+    }
+}
+
+/// Span and event fields as OTLP attributes, with an event's message kept apart.
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    attributes: Vec<Value>,
+}
+
+impl Fields {
+    fn push(&mut self, field: &Field, value: Value) {
+        self.attributes
+            .push(json!({"key": field.name(), "value": value}));
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let value = format!("{value:?}");
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.push(field, json!({ "stringValue": value }));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.push(field, json!({ "stringValue": value }));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, json!({ "doubleValue": value }));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, json!({ "boolValue": value }));
+    }
+}
+
+fn scope() -> Value {
+    json!({"name": "kirby", "version": env!("CARGO_PKG_VERSION")})
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({"key": key, "value": {"stringValue": value}})
+}
+
+fn int_attribute(key: &str, value: impl ToString) -> Value {
+    json!({"key": key, "value": {"intValue": value.to_string()}})
+}
+
+/// OTLP's JSON encoding takes 64-bit integers as strings.
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    use tracing::info;
+
+    use super::*;
+    use crate::trace::{url_span, Stage};
+
+    /// Answers `requests` requests with 200, returning their paths and bodies.
+    fn collector(requests: usize) -> (Url, JoinHandle<Vec<(String, Value)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let thread = thread::spawn(move || {
+            let mut received = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap().to_string();
+                let mut length = 0;
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                received.push((path, serde_json::from_slice(&body).unwrap()));
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+            }
+            received
+        });
+        (url, thread)
+    }
+
+    fn resource_attribute<'a>(body: &'a Value, resource: &str, key: &str) -> &'a Value {
+        body[resource][0]["resource"]["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|attribute| attribute["key"] == key)
+            .map(|attribute| &attribute["value"]["stringValue"])
+            .unwrap()
+    }
+
+    #[test]
+    fn exports_nested_spans_and_metrics() {
+        let (endpoint, collector) = collector(2);
+        let exporter = OtlpExporter::new(endpoint)
+            .bot("kirby-test")
+            .crawl_id("crawl-1");
+
+        let tracer = exporter.tracer();
+        tracing::subscriber::with_default(tracer.clone(), || {
+            let url = Url::parse("https://example.com/a").unwrap();
+            let fetch = url_span(Stage::Fetch, &url, 1, Some(2));
+            let _fetch = fetch.enter();
+            info!(status = 200, "fetched");
+            let _parse = url_span(Stage::Parse, &url, 1, None).entered();
+        });
+        tracer.flush().unwrap();
+
+        let metrics = Metrics::new();
+        metrics.record_fetch(200, Duration::from_millis(20), 100);
+        metrics.record_fetch(200, Duration::from_secs(30), 10);
+        exporter.export_metrics(&metrics).unwrap();
+
+        let received = collector.join().unwrap();
+        let (path, traces) = &received[0];
+        assert_eq!(path, "/v1/traces");
+        assert_eq!(
+            resource_attribute(traces, "resourceSpans", "kirby.crawl.id"),
+            "crawl-1"
+        );
+        let spans = traces["resourceSpans"][0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap();
+        // The inner span ends first.
+        let (parse, fetch) = (&spans[0], &spans[1]);
+        assert_eq!(parse["name"], "parse");
+        assert_eq!(fetch["name"], "fetch");
+        assert_eq!(parse["traceId"], fetch["traceId"]);
+        assert_eq!(parse["parentSpanId"], fetch["spanId"]);
+        assert!(fetch.get("parentSpanId").is_none());
+        assert!(fetch["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({"key": "attempt", "value": {"intValue": "2"}})));
+        assert_eq!(fetch["events"][0]["name"], "fetched");
+
+        let (path, metrics) = &received[1];
+        assert_eq!(path, "/v1/metrics");
+        assert_eq!(
+            resource_attribute(metrics, "resourceMetrics", "kirby.bot"),
+            "kirby-test"
+        );
+        let metrics = &metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["sum"]["dataPoints"][0]["asInt"], "2");
+        let histogram = &metrics[1]["histogram"]["dataPoints"][0];
+        assert_eq!(histogram["count"], "2");
+        assert_eq!(histogram["bucketCounts"][2], "1");
+        assert_eq!(histogram["bucketCounts"][LATENCY_BUCKETS.len()], "1");
+    }
+}
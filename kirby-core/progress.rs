@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::CrawlEvent;
+
+/// The window rates are measured over by default.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(300);
+
+/// How far along a crawl is, and a rough estimate of how long it has left.
+///
+/// Frontiers grow as pages are crawled, so the estimate is a range: `eta_best_secs` assumes
+/// no more URLs are discovered, `eta_secs` that they keep being discovered at the current
+/// rate. When the frontier grows faster than it's crawled, there's no end in sight and
+/// `eta_secs` is `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    /// URLs enqueued so far.
+    pub discovered: u64,
+    /// Enqueued URLs that were fetched or given up on.
+    pub completed: u64,
+    /// `completed` out of `discovered`, between 0 and 1.
+    pub fraction: f64,
+    /// URLs completed per second, over the latest window.
+    pub completion_rate: f64,
+    /// URLs discovered per second, over the latest window.
+    pub discovery_rate: f64,
+    /// Seconds left if discovery keeps its pace.
+    pub eta_secs: Option<u64>,
+    /// Seconds left if no more URLs are discovered.
+    pub eta_best_secs: Option<u64>,
+}
+
+impl Progress {
+    pub fn remaining(&self) -> u64 {
+        self.discovered.saturating_sub(self.completed)
+    }
+}
+
+/// Tracks frontier growth against completions to estimate a crawl's progress.
+///
+/// Enqueued URLs count as discovered; responses, robots.txt denials and errors that aren't
+/// retried count as completed. Rates are measured over a sliding window, so the estimate
+/// follows changes in pace.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use kirby_core::events::CrawlEvent;
+/// use kirby_core::progress::ProgressTracker;
+/// use url::Url;
+///
+/// let mut tracker = ProgressTracker::new();
+/// let start = Instant::now();
+/// let url = Url::parse("https://example.com/").unwrap();
+/// for _ in 0..100 {
+///     tracker.observe_at(&CrawlEvent::UrlEnqueued { url: url.clone(), depth: 1 }, start);
+/// }
+/// for second in 1..=10 {
+///     let finished = CrawlEvent::FetchFinished {
+///         url: url.clone(),
+///         status: 200,
+///         duration: Duration::from_millis(100),
+///         bytes: 512,
+///     };
+///     tracker.observe_at(&finished, start + Duration::from_secs(second));
+/// }
+///
+/// let progress = tracker.progress_at(start + Duration::from_secs(10));
+/// assert_eq!(progress.fraction, 0.1);
+/// assert_eq!(progress.eta_best_secs, Some(90));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    window: Duration,
+    discovered: u64,
+    completed: u64,
+    /// The counts as of the start of each second, oldest first.
+    samples: VecDeque<Sample>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    discovered: u64,
+    completed: u64,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressTracker {
+    /// Measures rates over the latest [`DEFAULT_WINDOW`].
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    pub fn with_window(window: Duration) -> Self {
+        Self {
+            window,
+            discovered: 0,
+            completed: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn observe(&mut self, event: &CrawlEvent) {
+        self.observe_at(event, Instant::now());
+    }
+
+    /// Takes an event that happened at `at` into account.
+    pub fn observe_at(&mut self, event: &CrawlEvent, at: Instant) {
+        let (discovered, completed) = match event {
+            CrawlEvent::UrlEnqueued { .. } => (1, 0),
+            CrawlEvent::FetchFinished { .. } | CrawlEvent::RobotsDenied { .. } => (0, 1),
+            CrawlEvent::Error { error } if !error.kind().is_retryable() => (0, 1),
+            _ => return,
+        };
+        // A sample per second is plenty for rates over minutes.
+        let sampled = self
+            .samples
+            .back()
+            .is_some_and(|sample| at.saturating_duration_since(sample.at) < Duration::from_secs(1));
+        if !sampled {
+            self.samples.push_back(Sample {
+                at,
+                discovered: self.discovered,
+                completed: self.completed,
+            });
+        }
+        self.discovered += discovered;
+        self.completed += completed;
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| at.saturating_duration_since(sample.at) > self.window)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress_at(Instant::now())
+    }
+
+    /// The progress as of `now`.
+    pub fn progress_at(&self, now: Instant) -> Progress {
+        let oldest = self
+            .samples
+            .iter()
+            .find(|sample| now.saturating_duration_since(sample.at) <= self.window);
+        let (completion_rate, discovery_rate) = match oldest {
+            Some(sample) if now.saturating_duration_since(sample.at) >= Duration::from_secs(1) => {
+                let seconds = now.saturating_duration_since(sample.at).as_secs_f64();
+                (
+                    (self.completed - sample.completed) as f64 / seconds,
+                    (self.discovered - sample.discovered) as f64 / seconds,
+                )
+            }
+            _ => (0.0, 0.0),
+        };
+
+        let remaining = self.discovered.saturating_sub(self.completed) as f64;
+        let eta = |rate: f64| {
+            if self.discovered == 0 {
+                None
+            } else if remaining == 0.0 {
+                Some(0)
+            } else if rate > 0.0 {
+                Some((remaining / rate).round() as u64)
+            } else {
+                None
+            }
+        };
+        Progress {
+            discovered: self.discovered,
+            completed: self.completed,
+            fraction: if self.discovered == 0 {
+                0.0
+            } else {
+                self.completed as f64 / self.discovered as f64
+            },
+            completion_rate,
+            discovery_rate,
+            eta_secs: eta(completion_rate - discovery_rate),
+            eta_best_secs: eta(completion_rate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+    use crate::error::{ErrorKind, KirbyError};
+
+    #[test]
+    fn estimates_a_range_while_the_frontier_grows() {
+        let mut tracker = ProgressTracker::with_window(Duration::from_secs(60));
+        let start = Instant::now();
+        let url = Url::parse("https://example.com/").unwrap();
+        let enqueued = CrawlEvent::UrlEnqueued {
+            url: url.clone(),
+            depth: 1,
+        };
+        let denied = CrawlEvent::RobotsDenied { url: url.clone() };
+        let retried = CrawlEvent::Error {
+            error: KirbyError::new(ErrorKind::Connect, "").with_url(url),
+        };
+        assert_eq!(tracker.progress_at(start).eta_secs, None);
+
+        // A burst long ago is outside of the window.
+        for _ in 0..1000 {
+            tracker.observe_at(&enqueued, start);
+        }
+        // Then 4 completions and 2 discoveries a second.
+        for second in 100..160 {
+            let at = start + Duration::from_secs(second);
+            for event in [
+                &denied, &denied, &denied, &denied, &enqueued, &enqueued, &retried,
+            ] {
+                tracker.observe_at(event, at);
+            }
+        }
+
+        let progress = tracker.progress_at(start + Duration::from_secs(160));
+        assert_eq!((progress.discovered, progress.completed), (1120, 240));
+        assert_eq!(progress.remaining(), 880);
+        assert_eq!(progress.completion_rate, 4.0);
+        assert_eq!(progress.discovery_rate, 2.0);
+        assert_eq!(progress.eta_best_secs, Some(220));
+        assert_eq!(progress.eta_secs, Some(440));
+    }
+}
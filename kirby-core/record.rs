@@ -0,0 +1,272 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+/// The version of the [`PageRecord`] schema written by this crate.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What was learnt about a crawled page, shared by the stores, export sinks and their readers.
+///
+/// The serialized form is stable within a schema version: fields are only added, always with
+/// a default so older records still read, and never renamed or removed. Anything else bumps
+/// [`SCHEMA_VERSION`]. Unknown fields are ignored, so older readers can read newer records of
+/// the same version. Use [`from_json`](Self::from_json) to reject records of a newer version.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::record::{DiscoverySource, PageRecord};
+/// use url::Url;
+///
+/// let seed = Url::parse("https://example.com/").unwrap();
+/// let mut record = PageRecord::new(seed.join("/about").unwrap(), 200, Utc::now());
+/// record.depth = 1;
+/// record.discovery = Some(DiscoverySource::Link { from: seed });
+///
+/// let json = serde_json::to_string(&record).unwrap();
+/// assert_eq!(PageRecord::from_json(&json).unwrap(), record);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageRecord {
+    #[serde(default = "first_version")]
+    pub schema_version: u32,
+    /// The URL that was requested.
+    pub url: Url,
+    /// The canonical URL the page declares, when it differs from `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<Url>,
+    /// The redirects followed before the final response, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<Redirect>,
+    /// The status of the final response.
+    pub status: u16,
+    pub fetched_at: DateTime<Utc>,
+    /// The response headers that were kept, in order.
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Timings::is_empty")]
+    pub timings: Timings,
+    /// The digest of the body, e.g. `sha256:..`.
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// The digest of the canonicalized content, which ignores markup-only changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_digest: Option<String>,
+    /// Output of the extractors, `null` when nothing was extracted.
+    #[serde(default)]
+    pub fields: Value,
+    /// How the URL was found, `None` for records written without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery: Option<DiscoverySource>,
+    /// How many links away from a seed the URL was found.
+    #[serde(default)]
+    pub depth: u32,
+    /// What the user attached to the seed the URL was found from, `null` when there's nothing.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub metadata: Value,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+impl PageRecord {
+    pub fn new(url: Url, status: u16, fetched_at: DateTime<Utc>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            url,
+            canonical_url: None,
+            redirects: Vec::new(),
+            status,
+            fetched_at,
+            headers: Vec::new(),
+            timings: Timings::default(),
+            digest: None,
+            content_digest: None,
+            fields: Value::Null,
+            discovery: None,
+            depth: 0,
+            metadata: Value::Null,
+        }
+    }
+
+    /// Reads a serialized record, rejecting ones written with a newer schema version.
+    pub fn from_json(json: &str) -> Result<Self, RecordError> {
+        let record = serde_json::from_str::<Self>(json).map_err(RecordError::Json)?;
+        if record.schema_version > SCHEMA_VERSION {
+            return Err(RecordError::UnsupportedVersion(record.schema_version));
+        }
+        Ok(record)
+    }
+
+    /// The first header with the given name, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Adds a field next to the extracted ones in `fields`.
+    pub fn add_field(&mut self, name: &str, value: Value) {
+        if !self.fields.is_object() {
+            self.fields = Value::Object(Map::new());
+        }
+        self.fields[name] = value;
+    }
+
+    /// The URL the final response came from, the last redirect's target if there were any.
+    pub fn final_url(&self) -> &Url {
+        self.redirects
+            .last()
+            .map(|redirect| &redirect.to)
+            .unwrap_or(&self.url)
+    }
+}
+
+/// One redirect of a fetch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redirect {
+    pub from: Url,
+    pub to: Url,
+    pub status: u16,
+}
+
+/// How long the phases of a fetch took, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_ms: Option<u64>,
+    /// Until the first byte of the response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<u64>,
+}
+
+impl Timings {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How a URL came to be crawled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DiscoverySource {
+    Seed,
+    /// A link on another page.
+    Link {
+        from: Url,
+    },
+    /// A URL listed in a sitemap.
+    Sitemap {
+        sitemap: Url,
+    },
+    /// An entry of an RSS or Atom feed.
+    Feed {
+        feed: Url,
+    },
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    Json(serde_json::Error),
+    /// The record was written with a newer schema version than this crate's.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "invalid page record: {error}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "page record schema version {version} is newer than the supported version \
+                 {SCHEMA_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            Self::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn serializes_the_version_1_schema() {
+        let date = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let mut record = PageRecord::new(url("/old"), 200, date);
+        record.redirects.push(Redirect {
+            from: url("/old"),
+            to: url("/new"),
+            status: 301,
+        });
+        record.timings.total_ms = Some(120);
+        record.discovery = Some(DiscoverySource::Sitemap {
+            sitemap: url("/sitemap.xml"),
+        });
+
+        // Changing this output breaks readers of stored records, see `SCHEMA_VERSION`.
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            json!({
+                "schema_version": 1,
+                "url": "https://example.com/old",
+                "redirects": [
+                    {"from": "https://example.com/old", "to": "https://example.com/new", "status": 301}
+                ],
+                "status": 200,
+                "fetched_at": "2026-05-01T08:00:00Z",
+                "headers": [],
+                "timings": {"total_ms": 120},
+                "digest": null,
+                "fields": null,
+                "discovery": {"type": "sitemap", "sitemap": "https://example.com/sitemap.xml"},
+                "depth": 0
+            })
+        );
+        assert_eq!(record.final_url(), &url("/new"));
+    }
+
+    #[test]
+    fn reads_minimal_and_rejects_newer_records() {
+        let record = PageRecord::from_json(
+            r#"{"url": "https://example.com/", "status": 404, "fetched_at": "2026-05-01T08:00:00Z", "future": 1}"#,
+        )
+        .unwrap();
+        assert_eq!((record.schema_version, record.status), (1, 404));
+        assert_eq!(record.discovery, None);
+
+        let newer = r#"{"schema_version": 2, "url": "https://example.com/", "status": 200, "fetched_at": "2026-05-01T08:00:00Z"}"#;
+        assert!(matches!(
+            PageRecord::from_json(newer),
+            Err(RecordError::UnsupportedVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,638 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+#[cfg(feature = "http")]
+use crate::config::RenderConfig;
+use crate::config::{CssSelector, UrlPattern};
+use crate::cookies::Cookie;
+use crate::dedup::digest::{ContentDigest, DigestAlgorithm};
+use crate::error::{ErrorKind, KirbyError};
+use crate::record::PageRecord;
+
+#[cfg(test)]
+pub(crate) mod test_browser;
+#[cfg(feature = "http")]
+pub mod webdriver;
+
+/// How tall a full-page screenshot may get, browsers can't capture much more at once.
+pub const MAX_FULL_PAGE_HEIGHT: u32 = 16_384;
+
+/// How long to wait for a page by default before giving up on it.
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long no resource may start loading for the network to count as idle.
+pub const NETWORK_IDLE: Duration = Duration::from_millis(500);
+
+/// How long to pause after scrolling by default, for the content it triggers to load.
+pub const DEFAULT_SCROLL_PAUSE: Duration = Duration::from_millis(500);
+
+/// How often a page is checked while waiting for it.
+const WAIT_POLL: Duration = Duration::from_millis(100);
+
+/// A browser session pages are loaded in, e.g. a [`WebDriver`](webdriver::WebDriver) session.
+///
+/// The methods are the few steps rendering needs, anything else is done with
+/// [`execute`](Self::execute).
+// A large error costs little next to the browser round trip it failed.
+pub trait Browser: Send {
+    /// Loads a URL, returning once the page has loaded.
+    fn navigate(&mut self, url: &Url) -> Result<(), KirbyError>;
+
+    /// The page's current DOM, serialized as HTML.
+    fn source(&mut self) -> Result<String, KirbyError>;
+
+    /// Runs `script` as the body of a function in the page, returning what it returns.
+    fn execute(&mut self, script: &str) -> Result<Value, KirbyError>;
+
+    /// How many elements match a CSS selector.
+    fn count(&mut self, selector: &str) -> Result<usize, KirbyError>;
+
+    /// Clicks every element matching a CSS selector, returning how many there were.
+    fn click(&mut self, selector: &str) -> Result<usize, KirbyError>;
+
+    /// Types `text` into the first element matching a CSS selector, replacing its value.
+    fn fill(&mut self, selector: &str, text: &str) -> Result<(), KirbyError>;
+
+    /// The cookies of the current page.
+    fn cookies(&mut self) -> Result<Vec<Cookie>, KirbyError>;
+
+    /// A PNG of what's visible in the window.
+    fn screenshot(&mut self) -> Result<Vec<u8>, KirbyError>;
+
+    /// The window's width and height.
+    fn window_size(&mut self) -> Result<(u32, u32), KirbyError>;
+
+    fn set_window_size(&mut self, width: u32, height: u32) -> Result<(), KirbyError>;
+}
+
+/// What's done to a page in the browser before it's captured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderOptions {
+    /// What to wait for once the page has loaded, before anything else is done.
+    #[serde(default)]
+    pub wait: Option<Wait>,
+    /// How long to wait at most, [`DEFAULT_WAIT_TIMEOUT`] when not set. The page fails when
+    /// it takes longer.
+    #[serde(default)]
+    pub wait_timeout_ms: Option<u64>,
+    /// Interactions run in order after the wait, e.g. to load what's behind infinite scrolling
+    /// or "show more" buttons.
+    #[serde(default)]
+    pub steps: Vec<Step>,
+    /// A JavaScript expression evaluated in the page, its result is added to the record under
+    /// `fields.script`.
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Captures a screenshot as well, written to the sinks as a record of its own.
+    #[serde(default)]
+    pub screenshot: Option<Screenshot>,
+}
+
+impl RenderOptions {
+    pub fn wait_timeout(&self) -> Duration {
+        self.wait_timeout_ms
+            .map_or(DEFAULT_WAIT_TIMEOUT, Duration::from_millis)
+    }
+}
+
+/// An interaction with a page, written as e.g. `{ action = "scroll", times = 5 }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Scrolls to the bottom of the page `times` times, pausing after each scroll,
+    /// [`DEFAULT_SCROLL_PAUSE`] when not set.
+    Scroll {
+        times: u32,
+        #[serde(default)]
+        pause_ms: Option<u64>,
+    },
+    /// Clicks every element matching the selector, none matching is fine.
+    Click { selector: CssSelector },
+    /// Pauses for a while.
+    Wait { ms: u64 },
+}
+
+/// Something a page's scripts do before it's complete.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wait {
+    /// Until an element matches the selector, e.g. `{ selector = ".results" }`.
+    Selector(CssSelector),
+    /// Until the page has loaded and no resource has started loading for [`NETWORK_IDLE`].
+    NetworkIdle,
+}
+
+/// A screenshot to capture of each page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Screenshot {
+    #[serde(default)]
+    pub area: ScreenshotArea,
+    /// JPEG and WebP need the `images` feature.
+    #[serde(default)]
+    pub format: ImageFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenshotArea {
+    /// What fits in the window.
+    #[default]
+    Viewport,
+    /// The whole page, up to [`MAX_FULL_PAGE_HEIGHT`] pixels tall.
+    FullPage,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn mime(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Png => "PNG",
+            Self::Jpeg => "JPEG",
+            Self::Webp => "WebP",
+        })
+    }
+}
+
+/// A page as the browser left it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendered {
+    /// The DOM after rendering, serialized as HTML.
+    pub html: String,
+    /// What the options' script evaluated to.
+    pub script: Option<Value>,
+    pub screenshot: Option<Capture>,
+}
+
+/// A screenshot of a page, in the format that was asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    pub screenshot: Screenshot,
+    pub image: Vec<u8>,
+}
+
+impl Capture {
+    /// A record of the screenshot for the page's `record`: the same URL with the image's
+    /// content type and digest, and the screenshot's options in `fields.screenshot`.
+    pub fn record(&self, page: &PageRecord) -> PageRecord {
+        let mut record = page.clone();
+        record.headers = vec![(
+            "Content-Type".to_string(),
+            self.screenshot.format.mime().to_string(),
+        )];
+        record.digest =
+            Some(ContentDigest::compute(DigestAlgorithm::Sha256, &self.image).to_string());
+        record.content_digest = None;
+        record.fields = json!({ "screenshot": self.screenshot });
+        record
+    }
+}
+
+/// Renders pages in a browser, so what scripts add to them is captured as well.
+///
+/// Pages are rendered one at a time in a single browser session. The options of the first
+/// profile whose pattern matches a URL are used for it, the default options for the rest.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::config::UrlPattern;
+/// use kirby_core::render::{Browser, RenderOptions, Renderer, Screenshot, ScreenshotArea};
+/// use url::Url;
+///
+/// fn render_blog(browser: impl Browser + 'static) {
+///     let full_page = RenderOptions {
+///         screenshot: Some(Screenshot {
+///             area: ScreenshotArea::FullPage,
+///             ..Screenshot::default()
+///         }),
+///         ..RenderOptions::default()
+///     };
+///     let renderer = Renderer::new(browser)
+///         .profile(&UrlPattern::parse(r"^https://example\.com/blog/").unwrap(), full_page);
+///
+///     let rendered = renderer
+///         .render(&Url::parse("https://example.com/blog/kirby").unwrap())
+///         .unwrap();
+///     println!("{} bytes of HTML", rendered.html.len());
+/// }
+/// ```
+pub struct Renderer {
+    browser: Mutex<Box<dyn Browser>>,
+    options: RenderOptions,
+    profiles: Vec<(Regex, RenderOptions)>,
+}
+
+impl Renderer {
+    /// Renders in `browser`, without screenshots.
+    pub fn new(browser: impl Browser + 'static) -> Self {
+        Self {
+            browser: Mutex::new(Box::new(browser)),
+            options: RenderOptions::default(),
+            profiles: Vec::new(),
+        }
+    }
+
+    /// Starts a session on the configuration's WebDriver server.
+    #[cfg(feature = "http")]
+    pub fn from_config(config: &RenderConfig) -> Result<Self, KirbyError> {
+        let browser = webdriver::WebDriver::connect(&config.webdriver)?;
+        let mut renderer = Self::new(browser).options(config.options.clone());
+        for profile in &config.profiles {
+            renderer = renderer.profile(&profile.pattern, profile.options.clone());
+        }
+        Ok(renderer)
+    }
+
+    /// What's done to the pages no profile matches.
+    pub fn options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Renders the URLs matching `pattern` with `options`, unless a profile added before
+    /// matches them as well.
+    pub fn profile(mut self, pattern: &UrlPattern, options: RenderOptions) -> Self {
+        self.profiles.push((pattern.regex(), options));
+        self
+    }
+
+    /// The options a URL is rendered with.
+    pub fn options_for(&self, url: &Url) -> &RenderOptions {
+        self.profiles
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(url.as_str()))
+            .map_or(&self.options, |(_, options)| options)
+    }
+
+    /// Loads a URL in the browser and captures it.
+    pub fn render(&self, url: &Url) -> Result<Rendered, KirbyError> {
+        let options = self.options_for(url);
+        let mut browser = self.browser();
+        browser.navigate(url)?;
+        if let Some(wait) = &options.wait {
+            wait_for(browser.as_mut(), wait, options.wait_timeout())?;
+        }
+        for step in &options.steps {
+            interact(browser.as_mut(), step)?;
+        }
+
+        let script = options
+            .script
+            .as_ref()
+            .map(|script| browser.execute(&format!("return ({script});")))
+            .transpose()?;
+        let html = browser.source()?;
+        let screenshot = options
+            .screenshot
+            .map(|screenshot| {
+                let image = capture(browser.as_mut(), screenshot)?;
+                Ok::<_, KirbyError>(Capture { screenshot, image })
+            })
+            .transpose()?;
+        Ok(Rendered {
+            html,
+            script,
+            screenshot,
+        })
+    }
+
+    pub(crate) fn browser(&self) -> MutexGuard<'_, Box<dyn Browser>> {
+        // A failed render leaves nothing half done that the next one would trip over.
+        self.browser
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+/// Polls the page until what `wait` asks for happened, failing after `timeout`.
+fn wait_for(browser: &mut dyn Browser, wait: &Wait, timeout: Duration) -> Result<(), KirbyError> {
+    let started = Instant::now();
+    // The number of resources the page had, and since when.
+    let mut resources = None;
+    loop {
+        let done = match wait {
+            Wait::Selector(selector) => browser.count(selector.as_str())? > 0,
+            Wait::NetworkIdle => {
+                let state = browser.execute(
+                    "return [document.readyState, performance.getEntriesByType('resource').length];",
+                )?;
+                let loaded = state[0] == "complete";
+                let count = state[1].as_u64();
+                match resources {
+                    Some((seen, since)) if loaded && seen == count => {
+                        Instant::now().duration_since(since) >= NETWORK_IDLE
+                    }
+                    _ => {
+                        resources = Some((count, Instant::now()));
+                        false
+                    }
+                }
+            }
+        };
+        if done {
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            let waited = match wait {
+                Wait::Selector(selector) => format!("`{}` to appear", selector.as_str()),
+                Wait::NetworkIdle => "the network to be idle".to_string(),
+            };
+            return Err(KirbyError::new(
+                ErrorKind::Render,
+                format!("gave up waiting for {waited} after {timeout:?}"),
+            ));
+        }
+        thread::sleep(WAIT_POLL);
+    }
+}
+
+fn interact(browser: &mut dyn Browser, step: &Step) -> Result<(), KirbyError> {
+    match step {
+        Step::Scroll { times, pause_ms } => {
+            let pause = pause_ms.map_or(DEFAULT_SCROLL_PAUSE, Duration::from_millis);
+            for _ in 0..*times {
+                browser.execute("window.scrollTo(0, document.documentElement.scrollHeight);")?;
+                thread::sleep(pause);
+            }
+        }
+        Step::Click { selector } => {
+            browser.click(selector.as_str())?;
+        }
+        Step::Wait { ms } => thread::sleep(Duration::from_millis(*ms)),
+    }
+    Ok(())
+}
+
+/// Takes a screenshot, resizing the window to fit the page for a full-page one.
+fn capture(browser: &mut dyn Browser, screenshot: Screenshot) -> Result<Vec<u8>, KirbyError> {
+    let png = match screenshot.area {
+        ScreenshotArea::Viewport => browser.screenshot()?,
+        ScreenshotArea::FullPage => {
+            let (width, height) = browser.window_size()?;
+            let page = browser.execute(
+                "const root = document.documentElement;\n\
+                 return [root.scrollWidth, root.scrollHeight];",
+            )?;
+            let size = |index: usize, fallback: u32| {
+                page[index].as_u64().map_or(fallback, |size| {
+                    size.min(u64::from(MAX_FULL_PAGE_HEIGHT)) as u32
+                })
+            };
+            browser.set_window_size(size(0, width).max(width), size(1, height).max(height))?;
+            let png = browser.screenshot();
+            browser.set_window_size(width, height)?;
+            png?
+        }
+    };
+    encode(png, screenshot.format)
+}
+
+/// Converts a PNG to `format`.
+fn encode(png: Vec<u8>, format: ImageFormat) -> Result<Vec<u8>, KirbyError> {
+    if format == ImageFormat::Png {
+        return Ok(png);
+    }
+
+    #[cfg(feature = "images")]
+    {
+        let converted = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .map(|image| image::DynamicImage::ImageRgb8(image.to_rgb8()))
+            .and_then(|image| {
+                let mut converted = Vec::new();
+                let target = match format {
+                    ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+                    _ => image::ImageFormat::WebP,
+                };
+                image.write_to(&mut std::io::Cursor::new(&mut converted), target)?;
+                Ok(converted)
+            });
+        converted.map_err(|error| {
+            KirbyError::new(
+                ErrorKind::Render,
+                format!("converting to {format}: {error}"),
+            )
+            .with_source(error)
+        })
+    }
+    #[cfg(not(feature = "images"))]
+    Err(KirbyError::new(
+        ErrorKind::Render,
+        format!("{format} screenshots need the images feature"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::test_browser::{TestBrowser, PNG};
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    fn screenshot(area: ScreenshotArea, format: ImageFormat) -> RenderOptions {
+        RenderOptions {
+            screenshot: Some(Screenshot { area, format }),
+            ..RenderOptions::default()
+        }
+    }
+
+    #[test]
+    fn captures_the_dom_and_a_full_page_screenshot() {
+        let browser = TestBrowser::new("<p>Rendered</p>").answer(json!([1024, 3000]));
+        let calls = browser.calls();
+        let renderer = Renderer::new(browser).profile(
+            &UrlPattern::parse("/blog/").unwrap(),
+            screenshot(ScreenshotArea::FullPage, ImageFormat::Png),
+        );
+
+        let rendered = renderer.render(&url("/blog/kirby")).unwrap();
+        assert_eq!(rendered.html, "<p>Rendered</p>");
+        let capture = rendered.screenshot.unwrap();
+        assert_eq!(capture.image, PNG);
+        assert_eq!(
+            calls.lock().unwrap()[3..],
+            ["window 1024x3000", "screenshot", "window 800x600"]
+        );
+
+        let record = capture.record(&PageRecord::new(url("/blog/kirby"), 200, Utc::now()));
+        assert_eq!(record.header("content-type"), Some("image/png"));
+        assert_eq!(
+            record.fields,
+            json!({"screenshot": {"area": "full_page", "format": "png"}})
+        );
+
+        assert!(renderer
+            .render(&url("/about"))
+            .unwrap()
+            .screenshot
+            .is_none());
+    }
+
+    #[test]
+    fn waits_for_a_selector_and_evaluates_the_script() {
+        let browser = TestBrowser::new("<ul class=results><li>Kirby</li></ul>").answer(json!(1));
+        let options = |selector: &str| RenderOptions {
+            wait: Some(Wait::Selector(CssSelector::parse(selector).unwrap())),
+            wait_timeout_ms: Some(300),
+            script: Some("document.querySelectorAll('li').length".to_string()),
+            ..RenderOptions::default()
+        };
+        let renderer = Renderer::new(browser)
+            .options(options(".results"))
+            .profile(&UrlPattern::parse("/slow").unwrap(), options(".missing"));
+
+        assert_eq!(renderer.render(&url("/")).unwrap().script, Some(json!(1)));
+
+        let started = Instant::now();
+        let error = renderer.render(&url("/slow")).unwrap_err();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(error.kind(), ErrorKind::Render);
+        assert_eq!(
+            error.message(),
+            "gave up waiting for `.missing` to appear after 300ms"
+        );
+    }
+
+    #[test]
+    fn waits_for_the_network_to_be_idle() {
+        let browser = TestBrowser::new("<p>Hi</p>")
+            .answer(json!(["loading", 1]))
+            .answer(json!(["complete", 4]));
+        let calls = browser.calls();
+        let renderer = Renderer::new(browser).options(RenderOptions {
+            wait: Some(Wait::NetworkIdle),
+            ..RenderOptions::default()
+        });
+
+        let started = Instant::now();
+        renderer.render(&url("/")).unwrap();
+        assert!(started.elapsed() >= NETWORK_IDLE);
+        let polls = calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| *call == "execute")
+            .count();
+        assert!(polls > 2);
+    }
+
+    #[test]
+    fn runs_the_steps_of_the_matching_profile() {
+        let browser = TestBrowser::new("<button class=more>More</button>");
+        let calls = browser.calls();
+        let feed = RenderOptions {
+            steps: vec![
+                Step::Scroll {
+                    times: 2,
+                    pause_ms: Some(0),
+                },
+                Step::Click {
+                    selector: CssSelector::parse(".more").unwrap(),
+                },
+                Step::Wait { ms: 0 },
+            ],
+            ..RenderOptions::default()
+        };
+        let renderer = Renderer::new(browser).profile(&UrlPattern::parse("/feed$").unwrap(), feed);
+
+        renderer.render(&url("/about")).unwrap();
+        renderer.render(&url("/feed")).unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "navigate https://example.com/about",
+                "source",
+                "navigate https://example.com/feed",
+                "execute",
+                "execute",
+                "click .more",
+                "source"
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_steps_from_configuration() {
+        let options = toml::from_str::<RenderOptions>(
+            r#"
+            steps = [
+                { action = "scroll", times = 3 },
+                { action = "click", selector = "button.expand" },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            options.steps[0],
+            Step::Scroll {
+                times: 3,
+                pause_ms: None
+            }
+        );
+        assert!(toml::from_str::<RenderOptions>(r#"steps = [{ action = "hover" }]"#).is_err());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn converts_screenshots() {
+        let browser = TestBrowser::new("<p>Hi</p>");
+        let renderer =
+            Renderer::new(browser).options(screenshot(ScreenshotArea::Viewport, ImageFormat::Jpeg));
+
+        let image = renderer
+            .render(&url("/"))
+            .unwrap()
+            .screenshot
+            .unwrap()
+            .image;
+        assert_eq!(
+            image::guess_format(&image).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+    }
+
+    #[cfg(not(feature = "images"))]
+    #[test]
+    fn needs_the_images_feature_to_convert_screenshots() {
+        let browser = TestBrowser::new("<p>Hi</p>");
+        let renderer =
+            Renderer::new(browser).options(screenshot(ScreenshotArea::Viewport, ImageFormat::Webp));
+
+        let error = renderer.render(&url("/")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Render);
+        assert_eq!(error.message(), "WebP screenshots need the images feature");
+    }
+}
@@ -0,0 +1,121 @@
+//! A scripted browser for testing rendering.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use scraper::{Html, Selector};
+use serde_json::Value;
+use url::Url;
+
+use super::Browser;
+use crate::cookies::Cookie;
+use crate::error::KirbyError;
+
+/// A 2×1 PNG, what every screenshot shows.
+pub(crate) const PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x7b, 0x40, 0xe8,
+    0xdd, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xcf, 0x00, 0x04,
+    0xff, 0x01, 0x07, 0x00, 0x01, 0xff, 0xe2, 0x23, 0x9e, 0x59, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45,
+    0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/// Serves the same HTML for every page, answers scripts in order, repeating the last answer,
+/// and logs what it's asked to do, e.g. `navigate https://example.com/` or `window 800x600`.
+pub(crate) struct TestBrowser {
+    html: String,
+    answers: VecDeque<Value>,
+    window: (u32, u32),
+    cookies: Vec<Cookie>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestBrowser {
+    /// An 800×600 window showing `html`.
+    pub fn new(html: &str) -> Self {
+        Self {
+            html: html.to_string(),
+            answers: VecDeque::new(),
+            window: (800, 600),
+            cookies: Vec::new(),
+            calls: Arc::default(),
+        }
+    }
+
+    /// Answers the next script without an answer yet with `value`, scripts get `null` until
+    /// there's an answer.
+    pub fn answer(mut self, value: Value) -> Self {
+        self.answers.push_back(value);
+        self
+    }
+
+    /// Has the cookie set from the start.
+    #[cfg(feature = "http")]
+    pub fn cookie(mut self, cookie: Cookie) -> Self {
+        self.cookies.push(cookie);
+        self
+    }
+
+    /// The log of calls, which fills up as the browser is used.
+    pub fn calls(&self) -> Arc<Mutex<Vec<String>>> {
+        Arc::clone(&self.calls)
+    }
+
+    fn log(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl Browser for TestBrowser {
+    fn navigate(&mut self, url: &Url) -> Result<(), KirbyError> {
+        self.log(format!("navigate {url}"));
+        Ok(())
+    }
+
+    fn source(&mut self) -> Result<String, KirbyError> {
+        self.log("source".to_string());
+        Ok(self.html.clone())
+    }
+
+    fn execute(&mut self, _script: &str) -> Result<Value, KirbyError> {
+        self.log("execute".to_string());
+        if self.answers.len() > 1 {
+            return Ok(self.answers.pop_front().unwrap_or_default());
+        }
+        Ok(self.answers.front().cloned().unwrap_or_default())
+    }
+
+    fn count(&mut self, selector: &str) -> Result<usize, KirbyError> {
+        let selector = Selector::parse(selector).unwrap();
+        Ok(Html::parse_document(&self.html).select(&selector).count())
+    }
+
+    fn click(&mut self, selector: &str) -> Result<usize, KirbyError> {
+        self.log(format!("click {selector}"));
+        self.count(selector)
+    }
+
+    fn fill(&mut self, selector: &str, text: &str) -> Result<(), KirbyError> {
+        self.log(format!("fill {selector} {text}"));
+        Ok(())
+    }
+
+    fn cookies(&mut self) -> Result<Vec<Cookie>, KirbyError> {
+        Ok(self.cookies.clone())
+    }
+
+    fn screenshot(&mut self) -> Result<Vec<u8>, KirbyError> {
+        self.log("screenshot".to_string());
+        Ok(PNG.to_vec())
+    }
+
+    fn window_size(&mut self) -> Result<(u32, u32), KirbyError> {
+        Ok(self.window)
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32) -> Result<(), KirbyError> {
+        self.log(format!("window {width}x{height}"));
+        self.window = (width, height);
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::time::Duration;
+
+use data_encoding::BASE64;
+use serde_json::{json, Value};
+use url::Url;
+
+use super::Browser;
+use crate::cookies::Cookie;
+use crate::error::{ErrorKind, KirbyError};
+
+/// How long a WebDriver command may take, page loads included.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The key element IDs are found under in WebDriver's answers.
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// A headless browser session on a [W3C WebDriver](https://www.w3.org/TR/webdriver2/) server,
+/// such as chromedriver or geckodriver.
+///
+/// The session is started with [`connect`](Self::connect) and ended when the value is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::render::webdriver::WebDriver;
+/// use kirby_core::render::Browser;
+/// use url::Url;
+///
+/// let mut browser = WebDriver::connect(&Url::parse("http://localhost:9515/").unwrap()).unwrap();
+/// browser.navigate(&Url::parse("https://example.com/").unwrap()).unwrap();
+/// let title = browser.execute("return document.title;").unwrap();
+/// ```
+pub struct WebDriver {
+    agent: ureq::Agent,
+    /// The session's URL, e.g. `http://localhost:9515/session/<id>/`.
+    session: Url,
+}
+
+impl WebDriver {
+    /// Starts a headless Chrome or Firefox session on the server at `server`, e.g.
+    /// chromedriver's `http://localhost:9515/`.
+    pub fn connect(server: &Url) -> Result<Self, KirbyError> {
+        let mut server = server.clone();
+        if !server.path().ends_with('/') {
+            server.set_path(&format!("{}/", server.path()));
+        }
+        let agent = ureq::AgentBuilder::new().timeout(DEFAULT_TIMEOUT).build();
+
+        let capabilities = json!({"capabilities": {"alwaysMatch": {
+            "goog:chromeOptions": {"args": ["--headless=new", "--disable-gpu"]},
+            "moz:firefoxOptions": {"args": ["-headless"]},
+        }}});
+        let session = command(
+            &agent,
+            "POST",
+            &join(&server, "session")?,
+            Some(capabilities),
+        )?;
+        let id = session["sessionId"]
+            .as_str()
+            .ok_or_else(|| KirbyError::new(ErrorKind::Parse, "the new session has no ID"))?;
+        Ok(Self {
+            session: join(&server, &format!("session/{id}/"))?,
+            agent,
+        })
+    }
+
+    /// The IDs of the elements matching a CSS selector.
+    fn elements(&self, selector: &str) -> Result<Vec<String>, KirbyError> {
+        let elements = self.command(
+            "POST",
+            "elements",
+            Some(json!({"using": "css selector", "value": selector})),
+        )?;
+        Ok(elements
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|element| element[ELEMENT_KEY].as_str())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Sends a command to the session, `path` is relative to it.
+    fn command(&self, method: &str, path: &str, body: Option<Value>) -> Result<Value, KirbyError> {
+        command(&self.agent, method, &join(&self.session, path)?, body)
+    }
+}
+
+impl Browser for WebDriver {
+    fn navigate(&mut self, url: &Url) -> Result<(), KirbyError> {
+        self.command("POST", "url", Some(json!({"url": url})))
+            .map_err(|error| error.with_url(url.clone()))?;
+        Ok(())
+    }
+
+    fn source(&mut self) -> Result<String, KirbyError> {
+        match self.command("GET", "source", None)? {
+            Value::String(html) => Ok(html),
+            _ => Err(KirbyError::new(
+                ErrorKind::Parse,
+                "the page source isn't text",
+            )),
+        }
+    }
+
+    fn execute(&mut self, script: &str) -> Result<Value, KirbyError> {
+        self.command(
+            "POST",
+            "execute/sync",
+            Some(json!({"script": script, "args": []})),
+        )
+    }
+
+    fn count(&mut self, selector: &str) -> Result<usize, KirbyError> {
+        Ok(self.elements(selector)?.len())
+    }
+
+    fn click(&mut self, selector: &str) -> Result<usize, KirbyError> {
+        let elements = self.elements(selector)?;
+        for element in &elements {
+            self.command("POST", &format!("element/{element}/click"), Some(json!({})))?;
+        }
+        Ok(elements.len())
+    }
+
+    fn fill(&mut self, selector: &str, text: &str) -> Result<(), KirbyError> {
+        let element = self.elements(selector)?.into_iter().next().ok_or_else(|| {
+            KirbyError::new(
+                ErrorKind::Render,
+                format!("no element matches `{selector}`"),
+            )
+        })?;
+        self.command("POST", &format!("element/{element}/clear"), Some(json!({})))?;
+        self.command(
+            "POST",
+            &format!("element/{element}/value"),
+            Some(json!({"text": text})),
+        )?;
+        Ok(())
+    }
+
+    fn cookies(&mut self) -> Result<Vec<Cookie>, KirbyError> {
+        let cookies = self.command("GET", "cookie", None)?;
+        Ok(cookies
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|cookie| {
+                // Cookies shared with subdomains have their domain written as `.example.com`.
+                let domain = cookie["domain"].as_str()?;
+                Some(Cookie {
+                    name: cookie["name"].as_str()?.to_string(),
+                    value: cookie["value"].as_str()?.to_string(),
+                    domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+                    host_only: !domain.starts_with('.'),
+                    path: cookie["path"].as_str().unwrap_or("/").to_string(),
+                    secure: cookie["secure"].as_bool().unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    fn screenshot(&mut self) -> Result<Vec<u8>, KirbyError> {
+        let encoded = self.command("GET", "screenshot", None)?;
+        encoded
+            .as_str()
+            .and_then(|encoded| BASE64.decode(encoded.as_bytes()).ok())
+            .ok_or_else(|| KirbyError::new(ErrorKind::Parse, "the screenshot isn't base64"))
+    }
+
+    fn window_size(&mut self) -> Result<(u32, u32), KirbyError> {
+        let rect = self.command("GET", "window/rect", None)?;
+        match (rect["width"].as_u64(), rect["height"].as_u64()) {
+            (Some(width), Some(height)) => Ok((width as u32, height as u32)),
+            _ => Err(KirbyError::new(ErrorKind::Parse, "the window has no size")),
+        }
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32) -> Result<(), KirbyError> {
+        self.command(
+            "POST",
+            "window/rect",
+            Some(json!({"width": width, "height": height})),
+        )?;
+        Ok(())
+    }
+}
+
+impl Drop for WebDriver {
+    fn drop(&mut self) {
+        // The server ends abandoned sessions itself eventually, so failing here is harmless.
+        let _ = command(&self.agent, "DELETE", &self.session, None);
+    }
+}
+
+fn join(base: &Url, path: &str) -> Result<Url, KirbyError> {
+    base.join(path).map_err(|error| {
+        KirbyError::new(ErrorKind::Parse, format!("{base}{path}: {error}")).with_source(error)
+    })
+}
+
+/// Sends a command and returns the `value` of its answer. WebDriver errors are
+/// [`ErrorKind::Render`] errors with the driver's message.
+fn command(
+    agent: &ureq::Agent,
+    method: &str,
+    url: &Url,
+    body: Option<Value>,
+) -> Result<Value, KirbyError> {
+    let request = agent.request(method, url.as_str());
+    let sent = match body {
+        Some(body) => request
+            .set("Content-Type", "application/json")
+            .send_bytes(body.to_string().as_bytes()),
+        None => request.call(),
+    };
+    let response = match sent {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(transport)) => {
+            return Err(
+                KirbyError::new(ErrorKind::Connect, transport.to_string()).with_source(transport)
+            );
+        }
+    };
+    let status = response.status();
+    let mut answer = response
+        .into_string()
+        .map_err(|error| KirbyError::new(ErrorKind::Connect, error.to_string()).with_source(error))
+        .and_then(|text| {
+            serde_json::from_str::<Value>(&text).map_err(|error| {
+                KirbyError::new(ErrorKind::Parse, format!("{method} {url}: {error}"))
+                    .with_source(error)
+            })
+        })?;
+    let value = answer["value"].take();
+    if status >= 400 {
+        let error = value["error"].as_str().unwrap_or("unknown error");
+        let message = value["message"].as_str().unwrap_or_default();
+        return Err(KirbyError::new(
+            ErrorKind::Render,
+            format!("{method} {url} failed with {error}: {message}"),
+        ));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+    use crate::render::test_browser::PNG;
+
+    #[test]
+    fn drives_a_session() {
+        let null = || Page::json(r#"{"value": null}"#);
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/session",
+                    Page::json(r#"{"value": {"sessionId": "s1", "capabilities": {}}}"#),
+                )
+                .page("/session/s1/url", null())
+                .page(
+                    "/session/s1/source",
+                    Page::json(r#"{"value": "<html><p>Rendered</p></html>"}"#),
+                )
+                .page(
+                    "/session/s1/screenshot",
+                    Page::json(
+                        r#"{"value": "iVBORw0KGgoAAAANSUhEUgAAAAIAAAABCAIAAAB7QOjdAAAADUlEQVR4nGP4zwAE/wEHAAH/4iOeWQAAAABJRU5ErkJggg=="}"#,
+                    ),
+                )
+                .page(
+                    "/session/s1/elements",
+                    Page::json(r#"{"value": [{"element-6066-11e4-a52e-4f735466cecf": "e1"}]}"#),
+                )
+                .page("/session/s1/element/e1/click", null())
+                .page(
+                    "/session/s1/execute/sync",
+                    Page::new(
+                        404,
+                        r#"{"value": {"error": "javascript error", "message": "boom"}}"#,
+                    )
+                    .header("Content-Type", "application/json"),
+                )
+                .page("/session/s1/", null()),
+        );
+        let mut browser = WebDriver::connect(server.url()).unwrap();
+        browser
+            .navigate(&Url::parse("https://example.com/").unwrap())
+            .unwrap();
+        assert_eq!(browser.source().unwrap(), "<html><p>Rendered</p></html>");
+        assert_eq!(browser.screenshot().unwrap(), PNG);
+        assert_eq!(browser.click(".more").unwrap(), 1);
+
+        let error = browser.execute("throw new Error('boom');").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Render);
+        assert!(error.message().ends_with("javascript error: boom"));
+        drop(browser);
+
+        let requests = server.requests();
+        let calls = requests
+            .iter()
+            .map(|request| format!("{} {}", request.method, request.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            [
+                "POST /session",
+                "POST /session/s1/url",
+                "GET /session/s1/source",
+                "GET /session/s1/screenshot",
+                "POST /session/s1/elements",
+                "POST /session/s1/element/e1/click",
+                "POST /session/s1/execute/sync",
+                "DELETE /session/s1/",
+            ]
+        );
+        let navigate = serde_json::from_slice::<Value>(&requests[1].body).unwrap();
+        assert_eq!(navigate, json!({"url": "https://example.com/"}));
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ErrorKind;
+use crate::events::CrawlEvent;
+use crate::hoststats::{HostHealth, HostStats};
+use crate::record::PageRecord;
+
+/// How many of the most common error types a report lists.
+pub const TOP_ERRORS: usize = 10;
+
+/// What a crawl did, produced at its end by a [`ReportBuilder`].
+///
+/// It serializes to JSON and its `Display` renders it as text for people.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Fetches that got a response.
+    pub responses: u64,
+    /// Responses by status class, e.g. `2xx`.
+    pub status_classes: BTreeMap<String, u64>,
+    pub bytes: u64,
+    /// The sum of every fetch's duration, in milliseconds.
+    pub fetch_ms: u64,
+    pub robots_denials: u64,
+    pub errors: u64,
+    /// The most common error types, most common first.
+    pub top_errors: Vec<ErrorCount>,
+    pub hosts: BTreeMap<String, HostReport>,
+    pub budgets: BTreeMap<String, BudgetUsage>,
+    /// Pages with a digest, whether or not it was seen before.
+    pub digested_pages: u64,
+    /// Pages whose digest was seen on an earlier page.
+    pub duplicates: u64,
+}
+
+impl CrawlReport {
+    pub fn duration(&self) -> chrono::Duration {
+        self.finished_at - self.started_at
+    }
+
+    /// The share of pages with a digest that were duplicates, between 0 and 1.
+    pub fn duplicate_rate(&self) -> f64 {
+        if self.digested_pages == 0 {
+            0.0
+        } else {
+            self.duplicates as f64 / self.digested_pages as f64
+        }
+    }
+}
+
+/// An error type and how often it occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorCount {
+    /// The error's kind, e.g. `connection failed` or `HTTP 503`.
+    pub kind: String,
+    pub count: u64,
+}
+
+/// What a crawl did on one host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostReport {
+    pub responses: u64,
+    pub status_classes: BTreeMap<String, u64>,
+    pub bytes: u64,
+    pub fetch_ms: u64,
+    pub robots_denials: u64,
+    pub errors: u64,
+    pub pauses: u64,
+    /// The host's latest statistics, see [`ReportBuilder::host_stats`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HostHealth>,
+}
+
+impl HostReport {
+    /// The mean duration of the host's fetches in milliseconds, 0 without fetches.
+    pub fn mean_fetch_ms(&self) -> u64 {
+        self.fetch_ms
+            .checked_div(self.responses)
+            .unwrap_or_default()
+    }
+}
+
+/// How much of a budget a crawl used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub used: u64,
+    /// `None` for budgets without a limit.
+    pub limit: Option<u64>,
+    pub exhausted: bool,
+}
+
+/// Collects a [`CrawlReport`] from the events and pages of a crawl.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use chrono::{TimeZone, Utc};
+/// use kirby_core::error::{ErrorKind, KirbyError};
+/// use kirby_core::events::CrawlEvent;
+/// use kirby_core::report::ReportBuilder;
+/// use url::Url;
+///
+/// let start = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+/// let mut builder = ReportBuilder::new(start);
+/// builder.observe(&CrawlEvent::FetchFinished {
+///     url: Url::parse("https://example.com/").unwrap(),
+///     status: 200,
+///     duration: Duration::from_millis(120),
+///     bytes: 2048,
+/// });
+/// let refused = KirbyError::new(ErrorKind::Connect, "connection refused")
+///     .with_url(Url::parse("https://example.com/down").unwrap());
+/// builder.observe(&CrawlEvent::Error { error: refused });
+/// builder.budget("pages", 1, Some(1000));
+///
+/// let report = builder.finish(start + chrono::Duration::minutes(5));
+/// assert_eq!(report.status_classes["2xx"], 1);
+/// assert_eq!(report.top_errors[0].kind, "connection failed");
+/// assert_eq!(report.hosts["example.com"].errors, 1);
+/// assert!(report.to_string().starts_with("Crawl took 5m 0s"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReportBuilder {
+    report: CrawlReport,
+    error_kinds: HashMap<ErrorKind, u64>,
+    digests: HashSet<String>,
+}
+
+impl ReportBuilder {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        Self {
+            report: CrawlReport {
+                started_at,
+                finished_at: started_at,
+                responses: 0,
+                status_classes: BTreeMap::new(),
+                bytes: 0,
+                fetch_ms: 0,
+                robots_denials: 0,
+                errors: 0,
+                top_errors: Vec::new(),
+                hosts: BTreeMap::new(),
+                budgets: BTreeMap::new(),
+                digested_pages: 0,
+                duplicates: 0,
+            },
+            error_kinds: HashMap::new(),
+            digests: HashSet::new(),
+        }
+    }
+
+    pub fn observe(&mut self, event: &CrawlEvent) {
+        let report = &mut self.report;
+        let host = event
+            .url()
+            .and_then(|url| url.host_str())
+            .map(str::to_string);
+        match event {
+            CrawlEvent::FetchFinished {
+                status,
+                duration,
+                bytes,
+                ..
+            } => {
+                let class = format!("{}xx", status / 100);
+                let fetch_ms = duration.as_millis() as u64;
+                report.responses += 1;
+                *report.status_classes.entry(class.clone()).or_default() += 1;
+                report.bytes += bytes;
+                report.fetch_ms += fetch_ms;
+                if let Some(host) = host {
+                    let host = report.hosts.entry(host).or_default();
+                    host.responses += 1;
+                    *host.status_classes.entry(class).or_default() += 1;
+                    host.bytes += bytes;
+                    host.fetch_ms += fetch_ms;
+                }
+            }
+            CrawlEvent::RobotsDenied { .. } => {
+                report.robots_denials += 1;
+                if let Some(host) = host {
+                    report.hosts.entry(host).or_default().robots_denials += 1;
+                }
+            }
+            CrawlEvent::HostPaused { host, .. } => {
+                report.hosts.entry(host.clone()).or_default().pauses += 1;
+            }
+            CrawlEvent::BudgetExhausted { budget } => {
+                report
+                    .budgets
+                    .entry(budget.clone())
+                    .or_insert(BudgetUsage {
+                        used: 0,
+                        limit: None,
+                        exhausted: false,
+                    })
+                    .exhausted = true;
+            }
+            CrawlEvent::Error { error } => {
+                report.errors += 1;
+                if let Some(host) = error.host() {
+                    report.hosts.entry(host.to_string()).or_default().errors += 1;
+                }
+                *self.error_kinds.entry(error.kind()).or_default() += 1;
+            }
+            CrawlEvent::UrlEnqueued { .. }
+            | CrawlEvent::FetchStarted { .. }
+            | CrawlEvent::Alert { .. } => {}
+        }
+    }
+
+    /// Counts a page towards the duplicate rate by its digest.
+    pub fn page(&mut self, record: &PageRecord) {
+        if let Some(digest) = &record.digest {
+            self.report.digested_pages += 1;
+            if !self.digests.insert(digest.clone()) {
+                self.report.duplicates += 1;
+            }
+        }
+    }
+
+    /// Adds each host's latest statistics to its report.
+    pub fn host_stats(&mut self, stats: &HostStats) {
+        for (host, health) in stats.hosts() {
+            self.report
+                .hosts
+                .entry(host.to_string())
+                .or_default()
+                .health = Some(health);
+        }
+    }
+
+    /// Sets how much of a budget was used, keeping whether it was exhausted.
+    pub fn budget(&mut self, name: &str, used: u64, limit: Option<u64>) {
+        let usage = self
+            .report
+            .budgets
+            .entry(name.to_string())
+            .or_insert(BudgetUsage {
+                used,
+                limit,
+                exhausted: false,
+            });
+        usage.used = used;
+        usage.limit = limit;
+        usage.exhausted |= limit.is_some_and(|limit| used >= limit);
+    }
+
+    pub fn finish(mut self, finished_at: DateTime<Utc>) -> CrawlReport {
+        let mut errors = self
+            .error_kinds
+            .into_iter()
+            .map(|(kind, count)| ErrorCount {
+                kind: kind.to_string(),
+                count,
+            })
+            .collect::<Vec<_>>();
+        errors.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.kind.cmp(&b.kind)));
+        errors.truncate(TOP_ERRORS);
+
+        self.report.top_errors = errors;
+        self.report.finished_at = finished_at;
+        self.report
+    }
+}
+
+impl fmt::Display for CrawlReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.duration().num_seconds().max(0);
+        let duration = match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+            (0, 0, s) => format!("{s}s"),
+            (0, m, s) => format!("{m}m {s}s"),
+            (h, m, s) => format!("{h}h {m}m {s}s"),
+        };
+        writeln!(
+            f,
+            "Crawl took {duration}, from {} to {}",
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.finished_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )?;
+        writeln!(
+            f,
+            "Responses: {}{}, {}",
+            self.responses,
+            classes(&self.status_classes),
+            bytes(self.bytes)
+        )?;
+        writeln!(
+            f,
+            "Duplicates: {} of {} ({:.1}%)",
+            self.duplicates,
+            self.digested_pages,
+            self.duplicate_rate() * 100.0
+        )?;
+        writeln!(f, "Robots.txt denials: {}", self.robots_denials)?;
+        writeln!(f, "Errors: {}", self.errors)?;
+        for error in &self.top_errors {
+            writeln!(f, "  {:>4}  {}", error.count, error.kind)?;
+        }
+
+        if !self.budgets.is_empty() {
+            writeln!(f, "Budgets:")?;
+            for (name, usage) in &self.budgets {
+                let limit = usage
+                    .limit
+                    .map_or_else(String::new, |limit| format!("/{limit}"));
+                let exhausted = if usage.exhausted { " (exhausted)" } else { "" };
+                writeln!(f, "  {name}: {}{limit}{exhausted}", usage.used)?;
+            }
+        }
+
+        if !self.hosts.is_empty() {
+            writeln!(f, "Hosts:")?;
+            for (name, host) in &self.hosts {
+                writeln!(
+                    f,
+                    "  {name}: {} responses{}, {}, {} ms mean, {} errors, {} denials, {} pauses",
+                    host.responses,
+                    classes(&host.status_classes),
+                    bytes(host.bytes),
+                    host.mean_fetch_ms(),
+                    host.errors,
+                    host.robots_denials,
+                    host.pauses
+                )?;
+                if let Some(health) = &host.health {
+                    writeln!(
+                        f,
+                        "    latest {}: {:.1}% ok, {:.1}% blocked, {} ms p50, {} ms p95",
+                        health.fetches,
+                        health.success_rate * 100.0,
+                        health.block_rate * 100.0,
+                        health.p50_ms,
+                        health.p95_ms
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// E.g. ` (2xx 10, 4xx 1)`, empty without responses.
+fn classes(classes: &BTreeMap<String, u64>) -> String {
+    if classes.is_empty() {
+        return String::new();
+    }
+    let counts = classes
+        .iter()
+        .map(|(class, count)| format!("{class} {count}"))
+        .collect::<Vec<_>>();
+    format!(" ({})", counts.join(", "))
+}
+
+/// E.g. `1.5 MiB`.
+fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::TimeZone;
+    use url::Url;
+
+    use super::*;
+    use crate::error::{KirbyError, Phase};
+
+    #[test]
+    fn summarizes_events_and_pages() {
+        let start = Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+        let url = |s: &str| Url::parse(s).unwrap();
+        let mut builder = ReportBuilder::new(start);
+        for (page, status, ms) in [
+            ("https://a.example/", 200, 100),
+            ("https://a.example/x", 404, 300),
+        ] {
+            builder.observe(&CrawlEvent::FetchFinished {
+                url: url(page),
+                status,
+                duration: Duration::from_millis(ms),
+                bytes: 1536,
+            });
+            let mut record = PageRecord::new(url(page), status, start);
+            record.digest = Some("sha256:same".to_string());
+            builder.page(&record);
+        }
+        let mut stats = HostStats::new();
+        stats.record_response("a.example", 200, Duration::from_millis(100), 1536);
+        stats.record_response("a.example", 429, Duration::from_millis(300), 0);
+        builder.host_stats(&stats);
+        let timeout = ErrorKind::Timeout { phase: Phase::Body };
+        for kind in [timeout, ErrorKind::Tls, timeout] {
+            let error = KirbyError::new(kind, "").with_url(url("https://b.example/"));
+            builder.observe(&CrawlEvent::Error { error });
+        }
+        builder.observe(&CrawlEvent::BudgetExhausted {
+            budget: "bytes".to_string(),
+        });
+        builder.budget("bytes", 3072, Some(4096));
+        builder.budget("pages", 2, Some(2));
+
+        let report = builder.finish(start + chrono::Duration::seconds(3723));
+        assert_eq!(report.duplicate_rate(), 0.5);
+        assert_eq!(report.hosts["a.example"].mean_fetch_ms(), 200);
+        assert_eq!(
+            report.top_errors,
+            [
+                ErrorCount {
+                    kind: "timed out reading the body".to_string(),
+                    count: 2
+                },
+                ErrorCount {
+                    kind: "TLS handshake failed".to_string(),
+                    count: 1
+                },
+            ]
+        );
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<CrawlReport>(&json).unwrap(), report);
+        assert_eq!(
+            report.to_string(),
+            "Crawl took 1h 2m 3s, from 2026-05-01 08:00:00 UTC to 2026-05-01 09:02:03 UTC\n\
+             Responses: 2 (2xx 1, 4xx 1), 3.0 KiB\n\
+             Duplicates: 1 of 2 (50.0%)\n\
+             Robots.txt denials: 0\n\
+             Errors: 3\n     \
+             2  timed out reading the body\n     \
+             1  TLS handshake failed\n\
+             Budgets:\n  \
+             bytes: 3072/4096 (exhausted)\n  \
+             pages: 2/2 (exhausted)\n\
+             Hosts:\n  \
+             a.example: 2 responses (2xx 1, 4xx 1), 3.0 KiB, 200 ms mean, 0 errors, 0 denials, 0 pauses\n    \
+             latest 2: 50.0% ok, 50.0% blocked, 100 ms p50, 300 ms p95\n  \
+             b.example: 0 responses, 0 B, 0 ms mean, 3 errors, 0 denials, 0 pauses\n"
+        );
+    }
+}
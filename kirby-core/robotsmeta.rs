@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+
+/// Page level robots directives from `<meta name="robots">` tags, bot specific meta tags such as
+/// `<meta name="kirbybot">` and `X-Robots-Tag` headers.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RobotsMeta {
+    /// Directives that apply to every bot.
+    generic: RobotsDirectives,
+    /// Mapping of lowercase bot name -> directives targeting only that bot.
+    bots: HashMap<String, RobotsDirectives>,
+}
+
+/// The directives that apply to a page, unknown directives are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct RobotsDirectives {
+    pub noindex: bool,
+    pub nofollow: bool,
+    pub noarchive: bool,
+    pub nosnippet: bool,
+    pub noimageindex: bool,
+    pub notranslate: bool,
+    /// Maximum snippet length in characters, `-1` means no limit.
+    pub max_snippet: Option<i64>,
+    /// One of `none`, `standard` or `large`.
+    pub max_image_preview: Option<String>,
+    /// Maximum video preview length in seconds, `-1` means no limit.
+    pub max_video_preview: Option<i64>,
+    /// The date after which the page should no longer be shown, as written in the directive.
+    pub unavailable_after: Option<String>,
+}
+
+impl RobotsMeta {
+    /// Parses the robots meta tags in an HTML document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let html = r#"
+    /// <meta name="robots" content="noindex, nofollow">
+    /// <meta name="KirbyBot" content="nofollow">
+    /// "#;
+    ///
+    /// let meta = kirby_core::robotsmeta::RobotsMeta::extract(html);
+    /// assert!(meta.directives_for("GoogleBot").noindex);
+    ///
+    /// // The KirbyBot specific tag overrides the generic one.
+    /// let kirby = meta.directives_for("kirbybot");
+    /// assert!(!kirby.noindex);
+    /// assert!(kirby.nofollow);
+    /// ```
+    pub fn extract(html: &str) -> Self {
+        let document = Html::parse_document(html);
+        Self::from_element(document.root_element())
+    }
+
+    /// Parses the robots meta tags within an element.
+    ///
+    /// Any meta tag whose content is made up entirely of robots directives is treated as
+    /// targeting the bot it is named after, other meta tags (`description`, `viewport`, ..) are
+    /// ignored.
+    pub fn from_element(element: ElementRef) -> Self {
+        let mut meta = Self::default();
+
+        // Unwrapping is safe here because the selector is a valid constant.
+        let selector = Selector::parse("meta[name][content]").unwrap();
+        for tag in element.select(&selector) {
+            let name = tag.attr("name").unwrap_or_default().trim().to_lowercase();
+            let content = tag.attr("content").unwrap_or_default();
+
+            if name == "robots" {
+                meta.generic.apply(content);
+            } else if let Some(directives) = parse_strict(content) {
+                meta.bots.entry(name).or_default().merge(&directives);
+            }
+        }
+
+        meta
+    }
+
+    /// Adds the directives of an `X-Robots-Tag` header value, which are either generic
+    /// (`noindex, nofollow`) or prefixed with the bot they target (`kirbybot: noindex`).
+    pub fn add_header(&mut self, value: &str) {
+        if let Some((bot, rest)) = value.split_once(':') {
+            let bot = bot.trim();
+            if !bot.is_empty() && !is_directive_name(bot) && !bot.contains(',') {
+                self.bots.entry(bot.to_lowercase()).or_default().apply(rest);
+                return;
+            }
+        }
+
+        self.generic.apply(value);
+    }
+
+    /// The directives that apply to every bot.
+    pub fn generic(&self) -> &RobotsDirectives {
+        &self.generic
+    }
+
+    /// Resolves the effective directives for a bot, identified by its product token (the part of
+    /// its user-agent before the `/`, matched case-insensitively).
+    ///
+    /// Bot specific directives override the generic robots directives entirely, when there are
+    /// none the generic directives apply.
+    pub fn directives_for(&self, bot: &str) -> &RobotsDirectives {
+        let token = bot.split('/').next().unwrap_or(bot).trim().to_lowercase();
+        self.bots.get(&token).unwrap_or(&self.generic)
+    }
+}
+
+impl RobotsDirectives {
+    /// Parses a comma separated list of directives, e.g. `noindex, max-snippet:20`.
+    pub fn parse(content: &str) -> Self {
+        let mut directives = Self::default();
+        directives.apply(content);
+        directives
+    }
+
+    /// Adds directives to this set, more restrictive values take precedence.
+    fn apply(&mut self, content: &str) {
+        for directive in content.split(',') {
+            self.apply_one(directive);
+        }
+    }
+
+    /// Applies a single directive, returning false when it isn't recognized.
+    fn apply_one(&mut self, directive: &str) -> bool {
+        let directive = directive.trim();
+        let (name, value) = match directive.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (directive, None),
+        };
+
+        match (name.to_ascii_lowercase().as_str(), value) {
+            ("all" | "index" | "follow", None) => {}
+            ("none", None) => {
+                self.noindex = true;
+                self.nofollow = true;
+            }
+            ("noindex", None) => self.noindex = true,
+            ("nofollow", None) => self.nofollow = true,
+            ("noarchive" | "nocache", None) => self.noarchive = true,
+            ("nosnippet", None) => self.nosnippet = true,
+            ("noimageindex", None) => self.noimageindex = true,
+            ("notranslate", None) => self.notranslate = true,
+            ("max-snippet", Some(value)) => match value.parse::<i64>() {
+                Ok(value) => self.max_snippet = Some(min_limit(self.max_snippet, value)),
+                Err(_) => return false,
+            },
+            ("max-video-preview", Some(value)) => match value.parse::<i64>() {
+                Ok(value) => {
+                    self.max_video_preview = Some(min_limit(self.max_video_preview, value))
+                }
+                Err(_) => return false,
+            },
+            ("max-image-preview", Some(value)) => {
+                let value = value.to_ascii_lowercase();
+                if !["none", "standard", "large"].contains(&value.as_str()) {
+                    return false;
+                }
+                self.max_image_preview = Some(value);
+            }
+            ("unavailable_after", Some(value)) if !value.is_empty() => {
+                self.unavailable_after = Some(value.to_string())
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Combines two sets of directives, keeping the most restrictive of each.
+    fn merge(&mut self, other: &RobotsDirectives) {
+        self.noindex |= other.noindex;
+        self.nofollow |= other.nofollow;
+        self.noarchive |= other.noarchive;
+        self.nosnippet |= other.nosnippet;
+        self.noimageindex |= other.noimageindex;
+        self.notranslate |= other.notranslate;
+        if let Some(value) = other.max_snippet {
+            self.max_snippet = Some(min_limit(self.max_snippet, value));
+        }
+        if let Some(value) = other.max_video_preview {
+            self.max_video_preview = Some(min_limit(self.max_video_preview, value));
+        }
+        if other.max_image_preview.is_some() {
+            self.max_image_preview.clone_from(&other.max_image_preview);
+        }
+        if other.unavailable_after.is_some() {
+            self.unavailable_after.clone_from(&other.unavailable_after);
+        }
+    }
+}
+
+/// Parses directives only when every one of them is recognized.
+fn parse_strict(content: &str) -> Option<RobotsDirectives> {
+    let mut directives = RobotsDirectives::default();
+    let mut any = false;
+    for directive in content.split(',') {
+        if directive.trim().is_empty() {
+            continue;
+        }
+        if !directives.apply_one(directive) {
+            return None;
+        }
+        any = true;
+    }
+
+    any.then_some(directives)
+}
+
+fn is_directive_name(name: &str) -> bool {
+    [
+        "max-snippet",
+        "max-image-preview",
+        "max-video-preview",
+        "unavailable_after",
+    ]
+    .iter()
+    .any(|d| d.eq_ignore_ascii_case(name))
+}
+
+/// The most restrictive of two limits where `-1` means unlimited.
+fn min_limit(current: Option<i64>, value: i64) -> i64 {
+    match current {
+        None | Some(-1) => value,
+        Some(current) if value == -1 => current,
+        Some(current) => current.min(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directives() {
+        let directives = RobotsDirectives::parse(
+            "NOINDEX, max-snippet: 50, max-snippet:-1, max-image-preview:LARGE, unknown, noarchive",
+        );
+        assert_eq!(
+            directives,
+            RobotsDirectives {
+                noindex: true,
+                noarchive: true,
+                max_snippet: Some(50),
+                max_image_preview: Some("large".into()),
+                ..Default::default()
+            }
+        );
+
+        let none = RobotsDirectives::parse("none");
+        assert!(none.noindex && none.nofollow);
+        assert_eq!(
+            RobotsDirectives::parse("all, index, follow"),
+            Default::default()
+        );
+    }
+
+    #[test]
+    fn bot_specific_tags_override_generic() {
+        let html = r#"
+        <meta name="description" content="A page about noindex">
+        <meta name="viewport" content="width=device-width">
+        <meta name="robots" content="noindex">
+        <meta name="googlebot" content="noarchive">
+        <meta name="KirbyBot" content="nofollow">
+        <meta name="kirbybot" content="max-snippet:10">
+        "#;
+
+        let meta = RobotsMeta::extract(html);
+        assert_eq!(meta.bots.len(), 2);
+        assert!(meta.generic().noindex);
+
+        let kirby = meta.directives_for("KirbyBot/1.0 (+https://example.com/bot)");
+        assert!(!kirby.noindex);
+        assert!(kirby.nofollow);
+        assert_eq!(kirby.max_snippet, Some(10));
+
+        let google = meta.directives_for("Googlebot");
+        assert!(!google.noindex);
+        assert!(google.noarchive);
+
+        assert!(meta.directives_for("OtherBot").noindex);
+    }
+
+    #[test]
+    fn parses_x_robots_tag_headers() {
+        let mut meta = RobotsMeta::default();
+        meta.add_header("noindex, unavailable_after: 25 Jun 2030 15:00:00 PST");
+        meta.add_header("KirbyBot: nofollow");
+        meta.add_header("max-snippet: 20");
+
+        assert!(meta.generic().noindex);
+        assert_eq!(meta.generic().max_snippet, Some(20));
+        assert_eq!(
+            meta.generic().unavailable_after.as_deref(),
+            Some("25 Jun 2030 15:00:00 PST")
+        );
+        assert!(meta.directives_for("kirbybot").nofollow);
+        assert!(!meta.directives_for("kirbybot").noindex);
+    }
+}
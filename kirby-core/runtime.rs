@@ -0,0 +1,184 @@
+use std::fmt;
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The async runtime the PostgreSQL pool and the gRPC sink run their futures on.
+///
+/// [`TokioRuntime`] is the default. Applications on async-std or smol pass
+/// [`AsyncStdRuntime`] or [`SmolRuntime`] instead, with the `async-std` or `smol` feature, to
+/// [`PostgresPool::connect_with_runtime`](crate::store::postgres::PostgresPool::connect_with_runtime)
+/// or [`GrpcSink::bind_with_runtime`](crate::export::grpc::GrpcSink::bind_with_runtime).
+///
+/// tonic only does its I/O on tokio's reactor, so the gRPC server still starts one, though
+/// its tasks run on the runtime given. The PostgreSQL queries use sqlx's async-std driver
+/// outside tokio, which smol's reactor drives as well.
+pub trait Runtime: fmt::Debug + Send + Sync {
+    /// Runs a future in the background.
+    fn spawn(&self, future: BoxFuture<'static, ()>);
+
+    /// A future that completes after `duration`.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Runs a future to completion, blocking the calling thread.
+    fn block_on(&self, future: BoxFuture<'_, ()>);
+}
+
+/// Runs a future returning a value to completion on a runtime.
+pub(crate) fn block_on<F>(runtime: &dyn Runtime, future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    let mut output = None;
+    runtime.block_on(Box::pin(async {
+        output = Some(future.await);
+    }));
+    output.expect("the future ran to completion")
+}
+
+/// tokio, on a runtime with one worker thread shared by the whole process and started on
+/// first use.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+impl TokioRuntime {
+    pub(crate) fn handle() -> &'static Handle {
+        static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+        RUNTIME
+            .get_or_init(|| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .thread_name("kirby-tokio")
+                    .enable_all()
+                    .build()
+                    .expect("the tokio runtime starts")
+            })
+            .handle()
+    }
+}
+
+impl Runtime for TokioRuntime {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        Self::handle().spawn(future);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let _guard = Self::handle().enter();
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    /// Blocking on a runtime from inside another one panics, so inside one it's done on a
+    /// scoped thread.
+    fn block_on(&self, future: BoxFuture<'_, ()>) {
+        if Handle::try_current().is_err() {
+            return Self::handle().block_on(future);
+        }
+        thread::scope(|scope| {
+            scope
+                .spawn(|| Self::handle().block_on(future))
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload))
+        })
+    }
+}
+
+/// async-std, on its global executor.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        async_std::task::spawn(future);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn block_on(&self, future: BoxFuture<'_, ()>) {
+        async_std::task::block_on(future)
+    }
+}
+
+/// smol, on its global executor.
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl Runtime for SmolRuntime {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        smol::spawn(future).detach();
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let timer = smol::Timer::after(duration);
+        Box::pin(async {
+            timer.await;
+        })
+    }
+
+    fn block_on(&self, future: BoxFuture<'_, ()>) {
+        smol::block_on(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    use super::*;
+
+    fn check_runtime(runtime: &dyn Runtime) {
+        let (sender, receiver) = mpsc::channel();
+        let sleep = runtime.sleep(Duration::from_millis(20));
+        runtime.spawn(Box::pin(async move {
+            sleep.await;
+            sender.send("slept").unwrap();
+        }));
+
+        let started = Instant::now();
+        assert_eq!(receiver.recv().unwrap(), "slept");
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        let borrowed = String::from("borrowed");
+        let output = block_on(runtime, async {
+            runtime.sleep(Duration::from_millis(1)).await;
+            borrowed.len()
+        });
+        assert_eq!(output, 8);
+    }
+
+    #[test]
+    fn spawns_and_sleeps_on_tokio() {
+        check_runtime(&TokioRuntime);
+    }
+
+    #[tokio::test]
+    async fn blocks_on_tokio_from_async_code() {
+        assert_eq!(block_on(&TokioRuntime, async { 1 + 1 }), 2);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn spawns_and_sleeps_on_async_std() {
+        check_runtime(&AsyncStdRuntime);
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn spawns_and_sleeps_on_smol() {
+        check_runtime(&SmolRuntime);
+    }
+}
@@ -0,0 +1,528 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+use crate::extract::{collapse_whitespace, element_text};
+use crate::linkgraph::page_links;
+use crate::record::PageRecord;
+use crate::robotsmeta::RobotsMeta;
+
+/// An SEO problem of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum Issue {
+    MissingTitle,
+    /// Other pages have the same title.
+    DuplicateTitle {
+        title: String,
+        pages: Vec<Url>,
+    },
+    MissingDescription,
+    /// Other pages have the same meta description.
+    DuplicateDescription {
+        description: String,
+        pages: Vec<Url>,
+    },
+    /// The canonical link isn't a URL, or points at a page that failed, redirects or is
+    /// `noindex`.
+    BrokenCanonical {
+        canonical: String,
+        reason: String,
+    },
+    /// The page was reached through more than one redirect, `hops` starts with the URL
+    /// requested and ends with the page.
+    RedirectChain {
+        hops: Vec<Url>,
+    },
+    /// The page asks not to be indexed but other pages link to it.
+    NoindexLinked {
+        from: Vec<Url>,
+    },
+    /// The sources of images without an `alt` attribute.
+    MissingAlt {
+        images: Vec<String>,
+    },
+    /// An hreflang alternate answered with an error status.
+    HreflangBroken {
+        lang: String,
+        alternate: Url,
+        status: u16,
+    },
+    /// An hreflang alternate doesn't list the page as an alternate in return.
+    HreflangNoReturn {
+        lang: String,
+        alternate: Url,
+    },
+}
+
+impl Issue {
+    /// The name of the kind of issue, as in the JSON `issue` field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MissingTitle => "missing_title",
+            Self::DuplicateTitle { .. } => "duplicate_title",
+            Self::MissingDescription => "missing_description",
+            Self::DuplicateDescription { .. } => "duplicate_description",
+            Self::BrokenCanonical { .. } => "broken_canonical",
+            Self::RedirectChain { .. } => "redirect_chain",
+            Self::NoindexLinked { .. } => "noindex_linked",
+            Self::MissingAlt { .. } => "missing_alt",
+            Self::HreflangBroken { .. } => "hreflang_broken",
+            Self::HreflangNoReturn { .. } => "hreflang_no_return",
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTitle => write!(f, "missing <title>"),
+            Self::DuplicateTitle { title, pages } => {
+                write!(f, "title {title:?} also on {}", list(pages))
+            }
+            Self::MissingDescription => write!(f, "missing meta description"),
+            Self::DuplicateDescription { description, pages } => {
+                write!(
+                    f,
+                    "meta description {description:?} also on {}",
+                    list(pages)
+                )
+            }
+            Self::BrokenCanonical { canonical, reason } => {
+                write!(f, "canonical {canonical} {reason}")
+            }
+            Self::RedirectChain { hops } => {
+                let hops = hops.iter().map(Url::as_str).collect::<Vec<_>>();
+                write!(f, "redirect chain {}", hops.join(" -> "))
+            }
+            Self::NoindexLinked { from } => write!(f, "noindex but linked from {}", list(from)),
+            Self::MissingAlt { images } => {
+                write!(f, "images without alt text: {}", images.join(", "))
+            }
+            Self::HreflangBroken {
+                lang,
+                alternate,
+                status,
+            } => write!(f, "hreflang {lang} alternate {alternate} answered {status}"),
+            Self::HreflangNoReturn { lang, alternate } => {
+                write!(f, "hreflang {lang} alternate {alternate} doesn't link back")
+            }
+        }
+    }
+}
+
+fn list(urls: &[Url]) -> String {
+    urls.iter().map(Url::as_str).collect::<Vec<_>>().join(", ")
+}
+
+/// An issue of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub url: Url,
+    #[serde(flatten)]
+    pub issue: Issue,
+}
+
+/// What the audit keeps of an HTML page.
+#[derive(Debug, Clone, Default)]
+struct Page {
+    title: Option<String>,
+    description: Option<String>,
+    /// The canonical link as written.
+    canonical: Option<String>,
+    noindex: bool,
+    links: Vec<Url>,
+    images_without_alt: Vec<String>,
+    /// The hreflang alternates by language.
+    alternates: Vec<(String, Url)>,
+}
+
+impl Page {
+    /// Whether the page declares another page canonical, which it's meant to duplicate.
+    fn points_elsewhere(&self, url: &Url) -> bool {
+        let canonical = self.canonical.as_deref().and_then(|c| url.join(c).ok());
+        canonical.is_some_and(|canonical| canonical != *url)
+    }
+}
+
+/// Audits crawled pages for common SEO problems: missing and duplicate titles and meta
+/// descriptions, broken canonicals, redirect chains, `noindex` pages that are linked to,
+/// images without alt text and inconsistent hreflang alternates.
+///
+/// Pages are added as they are crawled. Problems involving other pages, like duplicates, are
+/// only found among the pages added.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::record::PageRecord;
+/// use kirby_core::seo::{Issue, SeoAudit};
+/// use url::Url;
+///
+/// let mut audit = SeoAudit::default();
+/// let mut page = PageRecord::new(Url::parse("https://example.com/").unwrap(), 200, Utc::now());
+/// page.headers = vec![("Content-Type".to_string(), "text/html".to_string())];
+/// audit.add_page(&page, Some(r#"<title>Home</title> <img src="/logo.png">"#));
+///
+/// let issues = audit.findings().into_iter().map(|finding| finding.issue).collect::<Vec<_>>();
+/// assert_eq!(
+///     issues,
+///     [
+///         Issue::MissingDescription,
+///         Issue::MissingAlt { images: vec!["/logo.png".to_string()] },
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SeoAudit {
+    pages: BTreeMap<Url, Page>,
+    /// The status of every URL fetched, by the URL requested and the final URL.
+    statuses: HashMap<Url, u16>,
+    /// Where the URLs that redirected ended up.
+    redirected: HashMap<Url, Url>,
+    redirect_chains: BTreeMap<Url, Vec<Url>>,
+}
+
+impl SeoAudit {
+    /// Adds a crawled page with its body, which is only read for successful HTML pages.
+    pub fn add_page(&mut self, record: &PageRecord, body: Option<&str>) {
+        let url = record.final_url();
+        self.statuses.insert(record.url.clone(), record.status);
+        self.statuses.insert(url.clone(), record.status);
+        if !record.redirects.is_empty() {
+            self.redirected.insert(record.url.clone(), url.clone());
+        }
+        if record.redirects.len() > 1 {
+            let mut hops = vec![record.url.clone()];
+            hops.extend(record.redirects.iter().map(|redirect| redirect.to.clone()));
+            self.redirect_chains.insert(url.clone(), hops);
+        }
+
+        let html = record
+            .header("content-type")
+            .is_some_and(|content_type| content_type.contains("html"));
+        if let Some(body) = body.filter(|_| html && (200..300).contains(&record.status)) {
+            let mut page = parse(&Html::parse_document(body), url);
+            for (_, value) in record
+                .headers
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case("x-robots-tag"))
+            {
+                let mut meta = RobotsMeta::default();
+                meta.add_header(value);
+                page.noindex |= meta.generic().noindex;
+            }
+            self.pages.insert(url.clone(), page);
+        }
+    }
+
+    /// The issues of the pages added, ordered by page URL.
+    pub fn findings(&self) -> Vec<Finding> {
+        let titles = self.same_values(|page| page.title.as_deref());
+        let descriptions = self.same_values(|page| page.description.as_deref());
+        let mut linked_from = HashMap::<&Url, Vec<Url>>::new();
+        for (url, page) in &self.pages {
+            for link in &page.links {
+                let from = linked_from.entry(link).or_default();
+                if link != url && !from.contains(url) {
+                    from.push(url.clone());
+                }
+            }
+        }
+
+        let mut findings = Vec::new();
+        for (url, page) in &self.pages {
+            let mut issue = |issue| {
+                findings.push(Finding {
+                    url: url.clone(),
+                    issue,
+                })
+            };
+            if let Some(hops) = self.redirect_chains.get(url) {
+                issue(Issue::RedirectChain { hops: hops.clone() });
+            }
+            match &page.title {
+                None => issue(Issue::MissingTitle),
+                Some(_) if page.points_elsewhere(url) => {}
+                Some(title) => {
+                    if let Some(pages) = others(&titles, title, url) {
+                        issue(Issue::DuplicateTitle {
+                            title: title.clone(),
+                            pages,
+                        });
+                    }
+                }
+            }
+            match &page.description {
+                None => issue(Issue::MissingDescription),
+                Some(_) if page.points_elsewhere(url) => {}
+                Some(description) => {
+                    if let Some(pages) = others(&descriptions, description, url) {
+                        issue(Issue::DuplicateDescription {
+                            description: description.clone(),
+                            pages,
+                        });
+                    }
+                }
+            }
+            if let Some(canonical) = &page.canonical {
+                if let Some(reason) = self.canonical_problem(url, canonical) {
+                    issue(Issue::BrokenCanonical {
+                        canonical: canonical.clone(),
+                        reason,
+                    });
+                }
+            }
+            if page.noindex {
+                if let Some(from) = linked_from.get(url).filter(|from| !from.is_empty()) {
+                    issue(Issue::NoindexLinked { from: from.clone() });
+                }
+            }
+            if !page.images_without_alt.is_empty() {
+                issue(Issue::MissingAlt {
+                    images: page.images_without_alt.clone(),
+                });
+            }
+            for (lang, alternate) in page.alternates.iter().filter(|(_, to)| to != url) {
+                let alternate = self.redirected.get(alternate).unwrap_or(alternate);
+                match (self.statuses.get(alternate), self.pages.get(alternate)) {
+                    (Some(status), _) if *status >= 400 => issue(Issue::HreflangBroken {
+                        lang: lang.clone(),
+                        alternate: alternate.clone(),
+                        status: *status,
+                    }),
+                    (_, Some(other)) if !other.alternates.iter().any(|(_, to)| to == url) => {
+                        issue(Issue::HreflangNoReturn {
+                            lang: lang.clone(),
+                            alternate: alternate.clone(),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
+        findings
+    }
+
+    /// Why a page's canonical link is broken, `None` when it's fine or points at a page that
+    /// wasn't crawled.
+    fn canonical_problem(&self, url: &Url, canonical: &str) -> Option<String> {
+        let Ok(target) = url.join(canonical) else {
+            return Some("is not a valid URL".to_string());
+        };
+        if let Some(to) = self.redirected.get(&target) {
+            return Some(format!("redirects to {to}"));
+        }
+        match self.statuses.get(&target) {
+            Some(status) if !(200..300).contains(status) => Some(format!("answered {status}")),
+            _ if self.pages.get(&target).is_some_and(|page| page.noindex) => {
+                Some("is noindex".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// The pages by value, leaving out the pages that declare another page canonical.
+    fn same_values<'a>(
+        &'a self,
+        value: impl Fn(&'a Page) -> Option<&'a str>,
+    ) -> HashMap<&'a str, Vec<&'a Url>> {
+        let mut pages = HashMap::<_, Vec<_>>::new();
+        for (url, page) in &self.pages {
+            if page.points_elsewhere(url) {
+                continue;
+            }
+            if let Some(value) = value(page) {
+                pages.entry(value).or_default().push(url);
+            }
+        }
+        pages
+    }
+}
+
+/// The other pages with a value, `None` when there are none.
+fn others(pages: &HashMap<&str, Vec<&Url>>, value: &str, url: &Url) -> Option<Vec<Url>> {
+    let others = pages
+        .get(value)?
+        .iter()
+        .filter(|other| **other != url)
+        .map(|other| (*other).clone())
+        .collect::<Vec<_>>();
+    (!others.is_empty()).then_some(others)
+}
+
+fn parse(document: &Html, url: &Url) -> Page {
+    // Unwrapping is safe here because the selectors are valid constants.
+    let title = Selector::parse("title").unwrap();
+    let description = Selector::parse(r#"meta[name="description" i][content]"#).unwrap();
+    let canonical = Selector::parse(r#"link[rel~="canonical" i][href]"#).unwrap();
+    let images = Selector::parse("img:not([alt])").unwrap();
+    let alternates = Selector::parse(r#"link[rel~="alternate" i][hreflang][href]"#).unwrap();
+
+    let non_empty = |text: String| (!text.is_empty()).then_some(text);
+    Page {
+        title: document
+            .select(&title)
+            .next()
+            .and_then(|title| non_empty(element_text(title))),
+        description: document
+            .select(&description)
+            .next()
+            .and_then(|meta| non_empty(collapse_whitespace(meta.attr("content")?))),
+        canonical: document
+            .select(&canonical)
+            .next()
+            .and_then(|link| Some(link.attr("href")?.trim().to_string())),
+        noindex: RobotsMeta::from_element(document.root_element())
+            .generic()
+            .noindex,
+        links: page_links(document, url)
+            .into_iter()
+            .map(|edge| edge.target)
+            .collect(),
+        images_without_alt: document
+            .select(&images)
+            .map(|image| image.attr("src").unwrap_or_default().to_string())
+            .collect(),
+        alternates: document
+            .select(&alternates)
+            .filter_map(|link| {
+                let mut alternate = url.join(link.attr("href")?.trim()).ok()?;
+                alternate.set_fragment(None);
+                Some((link.attr("hreflang")?.to_string(), alternate))
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::record::Redirect;
+
+    fn page(path: &str, status: u16) -> PageRecord {
+        let url = Url::parse("https://example.com")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        let mut record = PageRecord::new(url, status, Utc::now());
+        record.headers = vec![("Content-Type".to_string(), "text/html".to_string())];
+        record
+    }
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn finds_issues_between_pages() {
+        let mut audit = SeoAudit::default();
+        let head = r#"<meta name="description" content="Kirby">"#;
+        audit.add_page(
+            &page("/", 200),
+            Some(&format!(
+                r#"<title>Kirby</title>{head}
+                   <link rel="alternate" hreflang="fr" href="/fr">
+                   <link rel="alternate" hreflang="de" href="/de">
+                   <a href="/hidden">Hidden</a>"#
+            )),
+        );
+        audit.add_page(
+            &page("/hidden", 200),
+            Some(&format!(
+                r#"<title>Kirby</title><meta name="robots" content="noindex">{head}
+                   <link rel="canonical" href="/gone">"#
+            )),
+        );
+        audit.add_page(
+            &page("/fr", 200),
+            Some(r#"<title>Kirby FR</title><meta name="description" content="Kirby FR">"#),
+        );
+        audit.add_page(&page("/de", 404), Some("Not found"));
+        audit.add_page(&page("/gone", 410), None);
+
+        let findings = audit
+            .findings()
+            .into_iter()
+            .map(|finding| (finding.url.path().to_string(), finding.issue))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            [
+                (
+                    "/".to_string(),
+                    Issue::HreflangNoReturn {
+                        lang: "fr".to_string(),
+                        alternate: url("/fr"),
+                    },
+                ),
+                (
+                    "/".to_string(),
+                    Issue::HreflangBroken {
+                        lang: "de".to_string(),
+                        alternate: url("/de"),
+                        status: 404,
+                    },
+                ),
+                (
+                    "/hidden".to_string(),
+                    Issue::BrokenCanonical {
+                        canonical: "/gone".to_string(),
+                        reason: "answered 410".to_string(),
+                    },
+                ),
+                (
+                    "/hidden".to_string(),
+                    Issue::NoindexLinked {
+                        from: vec![url("/")],
+                    },
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_duplicates_and_redirect_chains() {
+        let mut audit = SeoAudit::default();
+        let mut moved = page("/old", 200);
+        moved.redirects = vec![
+            Redirect {
+                from: url("/old"),
+                to: url("/older"),
+                status: 301,
+            },
+            Redirect {
+                from: url("/older"),
+                to: url("/new"),
+                status: 301,
+            },
+        ];
+        audit.add_page(&moved, Some("<title>Same</title>"));
+        audit.add_page(&page("/other", 200), Some("<title> Same </title>"));
+
+        let issues = audit
+            .findings()
+            .into_iter()
+            .filter(|finding| finding.url == url("/new"))
+            .map(|finding| finding.issue.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            [
+                "redirect chain https://example.com/old -> https://example.com/older -> \
+                 https://example.com/new",
+                r#"title "Same" also on https://example.com/other"#,
+                "missing meta description",
+            ]
+        );
+    }
+}
@@ -0,0 +1,486 @@
+use std::collections::HashSet;
+#[cfg(feature = "http")]
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Read;
+
+use chrono::{DateTime, NaiveDate};
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[cfg(feature = "http")]
+use crate::error::KirbyError;
+#[cfg(feature = "http")]
+use crate::fetch::Fetcher;
+
+/// The most URLs a sitemap may list, per the sitemaps.org protocol.
+pub const MAX_ENTRIES: usize = 50_000;
+
+/// The largest a sitemap may be uncompressed, 50 MiB per the sitemaps.org protocol.
+pub const MAX_BYTES: usize = 50 * 1024 * 1024;
+
+const CHANGE_FREQUENCIES: &[&str] = &[
+    "always", "hourly", "daily", "weekly", "monthly", "yearly", "never",
+];
+
+/// Whether a sitemap lists pages or other sitemaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SitemapKind {
+    /// A `<urlset>`, or a text file with one URL per line.
+    UrlSet,
+    /// A `<sitemapindex>` of other sitemaps.
+    Index,
+}
+
+/// A page listed in a sitemap, or a sitemap listed in an index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SitemapEntry {
+    pub loc: Url,
+    /// When the page last changed, as written, e.g. `2024-05-01`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lastmod: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changefreq: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+}
+
+/// Something in a sitemap that doesn't follow the protocol. Entries with issues are kept
+/// when their location is a valid URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Issue {
+    /// The location of the entry the issue is about, as written.
+    pub loc: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.loc {
+            Some(loc) => write!(f, "{loc}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SitemapError {
+    Xml(String),
+    /// The document isn't a `<urlset>` or `<sitemapindex>`.
+    NotSitemap,
+    /// The body looked gzipped but couldn't be decompressed.
+    Decompress(std::io::Error),
+    #[cfg(feature = "http")]
+    Fetch(Box<KirbyError>),
+    /// The sitemap was answered with a status other than 2xx.
+    #[cfg(feature = "http")]
+    Status(u16),
+}
+
+impl fmt::Display for SitemapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(error) => write!(f, "invalid XML: {error}"),
+            Self::NotSitemap => write!(f, "not a urlset or sitemapindex"),
+            Self::Decompress(error) => write!(f, "decompressing failed: {error}"),
+            #[cfg(feature = "http")]
+            Self::Fetch(error) => write!(f, "fetching failed: {error}"),
+            #[cfg(feature = "http")]
+            Self::Status(status) => write!(f, "answered with status {status}"),
+        }
+    }
+}
+
+impl std::error::Error for SitemapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decompress(error) => Some(error),
+            #[cfg(feature = "http")]
+            Self::Fetch(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed sitemap, XML or plain text, with the issues found while validating it.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::sitemap::{Sitemap, SitemapKind};
+/// use url::Url;
+///
+/// let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+///   <url><loc>https://example.com/</loc><lastmod>2024-05-01</lastmod></url>
+///   <url><loc>https://example.com/about</loc><priority>1.5</priority></url>
+/// </urlset>"#;
+///
+/// let location = Url::parse("https://example.com/sitemap.xml").unwrap();
+/// let sitemap = Sitemap::parse(&location, xml.as_bytes()).unwrap();
+/// assert_eq!(sitemap.kind, SitemapKind::UrlSet);
+/// assert_eq!(sitemap.entries.len(), 2);
+/// assert_eq!(
+///     sitemap.issues[0].to_string(),
+///     "https://example.com/about: priority 1.5 is outside of 0.0 to 1.0"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sitemap {
+    pub kind: SitemapKind,
+    pub entries: Vec<SitemapEntry>,
+    pub issues: Vec<Issue>,
+}
+
+impl Sitemap {
+    /// Parses a sitemap fetched from `location`, decompressing it first when it's gzipped.
+    ///
+    /// Besides the format, entries are checked to be absolute HTTP(S) URLs on the sitemap's
+    /// host with valid dates, frequencies and priorities, and the sitemap against the size
+    /// limits of the protocol.
+    pub fn parse(location: &Url, body: &[u8]) -> Result<Self, SitemapError> {
+        let mut issues = Vec::new();
+        let decompressed;
+        let body = if body.starts_with(&[0x1f, 0x8b]) {
+            let mut bytes = Vec::new();
+            GzDecoder::new(body)
+                .take(MAX_BYTES as u64 + 1)
+                .read_to_end(&mut bytes)
+                .map_err(SitemapError::Decompress)?;
+            decompressed = bytes;
+            &decompressed[..]
+        } else {
+            body
+        };
+        if body.len() > MAX_BYTES {
+            issues.push(Issue {
+                loc: None,
+                message: format!("larger than the limit of {MAX_BYTES} bytes"),
+            });
+        }
+
+        let text = String::from_utf8_lossy(body);
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+        let (kind, raw) = if text.starts_with('<') {
+            parse_xml(text)?
+        } else {
+            let locs = text.lines().map(str::trim).filter(|line| !line.is_empty());
+            let raw = locs.map(|loc| RawEntry {
+                loc: Some(loc.to_string()),
+                ..RawEntry::default()
+            });
+            (SitemapKind::UrlSet, raw.collect())
+        };
+
+        if raw.len() > MAX_ENTRIES {
+            issues.push(Issue {
+                loc: None,
+                message: format!(
+                    "lists {} entries, more than the limit of {MAX_ENTRIES}",
+                    raw.len()
+                ),
+            });
+        }
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        for raw in raw {
+            if let Some(entry) = raw.validate(location, kind, &mut seen, &mut issues) {
+                entries.push(entry);
+            }
+        }
+
+        Ok(Self {
+            kind,
+            entries,
+            issues,
+        })
+    }
+}
+
+/// An entry as written, before it's validated.
+#[derive(Debug, Default)]
+struct RawEntry {
+    loc: Option<String>,
+    lastmod: Option<String>,
+    changefreq: Option<String>,
+    priority: Option<String>,
+}
+
+impl RawEntry {
+    fn validate(
+        self,
+        location: &Url,
+        kind: SitemapKind,
+        seen: &mut HashSet<Url>,
+        issues: &mut Vec<Issue>,
+    ) -> Option<SitemapEntry> {
+        let Some(written) = self.loc else {
+            issues.push(Issue {
+                loc: None,
+                message: "an entry has no loc".to_string(),
+            });
+            return None;
+        };
+        let mut issue = |message: String| {
+            issues.push(Issue {
+                loc: Some(written.clone()),
+                message,
+            })
+        };
+
+        let loc = match Url::parse(&written) {
+            Ok(loc) if matches!(loc.scheme(), "http" | "https") => loc,
+            Ok(_) => {
+                issue("not an HTTP(S) URL".to_string());
+                return None;
+            }
+            Err(error) => {
+                issue(format!("not an absolute URL: {error}"));
+                return None;
+            }
+        };
+        if loc.host_str() != location.host_str() {
+            issue(format!(
+                "on another host than the sitemap's, {}",
+                location.host_str().unwrap_or_default()
+            ));
+        }
+        if !seen.insert(loc.clone()) {
+            issue("listed more than once".to_string());
+            return None;
+        }
+
+        if let Some(lastmod) = self.lastmod.as_deref().filter(|l| !is_w3c_datetime(l)) {
+            issue(format!("lastmod {lastmod:?} isn't a W3C datetime"));
+        }
+        let changefreq = self.changefreq.map(|c| c.to_ascii_lowercase());
+        if let Some(changefreq) = &changefreq {
+            if kind == SitemapKind::Index {
+                issue("changefreq isn't allowed in a sitemap index".to_string());
+            } else if !CHANGE_FREQUENCIES.contains(&changefreq.as_str()) {
+                issue(format!("changefreq {changefreq:?} is unknown"));
+            }
+        }
+        let priority = match self.priority.as_deref().map(str::parse::<f64>) {
+            Some(Ok(priority)) if (0.0..=1.0).contains(&priority) => Some(priority),
+            Some(Ok(priority)) => {
+                issue(format!("priority {priority:?} is outside of 0.0 to 1.0"));
+                Some(priority)
+            }
+            Some(Err(_)) => {
+                // Unwrapping is safe here because only a present priority fails to parse.
+                issue(format!(
+                    "priority {:?} isn't a number",
+                    self.priority.unwrap()
+                ));
+                None
+            }
+            None => None,
+        };
+
+        Some(SitemapEntry {
+            loc,
+            lastmod: self.lastmod,
+            changefreq,
+            priority,
+        })
+    }
+}
+
+fn parse_xml(xml: &str) -> Result<(SitemapKind, Vec<RawEntry>), SitemapError> {
+    let mut reader = Reader::from_str(xml);
+    let mut kind = None;
+    let mut entries = Vec::new();
+    let mut entry = RawEntry::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let xml_error = |e: quick_xml::Error| SitemapError::Xml(e.to_string());
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(start) => {
+                let name = String::from_utf8_lossy(start.local_name().as_ref()).to_string();
+                if path.is_empty() {
+                    kind = Some(match name.as_str() {
+                        "urlset" => SitemapKind::UrlSet,
+                        "sitemapindex" => SitemapKind::Index,
+                        _ => return Err(SitemapError::NotSitemap),
+                    });
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Empty(start) if path.is_empty() => {
+                return match start.local_name().as_ref() {
+                    b"urlset" => Ok((SitemapKind::UrlSet, entries)),
+                    b"sitemapindex" => Ok((SitemapKind::Index, entries)),
+                    _ => Err(SitemapError::NotSitemap),
+                };
+            }
+            Event::Text(t) => text.push_str(&t.decode().map_err(|e| xml_error(e.into()))?),
+            Event::CData(t) => text.push_str(&t.decode().map_err(|e| xml_error(e.into()))?),
+            Event::GeneralRef(r) => {
+                let raw = format!("&{};", r.decode().map_err(|e| xml_error(e.into()))?);
+                match quick_xml::escape::unescape(&raw) {
+                    Ok(resolved) => text.push_str(&resolved),
+                    Err(_) => text.push_str(&raw),
+                }
+            }
+            Event::End(_) => {
+                let value = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+                match path.len() {
+                    3 => match path[2].as_str() {
+                        "loc" => entry.loc = value,
+                        "lastmod" => entry.lastmod = value,
+                        "changefreq" => entry.changefreq = value,
+                        "priority" => entry.priority = value,
+                        _ => {}
+                    },
+                    2 if matches!(path[1].as_str(), "url" | "sitemap") => {
+                        entries.push(std::mem::take(&mut entry));
+                    }
+                    _ => {}
+                }
+                path.pop();
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    kind.map(|kind| (kind, entries))
+        .ok_or(SitemapError::NotSitemap)
+}
+
+/// Whether a date is in one of the W3C datetime formats sitemaps use, from `2024` to
+/// `2024-05-01T10:30:00.5+02:00`.
+fn is_w3c_datetime(value: &str) -> bool {
+    let date = |padded: String| NaiveDate::parse_from_str(&padded, "%Y-%m-%d").is_ok();
+    DateTime::parse_from_rfc3339(value).is_ok()
+        || date(value.to_string())
+        || date(format!("{value}-01"))
+        || date(format!("{value}-01-01"))
+        // Minutes without seconds.
+        || DateTime::parse_from_str(&value.replace('Z', "+00:00"), "%Y-%m-%dT%H:%M%:z").is_ok()
+}
+
+/// A sitemap fetched by [`fetch_all`].
+#[cfg(feature = "http")]
+#[derive(Debug)]
+pub struct FetchedSitemap {
+    pub url: Url,
+    pub result: Result<Sitemap, SitemapError>,
+}
+
+/// Fetches sitemaps and, recursively, the sitemaps their indexes list, up to `max_sitemaps` in
+/// total. Each sitemap is fetched once, in the order they were found.
+#[cfg(feature = "http")]
+pub fn fetch_all(fetcher: &Fetcher, roots: &[Url], max_sitemaps: usize) -> Vec<FetchedSitemap> {
+    let mut queue = roots.iter().cloned().collect::<VecDeque<_>>();
+    let mut seen = roots.iter().cloned().collect::<HashSet<_>>();
+    let mut fetched = Vec::new();
+    while let Some(url) = queue.pop_front() {
+        if fetched.len() == max_sitemaps {
+            break;
+        }
+        let result = match fetcher.fetch(&url) {
+            Ok(response) if (200..300).contains(&response.status) => {
+                Sitemap::parse(response.final_url(), &response.body)
+            }
+            Ok(response) => Err(SitemapError::Status(response.status)),
+            Err(error) => Err(SitemapError::Fetch(Box::new(error))),
+        };
+        if let Ok(sitemap) = &result {
+            let children = sitemap
+                .entries
+                .iter()
+                .filter(|_| sitemap.kind == SitemapKind::Index);
+            for entry in children {
+                if seen.insert(entry.loc.clone()) {
+                    queue.push_back(entry.loc.clone());
+                }
+            }
+        }
+        fetched.push(FetchedSitemap { url, result });
+    }
+    fetched
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    fn location() -> Url {
+        Url::parse("https://example.com/sitemap.xml").unwrap()
+    }
+
+    #[test]
+    fn parses_indexes_text_and_gzipped_sitemaps() {
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <sitemap><loc>https://example.com/a.xml?x=1&amp;y=2</loc></sitemap>
+            <sitemap><loc><![CDATA[https://example.com/b.xml]]></loc><lastmod>2024-05</lastmod></sitemap>
+        </sitemapindex>"#;
+        let sitemap = Sitemap::parse(&location(), index.as_bytes()).unwrap();
+        assert_eq!(sitemap.kind, SitemapKind::Index);
+        assert_eq!(
+            sitemap.entries[0].loc.as_str(),
+            "https://example.com/a.xml?x=1&y=2"
+        );
+        assert_eq!(sitemap.entries[1].lastmod.as_deref(), Some("2024-05"));
+        assert!(sitemap.issues.is_empty());
+
+        let text = "\u{feff}https://example.com/\n\nhttps://example.com/a\n";
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(text.as_bytes()).unwrap();
+        let sitemap = Sitemap::parse(&location(), &gzipped.finish().unwrap()).unwrap();
+        assert_eq!(sitemap.kind, SitemapKind::UrlSet);
+        assert_eq!(sitemap.entries.len(), 2);
+
+        assert!(matches!(
+            Sitemap::parse(&location(), b"<html><body/></html>"),
+            Err(SitemapError::NotSitemap)
+        ));
+    }
+
+    #[test]
+    fn reports_protocol_violations() {
+        let xml = r#"<urlset>
+            <url><loc>https://example.com/</loc><lastmod>2024-05-01T10:30+02:00</lastmod></url>
+            <url><loc>/relative</loc></url>
+            <url><loc>https://other.example/</loc><changefreq>sometimes</changefreq></url>
+            <url><loc>https://example.com/</loc></url>
+            <url><lastmod>yesterday</lastmod></url>
+            <url><loc>https://example.com/a</loc><lastmod>yesterday</lastmod><priority>high</priority></url>
+        </urlset>"#;
+        let sitemap = Sitemap::parse(&location(), xml.as_bytes()).unwrap();
+        let entries = sitemap.entries.iter().map(|e| e.loc.path());
+        assert_eq!(entries.collect::<Vec<_>>(), ["/", "/", "/a"]);
+        let issues = sitemap
+            .issues
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            [
+                "/relative: not an absolute URL: relative URL without a base",
+                "https://other.example/: on another host than the sitemap's, example.com",
+                "https://other.example/: changefreq \"sometimes\" is unknown",
+                "https://example.com/: listed more than once",
+                "an entry has no loc",
+                "https://example.com/a: lastmod \"yesterday\" isn't a W3C datetime",
+                "https://example.com/a: priority \"high\" isn't a number",
+            ]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::store::StoreError;
+
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// Where a URL is in the crawl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlStatus {
+    /// Discovered but not fetched yet.
+    Pending,
+    /// The last fetch got a response.
+    Fetched,
+    /// The last fetch failed without a response.
+    Failed,
+}
+
+impl UrlStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Fetched => "fetched",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "fetched" => Some(Self::Fetched),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// One attempt at fetching a URL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchAttempt {
+    pub url: Url,
+    pub fetched_at: DateTime<Utc>,
+    /// The HTTP status, `None` when no response was received.
+    pub status: Option<u16>,
+    /// The digest of the body, e.g. `sha256:..`.
+    pub digest: Option<String>,
+    /// Why the fetch failed, when it did.
+    pub error: Option<String>,
+}
+
+impl FetchAttempt {
+    /// An attempt that received a response.
+    pub fn response(url: Url, fetched_at: DateTime<Utc>, status: u16) -> Self {
+        Self {
+            url,
+            fetched_at,
+            status: Some(status),
+            digest: None,
+            error: None,
+        }
+    }
+
+    /// An attempt that failed without a response.
+    pub fn failure(url: Url, fetched_at: DateTime<Utc>, error: impl Into<String>) -> Self {
+        Self {
+            url,
+            fetched_at,
+            status: None,
+            digest: None,
+            error: Some(error.into()),
+        }
+    }
+
+    pub fn with_digest(mut self, digest: impl Into<String>) -> Self {
+        self.digest = Some(digest.into());
+        self
+    }
+}
+
+/// What is known about a URL, combining its discovery with its latest fetch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlState {
+    pub url: Url,
+    pub status: UrlStatus,
+    /// How many links away from a seed the URL was found.
+    pub depth: u32,
+    pub discovered_at: DateTime<Utc>,
+    pub last_fetched_at: Option<DateTime<Utc>>,
+    /// The HTTP status of the latest response.
+    pub http_status: Option<u16>,
+    /// The digest of the latest response body.
+    pub digest: Option<String>,
+    /// The number of fetch attempts, failed ones included.
+    pub attempts: u32,
+}
+
+/// Persistent crawl state, so a crawl can be resumed or repeated incrementally.
+///
+/// | Backend                    | Feature    |
+/// |----------------------------|------------|
+/// | `postgres::PostgresState`  | `postgres` |
+/// | `sqlite::SqliteState`      | `sqlite`   |
+pub trait StateStore {
+    /// Records a discovered URL, returning false when it was already known.
+    fn discover(&mut self, url: &Url, depth: u32, at: DateTime<Utc>) -> Result<bool, StoreError>;
+
+    /// Records a fetch attempt, discovering the URL at depth 0 when it wasn't known.
+    fn record(&mut self, attempt: &FetchAttempt) -> Result<(), StoreError>;
+
+    fn state(&self, url: &Url) -> Result<Option<UrlState>, StoreError>;
+
+    /// Up to `limit` pending URLs, shallowest and oldest first.
+    fn pending(&self, limit: usize) -> Result<Vec<Url>, StoreError>;
+
+    /// Fetched URLs whose latest fetch happened before `before`, oldest first.
+    fn stale(&self, before: DateTime<Utc>) -> Result<Vec<Url>, StoreError>;
+
+    /// Every fetch attempt for a URL, oldest first.
+    fn history(&self, url: &Url) -> Result<Vec<FetchAttempt>, StoreError>;
+}
+
+/// Exercises a state store through the trait so every backend is held to the same behaviour.
+#[cfg(all(test, any(feature = "postgres", feature = "sqlite")))]
+pub(crate) fn check_state_store(state: &mut dyn StateStore) {
+    use chrono::TimeZone;
+
+    let at = |hour| Utc.with_ymd_and_hms(2026, 5, 1, hour, 0, 0).unwrap();
+    let seed = Url::parse("https://example.com/").unwrap();
+    let about = Url::parse("https://example.com/about").unwrap();
+    let blog = Url::parse("https://example.com/blog").unwrap();
+
+    assert!(state.discover(&seed, 0, at(1)).unwrap());
+    assert!(state.discover(&blog, 1, at(2)).unwrap());
+    assert!(state.discover(&about, 1, at(3)).unwrap());
+    assert!(!state.discover(&about, 2, at(4)).unwrap());
+    assert_eq!(state.pending(2).unwrap(), vec![seed.clone(), blog.clone()]);
+
+    state
+        .record(&FetchAttempt::response(seed.clone(), at(5), 200).with_digest("sha256:aa"))
+        .unwrap();
+    state
+        .record(&FetchAttempt::failure(
+            blog.clone(),
+            at(6),
+            "connection reset",
+        ))
+        .unwrap();
+    state
+        .record(&FetchAttempt::response(blog.clone(), at(7), 200))
+        .unwrap();
+    assert_eq!(state.pending(10).unwrap(), vec![about.clone()]);
+
+    let seed_state = state.state(&seed).unwrap().unwrap();
+    assert_eq!(seed_state.status, UrlStatus::Fetched);
+    assert_eq!(seed_state.digest.as_deref(), Some("sha256:aa"));
+    assert_eq!(seed_state.last_fetched_at, Some(at(5)));
+
+    let blog_state = state.state(&blog).unwrap().unwrap();
+    assert_eq!((blog_state.depth, blog_state.attempts), (1, 2));
+    let history = state.history(&blog).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].error.as_deref(), Some("connection reset"));
+
+    assert_eq!(state.stale(at(6)).unwrap(), vec![seed.clone()]);
+    assert_eq!(state.stale(at(8)).unwrap(), vec![seed, blog]);
+    assert_eq!(
+        state
+            .state(&Url::parse("https://other.example/").unwrap())
+            .unwrap(),
+        None
+    );
+}
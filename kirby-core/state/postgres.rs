@@ -0,0 +1,248 @@
+//! Crawl state in PostgreSQL, for crawls that several workers share or that are reported on while
+//! they run.
+//!
+//! The tables mirror the [SQLite schema](super::sqlite) with native types: timestamps are
+//! `TIMESTAMPTZ` and `fetches.id` is a `BIGSERIAL`.
+
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use url::Url;
+
+use super::{FetchAttempt, StateStore, UrlState, UrlStatus};
+use crate::store::postgres::PostgresPool;
+use crate::store::StoreError;
+use crate::trace::{url_span, Stage};
+
+/// The migrations for the `urls` and `fetches` tables, in order.
+pub const MIGRATIONS: &[&str] = &["CREATE TABLE urls (
+        url TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        depth INTEGER NOT NULL,
+        discovered_at TIMESTAMPTZ NOT NULL,
+        last_fetched_at TIMESTAMPTZ,
+        http_status INTEGER,
+        digest TEXT,
+        attempts INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE fetches (
+        id BIGSERIAL PRIMARY KEY,
+        url TEXT NOT NULL REFERENCES urls (url),
+        fetched_at TIMESTAMPTZ NOT NULL,
+        http_status INTEGER,
+        digest TEXT,
+        error TEXT
+    );
+    CREATE INDEX urls_by_status ON urls (status, depth, discovered_at);
+    CREATE INDEX fetches_by_url ON fetches (url, fetched_at);"];
+
+/// Crawl state kept in PostgreSQL.
+///
+/// Use [`discover_many`](Self::discover_many) to record the links of a page in one round trip.
+#[derive(Debug, Clone)]
+pub struct PostgresState {
+    pool: PostgresPool,
+}
+
+impl PostgresState {
+    /// Uses a pool, creating or migrating the tables when needed.
+    pub fn open(pool: PostgresPool) -> Result<Self, StoreError> {
+        pool.migrate("state", MIGRATIONS)?;
+        Ok(Self { pool })
+    }
+
+    /// Records a batch of discovered URLs with their depths, returning how many were new.
+    pub fn discover_many(
+        &mut self,
+        urls: &[(Url, u32)],
+        at: DateTime<Utc>,
+    ) -> Result<usize, StoreError> {
+        let (urls, depths): (Vec<String>, Vec<i32>) = urls
+            .iter()
+            .map(|(url, depth)| (url.to_string(), *depth as i32))
+            .unzip();
+
+        let result = self.pool.block_on(
+            sqlx::query(
+                "INSERT INTO urls (url, status, depth, discovered_at)
+                 SELECT url, 'pending', depth, $3
+                 FROM UNNEST($1::text[], $2::int[]) AS batch (url, depth)
+                 ON CONFLICT (url) DO NOTHING",
+            )
+            .bind(urls)
+            .bind(depths)
+            .bind(at)
+            .execute(self.pool.pool()),
+        )?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    fn urls(&self, rows: Vec<String>) -> Result<Vec<Url>, StoreError> {
+        rows.iter().map(|url| parse_url(url)).collect()
+    }
+}
+
+impl StateStore for PostgresState {
+    fn discover(&mut self, url: &Url, depth: u32, at: DateTime<Utc>) -> Result<bool, StoreError> {
+        let _span = url_span(Stage::Enqueue, url, depth, None).entered();
+        Ok(self.discover_many(&[(url.clone(), depth)], at)? > 0)
+    }
+
+    fn record(&mut self, attempt: &FetchAttempt) -> Result<(), StoreError> {
+        let url = attempt.url.as_str();
+        let status = attempt.status.map(i32::from);
+
+        self.pool.block_on(async {
+            let mut transaction = self.pool.pool().begin().await?;
+            sqlx::query(
+                "INSERT INTO urls (url, status, depth, discovered_at) VALUES ($1, 'pending', 0, $2)
+                 ON CONFLICT (url) DO NOTHING",
+            )
+            .bind(url)
+            .bind(attempt.fetched_at)
+            .execute(&mut *transaction)
+            .await?;
+            sqlx::query(
+                "INSERT INTO fetches (url, fetched_at, http_status, digest, error)
+                 VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(url)
+            .bind(attempt.fetched_at)
+            .bind(status)
+            .bind(&attempt.digest)
+            .bind(&attempt.error)
+            .execute(&mut *transaction)
+            .await?;
+
+            // A failure keeps the details of the last response that was received.
+            let update = if status.is_some() {
+                sqlx::query(
+                    "UPDATE urls SET status = 'fetched', last_fetched_at = $2, http_status = $3,
+                     digest = $4, attempts = attempts + 1 WHERE url = $1",
+                )
+                .bind(url)
+                .bind(attempt.fetched_at)
+                .bind(status)
+                .bind(&attempt.digest)
+            } else {
+                sqlx::query(
+                    "UPDATE urls SET status = 'failed', attempts = attempts + 1 WHERE url = $1",
+                )
+                .bind(url)
+            };
+            update.execute(&mut *transaction).await?;
+
+            transaction.commit().await
+        })?;
+        Ok(())
+    }
+
+    fn state(&self, url: &Url) -> Result<Option<UrlState>, StoreError> {
+        let row: Option<PgRow> = self.pool.block_on(
+            sqlx::query(
+                "SELECT url, status, depth, discovered_at, last_fetched_at, http_status, digest,
+                 attempts FROM urls WHERE url = $1",
+            )
+            .bind(url.as_str())
+            .fetch_optional(self.pool.pool()),
+        )?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let status: String = row.try_get("status")?;
+        Ok(Some(UrlState {
+            url: parse_url(row.try_get("url")?)?,
+            status: UrlStatus::parse(&status)
+                .ok_or_else(|| invalid(format!("unknown URL status {status:?}")))?,
+            depth: row.try_get::<i32, _>("depth")? as u32,
+            discovered_at: row.try_get("discovered_at")?,
+            last_fetched_at: row.try_get("last_fetched_at")?,
+            http_status: row
+                .try_get::<Option<i32>, _>("http_status")?
+                .map(|status| status as u16),
+            digest: row.try_get("digest")?,
+            attempts: row.try_get::<i32, _>("attempts")? as u32,
+        }))
+    }
+
+    fn pending(&self, limit: usize) -> Result<Vec<Url>, StoreError> {
+        let rows = self.pool.block_on(
+            sqlx::query_scalar(
+                "SELECT url FROM urls WHERE status = 'pending'
+                 ORDER BY depth, discovered_at, url LIMIT $1",
+            )
+            .bind(limit as i64)
+            .fetch_all(self.pool.pool()),
+        )?;
+        self.urls(rows)
+    }
+
+    fn stale(&self, before: DateTime<Utc>) -> Result<Vec<Url>, StoreError> {
+        let rows = self.pool.block_on(
+            sqlx::query_scalar(
+                "SELECT url FROM urls WHERE status = 'fetched' AND last_fetched_at < $1
+                 ORDER BY last_fetched_at, url",
+            )
+            .bind(before)
+            .fetch_all(self.pool.pool()),
+        )?;
+        self.urls(rows)
+    }
+
+    fn history(&self, url: &Url) -> Result<Vec<FetchAttempt>, StoreError> {
+        let rows: Vec<PgRow> = self.pool.block_on(
+            sqlx::query(
+                "SELECT fetched_at, http_status, digest, error FROM fetches
+                 WHERE url = $1 ORDER BY fetched_at, id",
+            )
+            .bind(url.as_str())
+            .fetch_all(self.pool.pool()),
+        )?;
+
+        let mut attempts = Vec::with_capacity(rows.len());
+        for row in rows {
+            attempts.push(FetchAttempt {
+                url: url.clone(),
+                fetched_at: row.try_get("fetched_at")?,
+                status: row
+                    .try_get::<Option<i32>, _>("http_status")?
+                    .map(|status| status as u16),
+                digest: row.try_get("digest")?,
+                error: row.try_get("error")?,
+            });
+        }
+        Ok(attempts)
+    }
+}
+
+fn parse_url(value: &str) -> Result<Url, StoreError> {
+    Url::parse(value).map_err(|error| invalid(format!("invalid URL {value:?}: {error}")))
+}
+
+fn invalid(message: String) -> StoreError {
+    StoreError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::check_state_store;
+    use crate::store::postgres::test_pool;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server in KIRBY_TEST_POSTGRES"]
+    fn tracks_crawl_state_in_postgres() {
+        let mut state = PostgresState::open(test_pool()).unwrap();
+        check_state_store(&mut state);
+
+        let links = [
+            (Url::parse("https://example.com/blog").unwrap(), 2),
+            (Url::parse("https://example.com/new").unwrap(), 2),
+        ];
+        assert_eq!(state.discover_many(&links, Utc::now()).unwrap(), 1);
+    }
+}
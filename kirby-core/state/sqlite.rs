@@ -0,0 +1,346 @@
+//! Crawl state in SQLite.
+//!
+//! The schema is meant to be queried directly after a crawl:
+//!
+//! ```sql
+//! -- One row per discovered URL, holding its latest state.
+//! CREATE TABLE urls (
+//!     url             TEXT PRIMARY KEY,
+//!     status          TEXT NOT NULL,      -- 'pending', 'fetched' or 'failed'
+//!     depth           INTEGER NOT NULL,
+//!     discovered_at   TEXT NOT NULL,      -- UTC, e.g. 2026-05-01T08:00:00.000000Z
+//!     last_fetched_at TEXT,
+//!     http_status     INTEGER,            -- of the latest response
+//!     digest          TEXT,               -- of the latest response body
+//!     attempts        INTEGER NOT NULL DEFAULT 0
+//! );
+//!
+//! -- Every fetch attempt, failed ones included.
+//! CREATE TABLE fetches (
+//!     id          INTEGER PRIMARY KEY,
+//!     url         TEXT NOT NULL REFERENCES urls (url),
+//!     fetched_at  TEXT NOT NULL,
+//!     http_status INTEGER,
+//!     digest      TEXT,
+//!     error       TEXT
+//! );
+//! ```
+//!
+//! Timestamps are stored with a fixed width so they sort as text. The schema version is kept in
+//! `PRAGMA user_version`, opening a database applies whichever [`MIGRATIONS`] it hasn't seen.
+//!
+//! For example, the hosts with the most failures:
+//!
+//! ```sql
+//! SELECT substr(url, 1, instr(substr(url, 9), '/') + 8) AS origin, count(*) AS failures
+//! FROM fetches WHERE error IS NOT NULL GROUP BY origin ORDER BY failures DESC;
+//! ```
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use url::Url;
+
+use super::{FetchAttempt, StateStore, UrlState, UrlStatus};
+use crate::store::StoreError;
+use crate::trace::{url_span, Stage};
+
+/// The schema migrations in order, the database's `user_version` is the number applied.
+pub const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE urls (
+        url TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        depth INTEGER NOT NULL,
+        discovered_at TEXT NOT NULL,
+        last_fetched_at TEXT,
+        http_status INTEGER,
+        digest TEXT,
+        attempts INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE fetches (
+        id INTEGER PRIMARY KEY,
+        url TEXT NOT NULL REFERENCES urls (url),
+        fetched_at TEXT NOT NULL,
+        http_status INTEGER,
+        digest TEXT,
+        error TEXT
+    );",
+    "CREATE INDEX urls_by_status ON urls (status, depth, discovered_at);
+    CREATE INDEX fetches_by_url ON fetches (url, fetched_at);",
+];
+
+/// Crawl state kept in an SQLite database.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::state::sqlite::SqliteState;
+/// use kirby_core::state::{FetchAttempt, StateStore, UrlStatus};
+/// use url::Url;
+///
+/// let mut state = SqliteState::in_memory().unwrap();
+/// let url = Url::parse("https://example.com/").unwrap();
+///
+/// state.discover(&url, 0, Utc::now()).unwrap();
+/// state.record(&FetchAttempt::response(url.clone(), Utc::now(), 200)).unwrap();
+///
+/// assert_eq!(state.state(&url).unwrap().unwrap().status, UrlStatus::Fetched);
+/// ```
+pub struct SqliteState {
+    connection: Connection,
+}
+
+impl SqliteState {
+    /// Opens or creates a database file, migrating it to the latest schema.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Uses an existing connection, migrating it to the latest schema.
+    pub fn from_connection(mut connection: Connection) -> Result<Self, StoreError> {
+        migrate(&mut connection)?;
+        Ok(Self { connection })
+    }
+
+    /// The connection, for running queries of your own.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// The version of the database's schema.
+    pub fn schema_version(&self) -> Result<usize, StoreError> {
+        Ok(user_version(&self.connection)?)
+    }
+}
+
+impl StateStore for SqliteState {
+    fn discover(&mut self, url: &Url, depth: u32, at: DateTime<Utc>) -> Result<bool, StoreError> {
+        let _span = url_span(Stage::Enqueue, url, depth, None).entered();
+        let inserted = self.connection.execute(
+            "INSERT INTO urls (url, status, depth, discovered_at) VALUES (?1, 'pending', ?2, ?3)
+             ON CONFLICT (url) DO NOTHING",
+            params![url.as_str(), depth, format_time(at)],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    fn record(&mut self, attempt: &FetchAttempt) -> Result<(), StoreError> {
+        let fetched_at = format_time(attempt.fetched_at);
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO urls (url, status, depth, discovered_at) VALUES (?1, 'pending', 0, ?2)
+             ON CONFLICT (url) DO NOTHING",
+            params![attempt.url.as_str(), fetched_at],
+        )?;
+        transaction.execute(
+            "INSERT INTO fetches (url, fetched_at, http_status, digest, error)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                attempt.url.as_str(),
+                fetched_at,
+                attempt.status,
+                attempt.digest,
+                attempt.error
+            ],
+        )?;
+
+        // A failure keeps the details of the last response that was received.
+        if attempt.status.is_some() {
+            transaction.execute(
+                "UPDATE urls SET status = 'fetched', last_fetched_at = ?2, http_status = ?3,
+                 digest = ?4, attempts = attempts + 1 WHERE url = ?1",
+                params![
+                    attempt.url.as_str(),
+                    fetched_at,
+                    attempt.status,
+                    attempt.digest
+                ],
+            )?;
+        } else {
+            transaction.execute(
+                "UPDATE urls SET status = 'failed', attempts = attempts + 1 WHERE url = ?1",
+                params![attempt.url.as_str()],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn state(&self, url: &Url) -> Result<Option<UrlState>, StoreError> {
+        let row = self
+            .connection
+            .query_row(
+                "SELECT url, status, depth, discovered_at, last_fetched_at, http_status, digest,
+                 attempts FROM urls WHERE url = ?1",
+                params![url.as_str()],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, u32>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, Option<u16>>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                        row.get::<_, u32>(7)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((
+            url,
+            status,
+            depth,
+            discovered_at,
+            last_fetched_at,
+            http_status,
+            digest,
+            attempts,
+        )) = row
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(UrlState {
+            url: parse_url(&url)?,
+            status: UrlStatus::parse(&status)
+                .ok_or_else(|| invalid(format!("unknown URL status {status:?}")))?,
+            depth,
+            discovered_at: parse_time(&discovered_at)?,
+            last_fetched_at: last_fetched_at.as_deref().map(parse_time).transpose()?,
+            http_status,
+            digest,
+            attempts,
+        }))
+    }
+
+    fn pending(&self, limit: usize) -> Result<Vec<Url>, StoreError> {
+        self.urls(
+            "SELECT url FROM urls WHERE status = 'pending'
+             ORDER BY depth, discovered_at, url LIMIT ?1",
+            params![limit as i64],
+        )
+    }
+
+    fn stale(&self, before: DateTime<Utc>) -> Result<Vec<Url>, StoreError> {
+        self.urls(
+            "SELECT url FROM urls WHERE status = 'fetched' AND last_fetched_at < ?1
+             ORDER BY last_fetched_at, url",
+            params![format_time(before)],
+        )
+    }
+
+    fn history(&self, url: &Url) -> Result<Vec<FetchAttempt>, StoreError> {
+        let mut statement = self.connection.prepare(
+            "SELECT fetched_at, http_status, digest, error FROM fetches
+             WHERE url = ?1 ORDER BY fetched_at, id",
+        )?;
+        let rows = statement.query_map(params![url.as_str()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<u16>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+
+        let mut attempts = Vec::new();
+        for row in rows {
+            let (fetched_at, status, digest, error) = row?;
+            attempts.push(FetchAttempt {
+                url: url.clone(),
+                fetched_at: parse_time(&fetched_at)?,
+                status,
+                digest,
+                error,
+            });
+        }
+        Ok(attempts)
+    }
+}
+
+impl SqliteState {
+    fn urls(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Url>, StoreError> {
+        let mut statement = self.connection.prepare(sql)?;
+        let rows = statement.query_map(params, |row| row.get::<_, String>(0))?;
+
+        let mut urls = Vec::new();
+        for row in rows {
+            urls.push(parse_url(&row?)?);
+        }
+        Ok(urls)
+    }
+}
+
+/// Applies the migrations the database hasn't seen, each in its own transaction.
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let applied = user_version(connection)?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+fn user_version(connection: &Connection) -> rusqlite::Result<usize> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, StoreError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|error| invalid(format!("invalid timestamp {value:?}: {error}")))
+}
+
+fn parse_url(value: &str) -> Result<Url, StoreError> {
+    Url::parse(value).map_err(|error| invalid(format!("invalid URL {value:?}: {error}")))
+}
+
+fn invalid(message: String) -> StoreError {
+    StoreError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::check_state_store;
+
+    #[test]
+    fn tracks_crawl_state_in_sqlite() {
+        let mut state = SqliteState::in_memory().unwrap();
+        check_state_store(&mut state);
+    }
+
+    #[test]
+    fn migrates_once_and_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        let url = Url::parse("https://example.com/").unwrap();
+
+        let mut state = SqliteState::open(&path).unwrap();
+        assert_eq!(state.schema_version().unwrap(), MIGRATIONS.len());
+        state.discover(&url, 0, Utc::now()).unwrap();
+        drop(state);
+
+        let state = SqliteState::open(&path).unwrap();
+        assert_eq!(state.schema_version().unwrap(), MIGRATIONS.len());
+        assert_eq!(state.pending(10).unwrap(), vec![url]);
+    }
+}
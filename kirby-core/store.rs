@@ -0,0 +1,143 @@
+use std::fmt;
+use std::io;
+
+use url::Url;
+
+use crate::record::PageRecord;
+
+pub mod blob;
+pub mod fs;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "http")]
+pub mod s3;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// A page record stored together with its body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredPage {
+    pub record: PageRecord,
+    pub body: Option<Vec<u8>>,
+}
+
+/// Where crawl output is kept, so the destination is a configuration choice.
+///
+/// Pages are keyed by URL, putting a page again replaces the earlier record and body.
+///
+/// | Backend                    | Feature    |
+/// |----------------------------|------------|
+/// | [`fs::FsStore`]            |            |
+/// | `postgres::PostgresStore`  | `postgres` |
+/// | `s3::S3Store`              | `http`     |
+/// | `sqlite::SqliteStore`      | `sqlite`   |
+pub trait Store {
+    /// Stores a page record and, optionally, its body.
+    fn put(&mut self, record: &PageRecord, body: Option<&[u8]>) -> Result<(), StoreError>;
+
+    /// The stored page for a URL.
+    fn get(&self, url: &Url) -> Result<Option<StoredPage>, StoreError>;
+
+    /// The URLs of every stored page, sorted.
+    fn list(&self) -> Result<Vec<Url>, StoreError>;
+
+    /// Removes a page, returning false when it wasn't stored.
+    fn delete(&mut self, url: &Url) -> Result<bool, StoreError>;
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    /// A stored record couldn't be encoded or decoded.
+    Serialization(serde_json::Error),
+    #[cfg(feature = "postgres")]
+    Postgres(sqlx::Error),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "store I/O failed: {error}"),
+            Self::Serialization(error) => write!(f, "invalid stored record: {error}"),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(error) => write!(f, "PostgreSQL store failed: {error}"),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(error) => write!(f, "SQLite store failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Serialization(error) => Some(error),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(error) => Some(error),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Serialization(error)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<sqlx::Error> for StoreError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Postgres(error)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for StoreError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Sqlite(error)
+    }
+}
+
+/// Exercises a store through the trait so every backend is held to the same behaviour.
+#[cfg(test)]
+pub(crate) fn check_store(store: &mut dyn Store) {
+    use chrono::TimeZone;
+
+    let a = Url::parse("https://example.com/a").unwrap();
+    let b = Url::parse("https://example.com/b?q=1").unwrap();
+    let date = chrono::Utc.with_ymd_and_hms(2026, 5, 1, 8, 0, 0).unwrap();
+
+    let mut record = PageRecord::new(a.clone(), 200, date);
+    record.fields = serde_json::json!({"title": "A"});
+    store.put(&record, Some(b"<p>a</p>")).unwrap();
+    store
+        .put(&PageRecord::new(b.clone(), 404, date), None)
+        .unwrap();
+
+    let page = store.get(&a).unwrap().unwrap();
+    assert_eq!(page.record, record);
+    assert_eq!(page.body.as_deref(), Some(&b"<p>a</p>"[..]));
+    assert_eq!(store.get(&b).unwrap().unwrap().body, None);
+    assert_eq!(store.list().unwrap(), vec![a.clone(), b.clone()]);
+
+    // Putting a page again replaces its record and body.
+    record.status = 304;
+    store.put(&record, None).unwrap();
+    let page = store.get(&a).unwrap().unwrap();
+    assert_eq!((page.record.status, page.body), (304, None));
+
+    assert!(store.delete(&a).unwrap());
+    assert!(!store.delete(&a).unwrap());
+    assert_eq!(store.get(&a).unwrap(), None);
+    assert_eq!(store.list().unwrap(), vec![b]);
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::dedup::digest::{ContentDigest, DigestAlgorithm};
+
+/// Stores bodies under their SHA-256 digest, so identical payloads across URLs and crawls are
+/// kept once.
+///
+/// Each blob counts its references: [`put`](Self::put) adds one and [`release`](Self::release)
+/// removes one, deleting the blob when none are left. With the `zstd` feature blobs are stored
+/// compressed, blobs written without it can still be read.
+///
+/// ```text
+/// <root>/3f/3fa4..e1.zst    the body, compressed (`.blob` without the `zstd` feature)
+/// <root>/3f/3fa4..e1.refs   the number of references, as text
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::store::blob::BlobStore;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let mut blobs = BlobStore::open(dir.path()).unwrap();
+///
+/// let digest = blobs.put(b"<p>Hi</p>").unwrap();
+/// assert_eq!(blobs.put(b"<p>Hi</p>").unwrap(), digest);
+/// assert_eq!(blobs.references(&digest.to_string()).unwrap(), 2);
+///
+/// let body = blobs.get(&digest.to_string()).unwrap().unwrap();
+/// assert_eq!(body, b"<p>Hi</p>");
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    /// Opens a store in a directory, creating it when needed.
+    pub fn open(root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Stores a body, or adds a reference when it's already stored, and returns its digest.
+    pub fn put(&mut self, body: &[u8]) -> io::Result<ContentDigest> {
+        let digest = ContentDigest::compute(DigestAlgorithm::Sha256, body);
+        let path = self.path(&digest.hex());
+
+        let references = read_references(&path)?;
+        if references == 0 {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_blob(&path, body)?;
+        }
+        write_atomic(
+            &path.with_extension("refs"),
+            (references + 1).to_string().as_bytes(),
+        )?;
+
+        Ok(digest)
+    }
+
+    /// The body stored under a digest such as `sha256:3fa4..e1`.
+    pub fn get(&self, digest: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = self.path(parse_digest(digest)?);
+
+        #[cfg(feature = "zstd")]
+        match fs::read(path.with_extension("zst")) {
+            Ok(compressed) => return zstd::decode_all(compressed.as_slice()).map(Some),
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+
+        match fs::read(path.with_extension("blob")) {
+            Ok(body) => Ok(Some(body)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn contains(&self, digest: &str) -> io::Result<bool> {
+        Ok(self.references(digest)? > 0)
+    }
+
+    /// The number of references to a blob, 0 when it isn't stored.
+    pub fn references(&self, digest: &str) -> io::Result<u64> {
+        read_references(&self.path(parse_digest(digest)?))
+    }
+
+    /// Removes a reference to a blob, deleting it when it was the last one.
+    ///
+    /// Returns whether the blob was deleted.
+    pub fn release(&mut self, digest: &str) -> io::Result<bool> {
+        let path = self.path(parse_digest(digest)?);
+        match read_references(&path)? {
+            0 => Ok(false),
+            1 => {
+                for extension in ["zst", "blob", "refs"] {
+                    match fs::remove_file(path.with_extension(extension)) {
+                        Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+                        _ => {}
+                    }
+                }
+                Ok(true)
+            }
+            references => {
+                write_atomic(
+                    &path.with_extension("refs"),
+                    (references - 1).to_string().as_bytes(),
+                )?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// The path of a blob's files without their extension.
+    fn path(&self, hex: &str) -> PathBuf {
+        self.root.join(&hex[..2]).join(hex)
+    }
+}
+
+/// The hex part of a `sha256:..` digest, which is checked so it can't address another path.
+fn parse_digest(digest: &str) -> io::Result<&str> {
+    digest
+        .strip_prefix("sha256:")
+        .filter(|hex| {
+            hex.len() == 64 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("not a SHA-256 digest: {digest:?}"),
+            )
+        })
+}
+
+fn read_references(path: &Path) -> io::Result<u64> {
+    match fs::read_to_string(path.with_extension("refs")) {
+        Ok(text) => text
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "invalid blob reference count")),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(0),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn write_blob(path: &Path, body: &[u8]) -> io::Result<()> {
+    write_atomic(&path.with_extension("zst"), &zstd::encode_all(body, 0)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn write_blob(path: &Path, body: &[u8]) -> io::Result<()> {
+    write_atomic(&path.with_extension("blob"), body)
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_references_and_deletes_unused_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut blobs = BlobStore::open(dir.path()).unwrap();
+
+        let digest = blobs.put(b"same body").unwrap().to_string();
+        blobs.put(b"same body").unwrap();
+        let other = blobs.put(b"other body").unwrap().to_string();
+        assert_ne!(digest, other);
+
+        assert!(!blobs.release(&digest).unwrap());
+        assert_eq!(
+            blobs.get(&digest).unwrap().as_deref(),
+            Some(&b"same body"[..])
+        );
+        assert!(blobs.release(&digest).unwrap());
+        assert_eq!(blobs.get(&digest).unwrap(), None);
+        assert!(!blobs.contains(&digest).unwrap());
+        assert!(!blobs.release(&digest).unwrap());
+        assert!(blobs.contains(&other).unwrap());
+    }
+
+    #[test]
+    fn rejects_digests_that_are_not_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let blobs = BlobStore::open(dir.path()).unwrap();
+
+        for digest in ["sha256:../../etc/passwd", "md5:abc", ""] {
+            let error = blobs.get(digest).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compresses_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut blobs = BlobStore::open(dir.path()).unwrap();
+        let body = "Kirby ".repeat(1000);
+
+        let digest = blobs.put(body.as_bytes()).unwrap();
+        let path = blobs.path(&digest.hex()).with_extension("zst");
+        assert!(fs::metadata(path).unwrap().len() < 100);
+        assert_eq!(
+            blobs.get(&digest.to_string()).unwrap().unwrap(),
+            body.as_bytes()
+        );
+    }
+}
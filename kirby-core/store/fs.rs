@@ -0,0 +1,161 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::{Store, StoreError, StoredPage};
+use crate::record::PageRecord;
+use crate::trace::{record_span, Stage};
+
+/// Stores pages in a local directory.
+///
+/// Every page is kept under the SHA-256 of its URL, sharded by the first two hex digits so no
+/// directory grows too large:
+///
+/// ```text
+/// <root>/pages/3f/3fa4..e1.json   the page record
+/// <root>/pages/3f/3fa4..e1.body   the body, when stored
+/// ```
+///
+/// Files are written to a temporary file first and renamed into place, so a crash never leaves
+/// a partially written record behind.
+///
+/// # Example
+///
+/// ```
+/// use chrono::Utc;
+/// use kirby_core::record::PageRecord;
+/// use kirby_core::store::fs::FsStore;
+/// use kirby_core::store::Store;
+/// use url::Url;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let mut store = FsStore::open(dir.path()).unwrap();
+///
+/// let url = Url::parse("https://example.com/").unwrap();
+/// store.put(&PageRecord::new(url.clone(), 200, Utc::now()), Some(b"<p>Hi</p>")).unwrap();
+///
+/// let page = store.get(&url).unwrap().unwrap();
+/// assert_eq!(page.body.as_deref(), Some(&b"<p>Hi</p>"[..]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FsStore {
+    root: PathBuf,
+}
+
+impl FsStore {
+    /// Opens a store in a directory, creating it when needed.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join("pages"))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path of a page's files without their extension.
+    fn page_path(&self, url: &Url) -> PathBuf {
+        let key = Sha256::digest(url.as_str().as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        self.root.join("pages").join(&key[..2]).join(key)
+    }
+}
+
+impl Store for FsStore {
+    fn put(&mut self, record: &PageRecord, body: Option<&[u8]>) -> Result<(), StoreError> {
+        let _span = record_span(Stage::Store, record).entered();
+        let path = self.page_path(&record.url);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match body {
+            Some(body) => write_atomic(&path.with_extension("body"), body)?,
+            None => {
+                remove_if_exists(&path.with_extension("body"))?;
+            }
+        }
+        write_atomic(&path.with_extension("json"), &serde_json::to_vec(record)?)?;
+        Ok(())
+    }
+
+    fn get(&self, url: &Url) -> Result<Option<StoredPage>, StoreError> {
+        let path = self.page_path(url);
+        let record = match fs::read(path.with_extension("json")) {
+            Ok(bytes) => serde_json::from_slice::<PageRecord>(&bytes)?,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let body = match fs::read(path.with_extension("body")) {
+            Ok(body) => Some(body),
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Some(StoredPage { record, body }))
+    }
+
+    fn list(&self) -> Result<Vec<Url>, StoreError> {
+        let mut urls = Vec::new();
+        for shard in fs::read_dir(self.root.join("pages"))? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(shard.path())? {
+                let path = file?.path();
+                if path.extension().is_some_and(|e| e == "json") {
+                    let record = serde_json::from_slice::<PageRecord>(&fs::read(&path)?)?;
+                    urls.push(record.url);
+                }
+            }
+        }
+
+        urls.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(urls)
+    }
+
+    fn delete(&mut self, url: &Url) -> Result<bool, StoreError> {
+        let path = self.page_path(url);
+        remove_if_exists(&path.with_extension("body"))?;
+        Ok(remove_if_exists(&path.with_extension("json"))?)
+    }
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+/// Removes a file, returning false when it didn't exist.
+fn remove_if_exists(path: &Path) -> io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::check_store;
+
+    #[test]
+    fn stores_pages_in_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = FsStore::open(dir.path().join("crawl")).unwrap();
+        check_store(&mut store);
+
+        // Pages are found again when the store is reopened.
+        let reopened = FsStore::open(dir.path().join("crawl")).unwrap();
+        assert_eq!(reopened.list().unwrap(), store.list().unwrap());
+    }
+}
@@ -0,0 +1,287 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::Executor;
+use url::Url;
+
+use super::{Store, StoreError, StoredPage};
+use crate::record::PageRecord;
+use crate::runtime::{self, Runtime, TokioRuntime};
+use crate::trace::{record_span, Stage};
+
+/// A pool of PostgreSQL connections, shared by [`PostgresStore`] and
+/// [`PostgresState`](crate::state::postgres::PostgresState).
+///
+/// The stores are blocking, so the pool drives the queries on a [`Runtime`], tokio's unless
+/// [`connect_with_runtime`](Self::connect_with_runtime) is given another one. They can be
+/// used from async code as well, though they block the calling task. Cloning is cheap and
+/// shares the connections.
+#[derive(Debug, Clone)]
+pub struct PostgresPool {
+    runtime: Arc<dyn Runtime>,
+    pool: PgPool,
+}
+
+impl PostgresPool {
+    /// Connects to a database URL, e.g. `postgres://kirby@localhost/crawl`, with up to 8
+    /// connections.
+    pub fn connect(url: &str) -> Result<Self, StoreError> {
+        let options = url
+            .parse::<PgConnectOptions>()
+            .map_err(StoreError::Postgres)?;
+        Self::connect_with(options, 8)
+    }
+
+    pub fn connect_with(
+        options: PgConnectOptions,
+        max_connections: u32,
+    ) -> Result<Self, StoreError> {
+        Self::connect_with_runtime(options, max_connections, Arc::new(TokioRuntime))
+    }
+
+    /// Connects with the queries driven on `runtime`. Outside tokio they go through sqlx's
+    /// async-std driver, enabled by the `async-std` and `smol` features.
+    pub fn connect_with_runtime(
+        options: PgConnectOptions,
+        max_connections: u32,
+        runtime: Arc<dyn Runtime>,
+    ) -> Result<Self, StoreError> {
+        let pool = runtime::block_on(
+            &*runtime,
+            PgPoolOptions::new()
+                .max_connections(max_connections)
+                .connect_with(options),
+        )?;
+        Ok(Self { runtime, pool })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    pub(crate) fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        runtime::block_on(&*self.runtime, future)
+    }
+
+    /// Brings one component's tables up to date, the versions applied are kept in `kirby_schema`.
+    ///
+    /// Concurrent crawlers may start together, so the migrations run under an advisory lock.
+    pub(crate) fn migrate(&self, component: &str, migrations: &[&str]) -> Result<(), StoreError> {
+        self.block_on(async {
+            let mut transaction = self.pool.begin().await?;
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext('kirby_schema'))")
+                .execute(&mut *transaction)
+                .await?;
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS kirby_schema (
+                    component TEXT PRIMARY KEY,
+                    version INTEGER NOT NULL
+                )",
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            let applied: Option<i32> =
+                sqlx::query_scalar("SELECT version FROM kirby_schema WHERE component = $1")
+                    .bind(component)
+                    .fetch_optional(&mut *transaction)
+                    .await?;
+            let applied = applied.unwrap_or(0) as usize;
+
+            for migration in migrations.iter().skip(applied) {
+                transaction.execute(sqlx::raw_sql(migration)).await?;
+            }
+            sqlx::query(
+                "INSERT INTO kirby_schema (component, version) VALUES ($1, $2)
+                 ON CONFLICT (component) DO UPDATE SET version = excluded.version",
+            )
+            .bind(component)
+            .bind(migrations.len().max(applied) as i32)
+            .execute(&mut *transaction)
+            .await?;
+
+            transaction.commit().await
+        })?;
+        Ok(())
+    }
+}
+
+/// The migrations for the `pages` table, in order.
+pub const MIGRATIONS: &[&str] = &["CREATE TABLE pages (
+        url TEXT PRIMARY KEY,
+        record JSONB NOT NULL,
+        body BYTEA
+    )"];
+
+/// Stores pages in PostgreSQL, one row per URL with the record as JSONB so it can be queried:
+///
+/// ```sql
+/// SELECT record->>'status' AS status, count(*) FROM pages GROUP BY 1;
+/// ```
+///
+/// Use [`put_many`](Self::put_many) to write a batch of pages in one round trip.
+#[derive(Debug, Clone)]
+pub struct PostgresStore {
+    pool: PostgresPool,
+}
+
+impl PostgresStore {
+    /// Uses a pool, creating or migrating the `pages` table when needed.
+    pub fn open(pool: PostgresPool) -> Result<Self, StoreError> {
+        pool.migrate("pages", MIGRATIONS)?;
+        Ok(Self { pool })
+    }
+
+    /// Stores a batch of pages with a single upsert.
+    pub fn put_many(&mut self, pages: &[(PageRecord, Option<Vec<u8>>)]) -> Result<(), StoreError> {
+        let mut urls = Vec::with_capacity(pages.len());
+        let mut records = Vec::with_capacity(pages.len());
+        let mut bodies = Vec::with_capacity(pages.len());
+        for (record, body) in pages {
+            urls.push(record.url.to_string());
+            records.push(serde_json::to_string(record)?);
+            bodies.push(body.clone());
+        }
+
+        self.pool.block_on(
+            sqlx::query(
+                "INSERT INTO pages (url, record, body)
+                 SELECT url, record::jsonb, body
+                 FROM UNNEST($1::text[], $2::text[], $3::bytea[]) AS batch (url, record, body)
+                 ON CONFLICT (url) DO UPDATE SET record = excluded.record, body = excluded.body",
+            )
+            .bind(urls)
+            .bind(records)
+            .bind(bodies)
+            .execute(self.pool.pool()),
+        )?;
+        Ok(())
+    }
+}
+
+impl Store for PostgresStore {
+    fn put(&mut self, record: &PageRecord, body: Option<&[u8]>) -> Result<(), StoreError> {
+        let _span = record_span(Stage::Store, record).entered();
+        self.pool.block_on(
+            sqlx::query(
+                "INSERT INTO pages (url, record, body) VALUES ($1, $2::jsonb, $3)
+                 ON CONFLICT (url) DO UPDATE SET record = excluded.record, body = excluded.body",
+            )
+            .bind(record.url.as_str())
+            .bind(serde_json::to_string(record)?)
+            .bind(body)
+            .execute(self.pool.pool()),
+        )?;
+        Ok(())
+    }
+
+    fn get(&self, url: &Url) -> Result<Option<StoredPage>, StoreError> {
+        let row: Option<(String, Option<Vec<u8>>)> = self.pool.block_on(
+            sqlx::query_as("SELECT record::text, body FROM pages WHERE url = $1")
+                .bind(url.as_str())
+                .fetch_optional(self.pool.pool()),
+        )?;
+
+        row.map(|(record, body)| {
+            Ok(StoredPage {
+                record: serde_json::from_str(&record)?,
+                body,
+            })
+        })
+        .transpose()
+    }
+
+    fn list(&self) -> Result<Vec<Url>, StoreError> {
+        let records: Vec<String> = self.pool.block_on(
+            sqlx::query_scalar("SELECT record::text FROM pages ORDER BY url COLLATE \"C\"")
+                .fetch_all(self.pool.pool()),
+        )?;
+
+        let mut urls = Vec::with_capacity(records.len());
+        for record in records {
+            urls.push(serde_json::from_str::<PageRecord>(&record)?.url);
+        }
+        Ok(urls)
+    }
+
+    fn delete(&mut self, url: &Url) -> Result<bool, StoreError> {
+        let result = self.pool.block_on(
+            sqlx::query("DELETE FROM pages WHERE url = $1")
+                .bind(url.as_str())
+                .execute(self.pool.pool()),
+        )?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Connects to the database in `KIRBY_TEST_POSTGRES` inside a fresh schema.
+///
+/// The tests using it need a server, so they're ignored unless run with `--ignored`.
+#[cfg(test)]
+pub(crate) fn test_pool() -> PostgresPool {
+    test_pool_with_runtime(Arc::new(TokioRuntime))
+}
+
+#[cfg(test)]
+fn test_pool_with_runtime(runtime: Arc<dyn Runtime>) -> PostgresPool {
+    let url = std::env::var("KIRBY_TEST_POSTGRES")
+        .expect("KIRBY_TEST_POSTGRES is the URL of the database to test with");
+    let schema = format!("kirby_test_{}", uuid::Uuid::new_v4().simple());
+
+    let setup = PostgresPool::connect(&url).unwrap();
+    setup
+        .block_on(sqlx::raw_sql(&format!("CREATE SCHEMA {schema}")).execute(setup.pool()))
+        .unwrap();
+
+    let options = url
+        .parse::<PgConnectOptions>()
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    PostgresPool::connect_with_runtime(options, 2, runtime).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::check_store;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server in KIRBY_TEST_POSTGRES"]
+    fn stores_pages_in_postgres() {
+        let pool = test_pool();
+        let mut store = PostgresStore::open(pool.clone()).unwrap();
+        check_store(&mut store);
+
+        // Migrating again is a no-op.
+        let mut store = PostgresStore::open(pool).unwrap();
+        let date = chrono::Utc::now();
+        let pages = ["https://example.com/c", "https://example.com/b?q=1"]
+            .map(|url| (PageRecord::new(Url::parse(url).unwrap(), 200, date), None));
+        store.put_many(&pages).unwrap();
+        assert_eq!(store.list().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in KIRBY_TEST_POSTGRES"]
+    async fn stores_pages_from_async_code() {
+        let mut store = PostgresStore::open(test_pool()).unwrap();
+        let url = Url::parse("https://example.com/").unwrap();
+        store
+            .put(&PageRecord::new(url.clone(), 200, chrono::Utc::now()), None)
+            .unwrap();
+        assert_eq!(store.list().unwrap(), vec![url]);
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    #[ignore = "needs a PostgreSQL server in KIRBY_TEST_POSTGRES"]
+    fn stores_pages_on_smol() {
+        let pool = test_pool_with_runtime(Arc::new(crate::runtime::SmolRuntime));
+        check_store(&mut PostgresStore::open(pool).unwrap());
+    }
+}
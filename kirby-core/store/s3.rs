@@ -0,0 +1,238 @@
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::{Store, StoreError, StoredPage};
+use crate::archive::s3::{xml_values, Failure, S3Uploader};
+use crate::record::PageRecord;
+use crate::trace::{record_span, Stage};
+
+/// Stores pages in an S3 bucket or S3-compatible object storage, through the signed client of
+/// an [`S3Uploader`] and with its retries.
+///
+/// Objects are laid out like the files of an [`FsStore`](super::fs::FsStore), under the
+/// SHA-256 of the page's URL:
+///
+/// ```text
+/// <prefix>pages/3f/3fa4..e1.json   the page record
+/// <prefix>pages/3f/3fa4..e1.body   the body, when stored
+/// ```
+///
+/// Listing reads every record to find its URL, so it's slow for large crawls.
+///
+/// # Example
+///
+/// ```no_run
+/// use chrono::Utc;
+/// use kirby_core::archive::s3::{S3Credentials, S3Uploader};
+/// use kirby_core::record::PageRecord;
+/// use kirby_core::store::s3::S3Store;
+/// use kirby_core::store::Store;
+/// use url::Url;
+///
+/// let credentials = S3Credentials {
+///     access_key_id: "AKIDEXAMPLE".to_string(),
+///     secret_access_key: "secret".to_string(),
+///     session_token: None,
+/// };
+/// let endpoint = Url::parse("https://s3.eu-west-1.amazonaws.com/").unwrap();
+/// let client = S3Uploader::new(endpoint, "eu-west-1", "crawls", credentials);
+/// let mut store = S3Store::new(client, "2026/05/");
+///
+/// let url = Url::parse("https://example.com/").unwrap();
+/// store.put(&PageRecord::new(url, 200, Utc::now()), Some(b"<p>Hi</p>")).unwrap();
+/// ```
+pub struct S3Store {
+    client: S3Uploader,
+    prefix: String,
+}
+
+impl S3Store {
+    /// Stores pages under `prefix` in the client's bucket, e.g. `crawls/2026/`. An empty prefix
+    /// stores them at the top of the bucket.
+    pub fn new(client: S3Uploader, prefix: impl Into<String>) -> Self {
+        let mut prefix = prefix.into();
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        Self { client, prefix }
+    }
+
+    /// The key of a page's objects without their extension.
+    fn page_key(&self, url: &Url) -> String {
+        let hash = Sha256::digest(url.as_str().as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        format!("{}pages/{}/{hash}", self.prefix, &hash[..2])
+    }
+
+    /// The content of an object, `None` when there's no such object.
+    fn object(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        match self.client.request("GET", key, &[], Vec::new(), &[]) {
+            Ok(response) => {
+                let mut content = Vec::new();
+                response.into_reader().read_to_end(&mut content)?;
+                Ok(Some(content))
+            }
+            Err(Failure::Status(404, _)) => Ok(None),
+            Err(failure) => Err(io::Error::from(failure).into()),
+        }
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StoreError> {
+        match self.client.request("HEAD", key, &[], Vec::new(), &[]) {
+            Ok(_) => Ok(true),
+            Err(Failure::Status(404, _)) => Ok(false),
+            Err(failure) => Err(io::Error::from(failure).into()),
+        }
+    }
+
+    fn send(&self, method: &str, key: &str, body: &[u8]) -> Result<(), StoreError> {
+        self.client
+            .request(method, key, &[], Vec::new(), body)
+            .map_err(io::Error::from)?;
+        Ok(())
+    }
+}
+
+impl Store for S3Store {
+    fn put(&mut self, record: &PageRecord, body: Option<&[u8]>) -> Result<(), StoreError> {
+        let _span = record_span(Stage::Store, record).entered();
+        let key = self.page_key(&record.url);
+        match body {
+            Some(body) => self.send("PUT", &format!("{key}.body"), body)?,
+            // Deleting an object that doesn't exist succeeds.
+            None => self.send("DELETE", &format!("{key}.body"), &[])?,
+        }
+        self.send("PUT", &format!("{key}.json"), &serde_json::to_vec(record)?)
+    }
+
+    fn get(&self, url: &Url) -> Result<Option<StoredPage>, StoreError> {
+        let key = self.page_key(url);
+        let Some(record) = self.object(&format!("{key}.json"))? else {
+            return Ok(None);
+        };
+        Ok(Some(StoredPage {
+            record: serde_json::from_slice(&record)?,
+            body: self.object(&format!("{key}.body"))?,
+        }))
+    }
+
+    fn list(&self) -> Result<Vec<Url>, StoreError> {
+        let prefix = format!("{}pages/", self.prefix);
+        let mut urls = Vec::new();
+        let mut continuation = None::<String>;
+        loop {
+            let mut query = vec![("list-type", "2".to_string()), ("prefix", prefix.clone())];
+            if let Some(token) = continuation.take() {
+                query.push(("continuation-token", token));
+            }
+            let response = self
+                .client
+                .request("GET", "", &query, Vec::new(), &[])
+                .map_err(io::Error::from)?;
+            let xml = response.into_string()?;
+
+            for key in xml_values(&xml, "Key")? {
+                if key.ends_with(".json") {
+                    if let Some(record) = self.object(&key)? {
+                        urls.push(serde_json::from_slice::<PageRecord>(&record)?.url);
+                    }
+                }
+            }
+            continuation = xml_values(&xml, "NextContinuationToken")?.pop();
+            if continuation.is_none() {
+                break;
+            }
+        }
+
+        urls.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(urls)
+    }
+
+    fn delete(&mut self, url: &Url) -> Result<bool, StoreError> {
+        let key = self.page_key(url);
+        let existed = self.exists(&format!("{key}.json"))?;
+        self.send("DELETE", &format!("{key}.body"), &[])?;
+        self.send("DELETE", &format!("{key}.json"), &[])?;
+        Ok(existed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::archive::s3::S3Credentials;
+    use crate::http_server::{HttpServer, Response};
+    use crate::store::check_store;
+
+    #[test]
+    fn stores_pages_in_a_bucket() {
+        // Enough of S3 to store objects and list them, one page at a time.
+        let objects = Arc::new(Mutex::new(BTreeMap::<String, Vec<u8>>::new()));
+        let bucket = Arc::clone(&objects);
+        let server = HttpServer::start("127.0.0.1:0", move |request| {
+            let mut objects = bucket.lock().unwrap();
+            let key = request.path.trim_start_matches("/crawls/").to_string();
+            match request.method.as_str() {
+                "GET" if key.is_empty() => {
+                    let query = request.query.as_deref().unwrap_or_default();
+                    let query = url::form_urlencoded::parse(query.as_bytes())
+                        .into_owned()
+                        .collect::<BTreeMap<String, String>>();
+                    let after = query.get("continuation-token").cloned().unwrap_or_default();
+                    let mut keys = objects
+                        .keys()
+                        .filter(|key| key.starts_with(&query["prefix"]) && **key > after);
+                    let mut xml = String::from("<ListBucketResult>");
+                    if let Some(key) = keys.next() {
+                        xml.push_str(&format!("<Contents><Key>{key}</Key></Contents>"));
+                        if keys.next().is_some() {
+                            xml.push_str(&format!(
+                                "<NextContinuationToken>{key}</NextContinuationToken>"
+                            ));
+                        }
+                    }
+                    xml.push_str("</ListBucketResult>");
+                    Response::new(200, "application/xml", xml)
+                }
+                "GET" | "HEAD" => match objects.get(&key) {
+                    Some(object) if request.method == "GET" => {
+                        Response::new(200, "application/octet-stream", object.clone())
+                    }
+                    Some(_) => Response::empty(200),
+                    None => Response::empty(404),
+                },
+                "PUT" => {
+                    objects.insert(key, request.body.clone());
+                    Response::empty(200)
+                }
+                "DELETE" => {
+                    objects.remove(&key);
+                    Response::empty(204)
+                }
+                _ => Response::empty(405),
+            }
+        })
+        .unwrap();
+
+        let endpoint = Url::parse(&format!("http://{}/", server.address())).unwrap();
+        let credentials = S3Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        };
+        let client = S3Uploader::new(endpoint, "us-east-1", "crawls", credentials);
+        let mut store = S3Store::new(client, "2026");
+        check_store(&mut store);
+
+        let keys = objects.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].starts_with("2026/pages/") && keys[0].ends_with(".json"));
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use url::Url;
+
+use super::{Store, StoreError, StoredPage};
+use crate::record::PageRecord;
+use crate::trace::{record_span, Stage};
+
+/// Stores pages in an SQLite database, one row per URL with the record as JSON:
+///
+/// ```sql
+/// CREATE TABLE pages (
+///     url    TEXT PRIMARY KEY,
+///     record TEXT NOT NULL,  -- the PageRecord as JSON
+///     body   BLOB
+/// );
+/// ```
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens or creates a database file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Creates a store that only lives in memory.
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Uses an existing connection, creating the `pages` table when needed.
+    pub fn from_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS pages (
+                url TEXT PRIMARY KEY,
+                record TEXT NOT NULL,
+                body BLOB
+            )",
+        )?;
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Store for SqliteStore {
+    fn put(&mut self, record: &PageRecord, body: Option<&[u8]>) -> Result<(), StoreError> {
+        let _span = record_span(Stage::Store, record).entered();
+        self.connection.execute(
+            "INSERT INTO pages (url, record, body) VALUES (?1, ?2, ?3)
+             ON CONFLICT (url) DO UPDATE SET record = excluded.record, body = excluded.body",
+            params![record.url.as_str(), serde_json::to_string(record)?, body],
+        )?;
+        Ok(())
+    }
+
+    fn get(&self, url: &Url) -> Result<Option<StoredPage>, StoreError> {
+        let row = self
+            .connection
+            .query_row(
+                "SELECT record, body FROM pages WHERE url = ?1",
+                params![url.as_str()],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<Vec<u8>>>(1)?)),
+            )
+            .optional()?;
+
+        row.map(|(record, body)| {
+            Ok(StoredPage {
+                record: serde_json::from_str(&record)?,
+                body,
+            })
+        })
+        .transpose()
+    }
+
+    fn list(&self) -> Result<Vec<Url>, StoreError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT record FROM pages ORDER BY url")?;
+        let records = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut urls = Vec::new();
+        for record in records {
+            urls.push(serde_json::from_str::<PageRecord>(&record?)?.url);
+        }
+        Ok(urls)
+    }
+
+    fn delete(&mut self, url: &Url) -> Result<bool, StoreError> {
+        let deleted = self
+            .connection
+            .execute("DELETE FROM pages WHERE url = ?1", params![url.as_str()])?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::check_store;
+
+    #[test]
+    fn stores_pages_in_sqlite() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        check_store(&mut store);
+    }
+}
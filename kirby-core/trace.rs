@@ -0,0 +1,217 @@
+use tracing::{debug, field, info, info_span, warn, Span};
+use url::Url;
+
+use crate::error::ErrorKind;
+use crate::events::CrawlEvent;
+use crate::record::PageRecord;
+
+#[cfg(test)]
+pub(crate) mod test_subscriber;
+
+/// The names of the fields on kirby's spans and log events, the same wherever they appear so
+/// logs can be queried without knowing which part of the crawl wrote them.
+pub mod fields {
+    /// The kind of crawl event, e.g. `fetch_finished`, as in [`CrawlEvent`]'s JSON.
+    ///
+    /// [`CrawlEvent`]: crate::events::CrawlEvent
+    pub const EVENT: &str = "event";
+    pub const URL: &str = "url";
+    pub const HOST: &str = "host";
+    pub const DEPTH: &str = "depth";
+    /// 1 for the first attempt.
+    pub const ATTEMPT: &str = "attempt";
+    /// The HTTP status of a response.
+    pub const STATUS: &str = "status";
+    pub const DURATION_MS: &str = "duration_ms";
+    pub const BYTES: &str = "bytes";
+    /// E.g. `timeout`, see [`ErrorKind::as_str`](crate::error::ErrorKind::as_str).
+    pub const ERROR_KIND: &str = "error_kind";
+    pub const BUDGET: &str = "budget";
+    pub const REASON: &str = "reason";
+}
+
+/// A step of a URL's way through the crawl, each with its own span name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Enqueue,
+    Fetch,
+    Parse,
+    Extract,
+    Store,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Enqueue => "enqueue",
+            Self::Fetch => "fetch",
+            Self::Parse => "parse",
+            Self::Extract => "extract",
+            Self::Store => "store",
+        }
+    }
+}
+
+/// A span for one stage of a URL's lifecycle, so a single URL can be followed through the
+/// logs or a tracing backend.
+///
+/// Every stage has the same fields: `url`, `host`, `depth` and `attempt`, which is left
+/// empty when the attempt isn't known at that stage. The state and page stores enter these
+/// spans themselves, and the [`Crawler`](crate::crawler::Crawler) enters the enqueue, fetch,
+/// parse and extract spans around its own work.
+///
+/// # Example
+///
+/// ```
+/// use kirby_core::trace::{url_span, Stage};
+/// use url::Url;
+///
+/// let url = Url::parse("https://example.com/kirby").unwrap();
+/// let span = url_span(Stage::Fetch, &url, 2, Some(1));
+/// let _entered = span.enter();
+/// // Events logged here carry the URL, host, depth and attempt.
+/// ```
+pub fn url_span(stage: Stage, url: &Url, depth: u32, attempt: Option<u32>) -> Span {
+    let host = url.host_str().unwrap_or_default();
+    let url = url.as_str();
+    // Span names have to be static, hence a call per stage.
+    let span = match stage {
+        Stage::Enqueue => info_span!("enqueue", url, host, depth, attempt = field::Empty),
+        Stage::Fetch => info_span!("fetch", url, host, depth, attempt = field::Empty),
+        Stage::Parse => info_span!("parse", url, host, depth, attempt = field::Empty),
+        Stage::Extract => info_span!("extract", url, host, depth, attempt = field::Empty),
+        Stage::Store => info_span!("store", url, host, depth, attempt = field::Empty),
+    };
+    if let Some(attempt) = attempt {
+        span.record("attempt", attempt);
+    }
+    span
+}
+
+/// A span for a stage of a fetched page, with the record's URL and depth.
+pub fn record_span(stage: Stage, record: &PageRecord) -> Span {
+    url_span(stage, &record.url, record.depth, None)
+}
+
+/// Logs a crawl event with the standard [`fields`]: at `debug` for enqueued URLs and started
+/// fetches, at `warn` for paused hosts, exhausted budgets, errors and alerts, and at `info`
+/// otherwise.
+///
+/// Attaching it to an [`EventBus`](crate::events::EventBus) logs the whole crawl:
+/// `bus.on(log_event)`.
+pub fn log_event(event: &CrawlEvent) {
+    let host = event.url().and_then(Url::host_str).unwrap_or_default();
+    match event {
+        CrawlEvent::UrlEnqueued { url, depth } => {
+            debug!(
+                event = "url_enqueued",
+                url = url.as_str(),
+                host,
+                depth,
+                "enqueued"
+            )
+        }
+        CrawlEvent::FetchStarted { url, attempt } => debug!(
+            event = "fetch_started",
+            url = url.as_str(),
+            host,
+            attempt,
+            "fetching"
+        ),
+        CrawlEvent::FetchFinished {
+            url,
+            status,
+            duration,
+            bytes,
+        } => info!(
+            event = "fetch_finished",
+            url = url.as_str(),
+            host,
+            status,
+            duration_ms = duration.as_millis() as u64,
+            bytes,
+            "fetched"
+        ),
+        CrawlEvent::RobotsDenied { url } => info!(
+            event = "robots_denied",
+            url = url.as_str(),
+            host,
+            "denied by robots.txt"
+        ),
+        CrawlEvent::HostPaused {
+            host,
+            until,
+            reason,
+        } => warn!(
+            event = "host_paused",
+            host = host.as_str(),
+            until = %until.to_rfc3339(),
+            reason = reason.as_str(),
+            "host paused"
+        ),
+        CrawlEvent::BudgetExhausted { budget } => warn!(
+            event = "budget_exhausted",
+            budget = budget.as_str(),
+            "budget exhausted"
+        ),
+        CrawlEvent::Error { error } => {
+            let status = match error.kind() {
+                ErrorKind::Http { status } => Some(status),
+                _ => None,
+            };
+            warn!(
+                event = "error",
+                url = error.url().map(Url::as_str),
+                host = error.host(),
+                attempt = error.attempt(),
+                error_kind = error.kind().as_str(),
+                status,
+                "{error}"
+            )
+        }
+        CrawlEvent::Alert { alert } => warn!(
+            event = "alert",
+            host = alert.host.as_deref(),
+            "{}",
+            alert.message
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_subscriber::Spans;
+    use super::*;
+    use crate::store::fs::FsStore;
+    use crate::store::Store;
+
+    #[test]
+    fn spans_carry_the_url_fields() {
+        let spans = Spans::default();
+        let dir = tempfile::tempdir().unwrap();
+        tracing::subscriber::with_default(spans.clone(), || {
+            let url = Url::parse("https://example.com/kirby").unwrap();
+            url_span(Stage::Fetch, &url, 2, Some(3));
+
+            let mut record = PageRecord::new(url, 200, chrono::Utc::now());
+            record.depth = 2;
+            FsStore::open(dir.path())
+                .unwrap()
+                .put(&record, None)
+                .unwrap();
+        });
+
+        let spans = spans.spans();
+        let (name, fields) = &spans[0];
+        assert_eq!(*name, "fetch");
+        assert_eq!(fields["url"], "https://example.com/kirby");
+        assert_eq!(fields["host"], "example.com");
+        assert_eq!(fields["depth"], "2");
+        assert_eq!(fields["attempt"], "3");
+
+        let (name, fields) = &spans[1];
+        assert_eq!(*name, "store");
+        assert_eq!(fields["depth"], "2");
+        assert!(!fields.contains_key("attempt"));
+    }
+}
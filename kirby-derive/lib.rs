@@ -0,0 +1,344 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, Ident, LitStr,
+    PathArguments, Token, Type,
+};
+
+/// Derives `kirby_core::extract::from_html::FromHtml` for a struct with named fields, see the
+/// trait documentation for the supported `#[selector(..)]` attributes.
+#[proc_macro_derive(FromHtml, attributes(selector))]
+pub fn derive_from_html(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `kirby_core::extract::from_json::FromJson` for a struct with named fields, see the
+/// trait documentation for the supported `#[json_path(..)]` attributes.
+#[proc_macro_derive(FromJson, attributes(json_path))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_json(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// How many matches a field consumes, decided by the outer type of the field.
+enum Cardinality {
+    One,
+    Optional,
+    All,
+}
+
+/// What to extract from the matched element(s).
+enum Kind {
+    Text,
+    Html,
+    InnerHtml,
+    Attr(LitStr),
+    Nested,
+}
+
+struct SelectorAttr {
+    selector: LitStr,
+    kind: Kind,
+}
+
+impl syn::parse::Parse for SelectorAttr {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let selector: LitStr = input.parse()?;
+        if let Err(error) = scraper::Selector::parse(&selector.value()) {
+            return Err(Error::new(
+                selector.span(),
+                format!("invalid CSS selector `{}`: {error}", selector.value()),
+            ));
+        }
+
+        let mut kind = Kind::Text;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            kind = match ident.to_string().as_str() {
+                "text" => Kind::Text,
+                "html" => Kind::Html,
+                "inner_html" => Kind::InnerHtml,
+                "nested" => Kind::Nested,
+                "attr" => {
+                    input.parse::<Token![=]>()?;
+                    Kind::Attr(input.parse()?)
+                }
+                other => {
+                    return Err(Error::new(
+                        ident.span(),
+                        format!(
+                            "unknown extraction `{other}`, expected one of `text`, `html`, \
+                             `inner_html`, `attr = \"..\"` or `nested`"
+                        ),
+                    ))
+                }
+            };
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens after the extraction kind"));
+        }
+
+        Ok(Self { selector, kind })
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            Span::call_site(),
+            "FromHtml can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            Span::call_site(),
+            "FromHtml can only be derived for structs with named fields",
+        ));
+    };
+
+    let krate = quote!(::kirby_core::extract::from_html);
+    let name = &input.ident;
+    let type_name = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut initializers = Vec::with_capacity(fields.named.len());
+    for field in &fields.named {
+        // Unwrapping is safe here because the fields are named.
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = ident.to_string();
+
+        let mut attrs = field.attrs.iter().filter(|a| a.path().is_ident("selector"));
+        let Some(attr) = attrs.next() else {
+            initializers.push(quote!(#ident: ::std::default::Default::default()));
+            continue;
+        };
+        if let Some(duplicate) = attrs.next() {
+            return Err(Error::new_spanned(
+                duplicate,
+                "only one #[selector] is allowed per field",
+            ));
+        }
+
+        let SelectorAttr { selector, kind } = attr.parse_args()?;
+        let (cardinality, inner) = cardinality(&field.ty);
+
+        let how = match &kind {
+            Kind::Text => quote!(#krate::Extract::Text),
+            Kind::Html => quote!(#krate::Extract::Html),
+            Kind::InnerHtml => quote!(#krate::Extract::InnerHtml),
+            Kind::Attr(attr) => quote!(#krate::Extract::Attr(#attr)),
+            Kind::Nested => quote!(),
+        };
+        let call = match (&kind, cardinality) {
+            (Kind::Nested, Cardinality::One) => {
+                quote!(#krate::__private::one_nested::<#inner>(element, &SELECTOR))
+            }
+            (Kind::Nested, Cardinality::Optional) => {
+                quote!(#krate::__private::optional_nested::<#inner>(element, &SELECTOR))
+            }
+            (Kind::Nested, Cardinality::All) => {
+                quote!(#krate::__private::all_nested::<#inner>(element, &SELECTOR))
+            }
+            (_, Cardinality::One) => {
+                quote!(#krate::__private::one::<#inner>(element, &SELECTOR, #how))
+            }
+            (_, Cardinality::Optional) => {
+                quote!(#krate::__private::optional::<#inner>(element, &SELECTOR, #how))
+            }
+            (_, Cardinality::All) => {
+                quote!(#krate::__private::all::<#inner>(element, &SELECTOR, #how))
+            }
+        };
+
+        initializers.push(quote! {
+            #ident: {
+                static SELECTOR: #krate::__private::LazyLock<#krate::__private::Selector> =
+                    #krate::__private::LazyLock::new(|| {
+                        // Validated when the derive was expanded.
+                        #krate::__private::Selector::parse(#selector).unwrap()
+                    });
+                #call.map_err(|kind| #krate::FromHtmlError {
+                    type_name: #type_name,
+                    field: #field_name,
+                    selector: #selector,
+                    kind,
+                })?
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics #krate::FromHtml for #name #ty_generics #where_clause {
+            fn from_element(
+                element: #krate::__private::ElementRef,
+            ) -> ::std::result::Result<Self, #krate::FromHtmlError> {
+                ::std::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+/// How a value matched by a JSON path is converted into the field's type.
+enum JsonKind {
+    Scalar,
+    Json,
+    Nested,
+}
+
+struct JsonPathAttr {
+    path: LitStr,
+    kind: JsonKind,
+}
+
+impl syn::parse::Parse for JsonPathAttr {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: LitStr = input.parse()?;
+
+        let mut kind = JsonKind::Scalar;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            kind = match ident.to_string().as_str() {
+                "scalar" => JsonKind::Scalar,
+                "json" => JsonKind::Json,
+                "nested" => JsonKind::Nested,
+                other => {
+                    return Err(Error::new(
+                        ident.span(),
+                        format!(
+                            "unknown extraction `{other}`, expected one of `scalar`, `json` or \
+                             `nested`"
+                        ),
+                    ))
+                }
+            };
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens after the extraction kind"));
+        }
+
+        Ok(Self { path, kind })
+    }
+}
+
+fn expand_json(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            Span::call_site(),
+            "FromJson can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            Span::call_site(),
+            "FromJson can only be derived for structs with named fields",
+        ));
+    };
+
+    let krate = quote!(::kirby_core::extract::from_json);
+    let name = &input.ident;
+    let type_name = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut initializers = Vec::with_capacity(fields.named.len());
+    for field in &fields.named {
+        // Unwrapping is safe here because the fields are named.
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = ident.to_string();
+
+        let mut attrs = field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("json_path"));
+        let Some(attr) = attrs.next() else {
+            initializers.push(quote!(#ident: ::std::default::Default::default()));
+            continue;
+        };
+        if let Some(duplicate) = attrs.next() {
+            return Err(Error::new_spanned(
+                duplicate,
+                "only one #[json_path] is allowed per field",
+            ));
+        }
+
+        let JsonPathAttr { path, kind } = attr.parse_args()?;
+        let (cardinality, inner) = cardinality(&field.ty);
+
+        let convert = match kind {
+            JsonKind::Scalar => quote!(#krate::__private::scalar::<#inner>),
+            JsonKind::Json => quote!(#krate::__private::json::<#inner>),
+            JsonKind::Nested => quote!(#krate::__private::nested::<#inner>),
+        };
+        let function = match cardinality {
+            Cardinality::One => quote!(one),
+            Cardinality::Optional => quote!(optional),
+            Cardinality::All => quote!(all),
+        };
+
+        initializers.push(quote! {
+            #ident: {
+                static PATH: #krate::__private::LazyLock<
+                    ::std::result::Result<
+                        #krate::__private::JsonPath,
+                        #krate::__private::JsonPathError,
+                    >,
+                > = #krate::__private::LazyLock::new(|| {
+                    #krate::__private::JsonPath::parse(#path)
+                });
+                #krate::__private::#function(value, &PATH, #convert).map_err(|kind| {
+                    #krate::FromJsonError {
+                        type_name: #type_name,
+                        field: #field_name,
+                        path: #path,
+                        kind,
+                    }
+                })?
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics #krate::FromJson for #name #ty_generics #where_clause {
+            fn from_value(
+                value: &#krate::__private::Value,
+            ) -> ::std::result::Result<Self, #krate::FromJsonError> {
+                ::std::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+/// Splits `Option<T>` and `Vec<T>` into their cardinality and inner type, any other type is a
+/// single required value.
+fn cardinality(ty: &Type) -> (Cardinality, &Type) {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let (1, Some(GenericArgument::Type(inner))) =
+                    (args.args.len(), args.args.first())
+                {
+                    if segment.ident == "Option" {
+                        return (Cardinality::Optional, inner);
+                    } else if segment.ident == "Vec" {
+                        return (Cardinality::All, inner);
+                    }
+                }
+            }
+        }
+    }
+
+    (Cardinality::One, ty)
+}
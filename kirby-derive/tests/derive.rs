@@ -0,0 +1,8 @@
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass.rs");
+    cases.compile_fail("tests/ui/invalid_selector.rs");
+    cases.compile_fail("tests/ui/unknown_extraction.rs");
+    cases.compile_fail("tests/ui/unsupported_field_type.rs");
+}
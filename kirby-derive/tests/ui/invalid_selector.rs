@@ -0,0 +1,9 @@
+use kirby_core::extract::from_html::FromHtml;
+
+#[derive(FromHtml)]
+struct Post {
+    #[selector("h1[")]
+    title: String,
+}
+
+fn main() {}
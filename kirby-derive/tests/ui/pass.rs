@@ -0,0 +1,51 @@
+use kirby_core::extract::from_html::FromHtml;
+use kirby_core::extract::from_json::FromJson;
+
+#[derive(FromHtml)]
+struct Author {
+    #[selector("span.name")]
+    name: String,
+}
+
+#[derive(FromHtml)]
+struct Post {
+    #[selector("h1.title", text)]
+    title: String,
+    #[selector("span.likes")]
+    likes: u32,
+    #[selector("a.tag", attr = "href")]
+    tags: Vec<String>,
+    #[selector("p.subtitle")]
+    subtitle: Option<String>,
+    #[selector("main", inner_html)]
+    body: String,
+    #[selector("div.author", nested)]
+    author: Author,
+    draft: bool,
+}
+
+#[derive(FromJson)]
+struct Product {
+    #[json_path("$.name")]
+    name: String,
+    #[json_path("$.tags[*]")]
+    tags: Vec<String>,
+}
+
+fn main() {
+    let html = r#"
+        <h1 class="title">Hello</h1>
+        <span class="likes">42</span>
+        <main><p>Body</p></main>
+        <div class="author"><span class="name">Kirby</span></div>
+    "#;
+    let post = Post::from_html(html).unwrap();
+    assert_eq!(post.title, "Hello");
+    assert_eq!(post.likes, 42);
+    assert!(post.tags.is_empty() && post.subtitle.is_none() && !post.draft);
+    assert_eq!(post.body, "<p>Body</p>");
+    assert_eq!(post.author.name, "Kirby");
+
+    let product = Product::from_json(r#"{"name": "Star", "tags": ["pink"]}"#).unwrap();
+    assert_eq!((product.name.as_str(), product.tags.len()), ("Star", 1));
+}
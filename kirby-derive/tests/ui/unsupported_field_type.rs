@@ -0,0 +1,11 @@
+use kirby_core::extract::from_html::FromHtml;
+
+struct Title(String);
+
+#[derive(FromHtml)]
+struct Post {
+    #[selector("h1")]
+    title: Title,
+}
+
+fn main() {}
@@ -0,0 +1,727 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use kirby_core::state::FetchAttempt;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::ring::HashRing;
+
+/// A URL waiting to be fetched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedUrl {
+    pub url: Url,
+    /// How many links away from a seed the URL was found.
+    pub depth: u32,
+}
+
+/// URLs of one host handed to a worker until it reports on them or the lease expires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkLease {
+    pub id: u64,
+    pub host: String,
+    pub urls: Vec<QueuedUrl>,
+    /// How long the lease lasts without being renewed or reported on.
+    pub ttl: Duration,
+    /// Whether the host belongs to another worker on the hash ring and was lent out while that
+    /// worker was busy, so the politeness state for it starts afresh.
+    pub stolen: bool,
+}
+
+/// The outcome of fetching a leased URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlResult {
+    pub url: Url,
+    /// The HTTP status, `None` when no response was received.
+    pub status: Option<u16>,
+    /// Why the fetch failed, when it did.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaseError {
+    /// The lease doesn't exist, it expired or was finished.
+    Unknown(u64),
+    /// The lease belongs to another worker.
+    NotOwner { lease: u64, worker: String },
+}
+
+impl fmt::Display for LeaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(lease) => write!(f, "lease {lease} is unknown or expired"),
+            Self::NotOwner { lease, worker } => {
+                write!(f, "lease {lease} doesn't belong to worker {worker}")
+            }
+        }
+    }
+}
+
+impl Error for LeaseError {}
+
+/// A worker that went silent for longer than the grace period, and had its leases reclaimed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostWorker {
+    pub worker: String,
+    pub at: DateTime<Utc>,
+    /// How long it had been silent for.
+    pub silent_for: Duration,
+    /// How many of its leases were reclaimed.
+    pub reclaimed: usize,
+}
+
+/// How a worker is doing, as far as the coordinator knows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerHealth {
+    pub worker: String,
+    /// How long ago it was last heard from.
+    pub silent_for: Duration,
+    /// How many leases it holds.
+    pub leases: usize,
+}
+
+#[derive(Debug)]
+struct ActiveLease {
+    worker: String,
+    host: String,
+    /// The leased URLs not reported yet.
+    pending: Vec<QueuedUrl>,
+    expires_at: Instant,
+}
+
+/// Owns the frontier of a distributed crawl and leases it out to workers by host.
+///
+/// A host is leased to one worker at a time, so the worker holding it can keep to the host's
+/// politeness rules on its own. Leases expire when a worker neither renews nor reports on them
+/// within the lease TTL, e.g. because it crashed; their unreported URLs go back to the queue
+/// to be leased again. Hosts take turns, so every host makes progress however many there are.
+///
+/// Every call from a worker, including [`heartbeat`](Self::heartbeat), counts as a sign of
+/// life. With a [grace period](Self::grace_period), a worker that stays silent for longer has
+/// all of its leases reclaimed at once, without waiting for them to expire one by one, and is
+/// recorded as [lost](Self::take_lost).
+///
+/// Results are kept as [`FetchAttempt`]s until [`take_results`](Self::take_results), e.g.
+/// to record them in a [`StateStore`](kirby_core::state::StateStore).
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use kirby_distributed::coordinator::{Coordinator, QueuedUrl, UrlResult};
+/// use url::Url;
+///
+/// let mut coordinator = Coordinator::new().lease_ttl(Duration::from_secs(60));
+/// let seed = Url::parse("https://example.com/").unwrap();
+/// coordinator.add(seed.clone(), 0);
+///
+/// let now = Instant::now();
+/// let lease = coordinator.lease("worker-1", 1, 100, now).remove(0);
+/// assert_eq!(lease.host, "example.com");
+/// // Nothing else to hand out while the host is leased.
+/// assert!(coordinator.lease("worker-2", 1, 100, now).is_empty());
+///
+/// let result = UrlResult { url: seed.clone(), status: Some(200), error: None };
+/// let about = QueuedUrl { url: seed.join("/about").unwrap(), depth: 1 };
+/// coordinator.report("worker-1", lease.id, vec![result], vec![about], true, now).unwrap();
+/// assert_eq!(coordinator.take_results().len(), 1);
+/// assert_eq!(coordinator.queued(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Coordinator {
+    lease_ttl: Duration,
+    max_depth: Option<u32>,
+    ring: Option<HashRing>,
+    steal_after: Option<Duration>,
+    grace_period: Option<Duration>,
+    /// When each worker was last heard from.
+    last_seen: HashMap<String, Instant>,
+    lost: Vec<LostWorker>,
+    /// When an idle worker first passed over a ready host of another worker, by host.
+    passed_over: HashMap<String, Instant>,
+    seen: HashSet<String>,
+    queues: HashMap<String, VecDeque<QueuedUrl>>,
+    /// The hosts with queued URLs that aren't leased, in the order they get their turn.
+    ready: VecDeque<String>,
+    leases: HashMap<u64, ActiveLease>,
+    next_lease: u64,
+    results: Vec<FetchAttempt>,
+}
+
+impl Default for Coordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coordinator {
+    /// An empty frontier with leases lasting 5 minutes and no depth limit.
+    pub fn new() -> Self {
+        Self {
+            lease_ttl: Duration::from_secs(300),
+            max_depth: None,
+            ring: None,
+            steal_after: None,
+            grace_period: None,
+            last_seen: HashMap::new(),
+            lost: Vec::new(),
+            passed_over: HashMap::new(),
+            seen: HashSet::new(),
+            queues: HashMap::new(),
+            ready: VecDeque::new(),
+            leases: HashMap::new(),
+            next_lease: 1,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn lease_ttl(mut self, lease_ttl: Duration) -> Self {
+        self.lease_ttl = lease_ttl;
+        self
+    }
+
+    /// Ignores discovered URLs deeper than this.
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Leases each host only to the worker it belongs to on a hash ring, so the host's
+    /// politeness state stays on one worker. Workers join the ring when they first ask for work
+    /// and leave it with [`remove_worker`](Self::remove_worker).
+    pub fn ring(mut self, ring: HashRing) -> Self {
+        self.ring = Some(ring);
+        self
+    }
+
+    /// Lets an idle worker take a ready host of another worker on the hash ring when idle
+    /// workers have been passing it over for this long, i.e. when its owner is too busy with
+    /// other hosts to lease it. The host returns to its owner once the lease ends, and is still
+    /// leased to one worker at a time.
+    pub fn steal_after(mut self, steal_after: Duration) -> Self {
+        self.steal_after = Some(steal_after);
+        self
+    }
+
+    /// Reclaims the leases of workers that weren't heard from for this long, to be leased to
+    /// other workers. It should span a few heartbeats, so a slow network doesn't cost a worker
+    /// its leases.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Queues a URL, returning false when it was seen before, is too deep or has no host.
+    pub fn add(&mut self, url: Url, depth: u32) -> bool {
+        if self.max_depth.is_some_and(|max| depth > max) {
+            return false;
+        }
+        let Some(host) = url.host_str().map(str::to_string) else {
+            return false;
+        };
+        let mut key = url.clone();
+        key.set_fragment(None);
+        if !self.seen.insert(key.into()) {
+            return false;
+        }
+
+        let queue = self.queues.entry(host.clone()).or_default();
+        queue.push_back(QueuedUrl { url, depth });
+        if queue.len() == 1 && !self.is_leased(&host) {
+            self.ready.push_back(host);
+        }
+        true
+    }
+
+    /// Leases up to `max_hosts` hosts to a worker, with up to `max_urls` of each host's URLs,
+    /// after expiring the leases that ran out by `now`.
+    pub fn lease(
+        &mut self,
+        worker: &str,
+        max_hosts: usize,
+        max_urls: usize,
+        now: Instant,
+    ) -> Vec<WorkLease> {
+        self.last_seen.insert(worker.to_string(), now);
+        self.expire(now);
+        if let Some(ring) = &mut self.ring {
+            ring.add(worker);
+        }
+
+        let mut hosts = Vec::new();
+        let mut skipped = Vec::new();
+        while hosts.len() < max_hosts.max(1) {
+            let Some(host) = self.ready.pop_front() else {
+                break;
+            };
+            match &self.ring {
+                Some(ring) if ring.owner(&host) != Some(worker) => skipped.push(host),
+                _ => hosts.push(host),
+            }
+        }
+        let mut stolen = Vec::new();
+        if let Some(steal_after) = self.steal_after.filter(|_| hosts.is_empty()) {
+            skipped.retain(|host| {
+                let since = *self.passed_over.entry(host.clone()).or_insert(now);
+                let steal = stolen.len() < max_hosts.max(1)
+                    && now.saturating_duration_since(since) >= steal_after;
+                if steal {
+                    stolen.push(host.clone());
+                }
+                !steal
+            });
+        }
+        // Hosts of other workers keep their turn.
+        for host in skipped.into_iter().rev() {
+            self.ready.push_front(host);
+        }
+
+        let hosts = hosts.into_iter().map(|host| (host, false));
+        let stolen = stolen.into_iter().map(|host| (host, true));
+        hosts
+            .chain(stolen)
+            .map(|(host, stolen)| self.grant(worker, host, max_urls, stolen, now))
+            .collect()
+    }
+
+    /// Removes a worker that left, ending its leases so their URLs can be leased again, and
+    /// handing its hosts to other workers when assigning by hash. Returns how many leases it
+    /// had.
+    pub fn remove_worker(&mut self, worker: &str) -> usize {
+        self.last_seen.remove(worker);
+        if let Some(ring) = &mut self.ring {
+            ring.remove(worker);
+        }
+        let leases = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.worker == worker)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in &leases {
+            self.end(*id);
+        }
+        leases.len()
+    }
+
+    /// Records that a worker is alive, returning the leases it holds. Leases it thinks it holds
+    /// but that aren't listed were reclaimed, and must not be fetched further.
+    pub fn heartbeat(&mut self, worker: &str, now: Instant) -> Vec<u64> {
+        self.last_seen.insert(worker.to_string(), now);
+        let mut leases = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.worker == worker)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        leases.sort_unstable();
+        leases
+    }
+
+    /// The workers heard from, most recently heard from first.
+    pub fn workers(&self, now: Instant) -> Vec<WorkerHealth> {
+        let mut workers = self
+            .last_seen
+            .iter()
+            .map(|(worker, seen)| WorkerHealth {
+                worker: worker.clone(),
+                silent_for: now.saturating_duration_since(*seen),
+                leases: self
+                    .leases
+                    .values()
+                    .filter(|lease| lease.worker == *worker)
+                    .count(),
+            })
+            .collect::<Vec<_>>();
+        workers.sort_by(|a, b| {
+            a.silent_for
+                .cmp(&b.silent_for)
+                .then_with(|| a.worker.cmp(&b.worker))
+        });
+        workers
+    }
+
+    /// The workers lost since the last call, in the order they were lost.
+    pub fn take_lost(&mut self) -> Vec<LostWorker> {
+        std::mem::take(&mut self.lost)
+    }
+
+    /// Extends a lease by the lease TTL from `now`.
+    pub fn renew(
+        &mut self,
+        worker: &str,
+        lease: u64,
+        now: Instant,
+    ) -> Result<Duration, LeaseError> {
+        self.last_seen.insert(worker.to_string(), now);
+        let ttl = self.lease_ttl;
+        self.active(worker, lease)?.expires_at = now + ttl;
+        Ok(ttl)
+    }
+
+    /// Records the results of leased URLs and queues the URLs discovered on them, returning
+    /// how many of those were new.
+    ///
+    /// The lease is extended, and ends once every URL was reported or when `done` is set; its
+    /// unreported URLs are then queued again.
+    pub fn report(
+        &mut self,
+        worker: &str,
+        lease: u64,
+        results: Vec<UrlResult>,
+        discovered: Vec<QueuedUrl>,
+        done: bool,
+        now: Instant,
+    ) -> Result<usize, LeaseError> {
+        self.last_seen.insert(worker.to_string(), now);
+        let ttl = self.lease_ttl;
+        let active = self.active(worker, lease)?;
+        active.expires_at = now + ttl;
+        for result in &results {
+            active.pending.retain(|queued| queued.url != result.url);
+        }
+        let finished = done || active.pending.is_empty();
+        self.results.extend(results.into_iter().map(|result| {
+            match (result.status, result.error) {
+                (Some(status), _) => FetchAttempt::response(result.url, Utc::now(), status),
+                (None, error) => FetchAttempt::failure(
+                    result.url,
+                    Utc::now(),
+                    error.unwrap_or_else(|| "no response".to_string()),
+                ),
+            }
+        }));
+
+        let queued = discovered
+            .into_iter()
+            .filter(|queued| self.add(queued.url.clone(), queued.depth))
+            .count();
+        if finished {
+            self.end(lease);
+        }
+        Ok(queued)
+    }
+
+    /// Ends the leases that weren't renewed or reported on by `now`, and those of workers
+    /// silent for longer than the grace period, queueing their unreported URLs again. Returns
+    /// how many leases ended.
+    ///
+    /// It's called whenever a worker asks for work, but can be called periodically as well to
+    /// notice lost workers sooner.
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let mut reclaimed = 0;
+        if let Some(grace_period) = self.grace_period {
+            let silent = self
+                .last_seen
+                .iter()
+                .map(|(worker, seen)| (worker.clone(), now.saturating_duration_since(*seen)))
+                .filter(|(_, silent_for)| *silent_for > grace_period)
+                .collect::<Vec<_>>();
+            for (worker, silent_for) in silent {
+                let leases = self.remove_worker(&worker);
+                tracing::warn!(
+                    worker,
+                    silent_ms = silent_for.as_millis() as u64,
+                    reclaimed = leases,
+                    "worker went silent, its leases were reclaimed"
+                );
+                self.lost.push(LostWorker {
+                    worker,
+                    at: Utc::now(),
+                    silent_for,
+                    reclaimed: leases,
+                });
+                reclaimed += leases;
+            }
+        }
+
+        let expired = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.expires_at <= now)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in &expired {
+            self.end(*id);
+        }
+        reclaimed + expired.len()
+    }
+
+    /// The results reported since the last call, in the order they were reported.
+    pub fn take_results(&mut self) -> Vec<FetchAttempt> {
+        std::mem::take(&mut self.results)
+    }
+
+    /// URLs waiting to be leased.
+    pub fn queued(&self) -> usize {
+        self.queues.values().map(VecDeque::len).sum()
+    }
+
+    pub fn active_leases(&self) -> usize {
+        self.leases.len()
+    }
+
+    /// Whether there's nothing left to lease and no lease that could add to it.
+    pub fn is_finished(&self) -> bool {
+        self.queues.is_empty() && self.leases.is_empty()
+    }
+
+    /// Leases up to `max_urls` of a ready host's URLs to a worker.
+    fn grant(
+        &mut self,
+        worker: &str,
+        host: String,
+        max_urls: usize,
+        stolen: bool,
+        now: Instant,
+    ) -> WorkLease {
+        self.passed_over.remove(&host);
+        // Unwrapping is safe here because ready hosts always have a queue.
+        let queue = self.queues.get_mut(&host).unwrap();
+        let take = queue.len().min(max_urls.max(1));
+        let urls = queue.drain(..take).collect::<Vec<_>>();
+        if queue.is_empty() {
+            self.queues.remove(&host);
+        }
+
+        let id = self.next_lease;
+        self.next_lease += 1;
+        self.leases.insert(
+            id,
+            ActiveLease {
+                worker: worker.to_string(),
+                host: host.clone(),
+                pending: urls.clone(),
+                expires_at: now + self.lease_ttl,
+            },
+        );
+        WorkLease {
+            id,
+            host,
+            urls,
+            ttl: self.lease_ttl,
+            stolen,
+        }
+    }
+
+    fn is_leased(&self, host: &str) -> bool {
+        self.leases.values().any(|lease| lease.host == host)
+    }
+
+    fn active(&mut self, worker: &str, lease: u64) -> Result<&mut ActiveLease, LeaseError> {
+        match self.leases.get_mut(&lease) {
+            Some(active) if active.worker == worker => Ok(active),
+            Some(_) => Err(LeaseError::NotOwner {
+                lease,
+                worker: worker.to_string(),
+            }),
+            None => Err(LeaseError::Unknown(lease)),
+        }
+    }
+
+    /// Ends a lease, putting its unreported URLs back at the front of the host's queue.
+    fn end(&mut self, lease: u64) {
+        let Some(lease) = self.leases.remove(&lease) else {
+            return;
+        };
+        let queue = self.queues.entry(lease.host.clone()).or_default();
+        for queued in lease.pending.into_iter().rev() {
+            queue.push_front(queued);
+        }
+        if queue.is_empty() {
+            self.queues.remove(&lease.host);
+        } else {
+            self.ready.push_back(lease.host);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn leases_hosts_in_turn_and_requeues_expired_work() {
+        let mut coordinator = Coordinator::new()
+            .lease_ttl(Duration::from_secs(10))
+            .max_depth(1);
+        let now = Instant::now();
+        for page in [
+            "https://a.example/1",
+            "https://a.example/2",
+            "https://b.example/",
+        ] {
+            assert!(coordinator.add(url(page), 0));
+        }
+        assert!(!coordinator.add(url("https://a.example/1#top"), 0));
+
+        let leases = coordinator.lease("one", 2, 1, now);
+        let hosts = leases
+            .iter()
+            .map(|lease| lease.host.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(hosts, ["a.example", "b.example"]);
+        assert!(coordinator.lease("two", 5, 5, now).is_empty());
+
+        // Another worker can't report on the lease.
+        let a = leases[0].id;
+        assert_eq!(
+            coordinator.report("two", a, Vec::new(), Vec::new(), false, now),
+            Err(LeaseError::NotOwner {
+                lease: a,
+                worker: "two".to_string()
+            })
+        );
+
+        let b = leases[1].id;
+        let discovered = vec![
+            QueuedUrl {
+                url: url("https://b.example/next"),
+                depth: 1,
+            },
+            QueuedUrl {
+                url: url("https://b.example/too/deep"),
+                depth: 2,
+            },
+        ];
+        let later = now + Duration::from_secs(8);
+        coordinator.renew("one", a, later).unwrap();
+        let result = UrlResult {
+            url: url("https://b.example/"),
+            status: Some(200),
+            error: None,
+        };
+        assert_eq!(
+            coordinator.report("one", b, vec![result], discovered, false, later),
+            Ok(1)
+        );
+
+        // Only the renewed lease survives; b's lease ended as all of its URLs were reported.
+        assert_eq!(coordinator.expire(now + Duration::from_secs(12)), 0);
+        assert_eq!(coordinator.active_leases(), 1);
+        let leases = coordinator.lease("two", 5, 5, now + Duration::from_secs(20));
+        let urls = leases
+            .iter()
+            .flat_map(|lease| lease.urls.iter().map(|queued| queued.url.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://b.example/next",
+                "https://a.example/1",
+                "https://a.example/2"
+            ]
+        );
+        assert_eq!(
+            coordinator.report("one", a, Vec::new(), Vec::new(), true, now),
+            Err(LeaseError::Unknown(a))
+        );
+
+        let results = coordinator.take_results();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, Some(200));
+        assert!(!coordinator.is_finished());
+    }
+
+    #[test]
+    fn leases_hosts_to_their_worker_on_the_ring() {
+        let mut ring = HashRing::new();
+        ring.add("one");
+        ring.add("two");
+        let mut coordinator = Coordinator::new().ring(ring.clone());
+        let now = Instant::now();
+        let hosts = (0..20)
+            .map(|n| format!("host-{n}.example"))
+            .collect::<Vec<_>>();
+        for host in &hosts {
+            coordinator.add(url(&format!("https://{host}/")), 0);
+        }
+
+        let leased = coordinator.lease("one", 20, 1, now);
+        assert!(!leased.is_empty());
+        assert!(leased
+            .iter()
+            .all(|lease| ring.owner(&lease.host) == Some("one")));
+        // Once "one" leaves, "two" owns every host, including those "one" had leased.
+        assert_eq!(coordinator.remove_worker("one"), leased.len());
+        assert_eq!(coordinator.lease("two", 20, 1, now).len(), hosts.len());
+    }
+
+    #[test]
+    fn lends_hosts_of_busy_workers_to_idle_ones() {
+        let mut ring = HashRing::new();
+        ring.add("busy");
+        ring.add("idle");
+        let mut coordinator = Coordinator::new()
+            .ring(ring.clone())
+            .steal_after(Duration::from_secs(30));
+        let now = Instant::now();
+        let hosts = (0..20).map(|n| format!("host-{n}.example"));
+        let busy = hosts
+            .filter(|host| ring.owner(host) == Some("busy"))
+            .take(2)
+            .collect::<Vec<_>>();
+        for host in &busy {
+            coordinator.add(url(&format!("https://{host}/")), 0);
+        }
+
+        assert_eq!(coordinator.lease("busy", 1, 1, now)[0].host, busy[0]);
+        assert!(coordinator.lease("idle", 5, 1, now).is_empty());
+        let lent = coordinator.lease("idle", 5, 1, now + Duration::from_secs(30));
+        assert_eq!(lent.len(), 1);
+        assert_eq!(lent[0].host, busy[1]);
+        assert!(lent[0].stolen);
+
+        // Once returned, the host goes back to its owner.
+        let later = now + Duration::from_secs(31);
+        coordinator.add(url(&format!("https://{}/next", busy[1])), 1);
+        coordinator
+            .report("idle", lent[0].id, Vec::new(), Vec::new(), true, later)
+            .unwrap();
+        assert!(coordinator.lease("idle", 5, 1, later).is_empty());
+        let returned = coordinator.lease("busy", 5, 5, later);
+        assert_eq!(returned[0].host, busy[1]);
+        assert!(!returned[0].stolen);
+    }
+
+    #[test]
+    fn reclaims_the_leases_of_silent_workers() {
+        let mut coordinator = Coordinator::new()
+            .lease_ttl(Duration::from_secs(600))
+            .grace_period(Duration::from_secs(30));
+        let now = Instant::now();
+        coordinator.add(url("https://a.example/"), 0);
+        coordinator.add(url("https://b.example/"), 0);
+        let lease = coordinator.lease("crashed", 1, 1, now).remove(0);
+        coordinator.lease("alive", 1, 1, now);
+
+        let later = now + Duration::from_secs(20);
+        assert_eq!(coordinator.heartbeat("crashed", later), [lease.id]);
+        let later = now + Duration::from_secs(40);
+        coordinator.heartbeat("alive", later);
+        let health = coordinator.workers(later);
+        assert_eq!(health[0].worker, "alive");
+        assert_eq!(health[1].silent_for, Duration::from_secs(20));
+
+        // Long before the lease would expire, its URL is leased to another worker.
+        let later = now + Duration::from_secs(55);
+        let reassigned = coordinator.lease("alive", 1, 1, later);
+        assert_eq!(reassigned[0].host, lease.host);
+        let lost = coordinator.take_lost();
+        assert_eq!(lost.len(), 1);
+        assert_eq!((lost[0].worker.as_str(), lost[0].reclaimed), ("crashed", 1));
+        assert!(coordinator.heartbeat("crashed", later).is_empty());
+        assert_eq!(
+            coordinator.renew("crashed", lease.id, later),
+            Err(LeaseError::Unknown(lease.id))
+        );
+    }
+}
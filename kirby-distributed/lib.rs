@@ -0,0 +1,16 @@
+// The gRPC methods fail with tonic's `Status`, which is large but rarely returned.
+#![allow(clippy::result_large_err)]
+
+pub mod coordinator;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod proto;
+#[cfg(feature = "redis")]
+pub mod ratelimit;
+pub mod ring;
+#[cfg(feature = "redis")]
+pub mod robots;
+#[cfg(feature = "redis")]
+pub mod seen;
+pub mod server;
+pub mod worker;
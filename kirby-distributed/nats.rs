@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_nats::jetstream::{self, consumer, stream};
+use kirby_core::export::Sink;
+use kirby_core::record::PageRecord;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+use tokio_stream::StreamExt;
+
+use crate::coordinator::QueuedUrl;
+use crate::ring::stable_hash;
+
+/// How many partitions URLs are spread over by default.
+pub const DEFAULT_PARTITIONS: u32 = 16;
+
+/// The partition of a host's URLs, the same in every process.
+pub fn partition(host: &str, partitions: u32) -> u32 {
+    let hash = stable_hash(host.as_bytes());
+    (hash % u64::from(partitions.max(1))) as u32
+}
+
+/// Groups URLs into one batch per partition, URLs without a host aside.
+fn batches(urls: &[QueuedUrl], partitions: u32) -> BTreeMap<u32, Vec<QueuedUrl>> {
+    let mut batches = BTreeMap::<u32, Vec<QueuedUrl>>::new();
+    for queued in urls {
+        if let Some(host) = queued.url.host_str() {
+            batches
+                .entry(partition(host, partitions))
+                .or_default()
+                .push(queued.clone());
+        }
+    }
+    batches
+}
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+    urls: Vec<QueuedUrl>,
+}
+
+/// Discovered URLs and crawled pages on a NATS JetStream stream, for crawls that are part of
+/// a streaming pipeline.
+///
+/// The bus has two streams, named after it like their subjects:
+///
+/// - `<name>` is a work queue of batches of URLs to crawl as JSON on `<name>.urls.<partition>`,
+///   partitioned by a hash of their host, so all of a host's URLs go to the worker consuming
+///   its partition.
+/// - `<name>-pages` keeps the [`PageRecord`]s of crawled pages as JSON on `<name>.pages`, e.g.
+///   for indexers.
+///
+/// Workers consume their partitions with a [`UrlConsumer`], publish the pages they crawl with
+/// [`Sink::write`] and the URLs they discover with [`publish_urls`](Self::publish_urls).
+/// Batches are removed once acknowledged, those of a worker that crashed are delivered again.
+/// A work queue hands each batch to a single consumer, so the partitions of the workers must
+/// not overlap.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use kirby_core::export::Sink;
+/// use kirby_distributed::coordinator::QueuedUrl;
+/// use kirby_distributed::nats::NatsBus;
+/// use url::Url;
+///
+/// let mut bus = NatsBus::connect("nats://localhost:4222", "kirby").unwrap();
+/// let seed = QueuedUrl { url: Url::parse("https://example.com/").unwrap(), depth: 0 };
+/// bus.publish_urls(&[seed]).unwrap();
+///
+/// // This worker crawls the first half of the partitions.
+/// let mut consumer = bus.consumer("worker-1", &(0..8).collect::<Vec<_>>()).unwrap();
+/// while let Some(batch) = consumer.next_batch(Duration::from_secs(5)).unwrap() {
+///     for queued in &batch.urls {
+///         // Fetch the URL, then `bus.write(..)` its page and `bus.publish_urls(..)` its links.
+///     }
+///     consumer.ack(batch).unwrap();
+/// }
+/// bus.finish().unwrap();
+/// ```
+pub struct NatsBus {
+    name: String,
+    partitions: u32,
+    runtime: Arc<Runtime>,
+    client: async_nats::Client,
+    jetstream: jetstream::Context,
+    stream: stream::Stream,
+}
+
+impl NatsBus {
+    /// Connects to a NATS server, creating the bus's streams when they don't exist.
+    ///
+    /// The name must be a valid stream name and subject token, e.g. `kirby`.
+    pub fn connect(server: &str, name: &str) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (client, jetstream, stream) = runtime.block_on(async {
+            let client = async_nats::connect(server)
+                .await
+                .map_err(io::Error::other)?;
+            let jetstream = jetstream::new(client.clone());
+            let stream = jetstream
+                .get_or_create_stream(stream::Config {
+                    name: name.to_string(),
+                    subjects: vec![format!("{name}.urls.*")],
+                    retention: stream::RetentionPolicy::WorkQueue,
+                    ..Default::default()
+                })
+                .await
+                .map_err(io::Error::other)?;
+            jetstream
+                .get_or_create_stream(stream::Config {
+                    name: format!("{name}-pages"),
+                    subjects: vec![format!("{name}.pages")],
+                    ..Default::default()
+                })
+                .await
+                .map_err(io::Error::other)?;
+            Ok::<_, io::Error>((client, jetstream, stream))
+        })?;
+
+        Ok(Self {
+            name: name.to_string(),
+            partitions: DEFAULT_PARTITIONS,
+            runtime: Arc::new(runtime),
+            client,
+            jetstream,
+            stream,
+        })
+    }
+
+    /// Spreads URLs over this many partitions instead of [`DEFAULT_PARTITIONS`]. Every
+    /// process of a crawl must use the same number.
+    pub fn partitions(mut self, partitions: u32) -> Self {
+        self.partitions = partitions.max(1);
+        self
+    }
+
+    /// Publishes URLs to crawl, one batch per partition, returning how many batches were
+    /// published.
+    pub fn publish_urls(&self, urls: &[QueuedUrl]) -> io::Result<usize> {
+        let batches = batches(urls, self.partitions);
+        self.runtime.block_on(async {
+            for (partition, urls) in &batches {
+                let payload = serde_json::to_vec(&Payload { urls: urls.clone() })?;
+                let subject = format!("{}.urls.{partition}", self.name);
+                self.publish(subject, payload).await?;
+            }
+            Ok(batches.len())
+        })
+    }
+
+    /// Consumes the URL batches of the given partitions, every partition when empty.
+    ///
+    /// Consumers are durable: a worker that comes back under the same name continues where it
+    /// left off.
+    pub fn consumer(&self, name: &str, partitions: &[u32]) -> io::Result<UrlConsumer> {
+        let filter_subjects = if partitions.is_empty() {
+            vec![format!("{}.urls.*", self.name)]
+        } else {
+            partitions
+                .iter()
+                .map(|partition| format!("{}.urls.{partition}", self.name))
+                .collect()
+        };
+        let config = consumer::pull::Config {
+            durable_name: Some(name.to_string()),
+            filter_subjects,
+            ack_policy: consumer::AckPolicy::Explicit,
+            ..Default::default()
+        };
+        let consumer = self
+            .runtime
+            .block_on(self.stream.get_or_create_consumer(name, config))
+            .map_err(io::Error::other)?;
+        Ok(UrlConsumer {
+            runtime: self.runtime.clone(),
+            consumer,
+        })
+    }
+
+    /// Waits for the stream to acknowledge a message.
+    async fn publish(&self, subject: String, payload: Vec<u8>) -> io::Result<()> {
+        self.jetstream
+            .publish(subject, payload.into())
+            .await
+            .map_err(io::Error::other)?
+            .await
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl Sink for NatsBus {
+    /// Publishes the page's record, bodies aren't published.
+    fn write(&mut self, record: &PageRecord, _: Option<&[u8]>) -> io::Result<()> {
+        let payload = serde_json::to_vec(record)?;
+        let subject = format!("{}.pages", self.name);
+        self.runtime.block_on(self.publish(subject, payload))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.runtime
+            .block_on(self.client.flush())
+            .map_err(io::Error::other)
+    }
+}
+
+/// URLs to crawl, as published together.
+pub struct UrlBatch {
+    pub partition: u32,
+    pub urls: Vec<QueuedUrl>,
+    message: jetstream::Message,
+}
+
+/// A worker's durable subscription to URL batches, see [`NatsBus::consumer`].
+pub struct UrlConsumer {
+    runtime: Arc<Runtime>,
+    consumer: consumer::Consumer<consumer::pull::Config>,
+}
+
+impl UrlConsumer {
+    /// Waits up to `wait` for the next batch, `None` when none arrived in time.
+    ///
+    /// A batch that isn't [acknowledged](Self::ack) within the stream's ack wait, 30 seconds
+    /// by default, is delivered again.
+    pub fn next_batch(&mut self, wait: Duration) -> io::Result<Option<UrlBatch>> {
+        self.runtime.block_on(async {
+            let mut messages = self
+                .consumer
+                .fetch()
+                .max_messages(1)
+                .expires(wait)
+                .messages()
+                .await
+                .map_err(io::Error::other)?;
+            let Some(message) = messages.next().await else {
+                return Ok(None);
+            };
+            let message = message.map_err(io::Error::other)?;
+
+            let partition = message
+                .subject
+                .rsplit('.')
+                .next()
+                .and_then(|partition| partition.parse().ok())
+                .unwrap_or_default();
+            let payload = serde_json::from_slice::<Payload>(&message.payload)?;
+            Ok(Some(UrlBatch {
+                partition,
+                urls: payload.urls,
+                message,
+            }))
+        })
+    }
+
+    /// Marks a batch as crawled, waiting for the stream to remove it.
+    pub fn ack(&mut self, batch: UrlBatch) -> io::Result<()> {
+        self.runtime
+            .block_on(batch.message.double_ack())
+            .map_err(io::Error::other)
+    }
+}
+
+/// The server `KIRBY_TEST_NATS` points to and a bus name of its own for a test.
+///
+/// The tests using it need a server, so they're ignored unless run with `--ignored`.
+#[cfg(test)]
+fn test_nats() -> (String, String) {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let server = std::env::var("KIRBY_TEST_NATS")
+        .expect("KIRBY_TEST_NATS is the URL of the server to test with");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    (server, format!("kirby-test-{}-{nanos}", std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use url::Url;
+
+    use super::*;
+
+    /// How many messages a stream of the bus holds.
+    fn messages(bus: &NatsBus, stream: &str) -> u64 {
+        bus.runtime.block_on(async {
+            let mut stream = bus.jetstream.get_stream(stream).await.unwrap();
+            stream.info().await.unwrap().state.messages
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a NATS server with JetStream in KIRBY_TEST_NATS"]
+    fn removes_acknowledged_batches() {
+        let (server, name) = test_nats();
+        let mut bus = NatsBus::connect(&server, &name).unwrap().partitions(1);
+        let url = Url::parse("https://example.com/").unwrap();
+        let seeds = vec![QueuedUrl {
+            url: url.clone(),
+            depth: 0,
+        }];
+        assert_eq!(bus.publish_urls(&seeds).unwrap(), 1);
+        let record = PageRecord::new(url, 200, Utc::now());
+        bus.write(&record, None).unwrap();
+        bus.finish().unwrap();
+
+        let mut consumer = bus.consumer("worker", &[]).unwrap();
+        let batch = consumer
+            .next_batch(Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!((batch.partition, &batch.urls), (0, &seeds));
+        consumer.ack(batch).unwrap();
+
+        assert_eq!(messages(&bus, &name), 0);
+        assert!(consumer
+            .next_batch(Duration::from_millis(500))
+            .unwrap()
+            .is_none());
+        // Pages stay for whoever reads them.
+        assert_eq!(messages(&bus, &format!("{name}-pages")), 1);
+    }
+
+    #[test]
+    fn batches_urls_by_host_partition() {
+        assert_eq!(partition("example.com", 16), partition("example.com", 16));
+        assert_eq!(partition("example.com", 0), 0);
+        assert!((0..100).all(|n| partition(&format!("host-{n}.example"), 16) < 16));
+
+        let queued = |url: &str| QueuedUrl {
+            url: Url::parse(url).unwrap(),
+            depth: 1,
+        };
+        let urls = [
+            queued("https://a.example/1"),
+            queued("https://b.example/"),
+            queued("https://a.example/2"),
+            queued("data:text/plain,no-host"),
+        ];
+        let batches = batches(&urls, 1024);
+        let a = &batches[&partition("a.example", 1024)];
+        assert_eq!(a, &[urls[0].clone(), urls[2].clone()]);
+        assert_eq!(batches.values().map(Vec::len).sum::<usize>(), 3);
+    }
+}
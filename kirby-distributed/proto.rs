@@ -0,0 +1,208 @@
+//! The messages of `proto/distributed.proto`, written out so building needs no `protoc`.
+
+use std::time::Duration;
+
+use tonic::Status;
+use url::Url;
+
+use crate::coordinator::{self, LeaseError, WorkLease};
+
+/// The path of the `Lease` method.
+pub const LEASE_PATH: &str = "/kirby.distributed.v1.Coordinator/Lease";
+/// The path of the `Renew` method.
+pub const RENEW_PATH: &str = "/kirby.distributed.v1.Coordinator/Renew";
+/// The path of the `Report` method.
+pub const REPORT_PATH: &str = "/kirby.distributed.v1.Coordinator/Report";
+/// The path of the `Heartbeat` method.
+pub const HEARTBEAT_PATH: &str = "/kirby.distributed.v1.Coordinator/Heartbeat";
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LeaseRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+    /// At most this many hosts, at least 1.
+    #[prost(uint32, tag = "2")]
+    pub max_hosts: u32,
+    /// At most this many URLs per host, at least 1.
+    #[prost(uint32, tag = "3")]
+    pub max_urls: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LeaseResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub leases: Vec<Lease>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Lease {
+    #[prost(uint64, tag = "1")]
+    pub lease_id: u64,
+    #[prost(string, tag = "2")]
+    pub host: String,
+    #[prost(message, repeated, tag = "3")]
+    pub urls: Vec<QueuedUrl>,
+    #[prost(uint64, tag = "4")]
+    pub ttl_ms: u64,
+    #[prost(bool, tag = "5")]
+    pub stolen: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueuedUrl {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(uint32, tag = "2")]
+    pub depth: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RenewRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+    #[prost(uint64, tag = "2")]
+    pub lease_id: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RenewResponse {
+    #[prost(uint64, tag = "1")]
+    pub ttl_ms: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReportRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+    #[prost(uint64, tag = "2")]
+    pub lease_id: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub results: Vec<UrlResult>,
+    #[prost(message, repeated, tag = "4")]
+    pub discovered: Vec<QueuedUrl>,
+    /// Ends the lease, returning its unreported URLs to the queue.
+    #[prost(bool, tag = "5")]
+    pub done: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UrlResult {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(uint32, optional, tag = "2")]
+    pub status: Option<u32>,
+    #[prost(string, optional, tag = "3")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReportResponse {
+    /// How many of the discovered URLs were new.
+    #[prost(uint32, tag = "1")]
+    pub queued: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HeartbeatRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HeartbeatResponse {
+    /// The leases the worker holds, the others it had were reclaimed.
+    #[prost(uint64, repeated, tag = "1")]
+    pub lease_ids: Vec<u64>,
+}
+
+fn parse_url(url: &str) -> Result<Url, Status> {
+    Url::parse(url).map_err(|error| Status::invalid_argument(format!("{url}: {error}")))
+}
+
+impl From<&coordinator::QueuedUrl> for QueuedUrl {
+    fn from(queued: &coordinator::QueuedUrl) -> Self {
+        Self {
+            url: queued.url.to_string(),
+            depth: queued.depth,
+        }
+    }
+}
+
+impl TryFrom<QueuedUrl> for coordinator::QueuedUrl {
+    type Error = Status;
+
+    fn try_from(queued: QueuedUrl) -> Result<Self, Status> {
+        Ok(Self {
+            url: parse_url(&queued.url)?,
+            depth: queued.depth,
+        })
+    }
+}
+
+impl From<&coordinator::UrlResult> for UrlResult {
+    fn from(result: &coordinator::UrlResult) -> Self {
+        Self {
+            url: result.url.to_string(),
+            status: result.status.map(u32::from),
+            error: result.error.clone(),
+        }
+    }
+}
+
+impl TryFrom<UrlResult> for coordinator::UrlResult {
+    type Error = Status;
+
+    fn try_from(result: UrlResult) -> Result<Self, Status> {
+        let status = result
+            .status
+            .map(|status| {
+                u16::try_from(status)
+                    .map_err(|_| Status::invalid_argument(format!("invalid status {status}")))
+            })
+            .transpose()?;
+        Ok(Self {
+            url: parse_url(&result.url)?,
+            status,
+            error: result.error,
+        })
+    }
+}
+
+impl From<&WorkLease> for Lease {
+    fn from(lease: &WorkLease) -> Self {
+        Self {
+            lease_id: lease.id,
+            host: lease.host.clone(),
+            urls: lease.urls.iter().map(QueuedUrl::from).collect(),
+            ttl_ms: lease.ttl.as_millis() as u64,
+            stolen: lease.stolen,
+        }
+    }
+}
+
+impl TryFrom<Lease> for WorkLease {
+    type Error = Status;
+
+    fn try_from(lease: Lease) -> Result<Self, Status> {
+        Ok(Self {
+            id: lease.lease_id,
+            host: lease.host,
+            urls: lease
+                .urls
+                .into_iter()
+                .map(coordinator::QueuedUrl::try_from)
+                .collect::<Result<_, _>>()?,
+            ttl: Duration::from_millis(lease.ttl_ms),
+            stolen: lease.stolen,
+        })
+    }
+}
+
+impl From<LeaseError> for Status {
+    fn from(error: LeaseError) -> Self {
+        match error {
+            LeaseError::Unknown(_) => Status::not_found(error.to_string()),
+            LeaseError::NotOwner { .. } => Status::permission_denied(error.to_string()),
+        }
+    }
+}
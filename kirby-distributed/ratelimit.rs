@@ -0,0 +1,198 @@
+use std::thread;
+use std::time::Duration;
+
+use redis::{Connection, RedisResult, Script};
+
+/// A request rate: `requests` per `per`, with bursts of up to `burst` requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub requests: u32,
+    pub per: Duration,
+    pub burst: u32,
+}
+
+impl Rate {
+    /// `requests` per `per`, without bursts.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            requests: requests.max(1),
+            per,
+            burst: 1,
+        }
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// The time between requests, in microseconds.
+    fn interval(&self) -> u64 {
+        (self.per.as_micros() / u128::from(self.requests)) as u64
+    }
+
+    /// How far ahead of schedule a burst may get, in microseconds.
+    fn tolerance(&self) -> u64 {
+        self.interval() * u64::from(self.burst - 1)
+    }
+}
+
+/// Checks every bucket of a request with the generic cell rate algorithm, taking a cell from
+/// each only when all of them allow it. Returns 0 when allowed, else how many microseconds to
+/// wait. Each key holds its bucket's theoretical arrival time, in microseconds of the server's
+/// clock so every worker agrees on the time.
+const ACQUIRE: &str = r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local wait = 0
+local arrivals = {}
+for i, key in ipairs(KEYS) do
+  local interval = tonumber(ARGV[2 * i - 1])
+  local tolerance = tonumber(ARGV[2 * i])
+  local arrival = math.max(tonumber(redis.call('GET', key) or now), now)
+  wait = math.max(wait, arrival - tolerance - now)
+  arrivals[i] = arrival + interval
+end
+if wait > 0 then
+  return wait
+end
+for i, key in ipairs(KEYS) do
+  local ttl = math.ceil((arrivals[i] - now) / 1000) + 1
+  redis.call('SET', key, string.format('%.0f', arrivals[i]), 'PX', ttl)
+end
+return 0
+";
+
+/// Rate limits that hold across every worker of a crawl, kept in Redis.
+///
+/// A request to a host counts against the crawl-wide limit and the host's limit alike, and
+/// is only allowed when both have room, so the fleet's aggregate rate to any host stays within
+/// policy however many workers there are. Limits are enforced with the generic cell rate
+/// algorithm, a token bucket that needs a single key per bucket, in one script per request.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use kirby_distributed::ratelimit::{Rate, RedisRateLimiter};
+///
+/// let mut limiter = RedisRateLimiter::connect("redis://127.0.0.1/", "kirby")
+///     .unwrap()
+///     .global(Rate::new(200, Duration::from_secs(1)))
+///     .per_host(Rate::new(1, Duration::from_secs(1)).burst(2))
+///     .host("api.example.com", Rate::new(10, Duration::from_secs(60)));
+///
+/// limiter.acquire("example.com").unwrap();
+/// // Fetch a page of example.com.
+/// ```
+pub struct RedisRateLimiter {
+    connection: Connection,
+    name: String,
+    global: Option<Rate>,
+    per_host: Option<Rate>,
+    hosts: Vec<(String, Rate)>,
+    script: Script,
+}
+
+impl RedisRateLimiter {
+    /// Connects to Redis at `url`, using the keys of the crawl called `name`. Without limits
+    /// every request is allowed.
+    pub fn connect(url: &str, name: &str) -> RedisResult<Self> {
+        Ok(Self {
+            connection: redis::Client::open(url)?.get_connection()?,
+            name: name.to_string(),
+            global: None,
+            per_host: None,
+            hosts: Vec::new(),
+            script: Script::new(ACQUIRE),
+        })
+    }
+
+    /// Limits requests to all hosts together.
+    pub fn global(mut self, rate: Rate) -> Self {
+        self.global = Some(rate);
+        self
+    }
+
+    /// Limits requests to each host without a limit of its own.
+    pub fn per_host(mut self, rate: Rate) -> Self {
+        self.per_host = Some(rate);
+        self
+    }
+
+    /// Limits requests to one host.
+    pub fn host(mut self, host: &str, rate: Rate) -> Self {
+        self.hosts.push((host.to_ascii_lowercase(), rate));
+        self
+    }
+
+    /// Takes a request to `host` off its limits, or returns how long to wait before trying
+    /// again when a limit has no room. Nothing is taken when it has to wait.
+    pub fn try_acquire(&mut self, host: &str) -> RedisResult<Option<Duration>> {
+        let host = host.to_ascii_lowercase();
+        let host_rate = self
+            .hosts
+            .iter()
+            .find(|(limited, _)| *limited == host)
+            .map(|(_, rate)| *rate)
+            .or(self.per_host);
+        let buckets = [
+            self.global
+                .map(|rate| (format!("{}:rate", self.name), rate)),
+            host_rate.map(|rate| (format!("{}:rate:{host}", self.name), rate)),
+        ];
+
+        let mut invocation = self.script.prepare_invoke();
+        let mut limited = false;
+        for (key, rate) in buckets.iter().flatten() {
+            invocation
+                .key(key)
+                .arg(rate.interval())
+                .arg(rate.tolerance());
+            limited = true;
+        }
+        if !limited {
+            return Ok(None);
+        }
+        let wait: u64 = invocation.invoke(&mut self.connection)?;
+        Ok((wait > 0).then(|| Duration::from_micros(wait)))
+    }
+
+    /// Waits until a request to `host` fits within its limits, then takes it off them.
+    pub fn acquire(&mut self, host: &str) -> RedisResult<()> {
+        while let Some(wait) = self.try_acquire(host)? {
+            thread::sleep(wait);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seen::test_redis;
+
+    #[test]
+    #[ignore = "needs a Redis server in KIRBY_TEST_REDIS"]
+    fn limits_hosts_across_workers() {
+        let (server, name) = test_redis();
+        let limiter = || {
+            RedisRateLimiter::connect(&server, &name)
+                .unwrap()
+                .per_host(Rate::new(1, Duration::from_secs(60)).burst(2))
+                .host("fast.example", Rate::new(100, Duration::from_secs(1)))
+        };
+        let (mut one, mut two) = (limiter(), limiter());
+
+        assert_eq!(one.try_acquire("example.com").unwrap(), None);
+        assert_eq!(two.try_acquire("EXAMPLE.com").unwrap(), None);
+        let wait = one.try_acquire("example.com").unwrap().unwrap();
+        assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+        assert!(two.try_acquire("example.com").unwrap().is_some());
+
+        assert_eq!(two.try_acquire("other.example").unwrap(), None);
+        one.acquire("fast.example").unwrap();
+        two.acquire("fast.example").unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How many points each worker has on a ring by default.
+pub const DEFAULT_REPLICAS: u32 = 128;
+
+/// A 64-bit hash that is the same in every process and release, unlike std's hashers.
+/// FNV-1a followed by the SplitMix64 finalizer to spread the bits.
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Assigns hosts to workers with consistent hashing, so a host's politeness state lives on
+/// exactly one worker.
+///
+/// Each worker is hashed onto a ring at several points and a host belongs to the first worker
+/// point after the host's hash. When a worker joins or leaves, only the hosts between its
+/// points and the previous ones change hands, about one in `workers` of them, and every other
+/// host stays where its state already is.
+///
+/// # Example
+///
+/// ```
+/// use kirby_distributed::ring::HashRing;
+///
+/// let mut ring = HashRing::new();
+/// ring.add("worker-1");
+/// ring.add("worker-2");
+/// let owner = ring.owner("example.com").unwrap().to_string();
+///
+/// ring.add("worker-3");
+/// let moved = ring.owner("example.com").unwrap();
+/// assert!(moved == owner || moved == "worker-3");
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashRing {
+    replicas: u32,
+    points: BTreeMap<u64, String>,
+    workers: BTreeSet<String>,
+}
+
+impl Default for HashRing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashRing {
+    /// An empty ring with [`DEFAULT_REPLICAS`] points per worker.
+    pub fn new() -> Self {
+        Self::with_replicas(DEFAULT_REPLICAS)
+    }
+
+    /// More points per worker spread hosts more evenly, at the cost of memory.
+    pub fn with_replicas(replicas: u32) -> Self {
+        Self {
+            replicas: replicas.max(1),
+            points: BTreeMap::new(),
+            workers: BTreeSet::new(),
+        }
+    }
+
+    /// Adds a worker, returning false when it was already on the ring.
+    pub fn add(&mut self, worker: &str) -> bool {
+        if !self.workers.insert(worker.to_string()) {
+            return false;
+        }
+        for replica in 0..self.replicas {
+            let point = stable_hash(format!("{worker}#{replica}").as_bytes());
+            // On the rare collision the smaller name wins, so every ring agrees.
+            let owner = self
+                .points
+                .entry(point)
+                .or_insert_with(|| worker.to_string());
+            if worker < owner.as_str() {
+                *owner = worker.to_string();
+            }
+        }
+        true
+    }
+
+    /// Removes a worker, returning false when it wasn't on the ring.
+    pub fn remove(&mut self, worker: &str) -> bool {
+        if !self.workers.remove(worker) {
+            return false;
+        }
+        self.points.retain(|_, owner| owner != worker);
+        // Points it won from other workers go back to them.
+        let workers = std::mem::take(&mut self.workers);
+        for other in &workers {
+            for replica in 0..self.replicas {
+                let point = stable_hash(format!("{other}#{replica}").as_bytes());
+                self.points.entry(point).or_insert_with(|| other.clone());
+            }
+        }
+        self.workers = workers;
+        true
+    }
+
+    /// The worker a host belongs to, `None` when the ring is empty.
+    pub fn owner(&self, host: &str) -> Option<&str> {
+        let hash = stable_hash(host.as_bytes());
+        self.points
+            .range(hash..)
+            .next()
+            .or_else(|| self.points.iter().next())
+            .map(|(_, worker)| worker.as_str())
+    }
+
+    pub fn contains(&self, worker: &str) -> bool {
+        self.workers.contains(worker)
+    }
+
+    pub fn workers(&self) -> impl Iterator<Item = &str> {
+        self.workers.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_few_hosts_when_workers_change() {
+        let hosts = (0..1000)
+            .map(|n| format!("host-{n}.example"))
+            .collect::<Vec<_>>();
+        let mut ring = HashRing::new();
+        assert_eq!(ring.owner("example.com"), None);
+        for worker in ["a", "b", "c", "d"] {
+            ring.add(worker);
+        }
+        let owners = |ring: &HashRing| {
+            hosts
+                .iter()
+                .map(|host| ring.owner(host).unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        let before = owners(&ring);
+        for worker in ["a", "b", "c", "d"] {
+            let owned = before.iter().filter(|owner| *owner == worker).count();
+            assert!((150..350).contains(&owned), "{worker} owns {owned}");
+        }
+
+        ring.add("e");
+        let after = owners(&ring);
+        let moved = before.iter().zip(&after).filter(|(a, b)| a != b);
+        assert!(moved.clone().all(|(_, to)| to == "e"));
+        assert!((100..300).contains(&moved.count()));
+
+        // Leaving again hands the hosts back to their previous owners.
+        assert!(ring.remove("e"));
+        assert!(!ring.remove("e"));
+        assert_eq!(owners(&ring), before);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use redis::{Connection, RedisResult};
+
+/// How long a robots.txt is used before it's fetched again by default, as RFC 9309 suggests.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a worker uses its copy of a robots.txt before checking the shared one by default.
+pub const DEFAULT_LOCAL_TTL: Duration = Duration::from_secs(60);
+
+/// How long other workers wait for the worker fetching a robots.txt before fetching it too.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+struct Local {
+    body: Arc<str>,
+    expires_at: Instant,
+}
+
+/// robots.txt files shared by the workers of a crawl through Redis, so each host's file is
+/// fetched once per TTL for the whole cluster instead of once per worker.
+///
+/// Each worker keeps a copy of the files it used and checks the shared file again after the
+/// local TTL, so a file that was [invalidated](Self::invalidate) or refreshed by another worker
+/// is picked up within it. When a file is missing, one worker takes a lock and fetches it while
+/// the others wait for it to appear.
+///
+/// Files are stored as `<name>:robots:<host>`, and parsed with
+/// [`RobotsTxt::parse`](kirby_core::robotstxt::RobotsTxt::parse) by the caller.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_core::robotstxt::RobotsTxt;
+/// use kirby_distributed::robots::SharedRobotsCache;
+///
+/// let mut cache = SharedRobotsCache::connect("redis://127.0.0.1/", "kirby").unwrap();
+/// let body = cache
+///     .get_or_fetch("example.com", || {
+///         // Fetch https://example.com/robots.txt, an empty file when there's none.
+///         Ok("User-agent: *\nDisallow: /private/".to_string())
+///     })
+///     .unwrap();
+/// assert!(!RobotsTxt::parse(&body).is_allowed("KirbyBot", "/private/report"));
+/// ```
+pub struct SharedRobotsCache {
+    connection: Connection,
+    name: String,
+    ttl: Duration,
+    local_ttl: Duration,
+    local: HashMap<String, Local>,
+}
+
+impl SharedRobotsCache {
+    /// Connects to Redis at `url`, using the keys of the crawl called `name`.
+    pub fn connect(url: &str, name: &str) -> RedisResult<Self> {
+        Ok(Self {
+            connection: redis::Client::open(url)?.get_connection()?,
+            name: name.to_string(),
+            ttl: DEFAULT_TTL,
+            local_ttl: DEFAULT_LOCAL_TTL,
+            local: HashMap::new(),
+        })
+    }
+
+    /// Fetches files again after this long instead of [`DEFAULT_TTL`].
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Checks the shared file after this long instead of [`DEFAULT_LOCAL_TTL`].
+    pub fn local_ttl(mut self, local_ttl: Duration) -> Self {
+        self.local_ttl = local_ttl;
+        self
+    }
+
+    /// The robots.txt of a host, fetched with `fetch` when no worker has it.
+    ///
+    /// `fetch` should return the file's body, an empty one when the host has none. When it
+    /// fails nothing is stored, so the next call fetches again.
+    pub fn get_or_fetch(
+        &mut self,
+        host: &str,
+        fetch: impl FnOnce() -> io::Result<String>,
+    ) -> io::Result<Arc<str>> {
+        let host = host.to_ascii_lowercase();
+        let now = Instant::now();
+        if let Some(local) = self.local.get(&host).filter(|local| local.expires_at > now) {
+            return Ok(local.body.clone());
+        }
+
+        let key = format!("{}:robots:{host}", self.name);
+        let lock = format!("{}:robots-lock:{host}", self.name);
+        let waiting_since = Instant::now();
+        let (body, remaining) = loop {
+            if let Some(shared) = self.shared(&key).map_err(io::Error::other)? {
+                break shared;
+            }
+            let locked: bool = redis::cmd("SET")
+                .arg(&lock)
+                .arg(1)
+                .arg("NX")
+                .arg("PX")
+                .arg(FETCH_TIMEOUT.as_millis() as u64)
+                .query::<Option<String>>(&mut self.connection)
+                .map_err(io::Error::other)?
+                .is_some();
+            if locked || waiting_since.elapsed() > FETCH_TIMEOUT {
+                let fetched = fetch();
+                if let Ok(body) = &fetched {
+                    self.store(&key, body).map_err(io::Error::other)?;
+                }
+                if locked {
+                    redis::cmd("DEL")
+                        .arg(&lock)
+                        .query::<()>(&mut self.connection)
+                        .map_err(io::Error::other)?;
+                }
+                break (fetched?, self.ttl);
+            }
+            // Another worker is fetching it.
+            thread::sleep(Duration::from_millis(100));
+        };
+
+        let body = Arc::<str>::from(body);
+        self.local.insert(
+            host,
+            Local {
+                body: body.clone(),
+                expires_at: now + remaining.min(self.local_ttl),
+            },
+        );
+        Ok(body)
+    }
+
+    /// Stores a freshly fetched robots.txt for every worker, e.g. after fetching it again early.
+    pub fn refresh(&mut self, host: &str, body: &str) -> RedisResult<()> {
+        let host = host.to_ascii_lowercase();
+        self.store(&format!("{}:robots:{host}", self.name), body)?;
+        self.local.insert(
+            host,
+            Local {
+                body: body.into(),
+                expires_at: Instant::now() + self.ttl.min(self.local_ttl),
+            },
+        );
+        Ok(())
+    }
+
+    /// Forgets a host's robots.txt, so the next worker to need it fetches it again. Other
+    /// workers stop using their copies within the local TTL.
+    pub fn invalidate(&mut self, host: &str) -> RedisResult<()> {
+        let host = host.to_ascii_lowercase();
+        self.local.remove(&host);
+        redis::cmd("DEL")
+            .arg(format!("{}:robots:{host}", self.name))
+            .query(&mut self.connection)
+    }
+
+    /// The shared file and how long it has left.
+    fn shared(&mut self, key: &str) -> RedisResult<Option<(String, Duration)>> {
+        let (body, ttl): (Option<String>, i64) = redis::pipe()
+            .cmd("GET")
+            .arg(key)
+            .cmd("PTTL")
+            .arg(key)
+            .query(&mut self.connection)?;
+        Ok(body.map(|body| (body, Duration::from_millis(ttl.max(0) as u64))))
+    }
+
+    fn store(&mut self, key: &str, body: &str) -> RedisResult<()> {
+        redis::cmd("SET")
+            .arg(key)
+            .arg(body)
+            .arg("PX")
+            .arg(self.ttl.as_millis().max(1) as u64)
+            .query(&mut self.connection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seen::test_redis;
+
+    #[test]
+    #[ignore = "needs a Redis server in KIRBY_TEST_REDIS"]
+    fn fetches_once_for_every_worker() {
+        let (server, name) = test_redis();
+        let mut one = SharedRobotsCache::connect(&server, &name).unwrap();
+        let mut two = SharedRobotsCache::connect(&server, &name)
+            .unwrap()
+            .local_ttl(Duration::ZERO);
+
+        let body = one
+            .get_or_fetch("Example.com", || Ok("Disallow: /a".to_string()))
+            .unwrap();
+        assert_eq!(&*body, "Disallow: /a");
+        let body = two
+            .get_or_fetch("example.com", || panic!("fetched twice"))
+            .unwrap();
+        assert_eq!(&*body, "Disallow: /a");
+
+        // A failed fetch isn't stored.
+        assert!(one
+            .get_or_fetch("other.example", || Err(io::Error::other("timed out")))
+            .is_err());
+        let body = two.get_or_fetch("other.example", || Ok(String::new()));
+        assert_eq!(&*body.unwrap(), "");
+
+        one.refresh("example.com", "Disallow: /b").unwrap();
+        let body = two.get_or_fetch("example.com", || panic!("fetched again"));
+        assert_eq!(&*body.unwrap(), "Disallow: /b");
+        two.invalidate("example.com").unwrap();
+        let body = two.get_or_fetch("example.com", || Ok("Disallow: /c".to_string()));
+        assert_eq!(&*body.unwrap(), "Disallow: /c");
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{HashSet, VecDeque};
+
+use kirby_core::dedup::digest::{ContentDigest, DigestMatch};
+use redis::{Connection, RedisResult};
+use url::Url;
+
+/// How many seen URLs are remembered locally by default.
+pub const DEFAULT_CACHE_CAPACITY: usize = 100_000;
+
+/// A seen-set and digest index in Redis, shared by the workers of a crawl so no URL is crawled
+/// twice and duplicate content is detected across workers.
+///
+/// URLs are claimed atomically: of several workers inserting the same URL, exactly one is told
+/// it's new. Checks are sent in batches as one pipeline, and URLs known to be seen are
+/// remembered locally, as that answer can't change, so repeated links don't cost a round trip.
+///
+/// The keys are `<name>:seen`, a set of URLs without their fragment, and `<name>:digests`, a
+/// hash of content digest to the first URL it was seen on.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_distributed::seen::RedisSeenSet;
+/// use url::Url;
+///
+/// let mut seen = RedisSeenSet::connect("redis://127.0.0.1/", "kirby").unwrap();
+/// let links = [
+///     Url::parse("https://example.com/a").unwrap(),
+///     Url::parse("https://example.com/b").unwrap(),
+/// ];
+/// for (url, new) in links.iter().zip(seen.insert_many(&links).unwrap()) {
+///     if new {
+///         println!("queueing {url}");
+///     }
+/// }
+/// ```
+pub struct RedisSeenSet {
+    connection: Connection,
+    seen_key: String,
+    digests_key: String,
+    cache: HashSet<String>,
+    /// The cached URLs, oldest first, to forget the oldest when the cache is full.
+    cached: VecDeque<String>,
+    cache_capacity: usize,
+}
+
+impl RedisSeenSet {
+    /// Connects to Redis at `url`, e.g. `redis://127.0.0.1/`, using the keys of the crawl
+    /// called `name`.
+    pub fn connect(url: &str, name: &str) -> RedisResult<Self> {
+        let connection = redis::Client::open(url)?.get_connection()?;
+        Ok(Self {
+            connection,
+            seen_key: format!("{name}:seen"),
+            digests_key: format!("{name}:digests"),
+            cache: HashSet::new(),
+            cached: VecDeque::new(),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        })
+    }
+
+    /// Remembers up to this many seen URLs locally instead of [`DEFAULT_CACHE_CAPACITY`], 0
+    /// to always ask Redis.
+    pub fn cache_capacity(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    /// Marks a URL as seen, returning false when it was seen before.
+    pub fn insert(&mut self, url: &Url) -> RedisResult<bool> {
+        Ok(self.insert_many(std::slice::from_ref(url))?[0])
+    }
+
+    /// Marks URLs as seen, returning for each whether it's new. Only the first of duplicates
+    /// within `urls` is new.
+    pub fn insert_many(&mut self, urls: &[Url]) -> RedisResult<Vec<bool>> {
+        let keys = urls.iter().map(key).collect::<Vec<_>>();
+        let mut new = vec![false; urls.len()];
+        let mut pipe = redis::pipe();
+        let mut asked = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            if !self.cache.contains(key) {
+                pipe.cmd("SADD").arg(&self.seen_key).arg(key);
+                asked.push(i);
+            }
+        }
+        if !asked.is_empty() {
+            let added: Vec<u32> = pipe.query(&mut self.connection)?;
+            for (i, added) in asked.into_iter().zip(added) {
+                new[i] = added == 1;
+            }
+        }
+        for key in keys {
+            self.remember(key);
+        }
+        Ok(new)
+    }
+
+    /// Whether each URL was seen, without marking any.
+    pub fn contains_many(&mut self, urls: &[Url]) -> RedisResult<Vec<bool>> {
+        let keys = urls.iter().map(key).collect::<Vec<_>>();
+        let mut seen = vec![true; urls.len()];
+        let mut pipe = redis::pipe();
+        let mut asked = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            if !self.cache.contains(key) {
+                pipe.cmd("SISMEMBER").arg(&self.seen_key).arg(key);
+                asked.push(i);
+            }
+        }
+        if !asked.is_empty() {
+            let members: Vec<bool> = pipe.query(&mut self.connection)?;
+            for (i, member) in asked.into_iter().zip(members) {
+                seen[i] = member;
+                if member {
+                    self.remember(keys[i].clone());
+                }
+            }
+        }
+        Ok(seen)
+    }
+
+    /// How many URLs were seen by the whole crawl.
+    pub fn len(&mut self) -> RedisResult<usize> {
+        redis::cmd("SCARD")
+            .arg(&self.seen_key)
+            .query(&mut self.connection)
+    }
+
+    pub fn is_empty(&mut self) -> RedisResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Records the digest of a page's content, returning whether another URL already had it.
+    pub fn add_digest(&mut self, url: &Url, digest: &ContentDigest) -> RedisResult<DigestMatch> {
+        Ok(self
+            .add_digests(&[(url.clone(), digest.clone())])?
+            .remove(0))
+    }
+
+    /// Records the digests of pages, in one round trip. Unlike
+    /// [`DigestIndex`](kirby_core::dedup::digest::DigestIndex), a digest's original is the
+    /// first URL it was recorded for, even when that URL's content changed since.
+    pub fn add_digests(&mut self, pages: &[(Url, ContentDigest)]) -> RedisResult<Vec<DigestMatch>> {
+        if pages.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Each digest is claimed and its original read in the same transaction.
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (url, digest) in pages {
+            let digest = digest.to_string();
+            pipe.cmd("HSETNX")
+                .arg(&self.digests_key)
+                .arg(&digest)
+                .arg(url.as_str())
+                .ignore();
+            pipe.cmd("HGET").arg(&self.digests_key).arg(&digest);
+        }
+        let originals: Vec<String> = pipe.query(&mut self.connection)?;
+
+        Ok(pages
+            .iter()
+            .zip(originals)
+            .map(|((url, _), original)| match Url::parse(&original) {
+                Ok(original) if original != *url => DigestMatch::Duplicate { original },
+                _ => DigestMatch::Unique,
+            })
+            .collect())
+    }
+
+    fn remember(&mut self, key: String) {
+        if self.cache_capacity == 0 || self.cache.contains(&key) {
+            return;
+        }
+        if self.cached.len() >= self.cache_capacity {
+            if let Some(oldest) = self.cached.pop_front() {
+                self.cache.remove(&oldest);
+            }
+        }
+        self.cache.insert(key.clone());
+        self.cached.push_back(key);
+    }
+}
+
+/// The URL as stored in the set, fragments don't make a URL new.
+fn key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.into()
+}
+
+/// The server in `KIRBY_TEST_REDIS` and the name of a fresh crawl.
+///
+/// The tests using it need a server, so they're ignored unless run with `--ignored`.
+#[cfg(test)]
+pub(crate) fn test_redis() -> (String, String) {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let url = std::env::var("KIRBY_TEST_REDIS")
+        .expect("KIRBY_TEST_REDIS is the URL of the server to test with");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    (url, format!("kirby-test-{}-{nanos}", std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use kirby_core::dedup::digest::DigestAlgorithm;
+
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a Redis server in KIRBY_TEST_REDIS"]
+    fn shares_seen_urls_and_digests_between_workers() {
+        let (server, name) = test_redis();
+        let mut one = RedisSeenSet::connect(&server, &name).unwrap();
+        let mut two = RedisSeenSet::connect(&server, &name)
+            .unwrap()
+            .cache_capacity(1);
+        let url = |path: &str| {
+            Url::parse("https://example.com/")
+                .unwrap()
+                .join(path)
+                .unwrap()
+        };
+
+        assert_eq!(
+            one.insert_many(&[url("/a"), url("/b"), url("/a#top")])
+                .unwrap(),
+            [true, true, false]
+        );
+        assert_eq!(
+            two.insert_many(&[url("/b"), url("/c")]).unwrap(),
+            [false, true]
+        );
+        assert_eq!(
+            one.contains_many(&[url("/c"), url("/d")]).unwrap(),
+            [true, false]
+        );
+        assert_eq!(one.len().unwrap(), 3);
+
+        let digest = ContentDigest::of_text(DigestAlgorithm::Sha256, "Hello");
+        assert_eq!(
+            one.add_digest(&url("/a"), &digest).unwrap(),
+            DigestMatch::Unique
+        );
+        assert_eq!(
+            two.add_digests(&[(url("/a"), digest.clone()), (url("/c"), digest)])
+                .unwrap(),
+            [
+                DigestMatch::Unique,
+                DigestMatch::Duplicate {
+                    original: url("/a")
+                }
+            ]
+        );
+    }
+}
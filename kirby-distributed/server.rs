@@ -0,0 +1,274 @@
+use std::convert::Infallible;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use prost::Message;
+use tokio::sync::oneshot;
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::coordinator::{self, Coordinator};
+use crate::proto::{self, HEARTBEAT_PATH, LEASE_PATH, RENEW_PATH, REPORT_PATH};
+
+type Shared = Arc<Mutex<Coordinator>>;
+
+/// Serves a [`Coordinator`] over gRPC to workers, e.g. [`WorkerClient`](crate::worker::WorkerClient)s.
+///
+/// The service is `kirby.distributed.v1.Coordinator` of `proto/distributed.proto`, from which
+/// workers in other languages can be generated. The server runs on its own thread, so the
+/// coordinator can be seeded and its results collected from synchronous code with
+/// [`with`](Self::with).
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_distributed::coordinator::Coordinator;
+/// use kirby_distributed::server::CoordinatorServer;
+/// use url::Url;
+///
+/// let server = CoordinatorServer::bind("0.0.0.0:50052", Coordinator::new()).unwrap();
+/// server.with(|coordinator| coordinator.add(Url::parse("https://example.com/").unwrap(), 0));
+///
+/// while !server.with(|coordinator| coordinator.is_finished()) {
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///     for attempt in server.with(|coordinator| coordinator.take_results()) {
+///         println!("{} {:?}", attempt.url, attempt.status);
+///     }
+/// }
+/// ```
+pub struct CoordinatorServer {
+    coordinator: Shared,
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<Result<(), tonic::transport::Error>>>,
+}
+
+impl CoordinatorServer {
+    pub fn bind(address: impl ToSocketAddrs, coordinator: Coordinator) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let coordinator = Arc::new(Mutex::new(coordinator));
+        let service = CoordinatorService {
+            coordinator: coordinator.clone(),
+        };
+        let (shutdown, signal) = oneshot::channel::<()>();
+
+        let incoming = {
+            let _guard = runtime.enter();
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            TcpIncoming::from_listener(listener, true, None).map_err(io::Error::other)?
+        };
+        let server = thread::spawn(move || {
+            runtime.block_on(
+                Server::builder()
+                    .add_service(service)
+                    .serve_with_incoming_shutdown(incoming, async {
+                        signal.await.ok();
+                    }),
+            )
+        });
+
+        Ok(Self {
+            coordinator,
+            address,
+            shutdown: Some(shutdown),
+            server: Some(server),
+        })
+    }
+
+    /// The address the server listens on, useful when binding to port 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Runs `f` with the coordinator, while workers wait.
+    pub fn with<T>(&self, f: impl FnOnce(&mut Coordinator) -> T) -> T {
+        f(&mut lock(&self.coordinator))
+    }
+
+    /// Stops the server once connected workers disconnect, returning the coordinator with
+    /// what's left of the crawl.
+    pub fn stop(mut self) -> io::Result<Coordinator> {
+        self.shut_down()?;
+        let coordinator = std::mem::take(&mut *lock(&self.coordinator));
+        Ok(coordinator)
+    }
+
+    fn shut_down(&mut self) -> io::Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+        match self.server.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result.map_err(io::Error::other),
+            Some(Err(_)) => Err(io::Error::other("the gRPC server panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CoordinatorServer {
+    fn drop(&mut self) {
+        self.shut_down().ok();
+    }
+}
+
+fn lock(coordinator: &Shared) -> std::sync::MutexGuard<'_, Coordinator> {
+    // The coordinator is left consistent between calls, so it's still usable after a panic.
+    coordinator
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+/// Routes requests to the service's methods.
+#[derive(Clone)]
+struct CoordinatorService {
+    coordinator: Shared,
+}
+
+impl NamedService for CoordinatorService {
+    const NAME: &'static str = "kirby.distributed.v1.Coordinator";
+}
+
+impl Service<http::Request<BoxBody>> for CoordinatorService {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Infallible>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+        let coordinator = self.coordinator.clone();
+        match request.uri().path() {
+            LEASE_PATH => unary(coordinator, request, lease),
+            RENEW_PATH => unary(coordinator, request, renew),
+            REPORT_PATH => unary(coordinator, request, report),
+            HEARTBEAT_PATH => unary(coordinator, request, heartbeat),
+            _ => Box::pin(async { Ok(Status::unimplemented("unknown method").into_http()) }),
+        }
+    }
+}
+
+type Handler<Req, Res> = fn(&mut Coordinator, Req, Instant) -> Result<Res, Status>;
+
+fn unary<Req, Res>(
+    coordinator: Shared,
+    request: http::Request<BoxBody>,
+    handler: Handler<Req, Res>,
+) -> BoxFuture<http::Response<BoxBody>, Infallible>
+where
+    Req: Message + Default + Send + 'static,
+    Res: Message + Send + 'static,
+{
+    let method = Method {
+        coordinator,
+        handler,
+    };
+    Box::pin(async move {
+        let mut grpc = Grpc::new(ProstCodec::default());
+        Ok(grpc.unary(method, request).await)
+    })
+}
+
+struct Method<Req, Res> {
+    coordinator: Shared,
+    handler: Handler<Req, Res>,
+}
+
+impl<Req, Res> UnaryService<Req> for Method<Req, Res>
+where
+    Req: Send + 'static,
+    Res: Send + 'static,
+{
+    type Response = Res;
+    type Future = BoxFuture<Response<Res>, Status>;
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let response = (self.handler)(
+            &mut lock(&self.coordinator),
+            request.into_inner(),
+            Instant::now(),
+        );
+        Box::pin(async move { response.map(Response::new) })
+    }
+}
+
+fn lease(
+    coordinator: &mut Coordinator,
+    request: proto::LeaseRequest,
+    now: Instant,
+) -> Result<proto::LeaseResponse, Status> {
+    let leases = coordinator.lease(
+        &request.worker_id,
+        request.max_hosts as usize,
+        request.max_urls as usize,
+        now,
+    );
+    Ok(proto::LeaseResponse {
+        leases: leases.iter().map(proto::Lease::from).collect(),
+    })
+}
+
+fn renew(
+    coordinator: &mut Coordinator,
+    request: proto::RenewRequest,
+    now: Instant,
+) -> Result<proto::RenewResponse, Status> {
+    let ttl = coordinator.renew(&request.worker_id, request.lease_id, now)?;
+    Ok(proto::RenewResponse {
+        ttl_ms: ttl.as_millis() as u64,
+    })
+}
+
+fn report(
+    coordinator: &mut Coordinator,
+    request: proto::ReportRequest,
+    now: Instant,
+) -> Result<proto::ReportResponse, Status> {
+    let results = request
+        .results
+        .into_iter()
+        .map(coordinator::UrlResult::try_from)
+        .collect::<Result<_, _>>()?;
+    let discovered = request
+        .discovered
+        .into_iter()
+        .map(coordinator::QueuedUrl::try_from)
+        .collect::<Result<_, _>>()?;
+    let queued = coordinator.report(
+        &request.worker_id,
+        request.lease_id,
+        results,
+        discovered,
+        request.done,
+        now,
+    )?;
+    Ok(proto::ReportResponse {
+        queued: queued as u32,
+    })
+}
+
+fn heartbeat(
+    coordinator: &mut Coordinator,
+    request: proto::HeartbeatRequest,
+    now: Instant,
+) -> Result<proto::HeartbeatResponse, Status> {
+    Ok(proto::HeartbeatResponse {
+        lease_ids: coordinator.heartbeat(&request.worker_id, now),
+    })
+}
@@ -0,0 +1,192 @@
+use std::io;
+use std::time::Duration;
+
+use tokio::runtime::Runtime;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+use crate::coordinator::{QueuedUrl, UrlResult, WorkLease};
+use crate::proto::{self, HEARTBEAT_PATH, LEASE_PATH, RENEW_PATH, REPORT_PATH};
+
+/// A blocking client of a [`CoordinatorServer`](crate::server::CoordinatorServer), for
+/// workers to lease URLs, fetch them and report back.
+///
+/// A worker should renew a lease, or report on it, well within its TTL, and send heartbeats
+/// well within the coordinator's grace period, otherwise the coordinator hands its URLs to
+/// another worker.
+///
+/// # Example
+///
+/// ```no_run
+/// use kirby_distributed::coordinator::UrlResult;
+/// use kirby_distributed::worker::WorkerClient;
+///
+/// let mut client = WorkerClient::connect("http://coordinator:50052", "worker-1").unwrap();
+/// loop {
+///     for lease in client.lease(4, 50).unwrap() {
+///         let results = lease
+///             .urls
+///             .iter()
+///             .map(|queued| UrlResult { url: queued.url.clone(), status: Some(200), error: None })
+///             .collect();
+///         client.report(lease.id, results, Vec::new(), true).unwrap();
+///     }
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+/// }
+/// ```
+pub struct WorkerClient {
+    worker_id: String,
+    runtime: Runtime,
+    client: tonic::client::Grpc<Channel>,
+}
+
+impl WorkerClient {
+    /// Connects to a coordinator at `endpoint`, e.g. `http://coordinator:50052`.
+    pub fn connect(endpoint: &str, worker_id: impl Into<String>) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let channel = Channel::from_shared(endpoint.to_string())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        let channel = runtime
+            .block_on(channel.connect())
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            worker_id: worker_id.into(),
+            runtime,
+            client: tonic::client::Grpc::new(channel),
+        })
+    }
+
+    pub fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    /// Leases up to `max_hosts` hosts with up to `max_urls` URLs each, none when there's
+    /// nothing to do for now.
+    pub fn lease(&mut self, max_hosts: u32, max_urls: u32) -> Result<Vec<WorkLease>, Status> {
+        let request = proto::LeaseRequest {
+            worker_id: self.worker_id.clone(),
+            max_hosts,
+            max_urls,
+        };
+        let response: proto::LeaseResponse = self.call(LEASE_PATH, request)?;
+        response
+            .leases
+            .into_iter()
+            .map(WorkLease::try_from)
+            .collect()
+    }
+
+    /// Keeps a lease from expiring, returning how long it now lasts.
+    pub fn renew(&mut self, lease: u64) -> Result<Duration, Status> {
+        let request = proto::RenewRequest {
+            worker_id: self.worker_id.clone(),
+            lease_id: lease,
+        };
+        let response: proto::RenewResponse = self.call(RENEW_PATH, request)?;
+        Ok(Duration::from_millis(response.ttl_ms))
+    }
+
+    /// Reports fetched URLs and the URLs discovered on them, returning how many of those were
+    /// new. Setting `done` ends the lease.
+    pub fn report(
+        &mut self,
+        lease: u64,
+        results: Vec<UrlResult>,
+        discovered: Vec<QueuedUrl>,
+        done: bool,
+    ) -> Result<usize, Status> {
+        let request = proto::ReportRequest {
+            worker_id: self.worker_id.clone(),
+            lease_id: lease,
+            results: results.iter().map(proto::UrlResult::from).collect(),
+            discovered: discovered.iter().map(proto::QueuedUrl::from).collect(),
+            done,
+        };
+        let response: proto::ReportResponse = self.call(REPORT_PATH, request)?;
+        Ok(response.queued as usize)
+    }
+
+    /// Tells the coordinator this worker is alive, returning the leases it holds. Fetching
+    /// should stop for the leases that aren't listed, as they were reclaimed.
+    pub fn heartbeat(&mut self) -> Result<Vec<u64>, Status> {
+        let request = proto::HeartbeatRequest {
+            worker_id: self.worker_id.clone(),
+        };
+        let response: proto::HeartbeatResponse = self.call(HEARTBEAT_PATH, request)?;
+        Ok(response.lease_ids)
+    }
+
+    fn call<Req, Res>(&mut self, path: &'static str, request: Req) -> Result<Res, Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Res: prost::Message + Default + Send + Sync + 'static,
+    {
+        let client = &mut self.client;
+        self.runtime.block_on(async {
+            client
+                .ready()
+                .await
+                .map_err(|error| Status::unavailable(error.to_string()))?;
+            let response = client
+                .unary(
+                    Request::new(request),
+                    PathAndQuery::from_static(path),
+                    ProstCodec::<Req, Res>::default(),
+                )
+                .await?;
+            Ok(response.into_inner())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+    use url::Url;
+
+    use super::*;
+    use crate::coordinator::Coordinator;
+    use crate::server::CoordinatorServer;
+
+    #[test]
+    fn leases_and_reports_over_grpc() {
+        let server = CoordinatorServer::bind("127.0.0.1:0", Coordinator::new()).unwrap();
+        let seed = Url::parse("https://example.com/").unwrap();
+        server.with(|coordinator| coordinator.add(seed.clone(), 0));
+        let endpoint = format!("http://{}", server.address());
+        let mut one = WorkerClient::connect(&endpoint, "one").unwrap();
+        let mut two = WorkerClient::connect(&endpoint, "two").unwrap();
+
+        let leases = one.lease(2, 10).unwrap();
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].urls[0].url, seed);
+        assert_eq!(leases[0].ttl, Duration::from_secs(300));
+        assert!(two.lease(2, 10).unwrap().is_empty());
+        let id = leases[0].id;
+        assert_eq!(two.renew(id).unwrap_err().code(), Code::PermissionDenied);
+        assert_eq!(one.renew(id).unwrap(), Duration::from_secs(300));
+        assert_eq!(one.heartbeat().unwrap(), [id]);
+
+        let result = UrlResult {
+            url: seed.clone(),
+            status: Some(200),
+            error: None,
+        };
+        let discovered = vec![QueuedUrl {
+            url: seed.join("/about").unwrap(),
+            depth: 1,
+        }];
+        assert_eq!(one.report(id, vec![result], discovered, true).unwrap(), 1);
+        assert_eq!(one.renew(id).unwrap_err().code(), Code::NotFound);
+        assert_eq!(two.lease(1, 1).unwrap()[0].urls[0].url.path(), "/about");
+
+        // The server waits for its connections to close.
+        drop((one, two));
+        let coordinator = server.stop().unwrap();
+        assert_eq!(coordinator.active_leases(), 1);
+    }
+}
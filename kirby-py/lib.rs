@@ -0,0 +1,330 @@
+//! Python bindings: robots.txt checks, URL normalization, sitemap parsing and crawling.
+//!
+//! Built into the `kirby` Python module with maturin, e.g. `maturin develop` in this directory.
+//!
+//! ```python
+//! import asyncio
+//! import kirby
+//!
+//! robots = kirby.RobotsTxt("User-agent: *\nDisallow: /private/")
+//! assert not robots.is_allowed("KirbyBot", "/private/page")
+//!
+//! config = 'seeds = ["https://example.com/"]\n[budget]\nmax_pages = 10\n'
+//! for page in kirby.Crawl(config):
+//!     print(page["status"], page["url"])
+//!
+//! async def main():
+//!     async for page in kirby.Crawl(config):
+//!         print(page["url"])
+//!
+//! asyncio.run(main())
+//! ```
+
+use std::sync::{Mutex, MutexGuard};
+
+use kirby_core::config::{ConfigError, CrawlConfig};
+use kirby_core::crawler::stream::Pages;
+use kirby_core::crawler::{Crawler, Summary};
+use kirby_core::robotstxt;
+use kirby_core::sitemap::Sitemap;
+use pyo3::exceptions::{PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::Serialize;
+use url::Url;
+
+/// A robots.txt file, checked against user agents and paths.
+#[pyclass(frozen)]
+struct RobotsTxt {
+    robots: robotstxt::RobotsTxt<'static>,
+}
+
+#[pymethods]
+impl RobotsTxt {
+    #[new]
+    fn new(text: &str) -> Self {
+        Self {
+            robots: robotstxt::RobotsTxt::parse(text).into_owned(),
+        }
+    }
+
+    /// Whether `user_agent` may crawl `path`, e.g. `/search?q=kirby`.
+    fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        self.robots.is_allowed(user_agent, path)
+    }
+
+    /// The sitemaps the file lists, as written.
+    #[getter]
+    fn sitemaps(&self) -> Vec<String> {
+        self.robots
+            .sitemaps()
+            .iter()
+            .map(|sitemap| sitemap.to_string())
+            .collect()
+    }
+}
+
+/// A URL the way the crawler compares them: the scheme and host lowercased, default ports,
+/// dot segments and the fragment dropped and special characters percent-encoded.
+#[pyfunction]
+fn normalize_url(url: &str) -> PyResult<String> {
+    let mut url = Url::parse(url).map_err(|error| PyValueError::new_err(error.to_string()))?;
+    url.set_fragment(None);
+    Ok(url.into())
+}
+
+/// Parses a sitemap fetched from `location` into a dict of its `kind`, `entries` and `issues`.
+#[pyfunction]
+fn parse_sitemap(py: Python<'_>, location: &str, body: &[u8]) -> PyResult<PyObject> {
+    let location =
+        Url::parse(location).map_err(|error| PyValueError::new_err(error.to_string()))?;
+    let sitemap = Sitemap::parse(&location, body)
+        .map_err(|error| PyValueError::new_err(error.to_string()))?;
+    let dict = PyDict::new(py);
+    dict.set_item("kind", to_python(py, &sitemap.kind)?)?;
+    dict.set_item("entries", to_python(py, &sitemap.entries)?)?;
+    let issues = sitemap.issues.iter().map(ToString::to_string);
+    dict.set_item("issues", issues.collect::<Vec<_>>())?;
+    Ok(dict.into_any().unbind())
+}
+
+/// Runs the crawl a TOML configuration describes, writing to its sinks, and returns what it did.
+///
+/// Other Python threads run while it crawls.
+#[pyfunction]
+fn crawl(py: Python<'_>, config: &str) -> PyResult<PyObject> {
+    let crawler = Crawler::from_config(read_config(config)?).map_err(invalid)?;
+    let summary = py.allow_threads(|| crawler.run())?;
+    summary_dict(py, summary)
+}
+
+/// The pages of a crawl running in the background, as dicts of their records.
+///
+/// Iterating waits for the next page with other Python threads running, iterating with
+/// `async for` waits in the event loop's default executor. The crawl waits for the pages to
+/// be read, so it goes no faster than they're used.
+#[pyclass(frozen)]
+struct Crawl {
+    pages: Mutex<Option<Pages>>,
+}
+
+#[pymethods]
+impl Crawl {
+    /// Starts the crawl a TOML configuration describes. It needs no sinks, the ones it has are
+    /// written to as well.
+    #[new]
+    fn new(config: &str) -> PyResult<Self> {
+        let pages = Crawler::builder()
+            .config(read_config(config)?)
+            .pages()
+            .map_err(invalid)?;
+        Ok(Self {
+            pages: Mutex::new(Some(pages)),
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let page = py.allow_threads(|| self.pages().as_mut().and_then(Iterator::next));
+        page.map(|page| to_python(py, &page)).transpose()
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let py = slf.py();
+        let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+        event_loop.call_method1(
+            "run_in_executor",
+            (py.None(), slf.getattr("_next_or_stop")?),
+        )
+    }
+
+    /// `__next__` for `__anext__`, which runs it in an executor and ends on the exception.
+    fn _next_or_stop(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.__next__(py)?
+            .ok_or_else(|| PyStopAsyncIteration::new_err(()))
+    }
+
+    /// Waits for the crawl to be over and returns what it did, as a dict. The pages that
+    /// weren't read are thrown away.
+    fn finish(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let Some(pages) = self.pages().take() else {
+            return Err(PyValueError::new_err("the crawl was already finished"));
+        };
+        let summary = py.allow_threads(|| pages.finish())?;
+        summary_dict(py, summary)
+    }
+}
+
+impl Crawl {
+    fn pages(&self) -> MutexGuard<'_, Option<Pages>> {
+        // Reading a page is a single step, the pages are whole after a panic.
+        self.pages.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+fn read_config(toml: &str) -> PyResult<CrawlConfig> {
+    CrawlConfig::from_toml(toml).map_err(invalid)
+}
+
+fn invalid(error: ConfigError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn summary_dict(py: Python<'_>, summary: Summary) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("pages", summary.pages)?;
+    dict.set_item("failed", summary.failed)?;
+    dict.set_item("denied", summary.denied)?;
+    Ok(dict.into_any().unbind())
+}
+
+/// Converts a value to Python by way of JSON.
+fn to_python(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json =
+        serde_json::to_string(value).map_err(|error| PyValueError::new_err(error.to_string()))?;
+    let value = py.import("json")?.call_method1("loads", (json,))?;
+    Ok(value.unbind())
+}
+
+#[pymodule]
+fn kirby(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<RobotsTxt>()?;
+    module.add_class::<Crawl>()?;
+    module.add_function(wrap_pyfunction!(normalize_url, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_sitemap, module)?)?;
+    module.add_function(wrap_pyfunction!(crawl, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use kirby_test::server::MockServer;
+    use kirby_test::site::{Page, Site};
+
+    use super::*;
+
+    /// Runs Python code with the module imported as `kirby`.
+    fn run(code: &CStr) -> PyResult<()> {
+        run_with(code, &[])
+    }
+
+    /// Runs Python code with the module imported as `kirby` and string globals.
+    fn run_with(code: &CStr, strings: &[(&str, &str)]) -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "kirby")?;
+            kirby(&module)?;
+            let globals = PyDict::new(py);
+            globals.set_item("kirby", module)?;
+            for (name, value) in strings {
+                globals.set_item(name, value)?;
+            }
+            py.run(code, Some(&globals), None)
+        })
+    }
+
+    /// A site of three pages linked from the home page, and the configuration of its crawl.
+    fn site() -> (MockServer, String) {
+        let server = MockServer::start(
+            Site::new()
+                .page("/", Page::links(["/about", "/contact"]))
+                .page("/about", Page::html("<h1>About</h1>"))
+                .page("/contact", Page::html("<h1>Contact</h1>")),
+        );
+        let config = format!(
+            "seeds = [\"{}\"]\n[politeness]\ndelay_ms = 0\nconcurrency = 1\n",
+            server.url()
+        );
+        (server, config)
+    }
+
+    #[test]
+    fn parses_robots_urls_and_sitemaps() {
+        run(cr#"
+robots = kirby.RobotsTxt("User-agent: *\nDisallow: /private/\nSitemap: https://example.com/sitemap.xml")
+assert robots.is_allowed("KirbyBot", "/about")
+assert not robots.is_allowed("KirbyBot", "/private/page")
+assert robots.sitemaps == ["https://example.com/sitemap.xml"]
+
+assert kirby.normalize_url("HTTP://Example.COM:80/a/../b#top") == "http://example.com/b"
+
+sitemap = kirby.parse_sitemap(
+    "https://example.com/sitemap.xml",
+    b'<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">'
+    b"<url><loc>https://example.com/about</loc><priority>1.5</priority></url></urlset>",
+)
+assert sitemap["kind"] == "url_set"
+assert sitemap["entries"][0]["loc"] == "https://example.com/about"
+assert "priority 1.5" in sitemap["issues"][0]
+"#)
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_configurations() {
+        run(cr#"
+try:
+    kirby.Crawl("seeds = []")
+    raise AssertionError("the crawl started")
+except ValueError as error:
+    assert "at least one seed is needed" in str(error), error
+"#)
+        .unwrap();
+    }
+
+    #[test]
+    fn iterates_over_the_pages_of_a_crawl() {
+        let (server, config) = site();
+        run_with(
+            cr#"
+crawl = kirby.Crawl(config)
+pages = []
+for page in crawl:
+    pages.append(page)
+
+assert [page["url"] for page in pages] == [site, site + "about", site + "contact"], pages
+assert [page["status"] for page in pages] == [200, 200, 200]
+assert [page["depth"] for page in pages] == [0, 1, 1]
+assert crawl.finish() == {"pages": 3, "failed": 0, "denied": 0}
+"#,
+            &[("config", &config), ("site", server.url().as_str())],
+        )
+        .unwrap();
+        server.assert_requested("/about", 1);
+    }
+
+    #[test]
+    fn iterates_over_the_pages_of_a_crawl_asynchronously() {
+        let (server, config) = site();
+        run_with(
+            cr#"
+import asyncio
+
+async def read(crawl):
+    pages = []
+    async for page in crawl:
+        pages.append(page)
+    return pages
+
+crawl = kirby.Crawl(config)
+pages = asyncio.run(read(crawl))
+
+assert [page["url"] for page in pages] == [site, site + "about", site + "contact"], pages
+assert [page["status"] for page in pages] == [200, 200, 200]
+assert crawl.finish() == {"pages": 3, "failed": 0, "denied": 0}
+"#,
+            &[("config", &config), ("site", server.url().as_str())],
+        )
+        .unwrap();
+        server.assert_requested("/contact", 1);
+    }
+}
@@ -0,0 +1,5 @@
+//! Test support for crawlers: an in-process HTTP server serving a scripted site, with
+//! assertions on the requests it received.
+
+pub mod server;
+pub mod site;
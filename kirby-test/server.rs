@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use url::Url;
+
+use crate::site::{self, Failure, Site};
+
+/// A request the server received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// The path with the query, e.g. `/search?q=kirby`.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Serves a [`Site`] over HTTP on localhost until it's dropped, recording the requests.
+///
+/// Each connection is served on a thread of its own, so a page's latency doesn't hold up
+/// the others, and closed after one response.
+///
+/// # Example
+///
+/// ```
+/// use kirby_test::server::MockServer;
+/// use kirby_test::site::{Page, Site};
+///
+/// let server = MockServer::start(Site::new().page("/", Page::links(["/about"])));
+/// // Crawl `server.url()`, then check what was fetched:
+/// server.assert_not_requested("/private/");
+/// ```
+pub struct MockServer {
+    address: SocketAddr,
+    url: Url,
+    shared: Arc<Shared>,
+    accept: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    site: Site,
+    /// Replaces `{base}` in bodies and headers.
+    base: String,
+    requests: Mutex<Vec<Request>>,
+    /// How many requests each page received, by the path it's served at.
+    attempts: Mutex<HashMap<String, usize>>,
+    stopped: AtomicBool,
+}
+
+impl MockServer {
+    /// Starts serving on a free port.
+    pub fn start(site: Site) -> Self {
+        // Unwrapping is safe here because binding an ephemeral port on localhost only fails
+        // when the machine is out of ports, which no test can carry on from.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // Unwrapping is safe here because an address and port make a valid URL.
+        let url = Url::parse(&format!("http://{address}/")).unwrap();
+        let shared = Arc::new(Shared {
+            site,
+            base: format!("http://{address}"),
+            requests: Mutex::default(),
+            attempts: Mutex::default(),
+            stopped: AtomicBool::new(false),
+        });
+
+        let accepting = Arc::clone(&shared);
+        let accept = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accepting.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let shared = Arc::clone(&accepting);
+                // A client going away mid-request isn't the server's problem.
+                thread::spawn(move || shared.serve(stream).ok());
+            }
+        });
+        Self {
+            address,
+            url,
+            shared,
+            accept: Some(accept),
+        }
+    }
+
+    /// The site's root, e.g. `http://127.0.0.1:41234/`.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The URL of a path on the site.
+    pub fn url_for(&self, path: &str) -> Url {
+        // Unwrapping is safe here because any path joins onto an HTTP URL.
+        self.url.join(path).unwrap()
+    }
+
+    /// The requests received so far, in the order they arrived.
+    pub fn requests(&self) -> Vec<Request> {
+        self.shared.requests().clone()
+    }
+
+    /// The requests received so far for `path`, with its query.
+    pub fn requests_to(&self, path: &str) -> Vec<Request> {
+        let requests = self.shared.requests();
+        requests
+            .iter()
+            .filter(|r| r.path == path)
+            .cloned()
+            .collect()
+    }
+
+    /// Panics unless `path` was requested exactly `times` times.
+    #[track_caller]
+    pub fn assert_requested(&self, path: &str, times: usize) {
+        let requested = self.requests_to(path).len();
+        assert_eq!(
+            requested,
+            times,
+            "{path} was requested {requested} times instead of {times}, the requests were {:?}",
+            self.paths()
+        );
+    }
+
+    /// Panics if `path` or anything under it was requested, e.g. `/private/` covers
+    /// `/private/drafts`.
+    #[track_caller]
+    pub fn assert_not_requested(&self, path: &str) {
+        let paths = self.paths();
+        assert!(
+            !paths.iter().any(|requested| requested.starts_with(path)),
+            "{path} was requested, the requests were {paths:?}"
+        );
+    }
+
+    /// Panics unless every request sent this `User-Agent`.
+    #[track_caller]
+    pub fn assert_user_agent(&self, user_agent: &str) {
+        for request in self.requests() {
+            assert_eq!(
+                request.header("user-agent"),
+                Some(user_agent),
+                "{} was requested with another user agent",
+                request.path
+            );
+        }
+    }
+
+    /// The paths requested so far, with their queries, in the order they arrived.
+    pub fn paths(&self) -> Vec<String> {
+        let requests = self.shared.requests();
+        requests
+            .iter()
+            .map(|request| request.path.clone())
+            .collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        // Wakes the accepting thread up to see it's stopped.
+        let _ = TcpStream::connect(self.address);
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+    }
+}
+
+impl Shared {
+    fn requests(&self) -> MutexGuard<'_, Vec<Request>> {
+        // Adding a request is a single step, the list is whole after a panic.
+        self.requests
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn serve(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        let request = read_request(&mut reader)?;
+        let path = request.path.clone();
+        self.requests().push(request);
+
+        let mut stream = reader.into_inner();
+        let Some((served_at, pages)) = self.site.get(&path) else {
+            return respond(&mut stream, 404, &[], "");
+        };
+        // How many requests for the pages came before this one, to pick the page and failure.
+        let attempt = {
+            // Counting is a single step, the counts are whole after a panic.
+            let mut attempts = self
+                .attempts
+                .lock()
+                .unwrap_or_else(|error| error.into_inner());
+            let attempts = attempts.entry(served_at.to_string()).or_default();
+            *attempts += 1;
+            *attempts - 1
+        };
+        let (page, failure) = site::turn(pages, attempt);
+        thread::sleep(page.latency);
+        match failure {
+            Some(Failure::Status(status)) => respond(&mut stream, status, &[], ""),
+            Some(Failure::Disconnect) => Ok(()),
+            None => {
+                let headers = page
+                    .headers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.replace("{base}", &self.base)))
+                    .collect::<Vec<_>>();
+                let body = page.body.replace("{base}", &self.base);
+                respond(&mut stream, page.status, &headers, &body)
+            }
+        }
+    }
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(String, String)],
+    body: &str,
+) -> io::Result<()> {
+    let headers = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect::<String>();
+    write!(
+        stream,
+        "HTTP/1.1 {status} Mock\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use kirby_core::crawler::builder::Identity;
+    use kirby_core::crawler::Crawler;
+
+    use super::*;
+    use crate::site::Page;
+
+    /// Sends a GET request for `path` and returns the response, empty if the connection was
+    /// closed without one.
+    fn get(server: &MockServer, path: &str) -> String {
+        let mut stream = TcpStream::connect(server.address).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok();
+        response
+    }
+
+    #[test]
+    fn injects_failures_and_latency_before_serving_pages() {
+        let server = MockServer::start(
+            Site::new()
+                .page(
+                    "/flaky",
+                    Page::text("Back up")
+                        .fail(Failure::Disconnect)
+                        .fail(Failure::Status(503)),
+                )
+                .page(
+                    "/slow",
+                    Page::redirect("{base}/flaky").latency(Duration::from_millis(50)),
+                ),
+        );
+
+        assert_eq!(get(&server, "/flaky"), "");
+        assert!(get(&server, "/flaky").starts_with("HTTP/1.1 503 "));
+        assert!(get(&server, "/flaky?again").ends_with("\r\n\r\nBack up"));
+        assert!(get(&server, "/missing").starts_with("HTTP/1.1 404 "));
+        let started = Instant::now();
+        let redirect = get(&server, "/slow");
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(redirect.contains(&format!("Location: {}flaky\r\n", server.url())));
+
+        server.assert_requested("/flaky", 2);
+        assert_eq!(server.requests_to("/flaky?again")[0].method, "GET");
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[test]
+    fn serves_scripted_pages_in_turn() {
+        let server = MockServer::start(Site::new().pages(
+            "/api",
+            [
+                Page::new(429, "Slow down"),
+                Page::json(r#"{"page": 1}"#).fail(Failure::Status(500)),
+                Page::json(r#"{"page": 2}"#),
+            ],
+        ));
+
+        assert!(get(&server, "/api").starts_with("HTTP/1.1 429 "));
+        assert!(get(&server, "/api").starts_with("HTTP/1.1 500 "));
+        assert!(get(&server, "/api").ends_with(r#"{"page": 1}"#));
+        assert!(get(&server, "/api?q").ends_with(r#"{"page": 2}"#));
+        assert!(get(&server, "/api").ends_with(r#"{"page": 2}"#));
+        server.assert_requested("/api", 4);
+    }
+
+    #[test]
+    fn checks_what_a_crawl_requested() {
+        let server = MockServer::start(
+            Site::new()
+                .robots("User-agent: *\nDisallow: /private/")
+                .page("/", Page::links(["/about", "/private/drafts", "/gone"]))
+                .page("/about", Page::links(["/"]))
+                .page("/private/drafts", Page::html("Secret")),
+        );
+        let summary = Crawler::builder()
+            .seed(server.url().clone())
+            .identity(Identity::new("TestBot/1.0"))
+            .delay(Duration::ZERO)
+            .pages()
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        assert_eq!(summary.pages, 3);
+        assert_eq!(summary.denied, 1);
+        server.assert_requested("/robots.txt", 1);
+        server.assert_requested("/", 1);
+        server.assert_requested("/gone", 1);
+        server.assert_not_requested("/private/");
+        server.assert_user_agent("TestBot/1.0");
+    }
+}
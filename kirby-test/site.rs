@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A scripted website, the pages a [`MockServer`](crate::server::MockServer) serves by path.
+///
+/// Bodies and headers are written before the server's port is known, so `{base}` in them is
+/// replaced with the server's URL without the trailing slash, e.g. `{base}/about`. Paths
+/// without a page are `404 Not Found`.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use kirby_test::site::{Failure, Page, Site};
+///
+/// let site = Site::new()
+///     .robots("User-agent: *\nDisallow: /private/\nSitemap: {base}/sitemap.xml")
+///     .sitemap("/sitemap.xml", ["/", "/about"])
+///     .page("/", Page::links(["/about", "/private/drafts", "/slow", "/flaky"]))
+///     .page("/about", Page::html("<h1>About</h1>"))
+///     .page("/slow", Page::html("Finally").latency(Duration::from_millis(200)))
+///     .page("/flaky", Page::html("Back up").fail(Failure::Status(503)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Site {
+    /// The pages served at each path, in turn.
+    pages: HashMap<String, Vec<Page>>,
+}
+
+impl Site {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `page` at `path`. A path with a query, e.g. `/search?q=kirby`, only matches
+    /// requests with that query, one without matches any query.
+    pub fn page(self, path: impl Into<String>, page: Page) -> Self {
+        self.pages(path, [page])
+    }
+
+    /// Serves `pages` at `path` in turn, each to one request once its failures are over, and
+    /// the last one to every request after, e.g. to script the answers an API gives.
+    ///
+    /// # Panics
+    ///
+    /// When there are no pages.
+    pub fn pages(mut self, path: impl Into<String>, pages: impl IntoIterator<Item = Page>) -> Self {
+        let path = path.into();
+        let pages = pages.into_iter().collect::<Vec<_>>();
+        assert!(!pages.is_empty(), "no pages to serve at {path}");
+        self.pages.insert(path, pages);
+        self
+    }
+
+    /// Serves a robots.txt file.
+    pub fn robots(self, robots: &str) -> Self {
+        self.page("/robots.txt", Page::text(robots))
+    }
+
+    /// Serves a sitemap at `path` listing the pages at `paths`.
+    pub fn sitemap<'a>(self, path: &str, paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let urls = paths
+            .into_iter()
+            .map(|path| format!("<url><loc>{{base}}{path}</loc></url>"))
+            .collect::<String>();
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">{urls}</urlset>"
+        );
+        self.page(
+            path,
+            Page::new(200, xml).header("Content-Type", "application/xml"),
+        )
+    }
+
+    /// The pages served for a requested path, with its query, and the path they're served at.
+    pub(crate) fn get(&self, path: &str) -> Option<(&str, &[Page])> {
+        let found = self.pages.get_key_value(path).or_else(|| {
+            let (without_query, _) = path.split_once('?')?;
+            self.pages.get_key_value(without_query)
+        });
+        found.map(|(path, pages)| (path.as_str(), pages.as_slice()))
+    }
+}
+
+/// The page answering a request that came after `attempt` others for the same pages, and how
+/// the request fails if it does.
+pub(crate) fn turn(pages: &[Page], mut attempt: usize) -> (&Page, Option<Failure>) {
+    // Unwrapping is safe here because a site has no paths without pages.
+    let (last, earlier) = pages.split_last().unwrap();
+    for page in earlier {
+        if let Some(failure) = page.failures.get(attempt) {
+            return (page, Some(*failure));
+        }
+        attempt -= page.failures.len();
+        if attempt == 0 {
+            return (page, None);
+        }
+        attempt -= 1;
+    }
+    (last, last.failures.get(attempt).copied())
+}
+
+/// A scripted response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: String,
+    pub(crate) latency: Duration,
+    /// How the first requests for the page fail, in order.
+    pub(crate) failures: Vec<Failure>,
+}
+
+/// A failure injected before a page is served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Responds with this status and an empty body.
+    Status(u16),
+    /// Closes the connection without responding.
+    Disconnect,
+}
+
+impl Page {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            latency: Duration::ZERO,
+            failures: Vec::new(),
+        }
+    }
+
+    pub fn html(body: impl Into<String>) -> Self {
+        Self::new(200, body).header("Content-Type", "text/html; charset=utf-8")
+    }
+
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::new(200, body).header("Content-Type", "text/plain; charset=utf-8")
+    }
+
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::new(200, body).header("Content-Type", "application/json")
+    }
+
+    /// An HTML page linking to `paths` on the site.
+    pub fn links<'a>(paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let links = paths
+            .into_iter()
+            .map(|path| format!("<a href=\"{path}\">{path}</a>\n"))
+            .collect::<String>();
+        Self::html(format!("<html><body>\n{links}</body></html>"))
+    }
+
+    /// A `301 Moved Permanently` to `location`, e.g. `/new` or `https://example.com/`.
+    pub fn redirect(location: &str) -> Self {
+        Self::new(301, "").header("Location", location)
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Waits this long before responding, failures included.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Fails the next request for the page that isn't failed yet, e.g. `.fail(a).fail(b)`
+    /// fails the first request with `a`, the second with `b` and serves the page from the
+    /// third.
+    pub fn fail(mut self, failure: Failure) -> Self {
+        self.failures.push(failure);
+        self
+    }
+}